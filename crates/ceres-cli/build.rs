@@ -0,0 +1,19 @@
+//! Generates the gRPC service from `proto/ceres.proto` when the `grpc`
+//! feature is enabled, using a bundled `protoc`.
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    #[cfg(feature = "grpc")]
+    {
+        let mut config = prost_build::Config::new();
+        config.protoc_executable(protoc_bin_vendored::protoc_bin_path()?);
+        let include = protoc_bin_vendored::include_path()?;
+        tonic_prost_build::configure()
+            .build_client(false)
+            .compile_with_config(
+                config,
+                &["proto/ceres.proto".into()],
+                &["proto".into(), include],
+            )?;
+    }
+    Ok(())
+}
@@ -0,0 +1,143 @@
+//! Process exit codes, so scripts and CI can tell how a harvest went.
+//!
+//! | Code | Meaning |
+//! |------|---------|
+//! | 0    | Success |
+//! | 1    | Failure: configuration, database or every portal failed |
+//! | 2    | Invalid command line |
+//! | 3    | Partial failure: some datasets or portals failed |
+//! | 130  | Interrupted by Ctrl-C or SIGTERM |
+//!
+//! Errors returned from `main` exit with 1 and clap exits with 2 on its own;
+//! harvests report the other outcomes with [`report`].
+
+use std::process::ExitCode;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+use ceres_core::{BatchHarvestSummary, SyncStats};
+
+/// Exit code of a run stopped by a signal, as shells report SIGINT.
+pub const INTERRUPTED: u8 = 130;
+
+/// How a command ended, from best to worst.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum Outcome {
+    Success,
+    Partial,
+    Failure,
+    Interrupted,
+}
+
+impl Outcome {
+    /// The outcome of a harvest of one portal that did not fail outright.
+    pub fn of_sync(stats: &SyncStats) -> Self {
+        if stats.interrupted {
+            Self::Interrupted
+        } else if stats.failed > 0 {
+            Self::Partial
+        } else {
+            Self::Success
+        }
+    }
+
+    /// The outcome of harvesting the portals of `summary`.
+    pub fn of_harvest(summary: &BatchHarvestSummary) -> Self {
+        let worst = summary
+            .results
+            .iter()
+            .map(|r| {
+                if r.is_failure() {
+                    Self::Partial
+                } else {
+                    // Skipped portals come with empty stats
+                    Self::of_sync(&r.stats)
+                }
+            })
+            .max()
+            .unwrap_or(Self::Success);
+        // Nothing harvested at all is no partial success
+        if worst == Self::Partial && summary.successful_count() == 0 {
+            Self::Failure
+        } else {
+            worst
+        }
+    }
+
+    fn code(self) -> u8 {
+        match self {
+            Self::Success => 0,
+            Self::Failure => 1,
+            Self::Partial => 3,
+            Self::Interrupted => INTERRUPTED,
+        }
+    }
+}
+
+static WORST: AtomicU8 = AtomicU8::new(Outcome::Success as u8);
+
+/// Records the outcome of a command; the worst one reported decides the
+/// exit code.
+pub fn report(outcome: Outcome) {
+    WORST.fetch_max(outcome as u8, Ordering::Relaxed);
+}
+
+/// The exit code of a run that returned without error.
+pub fn exit_code() -> ExitCode {
+    let worst = match WORST.load(Ordering::Relaxed) {
+        0 => Outcome::Success,
+        1 => Outcome::Partial,
+        2 => Outcome::Failure,
+        _ => Outcome::Interrupted,
+    };
+    ExitCode::from(worst.code())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ceres_core::{FailureKind, PortalHarvestResult};
+
+    fn portal(name: &str, stats: SyncStats) -> PortalHarvestResult {
+        PortalHarvestResult::success(name.to_string(), format!("https://{}.it", name), stats)
+    }
+
+    #[test]
+    fn test_outcome_of_harvest() {
+        let mut summary = BatchHarvestSummary::new();
+        assert_eq!(Outcome::of_harvest(&summary), Outcome::Success);
+
+        summary.add(portal("milano", SyncStats::default()));
+        assert_eq!(Outcome::of_harvest(&summary), Outcome::Success);
+
+        let mut stats = SyncStats::default();
+        stats.record_failure(FailureKind::Embedding);
+        summary.add(portal("torino", stats));
+        assert_eq!(Outcome::of_harvest(&summary), Outcome::Partial);
+
+        let mut failed = BatchHarvestSummary::new();
+        failed.add(PortalHarvestResult::failure(
+            "sicilia".to_string(),
+            "https://sicilia.it".to_string(),
+            "timeout".to_string(),
+        ));
+        assert_eq!(Outcome::of_harvest(&failed), Outcome::Failure);
+
+        // A portal locked by another harvest fails nothing
+        let mut skipped = BatchHarvestSummary::new();
+        skipped.add(PortalHarvestResult::skipped(
+            "lazio".to_string(),
+            "https://lazio.it".to_string(),
+            "locked".to_string(),
+        ));
+        assert_eq!(Outcome::of_harvest(&skipped), Outcome::Success);
+
+        summary.add(portal(
+            "roma",
+            SyncStats {
+                interrupted: true,
+                ..Default::default()
+            },
+        ));
+        assert_eq!(Outcome::of_harvest(&summary), Outcome::Interrupted);
+    }
+}
@@ -0,0 +1,180 @@
+//! Export destination files (`ceres export --output`).
+
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+use flate2::write::GzEncoder;
+use tempfile::NamedTempFile;
+
+/// Compression applied to an export file, chosen by its extension.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    None,
+    /// `.gz`
+    Gzip,
+    /// `.zst` or `.zstd`
+    Zstd,
+}
+
+impl Compression {
+    /// Compression for `path`, from its extension.
+    pub fn from_path(path: &Path) -> Self {
+        match path.extension().and_then(|ext| ext.to_str()) {
+            Some("gz") => Self::Gzip,
+            Some("zst" | "zstd") => Self::Zstd,
+            _ => Self::None,
+        }
+    }
+}
+
+enum Encoder {
+    Plain(BufWriter<NamedTempFile>),
+    Gzip(GzEncoder<BufWriter<NamedTempFile>>),
+    Zstd(zstd::Encoder<'static, BufWriter<NamedTempFile>>),
+}
+
+/// An export being written to a file.
+///
+/// Output goes to a temporary file next to the destination, compressed
+/// according to [`Compression::from_path`]. [`commit`](Self::commit) renames
+/// it over the destination, so readers never see a partial export and a
+/// failed or interrupted export leaves any previous file untouched.
+pub struct ExportFile {
+    encoder: Encoder,
+    path: PathBuf,
+}
+
+impl ExportFile {
+    /// Starts writing an export to `path`.
+    pub fn create(path: &Path) -> anyhow::Result<Self> {
+        let dir = match path.parent() {
+            Some(parent) if !parent.as_os_str().is_empty() => parent,
+            _ => Path::new("."),
+        };
+        let mut builder = tempfile::Builder::new();
+        builder.prefix(".ceres-export-");
+        // Temporary files are private by default; the export gets the usual
+        // permissions of a new file (0666 minus the umask)
+        #[cfg(unix)]
+        {
+            use std::os::unix::fs::PermissionsExt;
+            builder.permissions(std::fs::Permissions::from_mode(0o666));
+        }
+        let temp = builder
+            .tempfile_in(dir)
+            .with_context(|| format!("Failed to create a temporary file in {}", dir.display()))?;
+        let out = BufWriter::new(temp);
+
+        let encoder = match Compression::from_path(path) {
+            Compression::None => Encoder::Plain(out),
+            Compression::Gzip => Encoder::Gzip(GzEncoder::new(out, flate2::Compression::default())),
+            Compression::Zstd => Encoder::Zstd(zstd::Encoder::new(out, 0)?),
+        };
+        Ok(Self {
+            encoder,
+            path: path.to_path_buf(),
+        })
+    }
+
+    /// Finishes compression and moves the file into place.
+    pub fn commit(self) -> anyhow::Result<()> {
+        let out = match self.encoder {
+            Encoder::Plain(out) => out,
+            Encoder::Gzip(encoder) => encoder.finish()?,
+            Encoder::Zstd(encoder) => encoder.finish()?,
+        };
+        let temp = out.into_inner().map_err(|e| e.into_error())?;
+        temp.as_file().sync_all()?;
+        temp.persist(&self.path)
+            .map_err(|e| e.error)
+            .with_context(|| format!("Failed to write {}", self.path.display()))?;
+        Ok(())
+    }
+}
+
+impl Write for ExportFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match &mut self.encoder {
+            Encoder::Plain(out) => out.write(buf),
+            Encoder::Gzip(encoder) => encoder.write(buf),
+            Encoder::Zstd(encoder) => encoder.write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match &mut self.encoder {
+            Encoder::Plain(out) => out.flush(),
+            Encoder::Gzip(encoder) => encoder.flush(),
+            Encoder::Zstd(encoder) => encoder.flush(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs::File;
+    use std::io::Read;
+
+    fn export(path: &Path, content: &[u8]) {
+        let mut file = ExportFile::create(path).unwrap();
+        file.write_all(content).unwrap();
+        file.commit().unwrap();
+    }
+
+    #[test]
+    fn test_compression_from_path() {
+        assert_eq!(
+            Compression::from_path(Path::new("a.jsonl")),
+            Compression::None
+        );
+        assert_eq!(
+            Compression::from_path(Path::new("a.jsonl.gz")),
+            Compression::Gzip
+        );
+        assert_eq!(
+            Compression::from_path(Path::new("a.csv.zst")),
+            Compression::Zstd
+        );
+    }
+
+    #[test]
+    fn test_compressed_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let content = b"{\"title\":\"Bilancio\"}\n".repeat(100);
+
+        let plain = dir.path().join("datasets.jsonl");
+        export(&plain, &content);
+        assert_eq!(std::fs::read(&plain).unwrap(), content);
+
+        let gzip = dir.path().join("datasets.jsonl.gz");
+        export(&gzip, &content);
+        let mut decoded = Vec::new();
+        flate2::read::GzDecoder::new(File::open(&gzip).unwrap())
+            .read_to_end(&mut decoded)
+            .unwrap();
+        assert_eq!(decoded, content);
+
+        let zstd = dir.path().join("datasets.jsonl.zst");
+        export(&zstd, &content);
+        assert_eq!(
+            zstd::decode_all(File::open(&zstd).unwrap()).unwrap(),
+            content
+        );
+    }
+
+    #[test]
+    fn test_uncommitted_export_leaves_previous_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("datasets.jsonl");
+        std::fs::write(&path, "previous").unwrap();
+
+        let mut file = ExportFile::create(&path).unwrap();
+        file.write_all(b"partial").unwrap();
+        drop(file);
+
+        assert_eq!(std::fs::read_to_string(&path).unwrap(), "previous");
+        assert_eq!(std::fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+}
@@ -0,0 +1,324 @@
+//! gRPC service served by `ceres serve --grpc-bind`.
+//!
+//! Search and GetDataset go through the same [`AppState`] methods as the
+//! HTTP API; Harvest runs a single-portal harvest as `ceres harvest` does.
+
+use std::future::Future;
+use std::net::SocketAddr;
+
+use anyhow::Context;
+use axum::http::StatusCode;
+use chrono::{DateTime, Utc};
+use tonic::{Request, Response, Status};
+use tracing::info;
+
+use ceres_core::facets::{FacetCount, SearchFacets};
+use ceres_core::harvest_filter::HarvestFilter;
+use ceres_core::history::SearchSource;
+use ceres_core::load_portals_config;
+
+use crate::server::{ApiError, AppState, SearchParams};
+use crate::{harvest_single, is_portal_locked, DatasetRecord, Notifiers, SearchResponse};
+
+mod pb {
+    tonic::include_proto!("ceres.v1");
+}
+
+use pb::ceres_server::{Ceres, CeresServer};
+use pb::harvest_request::Portal;
+
+/// Serves the gRPC service on `bind` until `shutdown` resolves.
+pub async fn serve(
+    state: AppState,
+    bind: SocketAddr,
+    shutdown: impl Future<Output = ()>,
+) -> anyhow::Result<()> {
+    info!("Serving the Ceres gRPC service on {}", bind);
+    tonic::transport::Server::builder()
+        .add_service(CeresServer::new(CeresService { state }))
+        .serve_with_shutdown(bind, shutdown)
+        .await
+        .with_context(|| format!("gRPC server error on {}", bind))
+}
+
+struct CeresService {
+    state: AppState,
+}
+
+#[tonic::async_trait]
+impl Ceres for CeresService {
+    async fn search(
+        &self,
+        request: Request<pb::SearchRequest>,
+    ) -> Result<Response<pb::SearchResponse>, Status> {
+        let request = request.into_inner();
+        let params = SearchParams {
+            q: Some(request.query),
+            limit: request.limit.map(|limit| limit as usize),
+            portal: request.portal,
+            theme: request.theme,
+            format: request.format,
+            license: request.license,
+            language: request.language,
+            tag: request.tag,
+            org: request.org,
+            bbox: request.bbox,
+            updated_after: request.updated_after,
+            updated_before: request.updated_before,
+            mode: request.mode,
+            quality_weight: request.quality_weight.map(|w| w.to_string()),
+        };
+        let response = self.state.search(params, SearchSource::Grpc).await?;
+        Ok(Response::new(response.into()))
+    }
+
+    async fn get_dataset(
+        &self,
+        request: Request<pb::GetDatasetRequest>,
+    ) -> Result<Response<pb::Dataset>, Status> {
+        let dataset = self.state.dataset(&request.into_inner().id).await?;
+        Ok(Response::new(dataset.into()))
+    }
+
+    async fn harvest(
+        &self,
+        request: Request<pb::HarvestRequest>,
+    ) -> Result<Response<pb::HarvestResponse>, Status> {
+        let embedder = self
+            .state
+            .embedder
+            .clone()
+            .ok_or_else(|| Status::failed_precondition("No embedding provider configured"))?;
+        let load_config =
+            |path| load_portals_config(path).map_err(|e| Status::internal(e.to_string()));
+
+        let store = self.state.store.as_ref();
+        let result = match request.into_inner().portal {
+            Some(Portal::PortalUrl(url)) => {
+                // As with `ceres harvest <url>`, notifiers need an explicit --config
+                let notifiers = match &self.state.portals_config {
+                    Some(path) => load_config(Some(path.clone()))?
+                        .map(|c| Notifiers::from_config(&c))
+                        .unwrap_or_default(),
+                    None => Notifiers::default(),
+                };
+                harvest_single(
+                    store,
+                    &embedder,
+                    &self.state.settings,
+                    &url,
+                    &url,
+                    None,
+                    false,
+                    None,
+                    &notifiers,
+                    None,
+                    None,
+                    false,
+                )
+                .await
+            }
+            Some(Portal::PortalName(name)) => {
+                let portals_config = load_config(self.state.portals_config.clone())?
+                    .ok_or_else(|| Status::failed_precondition("No portals configuration found"))?;
+                let portal = portals_config.find_by_name(&name).ok_or_else(|| {
+                    Status::not_found(format!("Portal '{}' not found in configuration", name))
+                })?;
+                let filter = HarvestFilter::for_portal(portal)
+                    .map_err(|e| Status::internal(e.to_string()))?;
+                harvest_single(
+                    store,
+                    &embedder,
+                    &self.state.settings,
+                    &portal.name,
+                    &portal.url,
+                    portal.embedding_model.as_deref(),
+                    portal.chunk_embeddings,
+                    filter.as_ref(),
+                    &Notifiers::from_config(&portals_config),
+                    None,
+                    None,
+                    false,
+                )
+                .await
+            }
+            None => return Err(Status::invalid_argument("Set portal_url or portal_name")),
+        };
+
+        // The portal's failure, recorded in its health like any harvest
+        let stats = result.map_err(|e| {
+            if is_portal_locked(&e) {
+                Status::aborted(e.to_string())
+            } else {
+                Status::unavailable(format!("Harvest failed: {:#}", e))
+            }
+        })?;
+        Ok(Response::new(pb::HarvestResponse {
+            created: stats.created as u64,
+            updated: stats.updated as u64,
+            unchanged: stats.unchanged as u64,
+            failed: stats.failed as u64,
+        }))
+    }
+}
+
+impl From<ApiError> for Status {
+    fn from(err: ApiError) -> Self {
+        match err.status {
+            StatusCode::BAD_REQUEST => Status::invalid_argument(err.message),
+            StatusCode::NOT_FOUND => Status::not_found(err.message),
+            _ => Status::internal(err.message),
+        }
+    }
+}
+
+fn timestamp(time: DateTime<Utc>) -> prost_types::Timestamp {
+    prost_types::Timestamp {
+        seconds: time.timestamp(),
+        nanos: time.timestamp_subsec_nanos() as i32,
+    }
+}
+
+impl From<DatasetRecord> for pb::Dataset {
+    fn from(record: DatasetRecord) -> Self {
+        Self {
+            id: record.id.to_string(),
+            original_id: record.original_id,
+            source_portal: record.source_portal,
+            url: record.url,
+            title: record.title,
+            description: record.description,
+            metadata_json: record.metadata.to_string(),
+            formats: record.formats,
+            quality: record.quality,
+            first_seen_at: Some(timestamp(record.first_seen_at)),
+            last_updated_at: Some(timestamp(record.last_updated_at)),
+        }
+    }
+}
+
+fn facet_counts(counts: Vec<FacetCount>) -> Vec<pb::FacetCount> {
+    counts
+        .into_iter()
+        .map(|c| pb::FacetCount {
+            value: c.value,
+            count: c.count as u64,
+        })
+        .collect()
+}
+
+impl From<SearchFacets> for pb::SearchFacets {
+    fn from(facets: SearchFacets) -> Self {
+        Self {
+            portal: facet_counts(facets.portal),
+            format: facet_counts(facets.format),
+            organization: facet_counts(facets.organization),
+            tag: facet_counts(facets.tag),
+            year: facet_counts(facets.year),
+        }
+    }
+}
+
+impl From<SearchResponse> for pb::SearchResponse {
+    fn from(response: SearchResponse) -> Self {
+        Self {
+            query: response.query,
+            expanded_queries: response.expanded_queries,
+            results: response
+                .results
+                .into_iter()
+                .map(|hit| pb::SearchHit {
+                    dataset: Some(hit.dataset.into()),
+                    score: hit.score,
+                })
+                .collect(),
+            facets: Some(response.facets.into()),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ceres_core::{NewDataset, Settings};
+    use ceres_db::{DatasetStore, MemoryStore};
+    use std::sync::Arc;
+    use tonic::Code;
+
+    async fn test_service() -> (CeresService, uuid::Uuid) {
+        let store = MemoryStore::new();
+        let id = store
+            .upsert(&NewDataset::from_metadata(
+                "bus".to_string(),
+                "https://dati.comune.milano.it".to_string(),
+                "https://dati.comune.milano.it/dataset/bus".to_string(),
+                "Fermate autobus".to_string(),
+                None,
+                serde_json::json!({"tags": [{"name": "trasporti"}]}),
+                &[],
+            ))
+            .await
+            .unwrap()
+            .id();
+        let state = AppState {
+            store: Arc::new(store),
+            embedder: None,
+            portals_config: None,
+            record_searches: false,
+            settings: Settings::default(),
+            api_token: None,
+            allow_private_webhooks: false,
+        };
+        (CeresService { state }, id)
+    }
+
+    #[tokio::test]
+    async fn test_search_and_get_dataset() {
+        let (service, id) = test_service().await;
+        let request = pb::SearchRequest {
+            query: "autobus".to_string(),
+            mode: Some("text".to_string()),
+            ..Default::default()
+        };
+        let response = service
+            .search(Request::new(request))
+            .await
+            .unwrap()
+            .into_inner();
+        let dataset = response.results[0].dataset.as_ref().unwrap();
+        assert_eq!(dataset.id, id.to_string());
+        assert_eq!(response.facets.unwrap().tag[0].value, "trasporti");
+
+        let request = pb::GetDatasetRequest { id: id.to_string() };
+        let dataset = service
+            .get_dataset(Request::new(request))
+            .await
+            .unwrap()
+            .into_inner();
+        assert_eq!(dataset.title, "Fermate autobus");
+        assert_eq!(dataset.metadata_json, r#"{"tags":[{"name":"trasporti"}]}"#);
+    }
+
+    #[tokio::test]
+    async fn test_status_codes() {
+        let (service, _) = test_service().await;
+        let request = pb::GetDatasetRequest {
+            id: uuid::Uuid::nil().to_string(),
+        };
+        let err = service
+            .get_dataset(Request::new(request))
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), Code::NotFound);
+
+        let request = pb::SearchRequest::default();
+        let err = service.search(Request::new(request)).await.unwrap_err();
+        assert_eq!(err.code(), Code::InvalidArgument);
+
+        let err = service
+            .harvest(Request::new(pb::HarvestRequest::default()))
+            .await
+            .unwrap_err();
+        assert_eq!(err.code(), Code::FailedPrecondition);
+    }
+}
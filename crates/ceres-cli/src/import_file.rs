@@ -0,0 +1,136 @@
+//! Dump files read by `ceres import`.
+
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::path::Path;
+
+use anyhow::Context;
+use ceres_core::content_hash::HashField;
+use ceres_core::NewDataset;
+use pgvector::Vector;
+use serde::Deserialize;
+use serde_json::Value;
+
+use crate::export_file::Compression;
+
+/// A dataset record of a JSON Lines export (`ceres export --format jsonl`).
+///
+/// Fields derived from the metadata (formats, tags, language, quality...)
+/// are recomputed on import, so only what harvests store verbatim is read.
+#[derive(Debug, Deserialize)]
+pub struct ImportRecord {
+    pub original_id: String,
+    pub source_portal: String,
+    pub url: String,
+    pub title: String,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub metadata: Value,
+    /// Present in exports made with `--embeddings`
+    #[serde(default)]
+    pub embedding: Option<Vec<f32>>,
+    #[serde(default)]
+    pub embedding_model: Option<String>,
+}
+
+impl ImportRecord {
+    /// The dataset to upsert, hashed over `hash_fields`. It keeps the
+    /// record's embedding only if the record also names the model that
+    /// produced it.
+    pub fn into_new_dataset(self, hash_fields: &[HashField]) -> NewDataset {
+        let metadata = match self.metadata {
+            Value::Null => Value::Object(Default::default()),
+            metadata => metadata,
+        };
+        let mut dataset = NewDataset::from_metadata(
+            self.original_id,
+            self.source_portal,
+            self.url,
+            self.title,
+            self.description,
+            metadata,
+            hash_fields,
+        );
+        if let (Some(embedding), Some(model)) = (self.embedding, self.embedding_model) {
+            dataset.embedding = Some(Vector::from(embedding));
+            dataset.embedding_model = Some(model);
+        }
+        dataset
+    }
+}
+
+/// Opens a dump for reading, decompressing it according to its extension
+/// (see [`Compression::from_path`]). `-` reads stdin.
+pub fn open_dump(path: &Path) -> anyhow::Result<Box<dyn BufRead>> {
+    if path == Path::new("-") {
+        return Ok(Box::new(io::stdin().lock()));
+    }
+    let file = File::open(path).with_context(|| format!("Failed to open {}", path.display()))?;
+    Ok(match Compression::from_path(path) {
+        Compression::None => Box::new(BufReader::new(file)),
+        Compression::Gzip => Box::new(BufReader::new(flate2::read::MultiGzDecoder::new(file))),
+        Compression::Zstd => Box::new(BufReader::new(zstd::Decoder::new(file)?)),
+    })
+}
+
+/// Reads the records of a JSON Lines dump, skipping blank lines.
+///
+/// Errors name the line they occurred on.
+pub fn read_records(reader: impl BufRead) -> impl Iterator<Item = anyhow::Result<ImportRecord>> {
+    reader
+        .lines()
+        .enumerate()
+        .map(|(i, line)| (i + 1, line))
+        .filter(|(_, line)| !matches!(line, Ok(line) if line.trim().is_empty()))
+        .map(|(line_no, line)| {
+            let line = line.with_context(|| format!("Failed to read line {}", line_no))?;
+            if line.trim_start().starts_with('[') {
+                anyhow::bail!(
+                    "Line {} starts a JSON array; import reads JSON Lines (export with --format jsonl)",
+                    line_no
+                );
+            }
+            serde_json::from_str(&line)
+                .with_context(|| format!("Invalid dataset record on line {}", line_no))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_read_records() {
+        let dump = r#"{"id":"x","original_id":"bus","source_portal":"https://dati.comune.milano.it","url":"https://dati.comune.milano.it/dataset/bus","title":"Fermate autobus","description":null,"metadata":{"tags":[{"name":"Trasporti"}]},"formats":[]}
+
+{"original_id":"aria","source_portal":"https://dati.comune.milano.it","url":"https://dati.comune.milano.it/dataset/aria","title":"Qualità dell'aria","embedding":[0.5,0.5],"embedding_model":"nomic-embed-text"}
+"#;
+        let records: Vec<ImportRecord> = read_records(dump.as_bytes())
+            .collect::<anyhow::Result<_>>()
+            .unwrap();
+        assert_eq!(records.len(), 2);
+
+        let mut records = records.into_iter();
+        let bus = records.next().unwrap().into_new_dataset(&[]);
+        assert_eq!(bus.tags, vec!["trasporti"]);
+        assert!(bus.embedding.is_none());
+
+        let air = records.next().unwrap().into_new_dataset(&[]);
+        assert_eq!(air.metadata, serde_json::json!({}));
+        assert_eq!(air.embedding.unwrap().as_slice(), &[0.5, 0.5]);
+        assert_eq!(air.embedding_model.as_deref(), Some("nomic-embed-text"));
+    }
+
+    #[test]
+    fn test_read_records_reports_line() {
+        let err = read_records("\n{\"title\": 1}\n".as_bytes())
+            .next()
+            .unwrap()
+            .unwrap_err();
+        assert!(err.to_string().contains("line 2"));
+
+        let err = read_records("[\n".as_bytes()).next().unwrap().unwrap_err();
+        assert!(err.to_string().contains("--format jsonl"));
+    }
+}
@@ -0,0 +1,109 @@
+//! Results of the last `ceres search`, which `ceres open <N>` refers to.
+
+use std::path::{Path, PathBuf};
+
+use anyhow::Context;
+use ceres_core::SearchResult;
+use serde::{Deserialize, Serialize};
+use uuid::Uuid;
+
+/// A search and its results, best first.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct LastSearch {
+    pub query: String,
+    pub results: Vec<SavedResult>,
+}
+
+/// A result of the last search.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SavedResult {
+    pub id: Uuid,
+    pub title: String,
+    pub url: String,
+}
+
+/// Where the last search is kept: `~/.cache/ceres/last-search.json`.
+pub fn default_path() -> Option<PathBuf> {
+    dirs::cache_dir().map(|dir| dir.join("ceres").join("last-search.json"))
+}
+
+impl LastSearch {
+    pub fn new(query: &str, results: &[SearchResult]) -> Self {
+        Self {
+            query: query.to_string(),
+            results: results
+                .iter()
+                .map(|r| SavedResult {
+                    id: r.dataset.id,
+                    title: r.dataset.title.clone(),
+                    url: r.dataset.url.clone(),
+                })
+                .collect(),
+        }
+    }
+
+    /// Writes the search to `path`, replacing the one kept before.
+    pub fn save(&self, path: &Path) -> anyhow::Result<()> {
+        if let Some(dir) = path.parent() {
+            std::fs::create_dir_all(dir)
+                .with_context(|| format!("Failed to create {}", dir.display()))?;
+        }
+        let json = serde_json::to_string(self)?;
+        std::fs::write(path, json).with_context(|| format!("Failed to write {}", path.display()))
+    }
+
+    /// Reads the search kept at `path`.
+    pub fn load(path: &Path) -> anyhow::Result<Self> {
+        let json = match std::fs::read_to_string(path) {
+            Ok(json) => json,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+                anyhow::bail!("No search to pick a result from; run `ceres search` first")
+            }
+            Err(e) => return Err(e).with_context(|| format!("Failed to read {}", path.display())),
+        };
+        serde_json::from_str(&json).with_context(|| format!("Failed to parse {}", path.display()))
+    }
+
+    /// The result numbered `rank`, counting from 1.
+    pub fn result(&self, rank: usize) -> anyhow::Result<&SavedResult> {
+        rank.checked_sub(1)
+            .and_then(|i| self.results.get(i))
+            .with_context(|| {
+                format!(
+                    "No result {} in the last search (\"{}\", {} results)",
+                    rank,
+                    self.query,
+                    self.results.len()
+                )
+            })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_save_and_pick_a_result() {
+        let path = std::env::temp_dir()
+            .join(format!("ceres-search-{}", Uuid::new_v4()))
+            .join("last-search.json");
+        let search = LastSearch {
+            query: "trasporto pubblico".to_string(),
+            results: vec![SavedResult {
+                id: Uuid::new_v4(),
+                title: "Fermate autobus".to_string(),
+                url: "https://dati.comune.milano.it/dataset/fermate".to_string(),
+            }],
+        };
+        search.save(&path).unwrap();
+        let loaded = LastSearch::load(&path).unwrap();
+        std::fs::remove_dir_all(path.parent().unwrap()).unwrap();
+
+        assert_eq!(loaded, search);
+        assert_eq!(loaded.result(1).unwrap().title, "Fermate autobus");
+        let err = loaded.result(2).unwrap_err().to_string();
+        assert!(err.contains("trasporto pubblico"), "{}", err);
+        assert!(LastSearch::load(&path).is_err());
+    }
+}
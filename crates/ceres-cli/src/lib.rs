@@ -3,5 +3,15 @@
 //! This crate provides the CLI application that ties together all Ceres components.
 
 pub mod config;
+pub mod export_file;
+pub mod import_file;
+pub mod last_search;
+pub mod outcome_log;
+pub mod projection;
 
-pub use config::{Command, Config, ExportFormat};
+pub use config::{
+    Command, Config, EmbeddingProviderArg, ExpandArg, ExportFormat, HashFieldArg, HistoryCommand,
+    IndexCommand, IndexKind, LockPolicyArg, OrgsCommand, PortalsCommand, RerankProviderArg,
+    ResultRef, SampleArg, SearchModeArg, SearchOutputArg, SearchStrategyArg, VectorStoreArg,
+    VectorStoreOptions, WatchCommand,
+};
@@ -1,23 +1,105 @@
 use anyhow::Context;
-use clap::Parser;
+use clap::builder::Resettable;
+use clap::{CommandFactory, FromArgMatches};
 use dotenvy::dotenv;
-use futures::stream::{self, StreamExt};
+use futures::channel::mpsc;
+use futures::stream::{self, BoxStream, StreamExt, TryStreamExt};
+use futures::FutureExt;
+use indicatif::{ProgressBar, ProgressStyle};
 use pgvector::Vector;
-use sqlx::postgres::PgPoolOptions;
-use std::sync::atomic::{AtomicUsize, Ordering};
+use serde::Serialize;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::Arc;
-use tracing::{error, info, Level};
-use tracing_subscriber::FmtSubscriber;
+use tracing::{debug, error, info, info_span, warn, Instrument};
+use tracing_subscriber::fmt::writer::BoxMakeWriter;
+use utoipa::ToSchema;
 
-use std::path::PathBuf;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::io::{IsTerminal, Write};
+use std::path::{Path, PathBuf};
+use std::process::ExitCode;
+use std::time::{Duration, Instant};
 
-use ceres_client::{CkanClient, GeminiClient};
+use chrono::{DateTime, Utc};
+use tokio::task::JoinSet;
+
+use ceres_client::cohere::CohereReranker;
+use ceres_client::embedding::ensure_compatible;
+use ceres_client::expansion::SynonymExpander;
+use ceres_client::gemini::{GeminiAnswerer, GeminiExpander, GeminiReranker};
+use ceres_client::{
+    AzureOpenAiClient, CkanClient, CohereClient, EmbeddingProvider, GeminiClient, OllamaClient,
+    QueryExpander, RateLimitedProvider, RateLimits, RegistryClient, Reranker, TeiClient,
+    VertexClient, VoyageClient, WebhookClient, WikidataClient, DEFAULT_EMBEDDING_DIMENSION,
+};
+use ceres_core::ask::source_document;
+use ceres_core::audit::{self, parse_ckan_timestamp, UpstreamRecord};
+use ceres_core::chunks::{build_chunks, chunk_text_hash, chunks_hash};
+use ceres_core::clustering::{cluster_keywords, kmeans, representatives, Topic, CLUSTER_KEYWORDS};
+use ceres_core::content_hash::{content_hash, HashField};
+use ceres_core::dcat;
+use ceres_core::enrichment::{extract_mentions, label_key, EntityRole};
+use ceres_core::expansion::{SynonymTable, MAX_EXPANSIONS};
+use ceres_core::facets::{compute_facets, SearchFacets, FACET_CANDIDATES};
+use ceres_core::harvest_filter::HarvestFilter;
+use ceres_core::health::{select_portals, PortalHealth, QuarantinePolicy, SkipReason};
+use ceres_core::history::{SearchRecord, SearchSource};
+use ceres_core::index_tuning::{check_indexable_dimension, recommend, tuning_grid, IndexFamily};
+use ceres_core::maintenance::{
+    datasets_per_month, format_size, retention_cutoff, suggest_retention, BudgetStatus,
+    GrowthProjection, RetentionSuggestion, TableSize,
+};
+use ceres_core::portal_check::{CheckStatus, PortalCheck, PortalProbe};
+use ceres_core::quality::LOW_QUALITY;
+use ceres_core::registry::{default_public_key, BundleRef};
+use ceres_core::schedule::{jitter_for, CronSchedule};
+use ceres_core::search::{
+    apply_quality_weight, apply_rerank_scores, merge_result_sets, rerank_document, SearchFilters,
+    SearchStrategy,
+};
+use ceres_core::snippet::{snippet, Snippet};
+use ceres_core::sync::ListingProgress;
+use ceres_core::watch::{
+    generate_secret, DeliveryStatus, Watch, WatchNotification, MAX_MATCHES_PER_DELIVERY,
+};
 use ceres_core::{
-    load_portals_config, needs_reprocessing, BatchHarvestSummary, Dataset, DbConfig, PortalEntry,
-    PortalHarvestResult, SyncConfig, SyncOutcome, SyncStats,
+    add_portal, default_config_path, default_settings_path, load_portals_config, load_settings,
+    merge_portals, remove_portal, rewrite_portal_url, set_portal_enabled, AppError,
+    BatchHarvestSummary, Dataset, DatasetOutcomeRecord, DbConfig, FailureCounts, FailureKind,
+    FailureStage, HarvestCheckpoint, HarvestFailure, HarvestNotification, HarvestReport,
+    HarvestSample, HttpConfig, LockPolicy, NewDataset, NotificationConfig, NotificationSink,
+    PortalEntry, PortalHarvestResult, PortalStats, PortalsConfig, ReprocessingDecision,
+    SampleMethod, SearchResult, Settings, StageDurations, SyncConfig, SyncOutcome, SyncStats,
+    UpsertOutcome, WebhookConfig,
+};
+#[cfg(feature = "qdrant")]
+use ceres_db::QdrantStore;
+#[cfg(feature = "sqlite")]
+use ceres_db::SqliteRepository;
+use ceres_db::{is_sqlite_url, DatasetRepository, DatasetStore};
+use ceres_search::export_file::ExportFile;
+use ceres_search::import_file::{open_dump, read_records};
+use ceres_search::last_search::{self, LastSearch};
+use ceres_search::outcome_log::OutcomeLog;
+use ceres_search::projection::{format_output, JqFilter};
+use ceres_search::{
+    Command, Config, EmbeddingProviderArg, ExpandArg, ExportFormat, HashFieldArg, HistoryCommand,
+    IndexCommand, IndexKind, LockPolicyArg, OrgsCommand, PortalsCommand, RerankProviderArg,
+    ResultRef, SampleArg, SearchModeArg, SearchOutputArg, SearchStrategyArg, VectorStoreArg,
+    VectorStoreOptions, WatchCommand,
 };
-use ceres_db::DatasetRepository;
-use ceres_search::{Command, Config, ExportFormat};
+use exit_status::Outcome;
+use term::{outln, AsciiWriter, Term};
+
+mod exit_status;
+#[cfg(feature = "grpc")]
+mod grpc;
+mod secrets;
+mod server;
+mod shutdown;
+mod telemetry;
+mod term;
+mod tui;
 
 /// Thread-safe wrapper for SyncStats using atomic counters.
 struct AtomicSyncStats {
@@ -25,6 +107,10 @@ struct AtomicSyncStats {
     updated: AtomicUsize,
     created: AtomicUsize,
     failed: AtomicUsize,
+    /// Failures by kind, indexed by [`FailureKind`] discriminant
+    failed_by_kind: [AtomicUsize; FailureKind::ALL.len()],
+    cache_hits: AtomicUsize,
+    cache_misses: AtomicUsize,
 }
 
 impl AtomicSyncStats {
@@ -34,6 +120,9 @@ impl AtomicSyncStats {
             updated: AtomicUsize::new(0),
             created: AtomicUsize::new(0),
             failed: AtomicUsize::new(0),
+            failed_by_kind: Default::default(),
+            cache_hits: AtomicUsize::new(0),
+            cache_misses: AtomicUsize::new(0),
         }
     }
 
@@ -46,82 +135,1398 @@ impl AtomicSyncStats {
         };
     }
 
+    fn record_failure(&self, kind: FailureKind) {
+        self.failed.fetch_add(1, Ordering::Relaxed);
+        self.failed_by_kind[kind as usize].fetch_add(1, Ordering::Relaxed);
+    }
+
+    fn record_cache_lookup(&self, hit: bool) {
+        if hit {
+            self.cache_hits.fetch_add(1, Ordering::Relaxed);
+        } else {
+            self.cache_misses.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+
     fn to_stats(&self) -> SyncStats {
+        let mut failed_by_kind = FailureCounts::default();
+        for (kind, count) in FailureKind::ALL.into_iter().zip(&self.failed_by_kind) {
+            failed_by_kind.add(kind, count.load(Ordering::Relaxed));
+        }
         SyncStats {
             unchanged: self.unchanged.load(Ordering::Relaxed),
             updated: self.updated.load(Ordering::Relaxed),
             created: self.created.load(Ordering::Relaxed),
             failed: self.failed.load(Ordering::Relaxed),
+            failed_by_kind,
+            cache_hits: self.cache_hits.load(Ordering::Relaxed),
+            cache_misses: self.cache_misses.load(Ordering::Relaxed),
+            interrupted: false,
         }
     }
 }
 
-#[tokio::main]
-async fn main() -> anyhow::Result<()> {
+fn main() -> anyhow::Result<ExitCode> {
+    // Before any thread exists, as it writes the environment
     dotenv().ok();
+    let config = parse_config()?;
+    let settings = resolve_settings(&config);
+
+    let term = Term::new(config.no_color, config.ascii);
+    term.install();
+
+    // The TUI owns the terminal, so its logs would only garble the screen
+    let writer = if matches!(config.command, Command::Tui { .. }) {
+        BoxMakeWriter::new(std::io::sink)
+    } else if term.ascii {
+        BoxMakeWriter::new(|| AsciiWriter(std::io::stderr()))
+    } else {
+        BoxMakeWriter::new(std::io::stderr)
+    };
+    let ansi = term.color && std::io::stderr().is_terminal();
+    let filter = telemetry::log_filter(config.verbose, config.quiet);
+    tokio::runtime::Builder::new_multi_thread()
+        .enable_all()
+        .build()
+        .context("Failed to start the async runtime")?
+        .block_on(async {
+            let telemetry = telemetry::init(writer, filter, ansi)?;
+            let result = run(config, &settings).await;
+            telemetry.shutdown();
+            result.map(|()| exit_status::exit_code())
+        })
+}
+
+/// Parses the command line, resolving each flag from its CLI argument, then
+/// the environment (or its `<NAME>_FILE` or `keyring:` secret), then the
+/// settings file, then its default.
+fn parse_config() -> anyhow::Result<Config> {
+    let env_vars = settings_env_vars(&Config::command());
+    let mut values = secrets::resolve_files(&env_vars)?;
+    for (name, value) in settings_file_values(&env_vars)? {
+        if std::env::var_os(&name).is_none() {
+            values.entry(name).or_insert(value);
+        }
+    }
+    let secrets = secrets::resolve_keyring(&env_vars, &values)?;
+    values.extend(secrets);
+    Ok(config_from(std::env::args_os(), &values).unwrap_or_else(|e| e.exit()))
+}
+
+/// Parses `args` with `values` standing in for the environment variables
+/// they name.
+fn config_from<I, T>(args: I, values: &HashMap<String, String>) -> Result<Config, clap::Error>
+where
+    I: IntoIterator<Item = T>,
+    T: Into<std::ffi::OsString> + Clone,
+{
+    let mut command = with_env_values(Config::command(), values);
+    let matches = command.try_get_matches_from_mut(args)?;
+    Config::from_arg_matches(&matches).map_err(|e| e.format(&mut command))
+}
+
+/// Makes each value of `values` the default of the flags reading the
+/// variable it is keyed by, in `command` and its subcommands.
+fn with_env_values(mut command: clap::Command, values: &HashMap<String, String>) -> clap::Command {
+    let defaults: Vec<(clap::Id, Vec<String>)> = command
+        .get_arguments()
+        .filter_map(|arg| {
+            let value = values.get(arg.get_env()?.to_str()?)?;
+            let defaults = if matches!(arg.get_action(), clap::ArgAction::SetTrue) {
+                // Flags read variables as clap does: set unless falsey
+                let falsey = ["", "0", "n", "no", "f", "false", "off"];
+                let set = !falsey.contains(&value.trim().to_lowercase().as_str());
+                vec![set.to_string()]
+            } else if let Some(delimiter) = arg.get_value_delimiter() {
+                value.split(delimiter).map(str::to_string).collect()
+            } else {
+                vec![value.clone()]
+            };
+            Some((arg.get_id().clone(), defaults))
+        })
+        .collect();
+    // A default does not satisfy `required`, but the value stands in for it
+    for (id, defaults) in defaults {
+        command = command.mut_arg(id, |arg| {
+            arg.env(Resettable::Reset)
+                .default_values(defaults)
+                .required(false)
+        });
+    }
+    let subcommands: Vec<String> = command
+        .get_subcommands()
+        .map(|subcommand| subcommand.get_name().to_string())
+        .collect();
+    for name in subcommands {
+        command = command.mut_subcommand(name, |subcommand| with_env_values(subcommand, values));
+    }
+    command
+}
+
+/// The tunables `config` resolved to.
+fn resolve_settings(config: &Config) -> Settings {
+    Settings {
+        db: DbConfig {
+            max_connections: config.db_max_connections,
+            connect_retries: config.db_connect_retries,
+            ..Default::default()
+        },
+        http: HttpConfig {
+            timeout: Duration::from_secs(config.http_timeout),
+            max_retries: config.http_max_retries,
+            proxy: config.proxy.clone(),
+            ca_certificates: config.ca_certs.clone(),
+            insecure_skip_verify: config.insecure_skip_verify,
+            user_agent: config.user_agent.clone(),
+            contact_email: config.contact_email.clone(),
+            ignore_robots_txt: config.ignore_robots_txt,
+            response_cache_dir: config.response_cache.clone(),
+            response_cache_max_age: config
+                .response_cache_max_age
+                .map(|hours| Duration::from_secs(hours * 3600)),
+            ..Default::default()
+        },
+        sync: SyncConfig {
+            concurrency: config.concurrency,
+            max_concurrency: config.max_concurrency,
+            hash_fields: config
+                .hash_fields
+                .iter()
+                .map(|field| match field {
+                    HashFieldArg::Resources => HashField::Resources,
+                    HashFieldArg::Tags => HashField::Tags,
+                    HashFieldArg::License => HashField::License,
+                    HashFieldArg::Organization => HashField::Organization,
+                    HashFieldArg::Modified => HashField::Modified,
+                })
+                .collect(),
+            on_locked: match config.on_locked {
+                LockPolicyArg::Skip => LockPolicy::Skip,
+                LockPolicyArg::Wait => LockPolicy::Wait,
+            },
+        },
+    }
+}
+
+/// The settings file's values, by the environment variable they name.
+fn settings_file_values(known: &HashSet<String>) -> anyhow::Result<Vec<(String, String)>> {
+    let Some(path) = default_settings_path() else {
+        return Ok(Vec::new());
+    };
+    let settings = load_settings(&path)?;
+    if let Some((name, _)) = settings.iter().find(|(name, _)| !known.contains(name)) {
+        anyhow::bail!(
+            "Unknown setting '{}' in '{}'",
+            name.to_lowercase(),
+            path.display()
+        );
+    }
+    Ok(settings)
+}
+
+/// Environment variables read by `command`'s flags and its subcommands'.
+fn settings_env_vars(command: &clap::Command) -> HashSet<String> {
+    let mut vars: HashSet<String> = command
+        .get_arguments()
+        .filter_map(|arg| arg.get_env())
+        .map(|var| var.to_string_lossy().into_owned())
+        .collect();
+    for subcommand in command.get_subcommands() {
+        vars.extend(settings_env_vars(subcommand));
+    }
+    vars
+}
 
-    let subscriber = FmtSubscriber::builder()
-        .with_max_level(Level::INFO)
-        .with_writer(std::io::stderr)
-        .finish();
-    tracing::subscriber::set_global_default(subscriber).expect("setting default subscriber failed");
+/// An error that stops the CLI before it can run a command, headed by its
+/// hint for the user.
+fn startup_error(err: AppError) -> anyhow::Error {
+    let message = err.user_message();
+    anyhow::Error::new(err).context(message)
+}
 
-    let config = Config::parse();
+async fn run(config: Config, settings: &Settings) -> anyhow::Result<()> {
+    // Only talks to portals, so it works before the database is set up
+    if let Command::Check {
+        portal,
+        config: config_path,
+    } = &config.command
+    {
+        return check_portals(
+            config_path.clone(),
+            portal.as_deref(),
+            config.json,
+            settings,
+        )
+        .await;
+    }
+    // Only reads the last search, so it works without the database
+    if let Command::Open {
+        result: ResultRef::Rank(rank),
+        print,
+    } = config.command
+    {
+        return open_search_result(rank, print);
+    }
+    if is_sqlite_url(&config.database_url) {
+        return run_sqlite(config, settings).await;
+    }
 
     info!("Connecting to database...");
-    let db_config = DbConfig::default();
-    let pool = PgPoolOptions::new()
-        .max_connections(db_config.max_connections)
-        .connect(&config.database_url)
+    let repo = DatasetRepository::connect(&config.database_url, &settings.db)
+        .await
+        .map_err(startup_error)?;
+    repo.check_pgvector().await.map_err(startup_error)?;
+
+    // Runs before anything reads the schema, which may not exist yet
+    if let Command::Migrate { dry_run } = config.command {
+        return migrate(&repo, dry_run).await;
+    }
+    let stored_dimension = repo
+        .embedding_dimension()
         .await
-        .context("Failed to connect to database")?;
+        .context("Failed to read the embedding column (run `ceres migrate` first)")?;
+    let (embedder, reranker) =
+        build_command_providers(&config, stored_dimension, &settings.http).await?;
 
-    let repo = DatasetRepository::new(pool);
-    let gemini_client = GeminiClient::new(&config.gemini_api_key)
-        .context("Failed to initialize embedding client")?;
+    if matches!(
+        config.command,
+        Command::Harvest { .. } | Command::Daemon { .. }
+    ) {
+        advise_hash_backfill(&repo).await;
+    }
 
+    let json = config.json;
+    let search_history = !config.no_search_history;
     match config.command {
+        command @ Command::Harvest { .. } => {
+            let store = open_vector_store(
+                repo.clone(),
+                &config.vector_store,
+                embedder.as_deref(),
+                &settings.http,
+            )
+            .await?;
+            let gemini_api_key = config.gemini_api_key.as_deref();
+            run_store_command(
+                store,
+                command,
+                embedder,
+                reranker,
+                settings,
+                gemini_api_key,
+                search_history,
+                json,
+            )
+            .await?;
+            deliver_watch_matches(&repo, &settings.http).await;
+        }
+        command @ (Command::Search { .. }
+        | Command::Export { .. }
+        | Command::Import { .. }
+        | Command::Stats { .. }
+        | Command::Delete { .. }
+        | Command::Prune { .. }
+        | Command::Failures { .. }
+        | Command::RetryFailed { .. }
+        | Command::Tui { .. }
+        | Command::Serve { .. }
+        | Command::Open { .. }
+        | Command::History { .. }) => {
+            let store = open_vector_store(
+                repo.clone(),
+                &config.vector_store,
+                embedder.as_deref(),
+                &settings.http,
+            )
+            .await?;
+            let gemini_api_key = config.gemini_api_key.as_deref();
+            run_store_command(
+                store,
+                command,
+                embedder,
+                reranker,
+                settings,
+                gemini_api_key,
+                search_history,
+                json,
+            )
+            .await?;
+        }
+        Command::Ask {
+            question,
+            top_k,
+            portal,
+            language,
+            mode,
+            model,
+            answer_model,
+        } => {
+            let api_key = config
+                .gemini_api_key
+                .as_deref()
+                .ok_or_else(|| anyhow::anyhow!("GEMINI_API_KEY is required for ask"))?;
+            let answerer = GeminiAnswerer::new(api_key, &settings.http)
+                .context("Failed to initialize answerer")?;
+            let answerer = match answer_model {
+                Some(model) => answerer.with_model(&model),
+                None => answerer,
+            };
+            if model.is_some() && mode == SearchModeArg::Text {
+                anyhow::bail!("--model has no effect with --mode text");
+            }
+            let embedder = embedder.map(|embedder| match &model {
+                Some(model) => embedder.for_model(model),
+                None => embedder,
+            });
+            let filters = SearchFilters {
+                portal,
+                embedding_model: embedder.as_ref().map(|e| e.model_id().to_string()),
+                language,
+                ..Default::default()
+            };
+            let options = SearchOptions {
+                limit: top_k,
+                filters: &filters,
+                strategy: SearchStrategy::Auto,
+                mode,
+                chunks: false,
+                rerank: None,
+                quality_weight: 0.0,
+                expander: None,
+                output: if json {
+                    SearchOutputArg::Json
+                } else {
+                    SearchOutputArg::Text
+                },
+                history: None,
+            };
+            let store = open_vector_store(
+                repo.clone(),
+                &config.vector_store,
+                embedder.as_deref(),
+                &settings.http,
+            )
+            .await?;
+            ask(
+                store.as_ref(),
+                embedder.as_deref(),
+                &answerer,
+                &question,
+                &options,
+            )
+            .await?;
+        }
+        Command::Show { id, ids_file, jq } => {
+            let projection = jq.as_deref().map(JqFilter::parse).transpose()?;
+            match (id, ids_file) {
+                (Some(id), _) => show_dataset(&repo, id, projection.as_ref()).await?,
+                (None, Some(path)) => show_datasets(&repo, &path, projection.as_ref()).await?,
+                (None, None) => unreachable!("clap requires an ID or --ids-file"),
+            }
+        }
+        Command::Migrate { .. } => unreachable!("handled before the schema is read"),
+        Command::Check { .. } => unreachable!("handled before connecting"),
+        Command::TopTags { limit, portal } => {
+            show_top_tags(&repo, portal.as_deref(), limit, json).await?;
+        }
+        Command::Orgs {
+            action: OrgsCommand::List { limit, portal },
+        } => {
+            list_orgs(&repo, portal.as_deref(), limit, json).await?;
+        }
+        Command::Cluster {
+            k,
+            portal,
+            model,
+            examples,
+        } => {
+            let model = match model {
+                Some(model) => model,
+                None => embedder.context(NO_EMBEDDER)?.model_id().to_string(),
+            };
+            cluster_topics(&repo, &model, portal.as_deref(), k, examples, json).await?;
+        }
+        Command::BackfillHashes { batch_size, rehash } => {
+            backfill_hashes(&repo, batch_size, rehash, &settings.sync.hash_fields).await?;
+        }
+        Command::BackfillLanguages { batch_size } => {
+            backfill_languages(&repo, batch_size).await?;
+        }
+        Command::BackfillQuality {
+            batch_size,
+            rescore,
+        } => {
+            backfill_quality(&repo, batch_size, rescore).await?;
+        }
+        Command::BackfillTags { batch_size } => {
+            backfill_tags(&repo, batch_size).await?;
+        }
+        Command::BackfillSpatial { batch_size } => {
+            backfill_spatial(&repo, batch_size).await?;
+        }
+        Command::Maintain {
+            disk_budget,
+            retention_months,
+            apply,
+        } => {
+            maintain(&repo, disk_budget, retention_months, apply).await?;
+        }
+        Command::Audit {
+            portal,
+            config: config_path,
+            limit,
+        } => {
+            let url = resolve_portal_url(config_path, &portal)?;
+            audit_portal(&repo, &url, json, limit, settings).await?;
+        }
+        Command::Enrich {
+            portal,
+            config: config_path,
+            limit,
+            language,
+            wikidata_url,
+        } => {
+            let url = portal
+                .map(|portal| resolve_portal_url(config_path, &portal))
+                .transpose()?;
+            let wikidata = WikidataClient::new(&settings.http)?
+                .with_language(&language)
+                .with_api_url(&wikidata_url);
+            enrich_datasets(&repo, &wikidata, url.as_deref(), limit).await?;
+        }
+        Command::Portals {
+            action,
+            config: config_path,
+        } => match action.unwrap_or(PortalsCommand::List) {
+            PortalsCommand::List => list_portals(&repo, config_path, json).await?,
+            PortalsCommand::Add {
+                name,
+                url,
+                portal_type,
+                description,
+                disabled,
+            } => {
+                let path = portal_config_path(config_path)?;
+                let portal = PortalEntry {
+                    name: name.clone(),
+                    url,
+                    portal_type: portal_type.as_str().to_string(),
+                    enabled: !disabled,
+                    description,
+                    schedule: None,
+                    embedding_model: None,
+                    chunk_embeddings: false,
+                    include_tags: Vec::new(),
+                    exclude_orgs: Vec::new(),
+                    include_title: None,
+                    exclude_title: None,
+                };
+                add_portal(&path, &portal)?;
+                outln!("✓ Added portal '{}' to {}", name, path.display());
+                if !disabled {
+                    outln!("  Harvest it with: ceres harvest --only {}", name);
+                }
+            }
+            PortalsCommand::Remove { name } => {
+                let path = portal_config_path(config_path)?;
+                let removed = remove_portal(&path, &name)?;
+                outln!("✓ Removed portal '{}' from {}", removed, path.display());
+                outln!("  Its indexed datasets are kept in the database.");
+            }
+            PortalsCommand::Enable { name } => {
+                let path = portal_config_path(config_path)?;
+                if set_portal_enabled(&path, &name, true)? {
+                    outln!("✓ Enabled portal '{}'", name);
+                } else {
+                    outln!("Portal '{}' is already enabled.", name);
+                }
+            }
+            PortalsCommand::Disable { name } => {
+                let path = portal_config_path(config_path)?;
+                if set_portal_enabled(&path, &name, false)? {
+                    outln!("✓ Disabled portal '{}'", name);
+                } else {
+                    outln!("Portal '{}' is already disabled.", name);
+                }
+            }
+            PortalsCommand::Unquarantine { name } => {
+                unquarantine_portal(&repo, config_path, &name).await?
+            }
+            PortalsCommand::Migrate {
+                from,
+                to,
+                skip_config,
+            } => {
+                let config_path = if skip_config {
+                    None
+                } else {
+                    config_path.or_else(default_config_path)
+                };
+                migrate_portal(&repo, config_path, &from, &to).await?
+            }
+            PortalsCommand::Install {
+                bundle,
+                registry,
+                registry_key,
+                allow_unsigned,
+            } => {
+                install_bundle(
+                    config_path,
+                    &bundle,
+                    &registry,
+                    registry_key.as_deref(),
+                    allow_unsigned,
+                    &settings.http,
+                )
+                .await?
+            }
+        },
+        Command::Daemon {
+            config: config_path,
+            default_schedule,
+            max_jitter,
+        } => {
+            let embedder = embedder.context(NO_EMBEDDER)?;
+            let store = open_vector_store(
+                repo.clone(),
+                &config.vector_store,
+                Some(embedder.as_ref()),
+                &settings.http,
+            )
+            .await?;
+            shutdown::listen();
+            run_daemon(
+                &repo,
+                store,
+                &embedder,
+                settings,
+                config_path,
+                default_schedule,
+                Duration::from_secs(max_jitter),
+            )
+            .await?;
+        }
+        Command::Watch { action } => match action {
+            WatchCommand::Add {
+                name,
+                query,
+                webhook,
+                min_score,
+                portal,
+                model,
+            } => {
+                let embedder = embedder.context(NO_EMBEDDER)?;
+                let embedder = match model {
+                    Some(model) => embedder.for_model(&model),
+                    None => embedder,
+                };
+                add_watch(
+                    &repo,
+                    embedder.as_ref(),
+                    name,
+                    query,
+                    webhook,
+                    min_score,
+                    portal,
+                )
+                .await?;
+            }
+            WatchCommand::List => list_watches(&repo).await?,
+            WatchCommand::Remove { name } => {
+                if repo.delete_watch(&name).await? {
+                    outln!("✓ Watch '{}' removed.", name);
+                } else {
+                    anyhow::bail!("Watch '{}' not found", name);
+                }
+            }
+            WatchCommand::Deliveries { name, limit } => {
+                show_watch_deliveries(&repo, &name, limit).await?;
+            }
+            WatchCommand::Run => {
+                let delivered = process_watches(&repo, &settings.http).await?;
+                outln!("✓ Delivered {} new matches.", delivered);
+            }
+        },
+        Command::Index { action } => match action {
+            IndexCommand::Tune {
+                queries,
+                k,
+                target_recall,
+                kind,
+                apply,
+            } => {
+                tune_index(&repo, queries, k, target_recall, kind, apply).await?;
+            }
+            IndexCommand::Resize { apply } => {
+                let embedder = embedder.context(NO_EMBEDDER)?;
+                resize_index(&repo, embedder.as_ref(), stored_dimension, apply).await?;
+            }
+        },
+    }
+
+    Ok(())
+}
+
+/// Returns true if `config.command` embeds text, so needs a provider.
+///
+/// Every other command runs without one, and without its API key.
+fn needs_embedder(config: &Config) -> bool {
+    match &config.command {
+        Command::Harvest { .. }
+        | Command::RetryFailed { .. }
+        | Command::Daemon { .. }
+        | Command::Serve { .. }
+        | Command::Watch {
+            action: WatchCommand::Add { .. },
+        }
+        | Command::Index {
+            action: IndexCommand::Resize { .. },
+        } => true,
+        Command::Search { mode, .. } | Command::Ask { mode, .. } | Command::Tui { mode, .. } => {
+            *mode != SearchModeArg::Text
+        }
+        // Topics are clustered for the provider's model unless one is given
+        Command::Cluster { model, .. } => model.is_none(),
+        // A new Qdrant collection is sized for the provider's vectors
+        Command::Import { .. } => config.vector_store.vector_store == VectorStoreArg::Qdrant,
+        _ => false,
+    }
+}
+
+/// Builds the embedding provider and reranker `config.command` needs.
+///
+/// Unless the command resizes the index, the provider must produce
+/// vectors of `stored_dimension` floats.
+async fn build_command_providers(
+    config: &Config,
+    stored_dimension: Option<usize>,
+    http_config: &HttpConfig,
+) -> anyhow::Result<(
+    Option<Arc<dyn EmbeddingProvider>>,
+    Option<Arc<dyn Reranker>>,
+)> {
+    let dimension = config
+        .embedding_dimension
+        .or(stored_dimension)
+        .unwrap_or(DEFAULT_EMBEDDING_DIMENSION);
+    let embedder = if needs_embedder(config) {
+        Some(build_embedding_provider(config, dimension, http_config).await?)
+    } else {
+        None
+    };
+
+    // `index resize` is how a mismatch gets fixed, so it must not be blocked by it
+    let resizing = matches!(
+        config.command,
+        Command::Index {
+            action: IndexCommand::Resize { .. }
+        }
+    );
+    if let (Some(embedder), Some(stored), false) = (&embedder, stored_dimension, resizing) {
+        ensure_compatible(embedder.as_ref(), stored)?;
+    }
+
+    let reranker = if matches!(config.command, Command::Search { rerank: true, .. }) {
+        Some(build_reranker(config, http_config).await?)
+    } else {
+        None
+    };
+    Ok((embedder, reranker))
+}
+
+/// Wraps `store` in the vector index selected with `--vector-store`.
+///
+/// A new Qdrant collection is sized for the embedder and filled with the
+/// embeddings already in the database.
+#[cfg(feature = "qdrant")]
+async fn open_vector_store<S: DatasetStore + 'static>(
+    store: S,
+    options: &VectorStoreOptions,
+    embedder: Option<&dyn EmbeddingProvider>,
+    http_config: &HttpConfig,
+) -> anyhow::Result<Arc<dyn DatasetStore>> {
+    if options.vector_store == VectorStoreArg::Database {
+        return Ok(Arc::new(store));
+    }
+
+    let mut qdrant = QdrantStore::new(store, &options.qdrant_url, http_config)?
+        .with_collection(&options.qdrant_collection);
+    if let Some(api_key) = &options.qdrant_api_key {
+        qdrant = qdrant.with_api_key(api_key);
+    }
+    if let Some(embedder) = embedder {
+        let created = qdrant
+            .ensure_collection(embedder.dimension())
+            .await
+            .context("Failed to open the Qdrant collection")?;
+        if created {
+            info!(
+                "Created Qdrant collection '{}', copying stored embeddings...",
+                qdrant.collection()
+            );
+            let copied = qdrant
+                .backfill()
+                .await
+                .context("Failed to fill the Qdrant collection")?;
+            info!("Copied {} embeddings to Qdrant", copied);
+        }
+    }
+    Ok(Arc::new(qdrant))
+}
+
+#[cfg(not(feature = "qdrant"))]
+async fn open_vector_store<S: DatasetStore + 'static>(
+    store: S,
+    options: &VectorStoreOptions,
+    _embedder: Option<&dyn EmbeddingProvider>,
+    _http_config: &HttpConfig,
+) -> anyhow::Result<Arc<dyn DatasetStore>> {
+    if options.vector_store == VectorStoreArg::Qdrant {
+        anyhow::bail!(
+            "Qdrant support is not compiled in. Reinstall with: cargo install ceres-search --features qdrant"
+        );
+    }
+    Ok(Arc::new(store))
+}
+
+/// Runs the commands every storage backend supports: harvest, search,
+/// export, import, stats, delete, prune, failures, retry-failed, tui, serve,
+/// open and history.
+#[allow(clippy::too_many_arguments)]
+async fn run_store_command(
+    store: Arc<dyn DatasetStore>,
+    command: Command,
+    embedder: Option<Arc<dyn EmbeddingProvider>>,
+    reranker: Option<Arc<dyn Reranker>>,
+    settings: &Settings,
+    gemini_api_key: Option<&str>,
+    search_history: bool,
+    json: bool,
+) -> anyhow::Result<()> {
+    match command {
         Command::Harvest {
             portal_url,
             portal,
+            only,
+            include_quarantined,
             config: config_path,
+            outcome_log,
+            chunks,
+            limit,
+            sample,
+            seed,
+            retry_failed,
         } => {
-            handle_harvest(&repo, &gemini_client, portal_url, portal, config_path).await?;
+            let outcome_log = outcome_log.as_deref().map(OutcomeLog::open).transpose()?;
+            let embedder = embedder.context(NO_EMBEDDER)?;
+            shutdown::listen();
+            let sample = limit.map(|limit| HarvestSample {
+                limit: limit as usize,
+                method: match sample {
+                    SampleArg::First => SampleMethod::First,
+                    SampleArg::Random => SampleMethod::Random {
+                        seed: seed.unwrap_or_else(random_seed),
+                    },
+                },
+            });
+            handle_harvest(
+                store.as_ref(),
+                &embedder,
+                settings,
+                portal_url,
+                portal,
+                &only,
+                include_quarantined,
+                config_path,
+                outcome_log.as_ref(),
+                chunks,
+                sample,
+                retry_failed,
+                json,
+            )
+            .await?;
         }
-        Command::Search { query, limit } => {
-            search(&repo, &gemini_client, &query, limit).await?;
+        Command::Search {
+            query,
+            limit,
+            portal,
+            theme,
+            format,
+            license,
+            updated_after,
+            updated_before,
+            language,
+            tag,
+            org,
+            r#where,
+            bbox,
+            near,
+            mode,
+            strategy,
+            model,
+            chunks,
+            rerank: _,
+            rerank_candidates,
+            quality_weight,
+            expand,
+            synonyms,
+            output,
+        } => {
+            if chunks && mode != SearchModeArg::Semantic {
+                anyhow::bail!("--chunks is only supported with --mode semantic");
+            }
+            if model.is_some() && mode == SearchModeArg::Text {
+                anyhow::bail!("--model has no effect with --mode text");
+            }
+            let expander = expand
+                .map(|source| build_expander(source, synonyms.as_deref(), gemini_api_key, &settings.http))
+                .transpose()?;
+            let embedder = embedder.map(|embedder| match &model {
+                Some(model) => embedder.for_model(model),
+                None => embedder,
+            });
+            let filters = SearchFilters {
+                portal,
+                theme,
+                embedding_model: embedder.as_ref().map(|e| e.model_id().to_string()),
+                format: format.as_deref().and_then(NewDataset::normalize_format),
+                license,
+                updated_after,
+                updated_before,
+                language,
+                tag,
+                organization: org,
+                metadata: r#where,
+                bbox: bbox.or(near),
+            };
+            let strategy = match strategy {
+                SearchStrategyArg::Auto => SearchStrategy::Auto,
+                SearchStrategyArg::Direct => SearchStrategy::Direct,
+                SearchStrategyArg::TwoStage => SearchStrategy::TwoStage,
+            };
+            let options = SearchOptions {
+                limit,
+                filters: &filters,
+                strategy,
+                mode,
+                chunks,
+                rerank: reranker.as_deref().map(|r| (r, rerank_candidates)),
+                quality_weight,
+                expander: expander.as_deref(),
+                output: if json { SearchOutputArg::Json } else { output },
+                history: search_history.then_some(SearchSource::Cli),
+            };
+            search(store.as_ref(), embedder.as_deref(), &query, &options).await?;
         }
         Command::Export {
             format,
             portal,
+            org,
+            r#where,
+            limit,
+            jq,
+            output,
+            embeddings,
+        } => {
+            let projection = jq.as_deref().map(JqFilter::parse).transpose()?;
+            let filters = SearchFilters {
+                portal,
+                organization: org,
+                metadata: r#where,
+                ..Default::default()
+            };
+            export(
+                store.as_ref(),
+                format,
+                &filters,
+                limit,
+                projection.as_ref(),
+                output.as_deref(),
+                embeddings,
+            )
+            .await?;
+        }
+        Command::Import { path } => {
+            import(store.as_ref(), &path, &settings.sync.hash_fields).await?;
+        }
+        Command::Stats { portal } => {
+            show_stats(store.as_ref(), portal.as_deref(), json).await?;
+        }
+        Command::Delete {
+            portal,
+            config: config_path,
+            yes,
+        } => {
+            delete_portal(store.as_ref(), config_path, &portal, yes).await?;
+        }
+        Command::Prune {
+            older_than,
+            portal,
+            config: config_path,
+            yes,
+        } => {
+            let portal = portal
+                .map(|portal| resolve_portal_url(config_path, &portal))
+                .transpose()?;
+            prune_unseen(store.as_ref(), older_than, portal.as_deref(), yes).await?;
+        }
+        Command::Failures {
+            portal,
+            config: config_path,
+            clear,
+        } => {
+            let portal = portal
+                .map(|portal| resolve_portal_url(config_path, &portal))
+                .transpose()?;
+            list_failures(store.as_ref(), portal.as_deref(), clear, json).await?;
+        }
+        Command::RetryFailed {
+            portal,
+            config: config_path,
+            outcome_log,
+        } => {
+            let outcome_log = outcome_log.as_deref().map(OutcomeLog::open).transpose()?;
+            let embedder = embedder.context(NO_EMBEDDER)?;
+            shutdown::listen();
+            let portal = portal
+                .map(|portal| resolve_portal_url(config_path.clone(), &portal))
+                .transpose()?;
+            retry_failures(
+                store.as_ref(),
+                &embedder,
+                settings,
+                portal.as_deref(),
+                config_path,
+                outcome_log.as_ref(),
+                json,
+            )
+            .await?;
+        }
+        Command::Tui {
+            query,
             limit,
+            portal,
+            mode,
+        } => {
+            let filters = SearchFilters {
+                portal,
+                embedding_model: embedder.as_ref().map(|e| e.model_id().to_string()),
+                ..Default::default()
+            };
+            let options = tui::TuiOptions {
+                limit,
+                filters,
+                mode,
+            };
+            tui::run(store.as_ref(), embedder.as_deref(), &options, query).await?;
+        }
+        Command::Serve {
+            bind,
+            grpc_bind,
+            config: portals_config,
+            allow_origin,
+            api_token,
+            allow_private_webhooks,
         } => {
-            export(&repo, format, portal.as_deref(), limit).await?;
+            let state = server::AppState {
+                store,
+                embedder,
+                portals_config,
+                record_searches: search_history,
+                settings: settings.clone(),
+                api_token,
+                allow_private_webhooks,
+            };
+            server::serve(state, bind, &allow_origin, grpc_bind).await?;
         }
-        Command::Stats => {
-            show_stats(&repo).await?;
+        Command::Open {
+            result: ResultRef::Id(id),
+            print,
+        } => {
+            let dataset = store
+                .datasets_by_ids(&[id], &SearchFilters::default())
+                .await?
+                .into_iter()
+                .next()
+                .with_context(|| format!("Dataset {} not found", id))?;
+            open_url(&dataset.url, print)?;
         }
+        Command::History {
+            action: HistoryCommand::Search { limit, clear },
+        } => list_search_history(store.as_ref(), limit, clear, json).await?,
+        _ => unreachable!(
+            "only harvest, search, export, import, stats, delete, prune, failures, retry-failed, tui, serve, open and history run on any store"
+        ),
     }
 
     Ok(())
 }
 
+/// Runs a command on a SQLite database (`DATABASE_URL=sqlite://...`).
+///
+/// The database file and its schema are created on first use.
+#[cfg(feature = "sqlite")]
+async fn run_sqlite(config: Config, settings: &Settings) -> anyhow::Result<()> {
+    if !matches!(
+        config.command,
+        Command::Harvest { .. }
+            | Command::Search { .. }
+            | Command::Export { .. }
+            | Command::Import { .. }
+            | Command::Stats { .. }
+            | Command::Delete { .. }
+            | Command::Prune { .. }
+            | Command::Failures { .. }
+            | Command::RetryFailed { .. }
+            | Command::Tui { .. }
+            | Command::Serve { .. }
+            | Command::Open { .. }
+            | Command::History { .. }
+            | Command::Migrate { .. }
+    ) {
+        anyhow::bail!(
+            "This command requires PostgreSQL; the SQLite backend supports harvest, search, export, import, stats, delete, prune, failures, retry-failed, tui, serve, open, history and migrate"
+        );
+    }
+    let store = match &config.vector_store.sqlite_vec {
+        Some(extension) => {
+            SqliteRepository::connect_with_sqlite_vec(&config.database_url, extension)
+                .await
+                .with_context(|| format!("Failed to open SQLite database with {}", extension))?
+        }
+        None => SqliteRepository::connect(&config.database_url)
+            .await
+            .context("Failed to open SQLite database")?,
+    };
+    if let Command::Migrate { dry_run } = config.command {
+        return migrate(&store, dry_run).await;
+    }
+    store
+        .run_migrations()
+        .await
+        .context("Failed to create the SQLite schema")?;
+
+    let stored_dimension = store.embedding_dimension().await?;
+    let (embedder, reranker) =
+        build_command_providers(&config, stored_dimension, &settings.http).await?;
+    let store = open_vector_store(
+        store,
+        &config.vector_store,
+        embedder.as_deref(),
+        &settings.http,
+    )
+    .await?;
+    let gemini_api_key = config.gemini_api_key.as_deref();
+    run_store_command(
+        store,
+        config.command,
+        embedder,
+        reranker,
+        settings,
+        gemini_api_key,
+        !config.no_search_history,
+        config.json,
+    )
+    .await
+}
+
+#[cfg(not(feature = "sqlite"))]
+async fn run_sqlite(_config: Config, _settings: &Settings) -> anyhow::Result<()> {
+    anyhow::bail!(
+        "SQLite support is not compiled in. Reinstall with: cargo install ceres-search --features sqlite"
+    )
+}
+
+/// Error for commands reached without an embedding provider, which is only
+/// skipped for keyword search.
+const NO_EMBEDDER: &str = "No embedding provider configured";
+
+/// Builds the embedding provider selected with `--embedding-provider`,
+/// producing vectors of `dimension` floats.
+///
+/// With `--embedding-rpm` or `--embedding-tpm` set, the provider is wrapped
+/// in a rate limiter shared by every worker of the process.
+async fn build_embedding_provider(
+    config: &Config,
+    dimension: usize,
+    http_config: &HttpConfig,
+) -> anyhow::Result<Arc<dyn EmbeddingProvider>> {
+    let provider: Arc<dyn EmbeddingProvider> = match config.embedding_provider {
+        EmbeddingProviderArg::Gemini => {
+            let api_key = config.gemini_api_key.as_deref().ok_or_else(|| {
+                anyhow::anyhow!("GEMINI_API_KEY is required for the gemini embedding provider")
+            })?;
+            let client = GeminiClient::new(api_key, http_config)
+                .context("Failed to initialize embedding client")?
+                .with_dimension(dimension);
+            match &config.embedding_model {
+                Some(model) => Arc::new(client.with_model(model)),
+                None => Arc::new(client),
+            }
+        }
+        EmbeddingProviderArg::Ollama => {
+            let mut client = OllamaClient::new(&config.embedding_base_url, http_config)
+                .context("Failed to initialize embedding client")?
+                .with_dimension(dimension);
+            if let Some(model) = &config.embedding_model {
+                client = client.with_model(model);
+            }
+            if let Some(api_key) = &config.embedding_api_key {
+                client = client.with_api_key(api_key);
+            }
+            Arc::new(client)
+        }
+        EmbeddingProviderArg::Cohere => {
+            let api_key = config.cohere_api_key.as_deref().ok_or_else(|| {
+                anyhow::anyhow!("COHERE_API_KEY is required for the cohere embedding provider")
+            })?;
+            let client = CohereClient::new(api_key, http_config)
+                .context("Failed to initialize embedding client")?
+                .with_dimension(dimension);
+            match &config.embedding_model {
+                Some(model) => Arc::new(client.with_model(model)),
+                None => Arc::new(client),
+            }
+        }
+        EmbeddingProviderArg::Voyage => {
+            let api_key = config.voyage_api_key.as_deref().ok_or_else(|| {
+                anyhow::anyhow!("VOYAGE_API_KEY is required for the voyage embedding provider")
+            })?;
+            let client = VoyageClient::new(api_key, http_config)
+                .context("Failed to initialize embedding client")?
+                .with_dimension(dimension);
+            match &config.embedding_model {
+                Some(model) => Arc::new(client.with_model(model)),
+                None => Arc::new(client),
+            }
+        }
+        EmbeddingProviderArg::Vertex => {
+            let client = VertexClient::new(
+                config.google_credentials.as_deref(),
+                config.vertex_project.as_deref(),
+                &config.vertex_location,
+                http_config,
+            )
+            .context("Failed to initialize embedding client")?
+            .with_dimension(dimension);
+            match &config.embedding_model {
+                Some(model) => Arc::new(client.with_model(model)),
+                None => Arc::new(client),
+            }
+        }
+        EmbeddingProviderArg::Azure => {
+            let required = |value: &Option<String>, name: &str| {
+                value.clone().ok_or_else(|| {
+                    anyhow::anyhow!("{} is required for the azure embedding provider", name)
+                })
+            };
+            let endpoint = required(&config.azure_endpoint, "AZURE_OPENAI_ENDPOINT")?;
+            let api_key = required(&config.azure_api_key, "AZURE_OPENAI_API_KEY")?;
+            let deployment = match &config.embedding_model {
+                Some(model) => model.clone(),
+                None => required(&config.azure_deployment, "AZURE_OPENAI_DEPLOYMENT")?,
+            };
+            Arc::new(
+                AzureOpenAiClient::new(&endpoint, &deployment, &api_key, http_config)
+                    .context("Failed to initialize embedding client")?
+                    .with_api_version(&config.azure_api_version)
+                    .with_dimension(dimension),
+            )
+        }
+        EmbeddingProviderArg::Tei => {
+            let url = config.tei_url.as_deref().ok_or_else(|| {
+                anyhow::anyhow!("TEI_URL is required for the tei embedding provider")
+            })?;
+            let mut client = TeiClient::new(url, http_config)
+                .context("Failed to initialize embedding client")?
+                .with_dimension(dimension);
+            if let Some(api_key) = &config.tei_api_key {
+                client = client.with_api_key(api_key);
+            }
+            let client = match &config.embedding_model {
+                Some(model) => client.with_model(model),
+                None => client.with_served_model().await.context(
+                    "Failed to read the model ID from the TEI server (set --embedding-model to skip)",
+                )?,
+            };
+            Arc::new(client)
+        }
+        #[cfg(feature = "local-embeddings")]
+        EmbeddingProviderArg::Local => {
+            use ceres_client::local::{LocalEmbedder, DEFAULT_LOCAL_MODEL};
+            let model = config
+                .embedding_model
+                .as_deref()
+                .unwrap_or(DEFAULT_LOCAL_MODEL);
+            Arc::new(LocalEmbedder::new(model).with_dimension(dimension))
+        }
+        #[cfg(not(feature = "local-embeddings"))]
+        EmbeddingProviderArg::Local => anyhow::bail!(
+            "The local embedding provider is not compiled in. Reinstall with: cargo install ceres-search --features local-embeddings"
+        ),
+    };
+    info!(
+        "Embedding provider: {:?} ({}, {} dimensions)",
+        config.embedding_provider,
+        provider.model_id(),
+        provider.dimension()
+    );
+
+    let limits = RateLimits {
+        requests_per_minute: config.embedding_rpm,
+        tokens_per_minute: config.embedding_tpm,
+    };
+    if limits.is_unlimited() {
+        return Ok(provider);
+    }
+    info!(
+        "Embedding rate limit: {} requests/min, {} tokens/min",
+        limits
+            .requests_per_minute
+            .map_or("unlimited".to_string(), |n| n.to_string()),
+        limits
+            .tokens_per_minute
+            .map_or("unlimited".to_string(), |n| n.to_string())
+    );
+    Ok(Arc::new(RateLimitedProvider::new(provider, limits)))
+}
+
+/// Creates the query expander selected by `search --expand`.
+fn build_expander(
+    source: ExpandArg,
+    synonyms: Option<&Path>,
+    gemini_api_key: Option<&str>,
+    http_config: &HttpConfig,
+) -> anyhow::Result<Box<dyn QueryExpander>> {
+    let expander: Box<dyn QueryExpander> = match source {
+        ExpandArg::Gemini => {
+            let api_key = gemini_api_key
+                .ok_or_else(|| anyhow::anyhow!("GEMINI_API_KEY is required for --expand gemini"))?;
+            Box::new(
+                GeminiExpander::new(api_key, http_config)
+                    .context("Failed to initialize query expander")?,
+            )
+        }
+        ExpandArg::Synonyms => {
+            let table = match synonyms {
+                Some(path) => SynonymTable::builtin_with_file(path)?,
+                None => SynonymTable::builtin(),
+            };
+            Box::new(SynonymExpander::new(table))
+        }
+    };
+    Ok(expander)
+}
+
+/// Creates the reranker selected by `--rerank-provider`.
+async fn build_reranker(
+    config: &Config,
+    http_config: &HttpConfig,
+) -> anyhow::Result<Arc<dyn Reranker>> {
+    let reranker: Arc<dyn Reranker> = match config.rerank_provider {
+        RerankProviderArg::Gemini => {
+            let api_key = config.gemini_api_key.as_deref().ok_or_else(|| {
+                anyhow::anyhow!("GEMINI_API_KEY is required for the gemini reranker")
+            })?;
+            let reranker = GeminiReranker::new(api_key, http_config)
+                .context("Failed to initialize reranker")?;
+            match &config.rerank_model {
+                Some(model) => Arc::new(reranker.with_model(model)),
+                None => Arc::new(reranker),
+            }
+        }
+        RerankProviderArg::Cohere => {
+            let api_key = config.cohere_api_key.as_deref().ok_or_else(|| {
+                anyhow::anyhow!("COHERE_API_KEY is required for the cohere reranker")
+            })?;
+            let reranker = CohereReranker::new(api_key, http_config)
+                .context("Failed to initialize reranker")?;
+            match &config.rerank_model {
+                Some(model) => Arc::new(reranker.with_model(model)),
+                None => Arc::new(reranker),
+            }
+        }
+        RerankProviderArg::Tei => {
+            let url = config
+                .rerank_url
+                .as_deref()
+                .ok_or_else(|| anyhow::anyhow!("RERANK_URL is required for the tei reranker"))?;
+            let mut client =
+                TeiClient::new(url, http_config).context("Failed to initialize reranker")?;
+            if let Some(api_key) = &config.tei_api_key {
+                client = client.with_api_key(api_key);
+            }
+            let client = match &config.rerank_model {
+                Some(model) => client.with_model(model),
+                None => client.with_served_model().await.context(
+                    "Failed to read the model ID from the TEI server (set --rerank-model to skip)",
+                )?,
+            };
+            Arc::new(client)
+        }
+    };
+    info!(
+        "Reranker: {:?} ({})",
+        config.rerank_provider,
+        reranker.model_id()
+    );
+    Ok(reranker)
+}
+
 /// Handle the harvest command with its three modes:
 /// 1. Direct URL (backward compatible)
 /// 2. Named portal from config
-/// 3. Batch mode (all enabled portals)
+/// 3. Batch mode (all enabled portals, or those named with `--only`)
+///
+/// Batch mode skips quarantined portals unless `include_quarantined` is set.
+/// Webhooks from the configuration file are notified when the harvest
+/// finishes. In direct URL mode the configuration is only read when
+/// `--config` is given. With `outcome_log`, every processed dataset is
+/// appended to the log. `chunks` enables chunk embeddings for every portal,
+/// `sample` limits each portal to a subset of its datasets, and
+/// `retry_failed` to those that failed in earlier harvests.
+#[allow(clippy::too_many_arguments)]
 async fn handle_harvest(
-    repo: &DatasetRepository,
-    gemini_client: &GeminiClient,
+    repo: &dyn DatasetStore,
+    embedder: &Arc<dyn EmbeddingProvider>,
+    settings: &Settings,
     portal_url: Option<String>,
     portal_name: Option<String>,
+    only: &[String],
+    include_quarantined: bool,
     config_path: Option<PathBuf>,
+    outcome_log: Option<&OutcomeLog>,
+    chunks: bool,
+    sample: Option<HarvestSample>,
+    retry_failed: bool,
+    json: bool,
 ) -> anyhow::Result<()> {
+    let started_at = Utc::now();
     match (portal_url, portal_name) {
         // Mode 1: Direct URL (backward compatible)
         (Some(url), None) => {
-            let stats = sync_portal(repo, gemini_client, &url).await?;
-            print_single_portal_summary(&url, &stats);
+            let notifiers = match config_path {
+                Some(path) => load_portals_config(Some(path))?
+                    .map(|c| Notifiers::from_config(&c))
+                    .unwrap_or_default(),
+                None => Notifiers::default(),
+            };
+            let result = harvest_single(
+                repo,
+                embedder,
+                settings,
+                &url,
+                &url,
+                None,
+                chunks,
+                None,
+                &notifiers,
+                outcome_log,
+                sample,
+                retry_failed,
+            )
+            .await;
+            finish_single(&url, &url, result, started_at, json)?;
         }
 
         // Mode 2: Named portal from config
@@ -142,8 +1547,23 @@ async fn handle_harvest(
                 );
             }
 
-            let stats = sync_portal(repo, gemini_client, &portal.url).await?;
-            print_single_portal_summary(&portal.url, &stats);
+            let filter = HarvestFilter::for_portal(portal)?;
+            let result = harvest_single(
+                repo,
+                embedder,
+                settings,
+                &portal.name,
+                &portal.url,
+                portal.embedding_model.as_deref(),
+                chunks || portal.chunk_embeddings,
+                filter.as_ref(),
+                &Notifiers::from_config(&portals_config),
+                outcome_log,
+                sample,
+                retry_failed,
+            )
+            .await;
+            finish_single(&portal.name, &portal.url, result, started_at, json)?;
         }
 
         // Mode 3: Batch mode (all enabled portals)
@@ -153,15 +1573,54 @@ async fn handle_harvest(
                     "No configuration file found. Create ~/.config/ceres/portals.toml or use --config"
                 ))?;
 
-            let enabled: Vec<&PortalEntry> = portals_config.enabled_portals();
+            let health: HashMap<String, PortalHealth> = repo
+                .list_portal_health()
+                .await?
+                .into_iter()
+                .map(|h| (h.portal_url.clone(), h))
+                .collect();
+            let selection = select_portals(
+                &portals_config,
+                only,
+                include_quarantined,
+                &health,
+                Utc::now(),
+            )?;
+
+            for (portal, reason) in &selection.skipped {
+                if let SkipReason::Quarantined(until) = reason {
+                    info!(
+                        "Skipping quarantined portal '{}' (until {}; use --include-quarantined or `ceres portals unquarantine {}`)",
+                        portal.name, until, portal.name
+                    );
+                }
+            }
 
-            if enabled.is_empty() {
-                info!("No enabled portals found in configuration.");
+            if selection.selected.is_empty() {
+                info!("No portals selected for harvesting.");
                 info!("Add portals to ~/.config/ceres/portals.toml or use: ceres harvest <url>");
-                return Ok(());
+                return print_harvest_end(BatchHarvestSummary::new(), started_at, json);
             }
 
-            batch_harvest(repo, gemini_client, &enabled).await;
+            let summary = batch_harvest(
+                repo,
+                embedder,
+                settings,
+                &selection.selected,
+                outcome_log,
+                chunks,
+                sample,
+                retry_failed,
+            )
+            .await;
+            exit_status::report(Outcome::of_harvest(&summary));
+            print_harvest_end(summary.clone(), started_at, json)?;
+            notify(
+                &Notifiers::from_config(&portals_config),
+                &settings.http,
+                summary,
+            )
+            .await;
         }
 
         // This case is prevented by clap's conflicts_with
@@ -171,34 +1630,325 @@ async fn handle_harvest(
     Ok(())
 }
 
-/// Harvest multiple portals sequentially with error isolation.
-///
-/// Failure in one portal does not stop processing of others.
-async fn batch_harvest(
-    repo: &DatasetRepository,
-    gemini_client: &GeminiClient,
-    portals: &[&PortalEntry],
-) -> BatchHarvestSummary {
+/// Harvest a single portal (modes 1 and 2), notify webhooks and sinks, and
+/// propagate failure.
+#[allow(clippy::too_many_arguments)]
+async fn harvest_single(
+    repo: &dyn DatasetStore,
+    embedder: &Arc<dyn EmbeddingProvider>,
+    settings: &Settings,
+    name: &str,
+    url: &str,
+    embedding_model: Option<&str>,
+    chunks: bool,
+    filter: Option<&HarvestFilter>,
+    notifiers: &Notifiers,
+    outcome_log: Option<&OutcomeLog>,
+    sample: Option<HarvestSample>,
+    retry_failed: bool,
+) -> anyhow::Result<SyncStats> {
+    let result = sync_portal(
+        repo,
+        embedder,
+        settings,
+        url,
+        embedding_model,
+        chunks,
+        filter,
+        outcome_log,
+        sample,
+        retry_failed,
+    )
+    .await;
+    // Skipping for a running harvest says nothing about the portal's health
+    if result.as_ref().is_err_and(is_portal_locked) {
+        return result;
+    }
+    // Nor does an interrupted harvest, which neither succeeded nor failed
+    let quarantined_until = if result.as_ref().is_ok_and(|stats| stats.interrupted) {
+        None
+    } else {
+        record_portal_health(
+            repo,
+            name,
+            url,
+            result.as_ref().err().map(|e| e.to_string()).as_deref(),
+        )
+        .await
+    };
+
     let mut summary = BatchHarvestSummary::new();
-    let total = portals.len();
+    match &result {
+        Ok(stats) => {
+            print_single_portal_summary(url, stats);
+            summary.add(PortalHarvestResult::success(
+                name.to_string(),
+                url.to_string(),
+                stats.clone(),
+            ));
+        }
+        Err(e) => {
+            let mut failure =
+                PortalHarvestResult::failure(name.to_string(), url.to_string(), e.to_string());
+            failure.quarantined_until = quarantined_until;
+            summary.add(failure);
+        }
+    }
+    notify(notifiers, &settings.http, summary).await;
 
-    info!("═══════════════════════════════════════════════════════");
-    info!("Starting batch harvest of {} portals", total);
-    info!("═══════════════════════════════════════════════════════");
+    result
+}
 
-    for (i, portal) in portals.iter().enumerate() {
-        info!("");
-        info!("───────────────────────────────────────────────────────");
-        info!(
-            "[Portal {}/{}] {} ({})",
-            i + 1,
-            total,
-            portal.name,
-            portal.url
-        );
+/// Returns true if `error` is a harvest skipped because another harvest of
+/// its portal is running.
+fn is_portal_locked(error: &anyhow::Error) -> bool {
+    matches!(
+        error.downcast_ref::<AppError>(),
+        Some(AppError::PortalLocked(_))
+    )
+}
+
+/// Ends a single-portal harvest (modes 1 and 2): sets the exit status and
+/// prints how it went, then propagates its failure.
+///
+/// A harvest skipped for another harvest of the portal, as `--on-locked
+/// skip` asks, does not fail and prints an empty summary.
+fn finish_single(
+    name: &str,
+    url: &str,
+    result: anyhow::Result<SyncStats>,
+    started_at: DateTime<Utc>,
+    json: bool,
+) -> anyhow::Result<()> {
+    let mut summary = BatchHarvestSummary::new();
+    match result {
+        Ok(stats) => {
+            exit_status::report(Outcome::of_sync(&stats));
+            summary.add(PortalHarvestResult::success(
+                name.to_string(),
+                url.to_string(),
+                stats,
+            ));
+            print_harvest_end(summary, started_at, json)
+        }
+        Err(e) if is_portal_locked(&e) => {
+            warn!("{}, skipped (use --on-locked wait to wait for it)", e);
+            print_harvest_end(summary, started_at, json)
+        }
+        Err(e) => {
+            // With --json, errors stay on stderr as before
+            if !json {
+                summary.add(PortalHarvestResult::failure(
+                    name.to_string(),
+                    url.to_string(),
+                    e.to_string(),
+                ));
+                print_harvest_end(summary, started_at, json)?;
+            }
+            Err(e)
+        }
+    }
+}
+
+/// Prints how a harvest went on stdout: with `--json` the payload webhooks
+/// receive, otherwise a one-line [`HarvestReport`] after the logs on
+/// stderr, for scripts that capture results without parsing the logs.
+fn print_harvest_end(
+    summary: BatchHarvestSummary,
+    started_at: DateTime<Utc>,
+    json: bool,
+) -> anyhow::Result<()> {
+    if json {
+        return print_json(&HarvestNotification::new(summary, Utc::now()));
+    }
+    let report = HarvestReport::new(summary, started_at, Utc::now());
+    println!("{}", serde_json::to_string(&report)?);
+    Ok(())
+}
+
+fn print_json<T: Serialize>(value: &T) -> anyhow::Result<()> {
+    println!("{}", serde_json::to_string_pretty(value)?);
+    Ok(())
+}
+
+/// Update a portal's health after a harvest; `error` is `None` on success.
+/// Returns the end of the quarantine if this failure quarantined the
+/// portal.
+///
+/// Health tracking is best-effort: storage errors are logged, never propagated.
+async fn record_portal_health(
+    repo: &dyn DatasetStore,
+    name: &str,
+    url: &str,
+    error: Option<&str>,
+) -> Option<DateTime<Utc>> {
+    let now = Utc::now();
+    let mut health = match repo.get_portal_health(url).await {
+        Ok(health) => health,
+        Err(e) => {
+            error!("Failed to load health of portal '{}': {}", name, e);
+            return None;
+        }
+    };
+
+    let mut quarantined_until = None;
+    match error {
+        None => health.record_success(now),
+        Some(error) => {
+            if health.record_failure(now, error, &QuarantinePolicy::default()) {
+                quarantined_until = Some(health.quarantined_until.unwrap_or(now));
+                error!(
+                    "Portal '{}' failed {} times in a row and is quarantined until {}",
+                    name,
+                    health.consecutive_failures,
+                    health.quarantined_until.unwrap_or(now)
+                );
+            }
+        }
+    }
+
+    if let Err(e) = repo.save_portal_health(&health).await {
+        error!("Failed to save health of portal '{}': {}", name, e);
+    }
+    quarantined_until
+}
+
+/// Webhooks and notification sinks of portals.toml, told about finished
+/// harvests.
+#[derive(Debug, Clone, Default)]
+struct Notifiers {
+    webhooks: Vec<WebhookConfig>,
+    sinks: Vec<NotificationConfig>,
+}
+
+impl Notifiers {
+    fn from_config(config: &PortalsConfig) -> Self {
+        Self {
+            webhooks: config.webhooks.clone(),
+            sinks: config.notifications.clone(),
+        }
+    }
+}
+
+/// Deliver a harvest notification to every subscribed webhook and
+/// notification sink.
+///
+/// Delivery failures are logged and never fail the harvest itself.
+async fn notify(notifiers: &Notifiers, http_config: &HttpConfig, summary: BatchHarvestSummary) {
+    let notification = HarvestNotification::new(summary, Utc::now());
+    let webhooks: Vec<&WebhookConfig> = notifiers
+        .webhooks
+        .iter()
+        .filter(|w| notification.triggers(&w.events, None))
+        .collect();
+    let sinks: Vec<&NotificationConfig> = notifiers
+        .sinks
+        .iter()
+        .filter(|s| notification.triggers(&s.events, s.failure_rate_above))
+        .collect();
+    if webhooks.is_empty() && sinks.is_empty() {
+        return;
+    }
+
+    let client = match WebhookClient::new(http_config) {
+        Ok(client) => client,
+        Err(e) => {
+            error!("Cannot send webhook notifications: {}", e);
+            return;
+        }
+    };
+
+    for webhook in webhooks {
+        match client.post(&webhook.url, &notification).await {
+            Ok(()) => info!("Notified webhook {}", webhook.url),
+            Err(e) => error!("Webhook {} failed: {}", webhook.url, e),
+        }
+    }
+    for sink in sinks {
+        let target = sink.sink.describe();
+        match send_notification(&client, &sink.sink, &notification).await {
+            Ok(()) => info!("Sent notification to {}", target),
+            Err(e) => error!("Notification to {} failed: {}", target, e),
+        }
+    }
+}
+
+/// Sends the readable message of `notification` to a sink.
+async fn send_notification(
+    client: &WebhookClient,
+    sink: &NotificationSink,
+    notification: &HarvestNotification,
+) -> Result<(), AppError> {
+    match sink {
+        NotificationSink::Slack { webhook_url } => {
+            let message = serde_json::json!({ "text": notification.message() });
+            client.post(webhook_url, &message).await
+        }
+        #[cfg(feature = "email")]
+        NotificationSink::Email(email) => {
+            ceres_client::EmailClient::new(email)?
+                .send(&notification.text, &notification.message())
+                .await
+        }
+        #[cfg(not(feature = "email"))]
+        NotificationSink::Email(_) => Err(AppError::ConfigError(
+            "Email notifications are not compiled in. Reinstall with: cargo install ceres-search --features email"
+                .to_string(),
+        )),
+    }
+}
+
+/// Harvest multiple portals sequentially with error isolation.
+///
+/// Failure in one portal does not stop processing of others. `chunks`
+/// enables chunk embeddings for every portal, and `sample` limits each
+/// portal to a subset of its datasets.
+#[allow(clippy::too_many_arguments)]
+async fn batch_harvest(
+    repo: &dyn DatasetStore,
+    embedder: &Arc<dyn EmbeddingProvider>,
+    settings: &Settings,
+    portals: &[&PortalEntry],
+    outcome_log: Option<&OutcomeLog>,
+    chunks: bool,
+    sample: Option<HarvestSample>,
+    retry_failed: bool,
+) -> BatchHarvestSummary {
+    let mut summary = BatchHarvestSummary::new();
+    let total = portals.len();
+
+    info!("═══════════════════════════════════════════════════════");
+    info!("Starting batch harvest of {} portals", total);
+    info!("═══════════════════════════════════════════════════════");
+
+    for (i, portal) in portals.iter().enumerate() {
+        info!("");
+        info!("───────────────────────────────────────────────────────");
+        info!(
+            "[Portal {}/{}] {} ({})",
+            i + 1,
+            total,
+            portal.name,
+            portal.url
+        );
         info!("───────────────────────────────────────────────────────");
 
-        match sync_portal(repo, gemini_client, &portal.url).await {
+        // Filters were validated when the configuration was loaded
+        let filter = HarvestFilter::for_portal(portal).ok().flatten();
+        match sync_portal(
+            repo,
+            embedder,
+            settings,
+            &portal.url,
+            portal.embedding_model.as_deref(),
+            chunks || portal.chunk_embeddings,
+            filter.as_ref(),
+            outcome_log,
+            sample,
+            retry_failed,
+        )
+        .await
+        {
             Ok(stats) => {
                 info!(
                     "[Portal {}/{}] Completed: {} datasets ({} created, {} updated, {} unchanged)",
@@ -209,21 +1959,46 @@ async fn batch_harvest(
                     stats.updated,
                     stats.unchanged
                 );
+                if !stats.interrupted {
+                    record_portal_health(repo, &portal.name, &portal.url, None).await;
+                }
                 summary.add(PortalHarvestResult::success(
                     portal.name.clone(),
                     portal.url.clone(),
                     stats,
                 ));
             }
+            Err(e) if is_portal_locked(&e) => {
+                warn!("[Portal {}/{}] Skipped: {}", i + 1, total, e);
+                summary.add(PortalHarvestResult::skipped(
+                    portal.name.clone(),
+                    portal.url.clone(),
+                    e.to_string(),
+                ));
+            }
             Err(e) => {
                 error!("[Portal {}/{}] Failed: {}", i + 1, total, e);
-                summary.add(PortalHarvestResult::failure(
+                let quarantined_until =
+                    record_portal_health(repo, &portal.name, &portal.url, Some(&e.to_string()))
+                        .await;
+                let mut failure = PortalHarvestResult::failure(
                     portal.name.clone(),
                     portal.url.clone(),
                     e.to_string(),
-                ));
+                );
+                failure.quarantined_until = quarantined_until;
+                summary.add(failure);
             }
         }
+
+        if shutdown::requested() && i + 1 < total {
+            warn!(
+                "Shutdown requested, leaving {} portals unharvested",
+                total - i - 1
+            );
+            exit_status::report(Outcome::Interrupted);
+            break;
+        }
     }
 
     // Print batch summary
@@ -234,44 +2009,327 @@ async fn batch_harvest(
 
 /// Print a summary of batch harvesting results.
 fn print_batch_summary(summary: &BatchHarvestSummary) {
-    info!("");
-    info!("═══════════════════════════════════════════════════════");
-    info!("BATCH HARVEST COMPLETE");
-    info!("═══════════════════════════════════════════════════════");
-    info!("  Portals processed:   {}", summary.total_portals());
-    info!("  Successful:          {}", summary.successful_count());
-    info!("  Failed:              {}", summary.failed_count());
-    info!("  Total datasets:      {}", summary.total_datasets());
+    info!(target: telemetry::SUMMARY, "");
+    info!(target: telemetry::SUMMARY, "═══════════════════════════════════════════════════════");
+    if shutdown::requested() {
+        info!(target: telemetry::SUMMARY, "BATCH HARVEST INTERRUPTED");
+    } else {
+        info!(target: telemetry::SUMMARY, "BATCH HARVEST COMPLETE");
+    }
+    info!(target: telemetry::SUMMARY, "═══════════════════════════════════════════════════════");
+    info!(target: telemetry::SUMMARY, "  Portals processed:   {}", summary.total_portals());
+    info!(target: telemetry::SUMMARY, "  Successful:          {}", summary.successful_count());
+    info!(target: telemetry::SUMMARY, "  Failed:              {}", summary.failed_count());
+    if summary.skipped_count() > 0 {
+        info!(target: telemetry::SUMMARY, "  Skipped (locked):    {}", summary.skipped_count());
+    }
+    info!(target: telemetry::SUMMARY, "  Total datasets:      {}", summary.total_datasets());
+    let failed_by_kind = summary.failed_by_kind().nonzero();
+    if !failed_by_kind.is_empty() {
+        let kinds: Vec<String> = failed_by_kind
+            .iter()
+            .map(|(kind, count)| format!("{} {}", count, kind.as_str()))
+            .collect();
+        let failed: usize = failed_by_kind.iter().map(|(_, count)| count).sum();
+        info!(target: telemetry::SUMMARY, "  Failed datasets:     {} ({})", failed, kinds.join(", "));
+    }
+    let (hits, misses) = summary.results.iter().fold((0, 0), |(hits, misses), r| {
+        (hits + r.stats.cache_hits, misses + r.stats.cache_misses)
+    });
+    if hits + misses > 0 {
+        info!(target: telemetry::SUMMARY, "  Embedding cache:     {} hits, {} misses", hits, misses);
+    }
 
     if summary.failed_count() > 0 {
-        info!("───────────────────────────────────────────────────────");
-        info!("Failed portals:");
-        for result in summary.results.iter().filter(|r| !r.is_success()) {
+        info!(target: telemetry::SUMMARY, "───────────────────────────────────────────────────────");
+        info!(target: telemetry::SUMMARY, "Failed portals:");
+        for result in summary.results.iter().filter(|r| r.is_failure()) {
             if let Some(err) = &result.error {
                 error!("  - {}: {}", result.portal_name, err);
             }
         }
     }
-    info!("═══════════════════════════════════════════════════════");
+    info!(target: telemetry::SUMMARY, "═══════════════════════════════════════════════════════");
 }
 
 /// Print a summary for single portal harvest (modes 1 and 2).
 fn print_single_portal_summary(portal_url: &str, stats: &SyncStats) {
-    info!("");
-    info!("═══════════════════════════════════════════════════════");
-    info!("Sync complete: {}", portal_url);
+    info!(target: telemetry::SUMMARY, "");
+    info!(target: telemetry::SUMMARY, "═══════════════════════════════════════════════════════");
+    if stats.interrupted {
+        info!(target: telemetry::SUMMARY, "Sync interrupted: {}", portal_url);
+    } else {
+        info!(target: telemetry::SUMMARY, "Sync complete: {}", portal_url);
+    }
+    info!(target: telemetry::SUMMARY, "═══════════════════════════════════════════════════════");
+    info!(target: telemetry::SUMMARY, "  = Unchanged:         {}", stats.unchanged);
+    info!(target: telemetry::SUMMARY, "  ↑ Updated:           {}", stats.updated);
+    info!(target: telemetry::SUMMARY, "  + Created:           {}", stats.created);
+    info!(target: telemetry::SUMMARY, "  ✗ Failed:            {}", stats.failed);
+    for (kind, count) in stats.failed_by_kind.nonzero() {
+        info!(target: telemetry::SUMMARY, "      {:<17}{}", kind.as_str(), count);
+    }
+    info!(target: telemetry::SUMMARY, "───────────────────────────────────────────────────────");
+    info!(target: telemetry::SUMMARY, "  Total processed:     {}", stats.total());
+    info!(target: telemetry::SUMMARY, "  Successful:          {}", stats.successful());
+    if let Some(rate) = stats.cache_hit_rate() {
+        info!(target: telemetry::SUMMARY,
+            "  Embedding cache:     {} hits, {} misses ({:.0}% reused)",
+            stats.cache_hits,
+            stats.cache_misses,
+            rate * 100.0
+        );
+    }
+    info!(target: telemetry::SUMMARY, "═══════════════════════════════════════════════════════");
+
+    if stats.interrupted {
+        info!(target: telemetry::SUMMARY, "Interrupted: the next harvest picks up the remaining datasets.");
+    } else if stats.failed == 0 {
+        info!(target: telemetry::SUMMARY, "All datasets processed successfully!");
+    }
+}
+
+/// A portal registered with the daemon scheduler.
+struct ScheduledPortal {
+    portal: PortalEntry,
+    schedule: CronSchedule,
+    /// Cron occurrence the next run belongs to (before jitter).
+    scheduled_for: DateTime<Utc>,
+    /// Actual start time of the next run (after jitter).
+    run_at: DateTime<Utc>,
+}
+
+impl ScheduledPortal {
+    fn new(portal: PortalEntry, schedule: CronSchedule, max_jitter: Duration) -> Option<Self> {
+        let mut scheduled = Self {
+            portal,
+            schedule,
+            scheduled_for: Utc::now(),
+            run_at: Utc::now(),
+        };
+        scheduled
+            .advance(Utc::now(), max_jitter)
+            .then_some(scheduled)
+    }
+
+    /// Moves to the first occurrence after `now`. Returns false if the schedule never fires again.
+    fn advance(&mut self, now: DateTime<Utc>, max_jitter: Duration) -> bool {
+        let Some(next) = self.schedule.next_after(now) else {
+            return false;
+        };
+        let jitter = jitter_for(&self.portal.name, next, max_jitter);
+        self.scheduled_for = next;
+        self.run_at = next + chrono::Duration::from_std(jitter).unwrap_or_default();
+        true
+    }
+}
+
+/// Run the scheduler loop until Ctrl-C or SIGTERM, then let running
+/// harvests wind down (see [`shutdown`]).
+///
+/// Each enabled portal with a schedule (or the default schedule) is harvested
+/// when its cron expression fires, delayed by a per-run jitter. A portal whose
+/// previous run is still in progress, or which is quarantined, skips the
+/// occurrence rather than overlapping.
+async fn run_daemon(
+    repo: &DatasetRepository,
+    store: Arc<dyn DatasetStore>,
+    embedder: &Arc<dyn EmbeddingProvider>,
+    settings: &Settings,
+    config_path: Option<PathBuf>,
+    default_schedule: Option<String>,
+    max_jitter: Duration,
+) -> anyhow::Result<()> {
+    let portals_config = load_portals_config(config_path)?.ok_or_else(|| {
+        anyhow::anyhow!(
+            "No configuration file found. Create ~/.config/ceres/portals.toml or use --config"
+        )
+    })?;
+
+    let mut jobs = Vec::new();
+    for portal in portals_config.enabled_portals() {
+        let schedule = match portal.cron_schedule()? {
+            Some(schedule) => schedule,
+            None => match default_schedule.as_deref() {
+                Some(expression) => {
+                    CronSchedule::parse(expression).context("Invalid --default-schedule")?
+                }
+                None => {
+                    info!("Portal '{}' has no schedule, skipping", portal.name);
+                    continue;
+                }
+            },
+        };
+        match ScheduledPortal::new(portal.clone(), schedule, max_jitter) {
+            Some(job) => jobs.push(job),
+            None => info!("Schedule for '{}' never fires, skipping", portal.name),
+        }
+    }
+
+    if jobs.is_empty() {
+        anyhow::bail!(
+            "No scheduled portals. Add `schedule = \"0 3 * * *\"` to portal entries or use --default-schedule"
+        );
+    }
+
     info!("═══════════════════════════════════════════════════════");
-    info!("  = Unchanged:         {}", stats.unchanged);
-    info!("  ↑ Updated:           {}", stats.updated);
-    info!("  + Created:           {}", stats.created);
-    info!("  ✗ Failed:            {}", stats.failed);
-    info!("───────────────────────────────────────────────────────");
-    info!("  Total processed:     {}", stats.total());
-    info!("  Successful:          {}", stats.successful());
+    info!("Ceres daemon started with {} scheduled portals", jobs.len());
+    for job in &jobs {
+        info!(
+            "  {} [{}] next run at {}",
+            job.portal.name,
+            job.schedule.expression(),
+            job.run_at
+        );
+    }
     info!("═══════════════════════════════════════════════════════");
 
-    if stats.failed == 0 {
-        info!("All datasets processed successfully!");
+    // Portal harvested by each running task, so a panicked task frees its
+    // portal too
+    let mut running: HashMap<tokio::task::Id, String> = HashMap::new();
+    let mut tasks: JoinSet<(String, anyhow::Result<SyncStats>)> = JoinSet::new();
+
+    loop {
+        let next_run_at = jobs.iter().map(|j| j.run_at).min().unwrap_or_else(Utc::now);
+        let wait = (next_run_at - Utc::now())
+            .to_std()
+            .unwrap_or(Duration::ZERO);
+
+        tokio::select! {
+            // With no schedules left, only running harvests are waited for
+            _ = tokio::time::sleep(wait), if !jobs.is_empty() => {
+                let now = Utc::now();
+                for job in jobs.iter_mut().filter(|j| j.run_at <= now) {
+                    let quarantined = match repo.get_portal_health(&job.portal.url).await {
+                        Ok(health) => health.is_quarantined(now).then_some(health.quarantined_until).flatten(),
+                        Err(e) => {
+                            error!("[daemon] Failed to load health of {}: {}", job.portal.name, e);
+                            None
+                        }
+                    };
+
+                    if running.values().any(|name| *name == job.portal.name) {
+                        info!(
+                            "[daemon] {} is still running, skipping run scheduled for {}",
+                            job.portal.name, job.scheduled_for
+                        );
+                    } else if let Some(until) = quarantined {
+                        info!(
+                            "[daemon] {} is quarantined until {}, skipping run scheduled for {}",
+                            job.portal.name, until, job.scheduled_for
+                        );
+                    } else {
+                        info!("[daemon] Starting scheduled harvest of {}", job.portal.name);
+
+                        let repo = repo.clone();
+                        let store = store.clone();
+                        let embedder = embedder.clone();
+                        let portal = job.portal.clone();
+                        let notifiers = Notifiers::from_config(&portals_config);
+                        let settings = settings.clone();
+                        let task = tasks.spawn(async move {
+                            // Validated when the configuration was loaded
+                            let filter = HarvestFilter::for_portal(&portal).ok().flatten();
+                            let result = harvest_single(
+                                store.as_ref(),
+                                &embedder,
+                                &settings,
+                                &portal.name,
+                                &portal.url,
+                                portal.embedding_model.as_deref(),
+                                portal.chunk_embeddings,
+                                filter.as_ref(),
+                                &notifiers,
+                                None,
+                                None,
+                                false,
+                            )
+                            .await;
+                            deliver_watch_matches(&repo, &settings.http).await;
+                            (portal.name, result)
+                        });
+                        running.insert(task.id(), job.portal.name.clone());
+                    }
+
+                    if !job.advance(now, max_jitter) {
+                        info!("[daemon] Schedule for {} has no further runs", job.portal.name);
+                    } else {
+                        info!("[daemon] Next run of {} at {}", job.portal.name, job.run_at);
+                    }
+                }
+                // Drop portals whose schedules are exhausted
+                jobs.retain(|j| j.run_at > now);
+                if jobs.is_empty() && running.is_empty() {
+                    info!("[daemon] No further scheduled runs, exiting");
+                    break;
+                }
+            }
+            Some(joined) = tasks.join_next_with_id(), if !tasks.is_empty() => {
+                log_daemon_result(joined, &mut running);
+                if jobs.is_empty() && running.is_empty() {
+                    info!("[daemon] No further scheduled runs, exiting");
+                    break;
+                }
+            }
+            _ = shutdown::wait() => {
+                info!("[daemon] Shutdown requested");
+                break;
+            }
+        }
+    }
+
+    if !tasks.is_empty() {
+        info!(
+            "[daemon] Waiting for {} running harvests to finish...",
+            tasks.len()
+        );
+    }
+    while let Some(joined) = tasks.join_next_with_id().await {
+        log_daemon_result(joined, &mut running);
+    }
+
+    Ok(())
+}
+
+/// Log the outcome of a daemon harvest task and mark the portal as idle,
+/// even if the task panicked.
+fn log_daemon_result(
+    joined: Result<(tokio::task::Id, (String, anyhow::Result<SyncStats>)), tokio::task::JoinError>,
+    running: &mut HashMap<tokio::task::Id, String>,
+) {
+    let id = match &joined {
+        Ok((id, _)) => *id,
+        Err(e) => e.id(),
+    };
+    let portal = running.remove(&id);
+    match joined.map(|(_, result)| result) {
+        Ok((name, Ok(stats))) => {
+            info!(
+                "[daemon] {} {}: {} datasets ({} created, {} updated, {} unchanged, {} failed)",
+                name,
+                if stats.interrupted {
+                    "interrupted"
+                } else {
+                    "completed"
+                },
+                stats.total(),
+                stats.created,
+                stats.updated,
+                stats.unchanged,
+                stats.failed
+            );
+        }
+        Ok((name, Err(e))) if is_portal_locked(&e) => {
+            info!("[daemon] {} skipped: {}", name, e);
+        }
+        Ok((name, Err(e))) => {
+            error!("[daemon] {} failed: {}", name, e);
+        }
+        Err(e) => error!(
+            "[daemon] Harvest of {} panicked: {}",
+            portal.as_deref().unwrap_or("unknown portal"),
+            e
+        ),
     }
 }
 
@@ -281,76 +2339,334 @@ fn print_single_portal_summary(portal_url: &str, stats: &SyncStats) {
 // to only fetch recently modified datasets.
 // See: https://github.com/AndreaBozzo/Ceres/issues/10
 
-// TODO(robustness): Add circuit breaker pattern for API failures
-// Currently no backpressure when Gemini/CKAN APIs fail repeatedly.
-// Consider: (1) Stop after N consecutive failures
-// (2) Exponential backoff on rate limits
-// (3) Health check before continuing after failure spike
+/// Fetched datasets checked per [`DatasetStore::datasets_needing_processing`]
+/// call during a harvest.
+const DELTA_BATCH_SIZE: usize = 100;
+
+/// Datasets stored per [`DatasetStore::upsert_batch`] call during a harvest.
+const UPSERT_BATCH_SIZE: usize = 100;
+
+/// Datasets waiting between two stages of a harvest.
+const STAGE_CAPACITY: usize = 100;
+
+/// Datasets of a portal's listing finished between two saves of its
+/// harvest checkpoint.
+const CHECKPOINT_INTERVAL: usize = 100;
 
-// TODO(performance): Batch embedding API calls
-// Each dataset embedding is generated individually. Gemini API may support
-// batching multiple texts per request, reducing latency and API calls.
+// TODO(performance): Batch embedding cache misses
+// Providers implement `EmbeddingProvider::embed_batch`, but the embed stage
+// still sends one request per dataset missing from the embedding cache.
+// Grouping the misses of a chunk into one call would cut round trips further.
 
 /// Sync a single portal and return statistics.
 ///
 /// This is the core harvesting function used by all harvest modes.
 /// It fetches datasets from the portal, compares with existing data,
 /// generates embeddings for new/updated content, and persists changes.
+/// With `chunks`, resource and description chunks of every dataset are
+/// embedded too, whenever they changed since the last harvest.
+/// Each dataset's outcome and stage timings are appended to `outcome_log`.
+/// Datasets failing the portal's `filter` are skipped; its tag and
+/// organization filters narrow the listing when the portal supports it.
+/// Failed datasets are recorded in the dead-letter table, and with
+/// `retry_failed` only those are harvested; a harvest covering every
+/// dataset clears the failures it got past.
+///
+/// The portal is locked for the harvest, so harvests of it started
+/// elsewhere meanwhile skip it or wait, following `SyncConfig::on_locked`;
+/// a skipped harvest fails with `AppError::PortalLocked`. After a shutdown
+/// request it starts no more datasets and ends with those under way,
+/// marking its stats interrupted (see [`shutdown`]).
+///
+/// The harvest runs as four stages connected by bounded channels: fetching
+/// from the portal; converting, filtering and detecting changes in one
+/// stage; embedding; and storing. A slow database or embedding provider fills the channels
+/// ahead of it and so pauses fetching, keeping memory bounded whatever the
+/// portal's size.
+///
+/// Each dataset gets a span of its own, with spans for its CKAN fetch,
+/// embedding and storage nested inside.
+#[tracing::instrument(skip_all, fields(portal = %portal_url))]
+#[allow(clippy::too_many_arguments)]
 async fn sync_portal(
-    repo: &DatasetRepository,
-    gemini_client: &GeminiClient,
+    repo: &dyn DatasetStore,
+    embedder: &Arc<dyn EmbeddingProvider>,
+    settings: &Settings,
     portal_url: &str,
+    embedding_model: Option<&str>,
+    chunks: bool,
+    filter: Option<&HarvestFilter>,
+    outcome_log: Option<&OutcomeLog>,
+    sample: Option<HarvestSample>,
+    retry_failed: bool,
 ) -> anyhow::Result<SyncStats> {
     info!("Syncing portal: {}", portal_url);
 
-    let ckan = CkanClient::new(portal_url).context("Invalid CKAN portal URL")?;
+    let lock = match repo.try_lock_portal(portal_url).await? {
+        Some(lock) => lock,
+        None => match settings.sync.on_locked {
+            LockPolicy::Skip => return Err(AppError::PortalLocked(portal_url.to_string()).into()),
+            LockPolicy::Wait => {
+                info!(
+                    "Another harvest of {} is running, waiting for it to finish",
+                    portal_url
+                );
+                repo.lock_portal(portal_url).await?
+            }
+        },
+    };
+
+    let embedder = match embedding_model {
+        Some(model) => {
+            info!("Using embedding model override: {}", model);
+            embedder.for_model(model)
+        }
+        None => embedder.clone(),
+    };
+
+    let ckan = CkanClient::new(portal_url, &settings.http, &settings.sync)
+        .context("Invalid CKAN portal URL")?;
 
-    let existing_hashes = repo.get_hashes_for_portal(portal_url).await?;
-    info!("Found {} existing datasets", existing_hashes.len());
+    let chunk_hashes = if chunks {
+        info!("Chunk embeddings enabled");
+        Arc::new(
+            repo.get_chunk_hashes_for_portal(portal_url, embedder.model_id())
+                .instrument(info_span!("db.get_chunk_hashes_for_portal"))
+                .await?,
+        )
+    } else {
+        Arc::new(HashMap::new())
+    };
 
-    let ids = ckan.list_package_ids().await?;
+    // Only a harvest of the whole listing resumes where an interrupted one
+    // stopped
+    let checkpointed = sample.is_none() && !retry_failed;
+    let resumed = if checkpointed {
+        repo.get_harvest_checkpoint(portal_url).await?
+    } else {
+        None
+    };
+    let started_at = resumed
+        .as_ref()
+        .map_or_else(Utc::now, |checkpoint| checkpoint.started_at);
+    let mut filtered_ids = None;
+    if retry_failed {
+        let ids: Vec<String> = repo
+            .list_harvest_failures(Some(portal_url))
+            .await?
+            .into_iter()
+            .map(|failure| failure.dataset_id)
+            .collect();
+        info!(
+            "Retrying {} datasets that failed in earlier harvests",
+            ids.len()
+        );
+        filtered_ids = Some(ids);
+    } else if let Some(fq) = filter.and_then(HarvestFilter::solr_query) {
+        debug!("Listing datasets with fq={}", fq);
+        match ckan
+            .search_dataset_ids(&fq)
+            .instrument(info_span!("ckan.search_dataset_ids"))
+            .await
+        {
+            Ok(ids) => {
+                info!(
+                    "Found {} datasets on portal matching its tag and organization filters",
+                    ids.len()
+                );
+                filtered_ids = Some(ids);
+            }
+            Err(e) => warn!(
+                "Cannot filter the portal's datasets with package_search ({}); listing all of them",
+                e
+            ),
+        }
+    }
+    let ids = match filtered_ids {
+        Some(ids) => ids,
+        None => {
+            let ids = ckan
+                .list_dataset_ids()
+                .instrument(info_span!("ckan.list_dataset_ids"))
+                .await?;
+            info!("Found {} datasets on portal", ids.len());
+            ids
+        }
+    };
+    let ids = match sample {
+        Some(sample) if ids.len() > sample.limit => {
+            let found = ids.len();
+            let ids = sample.apply(ids);
+            match sample.method {
+                SampleMethod::First => {
+                    info!("Sampling the first {} of {} datasets", ids.len(), found)
+                }
+                SampleMethod::Random { seed } => info!(
+                    "Sampling {} of {} datasets at random (--seed {} picks them again)",
+                    ids.len(),
+                    found,
+                    seed
+                ),
+            }
+            ids
+        }
+        _ => ids,
+    };
     let total = ids.len();
-    info!("Found {} datasets on portal", total);
+    let skipped = resumed.map_or(0, |checkpoint| checkpoint.offset.min(total));
+    if skipped > 0 {
+        info!(
+            "Resuming an interrupted harvest after {} of {} datasets",
+            skipped, total
+        );
+    }
+    let (checkpoint_tx, checkpoint_rx) = mpsc::unbounded();
+    let listing =
+        checkpointed.then(|| Arc::new(ListingCheckpoints::new(skipped, checkpoint_tx.clone())));
 
     let stats = Arc::new(AtomicSyncStats::new());
+    let sync_config = &settings.sync;
+    let concurrency = sync_config.concurrency;
+    // With adaptive concurrency the client limits the requests in flight
+    let fetch_concurrency = sync_config.max_concurrency.unwrap_or(concurrency);
+    let excluded_count = AtomicUsize::new(0);
+    let excluded = &excluded_count;
+    let failed_datasets = FailedDatasets::default();
+    let failed = &failed_datasets;
 
-    let _results: Vec<_> = stream::iter(ids.into_iter().enumerate())
+    let (fetched_tx, fetched_rx) = mpsc::channel(STAGE_CAPACITY);
+    let (detected_tx, detected_rx) = mpsc::channel(STAGE_CAPACITY);
+    let (embedded_tx, embedded_rx) = mpsc::channel(STAGE_CAPACITY);
+
+    // Set when a shutdown stops the listing short
+    let stopped_early = AtomicBool::new(false);
+    let stopped = &stopped_early;
+    let fetch = stream::iter(ids.into_iter().enumerate().skip(skipped))
+        .take_while(|_| {
+            let go_on = !shutdown::requested();
+            stopped.fetch_or(!go_on, Ordering::Relaxed);
+            futures::future::ready(go_on)
+        })
         .map(|(i, id)| {
             let ckan = ckan.clone();
-            let gemini = gemini_client.clone();
-            let repo = repo.clone();
-            let portal_url = portal_url.to_string();
-            let existing_hashes = existing_hashes.clone();
             let stats = Arc::clone(&stats);
+            let mut progress = DatasetProgress::new(i, id, listing.clone());
+            let span = progress.span.clone();
 
             async move {
-                let ckan_data = match ckan.show_package(&id).await {
-                    Ok(data) => data,
+                let stage = Instant::now();
+                let fetched = ckan
+                    .show_package(&progress.id)
+                    .instrument(info_span!("ckan.show_package"))
+                    .await;
+                progress.durations.fetch_ms = Some(elapsed_ms(stage));
+                match fetched {
+                    Ok(data) => Some((data, progress)),
                     Err(e) => {
-                        error!("[{}/{}] Failed to fetch {}: {}", i + 1, total, id, e);
-                        stats.record(SyncOutcome::Failed);
-                        return Err(e);
+                        error!(
+                            "[{}/{}] Failed to fetch {}: {}",
+                            i + 1,
+                            total,
+                            progress.id,
+                            e
+                        );
+                        stats.record_failure(FailureKind::of(FailureStage::Fetch, &e));
+                        failed.record(portal_url, &progress.id, FailureStage::Fetch, &e);
+                        progress.finish(
+                            outcome_log,
+                            portal_url,
+                            SyncOutcome::Failed,
+                            Some(e.to_string()),
+                        );
+                        None
                     }
-                };
+                }
+            }
+            .instrument(span)
+        })
+        .buffer_unordered(fetch_concurrency)
+        .filter_map(futures::future::ready)
+        .map(Ok)
+        .forward(fetched_tx);
 
-                let mut new_dataset = CkanClient::into_new_dataset(ckan_data, &portal_url);
-                let decision = needs_reprocessing(
-                    existing_hashes.get(&new_dataset.original_id),
-                    &new_dataset.content_hash,
+    let transform = fetched_rx
+        .filter_map(|(data, progress)| {
+            let new_dataset = CkanClient::into_new_dataset(data, portal_url, &settings.sync.hash_fields);
+            if filter.is_some_and(|f| !f.matches(&new_dataset)) {
+                debug!(
+                    "[{}/{}] Excluded by the portal's filters: {}",
+                    progress.position + 1,
+                    total,
+                    new_dataset.title
                 );
+                excluded.fetch_add(1, Ordering::Relaxed);
+                progress.exclude();
+                return futures::future::ready(None);
+            }
+            futures::future::ready(Some((new_dataset, progress)))
+        })
+        .chunks(DELTA_BATCH_SIZE)
+        .then(|batch| {
+            detect_changes(
+                repo,
+                portal_url,
+                embedder.model_id(),
+                batch,
+                &stats,
+                outcome_log,
+                failed,
+            )
+        })
+        .flat_map(stream::iter)
+        // Erasing the stage types keeps the harvest future provably Send for
+        // the daemon's spawned tasks
+        .boxed()
+        .map(Ok)
+        .forward(detected_tx);
 
+    let embed = detected_rx
+        .map(|(mut new_dataset, mut progress, decision)| {
+            let embedder = embedder.clone();
+            let chunk_hashes = Arc::clone(&chunk_hashes);
+            let stats = Arc::clone(&stats);
+            let span = progress.span.clone();
+
+            async move {
+                let i = progress.position;
                 match decision.outcome {
                     SyncOutcome::Unchanged => {
                         info!("[{}/{}] = Unchanged: {}", i + 1, total, new_dataset.title);
                         stats.record(SyncOutcome::Unchanged);
 
-                        if let Err(e) = repo
-                            .update_timestamp_only(&portal_url, &new_dataset.original_id)
-                            .await
-                        {
+                        let stage = Instant::now();
+                        let updated = repo
+                            .update_timestamp_only(portal_url, &new_dataset.original_id)
+                            .instrument(info_span!("db.update_timestamp_only"))
+                            .await;
+                        progress.durations.store_ms = Some(elapsed_ms(stage));
+                        if let Err(e) = &updated {
                             error!("[{}/{}] Failed to update timestamp: {}", i + 1, total, e);
                         }
-                        return Ok(());
+                        if let (true, Some((id, stored))) =
+                            (chunks, chunk_hashes.get(&new_dataset.original_id))
+                        {
+                            sync_chunks(
+                                repo,
+                                embedder.as_ref(),
+                                *id,
+                                &new_dataset,
+                                stored.as_deref(),
+                                &stats,
+                            )
+                            .await;
+                        }
+                        progress.finish(
+                            outcome_log,
+                            portal_url,
+                            SyncOutcome::Unchanged,
+                            updated.err().map(|e| e.to_string()),
+                        );
+                        return None;
                     }
                     SyncOutcome::Updated => {
                         let label = if decision.is_legacy() {
@@ -366,6 +2682,7 @@ async fn sync_portal(
                     SyncOutcome::Failed => unreachable!("needs_reprocessing never returns Failed"),
                 }
 
+                let mut embed_error = None;
                 if decision.needs_embedding {
                     let combined_text = format!(
                         "{} {}",
@@ -374,211 +2691,3295 @@ async fn sync_portal(
                     );
 
                     if !combined_text.trim().is_empty() {
-                        match gemini.get_embeddings(&combined_text).await {
+                        // Keyed by the embedded text alone, whatever the
+                        // content hash covers
+                        let text_hash = NewDataset::compute_content_hash(
+                            &new_dataset.title,
+                            new_dataset.description.as_deref(),
+                        );
+                        let stage = Instant::now();
+                        let embedded = embed_cached(
+                            repo,
+                            embedder.as_ref(),
+                            &text_hash,
+                            &combined_text,
+                            &stats,
+                        )
+                        .await;
+                        progress.durations.embed_ms = Some(elapsed_ms(stage));
+                        match embedded {
                             Ok(emb) => {
-                                new_dataset.embedding = Some(Vector::from(emb));
-                                stats.record(decision.outcome);
+                                new_dataset.embedding = Some(emb);
+                                new_dataset.embedding_model = Some(embedder.model_id().to_string());
                             }
                             Err(e) => {
                                 error!(
                                     "[{}/{}] Failed to generate embedding for {}: {}",
                                     i + 1,
                                     total,
-                                    id,
+                                    progress.id,
                                     e
                                 );
-                                stats.record(SyncOutcome::Failed);
+                                stats.record_failure(FailureKind::Embedding);
+                                // The new content hash next to the old vector
+                                // would pass for unchanged on the next harvest,
+                                // so a stored dataset keeps its row until it
+                                // embeds
+                                if decision.outcome == SyncOutcome::Updated {
+                                    if let Err(update_error) = repo
+                                        .update_timestamp_only(portal_url, &new_dataset.original_id)
+                                        .instrument(info_span!("db.update_timestamp_only"))
+                                        .await
+                                    {
+                                        error!(
+                                            "[{}/{}] Failed to update timestamp: {}",
+                                            i + 1,
+                                            total,
+                                            update_error
+                                        );
+                                    }
+                                    failed.record(
+                                        portal_url,
+                                        &progress.id,
+                                        FailureStage::Embed,
+                                        &e,
+                                    );
+                                    progress.finish(
+                                        outcome_log,
+                                        portal_url,
+                                        SyncOutcome::Failed,
+                                        Some(e.to_string()),
+                                    );
+                                    return None;
+                                }
+                                embed_error = Some(e.to_string());
                             }
                         }
                     }
                 }
 
-                match repo.upsert(&new_dataset).await {
-                    Ok(uuid) => {
-                        if decision.needs_embedding {
-                            info!(
-                                "[{}/{}] ✓ Indexed: {} ({})",
-                                i + 1,
-                                total,
-                                new_dataset.title,
-                                uuid
-                            );
+                let pending = PendingUpsert {
+                    progress,
+                    decision,
+                    embed_error,
+                };
+                Some((new_dataset, pending))
+            }
+            .instrument(span)
+        })
+        .buffer_unordered(concurrency)
+        .filter_map(futures::future::ready)
+        .map(Ok)
+        .forward(embedded_tx);
+
+    let persist = embedded_rx
+        .chunks(UPSERT_BATCH_SIZE)
+        .for_each(|batch| {
+            let embedder = embedder.clone();
+            let chunk_hashes = Arc::clone(&chunk_hashes);
+            let stats = Arc::clone(&stats);
+
+            async move {
+                let (datasets, pending): (Vec<NewDataset>, Vec<PendingUpsert>) =
+                    batch.into_iter().unzip();
+                let stage = Instant::now();
+                let stored = store_batch(repo, &datasets).await;
+                let store_ms = elapsed_ms(stage);
+
+                stream::iter(datasets.iter().zip(pending).zip(stored))
+                    .for_each_concurrent(concurrency, |((new_dataset, pending), stored)| {
+                        let PendingUpsert {
+                            mut progress,
+                            decision,
+                            embed_error,
+                        } = pending;
+                        progress.durations.store_ms = Some(store_ms);
+                        let embedder = embedder.clone();
+                        let chunk_hashes = Arc::clone(&chunk_hashes);
+                        let stats = Arc::clone(&stats);
+                        let span = progress.span.clone();
+
+                        async move {
+                            let i = progress.position;
+                            match stored {
+                                Ok(upserted) => {
+                                    let uuid = upserted.id();
+                                    if decision.needs_embedding {
+                                        info!(
+                                            "[{}/{}] ✓ Indexed: {} ({})",
+                                            i + 1,
+                                            total,
+                                            new_dataset.title,
+                                            uuid
+                                        );
+                                    }
+                                    if chunks && embed_error.is_none() {
+                                        let stored = chunk_hashes
+                                            .get(&new_dataset.original_id)
+                                            .and_then(|(_, hash)| hash.as_deref());
+                                        sync_chunks(
+                                            repo,
+                                            embedder.as_ref(),
+                                            uuid,
+                                            new_dataset,
+                                            stored,
+                                            &stats,
+                                        )
+                                        .await;
+                                    }
+                                    match embed_error {
+                                        Some(e) => {
+                                            failed.record(
+                                                portal_url,
+                                                &progress.id,
+                                                FailureStage::Embed,
+                                                &e,
+                                            );
+                                            progress.finish(
+                                                outcome_log,
+                                                portal_url,
+                                                SyncOutcome::Failed,
+                                                Some(e),
+                                            )
+                                        }
+                                        // What the database did, which a concurrent
+                                        // harvest of the portal may have changed
+                                        None => {
+                                            stats.record(upserted.sync_outcome());
+                                            progress.finish(
+                                                outcome_log,
+                                                portal_url,
+                                                upserted.sync_outcome(),
+                                                None,
+                                            )
+                                        }
+                                    }
+                                }
+                                Err(e) => {
+                                    error!(
+                                        "[{}/{}] Failed to save {}: {}",
+                                        i + 1,
+                                        total,
+                                        progress.id,
+                                        e
+                                    );
+                                    stats.record_failure(FailureKind::Database);
+                                    failed.record(
+                                        portal_url,
+                                        &progress.id,
+                                        FailureStage::Store,
+                                        &e,
+                                    );
+                                    progress.finish(
+                                        outcome_log,
+                                        portal_url,
+                                        SyncOutcome::Failed,
+                                        Some(e.to_string()),
+                                    );
+                                }
+                            }
                         }
-                        Ok(())
-                    }
-                    Err(e) => {
-                        error!("[{}/{}] Failed to save {}: {}", i + 1, total, id, e);
-                        stats.record(SyncOutcome::Failed);
-                        Err(e)
-                    }
-                }
+                        .instrument(span)
+                    })
+                    .await;
             }
         })
-        .buffer_unordered(SyncConfig::default().concurrency)
-        .collect()
-        .await;
+        // Likewise for the Send-ness of the storing stage
+        .boxed();
+
+    let save_checkpoints = checkpoint_rx
+        .for_each(|offset| async move {
+            let checkpoint = HarvestCheckpoint {
+                portal_url: portal_url.to_string(),
+                offset,
+                started_at,
+            };
+            if let Err(e) = repo.save_harvest_checkpoint(&checkpoint).await {
+                warn!("Failed to save the harvest checkpoint of {}: {}", portal_url, e);
+            }
+        })
+        // Likewise for the Send-ness of the checkpoint saves
+        .boxed();
+    let pipeline = async {
+        // A stage's sends fail only if the next stage has stopped, which it
+        // does not before its input ends
+        let _ = futures::join!(fetch, transform, embed, persist);
+        // Lets the checkpoints saved so far drain
+        checkpoint_tx.close_channel();
+    };
+    futures::join!(pipeline, save_checkpoints);
+
+    let excluded = excluded_count.into_inner();
+    if excluded > 0 {
+        info!("{} datasets excluded by the portal's filters", excluded);
+    }
+    if let Some(limit) = ckan.concurrency_limit() {
+        info!("Adaptive concurrency ended at {} requests in flight", limit);
+    }
+
+    let mut sync_stats = stats.to_stats();
+    sync_stats.interrupted = stopped_early.into_inner();
+    if sync_stats.interrupted {
+        warn!(
+            "Harvest of {} interrupted after {} of {} datasets; the next harvest picks up the rest",
+            portal_url,
+            skipped + sync_stats.total() + excluded,
+            total
+        );
+    }
+    if let Some(listing) = listing {
+        let saved = if sync_stats.interrupted {
+            repo.save_harvest_checkpoint(&HarvestCheckpoint {
+                portal_url: portal_url.to_string(),
+                offset: listing.offset(),
+                started_at,
+            })
+            .await
+        } else {
+            repo.clear_harvest_checkpoint(portal_url).await
+        };
+        if let Err(e) = saved {
+            warn!(
+                "Failed to update the harvest checkpoint of {}: {}",
+                portal_url, e
+            );
+        }
+    }
+    // Recorded first, so datasets failing again keep their attempt count
+    // and are not resolved below
+    let failures = failed_datasets.into_inner();
+    if let Err(e) = repo.record_harvest_failures(&failures).await {
+        warn!("Failed to record {} failed datasets: {}", failures.len(), e);
+    }
+    // Every dataset that failed before was retried, unless sampled out or
+    // interrupted
+    if sample.is_none() && !sync_stats.interrupted {
+        match repo
+            .resolve_harvest_failures(Some(portal_url), started_at)
+            .await
+        {
+            Ok(0) => {}
+            Ok(resolved) => info!("{} earlier failures resolved", resolved),
+            Err(e) => warn!("Failed to resolve earlier harvest failures: {}", e),
+        }
+    }
+    if let Err(e) = lock.release().await {
+        warn!("Failed to unlock portal {}: {}", portal_url, e);
+    }
+    Ok(sync_stats)
+}
 
-    Ok(stats.to_stats())
+/// A dataset's place in a harvest: its position in the portal's package
+/// list, its span and the time spent in each stage so far.
+struct DatasetProgress {
+    position: usize,
+    id: String,
+    durations: StageDurations,
+    started: Instant,
+    span: tracing::Span,
+    listing: Option<Arc<ListingCheckpoints>>,
 }
 
-async fn search(
-    repo: &DatasetRepository,
-    gemini_client: &GeminiClient,
-    query: &str,
-    limit: usize,
-) -> anyhow::Result<()> {
-    info!("Searching for: '{}' (limit: {})", query, limit);
+impl DatasetProgress {
+    fn new(position: usize, id: String, listing: Option<Arc<ListingCheckpoints>>) -> Self {
+        let span = info_span!("dataset", id = %id, outcome = tracing::field::Empty);
+        Self {
+            position,
+            id,
+            durations: StageDurations::default(),
+            started: Instant::now(),
+            span,
+            listing,
+        }
+    }
+
+    /// Counts a dataset the portal's filters excluded as done for the
+    /// harvest checkpoint.
+    fn exclude(self) {
+        if let Some(listing) = &self.listing {
+            listing.finish(self.position);
+        }
+    }
+
+    /// Records the dataset's outcome on its span and in `outcome_log`.
+    fn finish(
+        mut self,
+        outcome_log: Option<&OutcomeLog>,
+        portal_url: &str,
+        outcome: SyncOutcome,
+        error: Option<String>,
+    ) {
+        self.span.record("outcome", tracing::field::debug(outcome));
+        if let Some(listing) = &self.listing {
+            listing.finish(self.position);
+        }
+        if let Some(log) = outcome_log {
+            self.durations.total_ms = elapsed_ms(self.started);
+            log.record(&DatasetOutcomeRecord {
+                timestamp: Utc::now(),
+                portal: portal_url.to_string(),
+                id: self.id,
+                outcome,
+                durations: self.durations,
+                error,
+            });
+        }
+    }
+}
+
+/// How far into a portal's listing a harvest has finished every dataset,
+/// sending the offset off to be saved each [`CHECKPOINT_INTERVAL`]
+/// datasets.
+struct ListingCheckpoints {
+    progress: std::sync::Mutex<ListingProgress>,
+    saves: mpsc::UnboundedSender<usize>,
+}
+
+impl ListingCheckpoints {
+    fn new(offset: usize, saves: mpsc::UnboundedSender<usize>) -> Self {
+        Self {
+            progress: std::sync::Mutex::new(ListingProgress::new(offset)),
+            saves,
+        }
+    }
+
+    fn finish(&self, position: usize) {
+        let mut progress = self.progress.lock().unwrap_or_else(|e| e.into_inner());
+        let before = progress.offset();
+        let offset = progress.finish(position);
+        if offset / CHECKPOINT_INTERVAL > before / CHECKPOINT_INTERVAL {
+            // Fails only once the harvest has ended, which saves its own
+            let _ = self.saves.unbounded_send(offset);
+        }
+    }
+
+    fn offset(&self) -> usize {
+        self.progress
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .offset()
+    }
+}
+
+/// Datasets a harvest failed to process, written to the dead-letter table
+/// when it ends.
+#[derive(Default)]
+struct FailedDatasets(std::sync::Mutex<Vec<HarvestFailure>>);
+
+impl FailedDatasets {
+    fn record(&self, portal_url: &str, id: &str, stage: FailureStage, error: &impl ToString) {
+        self.0
+            .lock()
+            .unwrap_or_else(|e| e.into_inner())
+            .push(HarvestFailure::new(
+                portal_url,
+                id,
+                stage,
+                error.to_string(),
+            ));
+    }
+
+    fn into_inner(self) -> Vec<HarvestFailure> {
+        self.0.into_inner().unwrap_or_else(|e| e.into_inner())
+    }
+}
+
+/// Pairs each fetched dataset of a batch with its delta detection decision.
+///
+/// If the lookup fails, the batch's datasets are recorded as failed and
+/// dropped.
+async fn detect_changes(
+    repo: &dyn DatasetStore,
+    portal_url: &str,
+    embedding_model: &str,
+    batch: Vec<(NewDataset, DatasetProgress)>,
+    stats: &AtomicSyncStats,
+    outcome_log: Option<&OutcomeLog>,
+    failed: &FailedDatasets,
+) -> Vec<(NewDataset, DatasetProgress, ReprocessingDecision)> {
+    let candidates: Vec<(String, String)> = batch
+        .iter()
+        .map(|(dataset, _)| (dataset.original_id.clone(), dataset.content_hash.clone()))
+        .collect();
+    let detected = repo
+        .datasets_needing_processing(portal_url, embedding_model, &candidates)
+        .instrument(info_span!(
+            "db.datasets_needing_processing",
+            datasets = candidates.len()
+        ))
+        .await;
+    match detected {
+        Ok(decisions) => batch
+            .into_iter()
+            .map(|(dataset, progress)| {
+                let decision = decisions
+                    .get(&dataset.original_id)
+                    .cloned()
+                    .unwrap_or_else(ReprocessingDecision::unchanged);
+                (dataset, progress, decision)
+            })
+            .collect(),
+        Err(e) => {
+            error!(
+                "Failed to check {} datasets for changes: {}",
+                batch.len(),
+                e
+            );
+            for (_, progress) in batch {
+                stats.record_failure(FailureKind::Database);
+                failed.record(portal_url, &progress.id, FailureStage::Detect, &e);
+                progress.finish(
+                    outcome_log,
+                    portal_url,
+                    SyncOutcome::Failed,
+                    Some(e.to_string()),
+                );
+            }
+            Vec::new()
+        }
+    }
+}
+
+/// A dataset fetched, and embedded when needed, waiting for its batch to be
+/// stored.
+struct PendingUpsert {
+    progress: DatasetProgress,
+    decision: ReprocessingDecision,
+    embed_error: Option<String>,
+}
+
+/// Stores a batch of datasets in one call. If the batch fails, each dataset
+/// is stored on its own so one bad record does not fail the others.
+async fn store_batch(
+    repo: &dyn DatasetStore,
+    datasets: &[NewDataset],
+) -> Vec<Result<UpsertOutcome, AppError>> {
+    let stored = repo
+        .upsert_batch(datasets)
+        .instrument(info_span!("db.upsert_batch", datasets = datasets.len()))
+        .await;
+    match stored {
+        Ok(ids) => ids.into_iter().map(Ok).collect(),
+        Err(e) => {
+            warn!(
+                "Failed to save a batch of {} datasets, retrying one by one: {}",
+                datasets.len(),
+                e
+            );
+            let mut results = Vec::with_capacity(datasets.len());
+            for new_dataset in datasets {
+                results.push(
+                    repo.upsert(new_dataset)
+                        .instrument(info_span!("db.upsert"))
+                        .await,
+                );
+            }
+            results
+        }
+    }
+}
+
+/// Embeds a dataset's text, reusing the cached vector of identical content.
+///
+/// Failing to read or write the cache only loses the reuse; the dataset
+/// is still embedded and stored.
+async fn embed_cached(
+    repo: &dyn DatasetStore,
+    embedder: &dyn EmbeddingProvider,
+    text_hash: &str,
+    text: &str,
+    stats: &AtomicSyncStats,
+) -> Result<Vector, AppError> {
+    match repo
+        .cached_embedding(text_hash, embedder.model_id())
+        .instrument(info_span!("db.cached_embedding"))
+        .await
+    {
+        Ok(Some(vector)) => {
+            stats.record_cache_lookup(true);
+            return Ok(vector);
+        }
+        Ok(None) => {}
+        Err(e) => warn!("Embedding cache lookup failed: {}", e),
+    }
+    stats.record_cache_lookup(false);
+
+    let vector = embedder
+        .embed(text)
+        .instrument(info_span!("embed", model = embedder.model_id()))
+        .await?;
+    let vector = Vector::from(vector);
+    if let Err(e) = repo
+        .cache_embedding(text_hash, embedder.model_id(), &vector)
+        .instrument(info_span!("db.cache_embedding"))
+        .await
+    {
+        warn!("Failed to cache embedding: {}", e);
+    }
+    Ok(vector)
+}
+
+/// Embeds and stores a dataset's chunks unless they match `stored_hash`.
+///
+/// Chunks supplement the dataset embedding, so failures are only logged;
+/// the stored hash is left as is and the next harvest retries.
+async fn sync_chunks(
+    repo: &dyn DatasetStore,
+    embedder: &dyn EmbeddingProvider,
+    dataset_id: uuid::Uuid,
+    dataset: &NewDataset,
+    stored_hash: Option<&str>,
+    stats: &AtomicSyncStats,
+) {
+    let chunks = build_chunks(
+        &dataset.title,
+        dataset.description.as_deref(),
+        &dataset.metadata,
+    );
+    let hash = chunks_hash(&chunks);
+    if hash.as_deref() == stored_hash {
+        return;
+    }
+
+    let mut embedded = Vec::with_capacity(chunks.len());
+    for chunk in chunks {
+        match embed_cached(
+            repo,
+            embedder,
+            &chunk_text_hash(&chunk.text),
+            &chunk.text,
+            stats,
+        )
+        .await
+        {
+            Ok(vector) => embedded.push((chunk, vector)),
+            Err(e) => {
+                warn!("Failed to embed chunks of {}: {}", dataset.original_id, e);
+                return;
+            }
+        }
+    }
+
+    if let Err(e) = repo
+        .replace_dataset_chunks(dataset_id, &embedded, embedder.model_id(), hash.as_deref())
+        .instrument(info_span!("db.replace_dataset_chunks"))
+        .await
+    {
+        warn!("Failed to store chunks of {}: {}", dataset.original_id, e);
+    }
+}
+
+/// Milliseconds elapsed since `start`, for the outcome log.
+fn elapsed_ms(start: Instant) -> u64 {
+    start.elapsed().as_millis() as u64
+}
+
+/// Seed of `--sample random` when `--seed` is not given.
+fn random_seed() -> u64 {
+    std::time::SystemTime::now()
+        .duration_since(std::time::UNIX_EPOCH)
+        .map(|d| d.as_nanos() as u64)
+        .unwrap_or_default()
+}
+
+/// Settings of a `ceres search` run.
+struct SearchOptions<'a> {
+    limit: usize,
+    filters: &'a SearchFilters,
+    strategy: SearchStrategy,
+    mode: SearchModeArg,
+    chunks: bool,
+    /// Reranker and number of candidates it re-scores
+    rerank: Option<(&'a dyn Reranker, usize)>,
+    /// Weight of metadata quality in ranking (0 leaves scores unchanged)
+    quality_weight: f32,
+    expander: Option<&'a dyn QueryExpander>,
+    output: SearchOutputArg,
+    /// Where the search is logged from in the search history; `None` leaves
+    /// it out
+    history: Option<SearchSource>,
+}
+
+/// Ranked results of a search, with the queries run and facet counts.
+struct SearchHits {
+    /// The query followed by its expansions
+    queries: Vec<String>,
+    results: Vec<SearchResult>,
+    facets: SearchFacets,
+}
+
+impl SearchHits {
+    /// Document printed by `ceres search --json` and served at `/search`.
+    fn response(&self) -> SearchResponse {
+        SearchResponse {
+            query: self.queries[0].clone(),
+            expanded_queries: self.queries[1..].to_vec(),
+            results: self
+                .results
+                .iter()
+                .map(|r| SearchHit {
+                    dataset: DatasetRecord::new(&r.dataset),
+                    score: r.similarity_score,
+                })
+                .collect(),
+            facets: self.facets.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+struct SearchResponse {
+    query: String,
+    /// Rewrites of the query searched alongside it (`--expand`)
+    expanded_queries: Vec<String>,
+    /// Matches, best first
+    results: Vec<SearchHit>,
+    /// Counts of the matches by portal, format, organization, tag and year
+    facets: SearchFacets,
+}
+
+#[derive(Debug, Serialize, ToSchema)]
+struct SearchHit {
+    #[serde(flatten)]
+    dataset: DatasetRecord,
+    /// Relevance to the query; higher is better
+    score: f32,
+}
+
+async fn search(
+    repo: &dyn DatasetStore,
+    embedder: Option<&dyn EmbeddingProvider>,
+    query: &str,
+    options: &SearchOptions<'_>,
+) -> anyhow::Result<()> {
+    info!("Searching for: '{}' (limit: {})", query, options.limit);
+    let hits = search_hits(repo, embedder, query, options).await?;
+    if let Some(path) = last_search::default_path() {
+        if let Err(e) = LastSearch::new(query, &hits.results).save(&path) {
+            warn!("Failed to keep the results for `ceres open`: {:#}", e);
+        }
+    }
+
+    match options.output {
+        SearchOutputArg::Json => {
+            println!("{}", serde_json::to_string_pretty(&hits.response())?);
+        }
+        SearchOutputArg::Csv => print!("{}", format_results_csv(&hits.results)),
+        SearchOutputArg::Table => print!("{}", format_results_table(&hits.results)),
+        SearchOutputArg::Text => print_results(query, &hits.results, &hits.facets),
+    }
+    Ok(())
+}
+
+/// Runs a search with expansion, reranking and quality weighting as set in
+/// `options`, keeping the best `options.limit` results.
+async fn search_hits(
+    repo: &dyn DatasetStore,
+    embedder: Option<&dyn EmbeddingProvider>,
+    query: &str,
+    options: &SearchOptions<'_>,
+) -> anyhow::Result<SearchHits> {
+    let SearchOptions {
+        limit,
+        rerank,
+        quality_weight,
+        ..
+    } = *options;
+
+    // Reranking re-scores a wider candidate set, then keeps the best `limit`
+    let shown = rerank.map_or(limit, |(_, k)| k.max(limit));
+    // Facets are counted over the top matches, beyond those shown
+    let candidates = shown.max(FACET_CANDIDATES);
+
+    let mut queries = vec![query.to_string()];
+    if let Some(expander) = options.expander {
+        match expander.expand(query, MAX_EXPANSIONS).await {
+            Ok(paraphrases) => {
+                info!("Expanded query: {:?}", paraphrases);
+                queries.extend(paraphrases);
+            }
+            Err(e) => warn!("Query expansion failed, searching the query only: {}", e),
+        }
+    }
+    let result_sets = futures::future::try_join_all(
+        queries
+            .iter()
+            .map(|text| run_search(repo, embedder, text, candidates, options)),
+    )
+    .await?;
+    let mut results = merge_result_sets(result_sets, candidates);
+    let facets = compute_facets(results.iter().map(|r| &r.dataset));
+
+    if let Some((reranker, _)) = rerank {
+        results.truncate(shown);
+        let documents: Vec<String> = results
+            .iter()
+            .map(|r| rerank_document(&r.dataset))
+            .collect();
+        let scores = reranker
+            .rerank(query, &documents)
+            .await
+            .context("Reranking failed")?;
+        info!(
+            "Reranked {} candidates with {}",
+            documents.len(),
+            reranker.model_id()
+        );
+        results = apply_rerank_scores(results, &scores, shown);
+    }
+    // Weighted over all candidates, so well-documented datasets can move up
+    if quality_weight > 0.0 {
+        results = apply_quality_weight(results, quality_weight);
+    }
+    results.truncate(limit);
+
+    if let Some(source) = options.history {
+        let record = SearchRecord::new(
+            query,
+            options.mode.as_str(),
+            options.filters,
+            &results,
+            source,
+        );
+        if let Err(e) = repo.record_search(&record).await {
+            warn!("Failed to record the search in the history: {}", e);
+        }
+    }
+
+    Ok(SearchHits {
+        queries,
+        results,
+        facets,
+    })
+}
+
+/// Opens result `rank` of the last search.
+fn open_search_result(rank: usize, print: bool) -> anyhow::Result<()> {
+    let path = last_search::default_path().context("No cache directory to keep searches in")?;
+    let search = LastSearch::load(&path)?;
+    open_url(&search.result(rank)?.url, print)
+}
+
+/// Opens `url` in the default browser, or prints it with `print`.
+fn open_url(url: &str, print: bool) -> anyhow::Result<()> {
+    if print {
+        println!("{}", url);
+        return Ok(());
+    }
+    info!("Opening {}", url);
+    webbrowser::open(url).with_context(|| format!("Failed to open {} in a browser", url))
+}
+
+/// Characters of each result's description shown under it.
+const SNIPPET_CHARS: usize = 160;
+
+/// Prints search results for humans, followed by facet counts.
+fn print_results(query: &str, results: &[SearchResult], facets: &SearchFacets) {
+    if results.is_empty() {
+        outln!("\n🔍 No results found for: \"{}\"\n", query);
+        outln!("Try:");
+        outln!("  • Using different keywords");
+        outln!("  • Searching in a different language");
+        outln!("  • Harvesting more portals with: ceres harvest <url>");
+    } else {
+        outln!("\n🔍 Search Results for: \"{}\"\n", query);
+        outln!("Found {} matching datasets:\n", results.len());
+
+        for (i, result) in results.iter().enumerate() {
+            // Similarity indicator
+            let similarity_bar = create_similarity_bar(result.similarity_score);
+
+            outln!(
+                "{}. {} [{:.0}%] {}",
+                i + 1,
+                similarity_bar,
+                result.similarity_score * 100.0,
+                result.dataset.title
+            );
+            outln!("   📍 {}", result.dataset.source_portal);
+            outln!("   🔗 {}", result.dataset.url);
+            if !result.dataset.formats.is_empty() {
+                outln!("   📄 {}", result.dataset.formats.join(", "));
+            }
+
+            if let Some(desc) = &result.dataset.description {
+                outln!("   📝 {}", highlight(&snippet(desc, query, SNIPPET_CHARS)));
+            }
+            outln!();
+        }
+
+        print_facets(facets);
+    }
+}
+
+/// Formats search results as CSV with a header row.
+///
+/// Formats are joined with `;`; `modified_at` is empty when unknown.
+fn format_results_csv(results: &[SearchResult]) -> String {
+    let mut csv = String::from("rank,score,id,title,url,source_portal,formats,modified_at\n");
+    for (i, result) in results.iter().enumerate() {
+        let dataset = &result.dataset;
+        csv.push_str(&format!(
+            "{},{:.4},{},{},{},{},{},{}\n",
+            i + 1,
+            result.similarity_score,
+            dataset.id,
+            escape_csv(&dataset.title),
+            escape_csv(&dataset.url),
+            escape_csv(&dataset.source_portal),
+            escape_csv(&dataset.formats.join(";")),
+            dataset
+                .modified_at
+                .map(|t| t.format("%Y-%m-%dT%H:%M:%SZ").to_string())
+                .unwrap_or_default(),
+        ));
+    }
+    csv
+}
+
+/// Longest title shown in `--output table`.
+const TABLE_TITLE_CHARS: usize = 60;
+
+/// Formats search results as plain-text columns.
+fn format_results_table(results: &[SearchResult]) -> String {
+    let header = ["#", "SCORE", "TITLE", "PORTAL", "FORMATS"].map(String::from);
+    let rows: Vec<[String; 5]> = results
+        .iter()
+        .enumerate()
+        .map(|(i, result)| {
+            let title = result
+                .dataset
+                .title
+                .split_whitespace()
+                .collect::<Vec<_>>()
+                .join(" ");
+            let title = if title.chars().count() > TABLE_TITLE_CHARS {
+                let cut: String = title.chars().take(TABLE_TITLE_CHARS - 3).collect();
+                format!("{}...", cut)
+            } else {
+                title
+            };
+            let portal = &result.dataset.source_portal;
+            let portal = portal
+                .strip_prefix("https://")
+                .or_else(|| portal.strip_prefix("http://"))
+                .unwrap_or(portal);
+            [
+                (i + 1).to_string(),
+                format!("{:.0}%", result.similarity_score * 100.0),
+                title,
+                portal.trim_end_matches('/').to_string(),
+                result.dataset.formats.join(","),
+            ]
+        })
+        .collect();
+
+    let mut widths = header.clone().map(|h| h.chars().count());
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(cell.chars().count());
+        }
+    }
+
+    let mut table = String::new();
+    for row in std::iter::once(&header).chain(&rows) {
+        let cells: Vec<String> = row
+            .iter()
+            .zip(widths)
+            .map(|(cell, width)| format!("{:<width$}", cell, width = width))
+            .collect();
+        table.push_str(cells.join("  ").trim_end());
+        table.push('\n');
+    }
+    table
+}
+
+/// Answers a question from the best-matching datasets, citing them.
+async fn ask(
+    store: &dyn DatasetStore,
+    embedder: Option<&dyn EmbeddingProvider>,
+    answerer: &GeminiAnswerer,
+    question: &str,
+    options: &SearchOptions<'_>,
+) -> anyhow::Result<()> {
+    info!("Answering: '{}' (sources: {})", question, options.limit);
+
+    let results = run_search(store, embedder, question, options.limit, options).await?;
+    if results.is_empty() {
+        if options.output == SearchOutputArg::Json {
+            let output = serde_json::json!({
+                "question": question,
+                "answer": null,
+                "sources": [],
+            });
+            println!("{}", serde_json::to_string_pretty(&output)?);
+        } else {
+            outln!("\n🔍 No indexed datasets match: \"{}\"\n", question);
+            outln!("Harvest more portals with: ceres harvest <url>");
+        }
+        return Ok(());
+    }
+
+    let sources: Vec<String> = results
+        .iter()
+        .map(|r| source_document(&r.dataset))
+        .collect();
+    let answer = answerer
+        .answer(question, &sources)
+        .await
+        .context("Answering failed")?;
+    info!(
+        "Answered from {} sources with {}",
+        sources.len(),
+        answerer.model()
+    );
+
+    if options.output == SearchOutputArg::Json {
+        let sources: Vec<serde_json::Value> = results
+            .iter()
+            .enumerate()
+            .map(|(i, r)| {
+                serde_json::json!({
+                    "number": i + 1,
+                    "id": r.dataset.id,
+                    "title": r.dataset.title,
+                    "url": r.dataset.url,
+                    "source_portal": r.dataset.source_portal,
+                    "score": r.similarity_score,
+                    "cited": answer.citations.contains(&i),
+                })
+            })
+            .collect();
+        let output = serde_json::json!({
+            "question": question,
+            "answer": answer.text,
+            "sources": sources,
+        });
+        println!("{}", serde_json::to_string_pretty(&output)?);
+        return Ok(());
+    }
+
+    outln!("\n💬 {}\n", answer.text);
+    if answer.citations.is_empty() {
+        outln!("(No dataset cited)");
+    } else {
+        outln!("Sources:");
+        for &i in &answer.citations {
+            let dataset = &results[i].dataset;
+            outln!("  [{}] {}", i + 1, dataset.title);
+            outln!("      🔗 {}", dataset.url);
+        }
+    }
+    outln!();
+    Ok(())
+}
+
+/// Retrieves `candidates` matches for `text` in the mode of `options`,
+/// embedding it unless searching text only.
+async fn run_search(
+    repo: &dyn DatasetStore,
+    embedder: Option<&dyn EmbeddingProvider>,
+    text: &str,
+    candidates: usize,
+    options: &SearchOptions<'_>,
+) -> anyhow::Result<Vec<SearchResult>> {
+    let filters = options.filters;
+    if options.mode == SearchModeArg::Text {
+        return Ok(repo.text_search(text, candidates, filters).await?);
+    }
+
+    let embedder = embedder.context(NO_EMBEDDER)?;
+    let query_vector = Vector::from(embedder.embed_query(text).await?);
+    let strategy = options.strategy;
+    let results = if options.chunks {
+        repo.search_with_chunks(query_vector, text, candidates, filters, strategy)
+            .await?
+    } else if options.mode == SearchModeArg::Hybrid {
+        repo.hybrid_search(query_vector, text, candidates, filters, strategy)
+            .await?
+    } else {
+        repo.search_with_filters(query_vector, text, candidates, filters, strategy)
+            .await?
+    };
+    Ok(results)
+}
+
+/// Prints facet counts of the top search matches.
+fn print_facets(facets: &SearchFacets) {
+    if facets.is_empty() {
+        return;
+    }
+    outln!("📊 Top {} matches by:", FACET_CANDIDATES);
+    for (name, values) in [
+        ("Portal", &facets.portal),
+        ("Format", &facets.format),
+        ("Organization", &facets.organization),
+        ("Tag", &facets.tag),
+        ("Year", &facets.year),
+    ] {
+        if values.is_empty() {
+            continue;
+        }
+        let values: Vec<String> = values
+            .iter()
+            .map(|f| format!("{} ({})", f.value, f.count))
+            .collect();
+        outln!("   {:<13} {}", format!("{}:", name), values.join(", "));
+    }
+    outln!();
+}
+
+/// Ten-cell bar for `score`, clamped to 0..1 and rounded to the nearest
+/// cell, so 5% already fills one.
+fn create_similarity_bar(score: f32) -> String {
+    // Rerankers may score outside 0..1 (e.g. TEI raw logits)
+    let filled = (score.clamp(0.0, 1.0) * 10.0).round() as usize;
+    let empty = 10 - filled;
+    let (full, blank) = if term::ascii() {
+        ("#", ".")
+    } else {
+        ("█", "░")
+    };
+    format!("[{}{}]", full.repeat(filled), blank.repeat(empty))
+}
+
+/// The text of `snippet` with its matches in bold on a colored terminal.
+fn highlight(snippet: &Snippet) -> String {
+    if !term::color() || !std::io::stdout().is_terminal() {
+        return snippet.text.clone();
+    }
+    let mut text = String::with_capacity(snippet.text.len());
+    let mut last = 0;
+    for range in &snippet.highlights {
+        text.push_str(&snippet.text[last..range.start]);
+        text.push_str(&format!("\x1b[1m{}\x1b[22m", &snippet.text[range.clone()]));
+        last = range.end;
+    }
+    text.push_str(&snippet.text[last..]);
+    text
+}
+
+/// Collapses whitespace in `text` and cuts it to `max_len` characters,
+/// marking a cut with `...`.
+fn truncate_text(text: &str, max_len: usize) -> String {
+    let cleaned: String = text
+        .chars()
+        .map(|c| if c.is_whitespace() { ' ' } else { c })
+        .collect();
+    let cleaned = cleaned.split_whitespace().collect::<Vec<_>>().join(" ");
+
+    match cleaned.char_indices().nth(max_len) {
+        Some((end, _)) => format!("{}...", &cleaned[..end]),
+        None => cleaned,
+    }
+}
+
+async fn show_stats(
+    repo: &dyn DatasetStore,
+    portal: Option<&str>,
+    json: bool,
+) -> anyhow::Result<()> {
+    let stats = repo.get_stats(portal).await?;
+    if json {
+        return print_json(&stats);
+    }
+
+    outln!(
+        "\n📊 Database Statistics{}\n",
+        portal.map(|p| format!(" for {}", p)).unwrap_or_default()
+    );
+    outln!("  Total datasets:        {}", stats.total_datasets);
+    outln!(
+        "  With embeddings:       {}",
+        stats.datasets_with_embeddings
+    );
+    outln!("  Unique portals:        {}", stats.total_portals);
+    if let Some(last_update) = stats.last_update {
+        outln!("  Last update:           {}", last_update);
+    }
+    if !stats.portals.is_empty() {
+        outln!("\n  Portals (least recently harvested first):");
+        for p in &stats.portals {
+            let harvested = p
+                .last_harvest_at
+                .or(p.last_update)
+                .map(|t| t.format("%Y-%m-%d %H:%M").to_string())
+                .unwrap_or_else(|| "never".to_string());
+            let failures = match p.failure_rate() {
+                Some(rate) => format!("{:.0}% of {} harvests failed", rate * 100.0, p.harvests),
+                None => "no harvest history".to_string(),
+            };
+            outln!(
+                "    {:<40} {} datasets, {:.0}% embedded, harvested {}, {}",
+                p.portal,
+                p.datasets,
+                p.embedding_coverage() * 100.0,
+                harvested,
+                failures
+            );
+        }
+    }
+    if !stats.formats.is_empty() {
+        outln!("\n  Formats:");
+        for facet in &stats.formats {
+            let size = if facet.total_size > 0 {
+                format!(", {}", format_size(facet.total_size as u64))
+            } else {
+                String::new()
+            };
+            outln!(
+                "    {:<20} {} datasets, {} resources{}",
+                facet.format,
+                facet.datasets,
+                facet.resources,
+                size
+            );
+        }
+    }
+    if !stats.languages.is_empty() {
+        outln!("\n  Languages:");
+        for count in &stats.languages {
+            let language = count.language.as_deref().unwrap_or("(undetected)");
+            outln!("    {:<20} {}", language, count.datasets);
+        }
+    }
+    if !stats.quality.is_empty() {
+        outln!(
+            "\n  Metadata quality (average, datasets below {}):",
+            LOW_QUALITY
+        );
+        for portal in &stats.quality {
+            outln!(
+                "    {:<40} {:.2}  {} of {}",
+                portal.portal,
+                portal.average,
+                portal.low,
+                portal.datasets
+            );
+        }
+    }
+    if !stats.embedding_models.is_empty() {
+        outln!("\n  Embedding models:");
+        for count in &stats.embedding_models {
+            let range = match (count.first_embedded_at, count.last_embedded_at) {
+                (Some(first), Some(last)) => format!(
+                    "  embedded {} to {}",
+                    first.format("%Y-%m-%d"),
+                    last.format("%Y-%m-%d")
+                ),
+                _ => String::new(),
+            };
+            outln!(
+                "    {:<40} {} datasets on {} portals{}",
+                count.model,
+                count.datasets,
+                count.portals,
+                range
+            );
+        }
+    }
+    outln!();
+
+    Ok(())
+}
+
+/// Apply pending schema migrations, or list them with `dry_run`.
+async fn migrate(repo: &dyn DatasetStore, dry_run: bool) -> anyhow::Result<()> {
+    if dry_run {
+        let pending = repo.pending_migrations().await?;
+        if pending.is_empty() {
+            outln!("✓ Database schema is up to date.");
+            return Ok(());
+        }
+        outln!("{} pending migration(s):", pending.len());
+        for migration in &pending {
+            outln!("  → {} {}", migration.version, migration.description);
+        }
+        return Ok(());
+    }
+
+    let applied = repo.run_migrations().await?;
+    if applied.is_empty() {
+        outln!("✓ Database schema is up to date.");
+        return Ok(());
+    }
+    for migration in &applied {
+        outln!("  ✓ {} {}", migration.version, migration.description);
+    }
+    outln!("✓ Applied {} migration(s).", applied.len());
+    Ok(())
+}
+
+/// Print the most used tags with their dataset counts.
+async fn show_top_tags(
+    repo: &DatasetRepository,
+    portal: Option<&str>,
+    limit: usize,
+    json: bool,
+) -> anyhow::Result<()> {
+    let tags = repo.top_tags(portal, limit).await?;
+    if json {
+        println!("{}", serde_json::to_string_pretty(&tags)?);
+        return Ok(());
+    }
+    if tags.is_empty() {
+        outln!("No tags found. Datasets indexed before tags were normalized");
+        outln!("can be tagged with: ceres backfill-tags");
+        return Ok(());
+    }
+
+    outln!(
+        "\n🏷️  Top tags{}\n",
+        portal.map(|p| format!(" on {}", p)).unwrap_or_default()
+    );
+    for (i, count) in tags.iter().enumerate() {
+        outln!("{:>4}. {:<40} {}", i + 1, count.tag, count.datasets);
+    }
+    outln!();
+    Ok(())
+}
+
+/// Print organizations by number of datasets.
+async fn list_orgs(
+    repo: &DatasetRepository,
+    portal: Option<&str>,
+    limit: usize,
+    json: bool,
+) -> anyhow::Result<()> {
+    let organizations = repo.list_organizations(portal, limit).await?;
+    if json {
+        println!("{}", serde_json::to_string_pretty(&organizations)?);
+        return Ok(());
+    }
+    if organizations.is_empty() {
+        outln!("No organizations found.");
+        return Ok(());
+    }
+
+    outln!(
+        "\n🏛️  Organizations{}\n",
+        portal.map(|p| format!(" on {}", p)).unwrap_or_default()
+    );
+    for (i, org) in organizations.iter().enumerate() {
+        let last_modified = org
+            .last_modified
+            .map(|t| t.format("%Y-%m-%d").to_string())
+            .unwrap_or_else(|| "-".to_string());
+        outln!(
+            "{:>4}. {:<40} {:>6} datasets  last modified {}",
+            i + 1,
+            org.title.as_deref().unwrap_or(&org.name),
+            org.datasets,
+            last_modified
+        );
+        outln!("      {} on {}", org.name, org.source_portal);
+    }
+    outln!();
+    Ok(())
+}
+
+/// Seed of the k-means++ initialization, so reruns find the same topics.
+const CLUSTER_SEED: u64 = 42;
+
+/// Cluster embeddings into topics, store the assignments and print an overview.
+async fn cluster_topics(
+    repo: &DatasetRepository,
+    model: &str,
+    portal: Option<&str>,
+    k: usize,
+    examples: usize,
+    json: bool,
+) -> anyhow::Result<()> {
+    if k == 0 {
+        anyhow::bail!("--k must be at least 1");
+    }
+    let rows = repo.cluster_candidates(model, portal).await?;
+    if rows.is_empty() {
+        anyhow::bail!(
+            "No datasets embedded with {}{}",
+            model,
+            portal.map(|p| format!(" from {}", p)).unwrap_or_default()
+        );
+    }
+    info!("Clustering {} embeddings into {} topics", rows.len(), k);
+
+    let vectors: Vec<Vec<f32>> = rows.iter().map(|(_, _, v)| v.to_vec()).collect();
+    let clustering = kmeans(&vectors, k, CLUSTER_SEED);
+    info!(
+        "k-means converged after {} iterations",
+        clustering.iterations
+    );
+
+    let titles: Vec<&str> = rows.iter().map(|(_, title, _)| title.as_str()).collect();
+    let keywords = cluster_keywords(
+        &titles,
+        &clustering.assignments,
+        clustering.centroids.len(),
+        CLUSTER_KEYWORDS,
+    );
+    let closest = representatives(&vectors, &clustering, examples);
+    let topics: Vec<Topic> = keywords
+        .into_iter()
+        .zip(clustering.sizes())
+        .zip(closest)
+        .enumerate()
+        .map(|(cluster, ((keywords, datasets), closest))| Topic {
+            cluster,
+            keywords,
+            datasets,
+            examples: closest.iter().map(|&i| titles[i].to_string()).collect(),
+        })
+        .collect();
+
+    let assignments: Vec<(uuid::Uuid, usize)> = rows
+        .iter()
+        .zip(&clustering.assignments)
+        .map(|((id, _, _), &cluster)| (*id, cluster))
+        .collect();
+    repo.replace_clusters(&topics, &assignments, model, portal)
+        .await?;
+
+    if json {
+        let output = serde_json::json!({
+            "embedding_model": model,
+            "portal": portal,
+            "datasets": rows.len(),
+            "topics": topics,
+        });
+        println!("{}", serde_json::to_string_pretty(&output)?);
+        return Ok(());
+    }
+
+    outln!(
+        "\n🧭 {} topics in {} datasets ({})\n",
+        topics.len(),
+        rows.len(),
+        model
+    );
+    for topic in &topics {
+        let label = if topic.keywords.is_empty() {
+            "(no distinctive words)".to_string()
+        } else {
+            topic.keywords.join(", ")
+        };
+        outln!(
+            "{:>3}. {}  ({} datasets)",
+            topic.cluster + 1,
+            label,
+            topic.datasets
+        );
+        for title in &topic.examples {
+            outln!("       • {}", title);
+        }
+    }
+    outln!();
+    Ok(())
+}
+
+/// Number of unhashed datasets above which harvests suggest `backfill-hashes`.
+const HASH_BACKFILL_ADVISORY_THRESHOLD: i64 = 100;
+
+/// Warn when many datasets lack a content hash and would be re-embedded.
+///
+/// Best-effort: a failed check is logged and otherwise ignored.
+async fn advise_hash_backfill(repo: &DatasetRepository) {
+    match repo.count_missing_hashes().await {
+        Ok(count) if count >= HASH_BACKFILL_ADVISORY_THRESHOLD => warn!(
+            "{} datasets have no content hash and will be re-embedded on every harvest. \
+             Run `ceres backfill-hashes` to fix this without re-embedding.",
+            count
+        ),
+        Ok(_) => {}
+        Err(e) => error!("Failed to count datasets without content hash: {}", e),
+    }
+}
+
+/// Compute missing content hashes in batches until none are left, or
+/// recompute every hash with `rehash`.
+async fn backfill_hashes(
+    repo: &DatasetRepository,
+    batch_size: usize,
+    rehash: bool,
+    hash_fields: &[HashField],
+) -> anyhow::Result<()> {
+    let remaining = repo.count_hash_backfill(rehash).await?;
+    if remaining == 0 {
+        outln!("✓ All embedded datasets already have a content hash.");
+        return Ok(());
+    }
+    let fields = hash_fields;
+    if fields.is_empty() {
+        info!("Hashing title and description of {} datasets", remaining);
+    } else {
+        let fields: Vec<String> = fields.iter().map(ToString::to_string).collect();
+        info!(
+            "Hashing title, description and {} of {} datasets",
+            fields.join(", "),
+            remaining
+        );
+    }
+
+    let (mut after, mut total) = (uuid::Uuid::nil(), 0);
+    while let Some((last, updated)) = repo
+        .backfill_content_hashes(after, batch_size.max(1), rehash, hash_fields)
+        .await?
+    {
+        after = last;
+        total += updated;
+        info!("Stored {} content hashes so far", total);
+    }
+
+    if rehash {
+        outln!(
+            "✓ Recomputed content hashes; {} of {} changed.",
+            total,
+            remaining
+        );
+    } else {
+        outln!("✓ Backfilled {} content hashes.", total);
+    }
+    Ok(())
+}
+
+async fn backfill_languages(repo: &DatasetRepository, batch_size: usize) -> anyhow::Result<()> {
+    let remaining = repo.count_missing_languages().await?;
+    if remaining == 0 {
+        outln!("✓ All datasets already have a language.");
+        return Ok(());
+    }
+    info!("Detecting languages of {} datasets", remaining);
+
+    let (mut after, mut detected) = (uuid::Uuid::nil(), 0);
+    while let Some((last, stored)) = repo.backfill_languages(after, batch_size.max(1)).await? {
+        after = last;
+        detected += stored;
+        info!("Detected {} languages so far", detected);
+    }
+
+    outln!(
+        "✓ Detected the language of {} of {} datasets.",
+        detected,
+        remaining
+    );
+    if detected < remaining as usize {
+        outln!("  The others have too little text to detect reliably.");
+    }
+    Ok(())
+}
+
+/// Score the metadata quality of datasets without a score, or of all
+/// datasets with `rescore`.
+async fn backfill_quality(
+    repo: &DatasetRepository,
+    batch_size: usize,
+    rescore: bool,
+) -> anyhow::Result<()> {
+    let remaining = repo.count_quality_backfill(rescore).await?;
+    if remaining == 0 {
+        outln!("✓ All datasets already have a quality score.");
+        return Ok(());
+    }
+    info!("Scoring the metadata quality of {} datasets", remaining);
+
+    let (mut after, mut scored) = (uuid::Uuid::nil(), 0);
+    while let Some((last, stored)) = repo
+        .backfill_quality(after, batch_size.max(1), rescore)
+        .await?
+    {
+        after = last;
+        scored += stored;
+        info!("Scored {} datasets so far", scored);
+    }
+
+    outln!("✓ Scored the metadata quality of {} datasets.", scored);
+    Ok(())
+}
+
+/// Store normalized tags of datasets with CKAN tags but none stored.
+async fn backfill_tags(repo: &DatasetRepository, batch_size: usize) -> anyhow::Result<()> {
+    let remaining = repo.count_untagged().await?;
+    if remaining == 0 {
+        outln!("✓ All tagged datasets already have normalized tags.");
+        return Ok(());
+    }
+    info!("Normalizing the tags of {} datasets", remaining);
+
+    let (mut after, mut tagged) = (uuid::Uuid::nil(), 0);
+    while let Some((last, stored)) = repo.backfill_tags(after, batch_size.max(1)).await? {
+        after = last;
+        tagged += stored;
+        info!("Tagged {} datasets so far", tagged);
+    }
+
+    outln!("✓ Stored the normalized tags of {} datasets.", tagged);
+    Ok(())
+}
+
+/// Store bounding boxes of datasets with a `spatial` extra but no box.
+async fn backfill_spatial(repo: &DatasetRepository, batch_size: usize) -> anyhow::Result<()> {
+    let remaining = repo.count_missing_bboxes().await?;
+    if remaining == 0 {
+        outln!("✓ No datasets with a spatial extra are missing a bounding box.");
+        return Ok(());
+    }
+    info!("Reading the spatial extent of {} datasets", remaining);
+
+    let (mut after, mut stored_total) = (uuid::Uuid::nil(), 0);
+    while let Some((last, stored)) = repo.backfill_bboxes(after, batch_size.max(1)).await? {
+        after = last;
+        stored_total += stored;
+        info!("Stored {} bounding boxes so far", stored_total);
+    }
+
+    outln!(
+        "✓ Stored the bounding box of {} of {} datasets.",
+        stored_total,
+        remaining
+    );
+    if stored_total < remaining as usize {
+        outln!("  The others have a spatial extra that is not valid GeoJSON.");
+    }
+    Ok(())
+}
+
+/// Report disk usage, project growth against the budget and suggest retention.
+///
+/// With `apply`, the automatic suggestions are carried out and the pruned
+/// tables vacuumed so later harvests reuse the space.
+async fn maintain(
+    repo: &DatasetRepository,
+    disk_budget: Option<u64>,
+    retention_months: u32,
+    apply: bool,
+) -> anyhow::Result<()> {
+    let now = Utc::now();
+    let used = repo.database_size().await?;
+    let tables = repo.table_sizes().await?;
+    let stats = repo.get_stats(None).await?;
+
+    outln!("\n💾 Disk Usage\n");
+    match disk_budget {
+        Some(budget) => outln!(
+            "  Database size:   {} of {} budget ({:.0}%)",
+            format_size(used),
+            format_size(budget),
+            used as f64 / budget as f64 * 100.0
+        ),
+        None => outln!("  Database size:   {}", format_size(used)),
+    }
+    outln!(
+        "\n  {:<24} {:>10} {:>10} {:>10}",
+        "Table",
+        "Data",
+        "Indexes",
+        "Total"
+    );
+    for table in &tables {
+        outln!(
+            "  {:<24} {:>10} {:>10} {:>10}",
+            table.table,
+            format_size(table.data_bytes),
+            format_size(table.index_bytes),
+            format_size(table.total_bytes())
+        );
+    }
+
+    let datasets_bytes = tables
+        .iter()
+        .find(|t| t.table == "datasets")
+        .map_or(0, TableSize::total_bytes);
+    let growth = repo.portal_growth().await?;
+    let projection = GrowthProjection::new(
+        datasets_bytes,
+        stats.total_datasets,
+        datasets_per_month(&growth, now),
+    );
+    outln!(
+        "\n  Growth:          ~{:.0} datasets/month × {} ≈ {}/month",
+        projection.datasets_per_month,
+        format_size(projection.bytes_per_dataset),
+        format_size(projection.bytes_per_month)
+    );
+    if let Some(budget) = disk_budget {
+        match projection.months_until(used, budget) {
+            Some(0.0) => outln!("  Budget reached:  already exceeded"),
+            Some(months) => outln!("  Budget reached:  in ~{:.0} months", months.ceil()),
+            None => outln!("  Budget reached:  not within 10 years at this rate"),
+        }
+        match BudgetStatus::of(used, budget) {
+            BudgetStatus::Exceeded => warn!(
+                "Database uses {} and exceeds its {} budget",
+                format_size(used),
+                format_size(budget)
+            ),
+            BudgetStatus::Near => warn!(
+                "Database uses {} of its {} budget",
+                format_size(used),
+                format_size(budget)
+            ),
+            BudgetStatus::Within => {}
+        }
+    }
+
+    let before = retention_cutoff(now, retention_months);
+    let suggestions = suggest_retention(
+        repo.count_stale_datasets(before).await?,
+        repo.count_failed_deliveries_before(before).await?,
+        repo.embedding_bytes().await?,
+        before,
+    );
+    if suggestions.is_empty() {
+        outln!(
+            "\n✓ Nothing to prune with a {}-month retention.\n",
+            retention_months
+        );
+        return Ok(());
+    }
+
+    outln!("\n  Retention ({} months):", retention_months);
+    for suggestion in &suggestions {
+        let description = match suggestion {
+            RetentionSuggestion::PruneStaleDatasets { datasets, .. } => format!(
+                "Prune {} datasets not seen upstream since {}",
+                datasets,
+                before.format("%Y-%m-%d")
+            ),
+            RetentionSuggestion::PruneFailedDeliveries { deliveries, .. } => format!(
+                "Prune {} failed watch deliveries older than {}",
+                deliveries,
+                before.format("%Y-%m-%d")
+            ),
+            RetentionSuggestion::QuantizeEmbeddings { .. } => {
+                "Store embeddings as halfvec (pgvector 0.7+, manual migration)".to_string()
+            }
+        };
+        outln!(
+            "    - {:<64} ~{}",
+            description,
+            format_size(suggestion.bytes())
+        );
+    }
+
+    if !apply {
+        if suggestions.iter().any(RetentionSuggestion::is_automatic) {
+            outln!("\n  Run with --apply to prune.");
+        }
+        outln!();
+        return Ok(());
+    }
+
+    outln!();
+    for suggestion in suggestions.iter().filter(|s| s.is_automatic()) {
+        match suggestion {
+            RetentionSuggestion::PruneStaleDatasets { before, .. } => {
+                let deleted = repo.prune_stale_datasets(*before).await?;
+                outln!("✓ Pruned {} stale datasets", deleted);
+            }
+            RetentionSuggestion::PruneFailedDeliveries { before, .. } => {
+                let deleted = repo.prune_failed_deliveries_before(*before).await?;
+                outln!("✓ Pruned {} failed watch deliveries", deleted);
+            }
+            RetentionSuggestion::QuantizeEmbeddings { .. } => {}
+        }
+    }
+    info!("Vacuuming pruned tables...");
+    repo.vacuum_pruned_tables().await?;
+    outln!("✓ Vacuumed; freed space is reused by later harvests.\n");
+
+    Ok(())
+}
+
+/// Register a watch: embed its query and store it with a fresh signing secret.
+async fn add_watch(
+    repo: &DatasetRepository,
+    embedder: &dyn EmbeddingProvider,
+    name: String,
+    query: String,
+    webhook_url: String,
+    min_score: f32,
+    portal: Option<String>,
+) -> anyhow::Result<()> {
+    if !(0.0..=1.0).contains(&min_score) {
+        anyhow::bail!("--min-score must be between 0 and 1");
+    }
+    url::Url::parse(&webhook_url).context("Invalid webhook URL")?;
+
+    let watch = register_watch(repo, embedder, name, query, webhook_url, min_score, portal).await?;
+
+    outln!("\n✓ Watch '{}' created.\n", watch.name);
+    outln!("  Webhook:  {}", watch.webhook_url);
+    outln!("  Secret:   {}", watch.secret);
+    outln!();
+    outln!("Keep the secret: it signs every delivery (X-Ceres-Signature header).");
+    outln!("Datasets harvested from now on that match the query will be delivered.\n");
+
+    Ok(())
+}
+
+/// Embeds the query of a new watch and stores the watch with a fresh
+/// secret, for `ceres watch add` and `POST /watches`.
+async fn register_watch(
+    store: &dyn DatasetStore,
+    embedder: &dyn EmbeddingProvider,
+    name: String,
+    query: String,
+    webhook_url: String,
+    min_score: f32,
+    portal: Option<String>,
+) -> Result<Watch, AppError> {
+    let embedding = embedder.embed_query(&query).await?;
+    let watch = Watch {
+        id: uuid::Uuid::new_v4(),
+        name,
+        query,
+        min_score,
+        portal,
+        embedding_model: embedder.model_id().to_string(),
+        webhook_url,
+        secret: generate_secret(),
+        created_at: Utc::now(),
+    };
+    store.create_watch(&watch, Vector::from(embedding)).await?;
+    Ok(watch)
+}
+
+/// Print registered watches.
+async fn list_watches(repo: &DatasetRepository) -> anyhow::Result<()> {
+    let watches = repo.list_watches().await?;
+    if watches.is_empty() {
+        outln!(
+            "\nNo watches registered. Add one with: ceres watch add <name> --query ... --webhook ...\n"
+        );
+        return Ok(());
+    }
+
+    outln!("\n👀 Watches\n");
+    for watch in &watches {
+        outln!(
+            "  {} — \"{}\" (min score {:.2})",
+            watch.name,
+            watch.query,
+            watch.min_score
+        );
+        outln!("     🔔 {}", watch.webhook_url);
+        if let Some(portal) = &watch.portal {
+            outln!("     📍 {}", portal);
+        }
+    }
+    outln!();
+
+    Ok(())
+}
+
+/// Print the delivery log of a watch.
+async fn show_watch_deliveries(
+    repo: &DatasetRepository,
+    name: &str,
+    limit: usize,
+) -> anyhow::Result<()> {
+    let watch = repo
+        .get_watch_by_name(name)
+        .await?
+        .ok_or_else(|| anyhow::anyhow!("Watch '{}' not found", name))?;
+    let deliveries = repo.list_watch_deliveries(watch.id, limit).await?;
+
+    if deliveries.is_empty() {
+        outln!("\nNo deliveries yet for '{}'.\n", name);
+        return Ok(());
+    }
+
+    outln!("\n📬 Deliveries for '{}'\n", name);
+    for delivery in &deliveries {
+        let icon = if delivery.status == DeliveryStatus::Delivered.as_str() {
+            "✓"
+        } else {
+            "✗"
+        };
+        outln!(
+            "  {} {}  {} datasets  {}",
+            icon,
+            delivery.created_at.format("%Y-%m-%d %H:%M:%S UTC"),
+            delivery.dataset_count,
+            delivery.id
+        );
+        if let Some(err) = &delivery.error {
+            outln!("      {}", truncate_text(err, 100));
+        }
+    }
+    outln!();
+
+    Ok(())
+}
+
+/// Deliver new watch matches after a harvest, logging instead of failing.
+async fn deliver_watch_matches(repo: &DatasetRepository, http_config: &HttpConfig) {
+    match process_watches(repo, http_config).await {
+        Ok(0) => {}
+        Ok(delivered) => info!("Delivered {} new watch matches", delivered),
+        Err(e) => error!("Failed to process watches: {}", e),
+    }
+}
+
+/// Send every watch its new matches as one signed delivery and log the outcome.
+///
+/// Returns the number of datasets delivered. A delivery that still fails
+/// after the client's own retries is logged, and its datasets are offered
+/// again after the next harvest.
+async fn process_watches(
+    repo: &DatasetRepository,
+    http_config: &HttpConfig,
+) -> anyhow::Result<usize> {
+    let watches = repo.list_watches().await?;
+    if watches.is_empty() {
+        return Ok(0);
+    }
+
+    let client = WebhookClient::new(http_config)?;
+    let mut delivered = 0;
+
+    for watch in &watches {
+        let matches = repo
+            .new_watch_matches(watch, MAX_MATCHES_PER_DELIVERY)
+            .await?;
+        if matches.is_empty() {
+            continue;
+        }
+
+        let dataset_ids: Vec<uuid::Uuid> = matches.iter().map(|m| m.id).collect();
+        let notification = WatchNotification::new(watch, matches, Utc::now());
+        let result = client
+            .post_signed(
+                &watch.webhook_url,
+                &notification,
+                &watch.secret,
+                notification.timestamp.timestamp(),
+            )
+            .await;
+
+        let (status, error) = match result {
+            Ok(()) => {
+                info!(
+                    "[watch] Delivered {} datasets to '{}'",
+                    dataset_ids.len(),
+                    watch.name
+                );
+                delivered += dataset_ids.len();
+                (DeliveryStatus::Delivered, None)
+            }
+            Err(e) => {
+                error!("[watch] Delivery to '{}' failed: {}", watch.name, e);
+                (DeliveryStatus::Failed, Some(e.to_string()))
+            }
+        };
+
+        repo.record_watch_delivery(
+            watch.id,
+            notification.delivery_id,
+            &dataset_ids,
+            status,
+            error.as_deref(),
+        )
+        .await?;
+    }
+
+    Ok(delivered)
+}
+
+/// Portal health keyed by portal URL.
+fn portal_health_by_url(health: Vec<PortalHealth>) -> HashMap<String, PortalHealth> {
+    health
+        .into_iter()
+        .map(|h| (h.portal_url.clone(), h))
+        .collect()
+}
+
+/// Portal stats keyed by URL without a trailing slash.
+fn portal_stats_by_url(stats: Vec<PortalStats>) -> HashMap<String, PortalStats> {
+    stats
+        .into_iter()
+        .map(|s| (s.portal.trim_end_matches('/').to_string(), s))
+        .collect()
+}
+
+/// A configured portal with its harvest health and indexed datasets.
+#[derive(Debug, Serialize, ToSchema)]
+struct PortalRecord {
+    name: String,
+    url: String,
+    /// Portal software (`ckan`)
+    #[serde(rename = "type")]
+    portal_type: String,
+    enabled: bool,
+    /// Cron expression `ceres daemon` harvests the portal on (UTC)
+    schedule: Option<String>,
+    /// Next harvest of the schedule, before jitter
+    next_run: Option<DateTime<Utc>>,
+    /// Datasets indexed from the portal
+    datasets: i64,
+    /// Indexed datasets with an embedding
+    with_embeddings: i64,
+    /// Most recent dataset update
+    last_update: Option<DateTime<Utc>>,
+    last_success_at: Option<DateTime<Utc>>,
+    /// Failed harvests since the last success
+    consecutive_failures: u32,
+    /// Set while harvests skip the portal
+    quarantined_until: Option<DateTime<Utc>>,
+    last_error: Option<String>,
+}
+
+/// The configured portals, as printed by `ceres portals list --json` and
+/// served at `/portals`.
+fn portal_records(
+    portals_config: &PortalsConfig,
+    health: Vec<PortalHealth>,
+    stats: Vec<PortalStats>,
+    now: DateTime<Utc>,
+) -> Vec<PortalRecord> {
+    let health = portal_health_by_url(health);
+    let indexed = portal_stats_by_url(stats);
+    portals_config
+        .portals
+        .iter()
+        .map(|portal| {
+            let h = health.get(&portal.url);
+            let stats = indexed.get(portal.url.trim_end_matches('/'));
+            PortalRecord {
+                name: portal.name.clone(),
+                url: portal.url.clone(),
+                portal_type: portal.portal_type.clone(),
+                enabled: portal.enabled,
+                schedule: portal.schedule.clone(),
+                // The configuration was validated when loaded
+                next_run: portal.next_scheduled_run(now).ok().flatten(),
+                datasets: stats.map_or(0, |s| s.datasets),
+                with_embeddings: stats.map_or(0, |s| s.with_embeddings),
+                last_update: stats.and_then(|s| s.last_update),
+                last_success_at: h.and_then(|h| h.last_success_at),
+                consecutive_failures: h.map_or(0, |h| h.consecutive_failures),
+                quarantined_until: h
+                    .and_then(|h| h.quarantined_until)
+                    .filter(|until| *until > now),
+                last_error: h.and_then(|h| h.last_error.clone()),
+            }
+        })
+        .collect()
+}
+
+/// Print configured portals with their indexed datasets and harvest health.
+async fn list_portals(
+    repo: &DatasetRepository,
+    config_path: Option<PathBuf>,
+    json: bool,
+) -> anyhow::Result<()> {
+    let portals_config = load_portals_config(config_path)?.ok_or_else(|| {
+        anyhow::anyhow!(
+            "No configuration file found. Create ~/.config/ceres/portals.toml or use --config"
+        )
+    })?;
+    let health = repo.list_portal_health().await?;
+    let stats = repo.portal_stats(None).await?;
+    let now = Utc::now();
+
+    if json {
+        let portals = portal_records(&portals_config, health, stats, now);
+        println!("{}", serde_json::to_string_pretty(&portals)?);
+        return Ok(());
+    }
+
+    let health = portal_health_by_url(health);
+    let indexed = portal_stats_by_url(stats);
+
+    outln!("\n🌐 Configured Portals\n");
+    for portal in &portals_config.portals {
+        let status = match health.get(&portal.url) {
+            Some(h) if h.is_quarantined(now) => format!(
+                "quarantined until {}",
+                h.quarantined_until
+                    .unwrap_or(now)
+                    .format("%Y-%m-%d %H:%M UTC")
+            ),
+            Some(h) if h.consecutive_failures > 0 => {
+                format!("failing ({} in a row)", h.consecutive_failures)
+            }
+            Some(_) => "ok".to_string(),
+            None => "never harvested".to_string(),
+        };
+        let enabled = if portal.enabled { "" } else { " [disabled]" };
+
+        outln!("  {}{} — {}", portal.name, enabled, status);
+        outln!("     🔗 {}", portal.url);
+        if let Some(schedule) = &portal.schedule {
+            match portal.next_scheduled_run(now)? {
+                Some(next) => outln!(
+                    "     ⏰ Schedule: {} (next run {})",
+                    schedule,
+                    next.format("%Y-%m-%d %H:%M UTC")
+                ),
+                None => outln!("     ⏰ Schedule: {}", schedule),
+            }
+        }
+        match indexed.get(portal.url.trim_end_matches('/')) {
+            Some(stats) => outln!(
+                "     📦 {} datasets ({} embedded), last sync {}",
+                stats.datasets,
+                stats.with_embeddings,
+                stats
+                    .last_update
+                    .map(|t| t.format("%Y-%m-%d %H:%M UTC").to_string())
+                    .unwrap_or_else(|| "unknown".to_string())
+            ),
+            None => outln!("     📦 No datasets indexed"),
+        }
+        if let Some(h) = health.get(&portal.url) {
+            if let Some(last_success) = h.last_success_at {
+                outln!(
+                    "     ✓ Last success: {}",
+                    last_success.format("%Y-%m-%d %H:%M UTC")
+                );
+            }
+            if h.consecutive_failures > 0 {
+                if let Some(err) = &h.last_error {
+                    outln!("     ✗ Last error: {}", truncate_text(err, 100));
+                }
+            }
+        }
+    }
+    outln!();
+
+    Ok(())
+}
+
+/// Clear a portal's quarantine. Accepts a configured portal name or a URL.
+async fn unquarantine_portal(
+    repo: &DatasetRepository,
+    config_path: Option<PathBuf>,
+    name: &str,
+) -> anyhow::Result<()> {
+    let url = resolve_portal_url(config_path, name)?;
+
+    let mut health = repo.get_portal_health(&url).await?;
+    if !health.is_quarantined(Utc::now()) {
+        outln!("Portal '{}' is not quarantined.", name);
+        return Ok(());
+    }
+
+    health.clear_quarantine();
+    repo.save_portal_health(&health).await?;
+    outln!("✓ Quarantine cleared for '{}'.", name);
+
+    Ok(())
+}
+
+/// The configuration file edited by `ceres portal` subcommands.
+fn portal_config_path(config_path: Option<PathBuf>) -> anyhow::Result<PathBuf> {
+    config_path
+        .or_else(default_config_path)
+        .ok_or_else(|| anyhow::anyhow!("Could not determine config path; use --config"))
+}
+
+/// Download a portal bundle from the registry and merge it into portals.toml.
+///
+/// The registry index must be signed with `registry_key`, or the built-in
+/// key of the default registry; only `allow_unsigned` skips the check.
+async fn install_bundle(
+    config_path: Option<PathBuf>,
+    bundle: &str,
+    registry: &str,
+    registry_key: Option<&str>,
+    allow_unsigned: bool,
+    http_config: &HttpConfig,
+) -> anyhow::Result<()> {
+    let config_path = portal_config_path(config_path)?;
+
+    let public_key = registry_key.or_else(|| default_public_key(registry));
+    if public_key.is_none() && !allow_unsigned {
+        anyhow::bail!(
+            "No public key for registry {}: set --registry-key (or CERES_REGISTRY_PUBLIC_KEY), or pass --allow-unsigned to install without verifying its index",
+            registry
+        );
+    }
+
+    let client = RegistryClient::new(registry, http_config)?;
+    let bundle = client
+        .fetch_bundle(&BundleRef::parse(bundle), public_key)
+        .await?;
+    if !bundle.signed {
+        warn!("Registry index signature not verified (--allow-unsigned)");
+    }
+
+    let source = format!("{}@{}", bundle.name, bundle.version);
+    let merge = merge_portals(&config_path, &bundle.portals, &source)?;
+
+    outln!("\n✓ Installed {} into {}", source, config_path.display());
+    for name in &merge.added {
+        outln!("  + {}", name);
+    }
+    for name in &merge.skipped {
+        outln!("  = {} (already configured)", name);
+    }
+    outln!(
+        "  {} added, {} skipped{}",
+        merge.added.len(),
+        merge.skipped.len(),
+        if bundle.signed {
+            ", signature verified"
+        } else {
+            ""
+        }
+    );
+    outln!();
+
+    Ok(())
+}
+
+/// Delete the datasets of a portal, or only count them without `yes`.
+async fn delete_portal(
+    store: &dyn DatasetStore,
+    config_path: Option<PathBuf>,
+    portal: &str,
+    yes: bool,
+) -> anyhow::Result<()> {
+    let url = resolve_portal_url(config_path, portal)?;
+    let count = store.count_portal_datasets(&url).await?;
+    if count == 0 {
+        outln!("No datasets from {} are indexed.", url);
+        return Ok(());
+    }
+    if !yes {
+        outln!("{} datasets from {} would be deleted.", count, url);
+        outln!("  Run with --yes to delete them.");
+        return Ok(());
+    }
+
+    let deleted = store.delete_portal_datasets(&url).await?;
+    outln!("✓ Deleted {} datasets from {}", deleted, url);
+    Ok(())
+}
+
+/// List the datasets harvests failed to process, optionally of one portal,
+/// or forget them with `clear`.
+async fn list_failures(
+    store: &dyn DatasetStore,
+    portal: Option<&str>,
+    clear: bool,
+    json: bool,
+) -> anyhow::Result<()> {
+    let scope = portal
+        .map(|url| format!(" from {}", url))
+        .unwrap_or_default();
+    if clear {
+        let cleared = store.resolve_harvest_failures(portal, Utc::now()).await?;
+        outln!("✓ Cleared {} failed datasets{}", cleared, scope);
+        return Ok(());
+    }
+
+    let failures = store.list_harvest_failures(portal).await?;
+    if json {
+        return print_json(&failures);
+    }
+    if failures.is_empty() {
+        outln!("✓ No failed datasets{}.", scope);
+        return Ok(());
+    }
+
+    let mut by_stage: BTreeMap<&str, usize> = BTreeMap::new();
+    for failure in &failures {
+        *by_stage.entry(&failure.stage).or_default() += 1;
+    }
+    let stages: Vec<String> = by_stage
+        .iter()
+        .map(|(stage, count)| format!("{} {}", count, stage))
+        .collect();
+    outln!(
+        "\n{} failed datasets{} ({})",
+        failures.len(),
+        scope,
+        stages.join(", ")
+    );
+    let mut current_portal = None;
+    for failure in &failures {
+        if current_portal != Some(&failure.portal_url) {
+            outln!("\n{}", failure.portal_url);
+            current_portal = Some(&failure.portal_url);
+        }
+        outln!(
+            "  {:<7} {:<40} {:>3}×  {}",
+            failure.stage,
+            failure.dataset_id,
+            failure.attempts,
+            failure.failed_at.format("%Y-%m-%d %H:%M")
+        );
+        outln!("          {}", failure.error);
+    }
+    outln!();
+    outln!("  Retry them with `ceres retry-failed`.");
+    Ok(())
+}
+
+/// Lists the latest `limit` searches, or clears the search history.
+async fn list_search_history(
+    store: &dyn DatasetStore,
+    limit: usize,
+    clear: bool,
+    json: bool,
+) -> anyhow::Result<()> {
+    if clear {
+        let cleared = store.clear_search_history().await?;
+        outln!("✓ Cleared {} searches", cleared);
+        return Ok(());
+    }
+
+    let searches = store.list_searches(limit).await?;
+    if json {
+        return print_json(&searches);
+    }
+    if searches.is_empty() {
+        outln!("✓ No searches recorded.");
+        return Ok(());
+    }
+
+    outln!("\n{} latest searches\n", searches.len());
+    for search in &searches {
+        let top_score = search
+            .top_score
+            .map_or_else(|| "-".to_string(), |score| format!("{:.3}", score));
+        outln!(
+            "  {}  {:<4} {:<8} {:>4} results  top {:<5}  {}",
+            search.searched_at.format("%Y-%m-%d %H:%M"),
+            search.source,
+            search.mode,
+            search.result_count,
+            top_score,
+            search.query
+        );
+        if search.filters.as_object().is_some_and(|f| !f.is_empty()) {
+            outln!("                    {}", search.filters);
+        }
+    }
+    Ok(())
+}
+
+/// Harvest again only the datasets harvests failed to process, optionally
+/// of one portal.
+///
+/// Portals configured in portals.toml keep their filters, embedding model
+/// and chunk settings, and its webhooks are notified; portals harvested by
+/// URL are retried by URL.
+async fn retry_failures(
+    store: &dyn DatasetStore,
+    embedder: &Arc<dyn EmbeddingProvider>,
+    settings: &Settings,
+    portal: Option<&str>,
+    config_path: Option<PathBuf>,
+    outcome_log: Option<&OutcomeLog>,
+    json: bool,
+) -> anyhow::Result<()> {
+    let started_at = Utc::now();
+    let failures = store.list_harvest_failures(portal).await?;
+    // Failures come ordered by portal
+    let mut urls: Vec<&str> = failures.iter().map(|f| f.portal_url.as_str()).collect();
+    urls.dedup();
+    if urls.is_empty() {
+        let scope = portal
+            .map(|url| format!(" from {}", url))
+            .unwrap_or_default();
+        info!("✓ No failed datasets{} to retry.", scope);
+        return print_harvest_end(BatchHarvestSummary::new(), started_at, json);
+    }
+
+    let portals_config = load_portals_config(config_path)?;
+    let configured = portals_config
+        .as_ref()
+        .map(|c| c.portals.as_slice())
+        .unwrap_or_default();
+    let portals: Vec<PortalEntry> = urls
+        .into_iter()
+        .map(|url| {
+            configured
+                .iter()
+                .find(|p| p.url.trim_end_matches('/') == url.trim_end_matches('/'))
+                .cloned()
+                .unwrap_or_else(|| PortalEntry {
+                    name: url.to_string(),
+                    url: url.to_string(),
+                    portal_type: "ckan".to_string(),
+                    enabled: true,
+                    description: None,
+                    schedule: None,
+                    embedding_model: None,
+                    chunk_embeddings: false,
+                    include_tags: Vec::new(),
+                    exclude_orgs: Vec::new(),
+                    include_title: None,
+                    exclude_title: None,
+                })
+        })
+        .collect();
+    info!(
+        "Retrying {} failed datasets from {} portals",
+        failures.len(),
+        portals.len()
+    );
+
+    let selected: Vec<&PortalEntry> = portals.iter().collect();
+    let summary = batch_harvest(
+        store,
+        embedder,
+        settings,
+        &selected,
+        outcome_log,
+        false,
+        None,
+        true,
+    )
+    .await;
+    exit_status::report(Outcome::of_harvest(&summary));
+    print_harvest_end(summary.clone(), started_at, json)?;
+    let notifiers = portals_config
+        .map(|c| Notifiers::from_config(&c))
+        .unwrap_or_default();
+    notify(&notifiers, &settings.http, summary).await;
+    Ok(())
+}
+
+/// Delete the datasets no harvest has seen for `older_than`, or only count
+/// them without `yes`.
+async fn prune_unseen(
+    store: &dyn DatasetStore,
+    older_than: chrono::Duration,
+    portal: Option<&str>,
+    yes: bool,
+) -> anyhow::Result<()> {
+    let before = Utc::now() - older_than;
+    let scope = portal
+        .map(|url| format!(" from {}", url))
+        .unwrap_or_default();
+    let count = store.count_unseen_datasets(before, portal).await?;
+    if count == 0 {
+        outln!(
+            "✓ Every dataset{} was seen since {}.",
+            scope,
+            before.format("%Y-%m-%d %H:%M")
+        );
+        return Ok(());
+    }
+    if !yes {
+        outln!(
+            "{} datasets{} not seen since {} would be pruned.",
+            count,
+            scope,
+            before.format("%Y-%m-%d %H:%M")
+        );
+        outln!("  Run with --yes to prune them.");
+        return Ok(());
+    }
+
+    let pruned = store.prune_unseen_datasets(before, portal).await?;
+    outln!(
+        "✓ Pruned {} datasets{} not seen since {}",
+        pruned.len(),
+        scope,
+        before.format("%Y-%m-%d %H:%M")
+    );
+    Ok(())
+}
+
+/// Resolve a portal name from the configuration file to its URL; URLs pass through.
+fn resolve_portal_url(config_path: Option<PathBuf>, name: &str) -> anyhow::Result<String> {
+    if name.contains("://") {
+        return Ok(name.to_string());
+    }
+    let portals_config = load_portals_config(config_path)?.ok_or_else(|| {
+        anyhow::anyhow!(
+            "No configuration file found. Create ~/.config/ceres/portals.toml or use --config"
+        )
+    })?;
+    Ok(portals_config
+        .find_by_name(name)
+        .ok_or_else(|| anyhow::anyhow!("Portal '{}' not found in configuration", name))?
+        .url
+        .clone())
+}
+
+/// Datasets read per enrichment page.
+const ENRICH_PAGE_SIZE: usize = 200;
+
+/// Link publishers and places of pending datasets to Wikidata items.
+///
+/// Names are resolved through the label cache first; only unseen names are
+/// searched. Datasets with a name that could not be looked up (e.g. network
+/// errors) stay pending for the next run.
+async fn enrich_datasets(
+    repo: &DatasetRepository,
+    wikidata: &WikidataClient,
+    portal: Option<&str>,
+    limit: Option<usize>,
+) -> anyhow::Result<()> {
+    let language = wikidata.language();
+    let pending = repo.count_pending_enrichment(portal).await? as usize;
+    let target = limit.map_or(pending, |limit| limit.min(pending));
+    info!("Enriching {} of {} pending datasets", target, pending);
+
+    let (mut processed, mut linked, mut skipped) = (0, 0, 0);
+    let (mut cached, mut searched, mut matched) = (0, 0, 0);
+    let mut after = None;
+
+    while processed + skipped < target {
+        let page_size = ENRICH_PAGE_SIZE.min(target - processed - skipped);
+        let page = repo.pending_enrichment(portal, after, page_size).await?;
+        let Some(last) = page.last() else {
+            break;
+        };
+        after = Some(last.id);
+
+        let mentions: Vec<_> = page
+            .iter()
+            .map(|dataset| extract_mentions(&dataset.metadata))
+            .collect();
+        let mut keys: Vec<String> = mentions
+            .iter()
+            .flatten()
+            .map(|m| label_key(&m.label))
+            .collect();
+        keys.sort();
+        keys.dedup();
+
+        let mut resolved = repo.cached_wikidata_labels(language, &keys).await?;
+        cached += resolved.len();
+        for mention in mentions.iter().flatten() {
+            let key = label_key(&mention.label);
+            if resolved.contains_key(&key) {
+                continue;
+            }
+            match wikidata.search(&mention.label).await {
+                Ok(entity) => {
+                    repo.cache_wikidata_label(language, &key, entity.as_ref())
+                        .await?;
+                    searched += 1;
+                    matched += usize::from(entity.is_some());
+                    resolved.insert(key, entity.map(|e| e.qid));
+                }
+                Err(e @ AppError::RateLimitExceeded) => {
+                    return Err(e).context("Wikidata throttled the lookups; run again later");
+                }
+                Err(e) => warn!("Could not look up '{}' on Wikidata: {}", mention.label, e),
+            }
+        }
+
+        for (dataset, mentions) in page.iter().zip(&mentions) {
+            let qids: Option<Vec<_>> = mentions
+                .iter()
+                .map(|m| resolved.get(&label_key(&m.label)).map(|qid| (m.role, qid)))
+                .collect();
+            let Some(qids) = qids else {
+                skipped += 1;
+                continue;
+            };
+            let links: Vec<(EntityRole, String)> = qids
+                .into_iter()
+                .filter_map(|(role, qid)| qid.clone().map(|qid| (role, qid)))
+                .collect();
+            repo.set_dataset_entities(dataset.id, &links, dataset.content_hash.as_deref())
+                .await?;
+            processed += 1;
+            linked += usize::from(!links.is_empty());
+        }
+        info!("Enriched {}/{} datasets", processed, target);
+    }
+
+    outln!("\n🔗 Wikidata Enrichment\n");
+    outln!("  Datasets enriched:     {} ({} linked)", processed, linked);
+    if skipped > 0 {
+        outln!("  Datasets left pending: {} (lookups failed)", skipped);
+    }
+    outln!(
+        "  Names resolved:        {} from cache, {} searched ({} matched)",
+        cached,
+        searched,
+        matched
+    );
+
+    let shared = repo.shared_entities(EntityRole::Publisher, 10).await?;
+    if !shared.is_empty() {
+        outln!("\n  Publishers on several portals:");
+        for entity in shared {
+            outln!(
+                "    {:<40} {:<12} {} portals, {} datasets",
+                entity.label,
+                entity.qid,
+                entity.portals,
+                entity.datasets
+            );
+        }
+    }
+    outln!();
+    Ok(())
+}
+
+/// Fetch every dataset of a portal and report drift from the database, read-only.
+async fn audit_portal(
+    repo: &DatasetRepository,
+    portal_url: &str,
+    json: bool,
+    limit: usize,
+    settings: &Settings,
+) -> anyhow::Result<()> {
+    info!("Auditing portal: {}", portal_url);
+    let ckan = CkanClient::new(portal_url, &settings.http, &settings.sync)
+        .context("Invalid CKAN portal URL")?;
+
+    let local = repo.list_sync_state(portal_url).await?;
+    info!("Found {} datasets in the database", local.len());
+
+    let ids = ckan.list_dataset_ids().await?;
+    let total = ids.len();
+    info!("Found {} datasets on portal, fetching details...", total);
+
+    let fetched: Vec<Result<UpstreamRecord, (String, String)>> = stream::iter(ids)
+        .map(|id| {
+            let ckan = ckan.clone();
+            async move {
+                let dataset = ckan
+                    .show_package(&id)
+                    .await
+                    .map_err(|e| (id.clone(), e.to_string()))?;
+                let modified_at = dataset
+                    .extras
+                    .get("metadata_modified")
+                    .and_then(|v| v.as_str())
+                    .and_then(parse_ckan_timestamp);
+                let content_hash = content_hash(
+                    &dataset.title,
+                    dataset.notes.as_deref(),
+                    &serde_json::Value::Object(dataset.extras),
+                    &settings.sync.hash_fields,
+                );
+                Ok(UpstreamRecord {
+                    original_id: dataset.id,
+                    title: dataset.title,
+                    content_hash,
+                    modified_at,
+                })
+            }
+        })
+        .buffer_unordered(settings.sync.concurrency)
+        .collect()
+        .await;
+
+    let (upstream, failures): (Vec<_>, Vec<_>) = fetched.into_iter().partition(Result::is_ok);
+    let report = audit::compare(
+        portal_url,
+        local,
+        upstream.into_iter().filter_map(Result::ok).collect(),
+        failures.into_iter().filter_map(Result::err).collect(),
+    );
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&report)?);
+        return Ok(());
+    }
+
+    let stale = report.hash_mismatches.iter().filter(|m| m.stale).count();
+    outln!("\nAudit of {}\n", report.portal_url);
+    outln!("  Upstream datasets:   {}", report.upstream_total);
+    outln!("  Local datasets:      {}", report.local_total);
+    outln!("  In sync:             {}", report.in_sync);
+    outln!("  Missing locally:     {}", report.missing_locally.len());
+    outln!("  Missing upstream:    {}", report.missing_upstream.len());
+    outln!(
+        "  Hash mismatches:     {} ({} stale, {} unexplained)",
+        report.hash_mismatches.len(),
+        stale,
+        report.hash_mismatches.len() - stale
+    );
+    outln!("  Fetch failures:      {}", report.fetch_failures.len());
+
+    print_audit_section(
+        "Missing locally",
+        report
+            .missing_locally
+            .iter()
+            .map(|(id, title)| format!("{}  {}", id, title)),
+        limit,
+    );
+    print_audit_section(
+        "Missing upstream",
+        report.missing_upstream.iter().cloned(),
+        limit,
+    );
+    print_audit_section(
+        "Hash mismatches",
+        report.hash_mismatches.iter().map(|m| {
+            format!(
+                "{}  {}  (synced {}, upstream modified {}){}",
+                m.original_id,
+                m.title,
+                m.local_updated_at.format("%Y-%m-%d %H:%M"),
+                m.upstream_modified_at
+                    .map(|t| t.format("%Y-%m-%d %H:%M").to_string())
+                    .unwrap_or_else(|| "unknown".to_string()),
+                if m.stale { " [stale]" } else { "" }
+            )
+        }),
+        limit,
+    );
+    print_audit_section(
+        "Fetch failures",
+        report
+            .fetch_failures
+            .iter()
+            .map(|(id, error)| format!("{}  {}", id, error)),
+        limit,
+    );
+
+    if report.is_clean() {
+        outln!("\n✓ Database matches the portal.");
+    }
+    outln!();
+
+    Ok(())
+}
+
+fn print_audit_section(title: &str, entries: impl ExactSizeIterator<Item = String>, limit: usize) {
+    let total = entries.len();
+    if total == 0 {
+        return;
+    }
+    outln!("\n{}:", title);
+    for entry in entries.take(limit) {
+        outln!("  {}", entry);
+    }
+    if total > limit {
+        outln!(
+            "  ... and {} more (use --json for the full list)",
+            total - limit
+        );
+    }
+}
+
+/// Probe the CKAN API of one portal, or of every enabled portal in the
+/// configuration file, and report those likely to fail a harvest.
+async fn check_portals(
+    config_path: Option<PathBuf>,
+    portal: Option<&str>,
+    json: bool,
+    settings: &Settings,
+) -> anyhow::Result<()> {
+    let targets: Vec<(Option<String>, String)> = match portal {
+        Some(url) if url.contains("://") => vec![(None, url.to_string())],
+        Some(name) => vec![(
+            Some(name.to_string()),
+            resolve_portal_url(config_path, name)?,
+        )],
+        None => {
+            let portals_config = load_portals_config(config_path)?.ok_or_else(|| {
+                anyhow::anyhow!(
+                    "No configuration file found. Create ~/.config/ceres/portals.toml or use --config"
+                )
+            })?;
+            portals_config
+                .enabled_portals()
+                .into_iter()
+                .map(|p| (Some(p.name.clone()), p.url.clone()))
+                .collect()
+        }
+    };
+
+    let checks: Vec<PortalCheck> = stream::iter(targets)
+        .map(|(name, url)| async move {
+            let probe = probe_portal(&url, settings).await;
+            PortalCheck::new(name, url, probe)
+        })
+        .buffered(settings.sync.concurrency)
+        .collect()
+        .await;
+    let failing = checks
+        .iter()
+        .filter(|c| c.status == CheckStatus::Failing)
+        .count();
+
+    if json {
+        println!("{}", serde_json::to_string_pretty(&checks)?);
+    } else {
+        outln!("\n🩺 Portal Checks\n");
+        for check in &checks {
+            let icon = match check.status {
+                CheckStatus::Ok => "✓",
+                CheckStatus::Warning => "⚠",
+                CheckStatus::Failing => "✗",
+            };
+            let probe = &check.probe;
+            let mut details = Vec::new();
+            if let Some(ms) = probe.response_ms {
+                details.push(format!("{} ms", ms));
+            }
+            if let Some(version) = &probe.ckan_version {
+                details.push(format!("CKAN {}", version));
+            }
+            if let Some(count) = probe.dataset_count {
+                details.push(format!("~{} datasets", count));
+            }
+            if let Some(listing) = &probe.listing {
+                details.push(format!("lists with {}", listing));
+            }
+            outln!(
+                "  {} {} — {}",
+                icon,
+                check.name.as_deref().unwrap_or(&check.url),
+                if details.is_empty() {
+                    "no answer".to_string()
+                } else {
+                    details.join(", ")
+                }
+            );
+            if check.name.is_some() {
+                outln!("      {}", check.url);
+            }
+            for issue in &check.issues {
+                outln!("      {}", issue);
+            }
+        }
+        outln!();
+    }
+
+    if failing > 0 {
+        anyhow::bail!(
+            "{} of {} portals are likely to fail a harvest",
+            failing,
+            checks.len()
+        );
+    }
+    Ok(())
+}
+
+/// Runs the API probes of `ceres check` against one portal; failures are
+/// recorded in the probe rather than returned.
+async fn probe_portal(url: &str, settings: &Settings) -> PortalProbe {
+    let ckan = match CkanClient::new(url, &settings.http, &settings.sync) {
+        Ok(ckan) => ckan,
+        Err(e) => {
+            return PortalProbe {
+                site_read_error: Some(e.to_string()),
+                ..Default::default()
+            }
+        }
+    };
+
+    let started = Instant::now();
+    if let Err(e) = ckan.site_read().await {
+        return PortalProbe {
+            site_read_error: Some(e.to_string()),
+            ..Default::default()
+        };
+    }
+    let response_ms = started.elapsed().as_millis() as u64;
+
+    let capabilities = ckan.probe_capabilities().await;
+    PortalProbe {
+        response_ms: Some(response_ms),
+        site_read_error: None,
+        listing: capabilities.listing_strategy().ok().map(|s| s.to_string()),
+        ckan_version: capabilities.ckan_version,
+        dataset_count: capabilities.dataset_count,
+        package_list_error: capabilities.package_list_error,
+        package_search_error: capabilities.package_search_error,
+    }
+}
+
+/// Move a portal to a new URL in the database, then in the configuration file.
+async fn migrate_portal(
+    repo: &DatasetRepository,
+    config_path: Option<PathBuf>,
+    from: &str,
+    to: &str,
+) -> anyhow::Result<()> {
+    for url in [from, to] {
+        url::Url::parse(url).with_context(|| format!("Invalid portal URL: {}", url))?;
+    }
+    if from.trim_end_matches('/') == to.trim_end_matches('/') {
+        anyhow::bail!("--from and --to are the same URL");
+    }
+
+    let migration = repo.migrate_portal(from, to).await?;
+    outln!("\n✓ Migrated {} to {}", from, to);
+    outln!(
+        "  Datasets:     {} ({} landing page URLs rewritten)",
+        migration.datasets,
+        migration.dataset_urls
+    );
+    outln!("  Watches:      {}", migration.watches);
+    outln!(
+        "  Health:       {}",
+        if migration.health_moved {
+            "moved"
+        } else {
+            "no record"
+        }
+    );
+
+    match config_path.filter(|p| p.exists()) {
+        Some(path) => {
+            let changed = rewrite_portal_url(&path, from, to)?;
+            outln!(
+                "  Config:       {} entries updated in {}",
+                changed,
+                path.display()
+            );
+        }
+        None => outln!("  Config:       unchanged"),
+    }
+    outln!();
+
+    Ok(())
+}
+
+/// Benchmark ANN index parameters against exact search and recommend (or apply) the best.
+async fn tune_index(
+    repo: &DatasetRepository,
+    query_count: usize,
+    k: usize,
+    target_recall: f64,
+    kind: IndexKind,
+    apply: bool,
+) -> anyhow::Result<()> {
+    let family = match kind {
+        IndexKind::Hnsw => IndexFamily::Hnsw,
+        IndexKind::Ivfflat => IndexFamily::IvfFlat,
+        IndexKind::All => IndexFamily::All,
+    };
+
+    let rows = repo.get_stats(None).await?.datasets_with_embeddings;
+    if rows <= k as i64 {
+        anyhow::bail!(
+            "Not enough embedded datasets to tune the index ({} found, need more than {})",
+            rows,
+            k
+        );
+    }
+
+    info!("Sampling {} query embeddings...", query_count);
+    let queries = repo.sample_embeddings(query_count).await?;
+
+    info!("Computing exact ground truth (k={})...", k);
+    let truth = repo.exact_neighbors(&queries, k).await?;
+
+    let grid = tuning_grid(rows as u64, k as u32, family);
+    let mut measurements = Vec::new();
+    for (i, candidate) in grid.iter().enumerate() {
+        info!(
+            "[{}/{}] Benchmarking {}",
+            i + 1,
+            grid.len(),
+            candidate.build
+        );
+        measurements.extend(repo.benchmark_index(candidate, &queries, &truth, k).await?);
+    }
+
+    outln!(
+        "\n📐 Index Tuning Results ({} embeddings, {} queries, k={})\n",
+        rows,
+        queries.len(),
+        k
+    );
+    outln!(
+        "  {:<34} {:<20} {:>7} {:>10} {:>10} {:>10}",
+        "Index",
+        "Query setting",
+        "Recall",
+        "p50",
+        "p95",
+        "Build"
+    );
+    for m in &measurements {
+        outln!(
+            "  {:<34} {:<20} {:>6.1}% {:>10} {:>10} {:>10}",
+            m.build.to_string(),
+            format!("{}={}", m.build.query_param_name(), m.query_value),
+            m.recall * 100.0,
+            format!("{:.1?}", m.p50),
+            format!("{:.1?}", m.p95),
+            format!("{:.1?}", m.build_time),
+        );
+    }
+
+    let Some(best) = recommend(&measurements, target_recall) else {
+        outln!("\nNo index configurations were benchmarked.\n");
+        return Ok(());
+    };
+
+    if best.recall < target_recall {
+        outln!(
+            "\n⚠ No configuration reached the target recall of {:.0}%; recommending the highest recall.",
+            target_recall * 100.0
+        );
+    }
+    outln!(
+        "\n✓ Recommended: {} with {}={} (recall {:.1}%, p95 {:.1?})\n",
+        best.build,
+        best.build.query_param_name(),
+        best.query_value,
+        best.recall * 100.0,
+        best.p95
+    );
+
+    if apply {
+        info!("Rebuilding embedding index with {}...", best.build);
+        repo.rebuild_embedding_index(best.build).await?;
+        if let Err(e) = repo
+            .persist_index_query_setting(best.build, best.query_value)
+            .await
+        {
+            error!(
+                "Could not persist {} as a database default: {}",
+                best.build.query_param_name(),
+                e
+            );
+            outln!(
+                "Index rebuilt. Set the query parameter manually:\n  ALTER DATABASE <db> SET {} = {};\n",
+                best.build.query_param_name(),
+                best.query_value
+            );
+        } else {
+            outln!("Index rebuilt and query setting applied.\n");
+        }
+    } else {
+        outln!("Run again with --apply to rebuild the index with this setting.\n");
+    }
+
+    Ok(())
+}
+
+/// Migrates the embedding columns to the provider's dimension.
+///
+/// Without `apply` only the plan is printed. Watch queries are re-embedded
+/// with the current provider before the migration, so a failing provider
+/// leaves the database untouched.
+async fn resize_index(
+    repo: &DatasetRepository,
+    embedder: &dyn EmbeddingProvider,
+    stored_dimension: Option<usize>,
+    apply: bool,
+) -> anyhow::Result<()> {
+    let target = embedder.dimension();
+    check_indexable_dimension(target)?;
+
+    if stored_dimension == Some(target) {
+        outln!(
+            "✓ The database already stores {}-dimensional embeddings.",
+            target
+        );
+        return Ok(());
+    }
+
+    let embedded = repo.count_embeddings().await?;
+    let watches = repo.list_watches().await?;
+    let from = stored_dimension.map_or_else(|| "unconstrained".to_string(), |d| d.to_string());
+
+    outln!(
+        "\n📐 Resize embeddings: {} → {} dimensions ({})\n",
+        from,
+        target,
+        embedder.model_id()
+    );
+    outln!(
+        "  Dataset embeddings cleared: {} (rebuilt by the next harvest)",
+        embedded
+    );
+    outln!("  Watch queries re-embedded:  {}\n", watches.len());
+
+    if !apply {
+        outln!("Run again with --apply to migrate the database.\n");
+        return Ok(());
+    }
 
-    let vector = gemini_client.get_embeddings(query).await?;
-    let query_vector = Vector::from(vector);
-    let results = repo.search(query_vector, limit).await?;
+    let mut watch_embeddings = Vec::with_capacity(watches.len());
+    for watch in &watches {
+        let embedding = embedder
+            .embed_query(&watch.query)
+            .await
+            .with_context(|| format!("Failed to re-embed watch '{}'", watch.name))?;
+        watch_embeddings.push((watch.id, Vector::from(embedding)));
+    }
 
-    if results.is_empty() {
-        println!("\n🔍 No results found for: \"{}\"\n", query);
-        println!("Try:");
-        println!("  • Using different keywords");
-        println!("  • Searching in a different language");
-        println!("  • Harvesting more portals with: ceres harvest <url>");
-    } else {
-        println!("\n🔍 Search Results for: \"{}\"\n", query);
-        println!("Found {} matching datasets:\n", results.len());
+    info!("Migrating embedding columns to vector({})...", target);
+    let cleared = repo
+        .resize_embeddings(target, &watch_embeddings, embedder.model_id())
+        .await?;
 
-        for (i, result) in results.iter().enumerate() {
-            // Similarity indicator
-            let similarity_bar = create_similarity_bar(result.similarity_score);
+    outln!(
+        "✓ Migrated to {} dimensions; {} embeddings cleared. Run `ceres harvest` to re-embed datasets.\n",
+        target, cleared
+    );
+    Ok(())
+}
 
-            println!(
-                "{}. {} [{:.0}%] {}",
-                i + 1,
-                similarity_bar,
-                result.similarity_score * 100.0,
-                result.dataset.title
-            );
-            println!("   📍 {}", result.dataset.source_portal);
-            println!("   🔗 {}", result.dataset.url);
+/// Print a dataset as pretty JSON, or the values of its `--jq` projection.
+async fn show_dataset(
+    repo: &DatasetRepository,
+    id: uuid::Uuid,
+    projection: Option<&JqFilter>,
+) -> anyhow::Result<()> {
+    let dataset = repo
+        .get(id)
+        .await?
+        .with_context(|| format!("Dataset {} not found", id))?;
 
-            if let Some(desc) = &result.dataset.description {
-                let truncated = truncate_text(desc, 120);
-                println!("   📝 {}", truncated);
+    let record = create_export_record(&dataset);
+    match projection {
+        Some(filter) => {
+            for value in filter.apply(record)? {
+                println!("{}", format_output(&value));
             }
-            println!();
         }
+        None => println!("{}", serde_json::to_string_pretty(&record)?),
     }
-
     Ok(())
 }
 
-// TODO(ui): Improve similarity bar for edge cases
-// Currently (0.05 * 10).round() = 1, showing 1 bar for 5% similarity.
-// Consider using floor() or a minimum threshold for more intuitive display.
-fn create_similarity_bar(score: f32) -> String {
-    let filled = (score * 10.0).round() as usize;
-    let empty = 10 - filled;
-    format!("[{}{}]", "█".repeat(filled), "░".repeat(empty))
-}
-
-// FIXME(unicode): Byte slicing can panic on multi-byte UTF-8 characters
-// `&cleaned[..max_len]` assumes ASCII. For text with emojis or non-Latin
-// characters, this will panic. Use `.chars().take(max_len)` instead.
-// See: https://doc.rust-lang.org/book/ch08-02-strings.html#bytes-and-scalar-values-and-grapheme-clusters
-fn truncate_text(text: &str, max_len: usize) -> String {
-    let cleaned: String = text
-        .chars()
-        .map(|c| if c.is_whitespace() { ' ' } else { c })
-        .collect();
-    let cleaned = cleaned.split_whitespace().collect::<Vec<_>>().join(" ");
-
-    if cleaned.len() <= max_len {
-        cleaned
+/// Hydrate a list of dataset IDs as JSON Lines, in file order.
+async fn show_datasets(
+    repo: &DatasetRepository,
+    path: &std::path::Path,
+    projection: Option<&JqFilter>,
+) -> anyhow::Result<()> {
+    let content = if path.as_os_str() == "-" {
+        std::io::read_to_string(std::io::stdin()).context("Failed to read IDs from stdin")?
     } else {
-        // FIXME: Use cleaned.chars().take(max_len).collect::<String>()
-        format!("{}...", &cleaned[..max_len])
-    }
-}
-
-async fn show_stats(repo: &DatasetRepository) -> anyhow::Result<()> {
-    let stats = repo.get_stats().await?;
+        std::fs::read_to_string(path)
+            .with_context(|| format!("Failed to read {}", path.display()))?
+    };
+    let ids = parse_id_list(&content)?;
 
-    println!("\n📊 Database Statistics\n");
-    println!("  Total datasets:        {}", stats.total_datasets);
-    println!(
-        "  With embeddings:       {}",
-        stats.datasets_with_embeddings
-    );
-    println!("  Unique portals:        {}", stats.total_portals);
-    if let Some(last_update) = stats.last_update {
-        println!("  Last update:           {}", last_update);
+    let mut missing = Vec::new();
+    for (id, dataset) in ids.iter().zip(repo.get_many(&ids).await?) {
+        let Some(dataset) = dataset else {
+            missing.push(*id);
+            continue;
+        };
+        let record = create_export_record(&dataset);
+        match projection {
+            Some(filter) => {
+                for value in filter.apply(record)? {
+                    println!("{}", format_output(&value));
+                }
+            }
+            None => println!("{}", serde_json::to_string(&record)?),
+        }
     }
-    println!();
 
+    if !missing.is_empty() {
+        for id in &missing {
+            eprintln!("Not found: {}", id);
+        }
+        anyhow::bail!("{} of {} dataset IDs not found", missing.len(), ids.len());
+    }
     Ok(())
 }
 
-// TODO(performance): Implement streaming export for large datasets
-// Currently loads all datasets into memory before writing.
-// For databases with millions of records, this causes OOM.
-// Consider: (1) Cursor-based pagination, (2) Streaming writes as records arrive
+/// Parses one UUID per line, skipping blank lines and `#` comments.
+fn parse_id_list(content: &str) -> anyhow::Result<Vec<uuid::Uuid>> {
+    content
+        .lines()
+        .enumerate()
+        .map(|(i, line)| (i + 1, line.trim()))
+        .filter(|(_, line)| !line.is_empty() && !line.starts_with('#'))
+        .map(|(line_no, line)| {
+            line.parse()
+                .with_context(|| format!("Invalid dataset ID on line {}: '{}'", line_no, line))
+        })
+        .collect()
+}
+
 async fn export(
-    repo: &DatasetRepository,
+    repo: &dyn DatasetStore,
     format: ExportFormat,
-    portal_filter: Option<&str>,
+    filters: &SearchFilters,
     limit: Option<usize>,
+    projection: Option<&JqFilter>,
+    output: Option<&Path>,
+    embeddings: bool,
 ) -> anyhow::Result<()> {
-    info!("Exporting datasets...");
+    if projection.is_some() && matches!(format, ExportFormat::Csv | ExportFormat::Dcat) {
+        anyhow::bail!("--jq is only supported with the json and jsonl formats");
+    }
+    if embeddings && matches!(format, ExportFormat::Csv | ExportFormat::Dcat) {
+        anyhow::bail!("--embeddings is only supported with the json and jsonl formats");
+    }
 
-    // TODO(performance): Stream results instead of loading all into Vec
-    let datasets = repo.list_all(portal_filter, limit).await?;
+    info!("Exporting datasets...");
 
-    if datasets.is_empty() {
+    let mut datasets = repo.stream_all(filters, limit);
+    let Some(first) = datasets.try_next().await? else {
         eprintln!("No datasets found to export.");
         return Ok(());
-    }
+    };
 
-    info!("Found {} datasets to export", datasets.len());
-
-    match format {
-        ExportFormat::Jsonl => {
-            export_jsonl(&datasets)?;
-        }
-        ExportFormat::Json => {
-            export_json(&datasets)?;
+    let exported = match output {
+        Some(path) => {
+            let progress = dataset_counter("exported");
+            let writer = ExportWriter::new(ExportFile::create(path)?, format, projection)?
+                .with_embeddings(embeddings);
+            let (file, exported) = write_export(writer, first, datasets, &progress).await?;
+            file.commit()?;
+            progress.finish_and_clear();
+            info!("Wrote {}", path.display());
+            exported
         }
-        ExportFormat::Csv => {
-            export_csv(&datasets)?;
+        None => {
+            let out = std::io::BufWriter::new(std::io::stdout());
+            let writer = ExportWriter::new(out, format, projection)?.with_embeddings(embeddings);
+            write_export(writer, first, datasets, &ProgressBar::hidden())
+                .await?
+                .1
         }
-    }
+    };
 
-    info!("Export complete: {} datasets", datasets.len());
+    info!("Export complete: {} datasets", exported);
     Ok(())
 }
 
-fn export_jsonl(datasets: &[Dataset]) -> anyhow::Result<()> {
-    for dataset in datasets {
-        let export_record = create_export_record(dataset);
-        let json = serde_json::to_string(&export_record)?;
-        println!("{}", json);
+/// Spinner counting processed datasets, drawn on stderr when it is a
+/// terminal.
+fn dataset_counter(verb: &str) -> ProgressBar {
+    let template = format!("{{spinner}} {{human_pos}} datasets {} ({{per_sec}})", verb);
+    let mut style = ProgressStyle::with_template(&template).expect("valid progress template");
+    if term::ascii() {
+        style = style.tick_chars("-\\|/ ");
     }
-    Ok(())
+    ProgressBar::new_spinner().with_style(style)
 }
 
-fn export_json(datasets: &[Dataset]) -> anyhow::Result<()> {
-    let export_records: Vec<_> = datasets.iter().map(create_export_record).collect();
-    let json = serde_json::to_string_pretty(&export_records)?;
-    println!("{}", json);
-    Ok(())
+/// Writes `first` and the rest of `datasets`, ticking `progress` per dataset.
+/// Returns the finished output and the number of datasets written.
+async fn write_export<W: Write>(
+    mut writer: ExportWriter<'_, W>,
+    first: Dataset,
+    mut datasets: BoxStream<'_, Result<Dataset, AppError>>,
+    progress: &ProgressBar,
+) -> anyhow::Result<(W, usize)> {
+    writer.write(&first)?;
+    progress.inc(1);
+    let mut exported = 1;
+    while let Some(dataset) = datasets.try_next().await? {
+        writer.write(&dataset)?;
+        progress.inc(1);
+        exported += 1;
+    }
+    Ok((writer.finish()?, exported))
+}
+
+/// Writes export records one at a time as datasets are read, so memory use
+/// does not grow with the export.
+struct ExportWriter<'a, W: Write> {
+    out: W,
+    format: ExportFormat,
+    projection: Option<&'a JqFilter>,
+    /// Elements written so far to the JSON array or DCAT dataset list.
+    elements: usize,
+    /// Add `embedding` and `embedding_model` to JSON records.
+    embeddings: bool,
 }
 
-fn export_csv(datasets: &[Dataset]) -> anyhow::Result<()> {
-    println!("id,original_id,source_portal,url,title,description,first_seen_at,last_updated_at");
+impl<'a, W: Write> ExportWriter<'a, W> {
+    /// Writes what precedes the first record: the CSV header, the opening
+    /// bracket of the JSON array or the DCAT catalog node.
+    fn new(
+        mut out: W,
+        format: ExportFormat,
+        projection: Option<&'a JqFilter>,
+    ) -> anyhow::Result<Self> {
+        match format {
+            ExportFormat::Csv => writeln!(
+                out,
+                "id,original_id,source_portal,url,title,description,first_seen_at,last_updated_at"
+            )?,
+            ExportFormat::Json => write!(out, "[")?,
+            ExportFormat::Jsonl => {}
+            ExportFormat::Dcat => {
+                // Datasets are streamed into the last member of the catalog
+                let catalog = serde_json::to_string_pretty(&dcat::catalog(Utc::now()))?;
+                let open = catalog.strip_suffix("\n}").unwrap_or(&catalog);
+                write!(out, "{},\n  \"dcat:dataset\": [", open)?;
+            }
+        }
+        Ok(Self {
+            out,
+            format,
+            projection,
+            elements: 0,
+            embeddings: false,
+        })
+    }
+
+    /// Includes each dataset's embedding and model in JSON records, so
+    /// `ceres import` can load them without re-embedding.
+    fn with_embeddings(mut self, embeddings: bool) -> Self {
+        self.embeddings = embeddings;
+        self
+    }
+
+    /// JSON record of a dataset (see [`create_export_record`]).
+    fn record(&self, dataset: &Dataset) -> serde_json::Value {
+        let mut record = create_export_record(dataset);
+        if self.embeddings {
+            record["embedding"] = serde_json::json!(dataset.embedding);
+            record["embedding_model"] = serde_json::json!(dataset.embedding_model);
+        }
+        record
+    }
+
+    fn write(&mut self, dataset: &Dataset) -> anyhow::Result<()> {
+        let values = match (&self.format, self.projection) {
+            (ExportFormat::Csv, _) => return self.write_csv(dataset),
+            (ExportFormat::Dcat, _) => {
+                return self.write_json_element(&dcat::dataset_node(dataset), "    ")
+            }
+            (_, Some(filter)) => filter.apply(self.record(dataset))?,
+            (_, None) => vec![self.record(dataset)],
+        };
+        for value in values {
+            if matches!(self.format, ExportFormat::Json) {
+                self.write_json_element(&value, "  ")?;
+            } else if self.projection.is_some() {
+                writeln!(self.out, "{}", format_output(&value))?;
+            } else {
+                writeln!(self.out, "{}", serde_json::to_string(&value)?)?;
+            }
+        }
+        Ok(())
+    }
+
+    /// Writes an element of a JSON array whose elements are indented by
+    /// `indent`, laid out as `serde_json::to_string_pretty` lays out the
+    /// whole array.
+    fn write_json_element(
+        &mut self,
+        value: &serde_json::Value,
+        indent: &str,
+    ) -> anyhow::Result<()> {
+        let separator = if self.elements == 0 { "\n" } else { ",\n" };
+        self.out.write_all(separator.as_bytes())?;
+        let pretty = serde_json::to_string_pretty(value)?;
+        for (i, line) in pretty.lines().enumerate() {
+            if i > 0 {
+                self.out.write_all(b"\n")?;
+            }
+            write!(self.out, "{}{}", indent, line)?;
+        }
+        self.elements += 1;
+        Ok(())
+    }
 
-    for dataset in datasets {
+    fn write_csv(&mut self, dataset: &Dataset) -> anyhow::Result<()> {
         let description = dataset
             .description
             .as_ref()
             .map(|d| escape_csv(d))
             .unwrap_or_default();
 
-        println!(
+        writeln!(
+            self.out,
             "{},{},{},{},{},{},{},{}",
             dataset.id,
             escape_csv(&dataset.original_id),
@@ -588,23 +5989,123 @@ fn export_csv(datasets: &[Dataset]) -> anyhow::Result<()> {
             description,
             dataset.first_seen_at.format("%Y-%m-%dT%H:%M:%SZ"),
             dataset.last_updated_at.format("%Y-%m-%dT%H:%M:%SZ"),
-        );
+        )?;
+        Ok(())
+    }
+
+    /// Closes the JSON array or DCAT catalog, flushes the output and
+    /// returns it.
+    fn finish(mut self) -> anyhow::Result<W> {
+        match self.format {
+            ExportFormat::Json => {
+                if self.elements > 0 {
+                    self.out.write_all(b"\n")?;
+                }
+                writeln!(self.out, "]")?;
+            }
+            ExportFormat::Dcat => {
+                if self.elements > 0 {
+                    self.out.write_all(b"\n  ")?;
+                }
+                writeln!(self.out, "]\n}}")?;
+            }
+            ExportFormat::Csv | ExportFormat::Jsonl => {}
+        }
+        self.out.flush()?;
+        Ok(self.out)
+    }
+}
+
+/// A dataset as exported, shown by `ceres show` and served by the API.
+#[derive(Debug, Serialize, ToSchema)]
+struct DatasetRecord {
+    id: uuid::Uuid,
+    /// Dataset ID on the source portal
+    original_id: String,
+    /// Source portal URL
+    source_portal: String,
+    /// Dataset page on the source portal
+    url: String,
+    title: String,
+    description: Option<String>,
+    /// CKAN package metadata as harvested
+    #[schema(value_type = Object)]
+    metadata: serde_json::Value,
+    /// Normalized resource formats
+    formats: Vec<String>,
+    /// Metadata quality score, from 0 to 1
+    quality: Option<f32>,
+    first_seen_at: DateTime<Utc>,
+    last_updated_at: DateTime<Utc>,
+}
+
+impl DatasetRecord {
+    fn new(dataset: &Dataset) -> Self {
+        Self {
+            id: dataset.id,
+            original_id: dataset.original_id.clone(),
+            source_portal: dataset.source_portal.clone(),
+            url: dataset.url.clone(),
+            title: dataset.title.clone(),
+            description: dataset.description.clone(),
+            metadata: dataset.metadata.0.clone(),
+            formats: dataset.formats.clone(),
+            quality: dataset.quality,
+            first_seen_at: dataset.first_seen_at,
+            last_updated_at: dataset.last_updated_at,
+        }
     }
-    Ok(())
 }
 
 fn create_export_record(dataset: &Dataset) -> serde_json::Value {
-    serde_json::json!({
-        "id": dataset.id,
-        "original_id": dataset.original_id,
-        "source_portal": dataset.source_portal,
-        "url": dataset.url,
-        "title": dataset.title,
-        "description": dataset.description,
-        "metadata": dataset.metadata,
-        "first_seen_at": dataset.first_seen_at,
-        "last_updated_at": dataset.last_updated_at
-    })
+    serde_json::to_value(DatasetRecord::new(dataset)).expect("dataset records serialize to JSON")
+}
+
+/// Upserts the datasets of a JSON Lines dump written by `ceres export`.
+async fn import(
+    store: &dyn DatasetStore,
+    path: &Path,
+    hash_fields: &[HashField],
+) -> anyhow::Result<()> {
+    let reader = open_dump(path)?;
+    // Every embedding must fit the column (PostgreSQL) or the first one stored
+    let mut dimension = store.embedding_dimension().await?;
+
+    info!("Importing datasets from {}...", path.display());
+    let progress = dataset_counter("imported");
+    let (mut imported, mut embedded) = (0, 0);
+    for record in read_records(reader) {
+        let dataset = record?.into_new_dataset(hash_fields);
+        if let Some(embedding) = &dataset.embedding {
+            let len = embedding.as_slice().len();
+            let expected = *dimension.get_or_insert(len);
+            if len != expected {
+                anyhow::bail!(
+                    "Dataset {} has a {}-dimensional embedding but the database stores {} dimensions",
+                    dataset.original_id,
+                    len,
+                    expected
+                );
+            }
+            embedded += 1;
+        }
+        store
+            .upsert(&dataset)
+            .await
+            .with_context(|| format!("Failed to import dataset {}", dataset.original_id))?;
+        imported += 1;
+        progress.inc(1);
+    }
+    progress.finish_and_clear();
+
+    info!(
+        "Imported {} datasets ({} with embeddings)",
+        imported, embedded
+    );
+    if embedded < imported {
+        info!("Datasets without embeddings are embedded by the next harvest of their portal");
+    }
+    Ok(())
 }
 
 fn escape_csv(s: &str) -> String {
@@ -618,6 +6119,7 @@ fn escape_csv(s: &str) -> String {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use ceres_db::MemoryStore;
 
     #[test]
     fn test_create_similarity_bar_full() {
@@ -637,6 +6139,12 @@ mod tests {
         assert_eq!(bar, "[░░░░░░░░░░]");
     }
 
+    #[test]
+    fn test_create_similarity_bar_out_of_range() {
+        assert_eq!(create_similarity_bar(3.7), "[██████████]");
+        assert_eq!(create_similarity_bar(-2.0), "[░░░░░░░░░░]");
+    }
+
     #[test]
     fn test_truncate_text_short() {
         let text = "Short text";
@@ -658,6 +6166,15 @@ mod tests {
         assert_eq!(result, "Line 1 Line 2 Line 3");
     }
 
+    #[test]
+    fn test_truncate_text_multibyte() {
+        // Cut inside no character, counting characters rather than bytes
+        let text = "Qualità dell'aria è già più alta";
+        assert_eq!(truncate_text(text, 7), "Qualità...");
+        assert_eq!(truncate_text("città", 5), "città");
+        assert_eq!(truncate_text("🚌🚌🚌", 2), "🚌🚌...");
+    }
+
     #[test]
     fn test_escape_csv_simple() {
         assert_eq!(escape_csv("simple"), "simple");
@@ -678,6 +6195,99 @@ mod tests {
         assert_eq!(escape_csv("line1\nline2"), "\"line1\nline2\"");
     }
 
+    fn search_result(title: &str, score: f32) -> SearchResult {
+        SearchResult {
+            dataset: Dataset {
+                id: uuid::Uuid::nil(),
+                original_id: "x".to_string(),
+                source_portal: "https://dati.gov.it/".to_string(),
+                url: "https://dati.gov.it/dataset/x".to_string(),
+                title: title.to_string(),
+                description: None,
+                embedding: None,
+                metadata: sqlx::types::Json(serde_json::json!({})),
+                formats: vec!["CSV".to_string(), "JSON".to_string()],
+                first_seen_at: Utc::now(),
+                last_updated_at: Utc::now(),
+                content_hash: None,
+                embedding_model: None,
+                embedded_at: None,
+                modified_at: None,
+                language: None,
+                quality: None,
+            },
+            similarity_score: score,
+        }
+    }
+
+    #[test]
+    fn test_export_writer_json_matches_pretty_array() {
+        let datasets = [
+            search_result("Bilancio 2024", 0.0).dataset,
+            search_result("Bilancio 2023", 0.0).dataset,
+        ];
+        let mut out = Vec::new();
+        let mut writer = ExportWriter::new(&mut out, ExportFormat::Json, None).unwrap();
+        for dataset in &datasets {
+            writer.write(dataset).unwrap();
+        }
+        writer.finish().unwrap();
+
+        let records: Vec<_> = datasets.iter().map(create_export_record).collect();
+        let expected = serde_json::to_string_pretty(&records).unwrap() + "\n";
+        assert_eq!(String::from_utf8(out).unwrap(), expected);
+
+        let mut empty = Vec::new();
+        let projection = JqFilter::parse("select(false)").unwrap();
+        let mut writer =
+            ExportWriter::new(&mut empty, ExportFormat::Json, Some(&projection)).unwrap();
+        writer.write(&datasets[0]).unwrap();
+        writer.finish().unwrap();
+        assert_eq!(String::from_utf8(empty).unwrap(), "[]\n");
+    }
+
+    #[test]
+    fn test_export_writer_dcat_catalog() {
+        let dataset = search_result("Bilancio 2024", 0.0).dataset;
+        for count in [0, 2] {
+            let mut out = Vec::new();
+            let mut writer = ExportWriter::new(&mut out, ExportFormat::Dcat, None).unwrap();
+            for _ in 0..count {
+                writer.write(&dataset).unwrap();
+            }
+            writer.finish().unwrap();
+
+            let catalog: serde_json::Value = serde_json::from_slice(&out).unwrap();
+            assert_eq!(catalog["@type"], "dcat:Catalog");
+            assert_eq!(catalog["dcat:dataset"].as_array().unwrap().len(), count);
+        }
+    }
+
+    #[test]
+    fn test_format_results_csv() {
+        let csv = format_results_csv(&[search_result("Bilancio, 2024", 0.87654)]);
+        assert_eq!(
+            csv,
+            "rank,score,id,title,url,source_portal,formats,modified_at\n\
+             1,0.8765,00000000-0000-0000-0000-000000000000,\"Bilancio, 2024\",\
+             https://dati.gov.it/dataset/x,https://dati.gov.it/,CSV;JSON,\n"
+        );
+    }
+
+    #[test]
+    fn test_format_results_table() {
+        let table = format_results_table(&[
+            search_result("Orari\nautobus", 0.9),
+            search_result(&"x".repeat(70), 0.456),
+        ]);
+        let lines: Vec<&str> = table.lines().collect();
+        assert_eq!(lines.len(), 3);
+        assert!(lines[0].starts_with("#  SCORE  TITLE"));
+        assert!(lines[1].starts_with("1  90%    Orari autobus"));
+        assert!(lines[1].ends_with("dati.gov.it  CSV,JSON"));
+        assert!(lines[2].contains(&format!("{}...", "x".repeat(57))));
+    }
+
     #[test]
     fn test_atomic_sync_stats_new() {
         let stats = AtomicSyncStats::new();
@@ -703,6 +6313,19 @@ mod tests {
         assert_eq!(result.failed, 1);
     }
 
+    #[test]
+    fn test_atomic_sync_stats_cache_lookups() {
+        let stats = AtomicSyncStats::new();
+        stats.record_cache_lookup(true);
+        stats.record_cache_lookup(true);
+        stats.record_cache_lookup(false);
+
+        let result = stats.to_stats();
+        assert_eq!(result.cache_hits, 2);
+        assert_eq!(result.cache_misses, 1);
+        assert_eq!(result.total(), 0);
+    }
+
     #[test]
     fn test_atomic_sync_stats_multiple_records() {
         let stats = AtomicSyncStats::new();
@@ -719,4 +6342,549 @@ mod tests {
         assert_eq!(result.total(), 15);
         assert_eq!(result.successful(), 15);
     }
+
+    #[test]
+    fn test_parse_id_list() {
+        let content = "# exported ids\n\
+            0b7e2c9a-4f7e-4c2a-9d8e-3a1f5b6c7d8e\n\
+            \n\
+              6f1d1f3c-2b7a-4f61-9a3e-8c2b5d4e3f21  \n";
+        let ids = parse_id_list(content).unwrap();
+        assert_eq!(ids.len(), 2);
+        assert_eq!(ids[1].to_string(), "6f1d1f3c-2b7a-4f61-9a3e-8c2b5d4e3f21");
+    }
+
+    #[test]
+    fn test_parse_id_list_reports_line() {
+        let err = parse_id_list("0b7e2c9a-4f7e-4c2a-9d8e-3a1f5b6c7d8e\nnot-a-uuid\n").unwrap_err();
+        assert!(err.to_string().contains("line 2"));
+    }
+
+    /// Embedder returning a fixed vector and counting calls.
+    #[derive(Default)]
+    struct CountingEmbedder {
+        calls: AtomicUsize,
+    }
+
+    #[async_trait::async_trait]
+    impl EmbeddingProvider for CountingEmbedder {
+        fn model_id(&self) -> &str {
+            "test-model"
+        }
+
+        fn dimension(&self) -> usize {
+            2
+        }
+
+        fn for_model(&self, _model: &str) -> Arc<dyn EmbeddingProvider> {
+            Arc::new(CountingEmbedder::default())
+        }
+
+        async fn embed(&self, _text: &str) -> Result<Vec<f32>, AppError> {
+            self.calls.fetch_add(1, Ordering::Relaxed);
+            Ok(vec![1.0, 0.0])
+        }
+    }
+
+    #[tokio::test]
+    async fn test_embed_cached_reuses_vectors() {
+        let store = MemoryStore::new();
+        let embedder = CountingEmbedder::default();
+        let stats = AtomicSyncStats::new();
+
+        for _ in 0..2 {
+            let vector = embed_cached(&store, &embedder, "hash", "Fermate autobus", &stats)
+                .await
+                .unwrap();
+            assert_eq!(vector.as_slice(), &[1.0, 0.0]);
+        }
+        assert_eq!(embedder.calls.load(Ordering::Relaxed), 1);
+        let stats = stats.to_stats();
+        assert_eq!((stats.cache_hits, stats.cache_misses), (1, 1));
+    }
+
+    /// Embedder whose embeddings each take up a permit of `gate`.
+    struct GatedEmbedder {
+        gate: Arc<tokio::sync::Semaphore>,
+    }
+
+    #[async_trait::async_trait]
+    impl EmbeddingProvider for GatedEmbedder {
+        fn model_id(&self) -> &str {
+            "test-model"
+        }
+
+        fn dimension(&self) -> usize {
+            2
+        }
+
+        fn for_model(&self, _model: &str) -> Arc<dyn EmbeddingProvider> {
+            // Shares the gate, so releasing permits lets either through
+            Arc::new(GatedEmbedder {
+                gate: self.gate.clone(),
+            })
+        }
+
+        async fn embed(&self, _text: &str) -> Result<Vec<f32>, AppError> {
+            self.gate.acquire().await.unwrap().forget();
+            Ok(vec![1.0, 0.0])
+        }
+    }
+
+    /// Embedder failing its first `failures` calls, as a rate-limited
+    /// provider would.
+    struct FlakyEmbedder {
+        failures: AtomicUsize,
+    }
+
+    #[async_trait::async_trait]
+    impl EmbeddingProvider for FlakyEmbedder {
+        fn model_id(&self) -> &str {
+            "test-model"
+        }
+
+        fn dimension(&self) -> usize {
+            2
+        }
+
+        fn for_model(&self, _model: &str) -> Arc<dyn EmbeddingProvider> {
+            Arc::new(FlakyEmbedder {
+                failures: AtomicUsize::new(self.failures.load(Ordering::SeqCst)),
+            })
+        }
+
+        async fn embed(&self, _text: &str) -> Result<Vec<f32>, AppError> {
+            match self
+                .failures
+                .fetch_update(Ordering::SeqCst, Ordering::SeqCst, |n| n.checked_sub(1))
+            {
+                Ok(_) => Err(AppError::RateLimitExceeded),
+                Err(_) => Ok(vec![1.0, 0.0]),
+            }
+        }
+    }
+
+    /// Serves a CKAN portal of `count` datasets on a local port, returning
+    /// its URL and the number of `package_show` requests it answered.
+    async fn mock_ckan(count: usize) -> (String, Arc<AtomicUsize>) {
+        use axum::extract::{Query, State};
+        use axum::routing::get;
+        use serde_json::json;
+
+        let ids: Vec<String> = (0..count).map(|i| format!("dataset-{:05}", i)).collect();
+        let shown = Arc::new(AtomicUsize::new(0));
+        let app = axum::Router::new()
+            .route(
+                "/api/3/action/status_show",
+                get(|| async {
+                    axum::Json(json!({"success": true, "result": {"ckan_version": "2.10.4"}}))
+                }),
+            )
+            .route(
+                "/api/3/action/package_search",
+                get(move || async move {
+                    axum::Json(json!({"success": true, "result": {"count": count, "results": []}}))
+                }),
+            )
+            .route(
+                "/api/3/action/package_list",
+                get(move || async move { axum::Json(json!({"success": true, "result": ids})) }),
+            )
+            .route(
+                "/api/3/action/package_show",
+                get(
+                    |State(shown): State<Arc<AtomicUsize>>,
+                     Query(params): Query<HashMap<String, String>>| async move {
+                        shown.fetch_add(1, Ordering::SeqCst);
+                        let id = &params["id"];
+                        axum::Json(json!({
+                            "success": true,
+                            "result": {"id": id, "name": id, "title": format!("Dataset {}", id)},
+                        }))
+                    },
+                ),
+            )
+            .with_state(Arc::clone(&shown));
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let url = format!("http://{}", listener.local_addr().unwrap());
+        tokio::spawn(async move { axum::serve(listener, app).await.unwrap() });
+        (url, shown)
+    }
+
+    /// Settings of harvests against [`mock_ckan`], which has no robots.txt.
+    fn test_settings() -> Settings {
+        Settings {
+            http: HttpConfig {
+                ignore_robots_txt: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        }
+    }
+
+    #[tokio::test]
+    async fn test_sync_portal_applies_backpressure() {
+        let total = 2000;
+        let (url, shown) = mock_ckan(total).await;
+        let store = MemoryStore::new();
+        let gated = Arc::new(GatedEmbedder {
+            gate: Arc::new(tokio::sync::Semaphore::new(0)),
+        });
+        let embedder: Arc<dyn EmbeddingProvider> = gated.clone();
+        let settings = test_settings();
+        let harvest = sync_portal(
+            &store, &embedder, &settings, &url, None, false, None, None, None, false,
+        );
+        tokio::pin!(harvest);
+
+        // With embedding stalled, fetching stops once the stages ahead of it
+        // are full rather than buffering the whole portal
+        tokio::select! {
+            _ = &mut harvest => panic!("the harvest ended with embedding stalled"),
+            _ = tokio::time::sleep(Duration::from_secs(2)) => {}
+        }
+        let fetched = shown.load(Ordering::SeqCst);
+        assert!(fetched > 0 && fetched < total / 2, "{} fetched", fetched);
+
+        gated.gate.add_permits(total);
+        let stats = harvest.await.unwrap();
+        assert_eq!(stats.created, total);
+        assert_eq!(shown.load(Ordering::SeqCst), total);
+        assert_eq!(
+            store.count_portal_datasets(&url).await.unwrap(),
+            total as i64
+        );
+    }
+
+    #[tokio::test]
+    async fn test_log_daemon_result_frees_panicked_portal() {
+        let mut tasks: JoinSet<(String, anyhow::Result<SyncStats>)> = JoinSet::new();
+        let mut running = HashMap::new();
+        let task = tasks.spawn(async { panic!("harvest bug") });
+        running.insert(task.id(), "milano".to_string());
+        let task = tasks.spawn(async { ("torino".to_string(), Ok(SyncStats::new())) });
+        running.insert(task.id(), "torino".to_string());
+
+        while let Some(joined) = tasks.join_next_with_id().await {
+            log_daemon_result(joined, &mut running);
+        }
+        assert!(running.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_retry_failed_reembeds_after_embedding_failure() {
+        let (url, _) = mock_ckan(1).await;
+        let store = MemoryStore::new();
+        let title = "Dataset dataset-00000";
+        let id = store
+            .upsert(&NewDataset {
+                original_id: "dataset-00000".to_string(),
+                source_portal: url.clone(),
+                url: format!("{}/dataset/dataset-00000", url),
+                title: title.to_string(),
+                description: None,
+                embedding: Some(Vector::from(vec![0.0, 1.0])),
+                embedding_model: Some("test-model".to_string()),
+                metadata: serde_json::json!({}),
+                formats: Vec::new(),
+                content_hash: "outdated".to_string(),
+                modified_at: None,
+                language: None,
+                bbox: None,
+                quality: None,
+                tags: Vec::new(),
+                organization: None,
+                resources: Vec::new(),
+            })
+            .await
+            .unwrap()
+            .id();
+        let embedder: Arc<dyn EmbeddingProvider> = Arc::new(FlakyEmbedder {
+            failures: AtomicUsize::new(1),
+        });
+        let settings = test_settings();
+        let stored_hash = || async {
+            store
+                .get_hashes_for_portal(&url, "test-model")
+                .await
+                .unwrap()
+                .remove("dataset-00000")
+                .flatten()
+        };
+
+        // The failed embedding leaves the stored hash behind, so the
+        // dataset still differs from the portal
+        let stats = sync_portal(
+            &store, &embedder, &settings, &url, None, false, None, None, None, false,
+        )
+        .await
+        .unwrap();
+        assert_eq!(stats.failed, 1);
+        assert_eq!(stored_hash().await.as_deref(), Some("outdated"));
+        assert_eq!(
+            store.list_harvest_failures(Some(&url)).await.unwrap().len(),
+            1
+        );
+
+        let stats = sync_portal(
+            &store, &embedder, &settings, &url, None, false, None, None, None, true,
+        )
+        .await
+        .unwrap();
+        assert_eq!(stats.updated, 1);
+        assert_ne!(stored_hash().await.as_deref(), Some("outdated"));
+        let dataset = store
+            .datasets_by_ids(&[id], &SearchFilters::default())
+            .await
+            .unwrap()
+            .remove(0);
+        assert_eq!(dataset.embedding.unwrap().as_slice(), &[1.0, 0.0]);
+        assert!(store
+            .list_harvest_failures(Some(&url))
+            .await
+            .unwrap()
+            .is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_embed_failure_outlives_later_harvests() {
+        let (url, _) = mock_ckan(1).await;
+        let store = MemoryStore::new();
+        let title = "Dataset dataset-00000";
+        store
+            .upsert(&NewDataset {
+                original_id: "dataset-00000".to_string(),
+                source_portal: url.clone(),
+                url: format!("{}/dataset/dataset-00000", url),
+                title: title.to_string(),
+                description: None,
+                embedding: Some(Vector::from(vec![0.0, 1.0])),
+                embedding_model: Some("test-model".to_string()),
+                metadata: serde_json::json!({}),
+                formats: Vec::new(),
+                content_hash: "outdated".to_string(),
+                modified_at: None,
+                language: None,
+                bbox: None,
+                quality: None,
+                tags: Vec::new(),
+                organization: None,
+                resources: Vec::new(),
+            })
+            .await
+            .unwrap();
+        let embedder: Arc<dyn EmbeddingProvider> = Arc::new(FlakyEmbedder {
+            failures: AtomicUsize::new(2),
+        });
+        let settings = test_settings();
+
+        // A full harvest that fails the dataset again keeps its failure
+        for attempts in 1..=2 {
+            let stats = sync_portal(
+                &store, &embedder, &settings, &url, None, false, None, None, None, false,
+            )
+            .await
+            .unwrap();
+            assert_eq!(stats.failed, 1);
+            let failures = store.list_harvest_failures(Some(&url)).await.unwrap();
+            assert_eq!(failures.len(), 1);
+            assert_eq!(failures[0].stage, "embed");
+            assert_eq!(failures[0].attempts, attempts);
+        }
+        let hashes = store
+            .get_hashes_for_portal(&url, "test-model")
+            .await
+            .unwrap();
+        assert_eq!(hashes["dataset-00000"].as_deref(), Some("outdated"));
+    }
+
+    #[tokio::test]
+    async fn test_sync_portal_resumes_from_checkpoint() {
+        let total = 1000;
+        let (url, shown) = mock_ckan(total).await;
+        let store = MemoryStore::new();
+        let gated = Arc::new(GatedEmbedder {
+            gate: Arc::new(tokio::sync::Semaphore::new(250)),
+        });
+        let embedder: Arc<dyn EmbeddingProvider> = gated.clone();
+        let settings = test_settings();
+        let mut harvest = Box::pin(sync_portal(
+            &store, &embedder, &settings, &url, None, false, None, None, None, false,
+        ));
+
+        // The harvest dies once it has stored a page or two, before
+        // embedding the rest
+        let checkpoint = tokio::select! {
+            _ = &mut harvest => panic!("the harvest ended with embedding stalled"),
+            checkpoint = async {
+                loop {
+                    if let Some(checkpoint) = store.get_harvest_checkpoint(&url).await.unwrap() {
+                        break checkpoint;
+                    }
+                    tokio::time::sleep(Duration::from_millis(10)).await;
+                }
+            } => checkpoint,
+        };
+        drop(harvest);
+        assert!(checkpoint.offset >= CHECKPOINT_INTERVAL && checkpoint.offset < total);
+        let stored = store.count_portal_datasets(&url).await.unwrap() as usize;
+        assert!(stored >= checkpoint.offset);
+
+        // The next harvest fetches only the datasets past the checkpoint,
+        // once the requests the dead one had in flight are through
+        tokio::time::sleep(Duration::from_millis(200)).await;
+        let fetched = shown.load(Ordering::SeqCst);
+        gated.gate.add_permits(total);
+        let stats = sync_portal(
+            &store, &embedder, &settings, &url, None, false, None, None, None, false,
+        )
+        .await
+        .unwrap();
+        assert_eq!(
+            shown.load(Ordering::SeqCst) - fetched,
+            total - checkpoint.offset
+        );
+        assert_eq!(stats.total(), total - checkpoint.offset);
+        assert_eq!(
+            store.count_portal_datasets(&url).await.unwrap(),
+            total as i64
+        );
+        assert!(store.get_harvest_checkpoint(&url).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_run_search_text_mode() {
+        let store = MemoryStore::new();
+        for (id, title) in [("bus", "Fermate autobus"), ("aria", "Qualità dell'aria")] {
+            store
+                .upsert(&NewDataset {
+                    original_id: id.to_string(),
+                    source_portal: "https://dati.comune.milano.it".to_string(),
+                    url: format!("https://dati.comune.milano.it/dataset/{}", id),
+                    title: title.to_string(),
+                    description: None,
+                    embedding: None,
+                    embedding_model: None,
+                    metadata: serde_json::json!({}),
+                    formats: Vec::new(),
+                    content_hash: NewDataset::compute_content_hash(title, None),
+                    modified_at: None,
+                    language: None,
+                    bbox: None,
+                    quality: None,
+                    tags: Vec::new(),
+                    organization: None,
+                    resources: Vec::new(),
+                })
+                .await
+                .unwrap();
+        }
+
+        let filters = SearchFilters::default();
+        let options = SearchOptions {
+            limit: 10,
+            filters: &filters,
+            strategy: SearchStrategy::Auto,
+            mode: SearchModeArg::Text,
+            chunks: false,
+            rerank: None,
+            quality_weight: 0.0,
+            expander: None,
+            output: SearchOutputArg::Text,
+            history: None,
+        };
+        let results = run_search(&store, None, "autobus", 10, &options)
+            .await
+            .unwrap();
+        assert_eq!(results.len(), 1);
+        assert_eq!(results[0].dataset.title, "Fermate autobus");
+
+        let semantic = SearchOptions {
+            mode: SearchModeArg::Semantic,
+            ..options
+        };
+        assert!(run_search(&store, None, "autobus", 10, &semantic)
+            .await
+            .is_err());
+    }
+
+    #[test]
+    fn test_settings_env_vars() {
+        let vars = settings_env_vars(&Config::command());
+        for var in [
+            "DATABASE_URL",
+            "DB_MAX_CONNECTIONS",
+            "HTTP_TIMEOUT",
+            "HTTP_MAX_RETRIES",
+            "SYNC_CONCURRENCY",
+            "SYNC_MAX_CONCURRENCY",
+            "SYNC_HASH_FIELDS",
+            "EMBEDDING_PROVIDER",
+        ] {
+            assert!(vars.contains(var), "{} is not settable", var);
+        }
+        assert!(!vars.contains("RUST_LOG"));
+    }
+
+    #[test]
+    fn test_config_from_env_values() {
+        let values = HashMap::from([
+            ("DATABASE_URL".to_string(), "sqlite://ceres.db".to_string()),
+            ("SYNC_CONCURRENCY".to_string(), "4".to_string()),
+            ("SYNC_HASH_FIELDS".to_string(), "resources,tags".to_string()),
+            ("CERES_IGNORE_ROBOTS_TXT".to_string(), "1".to_string()),
+            ("CERES_INSECURE_SKIP_VERIFY".to_string(), "off".to_string()),
+        ]);
+        let config = config_from(["ceres", "stats"], &values).unwrap();
+        let settings = resolve_settings(&config);
+        assert_eq!(config.database_url, "sqlite://ceres.db");
+        assert_eq!(settings.sync.concurrency, 4);
+        assert_eq!(
+            settings.sync.hash_fields,
+            [HashField::Resources, HashField::Tags]
+        );
+        assert!(settings.http.ignore_robots_txt);
+        assert!(!settings.http.insecure_skip_verify);
+
+        // Flags still win over settings
+        let config = config_from(["ceres", "stats", "--concurrency", "8"], &values).unwrap();
+        assert_eq!(resolve_settings(&config).sync.concurrency, 8);
+    }
+
+    #[tokio::test]
+    async fn test_install_bundle_requires_registry_key() {
+        let config = std::env::temp_dir().join("ceres-install-unsigned.toml");
+        // Refused before anything is fetched
+        let err = install_bundle(
+            Some(config),
+            "italy-regions",
+            "http://127.0.0.1:9/registry",
+            None,
+            false,
+            &HttpConfig::default(),
+        )
+        .await
+        .unwrap_err();
+        assert!(err.to_string().contains("--allow-unsigned"));
+    }
+
+    #[test]
+    fn test_needs_embedder() {
+        let values = HashMap::from([(
+            "DATABASE_URL".to_string(),
+            "postgresql://localhost/ceres".to_string(),
+        )]);
+        let needs = |args: &[&str]| needs_embedder(&config_from(args, &values).unwrap());
+
+        assert!(needs(&["ceres", "harvest"]));
+        assert!(needs(&["ceres", "search", "bus"]));
+        assert!(!needs(&["ceres", "search", "bus", "--mode", "text"]));
+        assert!(needs(&["ceres", "index", "resize"]));
+        for command in ["stats", "export", "top-tags", "maintain"] {
+            assert!(!needs(&["ceres", command]), "{}", command);
+        }
+        assert!(!needs(&["ceres", "orgs", "list"]));
+        assert!(!needs(&["ceres", "portals", "list"]));
+        assert!(!needs(&["ceres", "index", "tune"]));
+    }
 }
@@ -0,0 +1,91 @@
+//! NDJSON log of per-dataset harvest outcomes (`ceres harvest --outcome-log`).
+
+use std::fs::{File, OpenOptions};
+use std::io::{LineWriter, Write};
+use std::path::Path;
+use std::sync::Mutex;
+
+use anyhow::Context;
+use ceres_core::DatasetOutcomeRecord;
+use tracing::warn;
+
+/// Appends one JSON line per processed dataset to a file.
+///
+/// Records are written as they complete, so the log is usable while a
+/// harvest is still running and survives interrupted runs.
+pub struct OutcomeLog {
+    writer: Mutex<LineWriter<File>>,
+}
+
+impl OutcomeLog {
+    /// Opens `path` for appending, creating it if needed.
+    pub fn open(path: &Path) -> anyhow::Result<Self> {
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(path)
+            .with_context(|| format!("Failed to open outcome log {}", path.display()))?;
+
+        Ok(Self {
+            writer: Mutex::new(LineWriter::new(file)),
+        })
+    }
+
+    /// Appends a record.
+    ///
+    /// Write errors are logged and never fail the harvest.
+    pub fn record(&self, record: &DatasetOutcomeRecord) {
+        let mut line = match serde_json::to_string(record) {
+            Ok(line) => line,
+            Err(e) => {
+                warn!("Failed to serialize outcome of {}: {}", record.id, e);
+                return;
+            }
+        };
+        line.push('\n');
+
+        let mut writer = self.writer.lock().unwrap_or_else(|e| e.into_inner());
+        if let Err(e) = writer.write_all(line.as_bytes()) {
+            warn!("Failed to write outcome log: {}", e);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ceres_core::{StageDurations, SyncOutcome};
+    use chrono::Utc;
+
+    fn record(id: &str) -> DatasetOutcomeRecord {
+        DatasetOutcomeRecord {
+            timestamp: Utc::now(),
+            portal: "https://dati.comune.milano.it".to_string(),
+            id: id.to_string(),
+            outcome: SyncOutcome::Unchanged,
+            durations: StageDurations::default(),
+            error: None,
+        }
+    }
+
+    #[test]
+    fn test_record_appends_lines() {
+        let path =
+            std::env::temp_dir().join(format!("ceres-outcomes-{}.ndjson", uuid::Uuid::new_v4()));
+
+        let log = OutcomeLog::open(&path).unwrap();
+        log.record(&record("a"));
+        drop(log);
+        let log = OutcomeLog::open(&path).unwrap();
+        log.record(&record("b"));
+        drop(log);
+
+        let content = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        let ids: Vec<String> = content
+            .lines()
+            .map(|l| serde_json::from_str::<serde_json::Value>(l).unwrap()["id"].to_string())
+            .collect();
+        assert_eq!(ids, vec!["\"a\"", "\"b\""]);
+    }
+}
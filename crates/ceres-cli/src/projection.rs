@@ -0,0 +1,159 @@
+//! jq-style projections for the `--jq` option of `show` and `export`.
+//!
+//! Filters use jq syntax and are evaluated in-process with jaq, so no
+//! external `jq` binary is needed.
+
+use jaq_core::load::{self, Arena, File, Loader};
+use jaq_core::{Compiler, Ctx, Native, RcIter};
+use jaq_json::Val;
+use serde_json::Value;
+
+/// A compiled jq filter.
+///
+/// # Examples
+///
+/// ```
+/// use ceres_search::projection::JqFilter;
+///
+/// let filter = JqFilter::parse(".metadata.resources[].format").unwrap();
+/// let record = serde_json::json!({
+///     "metadata": { "resources": [{ "format": "CSV" }, { "format": "JSON" }] }
+/// });
+/// assert_eq!(filter.apply(record).unwrap(), vec!["CSV", "JSON"]);
+/// ```
+pub struct JqFilter {
+    filter: jaq_core::Filter<Native<Val>>,
+}
+
+impl JqFilter {
+    /// Parses and compiles a jq filter, including the jq standard library.
+    ///
+    /// # Errors
+    ///
+    /// Returns an error describing the first syntax error or undefined symbol.
+    pub fn parse(code: &str) -> anyhow::Result<Self> {
+        let program = File { code, path: () };
+        let loader = Loader::new(jaq_std::defs().chain(jaq_json::defs()));
+        let arena = Arena::default();
+
+        let modules = loader
+            .load(&arena, program)
+            .map_err(|errs| anyhow::anyhow!("Invalid --jq filter: {}", describe_load(&errs)))?;
+
+        let filter = Compiler::default()
+            .with_funs(jaq_std::funs().chain(jaq_json::funs()))
+            .compile(modules)
+            .map_err(|errs| {
+                let names: Vec<&str> = errs
+                    .iter()
+                    .flat_map(|(_, errs)| errs.iter().map(|(name, _)| *name))
+                    .collect();
+                anyhow::anyhow!("Invalid --jq filter: undefined {}", names.join(", "))
+            })?;
+
+        Ok(Self { filter })
+    }
+
+    /// Runs the filter on `input` and returns all of its outputs.
+    ///
+    /// # Errors
+    ///
+    /// Returns the first runtime error, e.g. indexing a string with `.field`.
+    pub fn apply(&self, input: Value) -> anyhow::Result<Vec<Value>> {
+        let inputs = RcIter::new(core::iter::empty());
+        self.filter
+            .run((Ctx::new([], &inputs), Val::from(input)))
+            .map(|out| {
+                out.map(Value::from)
+                    .map_err(|e| anyhow::anyhow!("--jq filter failed: {}", e))
+            })
+            .collect()
+    }
+}
+
+/// Formats one filter output for line-oriented output.
+///
+/// Strings are printed raw (like `jq -r`) so they can be piped directly;
+/// everything else is printed as compact JSON.
+pub fn format_output(value: &Value) -> String {
+    match value {
+        Value::String(s) => s.clone(),
+        other => other.to_string(),
+    }
+}
+
+fn describe_load(errs: &load::Errors<&str, ()>) -> String {
+    let first = errs.iter().find_map(|(_, err)| match err {
+        load::Error::Io(errs) => errs.first().map(|(_, msg)| msg.clone()),
+        load::Error::Lex(errs) => errs
+            .first()
+            .map(|(expect, rest)| expected_at(expect.as_str(), rest)),
+        load::Error::Parse(errs) => errs
+            .first()
+            .map(|(expect, rest)| expected_at(expect.as_str(), rest)),
+    });
+    first.unwrap_or_else(|| "syntax error".to_string())
+}
+
+fn expected_at(expected: &str, rest: &str) -> String {
+    if rest.is_empty() {
+        format!("expected {} at end of filter", expected)
+    } else {
+        let near: String = rest.chars().take(20).collect();
+        format!("expected {} near `{}`", expected, near)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_projection_field() {
+        let filter = JqFilter::parse(".title").unwrap();
+        let out = filter.apply(json!({ "title": "Bilancio 2024" })).unwrap();
+        assert_eq!(out, vec![json!("Bilancio 2024")]);
+    }
+
+    #[test]
+    fn test_projection_object_construction() {
+        let filter = JqFilter::parse("{id, n: (.tags | length)}").unwrap();
+        let out = filter
+            .apply(json!({ "id": 1, "tags": ["a", "b"] }))
+            .unwrap();
+        assert_eq!(out, vec![json!({ "id": 1, "n": 2 })]);
+    }
+
+    #[test]
+    fn test_projection_std_functions() {
+        let filter = JqFilter::parse("[.[] | ascii_downcase] | unique").unwrap();
+        let out = filter.apply(json!(["CSV", "csv", "JSON"])).unwrap();
+        assert_eq!(out, vec![json!(["csv", "json"])]);
+    }
+
+    #[test]
+    fn test_projection_syntax_error() {
+        let err = JqFilter::parse(".metadata[").err().unwrap();
+        assert!(err.to_string().starts_with("Invalid --jq filter"));
+    }
+
+    #[test]
+    fn test_projection_undefined_function() {
+        let err = JqFilter::parse("nosuchfn").err().unwrap();
+        assert!(err.to_string().contains("nosuchfn"));
+    }
+
+    #[test]
+    fn test_projection_runtime_error() {
+        let filter = JqFilter::parse(".title.x").unwrap();
+        assert!(filter.apply(json!({ "title": "abc" })).is_err());
+    }
+
+    #[test]
+    fn test_format_output() {
+        assert_eq!(format_output(&json!("CSV")), "CSV");
+        assert_eq!(format_output(&json!({ "a": 1 })), r#"{"a":1}"#);
+        assert_eq!(format_output(&json!(null)), "null");
+    }
+}
@@ -0,0 +1,133 @@
+//! Secrets kept out of `.env` files.
+//!
+//! Any variable the CLI reads can instead name a file holding its value in
+//! `<NAME>_FILE`, as Docker and Kubernetes secrets are mounted, or be set to
+//! `keyring:` to read it from the OS keyring (`keyring` feature). Both are
+//! resolved before the CLI parses its flags and stand in for the variable,
+//! so clients only ever see the plain value.
+
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+
+use anyhow::Context;
+
+/// Keyring service under which Ceres' secrets are stored, one entry per
+/// variable name.
+#[cfg(feature = "keyring")]
+const KEYRING_SERVICE: &str = "ceres";
+
+const KEYRING_PREFIX: &str = "keyring:";
+
+/// Reads the `<NAME>_FILE` of each of `names` that is unset, returning the
+/// contents by variable name.
+pub fn resolve_files(names: &HashSet<String>) -> anyhow::Result<HashMap<String, String>> {
+    let mut values = HashMap::new();
+    for name in names {
+        let file_var = format!("{}_FILE", name);
+        let Some(path) = std::env::var_os(&file_var) else {
+            continue;
+        };
+        if std::env::var_os(name).is_some() {
+            anyhow::bail!("Both {} and {} are set; unset one of them", name, file_var);
+        }
+        let value = read_secret_file(Path::new(&path))
+            .with_context(|| format!("Failed to read {} from {}", name, file_var))?;
+        values.insert(name.clone(), value);
+    }
+    Ok(values)
+}
+
+/// Reads the OS keyring secret of each of `names` whose value, from
+/// `resolved` else the environment, is `keyring:` (or `keyring:<entry>`),
+/// returning the secrets by variable name.
+pub fn resolve_keyring(
+    names: &HashSet<String>,
+    resolved: &HashMap<String, String>,
+) -> anyhow::Result<HashMap<String, String>> {
+    let mut secrets = HashMap::new();
+    for name in names {
+        let Some(value) = resolved
+            .get(name)
+            .cloned()
+            .or_else(|| std::env::var(name).ok())
+        else {
+            continue;
+        };
+        if let Some(entry) = keyring_entry(name, &value) {
+            let secret = read_keyring(entry)
+                .with_context(|| format!("Failed to read {} from the OS keyring", name))?;
+            secrets.insert(name.clone(), secret);
+        }
+    }
+    Ok(secrets)
+}
+
+/// The keyring entry `value` refers to, if any: the name after `keyring:`,
+/// else the variable's own name.
+fn keyring_entry<'a>(name: &'a str, value: &'a str) -> Option<&'a str> {
+    let entry = value.strip_prefix(KEYRING_PREFIX)?;
+    Some(if entry.is_empty() { name } else { entry })
+}
+
+/// Reads a secret file, without the trailing newline editors add.
+fn read_secret_file(path: &Path) -> anyhow::Result<String> {
+    let content = std::fs::read_to_string(path)
+        .with_context(|| format!("Cannot read '{}'", path.display()))?;
+    Ok(content.trim_end_matches(['\n', '\r']).to_string())
+}
+
+#[cfg(feature = "keyring")]
+fn read_keyring(entry: &str) -> anyhow::Result<String> {
+    match keyring::Entry::new(KEYRING_SERVICE, entry).and_then(|e| e.get_password()) {
+        Ok(secret) => Ok(secret),
+        Err(keyring::Error::NoEntry) => anyhow::bail!(
+            "No secret stored for '{}' under service '{}'",
+            entry,
+            KEYRING_SERVICE
+        ),
+        Err(e) => Err(e).context("The OS keyring is unavailable"),
+    }
+}
+
+#[cfg(not(feature = "keyring"))]
+fn read_keyring(_entry: &str) -> anyhow::Result<String> {
+    anyhow::bail!(
+        "OS keyring support is not compiled in. Reinstall with: cargo install ceres-search --features keyring"
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_keyring_entry() {
+        assert_eq!(
+            keyring_entry("GEMINI_API_KEY", "keyring:"),
+            Some("GEMINI_API_KEY")
+        );
+        assert_eq!(
+            keyring_entry("GEMINI_API_KEY", "keyring:gemini-prod"),
+            Some("gemini-prod")
+        );
+        assert_eq!(keyring_entry("GEMINI_API_KEY", "AIza-key"), None);
+    }
+
+    #[test]
+    fn test_resolve_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("gemini_api_key");
+        std::fs::write(&path, "AIza-secret\n").unwrap();
+        assert_eq!(read_secret_file(&path).unwrap(), "AIza-secret");
+
+        let name = "CERES_TEST_SECRET".to_string();
+        std::env::set_var("CERES_TEST_SECRET_FILE", &path);
+        let values = resolve_files(&HashSet::from([name.clone()])).unwrap();
+        assert_eq!(values[&name], "AIza-secret");
+        assert!(std::env::var_os(&name).is_none());
+
+        // Set both ways, it is unclear which one is meant
+        std::env::set_var(&name, "AIza-other");
+        assert!(resolve_files(&HashSet::from([name])).is_err());
+    }
+}
@@ -0,0 +1,1050 @@
+//! HTTP JSON API served by `ceres serve`.
+//!
+//! Exposes search, single datasets, statistics and configured portals over
+//! the same store and embedding provider the CLI uses, with the same JSON
+//! shapes as `--json` output, and manages watches as `ceres watch` does. The OpenAPI document is generated from the
+//! handlers below and served at `/openapi.json`, with a Swagger UI at
+//! `/docs`. A small search page built on the API is served at `/`.
+
+use std::net::{IpAddr, SocketAddr};
+use std::path::PathBuf;
+use std::sync::Arc;
+
+use anyhow::Context;
+use axum::extract::rejection::{JsonRejection, QueryRejection};
+use axum::extract::{Path, Query, State};
+use axum::http::{header, HeaderMap, HeaderValue, Method, StatusCode};
+use axum::response::{Html, IntoResponse, Response};
+use axum::routing::{delete, get};
+use axum::{Json, Router};
+use chrono::{DateTime, Utc};
+use clap::ValueEnum;
+use serde::{Deserialize, Serialize};
+use tower_http::cors::CorsLayer;
+use tracing::{error, info};
+use url::{Host, Url};
+use utoipa::{IntoParams, OpenApi, ToSchema};
+use utoipa_swagger_ui::SwaggerUi;
+use uuid::Uuid;
+
+use ceres_client::EmbeddingProvider;
+use ceres_core::history::SearchSource;
+use ceres_core::language::parse_language;
+use ceres_core::quality::parse_quality_weight;
+use ceres_core::search::{parse_date_bound, SearchFilters, SearchStrategy};
+use ceres_core::spatial::parse_bbox;
+use ceres_core::tags::parse_tag;
+use ceres_core::watch::{is_public_address, Watch, DEFAULT_MIN_SCORE};
+use ceres_core::{load_portals_config, AppError, DatabaseStats, NewDataset, Settings};
+use ceres_db::DatasetStore;
+use ceres_search::{SearchModeArg, SearchOutputArg};
+
+use crate::{
+    portal_records, register_watch, search_hits, DatasetRecord, PortalRecord, SearchOptions,
+    SearchResponse,
+};
+
+/// Results returned by `/search` without a `limit`.
+const DEFAULT_SEARCH_LIMIT: usize = 10;
+
+/// Most results `/search` returns, whatever `limit` asks for.
+const MAX_SEARCH_LIMIT: usize = 100;
+
+/// Shared by every request handler, HTTP and gRPC.
+#[derive(Clone)]
+pub struct AppState {
+    pub store: Arc<dyn DatasetStore>,
+    /// Embeds queries; `None` limits search to `mode=text`
+    pub embedder: Option<Arc<dyn EmbeddingProvider>>,
+    /// Custom portals.toml path (`--config`)
+    pub portals_config: Option<PathBuf>,
+    /// Keep searches in the search history
+    pub record_searches: bool,
+    /// Tunables of harvests started over gRPC
+    #[cfg_attr(not(feature = "grpc"), allow(dead_code))]
+    pub settings: Settings,
+    /// Bearer token of the routes that change watches; `None` disables them
+    pub api_token: Option<String>,
+    /// Accept webhooks on addresses that are not public
+    pub allow_private_webhooks: bool,
+}
+
+impl AppState {
+    /// Runs a search as `ceres search --json` would, without reranking or
+    /// query expansion.
+    pub(crate) async fn search(
+        &self,
+        params: SearchParams,
+        source: SearchSource,
+    ) -> Result<SearchResponse, ApiError> {
+        let query = params
+            .q
+            .as_deref()
+            .map(str::trim)
+            .filter(|q| !q.is_empty())
+            .ok_or_else(|| ApiError::bad_request("Missing search query"))?;
+        let mode = params.mode()?;
+        let quality_weight = params
+            .quality_weight
+            .as_deref()
+            .map(parse_quality_weight)
+            .transpose()?
+            .unwrap_or(0.0);
+        let limit = params
+            .limit
+            .unwrap_or(DEFAULT_SEARCH_LIMIT)
+            .clamp(1, MAX_SEARCH_LIMIT);
+
+        // Keyword search embeds nothing, as with `ceres search --mode text`
+        let embedder = match mode {
+            SearchModeArg::Text => None,
+            _ => Some(self.embedder.as_deref().ok_or_else(|| {
+                ApiError::bad_request("No embedding provider configured; use mode=text")
+            })?),
+        };
+        let filters = SearchFilters {
+            embedding_model: embedder.map(|e| e.model_id().to_string()),
+            ..params.filters()?
+        };
+        let options = SearchOptions {
+            limit,
+            filters: &filters,
+            strategy: SearchStrategy::Auto,
+            mode,
+            chunks: false,
+            rerank: None,
+            quality_weight,
+            expander: None,
+            output: SearchOutputArg::Json,
+            history: self.record_searches.then_some(source),
+        };
+
+        let hits = search_hits(self.store.as_ref(), embedder, query, &options).await?;
+        Ok(hits.response())
+    }
+
+    /// Rejects requests without the API token as `Authorization: Bearer`.
+    fn authorize(&self, headers: &HeaderMap) -> Result<(), ApiError> {
+        let token = self.api_token.as_deref().ok_or_else(|| {
+            ApiError::forbidden(
+                "Managing watches is disabled; start the server with --api-token or CERES_API_TOKEN",
+            )
+        })?;
+        let given = headers
+            .get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "));
+        match given {
+            Some(given) if constant_time_eq(given.as_bytes(), token.as_bytes()) => Ok(()),
+            _ => Err(ApiError::unauthorized("Missing or wrong API token")),
+        }
+    }
+
+    /// Rejects webhooks other than http(s) URLs and, unless
+    /// `allow_private_webhooks`, those whose host resolves to an address
+    /// that is not public, so watches cannot make the harvester call
+    /// services on its own host or network.
+    async fn check_webhook(&self, webhook: &str) -> Result<(), ApiError> {
+        let invalid = |reason: &str| {
+            ApiError::bad_request(format!("Invalid webhook URL '{}': {}", webhook, reason))
+        };
+        let url = Url::parse(webhook).map_err(|e| invalid(&e.to_string()))?;
+        if !matches!(url.scheme(), "http" | "https") {
+            return Err(invalid("only http and https are supported"));
+        }
+        let addresses: Vec<IpAddr> = match url.host() {
+            Some(Host::Ipv4(ip)) => vec![ip.into()],
+            Some(Host::Ipv6(ip)) => vec![ip.into()],
+            Some(Host::Domain(domain)) if !self.allow_private_webhooks => {
+                let port = url.port_or_known_default().unwrap_or(443);
+                tokio::net::lookup_host((domain, port))
+                    .await
+                    .map_err(|_| invalid("its host does not resolve"))?
+                    .map(|address| address.ip())
+                    .collect()
+            }
+            Some(Host::Domain(_)) => Vec::new(),
+            None => return Err(invalid("no host")),
+        };
+        if !self.allow_private_webhooks && !addresses.iter().all(|&ip| is_public_address(ip)) {
+            return Err(invalid(
+                "it points to a loopback, private or link-local address (see --allow-private-webhooks)",
+            ));
+        }
+        Ok(())
+    }
+
+    /// The dataset with ID `id`, a UUID string.
+    pub(crate) async fn dataset(&self, id: &str) -> Result<DatasetRecord, ApiError> {
+        let id = Uuid::parse_str(id)
+            .map_err(|_| ApiError::bad_request(format!("Invalid dataset ID '{}'", id)))?;
+        let dataset = self
+            .store
+            .datasets_by_ids(&[id], &SearchFilters::default())
+            .await?
+            .into_iter()
+            .next()
+            .ok_or_else(|| ApiError::not_found(format!("Dataset {} not found", id)))?;
+        Ok(DatasetRecord::new(&dataset))
+    }
+}
+
+#[derive(OpenApi)]
+#[openapi(
+    info(
+        title = "Ceres",
+        description = "Semantic search over datasets harvested from open data portals."
+    ),
+    paths(
+        search,
+        dataset,
+        stats,
+        portals,
+        create_watch,
+        list_watches,
+        delete_watch
+    ),
+    modifiers(&ApiTokenScheme)
+)]
+pub struct ApiDoc;
+
+/// Declares the bearer token the watch routes require.
+struct ApiTokenScheme;
+
+impl utoipa::Modify for ApiTokenScheme {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        use utoipa::openapi::security::{Http, HttpAuthScheme, SecurityScheme};
+
+        openapi
+            .components
+            .get_or_insert_with(Default::default)
+            .add_security_scheme(
+                "api_token",
+                SecurityScheme::Http(Http::new(HttpAuthScheme::Bearer)),
+            );
+    }
+}
+
+/// Routes of the API and its documentation, without CORS.
+pub fn router(state: AppState) -> Router {
+    Router::new()
+        .route("/", get(index))
+        .route("/search", get(search))
+        .route("/datasets/{id}", get(dataset))
+        .route("/stats", get(stats))
+        .route("/portals", get(portals))
+        .route("/watches", get(list_watches).post(create_watch))
+        .route("/watches/{id}", delete(delete_watch))
+        .with_state(state)
+        .merge(SwaggerUi::new("/docs").url("/openapi.json", ApiDoc::openapi()))
+}
+
+/// Serves the API on `bind`, and the gRPC service on `grpc_bind` if set,
+/// until Ctrl-C.
+///
+/// Browsers may call the API from `allow_origins`; with none, no CORS
+/// headers are sent.
+pub async fn serve(
+    state: AppState,
+    bind: SocketAddr,
+    allow_origins: &[String],
+    grpc_bind: Option<SocketAddr>,
+) -> anyhow::Result<()> {
+    #[cfg(not(feature = "grpc"))]
+    if grpc_bind.is_some() {
+        anyhow::bail!(
+            "gRPC support is not compiled in. Reinstall with: cargo install ceres-search --features grpc"
+        );
+    }
+
+    let mut app = router(state.clone());
+    if !allow_origins.is_empty() {
+        let origins = allow_origins
+            .iter()
+            .map(|origin| {
+                origin
+                    .parse::<HeaderValue>()
+                    .with_context(|| format!("Invalid origin '{}'", origin))
+            })
+            .collect::<anyhow::Result<Vec<_>>>()?;
+        app = app.layer(
+            CorsLayer::new()
+                .allow_origin(origins)
+                .allow_methods([Method::GET, Method::POST, Method::DELETE])
+                .allow_headers([header::CONTENT_TYPE, header::AUTHORIZATION]),
+        );
+    }
+
+    let listener = tokio::net::TcpListener::bind(bind)
+        .await
+        .with_context(|| format!("Failed to listen on {}", bind))?;
+    info!("Serving the Ceres API on http://{}", listener.local_addr()?);
+
+    let (stop, stopped) = tokio::sync::watch::channel(false);
+    tokio::spawn(async move {
+        tokio::signal::ctrl_c().await.ok();
+        info!("Shutting down");
+        stop.send_replace(true);
+    });
+    let http = async {
+        axum::serve(listener, app)
+            .with_graceful_shutdown(shutdown(stopped.clone()))
+            .await
+            .context("HTTP server error")
+    };
+
+    match grpc_bind {
+        #[cfg(feature = "grpc")]
+        Some(grpc_bind) => {
+            let grpc = crate::grpc::serve(state, grpc_bind, shutdown(stopped.clone()));
+            tokio::try_join!(http, grpc)?;
+        }
+        _ => http.await?,
+    }
+    Ok(())
+}
+
+/// Resolves once Ctrl-C is pressed.
+async fn shutdown(mut stopped: tokio::sync::watch::Receiver<bool>) {
+    stopped.wait_for(|&stopped| stopped).await.ok();
+}
+
+/// Body of every error response.
+#[derive(Debug, Serialize, ToSchema)]
+struct ErrorBody {
+    error: String,
+}
+
+/// Error response: `{"error": message}` with `status`.
+#[derive(Debug)]
+pub(crate) struct ApiError {
+    pub(crate) status: StatusCode,
+    pub(crate) message: String,
+}
+
+impl ApiError {
+    pub(crate) fn bad_request(message: impl Into<String>) -> Self {
+        Self {
+            status: StatusCode::BAD_REQUEST,
+            message: message.into(),
+        }
+    }
+
+    pub(crate) fn unauthorized(message: impl Into<String>) -> Self {
+        Self {
+            status: StatusCode::UNAUTHORIZED,
+            message: message.into(),
+        }
+    }
+
+    pub(crate) fn forbidden(message: impl Into<String>) -> Self {
+        Self {
+            status: StatusCode::FORBIDDEN,
+            message: message.into(),
+        }
+    }
+
+    pub(crate) fn not_found(message: impl Into<String>) -> Self {
+        Self {
+            status: StatusCode::NOT_FOUND,
+            message: message.into(),
+        }
+    }
+
+    /// Logs `err` and hides its details from the client.
+    pub(crate) fn internal(err: impl std::fmt::Display) -> Self {
+        error!("Request failed: {:#}", err);
+        Self {
+            status: StatusCode::INTERNAL_SERVER_ERROR,
+            message: "Internal server error".to_string(),
+        }
+    }
+}
+
+impl From<AppError> for ApiError {
+    fn from(err: AppError) -> Self {
+        match err {
+            AppError::ConfigError(msg) => Self::bad_request(msg),
+            err => Self::internal(err),
+        }
+    }
+}
+
+impl From<anyhow::Error> for ApiError {
+    fn from(err: anyhow::Error) -> Self {
+        Self::internal(err)
+    }
+}
+
+impl From<JsonRejection> for ApiError {
+    fn from(rejection: JsonRejection) -> Self {
+        Self::bad_request(rejection.body_text())
+    }
+}
+
+impl From<QueryRejection> for ApiError {
+    fn from(rejection: QueryRejection) -> Self {
+        Self::bad_request(rejection.body_text())
+    }
+}
+
+impl IntoResponse for ApiError {
+    fn into_response(self) -> Response {
+        let body = Json(ErrorBody {
+            error: self.message,
+        });
+        (self.status, body).into_response()
+    }
+}
+
+/// Query string of `/search`, named after the `ceres search` options.
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub(crate) struct SearchParams {
+    /// Search query
+    #[param(required = true)]
+    pub(crate) q: Option<String>,
+    /// Maximum number of results (default 10, at most 100)
+    pub(crate) limit: Option<usize>,
+    /// Only datasets from this portal URL
+    pub(crate) portal: Option<String>,
+    /// Only datasets in this CKAN group
+    pub(crate) theme: Option<String>,
+    /// Only datasets with a resource in this format (e.g. `csv`)
+    pub(crate) format: Option<String>,
+    /// Only datasets under this license ID
+    pub(crate) license: Option<String>,
+    /// Only datasets in this language (ISO 639-1 code)
+    pub(crate) language: Option<String>,
+    /// Only datasets with this tag, matched after normalization
+    pub(crate) tag: Option<String>,
+    /// Only datasets published by this organization (CKAN name or title)
+    pub(crate) org: Option<String>,
+    /// Only datasets intersecting `min_lon,min_lat,max_lon,max_lat`
+    pub(crate) bbox: Option<String>,
+    /// Only datasets modified upstream since this date (`2024-01-31` or RFC 3339)
+    pub(crate) updated_after: Option<String>,
+    /// Only datasets modified upstream before this date
+    pub(crate) updated_before: Option<String>,
+    /// Ranking: `semantic` (default), `hybrid` or `text`
+    pub(crate) mode: Option<String>,
+    /// Weight of metadata quality in ranking, from 0 (default) to 1
+    #[param(value_type = Option<f32>)]
+    pub(crate) quality_weight: Option<String>,
+}
+
+impl SearchParams {
+    fn mode(&self) -> Result<SearchModeArg, ApiError> {
+        match &self.mode {
+            Some(mode) => SearchModeArg::from_str(mode, true)
+                .map_err(|_| ApiError::bad_request(format!("Unknown search mode '{}'", mode))),
+            None => Ok(SearchModeArg::Semantic),
+        }
+    }
+
+    /// Filters of the query; `embedding_model` is left for the caller.
+    fn filters(&self) -> Result<SearchFilters, AppError> {
+        Ok(SearchFilters {
+            portal: self.portal.clone(),
+            theme: self.theme.clone(),
+            format: self
+                .format
+                .as_deref()
+                .and_then(NewDataset::normalize_format),
+            license: self.license.clone(),
+            updated_after: self
+                .updated_after
+                .as_deref()
+                .map(parse_date_bound)
+                .transpose()?,
+            updated_before: self
+                .updated_before
+                .as_deref()
+                .map(parse_date_bound)
+                .transpose()?,
+            language: self.language.as_deref().map(parse_language).transpose()?,
+            tag: self.tag.as_deref().map(parse_tag).transpose()?,
+            organization: self.org.clone(),
+            bbox: self.bbox.as_deref().map(parse_bbox).transpose()?,
+            ..Default::default()
+        })
+    }
+}
+
+/// Search indexed datasets
+#[utoipa::path(
+    get,
+    path = "/search",
+    params(SearchParams),
+    responses(
+        (status = 200, description = "Matches, best first, with facet counts", body = SearchResponse),
+        (status = 400, description = "Missing query or invalid parameter", body = ErrorBody),
+    )
+)]
+async fn search(
+    State(state): State<AppState>,
+    params: Result<Query<SearchParams>, QueryRejection>,
+) -> Result<Json<SearchResponse>, ApiError> {
+    let Query(params) = params?;
+    Ok(Json(state.search(params, SearchSource::Api).await?))
+}
+
+/// Get a dataset by ID
+#[utoipa::path(
+    get,
+    path = "/datasets/{id}",
+    params(("id" = Uuid, Path, description = "Dataset ID")),
+    responses(
+        (status = 200, description = "The dataset", body = DatasetRecord),
+        (status = 400, description = "Malformed ID", body = ErrorBody),
+        (status = 404, description = "No dataset with this ID", body = ErrorBody),
+    )
+)]
+async fn dataset(
+    State(state): State<AppState>,
+    Path(id): Path<String>,
+) -> Result<Json<DatasetRecord>, ApiError> {
+    Ok(Json(state.dataset(&id).await?))
+}
+
+#[derive(Debug, Default, Deserialize, IntoParams)]
+#[into_params(parameter_in = Query)]
+struct StatsParams {
+    /// Only count datasets from this portal URL
+    portal: Option<String>,
+}
+
+/// Database statistics
+#[utoipa::path(
+    get,
+    path = "/stats",
+    params(StatsParams),
+    responses((status = 200, description = "Dataset counts and portal harvest history", body = DatabaseStats))
+)]
+async fn stats(
+    State(state): State<AppState>,
+    params: Result<Query<StatsParams>, QueryRejection>,
+) -> Result<Json<DatabaseStats>, ApiError> {
+    let Query(params) = params?;
+    Ok(Json(state.store.get_stats(params.portal.as_deref()).await?))
+}
+
+/// Search page, self-contained so it works without network access.
+const INDEX_HTML: &str = include_str!("../static/index.html");
+
+async fn index() -> Html<&'static str> {
+    Html(INDEX_HTML)
+}
+
+/// Configured portals
+#[utoipa::path(
+    get,
+    path = "/portals",
+    responses(
+        (status = 200, description = "Portals with their harvest health and indexed datasets", body = Vec<PortalRecord>),
+        (status = 404, description = "No portals.toml found", body = ErrorBody),
+    )
+)]
+async fn portals(State(state): State<AppState>) -> Result<Json<Vec<PortalRecord>>, ApiError> {
+    let portals_config = load_portals_config(state.portals_config.clone())?
+        .ok_or_else(|| ApiError::not_found("No portals configuration found"))?;
+    let health = state.store.list_portal_health().await?;
+    let stats = state.store.get_stats(None).await?;
+    Ok(Json(portal_records(
+        &portals_config,
+        health,
+        stats.portals,
+        Utc::now(),
+    )))
+}
+
+/// A watch, as listed by `ceres watch list`.
+#[derive(Debug, Serialize, ToSchema)]
+struct WatchRecord {
+    id: Uuid,
+    name: String,
+    /// Query text the watch matches datasets against
+    query: String,
+    /// Minimum similarity for a dataset to match
+    min_score: f32,
+    /// Only datasets from this portal URL match
+    portal: Option<String>,
+    /// Embedding model of the query; only datasets embedded with it match
+    embedding_model: String,
+    /// URL receiving signed deliveries
+    webhook_url: String,
+    /// HMAC secret signing deliveries, only returned when the watch is
+    /// created
+    #[serde(skip_serializing_if = "Option::is_none")]
+    secret: Option<String>,
+    created_at: DateTime<Utc>,
+}
+
+impl WatchRecord {
+    /// The watch without its secret.
+    fn new(watch: Watch) -> Self {
+        Self {
+            id: watch.id,
+            name: watch.name,
+            query: watch.query,
+            min_score: watch.min_score,
+            portal: watch.portal,
+            embedding_model: watch.embedding_model,
+            webhook_url: watch.webhook_url,
+            secret: None,
+            created_at: watch.created_at,
+        }
+    }
+}
+
+/// Body of `POST /watches`, named after the `ceres watch add` options.
+#[derive(Debug, Deserialize, ToSchema)]
+struct NewWatch {
+    /// Unique name of the watch
+    name: String,
+    /// Query text to match new datasets against
+    query: String,
+    /// URL receiving signed deliveries
+    webhook: String,
+    /// Minimum similarity for a match, from 0 to 1 (default 0.75)
+    min_score: Option<f32>,
+    /// Only match datasets from this portal URL
+    portal: Option<String>,
+}
+
+/// Register a watch
+#[utoipa::path(
+    post,
+    path = "/watches",
+    request_body = NewWatch,
+    security(("api_token" = [])),
+    responses(
+        (status = 201, description = "The watch, with the secret signing its deliveries", body = WatchRecord),
+        (status = 400, description = "Invalid watch, taken name or no embedding provider", body = ErrorBody),
+        (status = 401, description = "Missing or wrong API token", body = ErrorBody),
+        (status = 403, description = "The server has no API token", body = ErrorBody),
+    )
+)]
+async fn create_watch(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    body: Result<Json<NewWatch>, JsonRejection>,
+) -> Result<(StatusCode, Json<WatchRecord>), ApiError> {
+    state.authorize(&headers)?;
+    let Json(body) = body?;
+    let min_score = body.min_score.unwrap_or(DEFAULT_MIN_SCORE);
+    if !(0.0..=1.0).contains(&min_score) {
+        return Err(ApiError::bad_request("min_score must be between 0 and 1"));
+    }
+    state.check_webhook(&body.webhook).await?;
+    let embedder = state
+        .embedder
+        .as_deref()
+        .ok_or_else(|| ApiError::bad_request("No embedding provider configured"))?;
+
+    let watch = register_watch(
+        state.store.as_ref(),
+        embedder,
+        body.name,
+        body.query,
+        body.webhook,
+        min_score,
+        body.portal,
+    )
+    .await?;
+    let secret = watch.secret.clone();
+    let record = WatchRecord {
+        secret: Some(secret),
+        ..WatchRecord::new(watch)
+    };
+    Ok((StatusCode::CREATED, Json(record)))
+}
+
+/// Registered watches
+#[utoipa::path(
+    get,
+    path = "/watches",
+    responses((status = 200, description = "Watches, oldest first, without their secrets", body = Vec<WatchRecord>))
+)]
+async fn list_watches(State(state): State<AppState>) -> Result<Json<Vec<WatchRecord>>, ApiError> {
+    let watches = state.store.list_watches().await?;
+    Ok(Json(watches.into_iter().map(WatchRecord::new).collect()))
+}
+
+/// Remove a watch
+#[utoipa::path(
+    delete,
+    path = "/watches/{id}",
+    params(("id" = Uuid, Path, description = "Watch ID")),
+    security(("api_token" = [])),
+    responses(
+        (status = 204, description = "The watch was removed"),
+        (status = 400, description = "Malformed ID", body = ErrorBody),
+        (status = 401, description = "Missing or wrong API token", body = ErrorBody),
+        (status = 403, description = "The server has no API token", body = ErrorBody),
+        (status = 404, description = "No watch with this ID", body = ErrorBody),
+    )
+)]
+async fn delete_watch(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    Path(id): Path<String>,
+) -> Result<StatusCode, ApiError> {
+    state.authorize(&headers)?;
+    let id = Uuid::parse_str(&id)
+        .map_err(|_| ApiError::bad_request(format!("Invalid watch ID '{}'", id)))?;
+    if !state.store.delete_watch_by_id(id).await? {
+        return Err(ApiError::not_found(format!("Watch {} not found", id)));
+    }
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Compares without returning early, so response times do not tell how
+/// much of the token a guess got right.
+fn constant_time_eq(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |acc, (x, y)| acc | (x ^ y)) == 0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use axum::body::Body;
+    use axum::http::Request;
+    use ceres_db::MemoryStore;
+    use tower::ServiceExt;
+
+    async fn test_state() -> (AppState, Uuid) {
+        let store = MemoryStore::new();
+        let title = "Fermate autobus";
+        let id = store
+            .upsert(&NewDataset {
+                original_id: "bus".to_string(),
+                source_portal: "https://dati.comune.milano.it".to_string(),
+                url: "https://dati.comune.milano.it/dataset/bus".to_string(),
+                title: title.to_string(),
+                description: None,
+                embedding: None,
+                embedding_model: None,
+                metadata: serde_json::json!({}),
+                formats: Vec::new(),
+                content_hash: NewDataset::compute_content_hash(title, None),
+                modified_at: None,
+                language: None,
+                bbox: None,
+                quality: None,
+                tags: Vec::new(),
+                organization: None,
+                resources: Vec::new(),
+            })
+            .await
+            .unwrap()
+            .id();
+        let state = AppState {
+            store: Arc::new(store),
+            embedder: None,
+            portals_config: None,
+            record_searches: true,
+            settings: Settings::default(),
+            api_token: Some("test-token".to_string()),
+            allow_private_webhooks: false,
+        };
+        (state, id)
+    }
+
+    /// Embedder returning the same vector for every text.
+    struct FixedEmbedder;
+
+    #[async_trait::async_trait]
+    impl EmbeddingProvider for FixedEmbedder {
+        fn model_id(&self) -> &str {
+            "test-model"
+        }
+
+        fn dimension(&self) -> usize {
+            2
+        }
+
+        fn for_model(&self, _model: &str) -> Arc<dyn EmbeddingProvider> {
+            Arc::new(FixedEmbedder)
+        }
+
+        async fn embed(&self, _text: &str) -> Result<Vec<f32>, AppError> {
+            Ok(vec![1.0, 0.0])
+        }
+    }
+
+    async fn get_json(state: AppState, uri: &str) -> (StatusCode, serde_json::Value) {
+        send_json(state, Request::get(uri).body(Body::empty()).unwrap()).await
+    }
+
+    /// POSTs `body` with the API token of [`test_state`].
+    async fn post_json(
+        state: AppState,
+        uri: &str,
+        body: serde_json::Value,
+    ) -> (StatusCode, serde_json::Value) {
+        let request = Request::post(uri)
+            .header(header::CONTENT_TYPE, "application/json")
+            .header(header::AUTHORIZATION, "Bearer test-token")
+            .body(Body::from(body.to_string()))
+            .unwrap();
+        send_json(state, request).await
+    }
+
+    async fn send_json(state: AppState, request: Request<Body>) -> (StatusCode, serde_json::Value) {
+        let response = router(state).oneshot(request).await.unwrap();
+        let status = response.status();
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        // Empty for 204 No Content
+        let body = serde_json::from_slice(&body).unwrap_or(serde_json::Value::Null);
+        (status, body)
+    }
+
+    #[tokio::test]
+    async fn test_search_text_mode() {
+        let (state, _) = test_state().await;
+        let (status, body) = get_json(state.clone(), "/search?q=autobus&mode=text").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["query"], "autobus");
+        assert_eq!(body["results"][0]["title"], "Fermate autobus");
+
+        let history = state.store.list_searches(10).await.unwrap();
+        assert_eq!(history.len(), 1);
+        assert_eq!(
+            (history[0].query.as_str(), history[0].source.as_str()),
+            ("autobus", "api")
+        );
+        assert_eq!(history[0].result_count, 1);
+    }
+
+    #[tokio::test]
+    async fn test_search_rejects_bad_parameters() {
+        let (state, _) = test_state().await;
+        for uri in [
+            "/search",
+            "/search?q=autobus&mode=fuzzy",
+            "/search?q=autobus&limit=many",
+            "/search?q=autobus&mode=text&language=xx",
+            "/search?q=autobus",
+        ] {
+            let (status, body) = get_json(state.clone(), uri).await;
+            assert_eq!(status, StatusCode::BAD_REQUEST, "{}", uri);
+            assert!(body["error"].is_string(), "{}", uri);
+        }
+    }
+
+    #[tokio::test]
+    async fn test_dataset_by_id() {
+        let (state, id) = test_state().await;
+        let (status, body) = get_json(state.clone(), &format!("/datasets/{}", id)).await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["title"], "Fermate autobus");
+
+        let (status, _) = get_json(state.clone(), &format!("/datasets/{}", Uuid::nil())).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+        let (status, _) = get_json(state, "/datasets/not-a-uuid").await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_openapi_document() {
+        let (state, _) = test_state().await;
+        let (status, body) = get_json(state, "/openapi.json").await;
+        assert_eq!(status, StatusCode::OK);
+        for path in [
+            "/search",
+            "/datasets/{id}",
+            "/stats",
+            "/portals",
+            "/watches",
+        ] {
+            assert!(body["paths"][path]["get"].is_object(), "{}", path);
+        }
+        assert!(body["paths"]["/watches"]["post"].is_object());
+        assert!(body["paths"]["/watches/{id}"]["delete"].is_object());
+        let schemas = &body["components"]["schemas"];
+        assert!(schemas["NewWatch"]["properties"]["webhook"].is_object());
+        assert!(schemas["WatchRecord"]["properties"]["secret"].is_object());
+        assert_eq!(
+            body["components"]["securitySchemes"]["api_token"]["scheme"],
+            "bearer"
+        );
+        assert!(schemas["SearchResponse"]["properties"]["facets"].is_object());
+        assert!(schemas["DatabaseStats"]["properties"]["portals"].is_object());
+    }
+
+    #[tokio::test]
+    async fn test_index_page() {
+        let (state, _) = test_state().await;
+        let request = Request::get("/").body(Body::empty()).unwrap();
+        let response = router(state).oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(
+            response.headers()["content-type"],
+            "text/html; charset=utf-8"
+        );
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        assert!(String::from_utf8_lossy(&body).contains("fetch(\"search?\""));
+    }
+
+    #[tokio::test]
+    async fn test_stats() {
+        let (state, _) = test_state().await;
+        let (status, body) = get_json(state, "/stats").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body["total_datasets"], 1);
+    }
+
+    #[tokio::test]
+    async fn test_watches() {
+        let (mut state, _) = test_state().await;
+        state.embedder = Some(Arc::new(FixedEmbedder));
+        let (status, body) = post_json(
+            state.clone(),
+            "/watches",
+            serde_json::json!({
+                "name": "air",
+                "query": "air quality",
+                "webhook": "https://93.184.215.14/ceres",
+            }),
+        )
+        .await;
+        assert_eq!(status, StatusCode::CREATED);
+        assert_eq!(body["name"], "air");
+        assert_eq!(body["embedding_model"], "test-model");
+        assert!(body["secret"].is_string());
+        let id = body["id"].as_str().unwrap().to_string();
+
+        let (status, body) = get_json(state.clone(), "/watches").await;
+        assert_eq!(status, StatusCode::OK);
+        assert_eq!(body.as_array().unwrap().len(), 1);
+        assert_eq!(body[0]["id"], id.as_str());
+        assert!(body[0].get("secret").is_none());
+
+        let request = Request::delete(format!("/watches/{}", id))
+            .header(header::AUTHORIZATION, "Bearer test-token")
+            .body(Body::empty())
+            .unwrap();
+        let (status, _) = send_json(state.clone(), request).await;
+        assert_eq!(status, StatusCode::NO_CONTENT);
+        let (_, body) = get_json(state.clone(), "/watches").await;
+        assert!(body.as_array().unwrap().is_empty());
+
+        let request = Request::delete(format!("/watches/{}", id))
+            .header(header::AUTHORIZATION, "Bearer test-token")
+            .body(Body::empty())
+            .unwrap();
+        let (status, _) = send_json(state, request).await;
+        assert_eq!(status, StatusCode::NOT_FOUND);
+    }
+
+    #[tokio::test]
+    async fn test_create_watch_rejects_bad_bodies() {
+        let (mut state, _) = test_state().await;
+        let watch = serde_json::json!({
+            "name": "air",
+            "query": "air quality",
+            "webhook": "https://93.184.215.14/ceres",
+        });
+        // Without an embedding provider
+        let (status, _) = post_json(state.clone(), "/watches", watch.clone()).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        state.embedder = Some(Arc::new(FixedEmbedder));
+        for body in [
+            serde_json::json!({"name": "air"}),
+            serde_json::json!({"name": "air", "query": "air", "webhook": "not a url"}),
+            serde_json::json!({
+                "name": "air",
+                "query": "air",
+                "webhook": "https://93.184.215.14/ceres",
+                "min_score": 1.5,
+            }),
+        ] {
+            let (status, response) = post_json(state.clone(), "/watches", body.clone()).await;
+            assert_eq!(status, StatusCode::BAD_REQUEST, "{}", body);
+            assert!(response["error"].is_string(), "{}", body);
+        }
+
+        // Names are unique
+        let (status, _) = post_json(state.clone(), "/watches", watch.clone()).await;
+        assert_eq!(status, StatusCode::CREATED);
+        let (status, _) = post_json(state.clone(), "/watches", watch).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+
+        let request = Request::delete("/watches/air")
+            .header(header::AUTHORIZATION, "Bearer test-token")
+            .body(Body::empty())
+            .unwrap();
+        let (status, _) = send_json(state, request).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+
+    #[tokio::test]
+    async fn test_watch_routes_require_token() {
+        let (mut state, _) = test_state().await;
+        state.embedder = Some(Arc::new(FixedEmbedder));
+        let body = serde_json::json!({
+            "name": "air",
+            "query": "air quality",
+            "webhook": "https://93.184.215.14/ceres",
+        });
+        for authorization in [None, Some("Bearer wrong-token"), Some("test-token")] {
+            let mut request =
+                Request::post("/watches").header(header::CONTENT_TYPE, "application/json");
+            if let Some(authorization) = authorization {
+                request = request.header(header::AUTHORIZATION, authorization);
+            }
+            let request = request.body(Body::from(body.to_string())).unwrap();
+            let (status, _) = send_json(state.clone(), request).await;
+            assert_eq!(status, StatusCode::UNAUTHORIZED, "{:?}", authorization);
+        }
+        let request = Request::delete(format!("/watches/{}", Uuid::nil()))
+            .body(Body::empty())
+            .unwrap();
+        let (status, _) = send_json(state.clone(), request).await;
+        assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+        // Without a token configured, watches cannot be changed at all
+        state.api_token = None;
+        let (status, body) = post_json(state, "/watches", body).await;
+        assert_eq!(status, StatusCode::FORBIDDEN);
+        assert!(body["error"].as_str().unwrap().contains("CERES_API_TOKEN"));
+    }
+
+    #[tokio::test]
+    async fn test_create_watch_rejects_private_webhooks() {
+        let (mut state, _) = test_state().await;
+        state.embedder = Some(Arc::new(FixedEmbedder));
+        let watch = |webhook: &str| serde_json::json!({"name": "air", "query": "air quality", "webhook": webhook});
+        for webhook in [
+            "http://169.254.169.254/latest/meta-data",
+            "http://127.0.0.1:8080/hook",
+            "http://localhost/hook",
+            "http://10.0.0.5/hook",
+            "http://[::1]/hook",
+            "file:///etc/passwd",
+            "ftp://93.184.215.14/hook",
+        ] {
+            let (status, body) = post_json(state.clone(), "/watches", watch(webhook)).await;
+            assert_eq!(status, StatusCode::BAD_REQUEST, "{}", webhook);
+            assert!(body["error"].is_string(), "{}", webhook);
+        }
+
+        // Unless the server allows them
+        state.allow_private_webhooks = true;
+        let (status, _) = post_json(
+            state.clone(),
+            "/watches",
+            watch("http://127.0.0.1:8080/hook"),
+        )
+        .await;
+        assert_eq!(status, StatusCode::CREATED);
+        let (status, _) = post_json(state, "/watches", watch("file:///etc/passwd")).await;
+        assert_eq!(status, StatusCode::BAD_REQUEST);
+    }
+}
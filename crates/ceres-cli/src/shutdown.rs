@@ -0,0 +1,69 @@
+//! Graceful shutdown of harvests on Ctrl-C or SIGTERM.
+//!
+//! Once a shutdown is requested, harvests stop starting datasets; the ones
+//! already fetched are still embedded and stored, and the harvest ends as
+//! usual, recording its failures and printing what it got through. Batch
+//! harvests leave the remaining portals alone, and the daemon starts no
+//! further runs. A second signal exits at once.
+
+use std::pin::pin;
+use std::sync::atomic::{AtomicBool, Ordering};
+
+use tokio::sync::Notify;
+use tracing::warn;
+
+use crate::exit_status;
+
+static REQUESTED: AtomicBool = AtomicBool::new(false);
+static REQUEST: Notify = Notify::const_new();
+
+/// Starts listening for Ctrl-C and SIGTERM.
+pub fn listen() {
+    tokio::spawn(async {
+        signal().await;
+        warn!("Shutdown requested: finishing the datasets in flight (press Ctrl-C again to abort)");
+        REQUESTED.store(true, Ordering::SeqCst);
+        REQUEST.notify_waiters();
+
+        signal().await;
+        warn!("Aborted");
+        std::process::exit(exit_status::INTERRUPTED.into());
+    });
+}
+
+/// Returns true once a shutdown has been requested.
+pub fn requested() -> bool {
+    REQUESTED.load(Ordering::SeqCst)
+}
+
+/// Completes when a shutdown is requested.
+pub async fn wait() {
+    // Registered before checking, so a request in between is not missed
+    let mut request = pin!(REQUEST.notified());
+    request.as_mut().enable();
+    if !requested() {
+        request.await;
+    }
+}
+
+#[cfg(unix)]
+async fn signal() {
+    use tokio::signal::unix::{signal, SignalKind};
+
+    match signal(SignalKind::terminate()) {
+        Ok(mut terminate) => {
+            tokio::select! {
+                _ = tokio::signal::ctrl_c() => {}
+                _ = terminate.recv() => {}
+            }
+        }
+        Err(_) => {
+            let _ = tokio::signal::ctrl_c().await;
+        }
+    }
+}
+
+#[cfg(not(unix))]
+async fn signal() {
+    let _ = tokio::signal::ctrl_c().await;
+}
@@ -0,0 +1,137 @@
+//! Log output and OpenTelemetry trace export.
+//!
+//! Logs go to the given writer, filtered by [`log_filter`]. When
+//! `OTEL_EXPORTER_OTLP_ENDPOINT` (or `OTEL_EXPORTER_OTLP_TRACES_ENDPOINT`) is
+//! set and the `otel` feature is compiled in, spans are also exported over
+//! OTLP/HTTP; the exporter reads the standard `OTEL_*` variables (headers,
+//! timeout, `OTEL_SERVICE_NAME`, `OTEL_RESOURCE_ATTRIBUTES`).
+
+use tracing::level_filters::LevelFilter;
+use tracing::warn;
+use tracing_subscriber::filter::{filter_fn, FilterExt};
+use tracing_subscriber::fmt::writer::BoxMakeWriter;
+use tracing_subscriber::layer::SubscriberExt;
+use tracing_subscriber::{EnvFilter, Layer};
+
+/// Target of the harvest summaries, which `--quiet` still logs.
+pub const SUMMARY: &str = "ceres::summary";
+
+const ENDPOINT_VARS: [&str; 2] = [
+    "OTEL_EXPORTER_OTLP_ENDPOINT",
+    "OTEL_EXPORTER_OTLP_TRACES_ENDPOINT",
+];
+
+/// Installed tracing; call [`Telemetry::shutdown`] before exiting so pending
+/// spans are exported.
+pub struct Telemetry {
+    #[cfg(feature = "otel")]
+    provider: Option<opentelemetry_sdk::trace::SdkTracerProvider>,
+}
+
+/// Log levels for `-v` repeated `verbose` times or `--quiet`; with neither,
+/// `RUST_LOG` if set, else INFO.
+pub fn log_filter(verbose: u8, quiet: bool) -> EnvFilter {
+    let directives = match (verbose, quiet) {
+        (_, true) => format!("warn,{}=info", SUMMARY),
+        (0, false) => {
+            return EnvFilter::builder()
+                .with_default_directive(LevelFilter::INFO.into())
+                .from_env_lossy()
+        }
+        // Crate targets all start with `ceres`
+        (1, false) => "info,ceres=debug".to_string(),
+        (2, false) => "debug".to_string(),
+        _ => "trace".to_string(),
+    };
+    EnvFilter::new(directives)
+}
+
+/// Installs the global subscriber, logging to `writer` what `filter` lets
+/// through, colored if `ansi`. Spans are exported at INFO whatever the log
+/// level.
+pub fn init(writer: BoxMakeWriter, filter: EnvFilter, ansi: bool) -> anyhow::Result<Telemetry> {
+    // Spans are only for export; printing them would prefix every log line
+    let logs = tracing_subscriber::fmt::layer()
+        .with_writer(writer)
+        .with_ansi(ansi)
+        .with_filter(filter_fn(|metadata| metadata.is_event()).and(filter));
+    let endpoint = ENDPOINT_VARS
+        .iter()
+        .find_map(|var| std::env::var(var).ok().filter(|v| !v.is_empty()));
+
+    #[cfg(feature = "otel")]
+    {
+        let provider = endpoint.map(|_| tracer_provider()).transpose()?;
+        let export = provider.as_ref().map(|provider| {
+            use opentelemetry::trace::TracerProvider as _;
+            tracing_opentelemetry::layer().with_tracer(provider.tracer("ceres"))
+        });
+        let subscriber = tracing_subscriber::registry()
+            .with(export.with_filter(LevelFilter::INFO))
+            .with(logs);
+        tracing::subscriber::set_global_default(subscriber)
+            .expect("setting default subscriber failed");
+        Ok(Telemetry { provider })
+    }
+
+    #[cfg(not(feature = "otel"))]
+    {
+        let subscriber = tracing_subscriber::registry().with(logs);
+        tracing::subscriber::set_global_default(subscriber)
+            .expect("setting default subscriber failed");
+        if endpoint.is_some() {
+            warn!(
+                "OTEL_EXPORTER_OTLP_ENDPOINT is set, but OpenTelemetry support is not compiled in. Reinstall with: cargo install ceres-search --features otel"
+            );
+        }
+        Ok(Telemetry {})
+    }
+}
+
+#[cfg(feature = "otel")]
+fn tracer_provider() -> anyhow::Result<opentelemetry_sdk::trace::SdkTracerProvider> {
+    use anyhow::Context;
+    use opentelemetry_otlp::{SpanExporter, WithExportConfig};
+    use opentelemetry_sdk::trace::SdkTracerProvider;
+    use opentelemetry_sdk::Resource;
+
+    let exporter = SpanExporter::builder()
+        .with_http()
+        .with_protocol(opentelemetry_otlp::Protocol::HttpBinary)
+        .build()
+        .context("Failed to create the OTLP span exporter")?;
+    // OTEL_SERVICE_NAME, when set, is picked up by the resource detectors
+    let mut resource = Resource::builder();
+    if std::env::var_os("OTEL_SERVICE_NAME").is_none() {
+        resource = resource.with_service_name("ceres");
+    }
+    Ok(SdkTracerProvider::builder()
+        .with_batch_exporter(exporter)
+        .with_resource(resource.build())
+        .build())
+}
+
+impl Telemetry {
+    /// Exports spans still buffered; failures are only logged.
+    pub fn shutdown(self) {
+        #[cfg(feature = "otel")]
+        if let Some(provider) = self.provider {
+            if let Err(e) = provider.shutdown() {
+                warn!("Failed to export traces: {}", e);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_log_filter() {
+        assert_eq!(log_filter(0, true).to_string(), "ceres::summary=info,warn");
+        assert_eq!(log_filter(1, false).to_string(), "ceres=debug,info");
+        assert_eq!(log_filter(2, false).to_string(), "debug");
+        assert_eq!(log_filter(5, false).to_string(), "trace");
+    }
+}
@@ -0,0 +1,185 @@
+//! Terminal output: color control and an ASCII-only mode.
+//!
+//! Colors are left out with `--no-color` or a non-empty `NO_COLOR`, and logs
+//! are only colored on a terminal. `--ascii` replaces the box-drawing
+//! characters, similarity bars, check marks and emojis of human-readable
+//! output with plain ASCII, for terminals and CI logs that garble Unicode.
+//! JSON, CSV and exports are always written as they are.
+
+use std::borrow::Cow;
+use std::io::{self, Write};
+use std::sync::OnceLock;
+
+use ratatui::symbols::border;
+
+/// How human-readable output is drawn.
+#[derive(Debug, Clone, Copy)]
+pub struct Term {
+    pub color: bool,
+    pub ascii: bool,
+}
+
+impl Default for Term {
+    fn default() -> Self {
+        Self {
+            color: true,
+            ascii: false,
+        }
+    }
+}
+
+static TERM: OnceLock<Term> = OnceLock::new();
+
+impl Term {
+    /// Output settings for the `--no-color` and `--ascii` flags and the
+    /// `NO_COLOR` environment variable.
+    pub fn new(no_color: bool, ascii: bool) -> Self {
+        let no_color_env = std::env::var_os("NO_COLOR").is_some_and(|v| !v.is_empty());
+        Self {
+            color: !no_color && !no_color_env,
+            ascii,
+        }
+    }
+
+    /// Makes these the settings of the process; later calls are ignored.
+    pub fn install(self) {
+        let _ = TERM.set(self);
+    }
+}
+
+fn current() -> Term {
+    TERM.get().copied().unwrap_or_default()
+}
+
+/// Returns false when colors are turned off.
+pub fn color() -> bool {
+    current().color
+}
+
+/// Returns true in ASCII-only mode.
+pub fn ascii() -> bool {
+    current().ascii
+}
+
+/// `text` as printed: unchanged, or with its symbols replaced in ASCII mode.
+pub fn render(text: &str) -> Cow<'_, str> {
+    if ascii() {
+        to_ascii(text)
+    } else {
+        Cow::Borrowed(text)
+    }
+}
+
+/// Prints a line of human-readable output to stdout, like `println!`, in
+/// ASCII in ASCII mode.
+macro_rules! outln {
+    () => {
+        println!()
+    };
+    ($($arg:tt)*) => {
+        println!("{}", $crate::term::render(&format!($($arg)*)))
+    };
+}
+pub(crate) use outln;
+
+/// Borders of TUI boxes in ASCII mode.
+pub const ASCII_BORDER: border::Set = border::Set {
+    top_left: "+",
+    top_right: "+",
+    bottom_left: "+",
+    bottom_right: "+",
+    vertical_left: "|",
+    vertical_right: "|",
+    horizontal_top: "-",
+    horizontal_bottom: "-",
+};
+
+/// Writer passing text on in ASCII, for logs in ASCII mode.
+pub struct AsciiWriter<W>(pub W);
+
+impl<W: Write> Write for AsciiWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        // Log events arrive whole, so a character is never split
+        match std::str::from_utf8(buf) {
+            Ok(text) => self.0.write_all(to_ascii(text).as_bytes())?,
+            Err(_) => self.0.write_all(buf)?,
+        }
+        Ok(buf.len())
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.0.flush()
+    }
+}
+
+/// Replaces the symbols Ceres prints with ASCII and drops emojis, along with
+/// the spaces after them. Letters, accented ones included, stay as they are.
+fn to_ascii(text: &str) -> Cow<'_, str> {
+    if text.is_ascii() {
+        return Cow::Borrowed(text);
+    }
+    let mut out = String::with_capacity(text.len());
+    let mut chars = text.chars().peekable();
+    while let Some(c) = chars.next() {
+        if let Some(symbol) = ascii_symbol(c) {
+            out.push_str(symbol);
+        } else if is_emoji(c) {
+            while chars
+                .next_if(|next| *next == '\u{FE0F}' || *next == ' ')
+                .is_some()
+            {}
+        } else {
+            out.push(c);
+        }
+    }
+    Cow::Owned(out)
+}
+
+fn ascii_symbol(c: char) -> Option<&'static str> {
+    let symbol = match c {
+        '═' => "=",
+        '─' | '—' | '–' => "-",
+        '│' | '·' => "|",
+        '┌' | '┐' | '└' | '┘' | '├' | '┤' | '┬' | '┴' | '┼' => "+",
+        '█' => "#",
+        '░' => ".",
+        '✓' => "+",
+        '✗' => "x",
+        '⚠' => "!",
+        '•' => "*",
+        '↑' => "^",
+        '↓' => "v",
+        '→' => "->",
+        '←' => "<-",
+        '×' => "x",
+        '≈' => "~",
+        '…' => "...",
+        '\u{00A0}' => " ",
+        _ => return None,
+    };
+    Some(symbol)
+}
+
+fn is_emoji(c: char) -> bool {
+    matches!(c, '\u{2300}'..='\u{23FF}' | '\u{2600}'..='\u{27BF}' | '\u{1F000}'..='\u{1FAFF}')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_to_ascii() {
+        assert!(matches!(to_ascii("plain text"), Cow::Borrowed(_)));
+        assert_eq!(to_ascii("════"), "====");
+        assert_eq!(to_ascii("[█████░░░░░]"), "[#####.....]");
+        assert_eq!(to_ascii("  ✓ Created: 3 → 4"), "  + Created: 3 -> 4");
+        assert_eq!(to_ascii("\n🏷️  Top tags"), "\nTop tags");
+        assert_eq!(to_ascii("     📦 12 datasets"), "     12 datasets");
+        // Data keeps its letters
+        assert_eq!(
+            to_ascii("Qualità dell'aria — Torino"),
+            "Qualità dell'aria - Torino"
+        );
+    }
+}
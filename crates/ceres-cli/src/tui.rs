@@ -0,0 +1,602 @@
+//! Interactive search (`ceres tui`).
+//!
+//! [`App`] holds the screen state and turns key presses into [`Action`]s;
+//! [`run`] owns the terminal, draws the app and carries the actions out.
+
+use std::time::{Duration, Instant};
+
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use ratatui::layout::{Constraint, Layout, Position};
+use ratatui::style::{Color, Modifier, Style, Stylize};
+use ratatui::text::{Line, Span, Text};
+use ratatui::widgets::{Block, List, ListItem, ListState, Paragraph, Wrap};
+use ratatui::{DefaultTerminal, Frame};
+
+use ceres_client::EmbeddingProvider;
+use ceres_core::facets::organization_name;
+use ceres_core::search::{SearchFilters, SearchStrategy};
+use ceres_core::SearchResult;
+use ceres_db::DatasetStore;
+use ceres_search::{SearchModeArg, SearchOutputArg};
+
+use crate::term::{self, ASCII_BORDER};
+use crate::{create_similarity_bar, search_hits, SearchOptions};
+
+/// Pause in typing after which the results refresh.
+const SEARCH_DEBOUNCE: Duration = Duration::from_millis(400);
+
+/// How long to wait for a key press before checking the debounce.
+const TICK: Duration = Duration::from_millis(50);
+
+/// Lines PgUp and PgDn scroll the detail pane by.
+const DETAIL_PAGE: u16 = 10;
+
+/// Search settings fixed for the session.
+pub struct TuiOptions {
+    pub limit: usize,
+    pub filters: SearchFilters,
+    pub mode: SearchModeArg,
+}
+
+/// Runs the TUI until the user quits, starting with `query` if given.
+pub async fn run(
+    store: &dyn DatasetStore,
+    embedder: Option<&dyn EmbeddingProvider>,
+    options: &TuiOptions,
+    query: Option<String>,
+) -> anyhow::Result<()> {
+    let mut terminal = ratatui::init();
+    let result = event_loop(&mut terminal, store, embedder, options, query).await;
+    ratatui::restore();
+    result
+}
+
+async fn event_loop(
+    terminal: &mut DefaultTerminal,
+    store: &dyn DatasetStore,
+    embedder: Option<&dyn EmbeddingProvider>,
+    options: &TuiOptions,
+    query: Option<String>,
+) -> anyhow::Result<()> {
+    let mut app = App::new(options.mode);
+    let mut action = match query {
+        Some(query) => {
+            app.set_query(&query);
+            Action::Search
+        }
+        None => Action::None,
+    };
+
+    loop {
+        match action {
+            Action::None => {}
+            Action::Quit => return Ok(()),
+            Action::Search => {
+                let query = app.begin_search();
+                terminal.draw(|frame| app.render(frame))?;
+                let search = SearchOptions {
+                    limit: options.limit,
+                    filters: &options.filters,
+                    strategy: SearchStrategy::Auto,
+                    mode: options.mode,
+                    chunks: false,
+                    rerank: None,
+                    quality_weight: 0.0,
+                    expander: None,
+                    output: SearchOutputArg::Text,
+                    history: None,
+                };
+                match search_hits(store, embedder, &query, &search).await {
+                    Ok(hits) => app.show_results(hits.results),
+                    Err(e) => app.status = format!("Search failed: {:#}", e),
+                }
+            }
+            Action::Open(url) => {
+                app.status = match webbrowser::open(&url) {
+                    Ok(()) => format!("Opened {}", url),
+                    Err(e) => format!("Could not open {}: {}", url, e),
+                };
+            }
+        }
+
+        terminal.draw(|frame| app.render(frame))?;
+        action = if event::poll(TICK)? {
+            match event::read()? {
+                Event::Key(key) if key.kind == KeyEventKind::Press => app.handle_key(key),
+                _ => Action::None,
+            }
+        } else {
+            app.tick(Instant::now())
+        };
+    }
+}
+
+/// What a key press asks the event loop to do.
+#[derive(Debug, PartialEq)]
+enum Action {
+    None,
+    Search,
+    Open(String),
+    Quit,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Focus {
+    Query,
+    Results,
+}
+
+struct App {
+    mode: SearchModeArg,
+    query: String,
+    /// Cursor position in `query`, in characters
+    cursor: usize,
+    /// When the query was last edited, until it is searched
+    edited_at: Option<Instant>,
+    /// Query of the results shown
+    searched: Option<String>,
+    results: Vec<SearchResult>,
+    list: ListState,
+    detail_scroll: u16,
+    focus: Focus,
+    status: String,
+}
+
+impl App {
+    fn new(mode: SearchModeArg) -> Self {
+        Self {
+            mode,
+            query: String::new(),
+            cursor: 0,
+            edited_at: None,
+            searched: None,
+            results: Vec::new(),
+            list: ListState::default(),
+            detail_scroll: 0,
+            focus: Focus::Query,
+            status: "Type a query and press Enter".to_string(),
+        }
+    }
+
+    fn set_query(&mut self, query: &str) {
+        self.query = query.to_string();
+        self.cursor = query.chars().count();
+    }
+
+    fn selected(&self) -> Option<&SearchResult> {
+        self.list.selected().and_then(|i| self.results.get(i))
+    }
+
+    fn handle_key(&mut self, key: KeyEvent) -> Action {
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        match key.code {
+            KeyCode::Char('c') if ctrl => return Action::Quit,
+            KeyCode::Esc => return Action::Quit,
+            KeyCode::Tab | KeyCode::BackTab => {
+                self.focus = match self.focus {
+                    Focus::Query => Focus::Results,
+                    Focus::Results => Focus::Query,
+                };
+            }
+            KeyCode::Up => self.select_offset(-1),
+            KeyCode::Down => self.select_offset(1),
+            KeyCode::PageUp => self.detail_scroll = self.detail_scroll.saturating_sub(DETAIL_PAGE),
+            KeyCode::PageDown => {
+                self.detail_scroll = self.detail_scroll.saturating_add(DETAIL_PAGE)
+            }
+            _ if self.focus == Focus::Results => return self.handle_results_key(key),
+            _ => return self.handle_query_key(key),
+        }
+        Action::None
+    }
+
+    fn handle_results_key(&mut self, key: KeyEvent) -> Action {
+        match key.code {
+            KeyCode::Char('k') => self.select_offset(-1),
+            KeyCode::Char('j') => self.select_offset(1),
+            KeyCode::Char('q') => return Action::Quit,
+            KeyCode::Enter | KeyCode::Char('o') => {
+                if let Some(result) = self.selected() {
+                    return Action::Open(result.dataset.url.clone());
+                }
+            }
+            KeyCode::Char(c) => {
+                // Typing goes back to the search box
+                self.focus = Focus::Query;
+                return self.handle_query_key(KeyEvent::new(KeyCode::Char(c), key.modifiers));
+            }
+            _ => {}
+        }
+        Action::None
+    }
+
+    fn handle_query_key(&mut self, key: KeyEvent) -> Action {
+        let ctrl = key.modifiers.contains(KeyModifiers::CONTROL);
+        match key.code {
+            KeyCode::Enter => return Action::Search,
+            KeyCode::Char('u') if ctrl => {
+                self.query.clear();
+                self.cursor = 0;
+            }
+            KeyCode::Char(c) if !ctrl => {
+                let at = self.byte_index(self.cursor);
+                self.query.insert(at, c);
+                self.cursor += 1;
+            }
+            KeyCode::Backspace if self.cursor > 0 => {
+                self.cursor -= 1;
+                self.query.remove(self.byte_index(self.cursor));
+            }
+            KeyCode::Delete if self.cursor < self.query.chars().count() => {
+                self.query.remove(self.byte_index(self.cursor));
+            }
+            KeyCode::Left => self.cursor = self.cursor.saturating_sub(1),
+            KeyCode::Right => self.cursor = (self.cursor + 1).min(self.query.chars().count()),
+            KeyCode::Home => self.cursor = 0,
+            KeyCode::End => self.cursor = self.query.chars().count(),
+            _ => return Action::None,
+        }
+        self.edited_at = Some(Instant::now());
+        Action::None
+    }
+
+    /// Searches once typing has paused, if the query changed.
+    fn tick(&mut self, now: Instant) -> Action {
+        match self.edited_at {
+            Some(edited_at) if now.duration_since(edited_at) >= SEARCH_DEBOUNCE => {
+                self.edited_at = None;
+                let query = self.query.trim();
+                if query.is_empty() || self.searched.as_deref() == Some(query) {
+                    Action::None
+                } else {
+                    Action::Search
+                }
+            }
+            _ => Action::None,
+        }
+    }
+
+    /// Records that the current query is being searched and returns it.
+    fn begin_search(&mut self) -> String {
+        let query = self.query.trim().to_string();
+        self.edited_at = None;
+        self.searched = Some(query.clone());
+        self.status = format!("Searching for \"{}\"...", query);
+        query
+    }
+
+    fn show_results(&mut self, results: Vec<SearchResult>) {
+        self.status = match results.len() {
+            0 => "No results".to_string(),
+            1 => "1 result".to_string(),
+            n => format!("{} results", n),
+        };
+        self.results = results;
+        self.list.select(if self.results.is_empty() {
+            None
+        } else {
+            Some(0)
+        });
+        self.detail_scroll = 0;
+    }
+
+    fn select_offset(&mut self, offset: isize) {
+        if self.results.is_empty() {
+            return;
+        }
+        let current = self.list.selected().unwrap_or(0);
+        let last = self.results.len() - 1;
+        self.list
+            .select(Some(current.saturating_add_signed(offset).min(last)));
+        self.detail_scroll = 0;
+    }
+
+    fn byte_index(&self, chars: usize) -> usize {
+        self.query
+            .char_indices()
+            .nth(chars)
+            .map_or(self.query.len(), |(i, _)| i)
+    }
+
+    fn render(&mut self, frame: &mut Frame) {
+        let [search_area, main_area, status_area] = Layout::vertical([
+            Constraint::Length(3),
+            Constraint::Min(3),
+            Constraint::Length(1),
+        ])
+        .areas(frame.area());
+        let [list_area, detail_area] =
+            Layout::horizontal([Constraint::Percentage(45), Constraint::Percentage(55)])
+                .areas(main_area);
+
+        let border = |focused: bool| {
+            if focused {
+                fg(Color::Cyan)
+            } else {
+                Style::new()
+            }
+        };
+
+        // Scroll the query horizontally to keep the cursor inside the box
+        let width = search_area.width.saturating_sub(2) as usize;
+        let skip = (self.cursor + 1).saturating_sub(width);
+        let visible: String = self.query.chars().skip(skip).collect();
+        let search = Paragraph::new(visible).block(
+            bordered()
+                .title(format!(" Search ({}) ", self.mode.as_str()))
+                .border_style(border(self.focus == Focus::Query)),
+        );
+        frame.render_widget(search, search_area);
+        if self.focus == Focus::Query {
+            frame.set_cursor_position(Position::new(
+                search_area.x + 1 + (self.cursor - skip) as u16,
+                search_area.y + 1,
+            ));
+        }
+
+        let items: Vec<ListItem> = self
+            .results
+            .iter()
+            .map(|r| {
+                let score = r.similarity_score.clamp(0.0, 1.0);
+                ListItem::new(Line::from(vec![
+                    Span::styled(create_similarity_bar(score), score_style(score)),
+                    Span::raw(format!(" {:>3.0}% ", score * 100.0)),
+                    Span::raw(r.dataset.title.as_str()),
+                ]))
+            })
+            .collect();
+        let list = List::new(items)
+            .block(
+                bordered()
+                    .title(format!(" Results ({}) ", self.results.len()))
+                    .border_style(border(self.focus == Focus::Results)),
+            )
+            .highlight_style(Style::new().add_modifier(Modifier::REVERSED))
+            .highlight_symbol("> ");
+        frame.render_stateful_widget(list, list_area, &mut self.list);
+
+        let detail = Paragraph::new(self.selected().map(detail_text).unwrap_or_default())
+            .wrap(Wrap { trim: false })
+            .scroll((self.detail_scroll, 0))
+            .block(bordered().title(" Dataset "));
+        frame.render_widget(detail, detail_area);
+
+        let keys = match self.focus {
+            Focus::Query => "Enter search · ↑↓ select · Tab results · Esc quit",
+            Focus::Results => "Enter/o open · ↑↓ select · PgUp/PgDn scroll · Tab search · q quit",
+        };
+        let status = Line::from(vec![
+            Span::raw(format!(" {} ", self.status)),
+            Span::styled(format!("  {}", term::render(keys)), fg(Color::DarkGray)),
+        ]);
+        frame.render_widget(Paragraph::new(status), status_area);
+    }
+}
+
+fn score_style(score: f32) -> Style {
+    let color = if score >= 0.75 {
+        Color::Green
+    } else if score >= 0.5 {
+        Color::Yellow
+    } else {
+        Color::Red
+    };
+    fg(color)
+}
+
+/// `color` as the foreground, unless colors are off.
+fn fg(color: Color) -> Style {
+    if term::color() {
+        Style::new().fg(color)
+    } else {
+        Style::new()
+    }
+}
+
+/// A box with ASCII borders in ASCII mode.
+fn bordered() -> Block<'static> {
+    let block = Block::bordered();
+    if term::ascii() {
+        block.border_set(ASCII_BORDER)
+    } else {
+        block
+    }
+}
+
+/// Metadata of the selected dataset for the detail pane.
+fn detail_text(result: &SearchResult) -> Text<'static> {
+    let dataset = &result.dataset;
+    let metadata = &dataset.metadata.0;
+    let field = |label: &str, value: String| {
+        Line::from(vec![
+            Span::styled(format!("{:<10}", label), Style::new().bold()),
+            Span::raw(value),
+        ])
+    };
+
+    let mut lines = vec![
+        Line::from(dataset.title.clone().bold()),
+        Line::default(),
+        field("Score", format!("{:.0}%", result.similarity_score * 100.0)),
+        field("Portal", dataset.source_portal.clone()),
+        Line::from(vec![
+            Span::styled(format!("{:<10}", "URL"), Style::new().bold()),
+            Span::styled(dataset.url.clone(), fg(Color::Cyan).underlined()),
+        ]),
+    ];
+    if let Some(organization) = organization_name(metadata) {
+        lines.push(field("Publisher", organization.to_string()));
+    }
+    if let Some(license) = ["license_title", "license_id"]
+        .iter()
+        .find_map(|key| metadata[key].as_str().filter(|s| !s.is_empty()))
+    {
+        lines.push(field("License", license.to_string()));
+    }
+    if !dataset.formats.is_empty() {
+        lines.push(field("Formats", dataset.formats.join(", ")));
+    }
+    if let Some(modified_at) = dataset.modified_at {
+        lines.push(field(
+            "Modified",
+            modified_at.format("%Y-%m-%d").to_string(),
+        ));
+    }
+    if let Some(quality) = dataset.quality {
+        lines.push(field("Quality", format!("{:.0}%", quality * 100.0)));
+    }
+    let tags: Vec<&str> = metadata["tags"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|tag| tag["name"].as_str())
+        .collect();
+    if !tags.is_empty() {
+        lines.push(field("Tags", tags.join(", ")));
+    }
+    if let Some(description) = dataset.description.as_deref().filter(|d| !d.is_empty()) {
+        lines.push(Line::default());
+        lines.extend(description.lines().map(|line| Line::from(line.to_string())));
+    }
+
+    let resources = metadata["resources"]
+        .as_array()
+        .map_or(&[][..], Vec::as_slice);
+    if !resources.is_empty() {
+        lines.push(Line::default());
+        lines.push(Line::from(
+            format!("Resources ({})", resources.len()).bold(),
+        ));
+        for resource in resources {
+            let name = resource["name"]
+                .as_str()
+                .or(resource["url"].as_str())
+                .unwrap_or("(unnamed)");
+            let format = resource["format"].as_str().unwrap_or_default();
+            let line = if format.is_empty() {
+                format!("  • {}", name)
+            } else {
+                format!("  • {} [{}]", name, format)
+            };
+            lines.push(Line::from(line));
+        }
+    }
+    Text::from(lines)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ceres_core::Dataset;
+    use chrono::Utc;
+    use ratatui::backend::TestBackend;
+    use ratatui::Terminal;
+    use sqlx::types::Json;
+
+    fn result(title: &str, score: f32) -> SearchResult {
+        SearchResult {
+            dataset: Dataset {
+                id: uuid::Uuid::new_v4(),
+                original_id: title.to_lowercase(),
+                source_portal: "https://dati.comune.milano.it".to_string(),
+                url: format!("https://dati.comune.milano.it/dataset/{}", title.len()),
+                title: title.to_string(),
+                description: Some("Rilevazioni giornaliere".to_string()),
+                embedding: None,
+                metadata: Json(serde_json::json!({
+                    "license_title": "CC-BY 4.0",
+                    "organization": {"title": "ARPA"},
+                    "resources": [{"name": "pm10.csv", "format": "CSV"}]
+                })),
+                formats: vec!["CSV".to_string()],
+                first_seen_at: Utc::now(),
+                last_updated_at: Utc::now(),
+                content_hash: None,
+                embedding_model: None,
+                embedded_at: None,
+                modified_at: None,
+                language: None,
+                quality: Some(0.8),
+            },
+            similarity_score: score,
+        }
+    }
+
+    fn press(app: &mut App, code: KeyCode) -> Action {
+        app.handle_key(KeyEvent::new(code, KeyModifiers::NONE))
+    }
+
+    fn type_text(app: &mut App, text: &str) {
+        for c in text.chars() {
+            press(app, KeyCode::Char(c));
+        }
+    }
+
+    #[test]
+    fn test_editing_and_debounced_search() {
+        let mut app = App::new(SearchModeArg::Semantic);
+        type_text(&mut app, "aria");
+        press(&mut app, KeyCode::Left);
+        press(&mut app, KeyCode::Backspace);
+        type_text(&mut app, "è");
+        assert_eq!(app.query, "arèa");
+
+        let edited_at = app.edited_at.unwrap();
+        assert_eq!(app.tick(edited_at), Action::None);
+        assert_eq!(app.tick(edited_at + SEARCH_DEBOUNCE), Action::Search);
+        assert_eq!(app.begin_search(), "arèa");
+
+        // An unchanged query is not searched again
+        press(&mut app, KeyCode::End);
+        let edited_at = app.edited_at.unwrap();
+        assert_eq!(app.tick(edited_at + SEARCH_DEBOUNCE), Action::None);
+        assert_eq!(press(&mut app, KeyCode::Enter), Action::Search);
+    }
+
+    #[test]
+    fn test_results_navigation_and_open() {
+        let mut app = App::new(SearchModeArg::Semantic);
+        app.show_results(vec![result("Aria", 0.9), result("Autobus", 0.4)]);
+        assert_eq!(app.status, "2 results");
+
+        press(&mut app, KeyCode::Down);
+        press(&mut app, KeyCode::Down);
+        assert_eq!(app.list.selected(), Some(1));
+
+        press(&mut app, KeyCode::Tab);
+        let url = app.results[1].dataset.url.clone();
+        assert_eq!(press(&mut app, KeyCode::Char('o')), Action::Open(url));
+
+        // Typing in the results returns to the search box
+        press(&mut app, KeyCode::Char('x'));
+        assert_eq!((app.focus, app.query.as_str()), (Focus::Query, "x"));
+        assert_eq!(press(&mut app, KeyCode::Esc), Action::Quit);
+    }
+
+    #[test]
+    fn test_render() {
+        let mut app = App::new(SearchModeArg::Hybrid);
+        app.set_query("qualità dell'aria");
+        app.show_results(vec![result("Qualità dell'aria PM10", 0.83)]);
+
+        let mut terminal = Terminal::new(TestBackend::new(100, 20)).unwrap();
+        terminal.draw(|frame| app.render(frame)).unwrap();
+        let screen: String = terminal
+            .backend()
+            .buffer()
+            .content()
+            .iter()
+            .map(|cell| cell.symbol())
+            .collect();
+        for expected in [
+            "Search (hybrid)",
+            "[████████░░]  83% Qualità",
+            "Publisher ARPA",
+            "License   CC-BY 4.0",
+            "• pm10.csv [CSV]",
+        ] {
+            assert!(screen.contains(expected), "missing {:?}", expected);
+        }
+    }
+}
@@ -0,0 +1,250 @@
+//! Azure OpenAI embeddings client.
+//!
+//! Azure serves OpenAI models from per-resource endpoints, addresses them by
+//! deployment name instead of model name, and authenticates with an
+//! `api-key` header. Responses use the OpenAI format.
+
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use ceres_core::error::AppError;
+use ceres_core::HttpConfig;
+use reqwest::Client;
+use serde::Serialize;
+
+use crate::embedding::{EmbeddingProvider, DEFAULT_EMBEDDING_DIMENSION};
+use crate::http::client_builder;
+use crate::ollama::{error_for_status, vectors_in_order, EmbeddingResponse};
+
+/// Default Azure OpenAI REST API version.
+pub const DEFAULT_AZURE_API_VERSION: &str = "2024-10-21";
+
+/// Inputs per request (Azure OpenAI limit).
+const MAX_BATCH_SIZE: usize = 2048;
+
+/// HTTP client for an Azure OpenAI embedding deployment.
+///
+/// The deployment must serve a model that can return vectors of the
+/// configured dimension (768 by default), e.g. `text-embedding-3-small` or
+/// `text-embedding-3-large`, which accept a `dimensions` parameter.
+///
+/// # Examples
+///
+/// ```no_run
+/// use ceres_client::AzureOpenAiClient;
+/// use ceres_core::HttpConfig;
+///
+/// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+/// let client = AzureOpenAiClient::new(
+///     "https://my-resource.openai.azure.com",
+///     "embeddings",
+///     "your-api-key",
+///     &HttpConfig::default(),
+/// )?;
+/// let embedding = client.get_embeddings("Hello, world!").await?;
+/// println!("Embedding dimension: {}", embedding.len()); // 768
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct AzureOpenAiClient {
+    client: Client,
+    http_config: HttpConfig,
+    endpoint: String,
+    deployment: String,
+    api_key: String,
+    api_version: String,
+    dimension: usize,
+}
+
+/// Request body for the embeddings endpoint
+#[derive(Serialize)]
+struct EmbeddingRequest<'a> {
+    input: &'a [String],
+    dimensions: usize,
+}
+
+impl AzureOpenAiClient {
+    /// Creates a client for `deployment` on the resource at `endpoint`
+    /// (e.g. `https://my-resource.openai.azure.com`).
+    ///
+    /// # Errors
+    ///
+    /// Returns `AppError::InvalidUrl` if `endpoint` is not a valid URL.
+    pub fn new(
+        endpoint: &str,
+        deployment: &str,
+        api_key: &str,
+        http_config: &HttpConfig,
+    ) -> Result<Self, AppError> {
+        reqwest::Url::parse(endpoint).map_err(|e| AppError::InvalidUrl(e.to_string()))?;
+
+        let client = client_builder(http_config)?
+            .build()
+            .map_err(|e| AppError::ClientError(e.to_string()))?;
+
+        Ok(Self {
+            client,
+            http_config: http_config.clone(),
+            endpoint: endpoint.trim_end_matches('/').to_string(),
+            deployment: deployment.to_string(),
+            api_key: api_key.to_string(),
+            api_version: DEFAULT_AZURE_API_VERSION.to_string(),
+            dimension: DEFAULT_EMBEDDING_DIMENSION,
+        })
+    }
+
+    /// Returns a client using a different deployment.
+    pub fn with_deployment(mut self, deployment: &str) -> Self {
+        self.deployment = deployment.to_string();
+        self
+    }
+
+    /// Returns a client using a different REST API version.
+    pub fn with_api_version(mut self, api_version: &str) -> Self {
+        self.api_version = api_version.to_string();
+        self
+    }
+
+    /// Returns a client requesting vectors of `dimension` floats.
+    pub fn with_dimension(mut self, dimension: usize) -> Self {
+        self.dimension = dimension;
+        self
+    }
+
+    /// Returns the deployment name, which identifies the model.
+    pub fn deployment(&self) -> &str {
+        &self.deployment
+    }
+
+    fn embeddings_url(&self) -> String {
+        format!(
+            "{}/openai/deployments/{}/embeddings?api-version={}",
+            self.endpoint, self.deployment, self.api_version
+        )
+    }
+
+    /// Generates an embedding for a single text.
+    ///
+    /// # Errors
+    ///
+    /// See [`get_embeddings_batch`](Self::get_embeddings_batch).
+    pub async fn get_embeddings(&self, text: &str) -> Result<Vec<f32>, AppError> {
+        let mut vectors = self.get_embeddings_batch(&[text.to_string()]).await?;
+        vectors.pop().ok_or(AppError::EmptyResponse)
+    }
+
+    /// Generates embeddings for several texts, in input order.
+    ///
+    /// # Errors
+    ///
+    /// Returns `AppError::RateLimitExceeded` on HTTP 429,
+    /// `AppError::ClientError` for server errors and `AppError::EmbeddingError`
+    /// if the request is rejected (e.g. unknown deployment, invalid key, or a
+    /// model without the `dimensions` parameter).
+    pub async fn get_embeddings_batch(&self, texts: &[String]) -> Result<Vec<Vec<f32>>, AppError> {
+        let mut vectors = Vec::with_capacity(texts.len());
+        let url = self.embeddings_url();
+
+        for chunk in texts.chunks(MAX_BATCH_SIZE) {
+            let request = EmbeddingRequest {
+                input: chunk,
+                dimensions: self.dimension,
+            };
+
+            let response = self
+                .client
+                .post(&url)
+                .header("api-key", &self.api_key)
+                .json(&request)
+                .send()
+                .await
+                .map_err(|e| {
+                    if e.is_timeout() {
+                        AppError::Timeout(self.http_config.timeout.as_secs())
+                    } else if e.is_connect() {
+                        AppError::NetworkError(format!(
+                            "Cannot reach Azure OpenAI at {}: {}",
+                            self.endpoint, e
+                        ))
+                    } else {
+                        AppError::ClientError(e.to_string())
+                    }
+                })?;
+
+            let status = response.status();
+            if !status.is_success() {
+                let body = response.text().await.unwrap_or_default();
+                return Err(error_for_status(status, &body));
+            }
+
+            let response: EmbeddingResponse = response
+                .json()
+                .await
+                .map_err(|e| AppError::ClientError(format!("Failed to parse response: {}", e)))?;
+            vectors.extend(vectors_in_order(response, chunk.len(), self.dimension)?);
+        }
+
+        Ok(vectors)
+    }
+}
+
+#[async_trait]
+impl EmbeddingProvider for AzureOpenAiClient {
+    fn model_id(&self) -> &str {
+        self.deployment()
+    }
+
+    fn dimension(&self) -> usize {
+        self.dimension
+    }
+
+    fn for_model(&self, model: &str) -> Arc<dyn EmbeddingProvider> {
+        Arc::new(self.clone().with_deployment(model))
+    }
+
+    async fn embed(&self, text: &str) -> Result<Vec<f32>, AppError> {
+        self.get_embeddings(text).await
+    }
+
+    async fn embed_batch(&self, texts: &[String]) -> Result<Vec<Vec<f32>>, AppError> {
+        self.get_embeddings_batch(texts).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_embeddings_url() {
+        let client = AzureOpenAiClient::new(
+            "https://res.openai.azure.com/",
+            "emb",
+            "key",
+            &HttpConfig::default(),
+        )
+        .unwrap()
+        .with_api_version("2024-02-01");
+        assert_eq!(
+            client.embeddings_url(),
+            "https://res.openai.azure.com/openai/deployments/emb/embeddings?api-version=2024-02-01"
+        );
+        assert!(matches!(
+            AzureOpenAiClient::new("not a url", "emb", "key", &HttpConfig::default()),
+            Err(AppError::InvalidUrl(_))
+        ));
+    }
+
+    #[test]
+    fn test_request_serialization() {
+        let texts = vec!["Hello world".to_string()];
+        let request = EmbeddingRequest {
+            input: &texts,
+            dimensions: DEFAULT_EMBEDDING_DIMENSION,
+        };
+        let json = serde_json::to_value(&request).unwrap();
+        assert_eq!(json["input"][0], "Hello world");
+        assert_eq!(json["dimensions"], 768);
+    }
+}
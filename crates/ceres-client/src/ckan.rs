@@ -14,13 +14,26 @@
 //! }
 //! ```
 
+use std::collections::HashSet;
+use std::sync::Arc;
+
+use ceres_core::content_hash::HashField;
 use ceres_core::error::AppError;
 use ceres_core::models::NewDataset;
-use ceres_core::HttpConfig;
+use ceres_core::{HttpConfig, SyncConfig};
+use reqwest::header::{ETAG, IF_NONE_MATCH};
 use reqwest::{Client, StatusCode, Url};
+use serde::de::DeserializeOwned;
 use serde::Deserialize;
 use serde_json::Value;
-use tokio::time::sleep;
+use tokio::sync::OnceCell;
+use tokio::time::{sleep, Instant};
+use tracing::{debug, info, warn};
+
+use crate::concurrency::AdaptiveLimit;
+use crate::http::client_builder;
+use crate::response_cache::ResponseCache;
+use crate::robots::{fetch_policy, robots_path, RobotsGate, RobotsPolicy};
 
 /// Generic wrapper for CKAN API responses.
 ///
@@ -39,6 +52,96 @@ struct CkanResponse<T> {
     result: T,
 }
 
+/// Parses a `package_show` response body for dataset `id`.
+///
+/// A body that is not a CKAN dataset is an `AppError::SerializationError`,
+/// so harvests can tell it from network failures.
+fn parse_package(body: &[u8], id: &str) -> Result<CkanDataset, AppError> {
+    let ckan_resp: CkanResponse<CkanDataset> =
+        serde_json::from_slice(body).map_err(AppError::SerializationError)?;
+
+    if !ckan_resp.success {
+        return Err(AppError::Generic(format!(
+            "CKAN failed to show package {}",
+            id
+        )));
+    }
+
+    Ok(ckan_resp.result)
+}
+
+/// Datasets per `package_search` page when listing through it; CKAN caps
+/// `rows` at 1000 by default.
+const PACKAGE_SEARCH_PAGE_SIZE: usize = 1000;
+
+/// Above this many datasets, listings page through `package_search` rather
+/// than fetching one `package_list` response, which may time out.
+pub const PACKAGE_LIST_MAX_DATASETS: usize = 50_000;
+
+/// How to list a portal's dataset IDs.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ListingStrategy {
+    /// One `package_list` call
+    PackageList,
+    /// `package_search` pages, for large portals or ones without
+    /// `package_list`
+    PackageSearch,
+}
+
+impl std::fmt::Display for ListingStrategy {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.write_str(match self {
+            ListingStrategy::PackageList => "package_list",
+            ListingStrategy::PackageSearch => "package_search",
+        })
+    }
+}
+
+/// What a portal's API supports, from [`CkanClient::probe_capabilities`].
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct CkanCapabilities {
+    /// Version from `status_show`, which some portals hide
+    pub ckan_version: Option<String>,
+    /// Datasets counted by `package_search`, if it works
+    pub dataset_count: Option<usize>,
+    /// Why `package_list` failed
+    pub package_list_error: Option<String>,
+    /// Why `package_search` failed
+    pub package_search_error: Option<String>,
+}
+
+impl CkanCapabilities {
+    /// The best way to list the portal's datasets: `package_list` unless the
+    /// portal is too large for it or lacks it, then `package_search`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `AppError::ClientError` if neither action works.
+    pub fn listing_strategy(&self) -> Result<ListingStrategy, AppError> {
+        let large = self
+            .dataset_count
+            .is_some_and(|n| n > PACKAGE_LIST_MAX_DATASETS);
+        match (&self.package_list_error, &self.package_search_error) {
+            (None, None) if large => Ok(ListingStrategy::PackageSearch),
+            (None, _) => Ok(ListingStrategy::PackageList),
+            (Some(_), None) => Ok(ListingStrategy::PackageSearch),
+            (Some(list_error), Some(search_error)) => Err(AppError::ClientError(format!(
+                "Portal supports neither package_list ({}) nor package_search ({})",
+                list_error, search_error
+            ))),
+        }
+    }
+}
+
+/// Site details returned by the CKAN `status_show` API.
+#[derive(Deserialize, Debug, Clone)]
+pub struct CkanStatus {
+    /// Version of the CKAN software, e.g. "2.10.4"
+    pub ckan_version: String,
+    #[serde(default)]
+    pub site_title: Option<String>,
+}
+
 /// Data Transfer Object for CKAN dataset details.
 ///
 /// This structure represents the core fields returned by the CKAN `package_show` API.
@@ -86,9 +189,10 @@ pub struct CkanDataset {
 ///
 /// ```no_run
 /// use ceres_client::CkanClient;
+/// use ceres_core::{HttpConfig, SyncConfig};
 ///
 /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
-/// let client = CkanClient::new("https://dati.gov.it")?;
+/// let client = CkanClient::new("https://dati.gov.it", &HttpConfig::default(), &SyncConfig::default())?;
 /// let dataset_ids = client.list_package_ids().await?;
 /// println!("Found {} datasets", dataset_ids.len());
 /// # Ok(())
@@ -97,7 +201,16 @@ pub struct CkanDataset {
 #[derive(Clone)]
 pub struct CkanClient {
     client: Client,
+    http_config: HttpConfig,
     base_url: Url,
+    /// The portal host's robots.txt, fetched before the first request and
+    /// shared by clones
+    robots: Arc<OnceCell<RobotsGate>>,
+    /// Raw `package_show` responses kept on disk, if enabled
+    cache: Option<ResponseCache>,
+    /// Requests in flight to the portal, shared by clones, if adaptive
+    /// concurrency is enabled
+    concurrency: Option<AdaptiveLimit>,
 }
 
 impl CkanClient {
@@ -106,6 +219,8 @@ impl CkanClient {
     /// # Arguments
     ///
     /// * `base_url_str` - The base URL of the CKAN portal (e.g., <https://dati.gov.it>)
+    /// * `http_config` - Timeouts, retries, TLS, proxy and response cache
+    /// * `sync_config` - Concurrency, adaptive when `max_concurrency` is set
     ///
     /// # Returns
     ///
@@ -118,19 +233,48 @@ impl CkanClient {
     // TODO(validation): Add optional portal validation on construction
     // Could probe /api/3/action/site_read to verify it's a valid CKAN portal.
     // Add: pub async fn new_validated(url: &str) -> Result<Self, AppError>
-    pub fn new(base_url_str: &str) -> Result<Self, AppError> {
+    pub fn new(
+        base_url_str: &str,
+        http_config: &HttpConfig,
+        sync_config: &SyncConfig,
+    ) -> Result<Self, AppError> {
         let base_url = Url::parse(base_url_str)
             .map_err(|_| AppError::Generic(format!("Invalid CKAN URL: {}", base_url_str)))?;
 
-        let http_config = HttpConfig::default();
-        let client = Client::builder()
-            // TODO(config): Make User-Agent configurable or use version from Cargo.toml
-            .user_agent("Ceres/0.1 (semantic-search-bot)")
-            .timeout(http_config.timeout)
+        if http_config.insecure_skip_verify {
+            warn!(
+                "Not verifying the TLS certificate of {} (--insecure-skip-verify)",
+                base_url
+            );
+        }
+        let client = client_builder(http_config)?
+            .danger_accept_invalid_certs(http_config.insecure_skip_verify)
             .build()
             .map_err(|e| AppError::ClientError(e.to_string()))?;
 
-        Ok(Self { client, base_url })
+        let cache = http_config
+            .response_cache_dir
+            .as_ref()
+            .map(|dir| ResponseCache::new(dir, http_config.response_cache_max_age));
+
+        let concurrency = sync_config
+            .max_concurrency
+            .map(|max| AdaptiveLimit::new(sync_config.concurrency, max));
+
+        Ok(Self {
+            client,
+            http_config: http_config.clone(),
+            base_url,
+            robots: Arc::new(OnceCell::new()),
+            cache,
+            concurrency,
+        })
+    }
+
+    /// The requests currently allowed in flight to the portal, if
+    /// adaptive concurrency is enabled.
+    pub fn concurrency_limit(&self) -> Option<usize> {
+        self.concurrency.as_ref().map(AdaptiveLimit::current)
     }
 
     /// Fetches the complete list of dataset IDs from the CKAN portal.
@@ -147,19 +291,15 @@ impl CkanClient {
     /// Returns `AppError::ClientError` if the HTTP request fails.
     /// Returns `AppError::Generic` if the CKAN API returns an error.
     ///
-    /// # Performance Note
-    ///
-    /// TODO(performance): Add pagination for large portals
-    /// Large portals can have 100k+ datasets. CKAN supports limit/offset params.
-    /// Consider: `list_package_ids_paginated(limit: usize, offset: usize)`
-    /// Or streaming: `list_package_ids_stream() -> impl Stream<Item = ...>`
+    /// Large portals answer with one huge response; [`Self::list_dataset_ids`]
+    /// pages through `package_search` for them instead.
     pub async fn list_package_ids(&self) -> Result<Vec<String>, AppError> {
         let url = self
             .base_url
             .join("api/3/action/package_list")
             .map_err(|e| AppError::Generic(e.to_string()))?;
 
-        let resp = self.request_with_retry(&url).await?;
+        let resp = self.request_with_retry(&url, None).await?;
 
         let ckan_resp: CkanResponse<Vec<String>> = resp
             .json()
@@ -187,6 +327,9 @@ impl CkanClient {
     /// # Returns
     ///
     /// A `CkanDataset` containing the dataset's metadata.
+    ///
+    /// With a response cache, a fresh cached response is used without a
+    /// request, and a stale one is revalidated with its ETag.
     pub async fn show_package(&self, id: &str) -> Result<CkanDataset, AppError> {
         let mut url = self
             .base_url
@@ -195,45 +338,312 @@ impl CkanClient {
 
         url.query_pairs_mut().append_pair("id", id);
 
-        let resp = self.request_with_retry(&url).await?;
+        let Some(cache) = &self.cache else {
+            let resp = self.request_with_retry(&url, None).await?;
+            let body = resp
+                .bytes()
+                .await
+                .map_err(|e| AppError::ClientError(e.to_string()))?;
+            return parse_package(&body, id);
+        };
+
+        let portal = self.base_url.as_str();
+        let cached = cache.get(portal, id).await;
+        if let Some(cached) = cached.as_ref().filter(|c| c.fresh) {
+            debug!("package_show {} served from the response cache", id);
+            return parse_package(&cached.body, id);
+        }
 
-        let ckan_resp: CkanResponse<CkanDataset> = resp
-            .json()
+        let etag = cached.as_ref().and_then(|c| c.etag.as_deref());
+        let resp = self.request_with_retry(&url, etag).await?;
+        if let (StatusCode::NOT_MODIFIED, Some(cached)) = (resp.status(), &cached) {
+            debug!(
+                "package_show {} unchanged, served from the response cache",
+                id
+            );
+            if let Err(e) = cache.touch(portal, id, etag).await {
+                warn!("Failed to update the response cache: {}", e);
+            }
+            return parse_package(&cached.body, id);
+        }
+
+        let etag = resp
+            .headers()
+            .get(ETAG)
+            .and_then(|v| v.to_str().ok())
+            .map(str::to_string);
+        let body = resp
+            .bytes()
             .await
             .map_err(|e| AppError::ClientError(e.to_string()))?;
+        let dataset = parse_package(&body, id)?;
+        if let Err(e) = cache.put(portal, id, etag.as_deref(), &body).await {
+            warn!("Failed to update the response cache: {}", e);
+        }
+        Ok(dataset)
+    }
+
+    /// Probes which listing actions the portal supports, and its version.
+    ///
+    /// Done once per harvest; failures are recorded rather than returned, so
+    /// [`CkanCapabilities::listing_strategy`] can pick what works.
+    pub async fn probe_capabilities(&self) -> CkanCapabilities {
+        let (status, count, head) = tokio::join!(
+            self.status(),
+            self.package_count(),
+            self.list_package_ids_head(1)
+        );
+        let (dataset_count, package_search_error) = match count {
+            Ok(count) => (Some(count), None),
+            Err(e) => (None, Some(e.to_string())),
+        };
+        CkanCapabilities {
+            ckan_version: status.ok().map(|s| s.ckan_version),
+            dataset_count,
+            package_list_error: head.err().map(|e| e.to_string()),
+            package_search_error,
+        }
+    }
 
+    /// Lists every dataset ID, probing the portal's capabilities first to
+    /// pick the listing strategy it supports best.
+    pub async fn list_dataset_ids(&self) -> Result<Vec<String>, AppError> {
+        let capabilities = self.probe_capabilities().await;
+        let strategy = capabilities.listing_strategy()?;
+        info!(
+            "Listing datasets with {} (CKAN {})",
+            strategy,
+            capabilities
+                .ckan_version
+                .as_deref()
+                .unwrap_or("version unknown")
+        );
+        if let Some(error) = &capabilities.package_list_error {
+            debug!("package_list is unavailable: {}", error);
+        }
+        self.list_dataset_ids_with(strategy).await
+    }
+
+    /// Lists every dataset ID with `strategy`.
+    pub async fn list_dataset_ids_with(
+        &self,
+        strategy: ListingStrategy,
+    ) -> Result<Vec<String>, AppError> {
+        match strategy {
+            ListingStrategy::PackageList => self.list_package_ids().await,
+            ListingStrategy::PackageSearch => self.search_package_ids(None).await,
+        }
+    }
+
+    /// Lists the IDs of the datasets matching the Solr filter query `fq`,
+    /// paging through `package_search`.
+    pub async fn search_dataset_ids(&self, fq: &str) -> Result<Vec<String>, AppError> {
+        self.search_package_ids(Some(fq)).await
+    }
+
+    /// Pages through `package_search` by name, keeping only the IDs of the
+    /// datasets matching `fq`, if any.
+    ///
+    /// Datasets created while paging can shift later pages, so IDs seen
+    /// twice are dropped.
+    async fn search_package_ids(&self, fq: Option<&str>) -> Result<Vec<String>, AppError> {
+        #[derive(Deserialize)]
+        struct IdOnly {
+            id: String,
+        }
+        #[derive(Deserialize)]
+        struct SearchPage {
+            count: usize,
+            results: Vec<IdOnly>,
+        }
+
+        let rows = PACKAGE_SEARCH_PAGE_SIZE.to_string();
+        let mut seen = HashSet::new();
+        let mut ids = Vec::new();
+        let mut start = 0;
+        loop {
+            let start_param = start.to_string();
+            let mut params = vec![
+                ("rows", rows.as_str()),
+                ("start", start_param.as_str()),
+                ("sort", "name asc"),
+                ("fl", "id"),
+            ];
+            if let Some(fq) = fq {
+                params.push(("fq", fq));
+            }
+            // Older CKAN versions ignore `fl` and return whole datasets
+            let page: SearchPage = self.action("package_search", &params).await?;
+            let fetched = page.results.len();
+            ids.extend(
+                page.results
+                    .into_iter()
+                    .map(|r| r.id)
+                    .filter(|id| seen.insert(id.clone())),
+            );
+            start += fetched;
+            debug!(
+                "Listed {}/{} datasets with package_search",
+                start, page.count
+            );
+            if fetched == 0 || start >= page.count {
+                return Ok(ids);
+            }
+        }
+    }
+
+    /// Calls `site_read`, the lightest CKAN action, to check that the API
+    /// answers.
+    pub async fn site_read(&self) -> Result<(), AppError> {
+        self.action::<Value>("site_read", &[]).await.map(|_| ())
+    }
+
+    /// Fetches the portal's CKAN version and site details (`status_show`).
+    pub async fn status(&self) -> Result<CkanStatus, AppError> {
+        self.action("status_show", &[]).await
+    }
+
+    /// Counts the portal's public datasets with a `package_search` returning
+    /// no rows.
+    pub async fn package_count(&self) -> Result<usize, AppError> {
+        #[derive(Deserialize)]
+        struct SearchCount {
+            count: usize,
+        }
+        let result: SearchCount = self.action("package_search", &[("rows", "0")]).await?;
+        Ok(result.count)
+    }
+
+    /// Fetches the first `limit` dataset IDs of `package_list`, the listing
+    /// harvests start from.
+    pub async fn list_package_ids_head(&self, limit: usize) -> Result<Vec<String>, AppError> {
+        self.action("package_list", &[("limit", &limit.to_string())])
+            .await
+    }
+
+    /// Calls a CKAN action and unwraps its `result`.
+    async fn action<T: DeserializeOwned>(
+        &self,
+        action: &str,
+        params: &[(&str, &str)],
+    ) -> Result<T, AppError> {
+        let mut url = self
+            .base_url
+            .join(&format!("api/3/action/{}", action))
+            .map_err(|e| AppError::Generic(e.to_string()))?;
+        if !params.is_empty() {
+            url.query_pairs_mut().extend_pairs(params);
+        }
+
+        let resp = self.request_with_retry(&url, None).await?;
+        let ckan_resp: CkanResponse<T> = resp
+            .json()
+            .await
+            .map_err(|e| AppError::ClientError(e.to_string()))?;
         if !ckan_resp.success {
             return Err(AppError::Generic(format!(
-                "CKAN failed to show package {}",
-                id
+                "CKAN action {} returned success: false",
+                action
             )));
         }
-
         Ok(ckan_resp.result)
     }
 
-    // TODO(observability): Add detailed retry logging
-    // Should log: (1) Attempt number and delay, (2) Reason for retry,
-    // (3) Final error if all retries exhausted. Use tracing crate.
-    async fn request_with_retry(&self, url: &Url) -> Result<reqwest::Response, AppError> {
-        let http_config = HttpConfig::default();
+    /// The portal host's robots.txt rules, fetched on first use unless
+    /// `--ignore-robots-txt` is set.
+    async fn robots(&self) -> &RobotsGate {
+        self.robots
+            .get_or_init(|| async {
+                let http_config = &self.http_config;
+                if http_config.ignore_robots_txt {
+                    return RobotsGate::new(RobotsPolicy::default());
+                }
+                let policy =
+                    fetch_policy(&self.client, &self.base_url, &http_config.user_agent()).await;
+                let disallowed: Vec<&str> = ["package_list", "package_search", "package_show"]
+                    .into_iter()
+                    .filter(|action| {
+                        self.base_url
+                            .join(&format!("api/3/action/{}", action))
+                            .is_ok_and(|url| !policy.is_allowed(&robots_path(&url)))
+                    })
+                    .collect();
+                if !disallowed.is_empty() {
+                    warn!(
+                        "robots.txt of {} disallows the CKAN API actions {}; requests to them are skipped (--ignore-robots-txt to override)",
+                        self.base_url,
+                        disallowed.join(", ")
+                    );
+                }
+                RobotsGate::new(policy)
+            })
+            .await
+    }
+
+    /// GETs `url`, retrying rate limits, server errors, timeouts and failed
+    /// connections. Each attempt and retry is logged at debug level.
+    ///
+    /// Paths the portal's robots.txt disallows fail without a request, and
+    /// each attempt waits for its Crawl-delay. With `if_none_match`, the
+    /// request is conditional and a `304 Not Modified` answer is returned.
+    /// With adaptive concurrency, each attempt also waits for a slot of the
+    /// portal's [`AdaptiveLimit`] and reports how the portal answered.
+    async fn request_with_retry(
+        &self,
+        url: &Url,
+        if_none_match: Option<&str>,
+    ) -> Result<reqwest::Response, AppError> {
+        let http_config = &self.http_config;
         let max_retries = http_config.max_retries;
         let base_delay = http_config.retry_base_delay;
         let mut last_error = AppError::Generic("No attempts made".to_string());
 
+        let robots = self.robots().await;
+        if !robots.policy.is_allowed(&robots_path(url)) {
+            return Err(AppError::ClientError(format!(
+                "{} is disallowed by the portal's robots.txt",
+                url
+            )));
+        }
+
         for attempt in 1..=max_retries {
-            match self.client.get(url.clone()).send().await {
+            robots.wait_turn().await;
+            let mut permit = match &self.concurrency {
+                Some(limit) => Some(limit.acquire().await),
+                None => None,
+            };
+            let started = Instant::now();
+            let mut request = self.client.get(url.clone());
+            if let Some(etag) = if_none_match {
+                request = request.header(IF_NONE_MATCH, etag);
+            }
+            let sent = request.send().await;
+            let elapsed_ms = started.elapsed().as_millis();
+            match sent {
                 Ok(resp) => {
                     let status = resp.status();
-
-                    if status.is_success() {
+                    debug!(
+                        "GET {} -> {} in {} ms (attempt {}/{})",
+                        url, status, elapsed_ms, attempt, max_retries
+                    );
+
+                    if status.is_success()
+                        || (status == StatusCode::NOT_MODIFIED && if_none_match.is_some())
+                    {
+                        if let Some(permit) = permit {
+                            permit.succeeded();
+                        }
                         return Ok(resp);
                     }
 
                     if status == StatusCode::TOO_MANY_REQUESTS {
+                        if let Some(permit) = permit.take() {
+                            permit.overloaded();
+                        }
                         last_error = AppError::RateLimitExceeded;
                         if attempt < max_retries {
                             let delay = base_delay * 2_u32.pow(attempt);
+                            debug!("Rate limited by {}, retrying in {:?}", url, delay);
                             sleep(delay).await;
                             continue;
                         }
@@ -245,7 +655,9 @@ impl CkanClient {
                             status.as_u16()
                         ));
                         if attempt < max_retries {
+                            drop(permit);
                             let delay = base_delay * attempt;
+                            debug!("Server error from {}, retrying in {:?}", url, delay);
                             sleep(delay).await;
                             continue;
                         }
@@ -258,6 +670,14 @@ impl CkanClient {
                     )));
                 }
                 Err(e) => {
+                    debug!(
+                        "GET {} failed after {} ms (attempt {}/{}): {}",
+                        url, elapsed_ms, attempt, max_retries, e
+                    );
+                    match permit {
+                        Some(permit) if e.is_timeout() => permit.overloaded(),
+                        permit => drop(permit),
+                    }
                     if e.is_timeout() {
                         last_error = AppError::Timeout(http_config.timeout.as_secs());
                     } else if e.is_connect() {
@@ -268,6 +688,7 @@ impl CkanClient {
 
                     if attempt < max_retries && (e.is_timeout() || e.is_connect()) {
                         let delay = base_delay * attempt;
+                        debug!("Retrying {} in {:?}", url, delay);
                         sleep(delay).await;
                         continue;
                     }
@@ -287,6 +708,7 @@ impl CkanClient {
     ///
     /// * `dataset` - The CKAN dataset to convert
     /// * `portal_url` - The base URL of the CKAN portal
+    /// * `hash_fields` - Metadata the content hash covers besides title and description
     ///
     /// # Returns
     ///
@@ -308,36 +730,34 @@ impl CkanClient {
     ///
     /// let new_dataset = CkanClient::into_new_dataset(
     ///     ckan_dataset,
-    ///     "https://dati.gov.it"
+    ///     "https://dati.gov.it",
+    ///     &[],
     /// );
     ///
     /// assert_eq!(new_dataset.original_id, "abc-123");
     /// assert_eq!(new_dataset.url, "https://dati.gov.it/dataset/air-quality-data");
     /// assert_eq!(new_dataset.title, "Air Quality Monitoring");
     /// ```
-    pub fn into_new_dataset(dataset: CkanDataset, portal_url: &str) -> NewDataset {
+    pub fn into_new_dataset(
+        dataset: CkanDataset,
+        portal_url: &str,
+        hash_fields: &[HashField],
+    ) -> NewDataset {
         let landing_page = format!(
             "{}/dataset/{}",
             portal_url.trim_end_matches('/'),
             dataset.name
         );
 
-        let metadata_json = serde_json::Value::Object(dataset.extras.clone());
-
-        // Compute content hash for delta detection
-        let content_hash =
-            NewDataset::compute_content_hash(&dataset.title, dataset.notes.as_deref());
-
-        NewDataset {
-            original_id: dataset.id,
-            source_portal: portal_url.to_string(),
-            url: landing_page,
-            title: dataset.title,
-            description: dataset.notes,
-            embedding: None,
-            metadata: metadata_json,
-            content_hash,
-        }
+        NewDataset::from_metadata(
+            dataset.id,
+            portal_url.to_string(),
+            landing_page,
+            dataset.title,
+            dataset.notes,
+            serde_json::Value::Object(dataset.extras),
+            hash_fields,
+        )
     }
 }
 
@@ -347,7 +767,11 @@ mod tests {
 
     #[test]
     fn test_new_with_valid_url() {
-        let result = CkanClient::new("https://dati.gov.it");
+        let result = CkanClient::new(
+            "https://dati.gov.it",
+            &HttpConfig::default(),
+            &SyncConfig::default(),
+        );
         assert!(result.is_ok());
         let client = result.unwrap();
         assert_eq!(client.base_url.as_str(), "https://dati.gov.it/");
@@ -355,7 +779,11 @@ mod tests {
 
     #[test]
     fn test_new_with_invalid_url() {
-        let result = CkanClient::new("not-a-valid-url");
+        let result = CkanClient::new(
+            "not-a-valid-url",
+            &HttpConfig::default(),
+            &SyncConfig::default(),
+        );
         assert!(result.is_err());
 
         if let Err(AppError::Generic(msg)) = result {
@@ -376,7 +804,7 @@ mod tests {
         };
 
         let portal_url = "https://dati.gov.it";
-        let new_dataset = CkanClient::into_new_dataset(ckan_dataset.clone(), portal_url);
+        let new_dataset = CkanClient::into_new_dataset(ckan_dataset.clone(), portal_url, &[]);
 
         assert_eq!(new_dataset.original_id, "dataset-123");
         assert_eq!(new_dataset.source_portal, "https://dati.gov.it");
@@ -389,6 +817,23 @@ mod tests {
             NewDataset::compute_content_hash(&ckan_dataset.title, ckan_dataset.notes.as_deref());
         assert_eq!(new_dataset.content_hash, expected_hash);
         assert_eq!(new_dataset.content_hash.len(), 64);
+        assert!(new_dataset.formats.is_empty());
+    }
+
+    #[test]
+    fn test_into_new_dataset_extracts_formats() {
+        let ckan_dataset: CkanDataset = serde_json::from_str(
+            r#"{
+                "id": "dataset-123",
+                "name": "my-dataset",
+                "title": "My Dataset",
+                "resources": [{"format": "CSV"}, {"format": "geojson"}, {"format": "csv"}]
+            }"#,
+        )
+        .unwrap();
+
+        let new_dataset = CkanClient::into_new_dataset(ckan_dataset, "https://dati.gov.it", &[]);
+        assert_eq!(new_dataset.formats, vec!["CSV", "GEOJSON"]);
     }
 
     #[test]
@@ -403,6 +848,55 @@ mod tests {
         assert_eq!(response.result.len(), 3);
     }
 
+    #[test]
+    fn test_listing_strategy() {
+        let capabilities = CkanCapabilities {
+            ckan_version: Some("2.10.4".to_string()),
+            dataset_count: Some(1_200),
+            ..Default::default()
+        };
+        assert_eq!(
+            capabilities.listing_strategy().unwrap(),
+            ListingStrategy::PackageList
+        );
+
+        let large = CkanCapabilities {
+            dataset_count: Some(PACKAGE_LIST_MAX_DATASETS + 1),
+            ..capabilities.clone()
+        };
+        assert_eq!(
+            large.listing_strategy().unwrap(),
+            ListingStrategy::PackageSearch
+        );
+
+        let without_list = CkanCapabilities {
+            package_list_error: Some("HTTP 403".to_string()),
+            ..capabilities.clone()
+        };
+        assert_eq!(
+            without_list.listing_strategy().unwrap(),
+            ListingStrategy::PackageSearch
+        );
+
+        // An old portal without package_search still lists with package_list
+        let without_search = CkanCapabilities {
+            ckan_version: None,
+            dataset_count: None,
+            package_search_error: Some("HTTP 404".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            without_search.listing_strategy().unwrap(),
+            ListingStrategy::PackageList
+        );
+
+        let neither = CkanCapabilities {
+            package_list_error: Some("HTTP 403".to_string()),
+            ..without_search
+        };
+        assert!(neither.listing_strategy().is_err());
+    }
+
     #[test]
     fn test_ckan_dataset_deserialization() {
         let json = r#"{
@@ -0,0 +1,442 @@
+//! Cohere embeddings client (embed v3 models) and reranker.
+//!
+//! Cohere's v3 models are asymmetric: documents and queries are embedded with
+//! different `input_type`s. Harvests use `search_document`, searches and
+//! watches use `search_query`.
+
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use ceres_core::error::AppError;
+use ceres_core::HttpConfig;
+use reqwest::{Client, StatusCode};
+use serde::{Deserialize, Serialize};
+
+use crate::embedding::{pad_to_dimension, EmbeddingProvider, DEFAULT_EMBEDDING_DIMENSION};
+use crate::http::client_builder;
+use crate::rerank::{scores_in_input_order, Reranker};
+
+/// Default model: multilingual, 384 native dimensions (padded to 768).
+///
+/// The full-size v3 models produce 1024-dimensional vectors, which only fit
+/// a database migrated to at least 1024 dimensions (see `ceres index resize`).
+pub const DEFAULT_COHERE_MODEL: &str = "embed-multilingual-light-v3.0";
+
+/// Maximum number of texts per embed request.
+const MAX_BATCH_SIZE: usize = 96;
+
+/// Default reranking model: multilingual.
+pub const DEFAULT_COHERE_RERANK_MODEL: &str = "rerank-v3.5";
+
+const COHERE_EMBED_URL: &str = "https://api.cohere.com/v2/embed";
+
+const COHERE_RERANK_URL: &str = "https://api.cohere.com/v2/rerank";
+
+/// How Cohere should treat the embedded text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InputType {
+    /// Texts stored in the index
+    SearchDocument,
+    /// Queries compared against stored documents
+    SearchQuery,
+}
+
+/// HTTP client for Cohere's embed API.
+///
+/// # Examples
+///
+/// ```no_run
+/// use ceres_client::cohere::{CohereClient, InputType};
+/// use ceres_core::HttpConfig;
+///
+/// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+/// let client = CohereClient::new("your-api-key", &HttpConfig::default())?;
+/// let vectors = client
+///     .get_embeddings(&["qualità dell'aria".to_string()], InputType::SearchQuery)
+///     .await?;
+/// println!("Embedding dimension: {}", vectors[0].len()); // 768 (padded)
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct CohereClient {
+    client: Client,
+    http_config: HttpConfig,
+    api_key: String,
+    model: String,
+    dimension: usize,
+}
+
+/// Request body for the v2 embed endpoint
+#[derive(Serialize)]
+struct EmbedRequest<'a> {
+    model: &'a str,
+    texts: &'a [String],
+    input_type: InputType,
+    embedding_types: [&'static str; 1],
+}
+
+/// Response from the v2 embed endpoint
+#[derive(Deserialize)]
+struct EmbedResponse {
+    embeddings: EmbeddingsByType,
+}
+
+#[derive(Deserialize)]
+struct EmbeddingsByType {
+    float: Vec<Vec<f32>>,
+}
+
+/// Request body for the v2 rerank endpoint
+#[derive(Serialize)]
+struct RerankRequest<'a> {
+    model: &'a str,
+    query: &'a str,
+    documents: &'a [String],
+    top_n: usize,
+}
+
+/// Response from the v2 rerank endpoint
+#[derive(Deserialize)]
+struct RerankResponse {
+    results: Vec<RerankResult>,
+}
+
+#[derive(Deserialize)]
+struct RerankResult {
+    index: usize,
+    relevance_score: f32,
+}
+
+/// Error body returned by Cohere
+#[derive(Deserialize)]
+struct CohereError {
+    message: String,
+}
+
+/// Maps Cohere's error taxonomy to `AppError`.
+///
+/// See <https://docs.cohere.com/reference/errors>.
+fn classify_cohere_error(status: StatusCode, message: &str) -> AppError {
+    match status.as_u16() {
+        401 | 498 => AppError::ConfigError(format!(
+            "Invalid Cohere API key ({}). Check COHERE_API_KEY",
+            message
+        )),
+        402 => AppError::EmbeddingError(format!("Cohere billing limit reached: {}", message)),
+        429 => AppError::RateLimitExceeded,
+        499 => AppError::ClientError(format!("Cohere request cancelled: {}", message)),
+        500..=599 => AppError::ClientError(format!(
+            "Cohere server error (HTTP {}): {}",
+            status.as_u16(),
+            message
+        )),
+        // 400 bad request, 404 unknown model, 422 unprocessable input, ...
+        code => AppError::EmbeddingError(format!("Cohere HTTP {}: {}", code, message)),
+    }
+}
+
+impl CohereClient {
+    /// Creates a new Cohere client with the specified API key.
+    pub fn new(api_key: &str, http_config: &HttpConfig) -> Result<Self, AppError> {
+        let client = client_builder(http_config)?
+            .build()
+            .map_err(|e| AppError::ClientError(e.to_string()))?;
+
+        Ok(Self {
+            client,
+            http_config: http_config.clone(),
+            api_key: api_key.to_string(),
+            model: DEFAULT_COHERE_MODEL.to_string(),
+            dimension: DEFAULT_EMBEDDING_DIMENSION,
+        })
+    }
+
+    /// Returns a client using a different embedding model.
+    pub fn with_model(mut self, model: &str) -> Self {
+        self.model = model.to_string();
+        self
+    }
+
+    /// Returns a client producing vectors of `dimension` floats.
+    ///
+    /// Smaller native vectors are zero-padded; larger ones are rejected.
+    pub fn with_dimension(mut self, dimension: usize) -> Self {
+        self.dimension = dimension;
+        self
+    }
+
+    /// Returns the embedding model name.
+    pub fn model(&self) -> &str {
+        &self.model
+    }
+
+    /// Embeds texts in input order, in requests of up to 96 texts.
+    ///
+    /// # Errors
+    ///
+    /// Returns `AppError::ConfigError` for an invalid API key,
+    /// `AppError::RateLimitExceeded` on HTTP 429, `AppError::ClientError`
+    /// for server errors, and `AppError::EmbeddingError` for rejected
+    /// requests or vectors larger than the database dimension.
+    pub async fn get_embeddings(
+        &self,
+        texts: &[String],
+        input_type: InputType,
+    ) -> Result<Vec<Vec<f32>>, AppError> {
+        let mut vectors = Vec::with_capacity(texts.len());
+
+        for chunk in texts.chunks(MAX_BATCH_SIZE) {
+            let request = EmbedRequest {
+                model: &self.model,
+                texts: chunk,
+                input_type,
+                embedding_types: ["float"],
+            };
+
+            let response = self
+                .client
+                .post(COHERE_EMBED_URL)
+                .bearer_auth(&self.api_key)
+                .json(&request)
+                .send()
+                .await
+                .map_err(|e| {
+                    if e.is_timeout() {
+                        AppError::Timeout(self.http_config.timeout.as_secs())
+                    } else if e.is_connect() {
+                        AppError::NetworkError(format!("Connection to Cohere failed: {}", e))
+                    } else {
+                        AppError::ClientError(e.to_string())
+                    }
+                })?;
+
+            let status = response.status();
+            if !status.is_success() {
+                let body = response.text().await.unwrap_or_default();
+                let message = serde_json::from_str::<CohereError>(&body)
+                    .map(|e| e.message)
+                    .unwrap_or(body);
+                return Err(classify_cohere_error(status, &message));
+            }
+
+            let response: EmbedResponse = response
+                .json()
+                .await
+                .map_err(|e| AppError::ClientError(format!("Failed to parse response: {}", e)))?;
+
+            if response.embeddings.float.len() != chunk.len() {
+                return Err(AppError::EmbeddingError(format!(
+                    "Expected {} embeddings, got {}",
+                    chunk.len(),
+                    response.embeddings.float.len()
+                )));
+            }
+            for vector in response.embeddings.float {
+                vectors.push(pad_to_dimension(vector, self.dimension)?);
+            }
+        }
+
+        Ok(vectors)
+    }
+
+    async fn embed_one(&self, text: &str, input_type: InputType) -> Result<Vec<f32>, AppError> {
+        let mut vectors = self.get_embeddings(&[text.to_string()], input_type).await?;
+        vectors.pop().ok_or(AppError::EmptyResponse)
+    }
+}
+
+#[async_trait]
+impl EmbeddingProvider for CohereClient {
+    fn model_id(&self) -> &str {
+        self.model()
+    }
+
+    fn dimension(&self) -> usize {
+        self.dimension
+    }
+
+    fn for_model(&self, model: &str) -> Arc<dyn EmbeddingProvider> {
+        Arc::new(self.clone().with_model(model))
+    }
+
+    async fn embed(&self, text: &str) -> Result<Vec<f32>, AppError> {
+        self.embed_one(text, InputType::SearchDocument).await
+    }
+
+    async fn embed_query(&self, text: &str) -> Result<Vec<f32>, AppError> {
+        self.embed_one(text, InputType::SearchQuery).await
+    }
+
+    async fn embed_batch(&self, texts: &[String]) -> Result<Vec<Vec<f32>>, AppError> {
+        self.get_embeddings(texts, InputType::SearchDocument).await
+    }
+}
+
+/// Cohere Rerank client.
+///
+/// Uses the same `COHERE_API_KEY` as [`CohereClient`]; see the
+/// [`Reranker`] trait for an example.
+#[derive(Clone)]
+pub struct CohereReranker {
+    client: Client,
+    http_config: HttpConfig,
+    api_key: String,
+    model: String,
+}
+
+impl CohereReranker {
+    /// Creates a reranker with the specified API key.
+    pub fn new(api_key: &str, http_config: &HttpConfig) -> Result<Self, AppError> {
+        let client = client_builder(http_config)?
+            .build()
+            .map_err(|e| AppError::ClientError(e.to_string()))?;
+
+        Ok(Self {
+            client,
+            http_config: http_config.clone(),
+            api_key: api_key.to_string(),
+            model: DEFAULT_COHERE_RERANK_MODEL.to_string(),
+        })
+    }
+
+    /// Returns a reranker using a different model.
+    pub fn with_model(mut self, model: &str) -> Self {
+        self.model = model.to_string();
+        self
+    }
+}
+
+#[async_trait]
+impl Reranker for CohereReranker {
+    fn model_id(&self) -> &str {
+        &self.model
+    }
+
+    async fn rerank(&self, query: &str, documents: &[String]) -> Result<Vec<f32>, AppError> {
+        if documents.is_empty() {
+            return Ok(Vec::new());
+        }
+        let request = RerankRequest {
+            model: &self.model,
+            query,
+            documents,
+            top_n: documents.len(),
+        };
+
+        let response = self
+            .client
+            .post(COHERE_RERANK_URL)
+            .bearer_auth(&self.api_key)
+            .json(&request)
+            .send()
+            .await
+            .map_err(|e| {
+                if e.is_timeout() {
+                    AppError::Timeout(self.http_config.timeout.as_secs())
+                } else if e.is_connect() {
+                    AppError::NetworkError(format!("Connection to Cohere failed: {}", e))
+                } else {
+                    AppError::ClientError(e.to_string())
+                }
+            })?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            let message = serde_json::from_str::<CohereError>(&body)
+                .map(|e| e.message)
+                .unwrap_or(body);
+            return Err(classify_cohere_error(status, &message));
+        }
+
+        let response: RerankResponse = response
+            .json()
+            .await
+            .map_err(|e| AppError::ClientError(format!("Failed to parse response: {}", e)))?;
+        scores_in_input_order(
+            documents.len(),
+            response
+                .results
+                .into_iter()
+                .map(|r| (r.index, r.relevance_score)),
+        )
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rerank_request_serialization() {
+        let documents = vec!["Orari autobus".to_string()];
+        let request = RerankRequest {
+            model: DEFAULT_COHERE_RERANK_MODEL,
+            query: "trasporto pubblico",
+            documents: &documents,
+            top_n: 1,
+        };
+
+        let json = serde_json::to_value(&request).unwrap();
+        assert_eq!(json["model"], "rerank-v3.5");
+        assert_eq!(json["documents"][0], "Orari autobus");
+        assert_eq!(json["top_n"], 1);
+
+        let body = r#"{"id":"x","results":[{"index":0,"relevance_score":0.82}],"meta":{}}"#;
+        let response: RerankResponse = serde_json::from_str(body).unwrap();
+        assert_eq!(response.results[0].index, 0);
+        assert_eq!(response.results[0].relevance_score, 0.82);
+    }
+
+    #[test]
+    fn test_request_serialization() {
+        let texts = vec!["Hello world".to_string()];
+        let request = EmbedRequest {
+            model: DEFAULT_COHERE_MODEL,
+            texts: &texts,
+            input_type: InputType::SearchQuery,
+            embedding_types: ["float"],
+        };
+
+        let json = serde_json::to_value(&request).unwrap();
+        assert_eq!(json["model"], "embed-multilingual-light-v3.0");
+        assert_eq!(json["input_type"], "search_query");
+        assert_eq!(json["embedding_types"][0], "float");
+        assert_eq!(json["texts"][0], "Hello world");
+    }
+
+    #[test]
+    fn test_response_parsing() {
+        let body = r#"{"id":"x","embeddings":{"float":[[0.1,0.2]]},"texts":["a"]}"#;
+        let response: EmbedResponse = serde_json::from_str(body).unwrap();
+        assert_eq!(response.embeddings.float, vec![vec![0.1, 0.2]]);
+    }
+
+    #[test]
+    fn test_classify_cohere_error_auth() {
+        let err = classify_cohere_error(StatusCode::UNAUTHORIZED, "invalid api token");
+        assert!(matches!(err, AppError::ConfigError(msg) if msg.contains("COHERE_API_KEY")));
+    }
+
+    #[test]
+    fn test_classify_cohere_error_rate_limit() {
+        let err = classify_cohere_error(StatusCode::TOO_MANY_REQUESTS, "trial key limit");
+        assert!(matches!(err, AppError::RateLimitExceeded));
+        assert!(err.is_retryable());
+    }
+
+    #[test]
+    fn test_classify_cohere_error_server() {
+        let err = classify_cohere_error(StatusCode::SERVICE_UNAVAILABLE, "overloaded");
+        assert!(err.is_retryable());
+    }
+
+    #[test]
+    fn test_classify_cohere_error_bad_request() {
+        let err = classify_cohere_error(StatusCode::NOT_FOUND, "model 'x' not found");
+        assert!(matches!(err, AppError::EmbeddingError(msg) if msg.contains("not found")));
+        assert!(!classify_cohere_error(StatusCode::PAYMENT_REQUIRED, "limit").is_retryable());
+    }
+}
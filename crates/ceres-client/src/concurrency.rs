@@ -0,0 +1,264 @@
+//! Adaptive concurrency of requests to a portal.
+//!
+//! A fixed number of parallel requests is too timid for some portals and
+//! too aggressive for others. [`AdaptiveLimit`] adjusts it per portal the
+//! way TCP adjusts its window (AIMD): every request answered without a
+//! slowdown raises the limit by `1 / limit`, about one more request per
+//! round of `limit` requests, and a `429 Too Many Requests` or a timeout
+//! halves it.
+//!
+//! A request counts as answered without a slowdown while its latency stays
+//! within twice the baseline, the lowest latency seen lately; the baseline
+//! slowly follows higher latencies so it adapts when the portal's usual
+//! speed changes.
+
+use std::pin::pin;
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use tokio::sync::Notify;
+
+/// Latencies up to this multiple of the baseline count as no slowdown.
+const LATENCY_TOLERANCE: u32 = 2;
+
+/// Fraction of the gap to a higher latency the baseline moves per request.
+const BASELINE_DRIFT: u32 = 64;
+
+/// The AIMD state, separate from the waiting so it can be tested with
+/// explicit times.
+#[derive(Debug)]
+struct Controller {
+    limit: f64,
+    max: usize,
+    in_flight: usize,
+    baseline: Option<Duration>,
+    /// When the limit was last halved; overloads of requests sent before
+    /// then were caused by the old limit and do not halve it again
+    decreased_at: Option<Instant>,
+}
+
+impl Controller {
+    fn new(initial: usize, max: usize) -> Self {
+        let max = max.max(1);
+        Self {
+            limit: initial.clamp(1, max) as f64,
+            max,
+            in_flight: 0,
+            baseline: None,
+            decreased_at: None,
+        }
+    }
+
+    fn limit(&self) -> usize {
+        self.limit as usize
+    }
+
+    fn try_acquire(&mut self) -> bool {
+        let free = self.in_flight < self.limit();
+        if free {
+            self.in_flight += 1;
+        }
+        free
+    }
+
+    /// Records a request answered after `latency`. Only requests that
+    /// found the limit in use raise it, so it does not grow while
+    /// something else holds the harvest back.
+    fn on_success(&mut self, latency: Duration, saturated: bool) {
+        let baseline = match self.baseline {
+            Some(baseline) if latency > baseline => {
+                baseline + (latency - baseline) / BASELINE_DRIFT
+            }
+            _ => latency,
+        };
+        self.baseline = Some(baseline);
+        if saturated && latency <= baseline * LATENCY_TOLERANCE {
+            self.limit = (self.limit + 1.0 / self.limit).min(self.max as f64);
+        }
+    }
+
+    /// Records a request sent at `sent_at` that the portal pushed back on.
+    fn on_overload(&mut self, sent_at: Instant, now: Instant) {
+        if self.decreased_at.is_some_and(|at| sent_at < at) {
+            return;
+        }
+        self.limit = (self.limit / 2.0).max(1.0);
+        self.decreased_at = Some(now);
+    }
+}
+
+#[derive(Debug)]
+struct Shared {
+    controller: Mutex<Controller>,
+    released: Notify,
+}
+
+/// A concurrency limit shared by clones, adapted to how the portal
+/// answers.
+///
+/// # Examples
+///
+/// ```
+/// use ceres_client::concurrency::AdaptiveLimit;
+///
+/// # #[tokio::main]
+/// # async fn main() {
+/// let limit = AdaptiveLimit::new(4, 32);
+/// let permit = limit.acquire().await;
+/// // The portal answered with 429 Too Many Requests
+/// permit.overloaded();
+/// assert_eq!(limit.current(), 2);
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct AdaptiveLimit {
+    shared: Arc<Shared>,
+}
+
+impl AdaptiveLimit {
+    /// Starts at `initial` requests in flight, never exceeding `max` nor
+    /// going below one.
+    pub fn new(initial: usize, max: usize) -> Self {
+        Self {
+            shared: Arc::new(Shared {
+                controller: Mutex::new(Controller::new(initial, max)),
+                released: Notify::new(),
+            }),
+        }
+    }
+
+    /// The number of requests currently allowed in flight.
+    pub fn current(&self) -> usize {
+        self.controller().limit()
+    }
+
+    /// Waits until a request may be sent.
+    pub async fn acquire(&self) -> LimitPermit {
+        loop {
+            // Registered before checking, so a release in between is not missed
+            let mut released = pin!(self.shared.released.notified());
+            released.as_mut().enable();
+            {
+                let mut controller = self.controller();
+                if controller.try_acquire() {
+                    let saturated = controller.in_flight >= controller.limit();
+                    return LimitPermit {
+                        limit: self.clone(),
+                        sent_at: Instant::now(),
+                        saturated,
+                    };
+                }
+            }
+            released.await;
+        }
+    }
+
+    fn controller(&self) -> std::sync::MutexGuard<'_, Controller> {
+        self.shared
+            .controller
+            .lock()
+            .unwrap_or_else(|poisoned| poisoned.into_inner())
+    }
+}
+
+/// Permission to send one request. Dropping it without reporting how the
+/// request went frees the slot without adjusting the limit, as for errors
+/// that say nothing about the portal's load.
+#[derive(Debug)]
+pub struct LimitPermit {
+    limit: AdaptiveLimit,
+    sent_at: Instant,
+    /// Whether the request filled the limit when it was sent
+    saturated: bool,
+}
+
+impl LimitPermit {
+    /// Reports that the portal answered; the latency is the time since
+    /// the permit was acquired.
+    pub fn succeeded(self) {
+        let latency = self.sent_at.elapsed();
+        self.limit.controller().on_success(latency, self.saturated);
+    }
+
+    /// Reports a `429 Too Many Requests` or a timeout.
+    pub fn overloaded(self) {
+        self.limit
+            .controller()
+            .on_overload(self.sent_at, Instant::now());
+    }
+}
+
+impl Drop for LimitPermit {
+    fn drop(&mut self) {
+        self.limit.controller().in_flight -= 1;
+        self.limit.shared.released.notify_waiters();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FAST: Duration = Duration::from_millis(100);
+
+    #[test]
+    fn test_additive_increase() {
+        let mut controller = Controller::new(2, 4);
+        for _ in 0..3 {
+            controller.on_success(FAST, true);
+        }
+        assert_eq!(controller.limit(), 3);
+
+        // Slow answers and unsaturated requests hold the limit
+        let mut controller = Controller::new(2, 4);
+        controller.on_success(FAST, false);
+        for _ in 0..10 {
+            controller.on_success(FAST * 5, true);
+            controller.on_success(FAST, false);
+        }
+        assert_eq!(controller.limit(), 2);
+
+        let mut controller = Controller::new(2, 4);
+        for _ in 0..100 {
+            controller.on_success(FAST, true);
+        }
+        assert_eq!(controller.limit(), 4);
+    }
+
+    #[test]
+    fn test_multiplicative_decrease() {
+        let start = Instant::now();
+        let mut controller = Controller::new(16, 32);
+        controller.on_overload(start, start + FAST);
+        assert_eq!(controller.limit(), 8);
+
+        // Requests sent before the decrease do not halve the limit again
+        controller.on_overload(start, start + FAST * 2);
+        assert_eq!(controller.limit(), 8);
+        controller.on_overload(start + FAST * 2, start + FAST * 3);
+        assert_eq!(controller.limit(), 4);
+
+        for i in 4..10 {
+            controller.on_overload(start + FAST * (2 * i), start + FAST * (2 * i + 1));
+        }
+        assert_eq!(controller.limit(), 1);
+    }
+
+    #[tokio::test]
+    async fn test_acquire_waits_for_release() {
+        let limit = AdaptiveLimit::new(1, 4);
+        let permit = limit.acquire().await;
+        let waiting = tokio::spawn({
+            let limit = limit.clone();
+            async move { drop(limit.acquire().await) }
+        });
+        tokio::time::sleep(Duration::from_millis(20)).await;
+        assert!(!waiting.is_finished());
+
+        drop(permit);
+        tokio::time::timeout(Duration::from_secs(1), waiting)
+            .await
+            .unwrap()
+            .unwrap();
+    }
+}
@@ -0,0 +1,146 @@
+//! SMTP client for harvest notifications sent by email.
+
+use std::time::Duration;
+
+use ceres_core::error::AppError;
+use ceres_core::{EmailConfig, SmtpSecurity};
+use lettre::message::header::ContentType;
+use lettre::message::Mailbox;
+use lettre::transport::smtp::authentication::Credentials;
+use lettre::{AsyncSmtpTransport, AsyncTransport, Message, Tokio1Executor};
+
+/// Time allowed for each SMTP command.
+const SMTP_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Sends plain-text emails through the SMTP server of an [`EmailConfig`].
+///
+/// # Examples
+///
+/// ```no_run
+/// use ceres_client::EmailClient;
+/// use ceres_core::EmailConfig;
+///
+/// # async fn example(config: &EmailConfig) -> Result<(), Box<dyn std::error::Error>> {
+/// let client = EmailClient::new(config)?;
+/// client.send("Ceres harvest failed", "milano: timeout").await?;
+/// # Ok(())
+/// # }
+/// ```
+pub struct EmailClient {
+    transport: AsyncSmtpTransport<Tokio1Executor>,
+    from: Mailbox,
+    to: Vec<Mailbox>,
+}
+
+impl EmailClient {
+    /// Creates a client for `config`, reading the password from the
+    /// variable it names.
+    ///
+    /// # Errors
+    ///
+    /// Returns `AppError::ConfigError` if an address is invalid or a user
+    /// name is set without its password variable.
+    pub fn new(config: &EmailConfig) -> Result<Self, AppError> {
+        let parse = |address: &str| {
+            address.parse::<Mailbox>().map_err(|e| {
+                AppError::ConfigError(format!("Invalid email address '{}': {}", address, e))
+            })
+        };
+        let from = parse(&config.from)?;
+        let to = config
+            .to
+            .iter()
+            .map(|address| parse(address))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let host = config.smtp_host.as_str();
+        let tls_error = |e: lettre::transport::smtp::Error| {
+            AppError::ConfigError(format!("Cannot set up TLS for {}: {}", host, e))
+        };
+        let mut builder = match config.smtp_security {
+            SmtpSecurity::Starttls => {
+                AsyncSmtpTransport::<Tokio1Executor>::starttls_relay(host).map_err(tls_error)?
+            }
+            SmtpSecurity::Tls => {
+                AsyncSmtpTransport::<Tokio1Executor>::relay(host).map_err(tls_error)?
+            }
+            SmtpSecurity::None => AsyncSmtpTransport::<Tokio1Executor>::builder_dangerous(host),
+        }
+        .port(config.smtp_port)
+        .timeout(Some(SMTP_TIMEOUT));
+        if let Some(username) = &config.smtp_username {
+            let password = std::env::var(&config.smtp_password_env).map_err(|_| {
+                AppError::ConfigError(format!(
+                    "Set {} to the SMTP password of {}",
+                    config.smtp_password_env, username
+                ))
+            })?;
+            builder = builder.credentials(Credentials::new(username.clone(), password));
+        }
+
+        Ok(Self {
+            transport: builder.build(),
+            from,
+            to,
+        })
+    }
+
+    /// Sends `body` as a plain-text email to every recipient.
+    ///
+    /// # Errors
+    ///
+    /// Returns `AppError::NetworkError` if the server cannot be reached or
+    /// rejects the message.
+    pub async fn send(&self, subject: &str, body: &str) -> Result<(), AppError> {
+        let mut message = Message::builder()
+            .from(self.from.clone())
+            .subject(subject)
+            .header(ContentType::TEXT_PLAIN);
+        for to in &self.to {
+            message = message.to(to.clone());
+        }
+        let message = message
+            .body(body.to_string())
+            .map_err(|e| AppError::Generic(format!("Cannot build email: {}", e)))?;
+
+        self.transport
+            .send(message)
+            .await
+            .map_err(|e| AppError::NetworkError(format!("SMTP delivery failed: {}", e)))?;
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> EmailConfig {
+        serde_json::from_value(serde_json::json!({
+            "smtp_host": "smtp.example.org",
+            "from": "Ceres <ceres@example.org>",
+            "to": ["ops@example.org"],
+        }))
+        .unwrap()
+    }
+
+    #[test]
+    fn test_new_validates_addresses() {
+        assert!(EmailClient::new(&config()).is_ok());
+
+        let invalid = EmailConfig {
+            to: vec!["not an address".to_string()],
+            ..config()
+        };
+        let err = EmailClient::new(&invalid).err().unwrap();
+        assert!(err.to_string().contains("not an address"));
+
+        let without_password = EmailConfig {
+            smtp_username: Some("ceres".to_string()),
+            smtp_password_env: "CERES_TEST_UNSET_SMTP_PASSWORD".to_string(),
+            ..config()
+        };
+        let err = EmailClient::new(&without_password).err().unwrap();
+        assert!(err.to_string().contains("CERES_TEST_UNSET_SMTP_PASSWORD"));
+    }
+}
@@ -0,0 +1,182 @@
+//! Embedding provider abstraction.
+//!
+//! Sync, search and watches only talk to [`EmbeddingProvider`], so adding a
+//! provider means implementing the trait and wiring it into provider
+//! selection in the CLI.
+
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use ceres_core::error::AppError;
+
+/// Default dimension of stored embeddings.
+///
+/// Matches the `vector(768)` column created by the initial migration.
+/// Providers are configured with the dimension the database actually stores
+/// (see `with_dimension` on each client) and must return vectors of exactly
+/// that length.
+pub const DEFAULT_EMBEDDING_DIMENSION: usize = 768;
+
+/// A service that turns text into embedding vectors.
+///
+/// # Examples
+///
+/// ```no_run
+/// use std::sync::Arc;
+/// use ceres_client::{EmbeddingProvider, GeminiClient};
+/// use ceres_core::HttpConfig;
+///
+/// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+/// let provider: Arc<dyn EmbeddingProvider> = Arc::new(GeminiClient::new("your-api-key", &HttpConfig::default())?);
+/// let vector = provider.embed("qualità dell'aria").await?;
+/// assert_eq!(vector.len(), provider.dimension());
+/// # Ok(())
+/// # }
+/// ```
+#[async_trait]
+pub trait EmbeddingProvider: Send + Sync {
+    /// Identifier of the model, stored alongside each vector so search only
+    /// compares embeddings from the same model.
+    fn model_id(&self) -> &str;
+
+    /// Length of the vectors returned by this provider.
+    ///
+    /// Providers derived with [`for_model`](Self::for_model) keep it.
+    fn dimension(&self) -> usize;
+
+    /// Returns a provider of the same kind that uses a different model.
+    fn for_model(&self, model: &str) -> Arc<dyn EmbeddingProvider>;
+
+    /// Embeds a single document text.
+    async fn embed(&self, text: &str) -> Result<Vec<f32>, AppError>;
+
+    /// Embeds a search query.
+    ///
+    /// Providers with asymmetric models (e.g. Cohere's `input_type`) embed
+    /// queries differently from documents; the default calls
+    /// [`embed`](Self::embed).
+    async fn embed_query(&self, text: &str) -> Result<Vec<f32>, AppError> {
+        self.embed(text).await
+    }
+
+    /// Embeds several texts, returning vectors in input order.
+    ///
+    /// The default implementation calls [`embed`](Self::embed) sequentially;
+    /// providers with a batch endpoint should override it.
+    async fn embed_batch(&self, texts: &[String]) -> Result<Vec<Vec<f32>>, AppError> {
+        let mut vectors = Vec::with_capacity(texts.len());
+        for text in texts {
+            vectors.push(self.embed(text).await?);
+        }
+        Ok(vectors)
+    }
+}
+
+/// Zero-pads a vector to `dimension`.
+///
+/// Padding with zeros preserves norms and dot products, so cosine
+/// similarity between padded vectors equals that of the originals. This
+/// lets models with smaller native dimensions share a wider column.
+///
+/// # Errors
+///
+/// Returns `AppError::EmbeddingError` if the vector is already longer.
+pub fn pad_to_dimension(mut vector: Vec<f32>, dimension: usize) -> Result<Vec<f32>, AppError> {
+    if vector.len() > dimension {
+        return Err(AppError::EmbeddingError(format!(
+            "Model returned {}-dimensional vectors, but the database stores {}",
+            vector.len(),
+            dimension
+        )));
+    }
+    vector.resize(dimension, 0.0);
+    Ok(vector)
+}
+
+/// Checks that a provider produces vectors the database can store.
+///
+/// `stored` is the dimension of the database's embedding column.
+///
+/// # Errors
+///
+/// Returns `AppError::ConfigError` if the dimensions differ, pointing at
+/// `ceres index resize`, which migrates the database to the provider's
+/// dimension.
+pub fn ensure_compatible(provider: &dyn EmbeddingProvider, stored: usize) -> Result<(), AppError> {
+    if provider.dimension() != stored {
+        return Err(AppError::ConfigError(format!(
+            "Embedding model '{}' is configured for {}-dimensional vectors, but the database \
+             stores {}. Run `ceres index resize` to migrate the database (embeddings are \
+             cleared and rebuilt by the next harvest), or set EMBEDDING_DIMENSION={}",
+            provider.model_id(),
+            provider.dimension(),
+            stored,
+            stored
+        )));
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    struct FixedProvider {
+        dimension: usize,
+    }
+
+    #[async_trait]
+    impl EmbeddingProvider for FixedProvider {
+        fn model_id(&self) -> &str {
+            "fixed"
+        }
+
+        fn dimension(&self) -> usize {
+            self.dimension
+        }
+
+        fn for_model(&self, _model: &str) -> Arc<dyn EmbeddingProvider> {
+            Arc::new(FixedProvider {
+                dimension: self.dimension,
+            })
+        }
+
+        async fn embed(&self, text: &str) -> Result<Vec<f32>, AppError> {
+            Ok(vec![text.len() as f32; self.dimension])
+        }
+    }
+
+    #[tokio::test]
+    async fn test_default_embed_batch_preserves_order() {
+        let provider = FixedProvider { dimension: 2 };
+        let texts = vec!["a".to_string(), "abc".to_string()];
+        let vectors = provider.embed_batch(&texts).await.unwrap();
+        assert_eq!(vectors, vec![vec![1.0, 1.0], vec![3.0, 3.0]]);
+    }
+
+    #[test]
+    fn test_pad_to_dimension_preserves_cosine() {
+        let a = vec![0.6_f32, 0.8, 0.0];
+        let b = vec![0.0_f32, 0.6, 0.8];
+        let cosine = |x: &[f32], y: &[f32]| x.iter().zip(y).map(|(p, q)| p * q).sum::<f32>();
+
+        let (pa, pb) = (
+            pad_to_dimension(a.clone(), DEFAULT_EMBEDDING_DIMENSION).unwrap(),
+            pad_to_dimension(b.clone(), DEFAULT_EMBEDDING_DIMENSION).unwrap(),
+        );
+        assert_eq!(pa.len(), DEFAULT_EMBEDDING_DIMENSION);
+        assert_eq!(cosine(&pa, &pb), cosine(&a, &b));
+
+        assert_eq!(pad_to_dimension(a, 1536).unwrap().len(), 1536);
+        assert!(pad_to_dimension(vec![0.0; 769], DEFAULT_EMBEDDING_DIMENSION).is_err());
+    }
+
+    #[test]
+    fn test_ensure_compatible() {
+        assert!(ensure_compatible(&FixedProvider { dimension: 1536 }, 1536).is_ok());
+        assert!(matches!(
+            ensure_compatible(&FixedProvider { dimension: 1536 }, 768),
+            Err(AppError::ConfigError(msg)) if msg.contains("ceres index resize")
+        ));
+    }
+}
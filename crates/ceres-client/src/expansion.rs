@@ -0,0 +1,50 @@
+//! Query expander abstraction.
+//!
+//! `ceres search --expand` also searches a few paraphrases of the query and
+//! merges the results (see [`ceres_core::expansion`]). Implemented by
+//! [`GeminiExpander`](crate::gemini::GeminiExpander) and
+//! [`SynonymExpander`].
+
+use async_trait::async_trait;
+use ceres_core::error::AppError;
+use ceres_core::expansion::SynonymTable;
+
+/// A source of alternative phrasings of search queries.
+#[async_trait]
+pub trait QueryExpander: Send + Sync {
+    /// Returns up to `max` paraphrases of `query`, not including `query`.
+    async fn expand(&self, query: &str, max: usize) -> Result<Vec<String>, AppError>;
+}
+
+/// Expands queries offline from a [`SynonymTable`].
+///
+/// # Examples
+///
+/// ```
+/// use ceres_client::expansion::{QueryExpander, SynonymExpander};
+/// use ceres_core::expansion::SynonymTable;
+///
+/// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+/// let expander = SynonymExpander::new(SynonymTable::builtin());
+/// let paraphrases = expander.expand("PM10", 3).await?;
+/// assert!(paraphrases.contains(&"polveri sottili".to_string()));
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Debug, Clone)]
+pub struct SynonymExpander {
+    table: SynonymTable,
+}
+
+impl SynonymExpander {
+    pub fn new(table: SynonymTable) -> Self {
+        Self { table }
+    }
+}
+
+#[async_trait]
+impl QueryExpander for SynonymExpander {
+    async fn expand(&self, query: &str, max: usize) -> Result<Vec<String>, AppError> {
+        Ok(self.table.expand(query, max))
+    }
+}
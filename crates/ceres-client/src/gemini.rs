@@ -1,27 +1,57 @@
-//! Google Gemini embeddings client.
+//! Google Gemini embeddings client, LLM reranker and query expander.
 //!
-//! # Future Extensions
-//!
-//! TODO: Implement switchable embedding providers (roadmap v0.3+)
-//! Consider creating an `EmbeddingProvider` trait:
-//! ```ignore
-//! #[async_trait]
-//! pub trait EmbeddingProvider: Send + Sync {
-//!     fn dimension(&self) -> usize;
-//!     async fn embed(&self, text: &str) -> Result<Vec<f32>, AppError>;
-//! }
-//! ```
-//!
-//! Potential providers to support:
+//! The first [`EmbeddingProvider`] implementation. Other potential providers:
 //! - OpenAI text-embedding-3-small/large
 //! - Cohere embed-multilingual-v3.0
 //! - E5-multilingual (local, for cross-language search)
-//! - Ollama (local embeddings)
 
+use std::sync::Arc;
+use std::time::Duration;
+
+use async_trait::async_trait;
 use ceres_core::error::{AppError, GeminiErrorDetails, GeminiErrorKind};
 use ceres_core::HttpConfig;
+use reqwest::header::{HeaderMap, RETRY_AFTER};
 use reqwest::Client;
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
+use tokio::time::sleep;
+
+use crate::embedding::{pad_to_dimension, EmbeddingProvider, DEFAULT_EMBEDDING_DIMENSION};
+use crate::expansion::QueryExpander;
+use crate::http::client_builder;
+use crate::rerank::Reranker;
+
+/// Embedding model used when no override is configured.
+pub const DEFAULT_EMBEDDING_MODEL: &str = "text-embedding-004";
+
+/// Generative model scoring relevance for [`GeminiReranker`].
+pub const DEFAULT_GEMINI_RERANK_MODEL: &str = "gemini-2.0-flash";
+
+/// Generative model paraphrasing queries for [`GeminiExpander`].
+pub const DEFAULT_GEMINI_EXPANSION_MODEL: &str = "gemini-2.0-flash";
+
+/// Generative model answering questions for [`GeminiAnswerer`].
+pub const DEFAULT_GEMINI_ANSWER_MODEL: &str = "gemini-2.0-flash";
+
+/// Maximum number of texts per `batchEmbedContents` request.
+const MAX_BATCH_SIZE: usize = 100;
+
+/// Longest `Retry-After` honored; longer waits are cut to this.
+const MAX_RETRY_AFTER: Duration = Duration::from_secs(60);
+
+/// Retrieval role of the embedded text, sent as `taskType`.
+///
+/// Embedding documents and queries with their own task type improves
+/// retrieval quality over untyped embeddings.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum TaskType {
+    /// Texts stored in the index
+    RetrievalDocument,
+    /// Queries compared against stored documents
+    RetrievalQuery,
+}
 
 /// HTTP client for interacting with Google's Gemini Embeddings API.
 ///
@@ -37,11 +67,14 @@ use serde::{Deserialize, Serialize};
 /// # Examples
 ///
 /// ```no_run
-/// use ceres_client::GeminiClient;
+/// use ceres_client::gemini::{GeminiClient, TaskType};
+/// use ceres_core::HttpConfig;
 ///
 /// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
-/// let client = GeminiClient::new("your-api-key")?;
-/// let embedding = client.get_embeddings("Hello, world!").await?;
+/// let client = GeminiClient::new("your-api-key", &HttpConfig::default())?;
+/// let embedding = client
+///     .get_embeddings("Hello, world!", TaskType::RetrievalQuery)
+///     .await?;
 /// println!("Embedding dimension: {}", embedding.len()); // 768
 /// # Ok(())
 /// # }
@@ -49,14 +82,20 @@ use serde::{Deserialize, Serialize};
 #[derive(Clone)]
 pub struct GeminiClient {
     client: Client,
+    http_config: HttpConfig,
     api_key: String,
+    model: String,
+    dimension: usize,
 }
 
 /// Request body for Gemini embedding API
 #[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
 struct EmbeddingRequest {
     model: String,
     content: Content,
+    task_type: TaskType,
+    output_dimensionality: usize,
 }
 
 #[derive(Serialize)]
@@ -69,17 +108,84 @@ struct Part {
     text: String,
 }
 
+/// Request body for Gemini batch embedding API
+#[derive(Serialize)]
+struct BatchEmbeddingRequest {
+    requests: Vec<EmbeddingRequest>,
+}
+
 /// Response from Gemini embedding API
 #[derive(Deserialize)]
 struct EmbeddingResponse {
     embedding: EmbeddingData,
 }
 
+/// Response from Gemini batch embedding API
+#[derive(Deserialize)]
+struct BatchEmbeddingResponse {
+    embeddings: Vec<EmbeddingData>,
+}
+
 #[derive(Deserialize)]
 struct EmbeddingData {
     values: Vec<f32>,
 }
 
+/// Request body for `generateContent`, asking for a JSON array of scores
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct GenerateRequest {
+    contents: Vec<Content>,
+    generation_config: GenerationConfig,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct GenerationConfig {
+    temperature: f32,
+    response_mime_type: &'static str,
+    response_schema: serde_json::Value,
+}
+
+impl GenerateRequest {
+    /// A single-turn request whose answer must match the JSON `schema`.
+    fn json(prompt: String, schema: serde_json::Value, temperature: f32) -> Self {
+        Self {
+            contents: vec![Content {
+                parts: vec![Part { text: prompt }],
+            }],
+            generation_config: GenerationConfig {
+                temperature,
+                response_mime_type: "application/json",
+                response_schema: schema,
+            },
+        }
+    }
+}
+
+/// Response from `generateContent`
+#[derive(Deserialize)]
+struct GenerateResponse {
+    #[serde(default)]
+    candidates: Vec<Candidate>,
+}
+
+#[derive(Deserialize)]
+struct Candidate {
+    content: Option<CandidateContent>,
+}
+
+#[derive(Deserialize)]
+struct CandidateContent {
+    #[serde(default)]
+    parts: Vec<CandidatePart>,
+}
+
+#[derive(Deserialize)]
+struct CandidatePart {
+    text: Option<String>,
+}
+
 /// Error response from Gemini API
 #[derive(Deserialize)]
 struct GeminiError {
@@ -123,67 +229,184 @@ fn classify_gemini_error(status_code: u16, message: &str) -> GeminiErrorKind {
 
 impl GeminiClient {
     /// Creates a new Gemini client with the specified API key.
-    pub fn new(api_key: &str) -> Result<Self, AppError> {
-        let http_config = HttpConfig::default();
-        let client = Client::builder()
-            .timeout(http_config.timeout)
+    pub fn new(api_key: &str, http_config: &HttpConfig) -> Result<Self, AppError> {
+        let client = client_builder(http_config)?
             .build()
             .map_err(|e| AppError::ClientError(e.to_string()))?;
 
         Ok(Self {
             client,
+            http_config: http_config.clone(),
             api_key: api_key.to_string(),
+            model: DEFAULT_EMBEDDING_MODEL.to_string(),
+            dimension: DEFAULT_EMBEDDING_DIMENSION,
         })
     }
 
-    /// Generates text embeddings using Google's text-embedding-004 model.
+    /// Returns a client using a different embedding model.
     ///
-    /// This method converts input text into a 768-dimensional vector representation
-    /// that captures semantic meaning.
+    /// Accepts both `gemini-embedding-001` and `models/gemini-embedding-001`.
+    pub fn with_model(mut self, model: &str) -> Self {
+        self.model = model.trim_start_matches("models/").to_string();
+        self
+    }
+
+    /// Returns the embedding model name (without the `models/` prefix).
+    ///
+    /// Stored alongside each vector so search only compares embeddings
+    /// from the same model.
+    pub fn model(&self) -> &str {
+        &self.model
+    }
+
+    /// Returns a client producing vectors of `dimension` floats.
+    ///
+    /// Sent as `outputDimensionality`; models whose native dimension is
+    /// smaller (text-embedding-004 stops at 768) are zero-padded.
+    pub fn with_dimension(mut self, dimension: usize) -> Self {
+        self.dimension = dimension;
+        self
+    }
+
+    /// Generates text embeddings using the configured model (text-embedding-004 by default).
+    ///
+    /// This method converts input text into a vector of the configured dimension
+    /// (768 by default) that captures semantic meaning.
     ///
     /// # Arguments
     ///
     /// * `text` - The input text to generate embeddings for
+    /// * `task_type` - Whether `text` is a document to index or a search query
     ///
     /// # Returns
     ///
-    /// A vector of 768 floating-point values representing the text embedding.
+    /// A vector of floating-point values representing the text embedding.
     ///
     /// # Errors
     ///
     /// Returns `AppError::ClientError` if the HTTP request fails.
     /// Returns `AppError::Generic` if the API returns an error.
-    pub async fn get_embeddings(&self, text: &str) -> Result<Vec<f32>, AppError> {
+    pub async fn get_embeddings(
+        &self,
+        text: &str,
+        task_type: TaskType,
+    ) -> Result<Vec<f32>, AppError> {
+        let response: EmbeddingResponse = self
+            .post("embedContent", &self.embedding_request(text, task_type))
+            .await?;
+
+        pad_to_dimension(response.embedding.values, self.dimension)
+    }
+
+    /// Generates embeddings for several texts with `batchEmbedContents`.
+    ///
+    /// Texts are sent in chunks of up to 100 per request; vectors are
+    /// returned in input order.
+    ///
+    /// # Errors
+    ///
+    /// Same as [`get_embeddings`](Self::get_embeddings). Also returns
+    /// `AppError::ClientError` if the API returns the wrong number of vectors.
+    pub async fn get_embeddings_batch(
+        &self,
+        texts: &[String],
+        task_type: TaskType,
+    ) -> Result<Vec<Vec<f32>>, AppError> {
+        let mut vectors = Vec::with_capacity(texts.len());
+
+        for chunk in texts.chunks(MAX_BATCH_SIZE) {
+            let request = BatchEmbeddingRequest {
+                requests: chunk
+                    .iter()
+                    .map(|t| self.embedding_request(t, task_type))
+                    .collect(),
+            };
+            let response: BatchEmbeddingResponse =
+                self.post("batchEmbedContents", &request).await?;
+
+            if response.embeddings.len() != chunk.len() {
+                return Err(AppError::ClientError(format!(
+                    "Expected {} embeddings, got {}",
+                    chunk.len(),
+                    response.embeddings.len()
+                )));
+            }
+            for embedding in response.embeddings {
+                vectors.push(pad_to_dimension(embedding.values, self.dimension)?);
+            }
+        }
+
+        Ok(vectors)
+    }
+
+    fn embedding_request(&self, text: &str, task_type: TaskType) -> EmbeddingRequest {
         // Sanitize text - replace newlines with spaces
         let sanitized_text = text.replace('\n', " ");
 
-        // TODO(config): Make API endpoint configurable via GEMINI_API_ENDPOINT env var
-        // Useful for: (1) Proxy servers, (2) Self-hosted alternatives, (3) Testing
-        let url = "https://generativelanguage.googleapis.com/v1beta/models/text-embedding-004:embedContent";
-
-        // TODO(config): Make embedding model configurable via GEMINI_EMBEDDING_MODEL env var
-        // Different models offer different cost/quality tradeoffs:
-        // - text-embedding-004 (current): 768 dimensions
-        // - Future models may have different dimensions - handle dynamically
-        let request_body = EmbeddingRequest {
-            model: "models/text-embedding-004".to_string(),
+        EmbeddingRequest {
+            model: format!("models/{}", self.model),
             content: Content {
                 parts: vec![Part {
                     text: sanitized_text,
                 }],
             },
-        };
+            task_type,
+            output_dimensionality: self.dimension,
+        }
+    }
+
+    /// Sends a request to a model method (`embedContent`, `batchEmbedContents`).
+    ///
+    /// Rate limits, server errors and timeouts are retried up to
+    /// `HttpConfig::max_retries` attempts with exponential backoff, waiting
+    /// for the server's `Retry-After` instead when it sends one.
+    async fn post<B: Serialize, R: DeserializeOwned>(
+        &self,
+        method: &str,
+        body: &B,
+    ) -> Result<R, AppError> {
+        let http_config = &self.http_config;
+        let max_retries = http_config.max_retries;
+        let base_delay = http_config.retry_base_delay;
+
+        let mut attempt = 1;
+        loop {
+            match self.post_once(method, body).await {
+                Ok(response) => return Ok(response),
+                Err((error, retry_after)) if attempt < max_retries && is_transient(&error) => {
+                    let delay = retry_after.unwrap_or(base_delay * 2_u32.pow(attempt));
+                    sleep(delay).await;
+                    attempt += 1;
+                }
+                Err((error, _)) => return Err(error),
+            }
+        }
+    }
+
+    /// Makes a single attempt of [`post`](Self::post). Failures carry the
+    /// server's `Retry-After`, if any.
+    async fn post_once<B: Serialize, R: DeserializeOwned>(
+        &self,
+        method: &str,
+        body: &B,
+    ) -> Result<R, (AppError, Option<Duration>)> {
+        // TODO(config): Make API endpoint configurable via GEMINI_API_ENDPOINT env var
+        // Useful for: (1) Proxy servers, (2) Self-hosted alternatives, (3) Testing
+        let url = format!(
+            "https://generativelanguage.googleapis.com/v1beta/models/{}:{}",
+            self.model, method
+        );
 
         let response = self
             .client
-            .post(url)
+            .post(&url)
             .header("x-goog-api-key", self.api_key.clone())
-            .json(&request_body)
+            .json(body)
             .send()
             .await
             .map_err(|e| {
-                if e.is_timeout() {
-                    AppError::Timeout(30)
+                let error = if e.is_timeout() {
+                    AppError::Timeout(self.http_config.timeout.as_secs())
                 } else if e.is_connect() {
                     AppError::GeminiError(GeminiErrorDetails::new(
                         GeminiErrorKind::NetworkError,
@@ -192,13 +415,15 @@ impl GeminiClient {
                     ))
                 } else {
                     AppError::ClientError(e.to_string())
-                }
+                };
+                (error, None)
             })?;
 
         let status = response.status();
 
         if !status.is_success() {
             let status_code = status.as_u16();
+            let retry_after = retry_after(response.headers());
             let error_text = response.text().await.unwrap_or_default();
 
             // Try to parse as structured Gemini error
@@ -213,29 +438,434 @@ impl GeminiClient {
             let kind = classify_gemini_error(status_code, &message);
 
             // Return structured error
-            return Err(AppError::GeminiError(GeminiErrorDetails::new(
-                kind,
-                message,
-                status_code,
-            )));
+            return Err((
+                AppError::GeminiError(GeminiErrorDetails::new(kind, message, status_code)),
+                retry_after,
+            ));
         }
 
-        let embedding_response: EmbeddingResponse = response
-            .json()
+        response.json().await.map_err(|e| {
+            (
+                AppError::ClientError(format!("Failed to parse response: {}", e)),
+                None,
+            )
+        })
+    }
+}
+
+/// Returns true for failures worth another attempt: any 429, server and
+/// connection errors, and timeouts.
+///
+/// Gemini reports per-minute quotas as `RESOURCE_EXHAUSTED` with a quota
+/// message, so 429s classified as `QuotaExceeded` are retried too.
+fn is_transient(error: &AppError) -> bool {
+    match error {
+        AppError::Timeout(_) => true,
+        AppError::GeminiError(details) => details.status_code == 429 || error.is_retryable(),
+        _ => false,
+    }
+}
+
+/// Reads a `Retry-After` header given in seconds, capped at [`MAX_RETRY_AFTER`].
+///
+/// The HTTP-date form is not used by Google APIs and is ignored.
+fn retry_after(headers: &HeaderMap) -> Option<Duration> {
+    let seconds: u64 = headers
+        .get(RETRY_AFTER)?
+        .to_str()
+        .ok()?
+        .trim()
+        .parse()
+        .ok()?;
+    Some(Duration::from_secs(seconds).min(MAX_RETRY_AFTER))
+}
+
+#[async_trait]
+impl EmbeddingProvider for GeminiClient {
+    fn model_id(&self) -> &str {
+        self.model()
+    }
+
+    fn dimension(&self) -> usize {
+        self.dimension
+    }
+
+    fn for_model(&self, model: &str) -> Arc<dyn EmbeddingProvider> {
+        Arc::new(self.clone().with_model(model))
+    }
+
+    async fn embed(&self, text: &str) -> Result<Vec<f32>, AppError> {
+        self.get_embeddings(text, TaskType::RetrievalDocument).await
+    }
+
+    async fn embed_query(&self, text: &str) -> Result<Vec<f32>, AppError> {
+        self.get_embeddings(text, TaskType::RetrievalQuery).await
+    }
+
+    async fn embed_batch(&self, texts: &[String]) -> Result<Vec<Vec<f32>>, AppError> {
+        self.get_embeddings_batch(texts, TaskType::RetrievalDocument)
             .await
-            .map_err(|e| AppError::ClientError(format!("Failed to parse response: {}", e)))?;
+    }
+}
+
+/// Reranker asking a Gemini model to rate each document's relevance.
+///
+/// A generative model reads the query and all candidates in one prompt and
+/// answers with a score per candidate. Uses the same `GEMINI_API_KEY` as
+/// [`GeminiClient`], and the same retries.
+///
+/// # Examples
+///
+/// ```no_run
+/// use ceres_client::gemini::GeminiReranker;
+/// use ceres_client::Reranker;
+/// use ceres_core::HttpConfig;
+///
+/// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+/// let reranker = GeminiReranker::new("your-api-key", &HttpConfig::default())?;
+/// let scores = reranker
+///     .rerank("orari autobus", &["Fermate e orari TPL".to_string()])
+///     .await?;
+/// println!("Relevance: {:.2}", scores[0]);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct GeminiReranker {
+    client: GeminiClient,
+}
+
+impl GeminiReranker {
+    /// Creates a reranker using [`DEFAULT_GEMINI_RERANK_MODEL`].
+    pub fn new(api_key: &str, http_config: &HttpConfig) -> Result<Self, AppError> {
+        Ok(Self {
+            client: GeminiClient::new(api_key, http_config)?
+                .with_model(DEFAULT_GEMINI_RERANK_MODEL),
+        })
+    }
+
+    /// Returns a reranker using a different generative model.
+    pub fn with_model(self, model: &str) -> Self {
+        Self {
+            client: self.client.with_model(model),
+        }
+    }
+}
+
+/// Builds the prompt asking for one relevance score per document.
+fn rerank_prompt(query: &str, documents: &[String]) -> String {
+    let mut prompt = format!(
+        "Rate how relevant each numbered dataset is to the search query, from 0 \
+         (irrelevant) to 1 (exactly what the user is looking for). Answer with a \
+         JSON array holding one score per dataset, in order.\n\nQuery: {}\n\nDatasets:\n",
+        query
+    );
+    for (i, document) in documents.iter().enumerate() {
+        prompt.push_str(&format!("[{}] {}\n", i + 1, document.replace('\n', " ")));
+    }
+    prompt
+}
+
+/// Returns the text of the first candidate of a `generateContent` response.
+fn response_text(response: GenerateResponse) -> Result<String, AppError> {
+    response
+        .candidates
+        .into_iter()
+        .next()
+        .and_then(|c| c.content)
+        .map(|c| c.parts.into_iter().filter_map(|p| p.text).collect())
+        .ok_or(AppError::EmptyResponse)
+}
+
+/// Reads the scores from a `generateContent` response.
+fn parse_rerank_scores(response: GenerateResponse, count: usize) -> Result<Vec<f32>, AppError> {
+    let text = response_text(response)?;
+    let scores: Vec<f32> = serde_json::from_str(text.trim())
+        .map_err(|e| AppError::ClientError(format!("Failed to parse rerank scores: {}", e)))?;
+    if scores.len() != count {
+        return Err(AppError::ClientError(format!(
+            "Expected {} rerank scores, got {}",
+            count,
+            scores.len()
+        )));
+    }
+    Ok(scores.into_iter().map(|s| s.clamp(0.0, 1.0)).collect())
+}
+
+#[async_trait]
+impl Reranker for GeminiReranker {
+    fn model_id(&self) -> &str {
+        self.client.model()
+    }
+
+    async fn rerank(&self, query: &str, documents: &[String]) -> Result<Vec<f32>, AppError> {
+        if documents.is_empty() {
+            return Ok(Vec::new());
+        }
+        let request = GenerateRequest::json(
+            rerank_prompt(query, documents),
+            serde_json::json!({ "type": "ARRAY", "items": { "type": "NUMBER" } }),
+            0.0,
+        );
+        let response: GenerateResponse = self.client.post("generateContent", &request).await?;
+        parse_rerank_scores(response, documents.len())
+    }
+}
+
+/// Query expander asking a Gemini model for paraphrases.
+///
+/// Paraphrases spell out codes and acronyms and add synonyms, in the
+/// query's language. Uses the same `GEMINI_API_KEY` as [`GeminiClient`].
+#[derive(Clone)]
+pub struct GeminiExpander {
+    client: GeminiClient,
+}
+
+impl GeminiExpander {
+    /// Creates an expander using [`DEFAULT_GEMINI_EXPANSION_MODEL`].
+    pub fn new(api_key: &str, http_config: &HttpConfig) -> Result<Self, AppError> {
+        Ok(Self {
+            client: GeminiClient::new(api_key, http_config)?
+                .with_model(DEFAULT_GEMINI_EXPANSION_MODEL),
+        })
+    }
+}
+
+/// Builds the prompt asking for `max` paraphrases of a search query.
+fn expansion_prompt(query: &str, max: usize) -> String {
+    format!(
+        "Write {} alternative phrasings of this search query for an open data \
+         catalog. Spell out codes and acronyms, use synonyms, and keep the query's \
+         language. Answer with a JSON array of strings.\n\nQuery: {}",
+        max, query
+    )
+}
+
+/// Reads up to `max` distinct paraphrases, other than `query`, from a
+/// `generateContent` response.
+fn parse_paraphrases(
+    response: GenerateResponse,
+    query: &str,
+    max: usize,
+) -> Result<Vec<String>, AppError> {
+    let text = response_text(response)?;
+    let candidates: Vec<String> = serde_json::from_str(text.trim())
+        .map_err(|e| AppError::ClientError(format!("Failed to parse paraphrases: {}", e)))?;
+
+    let mut paraphrases: Vec<String> = Vec::new();
+    for candidate in candidates {
+        let candidate = candidate.trim();
+        let duplicate = candidate.eq_ignore_ascii_case(query.trim())
+            || paraphrases
+                .iter()
+                .any(|p| p.eq_ignore_ascii_case(candidate));
+        if !candidate.is_empty() && !duplicate {
+            paraphrases.push(candidate.to_string());
+        }
+    }
+    paraphrases.truncate(max);
+    Ok(paraphrases)
+}
+
+#[async_trait]
+impl QueryExpander for GeminiExpander {
+    async fn expand(&self, query: &str, max: usize) -> Result<Vec<String>, AppError> {
+        if max == 0 {
+            return Ok(Vec::new());
+        }
+        let request = GenerateRequest::json(
+            expansion_prompt(query, max),
+            serde_json::json!({ "type": "ARRAY", "items": { "type": "STRING" } }),
+            0.4,
+        );
+        let response: GenerateResponse = self.client.post("generateContent", &request).await?;
+        parse_paraphrases(response, query, max)
+    }
+}
+
+/// An answer written from numbered sources.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GroundedAnswer {
+    /// Answer text, citing sources as `[1]`, `[2]`, ...
+    pub text: String,
+    /// Indexes (0-based) of the sources the answer relies on, in citation order
+    pub citations: Vec<usize>,
+}
+
+/// Question answering with a Gemini model, grounded in given sources.
+///
+/// The model is told to answer from the sources only and to say so when
+/// they do not contain the answer. Uses the same `GEMINI_API_KEY` as
+/// [`GeminiClient`].
+///
+/// # Examples
+///
+/// ```no_run
+/// use ceres_client::gemini::GeminiAnswerer;
+/// use ceres_core::HttpConfig;
+///
+/// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+/// let answerer = GeminiAnswerer::new("your-api-key", &HttpConfig::default())?;
+/// let sources = vec!["Qualità dell'aria\nMisure giornaliere di PM10".to_string()];
+/// let answer = answerer
+///     .answer("Dove trovo i dati sulle polveri sottili?", &sources)
+///     .await?;
+/// println!("{}", answer.text);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct GeminiAnswerer {
+    client: GeminiClient,
+}
+
+impl GeminiAnswerer {
+    /// Creates an answerer using [`DEFAULT_GEMINI_ANSWER_MODEL`].
+    pub fn new(api_key: &str, http_config: &HttpConfig) -> Result<Self, AppError> {
+        Ok(Self {
+            client: GeminiClient::new(api_key, http_config)?
+                .with_model(DEFAULT_GEMINI_ANSWER_MODEL),
+        })
+    }
+
+    /// Returns an answerer using a different generative model.
+    pub fn with_model(self, model: &str) -> Self {
+        Self {
+            client: self.client.with_model(model),
+        }
+    }
+
+    /// Returns the generative model name.
+    pub fn model(&self) -> &str {
+        self.client.model()
+    }
+
+    /// Answers `question` from `sources`, citing them by number.
+    pub async fn answer(
+        &self,
+        question: &str,
+        sources: &[String],
+    ) -> Result<GroundedAnswer, AppError> {
+        let request = GenerateRequest::json(
+            answer_prompt(question, sources),
+            serde_json::json!({
+                "type": "OBJECT",
+                "properties": {
+                    "answer": { "type": "STRING" },
+                    "sources": { "type": "ARRAY", "items": { "type": "INTEGER" } }
+                },
+                "required": ["answer", "sources"]
+            }),
+            0.2,
+        );
+        let response: GenerateResponse = self.client.post("generateContent", &request).await?;
+        parse_answer(response, sources.len())
+    }
+}
 
-        Ok(embedding_response.embedding.values)
+/// Builds the prompt asking for an answer grounded in numbered sources.
+fn answer_prompt(question: &str, sources: &[String]) -> String {
+    let mut prompt = format!(
+        "You help people find open data. Answer the question using only the \
+         numbered datasets below, in the question's language. Cite the datasets \
+         you rely on by number in square brackets, e.g. [2]. If none of them \
+         answers the question, say so. Answer with a JSON object: \"answer\" \
+         holds the text, \"sources\" the numbers of the cited datasets.\n\n\
+         Question: {}\n\nDatasets:\n",
+        question
+    );
+    for (i, source) in sources.iter().enumerate() {
+        prompt.push_str(&format!("[{}] {}\n\n", i + 1, source));
     }
+    prompt
+}
+
+/// Structured answer requested from the model
+#[derive(Deserialize)]
+struct AnswerPayload {
+    answer: String,
+    #[serde(default)]
+    sources: Vec<i64>,
+}
+
+/// Reads the answer and its citations of `count` sources from a
+/// `generateContent` response.
+///
+/// Source numbers out of range are dropped, as are repeated ones.
+fn parse_answer(response: GenerateResponse, count: usize) -> Result<GroundedAnswer, AppError> {
+    let text = response_text(response)?;
+    let payload: AnswerPayload = serde_json::from_str(text.trim())
+        .map_err(|e| AppError::ClientError(format!("Failed to parse answer: {}", e)))?;
+
+    let mut citations: Vec<usize> = Vec::new();
+    for number in payload.sources {
+        let Some(index) = usize::try_from(number)
+            .ok()
+            .and_then(|n| n.checked_sub(1))
+            .filter(|&i| i < count)
+        else {
+            continue;
+        };
+        if !citations.contains(&index) {
+            citations.push(index);
+        }
+    }
+    Ok(GroundedAnswer {
+        text: payload.answer.trim().to_string(),
+        citations,
+    })
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_parse_answer_keeps_valid_citations() {
+        let body = r#"{"candidates":[{"content":{"parts":[{"text":"{\"answer\": \" Vedi [2] e [1]. \", \"sources\": [2, 1, 2, 0, 7]}"}]}}]}"#;
+        let answer = parse_answer(serde_json::from_str(body).unwrap(), 3).unwrap();
+        assert_eq!(answer.text, "Vedi [2] e [1].");
+        assert_eq!(answer.citations, vec![1, 0]);
+
+        let body = r#"{"candidates":[{"content":{"parts":[{"text":"not json"}]}}]}"#;
+        assert!(matches!(
+            parse_answer(serde_json::from_str(body).unwrap(), 3),
+            Err(AppError::ClientError(_))
+        ));
+    }
+
+    #[test]
+    fn test_rerank_prompt_numbers_documents() {
+        let documents = vec!["Orari\nautobus".to_string(), "Bilancio".to_string()];
+        let prompt = rerank_prompt("trasporto pubblico", &documents);
+        assert!(prompt.contains("Query: trasporto pubblico"));
+        assert!(prompt.ends_with("[1] Orari autobus\n[2] Bilancio\n"));
+    }
+
+    #[test]
+    fn test_parse_paraphrases() {
+        let body = r#"{"candidates":[{"content":{"parts":[{"text":"[\"PM10\", \"polveri sottili\", \" \", \"Polveri sottili\", \"particolato\", \"pm 10\"]"}]}}]}"#;
+        let paraphrases = parse_paraphrases(serde_json::from_str(body).unwrap(), "pm10", 2);
+        assert_eq!(paraphrases.unwrap(), vec!["polveri sottili", "particolato"]);
+    }
+
+    #[test]
+    fn test_parse_rerank_scores() {
+        let parse =
+            |body: &str, count| parse_rerank_scores(serde_json::from_str(body).unwrap(), count);
+        let body =
+            r#"{"candidates":[{"content":{"parts":[{"text":"[0.9, 1.2, 0]"}],"role":"model"}}]}"#;
+        assert_eq!(parse(body, 3).unwrap(), vec![0.9, 1.0, 0.0]);
+        assert!(matches!(parse(body, 2), Err(AppError::ClientError(_))));
+        assert!(matches!(
+            parse(r#"{"candidates":[]}"#, 1),
+            Err(AppError::EmptyResponse)
+        ));
+    }
+
     #[test]
     fn test_new_client() {
-        let client = GeminiClient::new("test-api-key");
+        let client = GeminiClient::new("test-api-key", &HttpConfig::default());
         assert!(client.is_ok());
     }
 
@@ -255,11 +885,92 @@ mod tests {
                     text: "Hello world".to_string(),
                 }],
             },
+            task_type: TaskType::RetrievalQuery,
+            output_dimensionality: DEFAULT_EMBEDDING_DIMENSION,
         };
 
         let json = serde_json::to_string(&request).unwrap();
         assert!(json.contains("text-embedding-004"));
         assert!(json.contains("Hello world"));
+        assert!(json.contains("\"taskType\":\"RETRIEVAL_QUERY\""));
+        assert!(json.contains("\"outputDimensionality\":768"));
+    }
+
+    #[test]
+    fn test_batch_request_serialization() {
+        let client = GeminiClient::new("test-api-key", &HttpConfig::default()).unwrap();
+        let request = BatchEmbeddingRequest {
+            requests: vec![
+                client.embedding_request("first\nline", TaskType::RetrievalDocument),
+                client.embedding_request("second", TaskType::RetrievalDocument),
+            ],
+        };
+
+        let json = serde_json::to_value(&request).unwrap();
+        assert_eq!(json["requests"].as_array().unwrap().len(), 2);
+        assert_eq!(json["requests"][0]["model"], "models/text-embedding-004");
+        assert_eq!(
+            json["requests"][0]["content"]["parts"][0]["text"],
+            "first line"
+        );
+        assert_eq!(json["requests"][1]["taskType"], "RETRIEVAL_DOCUMENT");
+    }
+
+    #[test]
+    fn test_provider_for_model() {
+        let client = GeminiClient::new("test-api-key", &HttpConfig::default()).unwrap();
+        let provider = client.for_model("gemini-embedding-001");
+        assert_eq!(provider.model_id(), "gemini-embedding-001");
+        assert_eq!(provider.dimension(), DEFAULT_EMBEDDING_DIMENSION);
+
+        let provider = client
+            .with_dimension(1536)
+            .for_model("gemini-embedding-001");
+        assert_eq!(provider.dimension(), 1536);
+    }
+
+    #[test]
+    fn test_with_model() {
+        let client = GeminiClient::new("test-api-key", &HttpConfig::default()).unwrap();
+        assert_eq!(client.model(), DEFAULT_EMBEDDING_MODEL);
+
+        let client = client.with_model("models/gemini-embedding-001");
+        assert_eq!(client.model(), "gemini-embedding-001");
+    }
+
+    #[test]
+    fn test_retry_after() {
+        let mut headers = HeaderMap::new();
+        assert_eq!(retry_after(&headers), None);
+
+        headers.insert(RETRY_AFTER, "7".parse().unwrap());
+        assert_eq!(retry_after(&headers), Some(Duration::from_secs(7)));
+
+        headers.insert(RETRY_AFTER, "3600".parse().unwrap());
+        assert_eq!(retry_after(&headers), Some(MAX_RETRY_AFTER));
+
+        headers.insert(
+            RETRY_AFTER,
+            "Wed, 21 Oct 2015 07:28:00 GMT".parse().unwrap(),
+        );
+        assert_eq!(retry_after(&headers), None);
+    }
+
+    #[test]
+    fn test_is_transient() {
+        let error = |kind| AppError::GeminiError(GeminiErrorDetails::new(kind, "x".into(), 0));
+        assert!(is_transient(&error(GeminiErrorKind::RateLimit)));
+        assert!(is_transient(&error(GeminiErrorKind::ServerError)));
+        assert!(is_transient(&AppError::Timeout(30)));
+        assert!(is_transient(&AppError::GeminiError(
+            GeminiErrorDetails::new(
+                GeminiErrorKind::QuotaExceeded,
+                "Resource has been exhausted (e.g. check quota).".into(),
+                429
+            )
+        )));
+        assert!(!is_transient(&error(GeminiErrorKind::Authentication)));
+        assert!(!is_transient(&AppError::ClientError("bad json".into())));
     }
 
     #[test]
@@ -2,17 +2,71 @@
 //!
 //! This crate provides HTTP clients for interacting with:
 //!
+//! - [`azure`] - Azure OpenAI embedding deployments
 //! - [`ckan`] - CKAN open data portals
+//! - [`cohere`] - Cohere embed v3 API
+//! - [`concurrency`] - Adaptive concurrency of requests to a portal
+//! - [`embedding`] - The [`EmbeddingProvider`] trait implemented by embedding clients
+//! - [`expansion`] - The [`QueryExpander`] trait implemented by query expanders
 //! - [`gemini`] - Google Gemini embeddings API
+//! - `local` - In-process BERT embeddings (feature `local-embeddings`)
+//! - [`ollama`] - Local Ollama or other OpenAI-compatible embedding servers
+//! - [`rate_limit`] - Client-side throttling of embedding calls
+//! - [`rerank`] - The [`Reranker`] trait implemented by reranking clients
+//! - [`registry`] - Portal bundle registries
+//! - [`response_cache`] - On-disk cache of CKAN `package_show` responses
+//! - [`robots`] - robots.txt rules and Crawl-delay of portal hosts
+//! - [`tei`] - Hugging Face Text Embeddings Inference servers
+//! - [`vertex`] - Google Vertex AI embeddings with service-account auth
+//! - [`voyage`] - Voyage AI embeddings API
+//! - [`webhook`] - Webhook endpoints for harvest notifications
+//! - [`wikidata`] - Wikidata entity search for metadata enrichment
 //!
 //! # Overview
 //!
 //! The clients handle authentication, request building, response parsing,
 //! and error handling for their respective APIs.
 
+pub mod azure;
 pub mod ckan;
+pub mod cohere;
+pub mod concurrency;
+#[cfg(feature = "email")]
+pub mod email;
+pub mod embedding;
+pub mod expansion;
 pub mod gemini;
+mod google_auth;
+mod http;
+#[cfg(feature = "local-embeddings")]
+pub mod local;
+pub mod ollama;
+pub mod rate_limit;
+pub mod registry;
+pub mod rerank;
+pub mod response_cache;
+pub mod robots;
+pub mod tei;
+pub mod vertex;
+pub mod voyage;
+pub mod webhook;
+pub mod wikidata;
 
 // Re-export main client types
+pub use azure::AzureOpenAiClient;
 pub use ckan::CkanClient;
+pub use cohere::CohereClient;
+#[cfg(feature = "email")]
+pub use email::EmailClient;
+pub use embedding::{EmbeddingProvider, DEFAULT_EMBEDDING_DIMENSION};
+pub use expansion::QueryExpander;
 pub use gemini::GeminiClient;
+pub use ollama::OllamaClient;
+pub use rate_limit::{RateLimitedProvider, RateLimits};
+pub use registry::{RegistryClient, VerifiedBundle};
+pub use rerank::Reranker;
+pub use tei::TeiClient;
+pub use vertex::VertexClient;
+pub use voyage::VoyageClient;
+pub use webhook::WebhookClient;
+pub use wikidata::WikidataClient;
@@ -0,0 +1,213 @@
+//! In-process embeddings with a BERT sentence-transformer (feature `local-embeddings`).
+//!
+//! Runs a model such as `sentence-transformers/all-MiniLM-L6-v2` on the CPU
+//! with candle, removing network latency and API quotas entirely. Model files
+//! are downloaded from the Hugging Face Hub on first use and cached (see
+//! `HF_HOME`); later runs work offline.
+//!
+//! Vectors are mean-pooled, L2-normalized and zero-padded to the database
+//! dimension, which leaves cosine similarity unchanged.
+
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use candle_core::{Device, Tensor};
+use candle_nn::VarBuilder;
+use candle_transformers::models::bert::{BertModel, Config, DTYPE};
+use ceres_core::error::AppError;
+use tokenizers::{PaddingParams, Tokenizer, TruncationParams};
+use tokio::sync::OnceCell;
+
+use crate::embedding::{pad_to_dimension, EmbeddingProvider, DEFAULT_EMBEDDING_DIMENSION};
+
+/// Default local model (384 native dimensions, padded to the configured dimension).
+pub const DEFAULT_LOCAL_MODEL: &str = "sentence-transformers/all-MiniLM-L6-v2";
+
+/// Texts per forward pass; bounds memory use on large batches.
+const FORWARD_BATCH_SIZE: usize = 32;
+
+/// Embedding provider running a BERT model in-process.
+///
+/// The model is loaded lazily on the first call, so constructing the
+/// provider (and switching models with [`for_model`](EmbeddingProvider::for_model))
+/// is cheap.
+///
+/// # Examples
+///
+/// ```no_run
+/// use ceres_client::local::LocalEmbedder;
+/// use ceres_client::EmbeddingProvider;
+///
+/// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+/// let embedder = LocalEmbedder::new("sentence-transformers/all-MiniLM-L6-v2");
+/// let vector = embedder.embed("qualità dell'aria").await?;
+/// assert_eq!(vector.len(), 768);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct LocalEmbedder {
+    model_id: String,
+    dimension: usize,
+    loaded: Arc<OnceCell<Arc<LoadedModel>>>,
+}
+
+struct LoadedModel {
+    model: BertModel,
+    tokenizer: Tokenizer,
+    device: Device,
+}
+
+impl LocalEmbedder {
+    /// Creates a provider for a Hugging Face Hub model with BERT architecture.
+    pub fn new(model_id: &str) -> Self {
+        Self {
+            model_id: model_id.to_string(),
+            dimension: DEFAULT_EMBEDDING_DIMENSION,
+            loaded: Arc::new(OnceCell::new()),
+        }
+    }
+
+    /// Returns a provider padding vectors to `dimension` floats.
+    pub fn with_dimension(mut self, dimension: usize) -> Self {
+        self.dimension = dimension;
+        self
+    }
+
+    async fn model(&self) -> Result<Arc<LoadedModel>, AppError> {
+        self.loaded
+            .get_or_try_init(|| load_model(self.model_id.clone(), self.dimension))
+            .await
+            .cloned()
+    }
+}
+
+async fn load_model(model_id: String, dimension: usize) -> Result<Arc<LoadedModel>, AppError> {
+    tracing::info!("Loading local embedding model {}", model_id);
+    let api = hf_hub::api::tokio::Api::new().map_err(embedding_error)?;
+    let repo = api.model(model_id.clone());
+
+    let config_path = repo.get("config.json").await.map_err(embedding_error)?;
+    let tokenizer_path = repo.get("tokenizer.json").await.map_err(embedding_error)?;
+    let weights_path = repo
+        .get("model.safetensors")
+        .await
+        .map_err(embedding_error)?;
+
+    tokio::task::spawn_blocking(move || {
+        let config: Config =
+            serde_json::from_str(&std::fs::read_to_string(config_path).map_err(embedding_error)?)?;
+        if config.hidden_size > dimension {
+            return Err(AppError::EmbeddingError(format!(
+                "Model '{}' produces {}-dimensional vectors, but the database stores {}",
+                model_id, config.hidden_size, dimension
+            )));
+        }
+
+        let mut tokenizer = Tokenizer::from_file(tokenizer_path).map_err(embedding_error)?;
+        tokenizer.with_padding(Some(PaddingParams::default()));
+        tokenizer
+            .with_truncation(Some(TruncationParams {
+                max_length: config.max_position_embeddings,
+                ..Default::default()
+            }))
+            .map_err(embedding_error)?;
+
+        let device = Device::Cpu;
+        let weights = std::fs::read(weights_path).map_err(embedding_error)?;
+        let vb = VarBuilder::from_buffered_safetensors(weights, DTYPE, &device)
+            .map_err(embedding_error)?;
+        let model = BertModel::load(vb, &config).map_err(embedding_error)?;
+
+        Ok(Arc::new(LoadedModel {
+            model,
+            tokenizer,
+            device,
+        }))
+    })
+    .await
+    .map_err(embedding_error)?
+}
+
+impl LoadedModel {
+    /// Runs the model and returns mean-pooled, L2-normalized vectors.
+    fn embed(&self, texts: &[String], dimension: usize) -> Result<Vec<Vec<f32>>, AppError> {
+        let encodings = self
+            .tokenizer
+            .encode_batch(texts.to_vec(), true)
+            .map_err(embedding_error)?;
+
+        let ids = encodings
+            .iter()
+            .map(|e| Tensor::new(e.get_ids(), &self.device))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(embedding_error)?;
+        let masks = encodings
+            .iter()
+            .map(|e| Tensor::new(e.get_attention_mask(), &self.device))
+            .collect::<Result<Vec<_>, _>>()
+            .map_err(embedding_error)?;
+
+        let pooled = (|| {
+            let input_ids = Tensor::stack(&ids, 0)?;
+            let attention_mask = Tensor::stack(&masks, 0)?;
+            let token_type_ids = input_ids.zeros_like()?;
+            let hidden = self
+                .model
+                .forward(&input_ids, &token_type_ids, Some(&attention_mask))?;
+
+            // Mean over real tokens only: [batch, seq, hidden] -> [batch, hidden]
+            let mask = attention_mask.to_dtype(DTYPE)?.unsqueeze(2)?;
+            let summed = hidden.broadcast_mul(&mask)?.sum(1)?;
+            let mean = summed.broadcast_div(&mask.sum(1)?)?;
+            let norm = mean.sqr()?.sum_keepdim(1)?.sqrt()?;
+            mean.broadcast_div(&norm)?.to_vec2::<f32>()
+        })()
+        .map_err(embedding_error)?;
+
+        pooled
+            .into_iter()
+            .map(|vector| pad_to_dimension(vector, dimension))
+            .collect()
+    }
+}
+
+fn embedding_error(e: impl std::fmt::Display) -> AppError {
+    AppError::EmbeddingError(e.to_string())
+}
+
+#[async_trait]
+impl EmbeddingProvider for LocalEmbedder {
+    fn model_id(&self) -> &str {
+        &self.model_id
+    }
+
+    fn dimension(&self) -> usize {
+        self.dimension
+    }
+
+    fn for_model(&self, model: &str) -> Arc<dyn EmbeddingProvider> {
+        Arc::new(LocalEmbedder::new(model).with_dimension(self.dimension))
+    }
+
+    async fn embed(&self, text: &str) -> Result<Vec<f32>, AppError> {
+        let mut vectors = self.embed_batch(&[text.to_string()]).await?;
+        vectors.pop().ok_or(AppError::EmptyResponse)
+    }
+
+    async fn embed_batch(&self, texts: &[String]) -> Result<Vec<Vec<f32>>, AppError> {
+        let model = self.model().await?;
+        let texts = texts.to_vec();
+        let dimension = self.dimension;
+
+        tokio::task::spawn_blocking(move || {
+            let mut vectors = Vec::with_capacity(texts.len());
+            for chunk in texts.chunks(FORWARD_BATCH_SIZE) {
+                vectors.extend(model.embed(chunk, dimension)?);
+            }
+            Ok(vectors)
+        })
+        .await
+        .map_err(embedding_error)?
+    }
+}
@@ -0,0 +1,357 @@
+//! Local embedding server client (Ollama or any OpenAI-compatible endpoint).
+//!
+//! Talks to the OpenAI-style `POST {base_url}/embeddings` API, which Ollama
+//! serves under `/v1` and which LM Studio, vLLM, llama.cpp's server and
+//! hosted OpenAI-compatible services also implement. This lets Ceres run
+//! fully offline.
+
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use ceres_core::error::AppError;
+use ceres_core::HttpConfig;
+use reqwest::{Client, StatusCode};
+use serde::{Deserialize, Serialize};
+
+use crate::embedding::{EmbeddingProvider, DEFAULT_EMBEDDING_DIMENSION};
+use crate::http::client_builder;
+
+/// Default base URL of a local Ollama server's OpenAI-compatible API.
+pub const DEFAULT_OLLAMA_BASE_URL: &str = "http://localhost:11434/v1";
+
+/// Default local model; produces 768-dimensional vectors like the database column.
+pub const DEFAULT_OLLAMA_MODEL: &str = "nomic-embed-text";
+
+/// HTTP client for OpenAI-compatible embedding endpoints.
+///
+/// # Examples
+///
+/// ```no_run
+/// use ceres_client::OllamaClient;
+/// use ceres_core::HttpConfig;
+///
+/// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+/// let client = OllamaClient::new("http://localhost:11434/v1", &HttpConfig::default())?.with_model("nomic-embed-text");
+/// let embedding = client.get_embeddings("Hello, world!").await?;
+/// println!("Embedding dimension: {}", embedding.len()); // 768
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct OllamaClient {
+    client: Client,
+    http_config: HttpConfig,
+    base_url: String,
+    model: String,
+    api_key: Option<String>,
+    dimension: usize,
+}
+
+/// Request body for the embeddings endpoint
+#[derive(Serialize)]
+struct EmbeddingRequest<'a> {
+    model: &'a str,
+    input: Vec<String>,
+}
+
+/// Response from the embeddings endpoint
+#[derive(Deserialize)]
+pub(crate) struct EmbeddingResponse {
+    data: Vec<EmbeddingData>,
+}
+
+#[derive(Deserialize)]
+struct EmbeddingData {
+    embedding: Vec<f32>,
+    #[serde(default)]
+    index: usize,
+}
+
+/// Error body (`{"error": {"message": ...}}` or `{"error": "..."}`)
+#[derive(Deserialize)]
+struct ErrorResponse {
+    error: ErrorBody,
+}
+
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum ErrorBody {
+    Message(String),
+    Object { message: String },
+}
+
+impl OllamaClient {
+    /// Creates a client for the server at `base_url` (e.g. `http://localhost:11434/v1`).
+    ///
+    /// # Errors
+    ///
+    /// Returns `AppError::InvalidUrl` if `base_url` is not a valid URL.
+    pub fn new(base_url: &str, http_config: &HttpConfig) -> Result<Self, AppError> {
+        reqwest::Url::parse(base_url).map_err(|e| AppError::InvalidUrl(e.to_string()))?;
+
+        let client = client_builder(http_config)?
+            .build()
+            .map_err(|e| AppError::ClientError(e.to_string()))?;
+
+        Ok(Self {
+            client,
+            http_config: http_config.clone(),
+            base_url: base_url.trim_end_matches('/').to_string(),
+            model: DEFAULT_OLLAMA_MODEL.to_string(),
+            api_key: None,
+            dimension: DEFAULT_EMBEDDING_DIMENSION,
+        })
+    }
+
+    /// Returns a client using a different embedding model.
+    pub fn with_model(mut self, model: &str) -> Self {
+        self.model = model.to_string();
+        self
+    }
+
+    /// Returns a client that sends `api_key` as a bearer token.
+    ///
+    /// Local Ollama servers don't need one; hosted compatible endpoints do.
+    pub fn with_api_key(mut self, api_key: &str) -> Self {
+        self.api_key = Some(api_key.to_string());
+        self
+    }
+
+    /// Returns a client expecting vectors of `dimension` floats.
+    ///
+    /// The server decides the dimension from the model, so this must match
+    /// it exactly, e.g. 1536 for OpenAI's `text-embedding-3-small`.
+    pub fn with_dimension(mut self, dimension: usize) -> Self {
+        self.dimension = dimension;
+        self
+    }
+
+    /// Returns the embedding model name.
+    pub fn model(&self) -> &str {
+        &self.model
+    }
+
+    /// Generates an embedding for a single text.
+    ///
+    /// # Errors
+    ///
+    /// See [`get_embeddings_batch`](Self::get_embeddings_batch).
+    pub async fn get_embeddings(&self, text: &str) -> Result<Vec<f32>, AppError> {
+        let mut vectors = self.get_embeddings_batch(&[text.to_string()]).await?;
+        vectors.pop().ok_or(AppError::EmptyResponse)
+    }
+
+    /// Generates embeddings for several texts in one request, in input order.
+    ///
+    /// # Errors
+    ///
+    /// Returns `AppError::NetworkError` if the server is unreachable,
+    /// `AppError::EmbeddingError` if it rejects the request (e.g. unknown
+    /// model) or returns vectors of a different dimension than configured.
+    pub async fn get_embeddings_batch(&self, texts: &[String]) -> Result<Vec<Vec<f32>>, AppError> {
+        if texts.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let request = EmbeddingRequest {
+            model: &self.model,
+            input: texts.iter().map(|t| t.replace('\n', " ")).collect(),
+        };
+
+        let mut builder = self
+            .client
+            .post(format!("{}/embeddings", self.base_url))
+            .json(&request);
+        if let Some(api_key) = &self.api_key {
+            builder = builder.bearer_auth(api_key);
+        }
+
+        let response = builder.send().await.map_err(|e| {
+            if e.is_timeout() {
+                AppError::Timeout(self.http_config.timeout.as_secs())
+            } else if e.is_connect() {
+                AppError::NetworkError(format!(
+                    "Cannot reach embedding server at {}: {}",
+                    self.base_url, e
+                ))
+            } else {
+                AppError::ClientError(e.to_string())
+            }
+        })?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            return Err(error_for_status(status, &body));
+        }
+
+        let response: EmbeddingResponse = response
+            .json()
+            .await
+            .map_err(|e| AppError::ClientError(format!("Failed to parse response: {}", e)))?;
+
+        vectors_in_order(response, texts.len(), self.dimension)
+    }
+}
+
+/// Maps a non-2xx response to an error, keeping the server's message.
+pub(crate) fn error_for_status(status: StatusCode, body: &str) -> AppError {
+    let message = match serde_json::from_str::<ErrorResponse>(body) {
+        Ok(ErrorResponse {
+            error: ErrorBody::Message(message) | ErrorBody::Object { message },
+        }) => message,
+        Err(_) => body.to_string(),
+    };
+
+    if status == StatusCode::TOO_MANY_REQUESTS {
+        AppError::RateLimitExceeded
+    } else if status.is_server_error() {
+        AppError::ClientError(format!("HTTP {}: {}", status.as_u16(), message))
+    } else {
+        AppError::EmbeddingError(format!("HTTP {}: {}", status.as_u16(), message))
+    }
+}
+
+/// Sorts response vectors by `index` and checks their count and dimension.
+pub(crate) fn vectors_in_order(
+    mut response: EmbeddingResponse,
+    expected: usize,
+    dimension: usize,
+) -> Result<Vec<Vec<f32>>, AppError> {
+    if response.data.len() != expected {
+        return Err(AppError::EmbeddingError(format!(
+            "Expected {} embeddings, got {}",
+            expected,
+            response.data.len()
+        )));
+    }
+    response.data.sort_by_key(|d| d.index);
+
+    if let Some(bad) = response
+        .data
+        .iter()
+        .find(|d| d.embedding.len() != dimension)
+    {
+        return Err(AppError::EmbeddingError(format!(
+            "Model returned {}-dimensional vectors, but the database stores {}",
+            bad.embedding.len(),
+            dimension
+        )));
+    }
+
+    Ok(response.data.into_iter().map(|d| d.embedding).collect())
+}
+
+#[async_trait]
+impl EmbeddingProvider for OllamaClient {
+    fn model_id(&self) -> &str {
+        self.model()
+    }
+
+    fn dimension(&self) -> usize {
+        self.dimension
+    }
+
+    fn for_model(&self, model: &str) -> Arc<dyn EmbeddingProvider> {
+        Arc::new(self.clone().with_model(model))
+    }
+
+    async fn embed(&self, text: &str) -> Result<Vec<f32>, AppError> {
+        self.get_embeddings(text).await
+    }
+
+    async fn embed_batch(&self, texts: &[String]) -> Result<Vec<Vec<f32>>, AppError> {
+        self.get_embeddings_batch(texts).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn response(items: &[(usize, usize)]) -> EmbeddingResponse {
+        EmbeddingResponse {
+            data: items
+                .iter()
+                .map(|&(index, dim)| EmbeddingData {
+                    embedding: vec![index as f32; dim],
+                    index,
+                })
+                .collect(),
+        }
+    }
+
+    #[test]
+    fn test_new_client() {
+        let client =
+            OllamaClient::new("http://localhost:11434/v1/", &HttpConfig::default()).unwrap();
+        assert_eq!(client.base_url, "http://localhost:11434/v1");
+        assert_eq!(client.model(), DEFAULT_OLLAMA_MODEL);
+        assert!(matches!(
+            OllamaClient::new("not a url", &HttpConfig::default()),
+            Err(AppError::InvalidUrl(_))
+        ));
+    }
+
+    #[test]
+    fn test_request_serialization() {
+        let request = EmbeddingRequest {
+            model: "nomic-embed-text",
+            input: vec!["Hello world".to_string()],
+        };
+        let json = serde_json::to_value(&request).unwrap();
+        assert_eq!(json["model"], "nomic-embed-text");
+        assert_eq!(json["input"][0], "Hello world");
+    }
+
+    #[test]
+    fn test_vectors_sorted_by_index() {
+        let vectors = vectors_in_order(
+            response(&[
+                (1, DEFAULT_EMBEDDING_DIMENSION),
+                (0, DEFAULT_EMBEDDING_DIMENSION),
+            ]),
+            2,
+            DEFAULT_EMBEDDING_DIMENSION,
+        )
+        .unwrap();
+        assert_eq!(vectors[0][0], 0.0);
+        assert_eq!(vectors[1][0], 1.0);
+    }
+
+    #[test]
+    fn test_vectors_wrong_dimension() {
+        let result = vectors_in_order(response(&[(0, 384)]), 1, DEFAULT_EMBEDDING_DIMENSION);
+        assert!(matches!(result, Err(AppError::EmbeddingError(msg)) if msg.contains("384")));
+
+        assert!(vectors_in_order(response(&[(0, 1536)]), 1, 1536).is_ok());
+    }
+
+    #[test]
+    fn test_vectors_wrong_count() {
+        let result = vectors_in_order(
+            response(&[(0, DEFAULT_EMBEDDING_DIMENSION)]),
+            2,
+            DEFAULT_EMBEDDING_DIMENSION,
+        );
+        assert!(matches!(result, Err(AppError::EmbeddingError(_))));
+    }
+
+    #[test]
+    fn test_error_for_status_keeps_message() {
+        let err = error_for_status(
+            StatusCode::NOT_FOUND,
+            r#"{"error":{"message":"model \"foo\" not found, try pulling it first"}}"#,
+        );
+        assert!(matches!(err, AppError::EmbeddingError(msg) if msg.contains("try pulling")));
+
+        let err = error_for_status(StatusCode::BAD_REQUEST, r#"{"error":"invalid input"}"#);
+        assert!(matches!(err, AppError::EmbeddingError(msg) if msg.contains("invalid input")));
+
+        assert!(matches!(
+            error_for_status(StatusCode::TOO_MANY_REQUESTS, ""),
+            AppError::RateLimitExceeded
+        ));
+        assert!(error_for_status(StatusCode::BAD_GATEWAY, "").is_retryable());
+    }
+}
@@ -0,0 +1,242 @@
+//! Client-side rate limiting of embedding calls.
+//!
+//! Sync workers embed concurrently, which easily bursts past a provider's
+//! per-minute quota. [`RateLimitedProvider`] wraps a provider so every call
+//! first reserves capacity from a limiter shared by all clones, and waits
+//! until the reservation is covered instead of failing.
+
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+use async_trait::async_trait;
+use ceres_core::error::AppError;
+use tokio::time::sleep;
+
+use crate::embedding::EmbeddingProvider;
+
+/// Per-minute limits for embedding calls; `None` leaves a dimension unlimited.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct RateLimits {
+    pub requests_per_minute: Option<u32>,
+    /// Estimated input tokens, see [`estimate_tokens`]
+    pub tokens_per_minute: Option<u32>,
+}
+
+impl RateLimits {
+    /// Returns true if no limit is set.
+    pub fn is_unlimited(&self) -> bool {
+        self.requests_per_minute.is_none() && self.tokens_per_minute.is_none()
+    }
+}
+
+/// Estimates the tokens of a text as one per four characters, rounded up.
+///
+/// Providers tokenize differently; the estimate only needs to keep usage
+/// in the right range of the quota.
+pub fn estimate_tokens(text: &str) -> u32 {
+    text.chars().count().div_ceil(4) as u32
+}
+
+/// A token bucket holding up to a minute's allowance.
+///
+/// Reservations may drive the balance negative; the caller then waits
+/// until refills cover the debt, which queues concurrent callers in
+/// reservation order.
+#[derive(Debug)]
+struct Bucket {
+    capacity: f64,
+    available: f64,
+    refilled_at: Instant,
+}
+
+impl Bucket {
+    fn new(per_minute: u32, now: Instant) -> Self {
+        let capacity = per_minute.max(1) as f64;
+        Self {
+            capacity,
+            available: capacity,
+            refilled_at: now,
+        }
+    }
+
+    /// Takes `cost` units and returns how long to wait before using them.
+    ///
+    /// Costs above the capacity are capped, so an oversized request waits
+    /// for a full minute's allowance rather than forever.
+    fn reserve(&mut self, cost: u32, now: Instant) -> Duration {
+        let per_second = self.capacity / 60.0;
+        let elapsed = now
+            .saturating_duration_since(self.refilled_at)
+            .as_secs_f64();
+        self.available = (self.available + elapsed * per_second).min(self.capacity);
+        self.refilled_at = now;
+
+        self.available -= (cost as f64).min(self.capacity);
+        if self.available >= 0.0 {
+            Duration::ZERO
+        } else {
+            Duration::from_secs_f64(-self.available / per_second)
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Limiter {
+    requests: Option<Bucket>,
+    tokens: Option<Bucket>,
+}
+
+impl Limiter {
+    fn new(limits: RateLimits, now: Instant) -> Self {
+        Self {
+            requests: limits.requests_per_minute.map(|n| Bucket::new(n, now)),
+            tokens: limits.tokens_per_minute.map(|n| Bucket::new(n, now)),
+        }
+    }
+
+    /// Reserves one request of `tokens` tokens; returns the wait before sending it.
+    fn reserve(&mut self, tokens: u32, now: Instant) -> Duration {
+        let requests = self.requests.as_mut().map(|b| b.reserve(1, now));
+        let tokens = self.tokens.as_mut().map(|b| b.reserve(tokens, now));
+        requests.unwrap_or_default().max(tokens.unwrap_or_default())
+    }
+}
+
+/// An [`EmbeddingProvider`] whose calls are throttled to [`RateLimits`].
+///
+/// Providers derived with [`for_model`](EmbeddingProvider::for_model)
+/// share the limiter, as quotas apply per API key rather than per model.
+/// A batch call counts as one request.
+///
+/// # Examples
+///
+/// ```no_run
+/// use std::sync::Arc;
+/// use ceres_client::rate_limit::{RateLimitedProvider, RateLimits};
+/// use ceres_client::{EmbeddingProvider, GeminiClient};
+/// use ceres_core::HttpConfig;
+///
+/// # fn example() -> Result<(), Box<dyn std::error::Error>> {
+/// let limits = RateLimits {
+///     requests_per_minute: Some(1500),
+///     tokens_per_minute: None,
+/// };
+/// let provider: Arc<dyn EmbeddingProvider> =
+///     Arc::new(RateLimitedProvider::new(Arc::new(GeminiClient::new("key", &HttpConfig::default())?), limits));
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct RateLimitedProvider {
+    inner: Arc<dyn EmbeddingProvider>,
+    limiter: Arc<Mutex<Limiter>>,
+}
+
+impl RateLimitedProvider {
+    pub fn new(inner: Arc<dyn EmbeddingProvider>, limits: RateLimits) -> Self {
+        Self {
+            inner,
+            limiter: Arc::new(Mutex::new(Limiter::new(limits, Instant::now()))),
+        }
+    }
+
+    /// Waits until a request of `tokens` estimated tokens may be sent.
+    async fn acquire(&self, tokens: u32) {
+        let wait = self
+            .limiter
+            .lock()
+            .expect("rate limiter lock poisoned")
+            .reserve(tokens, Instant::now());
+        if !wait.is_zero() {
+            sleep(wait).await;
+        }
+    }
+}
+
+#[async_trait]
+impl EmbeddingProvider for RateLimitedProvider {
+    fn model_id(&self) -> &str {
+        self.inner.model_id()
+    }
+
+    fn dimension(&self) -> usize {
+        self.inner.dimension()
+    }
+
+    fn for_model(&self, model: &str) -> Arc<dyn EmbeddingProvider> {
+        Arc::new(Self {
+            inner: self.inner.for_model(model),
+            limiter: Arc::clone(&self.limiter),
+        })
+    }
+
+    async fn embed(&self, text: &str) -> Result<Vec<f32>, AppError> {
+        self.acquire(estimate_tokens(text)).await;
+        self.inner.embed(text).await
+    }
+
+    async fn embed_query(&self, text: &str) -> Result<Vec<f32>, AppError> {
+        self.acquire(estimate_tokens(text)).await;
+        self.inner.embed_query(text).await
+    }
+
+    async fn embed_batch(&self, texts: &[String]) -> Result<Vec<Vec<f32>>, AppError> {
+        let tokens = texts.iter().map(|t| estimate_tokens(t)).sum();
+        self.acquire(tokens).await;
+        self.inner.embed_batch(texts).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_estimate_tokens() {
+        assert_eq!(estimate_tokens(""), 0);
+        assert_eq!(estimate_tokens("abcd"), 1);
+        assert_eq!(estimate_tokens("qualità"), 2);
+    }
+
+    #[test]
+    fn test_bucket_spaces_requests_after_burst() {
+        let start = Instant::now();
+        let mut bucket = Bucket::new(60, start);
+
+        for _ in 0..60 {
+            assert_eq!(bucket.reserve(1, start), Duration::ZERO);
+        }
+        // One per second once the minute's allowance is spent
+        assert_eq!(bucket.reserve(1, start), Duration::from_secs(1));
+        assert_eq!(bucket.reserve(1, start), Duration::from_secs(2));
+
+        // Refills cover the debt over time
+        let later = start + Duration::from_secs(10);
+        assert_eq!(bucket.reserve(1, later), Duration::ZERO);
+    }
+
+    #[test]
+    fn test_bucket_caps_oversized_cost() {
+        let start = Instant::now();
+        let mut bucket = Bucket::new(60, start);
+        assert_eq!(bucket.reserve(1_000, start), Duration::ZERO);
+        assert_eq!(bucket.reserve(60, start), Duration::from_secs(60));
+    }
+
+    #[test]
+    fn test_limiter_waits_for_slowest_bucket() {
+        let start = Instant::now();
+        let limits = RateLimits {
+            requests_per_minute: Some(600),
+            tokens_per_minute: Some(60),
+        };
+        let mut limiter = Limiter::new(limits, start);
+
+        assert_eq!(limiter.reserve(60, start), Duration::ZERO);
+        assert_eq!(limiter.reserve(30, start), Duration::from_secs(30));
+        assert_eq!(
+            Limiter::new(RateLimits::default(), start).reserve(1, start),
+            Duration::ZERO
+        );
+    }
+}
@@ -0,0 +1,141 @@
+//! Client for portal bundle registries (`ceres portals install`).
+
+use ceres_core::config::PortalEntry;
+use ceres_core::error::AppError;
+use ceres_core::registry::{
+    parse_bundle, verify_checksum, verify_signature, BundleRef, RegistryIndex,
+};
+use ceres_core::HttpConfig;
+use reqwest::{Client, StatusCode};
+
+use crate::http::client_builder;
+
+/// A bundle downloaded from a registry and checked against its index.
+#[derive(Debug, Clone)]
+pub struct VerifiedBundle {
+    pub name: String,
+    /// Resolved version (the bundle's `latest` if none was requested)
+    pub version: String,
+    pub portals: Vec<PortalEntry>,
+    /// True if the index signature was verified against a public key
+    pub signed: bool,
+}
+
+/// HTTP client for a static bundle registry.
+///
+/// # Examples
+///
+/// ```no_run
+/// use ceres_client::RegistryClient;
+/// use ceres_core::registry::{BundleRef, DEFAULT_REGISTRY_PUBLIC_KEY, DEFAULT_REGISTRY_URL};
+/// use ceres_core::HttpConfig;
+///
+/// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+/// let registry = RegistryClient::new(DEFAULT_REGISTRY_URL, &HttpConfig::default())?;
+/// let bundle = registry
+///     .fetch_bundle(
+///         &BundleRef::parse("italy-regions@1.0.0"),
+///         Some(DEFAULT_REGISTRY_PUBLIC_KEY),
+///     )
+///     .await?;
+/// println!("{} portals", bundle.portals.len());
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct RegistryClient {
+    client: Client,
+    http_config: HttpConfig,
+    base_url: String,
+}
+
+impl RegistryClient {
+    /// Creates a client for the registry at `base_url`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `AppError::ClientError` if the HTTP client cannot be built.
+    pub fn new(base_url: &str, http_config: &HttpConfig) -> Result<Self, AppError> {
+        let client = client_builder(http_config)?
+            .build()
+            .map_err(|e| AppError::ClientError(e.to_string()))?;
+
+        Ok(Self {
+            client,
+            http_config: http_config.clone(),
+            base_url: base_url.trim_end_matches('/').to_string(),
+        })
+    }
+
+    /// Downloads and verifies a bundle.
+    ///
+    /// With a `public_key` (base64 Ed25519), the registry index must carry a
+    /// valid `index.json.sig`; see
+    /// [`default_public_key`](ceres_core::registry::default_public_key) for
+    /// the default registry's. Without one the index is not verified. The
+    /// bundle file is always checked against the SHA-256 listed in the index.
+    ///
+    /// # Errors
+    ///
+    /// Returns `AppError::ConfigError` if the bundle is unknown or fails
+    /// verification, or a network error if the registry is unreachable.
+    pub async fn fetch_bundle(
+        &self,
+        bundle: &BundleRef,
+        public_key: Option<&str>,
+    ) -> Result<VerifiedBundle, AppError> {
+        let index_bytes = self.get("index.json").await?;
+        if let Some(public_key) = public_key {
+            let signature = self.get("index.json.sig").await?;
+            verify_signature(
+                &index_bytes,
+                &String::from_utf8_lossy(&signature),
+                public_key,
+            )?;
+        }
+
+        let index = RegistryIndex::parse(&index_bytes)?;
+        let (version, file) = index.resolve(bundle)?;
+
+        let content = self.get(&file.path).await?;
+        let source = format!("{}@{}", bundle.name, version);
+        verify_checksum(&source, &content, &file.sha256)?;
+        let content = String::from_utf8(content)
+            .map_err(|e| AppError::ConfigError(format!("Bundle {} is not UTF-8: {}", source, e)))?;
+
+        Ok(VerifiedBundle {
+            name: bundle.name.clone(),
+            version,
+            portals: parse_bundle(&content)?,
+            signed: public_key.is_some(),
+        })
+    }
+
+    async fn get(&self, path: &str) -> Result<Vec<u8>, AppError> {
+        let url = format!("{}/{}", self.base_url, path.trim_start_matches('/'));
+        let response = self.client.get(&url).send().await.map_err(|e| {
+            if e.is_timeout() {
+                AppError::Timeout(self.http_config.timeout.as_secs())
+            } else {
+                AppError::NetworkError(format!("Failed to fetch {}: {}", url, e))
+            }
+        })?;
+
+        match response.status() {
+            status if status.is_success() => Ok(response
+                .bytes()
+                .await
+                .map_err(|e| AppError::NetworkError(e.to_string()))?
+                .to_vec()),
+            StatusCode::NOT_FOUND => Err(AppError::ConfigError(format!(
+                "Registry file not found: {}",
+                url
+            ))),
+            status => Err(AppError::ClientError(format!(
+                "Registry returned HTTP {} for {}",
+                status.as_u16(),
+                url
+            ))),
+        }
+    }
+}
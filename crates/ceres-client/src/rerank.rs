@@ -0,0 +1,85 @@
+//! Reranker abstraction.
+//!
+//! `ceres search --rerank` re-scores the top candidates of vector search
+//! with a model that reads query and dataset text together, which is more
+//! precise than comparing independently computed embeddings. Implemented by
+//! [`CohereReranker`](crate::cohere::CohereReranker),
+//! [`GeminiReranker`](crate::gemini::GeminiReranker) and
+//! [`TeiClient`](crate::TeiClient) serving a cross-encoder.
+
+use async_trait::async_trait;
+use ceres_core::error::AppError;
+
+/// A model scoring the relevance of documents to a query.
+///
+/// # Examples
+///
+/// ```no_run
+/// use ceres_client::cohere::CohereReranker;
+/// use ceres_client::Reranker;
+/// use ceres_core::HttpConfig;
+///
+/// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+/// let reranker = CohereReranker::new("your-api-key", &HttpConfig::default())?;
+/// let documents = vec!["Orari autobus".to_string(), "Bilancio 2024".to_string()];
+/// let scores = reranker.rerank("trasporto pubblico", &documents).await?;
+/// assert_eq!(scores.len(), documents.len());
+/// # Ok(())
+/// # }
+/// ```
+#[async_trait]
+pub trait Reranker: Send + Sync {
+    /// Identifier of the reranking model.
+    fn model_id(&self) -> &str;
+
+    /// Scores each document's relevance to `query`, in input order.
+    ///
+    /// Scores range from 0 (irrelevant) to 1 (highly relevant).
+    async fn rerank(&self, query: &str, documents: &[String]) -> Result<Vec<f32>, AppError>;
+}
+
+/// Arranges `(index, score)` results, as returned by rerank APIs, in the
+/// input order of `count` documents.
+///
+/// # Errors
+///
+/// Returns `AppError::ClientError` if an index is out of range or a
+/// document has no score.
+pub(crate) fn scores_in_input_order(
+    count: usize,
+    results: impl IntoIterator<Item = (usize, f32)>,
+) -> Result<Vec<f32>, AppError> {
+    let mut scores = vec![None; count];
+    for (index, score) in results {
+        let slot = scores.get_mut(index).ok_or_else(|| {
+            AppError::ClientError(format!(
+                "Reranker returned index {} for {} documents",
+                index, count
+            ))
+        })?;
+        *slot = Some(score);
+    }
+    scores
+        .into_iter()
+        .enumerate()
+        .map(|(index, score)| {
+            score.ok_or_else(|| {
+                AppError::ClientError(format!("Reranker returned no score for document {}", index))
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_scores_in_input_order() {
+        let scores = scores_in_input_order(3, [(2, 0.9), (0, 0.5), (1, 0.1)]).unwrap();
+        assert_eq!(scores, vec![0.5, 0.1, 0.9]);
+
+        assert!(scores_in_input_order(2, [(0, 0.5)]).is_err());
+        assert!(scores_in_input_order(1, [(0, 0.5), (1, 0.4)]).is_err());
+    }
+}
@@ -0,0 +1,217 @@
+//! On-disk cache of raw CKAN `package_show` responses.
+//!
+//! Re-running harvests, e.g. to try a new embedding model, otherwise
+//! downloads every dataset again. Response bodies are content-addressed by
+//! portal, dataset ID and ETag under `objects/`; `index/` maps each portal
+//! and dataset ID to the ETag and fetch time of its latest body. The next
+//! request for the dataset reuses the body outright while it is younger than
+//! the max age, and otherwise revalidates it with `If-None-Match`.
+//!
+//! Failing to read or write the cache never fails a request: it is logged
+//! and the portal is asked as if there were no cache.
+
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+use ceres_core::error::AppError;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use tracing::debug;
+
+/// Where a dataset's latest body is, and when it was fetched.
+#[derive(Debug, Serialize, Deserialize)]
+struct IndexEntry {
+    etag: Option<String>,
+    /// Seconds since the Unix epoch
+    fetched_at: u64,
+}
+
+/// A cached response body.
+#[derive(Debug, Clone, PartialEq)]
+pub struct CachedResponse {
+    /// ETag the portal sent with the body, to revalidate it
+    pub etag: Option<String>,
+    pub body: Vec<u8>,
+    /// Younger than the max age: usable without asking the portal
+    pub fresh: bool,
+}
+
+/// Cache of `package_show` bodies in a directory, shared by all portals.
+#[derive(Debug, Clone)]
+pub struct ResponseCache {
+    dir: PathBuf,
+    max_age: Option<Duration>,
+}
+
+impl ResponseCache {
+    /// Caches in `dir`, created on first write. Bodies younger than
+    /// `max_age` are reused without revalidation; without one, every reuse
+    /// is revalidated.
+    pub fn new(dir: impl Into<PathBuf>, max_age: Option<Duration>) -> Self {
+        Self {
+            dir: dir.into(),
+            max_age,
+        }
+    }
+
+    /// The cached body of dataset `id` of `portal`, if any.
+    pub async fn get(&self, portal: &str, id: &str) -> Option<CachedResponse> {
+        let index_path = self.index_path(portal, id);
+        let entry = match tokio::fs::read(&index_path).await {
+            Ok(bytes) => serde_json::from_slice::<IndexEntry>(&bytes).ok()?,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return None,
+            Err(e) => {
+                debug!("Cannot read {}: {}", index_path.display(), e);
+                return None;
+            }
+        };
+        let object_path = self.object_path(portal, id, entry.etag.as_deref());
+        // An index entry whose body is gone is a miss
+        let body = tokio::fs::read(&object_path).await.ok()?;
+        let age = Duration::from_secs(unix_now().saturating_sub(entry.fetched_at));
+        Some(CachedResponse {
+            etag: entry.etag,
+            body,
+            fresh: self.max_age.is_some_and(|max_age| age < max_age),
+        })
+    }
+
+    /// Stores `body` as the latest response for dataset `id` of `portal`,
+    /// removing the body it replaces.
+    pub async fn put(
+        &self,
+        portal: &str,
+        id: &str,
+        etag: Option<&str>,
+        body: &[u8],
+    ) -> Result<(), AppError> {
+        let index_path = self.index_path(portal, id);
+        let previous = tokio::fs::read(&index_path)
+            .await
+            .ok()
+            .and_then(|bytes| serde_json::from_slice::<IndexEntry>(&bytes).ok());
+
+        let object_path = self.object_path(portal, id, etag);
+        write_atomic(&object_path, body).await?;
+        self.write_index(&index_path, etag).await?;
+
+        if let Some(previous) = previous {
+            let previous_path = self.object_path(portal, id, previous.etag.as_deref());
+            if previous_path != object_path {
+                let _ = tokio::fs::remove_file(previous_path).await;
+            }
+        }
+        Ok(())
+    }
+
+    /// Records that the portal confirmed the cached body of dataset `id` is
+    /// current, so it is fresh again.
+    pub async fn touch(&self, portal: &str, id: &str, etag: Option<&str>) -> Result<(), AppError> {
+        self.write_index(&self.index_path(portal, id), etag).await
+    }
+
+    async fn write_index(&self, index_path: &Path, etag: Option<&str>) -> Result<(), AppError> {
+        let entry = IndexEntry {
+            etag: etag.map(str::to_string),
+            fetched_at: unix_now(),
+        };
+        let bytes = serde_json::to_vec(&entry)?;
+        write_atomic(index_path, &bytes).await
+    }
+
+    fn index_path(&self, portal: &str, id: &str) -> PathBuf {
+        sharded(&self.dir.join("index"), &cache_key(&[portal, id]))
+    }
+
+    fn object_path(&self, portal: &str, id: &str, etag: Option<&str>) -> PathBuf {
+        sharded(
+            &self.dir.join("objects"),
+            &cache_key(&[portal, id, etag.unwrap_or_default()]),
+        )
+    }
+}
+
+/// SHA-256 of `parts`, NUL-separated so they cannot run into each other.
+fn cache_key(parts: &[&str]) -> String {
+    format!("{:x}", Sha256::digest(parts.join("\0").as_bytes()))
+}
+
+/// `dir/<first two hex digits>/<key>.json`, keeping directories small for
+/// portals with hundreds of thousands of datasets.
+fn sharded(dir: &Path, key: &str) -> PathBuf {
+    dir.join(&key[..2]).join(format!("{}.json", key))
+}
+
+/// Writes through a temporary file and a rename, so readers never see a
+/// partial file.
+async fn write_atomic(path: &Path, bytes: &[u8]) -> Result<(), AppError> {
+    let failed =
+        |e: std::io::Error| AppError::Generic(format!("Failed to write {}: {}", path.display(), e));
+    if let Some(parent) = path.parent() {
+        tokio::fs::create_dir_all(parent).await.map_err(failed)?;
+    }
+    let tmp = path.with_extension(format!("{}.tmp", std::process::id()));
+    tokio::fs::write(&tmp, bytes).await.map_err(failed)?;
+    tokio::fs::rename(&tmp, path).await.map_err(failed)
+}
+
+fn unix_now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|d| d.as_secs())
+        .unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PORTAL: &str = "https://dati.comune.milano.it/";
+
+    #[tokio::test]
+    async fn test_put_and_get() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = ResponseCache::new(dir.path(), None);
+        assert_eq!(cache.get(PORTAL, "bus").await, None);
+
+        cache
+            .put(PORTAL, "bus", Some("\"v1\""), b"{\"v\":1}")
+            .await
+            .unwrap();
+        let cached = cache.get(PORTAL, "bus").await.unwrap();
+        assert_eq!(cached.etag.as_deref(), Some("\"v1\""));
+        assert_eq!(cached.body, b"{\"v\":1}");
+        // Without a max age, bodies are always revalidated
+        assert!(!cached.fresh);
+        assert_eq!(cache.get("https://other.portal/", "bus").await, None);
+
+        // A new ETag replaces the body and removes the old one
+        cache
+            .put(PORTAL, "bus", Some("\"v2\""), b"{\"v\":2}")
+            .await
+            .unwrap();
+        assert_eq!(cache.get(PORTAL, "bus").await.unwrap().body, b"{\"v\":2}");
+        let old = cache.object_path(PORTAL, "bus", Some("\"v1\""));
+        assert!(!old.exists());
+    }
+
+    #[tokio::test]
+    async fn test_freshness() {
+        let dir = tempfile::tempdir().unwrap();
+        let cache = ResponseCache::new(dir.path(), Some(Duration::from_secs(3600)));
+        cache.put(PORTAL, "aria", None, b"{}").await.unwrap();
+        assert!(cache.get(PORTAL, "aria").await.unwrap().fresh);
+
+        // Written an hour and a bit ago
+        let index_path = cache.index_path(PORTAL, "aria");
+        let stale = IndexEntry {
+            etag: None,
+            fetched_at: unix_now() - 3700,
+        };
+        std::fs::write(&index_path, serde_json::to_vec(&stale).unwrap()).unwrap();
+        assert!(!cache.get(PORTAL, "aria").await.unwrap().fresh);
+
+        cache.touch(PORTAL, "aria", None).await.unwrap();
+        assert!(cache.get(PORTAL, "aria").await.unwrap().fresh);
+    }
+}
@@ -0,0 +1,385 @@
+//! robots.txt compliance for portal harvests.
+//!
+//! [`CkanClient`](crate::CkanClient) fetches the robots.txt of its portal's
+//! host once, before its first request, and keeps the policy for its
+//! lifetime, which is one harvest. Requests to disallowed paths fail without
+//! being sent, and a `Crawl-delay` spaces out every request to the host,
+//! however many workers share the client.
+//!
+//! Rules follow RFC 9309: the groups naming Ceres' product token apply, else
+//! the `*` groups; the longest matching `Allow`/`Disallow` pattern wins, an
+//! `Allow` on ties. A missing robots.txt (any 4xx answer) allows everything;
+//! a server error (5xx) or a failed connection disallows everything, as the
+//! RFC requires, so the harvest fails instead of crawling a host whose
+//! rules are unknown. A `Crawl-delay` above [`MAX_CRAWL_DELAY`] is capped
+//! with a warning.
+
+use std::sync::Mutex;
+use std::time::Duration;
+
+use reqwest::{Client, StatusCode};
+use tokio::time::{sleep_until, Instant};
+use tracing::{debug, info, warn};
+use url::Url;
+
+/// Longest Crawl-delay honoured; longer ones would stall a harvest for days.
+pub const MAX_CRAWL_DELAY: Duration = Duration::from_secs(60);
+
+/// Allow and Disallow rules, and the Crawl-delay, robots.txt sets for Ceres.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct RobotsPolicy {
+    rules: Vec<Rule>,
+    crawl_delay: Option<Duration>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Rule {
+    allow: bool,
+    pattern: String,
+}
+
+/// A group of rules and the user agents it applies to.
+#[derive(Default)]
+struct Group {
+    agents: Vec<String>,
+    rules: Vec<Rule>,
+    crawl_delay: Option<Duration>,
+}
+
+impl RobotsPolicy {
+    /// A policy disallowing every path, for a robots.txt that could not be
+    /// read.
+    pub fn disallow_all() -> Self {
+        Self {
+            rules: vec![Rule {
+                allow: false,
+                pattern: "/".to_string(),
+            }],
+            crawl_delay: None,
+        }
+    }
+
+    /// The policy of a host whose robots.txt request answered `status`
+    /// instead of a robots.txt: a server error disallows everything,
+    /// anything else allows it.
+    pub fn unavailable(status: StatusCode) -> Self {
+        if status.is_server_error() {
+            Self::disallow_all()
+        } else {
+            Self::default()
+        }
+    }
+
+    /// Parses `robots_txt` for the crawler named `product_token`.
+    pub fn parse(robots_txt: &str, product_token: &str) -> Self {
+        let mut groups: Vec<Group> = Vec::new();
+        let mut in_agent_lines = false;
+        for line in robots_txt.lines() {
+            let line = line.split('#').next().unwrap_or("").trim();
+            let Some((key, value)) = line.split_once(':') else {
+                continue;
+            };
+            let value = value.trim();
+            match key.trim().to_ascii_lowercase().as_str() {
+                "user-agent" => {
+                    // Consecutive user-agent lines share the group below them
+                    if !in_agent_lines {
+                        groups.push(Group::default());
+                    }
+                    in_agent_lines = true;
+                    if let Some(group) = groups.last_mut() {
+                        group.agents.push(value.to_ascii_lowercase());
+                    }
+                }
+                key @ ("allow" | "disallow") => {
+                    in_agent_lines = false;
+                    // An empty Disallow allows everything, like no rule
+                    if let (Some(group), false) = (groups.last_mut(), value.is_empty()) {
+                        group.rules.push(Rule {
+                            allow: key == "allow",
+                            pattern: value.to_string(),
+                        });
+                    }
+                }
+                "crawl-delay" => {
+                    in_agent_lines = false;
+                    let delay = value
+                        .parse::<f64>()
+                        .ok()
+                        .filter(|d| d.is_finite() && *d > 0.0);
+                    if let (Some(group), Some(delay)) = (groups.last_mut(), delay) {
+                        group.crawl_delay = Some(Duration::from_secs_f64(delay));
+                    }
+                }
+                // Sitemap and unknown lines belong to no group
+                _ => {}
+            }
+        }
+
+        let token = product_token.to_ascii_lowercase();
+        let named: Vec<&Group> = groups
+            .iter()
+            .filter(|g| g.agents.contains(&token))
+            .collect();
+        let applicable = if named.is_empty() {
+            groups
+                .iter()
+                .filter(|g| g.agents.iter().any(|a| a == "*"))
+                .collect()
+        } else {
+            named
+        };
+        Self {
+            rules: applicable
+                .iter()
+                .flat_map(|g| g.rules.iter().cloned())
+                .collect(),
+            crawl_delay: applicable.iter().filter_map(|g| g.crawl_delay).max(),
+        }
+    }
+
+    /// Returns true if robots.txt lets Ceres fetch `path` (with its query).
+    pub fn is_allowed(&self, path: &str) -> bool {
+        self.rules
+            .iter()
+            .filter(|rule| matches(&rule.pattern, path))
+            .max_by_key(|rule| (rule.pattern.len(), rule.allow))
+            .is_none_or(|rule| rule.allow)
+    }
+
+    /// Minimum time between two requests to the host, if robots.txt sets one.
+    pub fn crawl_delay(&self) -> Option<Duration> {
+        self.crawl_delay
+    }
+}
+
+/// Returns true if `path` starts with what `pattern` describes: `*` matches
+/// any characters and a final `$` anchors the pattern at the end of `path`.
+fn matches(pattern: &str, path: &str) -> bool {
+    let (pattern, anchored) = match pattern.strip_suffix('$') {
+        Some(pattern) => (pattern, true),
+        None => (pattern, false),
+    };
+    let mut parts = pattern.split('*');
+    let Some(mut rest) = path.strip_prefix(parts.next().unwrap_or("")) else {
+        return false;
+    };
+    let parts: Vec<&str> = parts.collect();
+    let Some((last, middle)) = parts.split_last() else {
+        return !anchored || rest.is_empty();
+    };
+    for part in middle {
+        match rest.find(part) {
+            Some(pos) => rest = &rest[pos + part.len()..],
+            None => return false,
+        }
+    }
+    if anchored {
+        rest.ends_with(last)
+    } else {
+        rest.contains(last)
+    }
+}
+
+/// The product token robots.txt groups name a crawler by: the User-Agent
+/// up to its first `/` or space, `Ceres` for Ceres' own.
+pub fn product_token(user_agent: &str) -> &str {
+    user_agent.split(['/', ' ']).next().unwrap_or(user_agent)
+}
+
+/// The robots.txt path of `url`: its path and query.
+pub fn robots_path(url: &Url) -> String {
+    match url.query() {
+        Some(query) => format!("{}?{}", url.path(), query),
+        None => url.path().to_string(),
+    }
+}
+
+/// Fetches and parses the robots.txt of `base_url`'s host.
+///
+/// A 4xx answer allows every path; a 5xx answer or a failed request
+/// disallows every path (see [`RobotsPolicy::unavailable`]). The
+/// Crawl-delay is capped at [`MAX_CRAWL_DELAY`].
+pub(crate) async fn fetch_policy(
+    client: &Client,
+    base_url: &Url,
+    user_agent: &str,
+) -> RobotsPolicy {
+    let Ok(url) = base_url.join("/robots.txt") else {
+        return RobotsPolicy::default();
+    };
+    let host = url.host_str().unwrap_or_default();
+    let text = match client.get(url.clone()).send().await {
+        Ok(resp) if resp.status().is_success() => resp.text().await,
+        Ok(resp) if resp.status().is_server_error() => {
+            warn!(
+                "robots.txt of {} answered HTTP {}; treating every path as disallowed (--ignore-robots-txt to override)",
+                host,
+                resp.status().as_u16()
+            );
+            return RobotsPolicy::unavailable(resp.status());
+        }
+        Ok(resp) => {
+            debug!("No robots.txt at {} (HTTP {})", url, resp.status().as_u16());
+            return RobotsPolicy::unavailable(resp.status());
+        }
+        Err(e) => Err(e),
+    };
+    match text {
+        Ok(text) => {
+            let mut policy = RobotsPolicy::parse(&text, product_token(user_agent));
+            match policy.crawl_delay() {
+                Some(delay) if delay > MAX_CRAWL_DELAY => {
+                    warn!(
+                        "robots.txt of {} asks for {:.0} s between requests; waiting {} s instead",
+                        host,
+                        delay.as_secs_f64(),
+                        MAX_CRAWL_DELAY.as_secs()
+                    );
+                    policy.crawl_delay = Some(MAX_CRAWL_DELAY);
+                }
+                Some(delay) => info!(
+                    "robots.txt of {} asks for {:.1} s between requests",
+                    host,
+                    delay.as_secs_f64()
+                ),
+                None => {}
+            }
+            policy
+        }
+        Err(e) => {
+            warn!(
+                "Failed to fetch {}: {}; treating every path as disallowed (--ignore-robots-txt to override)",
+                url, e
+            );
+            RobotsPolicy::disallow_all()
+        }
+    }
+}
+
+/// A host's robots.txt policy, and when its Crawl-delay next lets a request
+/// through.
+#[derive(Debug)]
+pub(crate) struct RobotsGate {
+    pub policy: RobotsPolicy,
+    next_request: Mutex<Option<Instant>>,
+}
+
+impl RobotsGate {
+    pub fn new(policy: RobotsPolicy) -> Self {
+        Self {
+            policy,
+            next_request: Mutex::new(None),
+        }
+    }
+
+    /// Waits until the Crawl-delay allows the next request.
+    pub async fn wait_turn(&self) {
+        if let Some(turn) = self.reserve(Instant::now()) {
+            sleep_until(turn).await;
+        }
+    }
+
+    /// Reserves the first request slot from `now`, one Crawl-delay after the
+    /// previous reservation, so concurrent callers go one delay apart.
+    fn reserve(&self, now: Instant) -> Option<Instant> {
+        let delay = self.policy.crawl_delay()?;
+        let mut next = self.next_request.lock().expect("robots gate lock poisoned");
+        let turn = next.map_or(now, |next| next.max(now));
+        *next = Some(turn + delay);
+        Some(turn)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ROBOTS_TXT: &str = "\
+# Portal robots.txt
+User-agent: *
+Disallow: /api/
+Allow: /api/3/action/package_show
+Crawl-delay: 2
+
+User-agent: Googlebot
+User-agent: ceres
+Disallow: /dataset/*/resource/*/download$
+Disallow: /user/
+Crawl-delay: 0.5
+
+Sitemap: https://dati.example.it/sitemap.xml
+";
+
+    #[test]
+    fn test_parse_selects_groups() {
+        let policy = RobotsPolicy::parse(ROBOTS_TXT, "Ceres");
+        assert_eq!(policy.crawl_delay(), Some(Duration::from_millis(500)));
+        assert!(policy.is_allowed("/api/3/action/package_list"));
+        assert!(!policy.is_allowed("/user/login"));
+
+        let policy = RobotsPolicy::parse(ROBOTS_TXT, "OtherBot");
+        assert_eq!(policy.crawl_delay(), Some(Duration::from_secs(2)));
+        assert!(!policy.is_allowed("/api/3/action/package_list"));
+        // The longer Allow wins over the Disallow
+        assert!(policy.is_allowed("/api/3/action/package_show?id=bus"));
+
+        assert_eq!(RobotsPolicy::parse("", "Ceres"), RobotsPolicy::default());
+        assert!(RobotsPolicy::default().is_allowed("/api/3/action/package_list"));
+    }
+
+    #[test]
+    fn test_unavailable_robots_txt() {
+        let disallowed = RobotsPolicy::unavailable(StatusCode::SERVICE_UNAVAILABLE);
+        assert!(!disallowed.is_allowed("/api/3/action/package_list"));
+        assert!(!disallowed.is_allowed("/"));
+        assert_eq!(disallowed, RobotsPolicy::disallow_all());
+
+        let missing = RobotsPolicy::unavailable(StatusCode::NOT_FOUND);
+        assert!(missing.is_allowed("/api/3/action/package_list"));
+        assert!(RobotsPolicy::unavailable(StatusCode::FORBIDDEN).is_allowed("/"));
+    }
+
+    #[tokio::test]
+    async fn test_fetch_policy_unreachable_disallows() {
+        // Nothing listens on the discard port
+        let base_url = Url::parse("http://127.0.0.1:9/").unwrap();
+        let policy = fetch_policy(&Client::new(), &base_url, "Ceres/0.1").await;
+        assert!(!policy.is_allowed("/api/3/action/package_list"));
+    }
+
+    #[test]
+    fn test_matches() {
+        assert!(matches("/api/", "/api/3/action/site_read"));
+        assert!(!matches("/api/", "/dataset/api"));
+        assert!(matches("/*.csv$", "/dataset/aria.csv"));
+        assert!(!matches("/*.csv$", "/dataset/aria.csv?download=1"));
+        assert!(matches("/dataset/*/resource/", "/dataset/aria/resource/1"));
+        assert!(matches("/api$", "/api"));
+        assert!(!matches("/api$", "/api/"));
+        assert!(matches("*", "/anything"));
+    }
+
+    #[test]
+    fn test_product_token() {
+        assert_eq!(product_token("Ceres/0.1.1 (https://example.org)"), "Ceres");
+        assert_eq!(product_token("MyHarvester"), "MyHarvester");
+        let url = Url::parse("https://dati.example.it/api/3/action/package_show?id=bus").unwrap();
+        assert_eq!(robots_path(&url), "/api/3/action/package_show?id=bus");
+    }
+
+    #[test]
+    fn test_crawl_delay_spaces_requests() {
+        let gate = RobotsGate::new(RobotsPolicy::parse(ROBOTS_TXT, "ceres"));
+        let now = Instant::now();
+        let turns: Vec<_> = (0..3).map(|_| gate.reserve(now).unwrap()).collect();
+        assert_eq!(turns[2] - now, Duration::from_secs(1));
+
+        // Once the reserved slots are past, requests go through at once
+        let later = now + Duration::from_secs(10);
+        assert_eq!(gate.reserve(later), Some(later));
+
+        let gate = RobotsGate::new(RobotsPolicy::default());
+        assert_eq!(gate.reserve(now), None);
+    }
+}
@@ -0,0 +1,423 @@
+//! Hugging Face Text Embeddings Inference (TEI) client.
+//!
+//! TEI serves a single model per server behind a plain `POST /embed`
+//! endpoint, which makes it a common choice for self-hosted GPU embedding
+//! services. Vectors are tagged with the served model's ID, read from
+//! `GET /info` unless a name is set explicitly.
+//!
+//! A TEI server running a cross-encoder (e.g. `BAAI/bge-reranker-base`)
+//! serves `POST /rerank` instead; [`TeiClient`] also implements
+//! [`Reranker`] for it.
+
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use ceres_core::error::AppError;
+use ceres_core::HttpConfig;
+use reqwest::{Client, RequestBuilder, StatusCode};
+use serde::{Deserialize, Serialize};
+
+use crate::embedding::{pad_to_dimension, EmbeddingProvider, DEFAULT_EMBEDDING_DIMENSION};
+use crate::http::client_builder;
+use crate::rerank::{scores_in_input_order, Reranker};
+
+/// Texts per request (TEI's default `--max-client-batch-size`).
+const MAX_BATCH_SIZE: usize = 32;
+
+/// HTTP client for a Text Embeddings Inference server.
+///
+/// Models with fewer dimensions than configured (768 by default) are
+/// zero-padded; larger ones are rejected.
+///
+/// # Examples
+///
+/// ```no_run
+/// use ceres_client::TeiClient;
+/// use ceres_core::HttpConfig;
+///
+/// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+/// let client = TeiClient::new("http://gpu-box:8080", &HttpConfig::default())?
+///     .with_api_key("your-token")
+///     .with_model("intfloat/multilingual-e5-base");
+/// let vectors = client.get_embeddings(&["trasporto pubblico".to_string()]).await?;
+/// println!("Embedding dimension: {}", vectors[0].len()); // 768
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct TeiClient {
+    client: Client,
+    http_config: HttpConfig,
+    base_url: String,
+    api_key: Option<String>,
+    model: String,
+    dimension: usize,
+}
+
+/// Request body for `POST /embed`
+#[derive(Serialize)]
+struct EmbedRequest<'a> {
+    inputs: &'a [String],
+    /// Truncate inputs longer than the model's maximum instead of failing
+    truncate: bool,
+    normalize: bool,
+}
+
+/// Request body for `POST /rerank`
+#[derive(Serialize)]
+struct RerankRequest<'a> {
+    query: &'a str,
+    texts: &'a [String],
+    truncate: bool,
+    /// Raw logits instead of sigmoid scores in 0..1
+    raw_scores: bool,
+}
+
+/// Entry of the `POST /rerank` response
+#[derive(Deserialize)]
+struct RerankHit {
+    index: usize,
+    score: f32,
+}
+
+/// Response from `GET /info`
+#[derive(Deserialize)]
+struct InfoResponse {
+    model_id: String,
+}
+
+/// Error body returned by TEI
+#[derive(Deserialize)]
+struct TeiError {
+    error: String,
+}
+
+/// Maps TEI's HTTP errors to `AppError`.
+///
+/// TEI answers 429 when its concurrency limit is reached and 413 when a
+/// batch exceeds `--max-client-batch-size`.
+fn classify_tei_error(status: StatusCode, message: &str) -> AppError {
+    match status.as_u16() {
+        401 | 403 => AppError::ConfigError(format!(
+            "TEI server rejected the token ({}). Check TEI_API_KEY",
+            message
+        )),
+        413 => AppError::EmbeddingError(format!(
+            "Batch rejected by TEI server; raise its --max-client-batch-size to at least {}: {}",
+            MAX_BATCH_SIZE, message
+        )),
+        429 => AppError::RateLimitExceeded,
+        500..=599 => AppError::ClientError(format!(
+            "TEI server error (HTTP {}): {}",
+            status.as_u16(),
+            message
+        )),
+        code => AppError::EmbeddingError(format!("TEI HTTP {}: {}", code, message)),
+    }
+}
+
+impl TeiClient {
+    /// Creates a client for the server at `base_url` (e.g. `http://localhost:8080`).
+    ///
+    /// The model name defaults to `"tei"`; set it with [`with_model`](Self::with_model)
+    /// or [`with_served_model`](Self::with_served_model).
+    ///
+    /// # Errors
+    ///
+    /// Returns `AppError::InvalidUrl` if `base_url` is not a valid URL.
+    pub fn new(base_url: &str, http_config: &HttpConfig) -> Result<Self, AppError> {
+        reqwest::Url::parse(base_url).map_err(|e| AppError::InvalidUrl(e.to_string()))?;
+
+        let client = client_builder(http_config)?
+            .build()
+            .map_err(|e| AppError::ClientError(e.to_string()))?;
+
+        Ok(Self {
+            client,
+            http_config: http_config.clone(),
+            base_url: base_url.trim_end_matches('/').to_string(),
+            api_key: None,
+            model: "tei".to_string(),
+            dimension: DEFAULT_EMBEDDING_DIMENSION,
+        })
+    }
+
+    /// Returns a client sending `api_key` as a bearer token.
+    pub fn with_api_key(mut self, api_key: &str) -> Self {
+        self.api_key = Some(api_key.to_string());
+        self
+    }
+
+    /// Returns a client tagging vectors with `model`.
+    ///
+    /// TEI serves one model per server, so this only names the vectors; it
+    /// must identify the model the server actually runs.
+    pub fn with_model(mut self, model: &str) -> Self {
+        self.model = model.to_string();
+        self
+    }
+
+    /// Returns a client tagging vectors with the model ID reported by the server.
+    ///
+    /// # Errors
+    ///
+    /// Returns a network or client error if `GET /info` fails.
+    pub async fn with_served_model(self) -> Result<Self, AppError> {
+        let response = self
+            .authorize(self.client.get(format!("{}/info", self.base_url)))
+            .send()
+            .await
+            .map_err(|e| self.request_error(e))?;
+
+        let status = response.status();
+        if !status.is_success() {
+            let body = response.text().await.unwrap_or_default();
+            return Err(classify_tei_error(status, &error_message(body)));
+        }
+
+        let info: InfoResponse = response
+            .json()
+            .await
+            .map_err(|e| AppError::ClientError(format!("Failed to parse /info: {}", e)))?;
+        Ok(self.with_model(&info.model_id))
+    }
+
+    /// Returns a client producing vectors of `dimension` floats.
+    ///
+    /// Smaller native vectors are zero-padded; larger ones are rejected.
+    pub fn with_dimension(mut self, dimension: usize) -> Self {
+        self.dimension = dimension;
+        self
+    }
+
+    /// Returns the embedding model name.
+    pub fn model(&self) -> &str {
+        &self.model
+    }
+
+    /// Embeds texts in input order, in requests of up to 32 texts.
+    ///
+    /// # Errors
+    ///
+    /// Returns `AppError::ConfigError` for a rejected token,
+    /// `AppError::RateLimitExceeded` when the server is overloaded,
+    /// `AppError::ClientError` for server errors, and
+    /// `AppError::EmbeddingError` for rejected requests or vectors larger
+    /// than the database dimension.
+    pub async fn get_embeddings(&self, texts: &[String]) -> Result<Vec<Vec<f32>>, AppError> {
+        let mut vectors = Vec::with_capacity(texts.len());
+
+        for chunk in texts.chunks(MAX_BATCH_SIZE) {
+            let request = EmbedRequest {
+                inputs: chunk,
+                truncate: true,
+                normalize: true,
+            };
+
+            let response = self
+                .authorize(self.client.post(format!("{}/embed", self.base_url)))
+                .json(&request)
+                .send()
+                .await
+                .map_err(|e| self.request_error(e))?;
+
+            let status = response.status();
+            if !status.is_success() {
+                let body = response.text().await.unwrap_or_default();
+                return Err(classify_tei_error(status, &error_message(body)));
+            }
+
+            let embeddings: Vec<Vec<f32>> = response
+                .json()
+                .await
+                .map_err(|e| AppError::ClientError(format!("Failed to parse response: {}", e)))?;
+
+            if embeddings.len() != chunk.len() {
+                return Err(AppError::EmbeddingError(format!(
+                    "Expected {} embeddings, got {}",
+                    chunk.len(),
+                    embeddings.len()
+                )));
+            }
+            for embedding in embeddings {
+                vectors.push(pad_to_dimension(embedding, self.dimension)?);
+            }
+        }
+
+        Ok(vectors)
+    }
+
+    /// Scores texts against `query` with the served cross-encoder, in
+    /// requests of up to 32 texts.
+    ///
+    /// # Errors
+    ///
+    /// Same as [`get_embeddings`](Self::get_embeddings); a server running an
+    /// embedding model rejects the request.
+    pub async fn get_rerank_scores(
+        &self,
+        query: &str,
+        texts: &[String],
+    ) -> Result<Vec<f32>, AppError> {
+        let mut hits = Vec::with_capacity(texts.len());
+
+        for (i, chunk) in texts.chunks(MAX_BATCH_SIZE).enumerate() {
+            let request = RerankRequest {
+                query,
+                texts: chunk,
+                truncate: true,
+                raw_scores: false,
+            };
+
+            let response = self
+                .authorize(self.client.post(format!("{}/rerank", self.base_url)))
+                .json(&request)
+                .send()
+                .await
+                .map_err(|e| self.request_error(e))?;
+
+            let status = response.status();
+            if !status.is_success() {
+                let body = response.text().await.unwrap_or_default();
+                return Err(classify_tei_error(status, &error_message(body)));
+            }
+
+            let chunk_hits: Vec<RerankHit> = response
+                .json()
+                .await
+                .map_err(|e| AppError::ClientError(format!("Failed to parse response: {}", e)))?;
+            let offset = i * MAX_BATCH_SIZE;
+            hits.extend(chunk_hits.into_iter().map(|h| (offset + h.index, h.score)));
+        }
+
+        scores_in_input_order(texts.len(), hits)
+    }
+
+    fn authorize(&self, request: RequestBuilder) -> RequestBuilder {
+        match &self.api_key {
+            Some(api_key) => request.bearer_auth(api_key),
+            None => request,
+        }
+    }
+
+    fn request_error(&self, e: reqwest::Error) -> AppError {
+        if e.is_timeout() {
+            AppError::Timeout(self.http_config.timeout.as_secs())
+        } else if e.is_connect() {
+            AppError::NetworkError(format!(
+                "Connection to TEI server at {} failed: {}",
+                self.base_url, e
+            ))
+        } else {
+            AppError::ClientError(e.to_string())
+        }
+    }
+}
+
+/// Extracts the message from a TEI error body, falling back to the raw body.
+fn error_message(body: String) -> String {
+    serde_json::from_str::<TeiError>(&body)
+        .map(|e| e.error)
+        .unwrap_or(body)
+}
+
+#[async_trait]
+impl EmbeddingProvider for TeiClient {
+    fn model_id(&self) -> &str {
+        self.model()
+    }
+
+    fn dimension(&self) -> usize {
+        self.dimension
+    }
+
+    fn for_model(&self, model: &str) -> Arc<dyn EmbeddingProvider> {
+        Arc::new(self.clone().with_model(model))
+    }
+
+    async fn embed(&self, text: &str) -> Result<Vec<f32>, AppError> {
+        let mut vectors = self.get_embeddings(&[text.to_string()]).await?;
+        vectors.pop().ok_or(AppError::EmptyResponse)
+    }
+
+    async fn embed_batch(&self, texts: &[String]) -> Result<Vec<Vec<f32>>, AppError> {
+        self.get_embeddings(texts).await
+    }
+}
+
+#[async_trait]
+impl Reranker for TeiClient {
+    fn model_id(&self) -> &str {
+        self.model()
+    }
+
+    async fn rerank(&self, query: &str, documents: &[String]) -> Result<Vec<f32>, AppError> {
+        self.get_rerank_scores(query, documents).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_request_serialization() {
+        let texts = vec!["Hello world".to_string()];
+        let request = EmbedRequest {
+            inputs: &texts,
+            truncate: true,
+            normalize: true,
+        };
+
+        let json = serde_json::to_value(&request).unwrap();
+        assert_eq!(json["inputs"][0], "Hello world");
+        assert_eq!(json["truncate"], true);
+    }
+
+    #[test]
+    fn test_rerank_response_parsing() {
+        let body = r#"[{"index":1,"score":0.98},{"index":0,"score":0.02}]"#;
+        let hits: Vec<RerankHit> = serde_json::from_str(body).unwrap();
+        let scores = scores_in_input_order(2, hits.into_iter().map(|h| (h.index, h.score)));
+        assert_eq!(scores.unwrap(), vec![0.02, 0.98]);
+    }
+
+    #[test]
+    fn test_new_rejects_invalid_url() {
+        assert!(matches!(
+            TeiClient::new("not a url", &HttpConfig::default()),
+            Err(AppError::InvalidUrl(_))
+        ));
+        let client = TeiClient::new("http://localhost:8080/", &HttpConfig::default()).unwrap();
+        assert_eq!(client.base_url, "http://localhost:8080");
+        assert_eq!(client.model(), "tei");
+    }
+
+    #[test]
+    fn test_error_message() {
+        assert_eq!(
+            error_message(
+                r#"{"error":"Input validation error","error_type":"Validation"}"#.to_string()
+            ),
+            "Input validation error"
+        );
+        assert_eq!(error_message("Bad Gateway".to_string()), "Bad Gateway");
+    }
+
+    #[test]
+    fn test_classify_tei_error() {
+        assert!(matches!(
+            classify_tei_error(StatusCode::UNAUTHORIZED, "bad token"),
+            AppError::ConfigError(msg) if msg.contains("TEI_API_KEY")
+        ));
+        assert!(matches!(
+            classify_tei_error(StatusCode::TOO_MANY_REQUESTS, "Model is overloaded"),
+            AppError::RateLimitExceeded
+        ));
+        assert!(classify_tei_error(StatusCode::BAD_GATEWAY, "").is_retryable());
+        assert!(matches!(
+            classify_tei_error(StatusCode::PAYLOAD_TOO_LARGE, "batch size 32 > 16"),
+            AppError::EmbeddingError(msg) if msg.contains("max-client-batch-size")
+        ));
+    }
+}
@@ -0,0 +1,374 @@
+//! Google Vertex AI embeddings client.
+//!
+//! For GCP projects that cannot use the public Gemini API. Requests go to the
+//! regional Vertex AI `predict` endpoint and are authorized with OAuth2
+//! tokens from a service-account key or Application Default Credentials.
+
+use std::path::Path;
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use ceres_core::error::AppError;
+use ceres_core::HttpConfig;
+use reqwest::{Client, StatusCode};
+use serde::{Deserialize, Serialize};
+
+use crate::embedding::{pad_to_dimension, EmbeddingProvider, DEFAULT_EMBEDDING_DIMENSION};
+use crate::google_auth::GoogleAuth;
+use crate::http::client_builder;
+
+/// Default model: multilingual, 768 dimensions.
+pub const DEFAULT_VERTEX_MODEL: &str = "text-multilingual-embedding-002";
+
+/// Default Vertex AI region.
+pub const DEFAULT_VERTEX_LOCATION: &str = "us-central1";
+
+/// Instances per request for the text-embedding models.
+const MAX_BATCH_SIZE: usize = 100;
+
+/// Retrieval role of the embedded text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "SCREAMING_SNAKE_CASE")]
+pub enum TaskType {
+    /// Texts stored in the index
+    RetrievalDocument,
+    /// Queries compared against stored documents
+    RetrievalQuery,
+}
+
+/// HTTP client for Vertex AI text embeddings.
+///
+/// # Examples
+///
+/// ```no_run
+/// use ceres_client::vertex::{TaskType, VertexClient};
+/// use ceres_core::HttpConfig;
+///
+/// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+/// // Uses GOOGLE_APPLICATION_CREDENTIALS-style key file or ADC
+/// let client = VertexClient::new(None, Some("my-project"), "europe-west8", &HttpConfig::default())?;
+/// let vectors = client
+///     .get_embeddings(&["dati aperti".to_string()], TaskType::RetrievalQuery)
+///     .await?;
+/// println!("Embedding dimension: {}", vectors[0].len()); // 768
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct VertexClient {
+    client: Client,
+    http_config: HttpConfig,
+    auth: Arc<GoogleAuth>,
+    project: String,
+    location: String,
+    model: String,
+    dimension: usize,
+}
+
+/// Request body for the `predict` endpoint
+#[derive(Serialize)]
+struct PredictRequest<'a> {
+    instances: Vec<Instance<'a>>,
+    parameters: Parameters,
+}
+
+#[derive(Serialize)]
+struct Instance<'a> {
+    content: &'a str,
+    task_type: TaskType,
+}
+
+#[derive(Serialize)]
+#[serde(rename_all = "camelCase")]
+struct Parameters {
+    output_dimensionality: usize,
+}
+
+/// Response from the `predict` endpoint
+#[derive(Deserialize)]
+struct PredictResponse {
+    predictions: Vec<Prediction>,
+}
+
+#[derive(Deserialize)]
+struct Prediction {
+    embeddings: PredictionEmbeddings,
+}
+
+#[derive(Deserialize)]
+struct PredictionEmbeddings {
+    values: Vec<f32>,
+}
+
+/// Error body returned by Google APIs
+#[derive(Deserialize)]
+struct GoogleErrorResponse {
+    error: GoogleErrorBody,
+}
+
+#[derive(Deserialize)]
+struct GoogleErrorBody {
+    message: String,
+}
+
+/// Maps Vertex AI HTTP errors to `AppError`.
+fn classify_vertex_error(status: StatusCode, message: &str) -> AppError {
+    match status.as_u16() {
+        401 | 403 => AppError::ConfigError(format!(
+            "Vertex AI denied access (HTTP {}): {}. Check the service account's \
+             roles (aiplatform.user) and the project",
+            status.as_u16(),
+            message
+        )),
+        429 => AppError::RateLimitExceeded,
+        500..=599 => AppError::ClientError(format!(
+            "Vertex AI server error (HTTP {}): {}",
+            status.as_u16(),
+            message
+        )),
+        code => AppError::EmbeddingError(format!("Vertex AI HTTP {}: {}", code, message)),
+    }
+}
+
+/// Instances per request; Gemini embedding models accept only one.
+fn batch_size(model: &str) -> usize {
+    if model.starts_with("gemini-embedding") {
+        1
+    } else {
+        MAX_BATCH_SIZE
+    }
+}
+
+impl VertexClient {
+    /// Creates a client for `project` in `location`.
+    ///
+    /// Credentials come from `credentials_path` (a service-account key or
+    /// `authorized_user` file), the gcloud ADC file, or the metadata server.
+    /// Without `project`, the credentials file's project is used.
+    ///
+    /// # Errors
+    ///
+    /// Returns `AppError::ConfigError` if the credentials are invalid or no
+    /// project is known.
+    pub fn new(
+        credentials_path: Option<&Path>,
+        project: Option<&str>,
+        location: &str,
+        http_config: &HttpConfig,
+    ) -> Result<Self, AppError> {
+        let client = client_builder(http_config)?
+            .build()
+            .map_err(|e| AppError::ClientError(e.to_string()))?;
+
+        let auth = GoogleAuth::from_adc(client.clone(), credentials_path)?;
+        let project = project
+            .or(auth.project_id())
+            .ok_or_else(|| {
+                AppError::ConfigError(
+                    "No Google Cloud project set. Use --vertex-project or GOOGLE_CLOUD_PROJECT"
+                        .to_string(),
+                )
+            })?
+            .to_string();
+
+        Ok(Self {
+            client,
+            http_config: http_config.clone(),
+            auth: Arc::new(auth),
+            project,
+            location: location.to_string(),
+            model: DEFAULT_VERTEX_MODEL.to_string(),
+            dimension: DEFAULT_EMBEDDING_DIMENSION,
+        })
+    }
+
+    /// Returns a client using a different embedding model.
+    pub fn with_model(mut self, model: &str) -> Self {
+        self.model = model.to_string();
+        self
+    }
+
+    /// Returns a client producing vectors of `dimension` floats.
+    ///
+    /// Sent as `outputDimensionality`; models that return fewer are
+    /// zero-padded.
+    pub fn with_dimension(mut self, dimension: usize) -> Self {
+        self.dimension = dimension;
+        self
+    }
+
+    /// Returns the embedding model name.
+    pub fn model(&self) -> &str {
+        &self.model
+    }
+
+    fn predict_url(&self) -> String {
+        format!(
+            "https://{location}-aiplatform.googleapis.com/v1/projects/{project}/locations/{location}/publishers/google/models/{model}:predict",
+            location = self.location,
+            project = self.project,
+            model = self.model
+        )
+    }
+
+    /// Embeds texts in input order.
+    ///
+    /// # Errors
+    ///
+    /// Returns `AppError::ConfigError` if authorization fails,
+    /// `AppError::RateLimitExceeded` on quota errors, `AppError::ClientError`
+    /// for server errors, and `AppError::EmbeddingError` for rejected requests.
+    pub async fn get_embeddings(
+        &self,
+        texts: &[String],
+        task_type: TaskType,
+    ) -> Result<Vec<Vec<f32>>, AppError> {
+        let mut vectors = Vec::with_capacity(texts.len());
+        let url = self.predict_url();
+
+        for chunk in texts.chunks(batch_size(&self.model)) {
+            let request = PredictRequest {
+                instances: chunk
+                    .iter()
+                    .map(|content| Instance { content, task_type })
+                    .collect(),
+                parameters: Parameters {
+                    output_dimensionality: self.dimension,
+                },
+            };
+
+            let token = self.auth.access_token().await?;
+            let response = self
+                .client
+                .post(&url)
+                .bearer_auth(token)
+                .json(&request)
+                .send()
+                .await
+                .map_err(|e| {
+                    if e.is_timeout() {
+                        AppError::Timeout(self.http_config.timeout.as_secs())
+                    } else if e.is_connect() {
+                        AppError::NetworkError(format!("Connection to Vertex AI failed: {}", e))
+                    } else {
+                        AppError::ClientError(e.to_string())
+                    }
+                })?;
+
+            let status = response.status();
+            if !status.is_success() {
+                let body = response.text().await.unwrap_or_default();
+                let message = serde_json::from_str::<GoogleErrorResponse>(&body)
+                    .map(|e| e.error.message)
+                    .unwrap_or(body);
+                return Err(classify_vertex_error(status, &message));
+            }
+
+            let response: PredictResponse = response
+                .json()
+                .await
+                .map_err(|e| AppError::ClientError(format!("Failed to parse response: {}", e)))?;
+
+            if response.predictions.len() != chunk.len() {
+                return Err(AppError::EmbeddingError(format!(
+                    "Expected {} embeddings, got {}",
+                    chunk.len(),
+                    response.predictions.len()
+                )));
+            }
+            for prediction in response.predictions {
+                vectors.push(pad_to_dimension(
+                    prediction.embeddings.values,
+                    self.dimension,
+                )?);
+            }
+        }
+
+        Ok(vectors)
+    }
+
+    async fn embed_one(&self, text: &str, task_type: TaskType) -> Result<Vec<f32>, AppError> {
+        let mut vectors = self.get_embeddings(&[text.to_string()], task_type).await?;
+        vectors.pop().ok_or(AppError::EmptyResponse)
+    }
+}
+
+#[async_trait]
+impl EmbeddingProvider for VertexClient {
+    fn model_id(&self) -> &str {
+        self.model()
+    }
+
+    fn dimension(&self) -> usize {
+        self.dimension
+    }
+
+    fn for_model(&self, model: &str) -> Arc<dyn EmbeddingProvider> {
+        Arc::new(self.clone().with_model(model))
+    }
+
+    async fn embed(&self, text: &str) -> Result<Vec<f32>, AppError> {
+        self.embed_one(text, TaskType::RetrievalDocument).await
+    }
+
+    async fn embed_query(&self, text: &str) -> Result<Vec<f32>, AppError> {
+        self.embed_one(text, TaskType::RetrievalQuery).await
+    }
+
+    async fn embed_batch(&self, texts: &[String]) -> Result<Vec<Vec<f32>>, AppError> {
+        self.get_embeddings(texts, TaskType::RetrievalDocument)
+            .await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_request_serialization() {
+        let request = PredictRequest {
+            instances: vec![Instance {
+                content: "trasporto pubblico",
+                task_type: TaskType::RetrievalQuery,
+            }],
+            parameters: Parameters {
+                output_dimensionality: DEFAULT_EMBEDDING_DIMENSION,
+            },
+        };
+
+        let json = serde_json::to_value(&request).unwrap();
+        assert_eq!(json["instances"][0]["task_type"], "RETRIEVAL_QUERY");
+        assert_eq!(json["parameters"]["outputDimensionality"], 768);
+    }
+
+    #[test]
+    fn test_response_parsing() {
+        let body = r#"{"predictions":[{"embeddings":{"statistics":{"truncated":false,"token_count":3},"values":[0.1,0.2]}}]}"#;
+        let response: PredictResponse = serde_json::from_str(body).unwrap();
+        assert_eq!(response.predictions[0].embeddings.values, vec![0.1, 0.2]);
+    }
+
+    #[test]
+    fn test_batch_size() {
+        assert_eq!(batch_size("text-embedding-005"), MAX_BATCH_SIZE);
+        assert_eq!(batch_size("gemini-embedding-001"), 1);
+    }
+
+    #[test]
+    fn test_classify_vertex_error() {
+        assert!(matches!(
+            classify_vertex_error(StatusCode::FORBIDDEN, "Permission denied"),
+            AppError::ConfigError(msg) if msg.contains("aiplatform.user")
+        ));
+        assert!(matches!(
+            classify_vertex_error(StatusCode::TOO_MANY_REQUESTS, "Quota exceeded"),
+            AppError::RateLimitExceeded
+        ));
+        assert!(classify_vertex_error(StatusCode::SERVICE_UNAVAILABLE, "").is_retryable());
+        assert!(matches!(
+            classify_vertex_error(StatusCode::NOT_FOUND, "model not found"),
+            AppError::EmbeddingError(_)
+        ));
+    }
+}
@@ -0,0 +1,332 @@
+//! Voyage AI embeddings client.
+//!
+//! Voyage's retrieval-tuned models are asymmetric like Cohere's: harvests
+//! embed with `input_type=document`, searches and watches with `query`.
+
+use std::sync::Arc;
+
+use async_trait::async_trait;
+use ceres_core::error::AppError;
+use ceres_core::HttpConfig;
+use reqwest::{Client, StatusCode};
+use serde::{Deserialize, Serialize};
+
+use crate::embedding::{pad_to_dimension, EmbeddingProvider, DEFAULT_EMBEDDING_DIMENSION};
+use crate::http::client_builder;
+
+/// Default model.
+pub const DEFAULT_VOYAGE_MODEL: &str = "voyage-3.5-lite";
+
+/// Output dimensions offered by models that support several, largest first.
+///
+/// The largest one that fits the database column is requested and
+/// zero-padded, e.g. 512 for the default 768 dimensions.
+const OUTPUT_DIMENSIONS: [u32; 4] = [2048, 1024, 512, 256];
+
+/// Texts per request, well below the API limit of 1000 so large
+/// descriptions stay within the per-request token limit.
+const MAX_BATCH_SIZE: usize = 128;
+
+const VOYAGE_EMBEDDINGS_URL: &str = "https://api.voyageai.com/v1/embeddings";
+
+/// How Voyage should treat the embedded text.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum InputType {
+    /// Texts stored in the index
+    Document,
+    /// Queries compared against stored documents
+    Query,
+}
+
+/// HTTP client for the Voyage AI embeddings API.
+///
+/// # Examples
+///
+/// ```no_run
+/// use ceres_client::voyage::{InputType, VoyageClient};
+/// use ceres_core::HttpConfig;
+///
+/// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+/// let client = VoyageClient::new("your-api-key", &HttpConfig::default())?.with_model("voyage-3.5");
+/// let vectors = client
+///     .get_embeddings(&["trasporto pubblico".to_string()], InputType::Query)
+///     .await?;
+/// println!("Embedding dimension: {}", vectors[0].len()); // 768 (padded)
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct VoyageClient {
+    client: Client,
+    http_config: HttpConfig,
+    api_key: String,
+    model: String,
+    dimension: usize,
+}
+
+/// Request body for the embeddings endpoint
+#[derive(Serialize)]
+struct EmbeddingRequest<'a> {
+    input: &'a [String],
+    model: &'a str,
+    input_type: InputType,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    output_dimension: Option<u32>,
+}
+
+/// Response from the embeddings endpoint
+#[derive(Deserialize)]
+struct EmbeddingResponse {
+    data: Vec<EmbeddingData>,
+}
+
+#[derive(Deserialize)]
+struct EmbeddingData {
+    embedding: Vec<f32>,
+    index: usize,
+}
+
+/// Error body returned by Voyage
+#[derive(Deserialize)]
+struct VoyageError {
+    detail: String,
+}
+
+/// Returns true if `model` accepts the `output_dimension` parameter.
+fn supports_output_dimension(model: &str) -> bool {
+    matches!(
+        model,
+        "voyage-3.5" | "voyage-3.5-lite" | "voyage-3-large" | "voyage-code-3"
+    )
+}
+
+/// Returns the `output_dimension` to request for `model`, if it supports one
+/// that fits `dimension`.
+fn output_dimension(model: &str, dimension: usize) -> Option<u32> {
+    if !supports_output_dimension(model) {
+        return None;
+    }
+    OUTPUT_DIMENSIONS
+        .into_iter()
+        .find(|&d| d as usize <= dimension)
+}
+
+/// Maps Voyage's HTTP errors to `AppError`.
+///
+/// See <https://docs.voyageai.com/docs/error-codes>.
+fn classify_voyage_error(status: StatusCode, message: &str) -> AppError {
+    match status.as_u16() {
+        401 => AppError::ConfigError(format!(
+            "Invalid Voyage AI API key ({}). Check VOYAGE_API_KEY",
+            message
+        )),
+        403 => AppError::ConfigError(format!("Voyage AI access denied: {}", message)),
+        429 => AppError::RateLimitExceeded,
+        500..=599 => AppError::ClientError(format!(
+            "Voyage AI server error (HTTP {}): {}",
+            status.as_u16(),
+            message
+        )),
+        code => AppError::EmbeddingError(format!("Voyage AI HTTP {}: {}", code, message)),
+    }
+}
+
+impl VoyageClient {
+    /// Creates a new Voyage AI client with the specified API key.
+    pub fn new(api_key: &str, http_config: &HttpConfig) -> Result<Self, AppError> {
+        let client = client_builder(http_config)?
+            .build()
+            .map_err(|e| AppError::ClientError(e.to_string()))?;
+
+        Ok(Self {
+            client,
+            http_config: http_config.clone(),
+            api_key: api_key.to_string(),
+            model: DEFAULT_VOYAGE_MODEL.to_string(),
+            dimension: DEFAULT_EMBEDDING_DIMENSION,
+        })
+    }
+
+    /// Returns a client using a different embedding model.
+    pub fn with_model(mut self, model: &str) -> Self {
+        self.model = model.to_string();
+        self
+    }
+
+    /// Returns a client producing vectors of `dimension` floats.
+    ///
+    /// Smaller native vectors are zero-padded; larger ones are rejected.
+    pub fn with_dimension(mut self, dimension: usize) -> Self {
+        self.dimension = dimension;
+        self
+    }
+
+    /// Returns the embedding model name.
+    pub fn model(&self) -> &str {
+        &self.model
+    }
+
+    /// Embeds texts in input order, in requests of up to 128 texts.
+    ///
+    /// # Errors
+    ///
+    /// Returns `AppError::ConfigError` for an invalid API key,
+    /// `AppError::RateLimitExceeded` on HTTP 429, `AppError::ClientError`
+    /// for server errors, and `AppError::EmbeddingError` for rejected
+    /// requests or vectors larger than the database dimension.
+    pub async fn get_embeddings(
+        &self,
+        texts: &[String],
+        input_type: InputType,
+    ) -> Result<Vec<Vec<f32>>, AppError> {
+        let mut vectors = Vec::with_capacity(texts.len());
+
+        for chunk in texts.chunks(MAX_BATCH_SIZE) {
+            let request = EmbeddingRequest {
+                input: chunk,
+                model: &self.model,
+                input_type,
+                output_dimension: output_dimension(&self.model, self.dimension),
+            };
+
+            let response = self
+                .client
+                .post(VOYAGE_EMBEDDINGS_URL)
+                .bearer_auth(&self.api_key)
+                .json(&request)
+                .send()
+                .await
+                .map_err(|e| {
+                    if e.is_timeout() {
+                        AppError::Timeout(self.http_config.timeout.as_secs())
+                    } else if e.is_connect() {
+                        AppError::NetworkError(format!("Connection to Voyage AI failed: {}", e))
+                    } else {
+                        AppError::ClientError(e.to_string())
+                    }
+                })?;
+
+            let status = response.status();
+            if !status.is_success() {
+                let body = response.text().await.unwrap_or_default();
+                let message = serde_json::from_str::<VoyageError>(&body)
+                    .map(|e| e.detail)
+                    .unwrap_or(body);
+                return Err(classify_voyage_error(status, &message));
+            }
+
+            let mut response: EmbeddingResponse = response
+                .json()
+                .await
+                .map_err(|e| AppError::ClientError(format!("Failed to parse response: {}", e)))?;
+
+            if response.data.len() != chunk.len() {
+                return Err(AppError::EmbeddingError(format!(
+                    "Expected {} embeddings, got {}",
+                    chunk.len(),
+                    response.data.len()
+                )));
+            }
+            response.data.sort_by_key(|d| d.index);
+            for data in response.data {
+                vectors.push(pad_to_dimension(data.embedding, self.dimension)?);
+            }
+        }
+
+        Ok(vectors)
+    }
+
+    async fn embed_one(&self, text: &str, input_type: InputType) -> Result<Vec<f32>, AppError> {
+        let mut vectors = self.get_embeddings(&[text.to_string()], input_type).await?;
+        vectors.pop().ok_or(AppError::EmptyResponse)
+    }
+}
+
+#[async_trait]
+impl EmbeddingProvider for VoyageClient {
+    fn model_id(&self) -> &str {
+        self.model()
+    }
+
+    fn dimension(&self) -> usize {
+        self.dimension
+    }
+
+    fn for_model(&self, model: &str) -> Arc<dyn EmbeddingProvider> {
+        Arc::new(self.clone().with_model(model))
+    }
+
+    async fn embed(&self, text: &str) -> Result<Vec<f32>, AppError> {
+        self.embed_one(text, InputType::Document).await
+    }
+
+    async fn embed_query(&self, text: &str) -> Result<Vec<f32>, AppError> {
+        self.embed_one(text, InputType::Query).await
+    }
+
+    async fn embed_batch(&self, texts: &[String]) -> Result<Vec<Vec<f32>>, AppError> {
+        self.get_embeddings(texts, InputType::Document).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_request_serialization() {
+        let texts = vec!["Hello world".to_string()];
+        let request = EmbeddingRequest {
+            input: &texts,
+            model: DEFAULT_VOYAGE_MODEL,
+            input_type: InputType::Document,
+            output_dimension: Some(512),
+        };
+
+        let json = serde_json::to_value(&request).unwrap();
+        assert_eq!(json["model"], "voyage-3.5-lite");
+        assert_eq!(json["input_type"], "document");
+        assert_eq!(json["output_dimension"], 512);
+
+        let request = EmbeddingRequest {
+            output_dimension: None,
+            ..request
+        };
+        let json = serde_json::to_value(&request).unwrap();
+        assert!(json.get("output_dimension").is_none());
+    }
+
+    #[test]
+    fn test_supports_output_dimension() {
+        assert!(supports_output_dimension("voyage-3.5"));
+        assert!(supports_output_dimension(DEFAULT_VOYAGE_MODEL));
+        assert!(!supports_output_dimension("voyage-3-lite"));
+    }
+
+    #[test]
+    fn test_output_dimension_fits_column() {
+        assert_eq!(output_dimension(DEFAULT_VOYAGE_MODEL, 768), Some(512));
+        assert_eq!(output_dimension("voyage-3-large", 1536), Some(1024));
+        assert_eq!(output_dimension("voyage-3-large", 2048), Some(2048));
+        assert_eq!(output_dimension("voyage-3-large", 128), None);
+        assert_eq!(output_dimension("voyage-3-lite", 768), None);
+    }
+
+    #[test]
+    fn test_classify_voyage_error() {
+        assert!(matches!(
+            classify_voyage_error(StatusCode::UNAUTHORIZED, "bad key"),
+            AppError::ConfigError(msg) if msg.contains("VOYAGE_API_KEY")
+        ));
+        assert!(matches!(
+            classify_voyage_error(StatusCode::TOO_MANY_REQUESTS, ""),
+            AppError::RateLimitExceeded
+        ));
+        assert!(classify_voyage_error(StatusCode::SERVICE_UNAVAILABLE, "").is_retryable());
+        assert!(matches!(
+            classify_voyage_error(StatusCode::BAD_REQUEST, "input too long"),
+            AppError::EmbeddingError(msg) if msg.contains("input too long")
+        ));
+    }
+}
@@ -0,0 +1,159 @@
+//! Webhook client for delivering harvest notifications.
+
+use ceres_core::error::AppError;
+use ceres_core::watch::{sign_payload, SIGNATURE_HEADER, TIMESTAMP_HEADER};
+use ceres_core::HttpConfig;
+use reqwest::header::{HeaderMap, HeaderValue, CONTENT_TYPE};
+use reqwest::{Client, StatusCode};
+use serde::Serialize;
+use tokio::time::sleep;
+
+use crate::http::client_builder;
+
+/// HTTP client that POSTs JSON payloads to webhook URLs.
+///
+/// Server errors, rate limits and connection failures are retried with
+/// backoff; any other non-2xx response fails immediately.
+///
+/// # Examples
+///
+/// ```no_run
+/// use ceres_client::WebhookClient;
+/// use ceres_core::HttpConfig;
+///
+/// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+/// let client = WebhookClient::new(&HttpConfig::default())?;
+/// let payload = serde_json::json!({ "text": "Harvest completed" });
+/// client.post("https://hooks.example.com/ceres", &payload).await?;
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct WebhookClient {
+    client: Client,
+    http_config: HttpConfig,
+}
+
+impl WebhookClient {
+    /// Creates a new webhook client.
+    ///
+    /// # Errors
+    ///
+    /// Returns `AppError::ClientError` if the HTTP client cannot be built.
+    pub fn new(http_config: &HttpConfig) -> Result<Self, AppError> {
+        let client = client_builder(http_config)?
+            .build()
+            .map_err(|e| AppError::ClientError(e.to_string()))?;
+
+        Ok(Self {
+            client,
+            http_config: http_config.clone(),
+        })
+    }
+
+    /// POSTs `payload` as JSON to `url`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `AppError::InvalidUrl` if `url` cannot be parsed, or the last
+    /// delivery error once retries are exhausted.
+    pub async fn post<T: Serialize>(&self, url: &str, payload: &T) -> Result<(), AppError> {
+        let body = serde_json::to_vec(payload)?;
+        self.send(url, body, HeaderMap::new()).await
+    }
+
+    /// POSTs `payload` as JSON to `url`, signed with `secret`.
+    ///
+    /// Adds the `X-Ceres-Timestamp` and `X-Ceres-Signature` headers described
+    /// in [`ceres_core::watch`]. The signature covers the exact bytes sent.
+    pub async fn post_signed<T: Serialize>(
+        &self,
+        url: &str,
+        payload: &T,
+        secret: &str,
+        timestamp: i64,
+    ) -> Result<(), AppError> {
+        let body = serde_json::to_vec(payload)?;
+        let signature = sign_payload(secret, timestamp, &body);
+
+        let mut headers = HeaderMap::new();
+        headers.insert(TIMESTAMP_HEADER, HeaderValue::from(timestamp));
+        headers.insert(
+            SIGNATURE_HEADER,
+            HeaderValue::from_str(&signature).map_err(|e| AppError::Generic(e.to_string()))?,
+        );
+        self.send(url, body, headers).await
+    }
+
+    async fn send(&self, url: &str, body: Vec<u8>, headers: HeaderMap) -> Result<(), AppError> {
+        let url = reqwest::Url::parse(url).map_err(|e| AppError::InvalidUrl(e.to_string()))?;
+        let http_config = &self.http_config;
+        let max_retries = http_config.max_retries;
+        let base_delay = http_config.retry_base_delay;
+        let mut last_error = AppError::Generic("No attempts made".to_string());
+
+        for attempt in 1..=max_retries {
+            let request = self
+                .client
+                .post(url.clone())
+                .header(CONTENT_TYPE, "application/json")
+                .headers(headers.clone())
+                .body(body.clone());
+
+            match request.send().await {
+                Ok(resp) => {
+                    let status = resp.status();
+
+                    if status.is_success() {
+                        return Ok(());
+                    }
+
+                    if status == StatusCode::TOO_MANY_REQUESTS || status.is_server_error() {
+                        last_error = AppError::ClientError(format!(
+                            "Webhook returned HTTP {}",
+                            status.as_u16()
+                        ));
+                        if attempt < max_retries {
+                            sleep(base_delay * 2_u32.pow(attempt)).await;
+                            continue;
+                        }
+                        break;
+                    }
+
+                    return Err(AppError::ClientError(format!(
+                        "Webhook returned HTTP {} from {}",
+                        status.as_u16(),
+                        url
+                    )));
+                }
+                Err(e) => {
+                    if e.is_timeout() {
+                        last_error = AppError::Timeout(http_config.timeout.as_secs());
+                    } else if e.is_connect() {
+                        last_error = AppError::NetworkError(format!("Connection failed: {}", e));
+                    } else {
+                        return Err(AppError::ClientError(e.to_string()));
+                    }
+
+                    if attempt < max_retries {
+                        sleep(base_delay * attempt).await;
+                    }
+                }
+            }
+        }
+
+        Err(last_error)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[tokio::test]
+    async fn test_post_rejects_invalid_url() {
+        let client = WebhookClient::new(&HttpConfig::default()).unwrap();
+        let result = client.post("not a url", &serde_json::json!({})).await;
+        assert!(matches!(result, Err(AppError::InvalidUrl(_))));
+    }
+}
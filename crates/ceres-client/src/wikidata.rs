@@ -0,0 +1,211 @@
+//! Wikidata entity search for metadata enrichment (`ceres enrich`).
+
+use ceres_core::enrichment::{label_key, WikidataEntity};
+use ceres_core::error::AppError;
+use ceres_core::HttpConfig;
+use reqwest::{Client, StatusCode};
+use serde::Deserialize;
+
+use crate::http::client_builder;
+
+/// Wikidata's MediaWiki action API.
+pub const DEFAULT_WIKIDATA_API_URL: &str = "https://www.wikidata.org/w/api.php";
+
+/// Candidates fetched per search; the first exact label match wins.
+const SEARCH_LIMIT: &str = "7";
+
+/// Response of `action=wbsearchentities`
+#[derive(Deserialize)]
+struct SearchResponse {
+    #[serde(default)]
+    search: Vec<SearchResult>,
+    error: Option<ApiError>,
+}
+
+#[derive(Deserialize)]
+struct SearchResult {
+    id: String,
+    label: Option<String>,
+    description: Option<String>,
+    #[serde(rename = "match")]
+    matched: SearchMatch,
+}
+
+/// The label or alias a result was found by
+#[derive(Deserialize)]
+struct SearchMatch {
+    text: String,
+}
+
+#[derive(Deserialize)]
+struct ApiError {
+    code: String,
+    info: String,
+}
+
+/// HTTP client for Wikidata's entity search.
+///
+/// # Examples
+///
+/// ```no_run
+/// use ceres_client::WikidataClient;
+/// use ceres_core::HttpConfig;
+///
+/// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+/// let wikidata = WikidataClient::new(&HttpConfig::default())?.with_language("it");
+/// if let Some(entity) = wikidata.search("Comune di Milano").await? {
+///     println!("{} ({})", entity.label, entity.qid);
+/// }
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Clone)]
+pub struct WikidataClient {
+    client: Client,
+    http_config: HttpConfig,
+    api_url: String,
+    language: String,
+}
+
+impl WikidataClient {
+    /// Creates a client searching English labels on wikidata.org.
+    ///
+    /// # Errors
+    ///
+    /// Returns `AppError::ClientError` if the HTTP client cannot be built.
+    pub fn new(http_config: &HttpConfig) -> Result<Self, AppError> {
+        let client = client_builder(http_config)?
+            .build()
+            .map_err(|e| AppError::ClientError(e.to_string()))?;
+
+        Ok(Self {
+            client,
+            http_config: http_config.clone(),
+            api_url: DEFAULT_WIKIDATA_API_URL.to_string(),
+            language: "en".to_string(),
+        })
+    }
+
+    /// Returns a client searching labels and aliases in `language` (e.g. `it`).
+    pub fn with_language(mut self, language: &str) -> Self {
+        self.language = language.to_string();
+        self
+    }
+
+    /// Returns a client using another MediaWiki API endpoint, e.g. a mirror.
+    pub fn with_api_url(mut self, api_url: &str) -> Self {
+        self.api_url = api_url.to_string();
+        self
+    }
+
+    /// Returns the search language.
+    pub fn language(&self) -> &str {
+        &self.language
+    }
+
+    /// Finds the item whose label or alias equals `label`.
+    ///
+    /// Only exact matches (ignoring case and whitespace) are accepted, so a
+    /// search for a small town never links to a namesake found by prefix.
+    /// Returns `None` if no candidate matches.
+    ///
+    /// # Errors
+    ///
+    /// Returns `AppError::RateLimitExceeded` when throttled,
+    /// `AppError::ClientError` for server errors and API errors, or a
+    /// network error if Wikidata is unreachable.
+    pub async fn search(&self, label: &str) -> Result<Option<WikidataEntity>, AppError> {
+        let response = self
+            .client
+            .get(&self.api_url)
+            .query(&[
+                ("action", "wbsearchentities"),
+                ("format", "json"),
+                ("type", "item"),
+                ("search", label),
+                ("language", &self.language),
+                ("uselang", &self.language),
+                ("limit", SEARCH_LIMIT),
+            ])
+            .send()
+            .await
+            .map_err(|e| {
+                if e.is_timeout() {
+                    AppError::Timeout(self.http_config.timeout.as_secs())
+                } else {
+                    AppError::NetworkError(format!("Connection to Wikidata failed: {}", e))
+                }
+            })?;
+
+        let status = response.status();
+        if status == StatusCode::TOO_MANY_REQUESTS {
+            return Err(AppError::RateLimitExceeded);
+        }
+        if !status.is_success() {
+            return Err(AppError::ClientError(format!(
+                "Wikidata returned HTTP {}",
+                status.as_u16()
+            )));
+        }
+
+        let response: SearchResponse = response
+            .json()
+            .await
+            .map_err(|e| AppError::ClientError(format!("Failed to parse response: {}", e)))?;
+        best_match(response, label)
+    }
+}
+
+/// Picks the first result found by a label or alias equal to `label`.
+fn best_match(response: SearchResponse, label: &str) -> Result<Option<WikidataEntity>, AppError> {
+    if let Some(error) = response.error {
+        return Err(AppError::ClientError(format!(
+            "Wikidata API error {}: {}",
+            error.code, error.info
+        )));
+    }
+
+    let key = label_key(label);
+    Ok(response
+        .search
+        .into_iter()
+        .find(|result| label_key(&result.matched.text) == key)
+        .map(|result| WikidataEntity {
+            label: result.label.unwrap_or_else(|| label.to_string()),
+            qid: result.id,
+            description: result.description,
+        }))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(body: &str) -> SearchResponse {
+        serde_json::from_str(body).unwrap()
+    }
+
+    #[test]
+    fn test_best_match_requires_exact_label() {
+        let body = r#"{"search":[
+            {"id":"Q1","label":"Milano Marittima","match":{"type":"label","language":"it","text":"Milano Marittima"}},
+            {"id":"Q490","label":"Milano","description":"città italiana","match":{"type":"alias","language":"it","text":"milano"}}
+        ]}"#;
+
+        let entity = best_match(parse(body), "Milano").unwrap().unwrap();
+        assert_eq!(entity.qid, "Q490");
+        assert_eq!(entity.description.as_deref(), Some("città italiana"));
+
+        assert_eq!(best_match(parse(body), "Milano 2").unwrap(), None);
+    }
+
+    #[test]
+    fn test_best_match_surfaces_api_errors() {
+        let body =
+            r#"{"error":{"code":"param-missing","info":"The search parameter must be set."}}"#;
+        assert!(matches!(
+            best_match(parse(body), "x"),
+            Err(AppError::ClientError(msg)) if msg.contains("param-missing")
+        ));
+    }
+}
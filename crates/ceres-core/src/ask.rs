@@ -0,0 +1,83 @@
+//! Question answering over the index (`ceres ask`).
+//!
+//! The question is embedded like a search query, and the best-matching
+//! datasets are handed to a generative model as numbered sources. The model
+//! answers from those sources only, citing them by number, so every claim
+//! can be traced back to a dataset title and URL.
+
+use crate::facets::organization_name;
+use crate::models::Dataset;
+
+/// Datasets retrieved as sources for an answer.
+pub const DEFAULT_ASK_SOURCES: usize = 8;
+
+/// Description characters included per source.
+const SOURCE_DESCRIPTION_CHARS: usize = 1500;
+
+/// Text of a dataset given to the answering model: title, publisher,
+/// formats, last modification and the start of the description.
+pub fn source_document(dataset: &Dataset) -> String {
+    let mut document = dataset.title.trim().to_string();
+    if let Some(organization) = organization_name(&dataset.metadata) {
+        document.push_str(&format!("\nPublisher: {}", organization));
+    }
+    if !dataset.formats.is_empty() {
+        document.push_str(&format!("\nFormats: {}", dataset.formats.join(", ")));
+    }
+    if let Some(modified_at) = dataset.modified_at {
+        document.push_str(&format!(
+            "\nLast modified: {}",
+            modified_at.format("%Y-%m-%d")
+        ));
+    }
+    if let Some(description) = dataset.description.as_deref().map(str::trim) {
+        if !description.is_empty() {
+            let description: String = description.chars().take(SOURCE_DESCRIPTION_CHARS).collect();
+            document.push_str(&format!("\n{}", description));
+        }
+    }
+    document
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeZone, Utc};
+    use serde_json::json;
+    use sqlx::types::Json;
+    use uuid::Uuid;
+
+    #[test]
+    fn test_source_document() {
+        let mut dataset = Dataset {
+            id: Uuid::new_v4(),
+            original_id: "x".to_string(),
+            source_portal: "https://dati.comune.milano.it".to_string(),
+            url: "https://dati.comune.milano.it/dataset/x".to_string(),
+            title: " Qualità dell'aria ".to_string(),
+            description: Some("Misure giornaliere di PM10.".to_string()),
+            embedding: None,
+            metadata: Json(json!({"organization": {"title": "Comune di Milano"}})),
+            formats: vec!["CSV".to_string(), "JSON".to_string()],
+            first_seen_at: Utc::now(),
+            last_updated_at: Utc::now(),
+            content_hash: None,
+            embedding_model: None,
+            embedded_at: None,
+            modified_at: Some(Utc.with_ymd_and_hms(2024, 5, 1, 0, 0, 0).unwrap()),
+            language: None,
+            quality: None,
+        };
+        assert_eq!(
+            source_document(&dataset),
+            "Qualità dell'aria\nPublisher: Comune di Milano\nFormats: CSV, JSON\n\
+             Last modified: 2024-05-01\nMisure giornaliere di PM10."
+        );
+
+        dataset.metadata = Json(json!({}));
+        dataset.formats.clear();
+        dataset.modified_at = None;
+        dataset.description = Some(" ".to_string());
+        assert_eq!(source_document(&dataset), "Qualità dell'aria");
+    }
+}
@@ -0,0 +1,261 @@
+//! Harvest audit: compare the database against a portal without writing.
+//!
+//! An audit fetches every dataset of a portal, hashes it exactly like a
+//! harvest would, and reports where the local copy has drifted:
+//!
+//! - **missing locally**: upstream datasets that were never stored
+//! - **missing upstream**: stored datasets the portal no longer lists
+//! - **hash mismatches**: stored content differs from upstream; flagged as
+//!   stale when upstream was modified after the local copy was last synced
+
+use std::collections::HashMap;
+
+use chrono::{DateTime, NaiveDateTime, Utc};
+use serde::Serialize;
+
+/// Sync state of a stored dataset.
+#[derive(Debug, Clone)]
+pub struct LocalRecord {
+    pub original_id: String,
+    pub content_hash: Option<String>,
+    pub last_updated_at: DateTime<Utc>,
+}
+
+/// Current state of a dataset on the portal.
+#[derive(Debug, Clone)]
+pub struct UpstreamRecord {
+    pub original_id: String,
+    pub title: String,
+    pub content_hash: String,
+    /// CKAN `metadata_modified`, if the portal reports it
+    pub modified_at: Option<DateTime<Utc>>,
+}
+
+/// A dataset whose stored hash differs from the portal's.
+#[derive(Debug, Clone, Serialize)]
+pub struct HashMismatch {
+    pub original_id: String,
+    pub title: String,
+    /// Stored hash (`None` for legacy records without one)
+    pub local_hash: Option<String>,
+    pub upstream_hash: String,
+    /// When the local copy was last synced
+    pub local_updated_at: DateTime<Utc>,
+    pub upstream_modified_at: Option<DateTime<Utc>>,
+    /// Upstream changed after the last sync, so the next harvest will pick it up
+    pub stale: bool,
+}
+
+/// Differences between the database and a portal.
+#[derive(Debug, Clone, Serialize)]
+pub struct AuditReport {
+    pub portal_url: String,
+    pub upstream_total: usize,
+    pub local_total: usize,
+    /// Datasets present on both sides with matching hashes
+    pub in_sync: usize,
+    /// Upstream IDs not in the database (title included)
+    pub missing_locally: Vec<(String, String)>,
+    /// Stored IDs the portal no longer lists
+    pub missing_upstream: Vec<String>,
+    pub hash_mismatches: Vec<HashMismatch>,
+    /// Upstream IDs whose details could not be fetched, with the error
+    pub fetch_failures: Vec<(String, String)>,
+}
+
+impl AuditReport {
+    /// Returns true if no discrepancy was found.
+    pub fn is_clean(&self) -> bool {
+        self.missing_locally.is_empty()
+            && self.missing_upstream.is_empty()
+            && self.hash_mismatches.is_empty()
+            && self.fetch_failures.is_empty()
+    }
+}
+
+/// Compares stored records with upstream ones.
+///
+/// `fetch_failures` are upstream IDs that are listed but whose details
+/// could not be fetched; they are neither reported missing nor compared.
+/// All lists are sorted by original ID for stable output.
+pub fn compare(
+    portal_url: &str,
+    local: Vec<LocalRecord>,
+    upstream: Vec<UpstreamRecord>,
+    fetch_failures: Vec<(String, String)>,
+) -> AuditReport {
+    let local_total = local.len();
+    let upstream_total = upstream.len() + fetch_failures.len();
+    let mut local: HashMap<String, LocalRecord> = local
+        .into_iter()
+        .map(|r| (r.original_id.clone(), r))
+        .collect();
+
+    let mut report = AuditReport {
+        portal_url: portal_url.to_string(),
+        upstream_total,
+        local_total,
+        in_sync: 0,
+        missing_locally: Vec::new(),
+        missing_upstream: Vec::new(),
+        hash_mismatches: Vec::new(),
+        fetch_failures,
+    };
+
+    for (id, _) in &report.fetch_failures {
+        local.remove(id);
+    }
+
+    for record in upstream {
+        match local.remove(&record.original_id) {
+            None => report
+                .missing_locally
+                .push((record.original_id, record.title)),
+            Some(stored) if stored.content_hash.as_deref() == Some(&record.content_hash) => {
+                report.in_sync += 1;
+            }
+            Some(stored) => {
+                let stale = record
+                    .modified_at
+                    .is_some_and(|modified| modified > stored.last_updated_at);
+                report.hash_mismatches.push(HashMismatch {
+                    original_id: record.original_id,
+                    title: record.title,
+                    local_hash: stored.content_hash,
+                    upstream_hash: record.content_hash,
+                    local_updated_at: stored.last_updated_at,
+                    upstream_modified_at: record.modified_at,
+                    stale,
+                });
+            }
+        }
+    }
+    report.missing_upstream = local.into_keys().collect();
+
+    report.missing_locally.sort();
+    report.missing_upstream.sort();
+    report
+        .hash_mismatches
+        .sort_by(|a, b| a.original_id.cmp(&b.original_id));
+    report.fetch_failures.sort();
+    report
+}
+
+/// Parses a CKAN timestamp such as `2024-01-15T10:30:00.123456` (UTC, no offset).
+pub fn parse_ckan_timestamp(value: &str) -> Option<DateTime<Utc>> {
+    DateTime::parse_from_rfc3339(value)
+        .map(|dt| dt.with_timezone(&Utc))
+        .ok()
+        .or_else(|| {
+            NaiveDateTime::parse_from_str(value, "%Y-%m-%dT%H:%M:%S%.f")
+                .ok()
+                .map(|dt| dt.and_utc())
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn at(hour: u32) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2025, 3, 1, hour, 0, 0).unwrap()
+    }
+
+    fn local(id: &str, hash: Option<&str>, updated: DateTime<Utc>) -> LocalRecord {
+        LocalRecord {
+            original_id: id.to_string(),
+            content_hash: hash.map(str::to_string),
+            last_updated_at: updated,
+        }
+    }
+
+    fn upstream(id: &str, hash: &str, modified: Option<DateTime<Utc>>) -> UpstreamRecord {
+        UpstreamRecord {
+            original_id: id.to_string(),
+            title: format!("Dataset {}", id),
+            content_hash: hash.to_string(),
+            modified_at: modified,
+        }
+    }
+
+    #[test]
+    fn test_compare_classifies_discrepancies() {
+        let report = compare(
+            "https://a.com",
+            vec![
+                local("same", Some("h1"), at(10)),
+                local("changed", Some("old"), at(10)),
+                local("gone", Some("h3"), at(10)),
+                local("legacy", None, at(10)),
+            ],
+            vec![
+                upstream("same", "h1", None),
+                upstream("changed", "new", Some(at(12))),
+                upstream("new", "h4", None),
+                upstream("legacy", "h5", Some(at(8))),
+            ],
+            Vec::new(),
+        );
+
+        assert_eq!(report.upstream_total, 4);
+        assert_eq!(report.local_total, 4);
+        assert_eq!(report.in_sync, 1);
+        assert_eq!(
+            report.missing_locally,
+            vec![("new".to_string(), "Dataset new".to_string())]
+        );
+        assert_eq!(report.missing_upstream, vec!["gone".to_string()]);
+
+        let ids: Vec<&str> = report
+            .hash_mismatches
+            .iter()
+            .map(|m| m.original_id.as_str())
+            .collect();
+        assert_eq!(ids, vec!["changed", "legacy"]);
+        assert!(report.hash_mismatches[0].stale);
+        assert!(!report.hash_mismatches[1].stale);
+        assert!(!report.is_clean());
+    }
+
+    #[test]
+    fn test_compare_fetch_failures_are_not_missing() {
+        let report = compare(
+            "https://a.com",
+            vec![local("flaky", Some("h1"), at(10))],
+            Vec::new(),
+            vec![("flaky".to_string(), "timeout".to_string())],
+        );
+
+        assert_eq!(report.upstream_total, 1);
+        assert!(report.missing_upstream.is_empty());
+        assert_eq!(report.fetch_failures.len(), 1);
+    }
+
+    #[test]
+    fn test_compare_clean() {
+        let report = compare(
+            "https://a.com",
+            vec![local("a", Some("h1"), at(10))],
+            vec![upstream("a", "h1", Some(at(12)))],
+            Vec::new(),
+        );
+        assert!(report.is_clean());
+        assert_eq!(report.in_sync, 1);
+    }
+
+    #[test]
+    fn test_parse_ckan_timestamp() {
+        let expected = Utc.with_ymd_and_hms(2024, 1, 15, 10, 30, 0).unwrap();
+        assert_eq!(
+            parse_ckan_timestamp("2024-01-15T10:30:00.000000"),
+            Some(expected)
+        );
+        assert_eq!(parse_ckan_timestamp("2024-01-15T10:30:00"), Some(expected));
+        assert_eq!(
+            parse_ckan_timestamp("2024-01-15T11:30:00+01:00"),
+            Some(expected)
+        );
+        assert_eq!(parse_ckan_timestamp("yesterday"), None);
+    }
+}
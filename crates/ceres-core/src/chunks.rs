@@ -0,0 +1,241 @@
+//! Chunk embeddings: several vectors per dataset.
+//!
+//! A dataset's own embedding covers its title and description. In
+//! multi-vector mode, each resource (file name, description, format) and
+//! each part of a long description is embedded separately into
+//! `dataset_chunks`, so a query matching a single file still finds its
+//! dataset. Search scores a dataset by its best-matching vector.
+
+use std::collections::HashMap;
+
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+/// Longest chunk text in characters; longer descriptions are split.
+pub const MAX_CHUNK_CHARS: usize = 1000;
+
+/// Resources embedded per dataset; further resources are ignored.
+pub const MAX_RESOURCE_CHUNKS: usize = 50;
+
+/// Chunk hits fetched per requested search result, as several chunks of a
+/// dataset often rank next to each other.
+pub const CHUNK_CANDIDATES_PER_RESULT: usize = 5;
+
+/// What part of a dataset a chunk was cut from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ChunkKind {
+    /// Part of a description too long for a single chunk
+    Description,
+    /// A resource's name, description and format
+    Resource,
+}
+
+impl ChunkKind {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ChunkKind::Description => "description",
+            ChunkKind::Resource => "resource",
+        }
+    }
+}
+
+/// A piece of dataset metadata embedded on its own.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DatasetChunk {
+    pub kind: ChunkKind,
+    /// Text sent to the embedding provider, prefixed with the dataset title
+    pub text: String,
+}
+
+/// Cuts a dataset into chunks.
+///
+/// Descriptions up to [`MAX_CHUNK_CHARS`] are already covered by the
+/// dataset embedding and produce no chunk; longer ones are split at
+/// paragraph, sentence or word boundaries. Every resource with a name or
+/// description produces one chunk. Each chunk starts with the dataset
+/// title, so it keeps its context.
+pub fn build_chunks(title: &str, description: Option<&str>, metadata: &Value) -> Vec<DatasetChunk> {
+    let mut chunks = Vec::new();
+    let title = title.trim();
+
+    if let Some(description) = description.map(str::trim) {
+        if description.chars().count() > MAX_CHUNK_CHARS {
+            for part in split_text(description, MAX_CHUNK_CHARS) {
+                chunks.push(DatasetChunk {
+                    kind: ChunkKind::Description,
+                    text: format!("{}: {}", title, part),
+                });
+            }
+        }
+    }
+
+    let resources = metadata["resources"].as_array().into_iter().flatten();
+    for resource in resources.take(MAX_RESOURCE_CHUNKS) {
+        let field = |key: &str| {
+            resource[key]
+                .as_str()
+                .map(str::trim)
+                .filter(|s| !s.is_empty())
+        };
+        let parts: Vec<&str> = [field("name"), field("description")]
+            .into_iter()
+            .flatten()
+            .collect();
+        if parts.is_empty() {
+            continue;
+        }
+
+        let mut text = format!("{}: {}", title, parts.join(" - "));
+        if let Some(format) = field("format") {
+            text.push_str(&format!(" ({})", format));
+        }
+        chunks.push(DatasetChunk {
+            kind: ChunkKind::Resource,
+            text: truncate_chars(&text, MAX_CHUNK_CHARS),
+        });
+    }
+
+    chunks
+}
+
+/// Hash of a dataset's chunk texts, stored to skip re-embedding unchanged chunks.
+///
+/// Returns `None` for datasets without chunks.
+pub fn chunks_hash(chunks: &[DatasetChunk]) -> Option<String> {
+    if chunks.is_empty() {
+        return None;
+    }
+    let mut hasher = Sha256::new();
+    for chunk in chunks {
+        hasher.update(chunk.kind.as_str().as_bytes());
+        hasher.update([0]);
+        hasher.update(chunk.text.as_bytes());
+        hasher.update([0]);
+    }
+    Some(format!("{:x}", hasher.finalize()))
+}
+
+/// Hash of a chunk text, used as its embedding cache key.
+pub fn chunk_text_hash(text: &str) -> String {
+    format!("{:x}", Sha256::digest(text.as_bytes()))
+}
+
+/// Scores each dataset by its best hit, best first.
+///
+/// `hits` holds similarity scores of dataset and chunk vectors, tagged
+/// with the dataset they belong to.
+pub fn aggregate_scores(hits: impl IntoIterator<Item = (Uuid, f32)>) -> Vec<(Uuid, f32)> {
+    let mut best: HashMap<Uuid, f32> = HashMap::new();
+    for (id, score) in hits {
+        best.entry(id)
+            .and_modify(|s| *s = s.max(score))
+            .or_insert(score);
+    }
+    let mut scores: Vec<(Uuid, f32)> = best.into_iter().collect();
+    scores.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(&b.0)));
+    scores
+}
+
+/// Splits `text` into parts of at most `max` characters, preferring to cut
+/// after a paragraph, then a sentence, then a word.
+fn split_text(text: &str, max: usize) -> Vec<String> {
+    let mut parts = Vec::new();
+    let mut rest = text.trim();
+
+    while rest.chars().count() > max {
+        let window_end = rest.char_indices().nth(max).map_or(rest.len(), |(i, _)| i);
+        let window = &rest[..window_end];
+        let cut = ["\n\n", ". ", " "]
+            .iter()
+            .find_map(|sep| {
+                window
+                    .rfind(sep)
+                    .filter(|&i| i > max / 2)
+                    .map(|i| i + sep.len())
+            })
+            .unwrap_or(window_end);
+
+        parts.push(rest[..cut].trim().to_string());
+        rest = rest[cut..].trim_start();
+    }
+    if !rest.is_empty() {
+        parts.push(rest.to_string());
+    }
+    parts
+}
+
+fn truncate_chars(text: &str, max: usize) -> String {
+    text.chars().take(max).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_build_chunks_from_resources() {
+        let metadata = json!({"resources": [
+            {"name": "centraline_2024.csv", "description": "Misure orarie PM10", "format": "CSV"},
+            {"name": " ", "url": "https://example.com/data.zip"},
+            {"name": "stazioni", "format": ""}
+        ]});
+
+        let chunks = build_chunks("Qualità dell'aria", Some("Breve."), &metadata);
+        assert_eq!(
+            chunks,
+            vec![
+                DatasetChunk {
+                    kind: ChunkKind::Resource,
+                    text: "Qualità dell'aria: centraline_2024.csv - Misure orarie PM10 (CSV)"
+                        .to_string()
+                },
+                DatasetChunk {
+                    kind: ChunkKind::Resource,
+                    text: "Qualità dell'aria: stazioni".to_string()
+                },
+            ]
+        );
+        assert!(build_chunks("x", None, &json!({})).is_empty());
+    }
+
+    #[test]
+    fn test_build_chunks_splits_long_descriptions() {
+        let sentence = "Il dataset riporta le misure giornaliere. ";
+        let description = sentence.repeat(60);
+
+        let chunks = build_chunks("Aria", Some(&description), &json!({}));
+        assert_eq!(chunks.len(), 3);
+        for chunk in &chunks {
+            assert_eq!(chunk.kind, ChunkKind::Description);
+            assert!(chunk.text.starts_with("Aria: Il dataset"));
+            assert!(chunk.text.ends_with("giornaliere."));
+        }
+    }
+
+    #[test]
+    fn test_split_text_without_boundaries() {
+        let parts = split_text(&"é".repeat(25), 10);
+        assert_eq!(parts.len(), 3);
+        assert_eq!(parts[2].chars().count(), 5);
+    }
+
+    #[test]
+    fn test_chunks_hash() {
+        let chunk = |text: &str| DatasetChunk {
+            kind: ChunkKind::Resource,
+            text: text.to_string(),
+        };
+        assert_eq!(chunks_hash(&[]), None);
+        assert_eq!(chunks_hash(&[chunk("a")]), chunks_hash(&[chunk("a")]));
+        assert_ne!(chunks_hash(&[chunk("a")]), chunks_hash(&[chunk("b")]));
+    }
+
+    #[test]
+    fn test_aggregate_scores_keeps_best_hit() {
+        let (a, b) = (Uuid::new_v4(), Uuid::new_v4());
+        let scores = aggregate_scores([(a, 0.6), (b, 0.7), (a, 0.9), (b, 0.5)]);
+        assert_eq!(scores, vec![(a, 0.9), (b, 0.7)]);
+    }
+}
@@ -0,0 +1,368 @@
+//! Topic discovery by clustering dataset embeddings.
+//!
+//! `ceres cluster` groups datasets whose embeddings point in similar
+//! directions with spherical k-means (k-means on unit vectors, comparing by
+//! cosine similarity) and labels each group with the title words that set it
+//! apart from the rest of the corpus. The result is a topic overview of what
+//! a portal actually contains.
+
+use std::collections::{HashMap, HashSet};
+
+use serde::Serialize;
+
+/// Clusters computed when `--k` is not given.
+pub const DEFAULT_CLUSTERS: usize = 10;
+
+/// Keywords labelling each cluster.
+pub const CLUSTER_KEYWORDS: usize = 5;
+
+/// Iterations after which k-means stops even if assignments still change.
+pub const MAX_ITERATIONS: usize = 50;
+
+/// Shortest word considered as a keyword.
+const MIN_KEYWORD_CHARS: usize = 3;
+
+/// Common Italian and English words that say nothing about a topic.
+pub(crate) const STOPWORDS: &[&str] = &[
+    "and", "the", "for", "with", "from", "data", "dataset", "datasets", "per", "del", "della",
+    "delle", "dei", "degli", "dal", "dalla", "nel", "nella", "nei", "sul", "sulla", "con", "tra",
+    "anno", "anni", "dati", "elenco", "comune", "are", "all", "its", "into", "year",
+];
+
+/// Result of k-means over a set of vectors.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Clustering {
+    /// Cluster of each input vector, in input order
+    pub assignments: Vec<usize>,
+    /// Unit-length center of each cluster
+    pub centroids: Vec<Vec<f32>>,
+    /// Iterations run until assignments stopped changing
+    pub iterations: usize,
+}
+
+impl Clustering {
+    /// Number of vectors in each cluster.
+    pub fn sizes(&self) -> Vec<usize> {
+        let mut sizes = vec![0; self.centroids.len()];
+        for &cluster in &self.assignments {
+            sizes[cluster] += 1;
+        }
+        sizes
+    }
+}
+
+/// A cluster as shown in the topic overview.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Topic {
+    /// Cluster number, largest cluster first
+    pub cluster: usize,
+    /// Words that set the cluster's titles apart, most distinctive first
+    pub keywords: Vec<String>,
+    /// Datasets in the cluster
+    pub datasets: usize,
+    /// Titles of the datasets closest to the cluster center
+    pub examples: Vec<String>,
+}
+
+/// Groups `vectors` into at most `k` clusters with spherical k-means.
+///
+/// Vectors are normalized, so only their direction matters. Initial
+/// centers are picked with k-means++ from a generator seeded by `seed`,
+/// making results reproducible. Empty clusters are dropped and the rest
+/// renumbered by decreasing size.
+pub fn kmeans(vectors: &[Vec<f32>], k: usize, seed: u64) -> Clustering {
+    let points: Vec<Vec<f32>> = vectors.iter().map(|v| normalized(v)).collect();
+    let k = k.min(points.len());
+    if k == 0 {
+        return Clustering {
+            assignments: Vec::new(),
+            centroids: Vec::new(),
+            iterations: 0,
+        };
+    }
+
+    let mut centroids = initial_centroids(&points, k, seed);
+    let mut assignments = vec![usize::MAX; points.len()];
+    let mut iterations = 0;
+    while iterations < MAX_ITERATIONS {
+        iterations += 1;
+        let mut changed = false;
+        for (point, assignment) in points.iter().zip(assignments.iter_mut()) {
+            let nearest = nearest_centroid(point, &centroids);
+            if nearest != *assignment {
+                *assignment = nearest;
+                changed = true;
+            }
+        }
+        if !changed {
+            break;
+        }
+
+        let dimension = points[0].len();
+        let mut sums = vec![vec![0.0f32; dimension]; k];
+        for (point, &cluster) in points.iter().zip(&assignments) {
+            for (sum, x) in sums[cluster].iter_mut().zip(point) {
+                *sum += x;
+            }
+        }
+        for (centroid, sum) in centroids.iter_mut().zip(sums) {
+            // A cluster that lost all its points keeps its previous center
+            if sum.iter().any(|x| *x != 0.0) {
+                *centroid = normalized(&sum);
+            }
+        }
+    }
+
+    renumber_by_size(Clustering {
+        assignments,
+        centroids,
+        iterations,
+    })
+}
+
+/// Labels each cluster with the `count` words most typical of its texts.
+///
+/// A word scores by the share of the cluster's texts containing it, times
+/// its inverse document frequency over all texts, so words common across
+/// the corpus rank low.
+pub fn cluster_keywords(
+    texts: &[&str],
+    assignments: &[usize],
+    clusters: usize,
+    count: usize,
+) -> Vec<Vec<String>> {
+    let words: Vec<HashSet<String>> = texts.iter().map(|text| keyword_candidates(text)).collect();
+
+    let mut document_frequency: HashMap<&str, usize> = HashMap::new();
+    for text_words in &words {
+        for word in text_words {
+            *document_frequency.entry(word).or_default() += 1;
+        }
+    }
+
+    let mut cluster_frequency: Vec<HashMap<&str, usize>> = vec![HashMap::new(); clusters];
+    let mut sizes = vec![0usize; clusters];
+    for (text_words, &cluster) in words.iter().zip(assignments) {
+        sizes[cluster] += 1;
+        for word in text_words {
+            *cluster_frequency[cluster].entry(word).or_default() += 1;
+        }
+    }
+
+    let total = texts.len() as f32;
+    cluster_frequency
+        .into_iter()
+        .zip(sizes)
+        .map(|(frequency, size)| {
+            let mut scored: Vec<(&str, f32)> = frequency
+                .into_iter()
+                // A word seen once says little about the cluster
+                .filter(|(_, n)| *n > 1 || size == 1)
+                .map(|(word, n)| {
+                    let idf = (total / document_frequency[word] as f32).ln();
+                    (word, n as f32 / size as f32 * idf)
+                })
+                .collect();
+            scored.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(b.0)));
+            scored
+                .into_iter()
+                .take(count)
+                .map(|(word, _)| word.to_string())
+                .collect()
+        })
+        .collect()
+}
+
+/// Indices of the `count` vectors closest to each cluster's center.
+pub fn representatives(
+    vectors: &[Vec<f32>],
+    clustering: &Clustering,
+    count: usize,
+) -> Vec<Vec<usize>> {
+    let mut members: Vec<Vec<(usize, f32)>> = vec![Vec::new(); clustering.centroids.len()];
+    for (index, (vector, &cluster)) in vectors.iter().zip(&clustering.assignments).enumerate() {
+        let similarity = dot(&normalized(vector), &clustering.centroids[cluster]);
+        members[cluster].push((index, similarity));
+    }
+    members
+        .into_iter()
+        .map(|mut scored| {
+            scored.sort_by(|a, b| b.1.total_cmp(&a.1));
+            scored.into_iter().take(count).map(|(i, _)| i).collect()
+        })
+        .collect()
+}
+
+/// Lowercase words of `text` that may serve as keywords.
+fn keyword_candidates(text: &str) -> HashSet<String> {
+    text.to_lowercase()
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|word| word.chars().count() >= MIN_KEYWORD_CHARS)
+        .filter(|word| !word.chars().all(|c| c.is_ascii_digit()))
+        .filter(|word| !STOPWORDS.contains(word))
+        .map(str::to_string)
+        .collect()
+}
+
+/// Picks `k` starting centers: the first at random, each next one with
+/// probability proportional to its distance from the chosen ones.
+fn initial_centroids(points: &[Vec<f32>], k: usize, seed: u64) -> Vec<Vec<f32>> {
+    let mut rng = XorShift::new(seed);
+    let mut centroids = vec![points[rng.below(points.len())].clone()];
+    let mut distances: Vec<f32> = points
+        .iter()
+        .map(|p| cosine_distance(p, &centroids[0]))
+        .collect();
+
+    while centroids.len() < k {
+        let total: f32 = distances.iter().sum();
+        let next = if total > 0.0 {
+            let mut target = rng.unit() * total;
+            distances
+                .iter()
+                .position(|d| {
+                    target -= d;
+                    target <= 0.0
+                })
+                .unwrap_or(points.len() - 1)
+        } else {
+            // All points coincide with a chosen center
+            rng.below(points.len())
+        };
+        centroids.push(points[next].clone());
+        for (distance, point) in distances.iter_mut().zip(points) {
+            *distance = distance.min(cosine_distance(point, &centroids[centroids.len() - 1]));
+        }
+    }
+    centroids
+}
+
+/// Drops empty clusters and renumbers the others, largest first.
+fn renumber_by_size(clustering: Clustering) -> Clustering {
+    let sizes = clustering.sizes();
+    let mut order: Vec<usize> = (0..sizes.len()).filter(|&c| sizes[c] > 0).collect();
+    order.sort_by(|&a, &b| sizes[b].cmp(&sizes[a]).then(a.cmp(&b)));
+
+    let mut new_index = vec![0; sizes.len()];
+    for (new, &old) in order.iter().enumerate() {
+        new_index[old] = new;
+    }
+    Clustering {
+        assignments: clustering
+            .assignments
+            .iter()
+            .map(|&c| new_index[c])
+            .collect(),
+        centroids: order
+            .iter()
+            .map(|&c| clustering.centroids[c].clone())
+            .collect(),
+        iterations: clustering.iterations,
+    }
+}
+
+fn nearest_centroid(point: &[f32], centroids: &[Vec<f32>]) -> usize {
+    centroids
+        .iter()
+        .map(|c| dot(point, c))
+        .enumerate()
+        .max_by(|a, b| a.1.total_cmp(&b.1).then(b.0.cmp(&a.0)))
+        .map_or(0, |(i, _)| i)
+}
+
+fn cosine_distance(a: &[f32], b: &[f32]) -> f32 {
+    (1.0 - dot(a, b)).max(0.0)
+}
+
+fn dot(a: &[f32], b: &[f32]) -> f32 {
+    a.iter().zip(b).map(|(x, y)| x * y).sum()
+}
+
+fn normalized(v: &[f32]) -> Vec<f32> {
+    let norm = dot(v, v).sqrt();
+    if norm == 0.0 {
+        v.to_vec()
+    } else {
+        v.iter().map(|x| x / norm).collect()
+    }
+}
+
+/// Small deterministic generator for k-means++ seeding.
+struct XorShift(u64);
+
+impl XorShift {
+    fn new(seed: u64) -> Self {
+        // Zero is a fixed point of xorshift
+        Self(seed.max(1))
+    }
+
+    fn next(&mut self) -> u64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        self.0
+    }
+
+    /// Uniform in [0, 1).
+    fn unit(&mut self) -> f32 {
+        (self.next() >> 40) as f32 / (1u64 << 24) as f32
+    }
+
+    fn below(&mut self, n: usize) -> usize {
+        (self.next() % n as u64) as usize
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_kmeans_separates_directions() {
+        let vectors = vec![
+            vec![1.0, 0.1],
+            vec![2.0, 0.0],
+            vec![0.9, -0.1],
+            vec![0.0, 1.0],
+            vec![0.1, 3.0],
+        ];
+        let clustering = kmeans(&vectors, 2, 42);
+        assert_eq!(clustering.assignments, vec![0, 0, 0, 1, 1]);
+        assert_eq!(clustering.sizes(), vec![3, 2]);
+        assert_eq!(clustering, kmeans(&vectors, 2, 42));
+    }
+
+    #[test]
+    fn test_kmeans_drops_empty_clusters() {
+        let vectors = vec![vec![1.0, 0.0]; 3];
+        let clustering = kmeans(&vectors, 5, 7);
+        assert_eq!(clustering.centroids.len(), 1);
+        assert!(kmeans(&[], 3, 7).assignments.is_empty());
+    }
+
+    #[test]
+    fn test_cluster_keywords() {
+        let texts = [
+            "Orari autobus linea 90",
+            "Fermate autobus e tram",
+            "Bilancio di previsione 2024",
+            "Bilancio consuntivo 2023",
+        ];
+        let keywords = cluster_keywords(&texts, &[0, 0, 1, 1], 2, 3);
+        assert_eq!(keywords, vec![vec!["autobus"], vec!["bilancio"]]);
+    }
+
+    #[test]
+    fn test_representatives_closest_first() {
+        let vectors = vec![vec![1.0, 0.5], vec![1.0, 0.0], vec![0.0, 1.0]];
+        let clustering = Clustering {
+            assignments: vec![0, 0, 1],
+            centroids: vec![vec![1.0, 0.0], vec![0.0, 1.0]],
+            iterations: 1,
+        };
+        assert_eq!(
+            representatives(&vectors, &clustering, 5),
+            vec![vec![1, 0], vec![2]]
+        );
+    }
+}
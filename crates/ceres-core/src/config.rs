@@ -1,43 +1,74 @@
 //! Configuration types for Ceres components.
 //!
-//! # Configuration Improvements
-//!
-//! TODO(config): Make all configuration values environment-configurable
-//! Currently all defaults are hardcoded. Should support:
-//! - `DB_MAX_CONNECTIONS` for database pool size
-//! - `SYNC_CONCURRENCY` for parallel dataset processing
-//! - `HTTP_TIMEOUT` for API request timeout
-//! - `HTTP_MAX_RETRIES` for retry attempts
-//!
-//! Consider using the `config` crate for layered configuration:
-//! defaults -> config file -> environment variables -> CLI args
+//! Tunables are resolved in layers: the `Default` impls here, then the
+//! settings file (see [`load_settings`]), then environment variables, then
+//! CLI flags. The CLI resolves them once into [`Settings`] and hands its
+//! parts to the clients, stores and sync pipeline it constructs.
 
+use chrono::{DateTime, Utc};
 use serde::{Deserialize, Serialize};
 use std::path::{Path, PathBuf};
 use std::time::Duration;
 
+use crate::content_hash::HashField;
 use crate::error::AppError;
+use crate::harvest_filter::HarvestFilter;
+use crate::notify::HarvestEvent;
+use crate::schedule::CronSchedule;
+
+/// Resolved tunables of a Ceres process.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Settings {
+    pub db: DbConfig,
+    pub http: HttpConfig,
+    pub sync: SyncConfig,
+}
 
 /// Database connection pool configuration.
-///
-/// TODO(config): Support environment variable `DB_MAX_CONNECTIONS`
-/// Default of 5 may be insufficient for high-concurrency scenarios.
+#[derive(Debug, Clone, PartialEq)]
 pub struct DbConfig {
     pub max_connections: u32,
+    /// Attempts after the first when the server is unreachable or starting
+    /// up
+    pub connect_retries: u32,
+    /// Wait before the first retry, doubled for each one after
+    pub connect_retry_base_delay: Duration,
 }
 
 impl Default for DbConfig {
     fn default() -> Self {
-        // TODO(config): Read from DB_MAX_CONNECTIONS env var
-        Self { max_connections: 5 }
+        Self {
+            max_connections: 5,
+            connect_retries: 5,
+            connect_retry_base_delay: Duration::from_secs(1),
+        }
     }
 }
 
 /// HTTP client configuration for external API calls.
+#[derive(Debug, Clone, PartialEq)]
 pub struct HttpConfig {
     pub timeout: Duration,
     pub max_retries: u32,
     pub retry_base_delay: Duration,
+    /// Proxy for every request, replacing the `*_PROXY` environment variables
+    pub proxy: Option<String>,
+    /// PEM files of root certificates trusted besides the system's
+    pub ca_certificates: Vec<PathBuf>,
+    /// Accept any certificate from CKAN portals. Other APIs are always
+    /// verified.
+    pub insecure_skip_verify: bool,
+    /// User-Agent replacing Ceres' own
+    pub user_agent: Option<String>,
+    /// Operator contact, sent in the `From` header and added to Ceres' own
+    /// User-Agent so portals can reach whoever runs the harvests
+    pub contact_email: Option<String>,
+    /// Skip fetching portals' robots.txt, ignoring its rules and Crawl-delay
+    pub ignore_robots_txt: bool,
+    /// Directory caching raw `package_show` responses across harvests
+    pub response_cache_dir: Option<PathBuf>,
+    /// Age under which cached responses are used without revalidation
+    pub response_cache_max_age: Option<Duration>,
 }
 
 impl Default for HttpConfig {
@@ -46,26 +77,165 @@ impl Default for HttpConfig {
             timeout: Duration::from_secs(30),
             max_retries: 3,
             retry_base_delay: Duration::from_millis(500),
+            proxy: None,
+            ca_certificates: Vec::new(),
+            insecure_skip_verify: false,
+            user_agent: None,
+            contact_email: None,
+            ignore_robots_txt: false,
+            response_cache_dir: None,
+            response_cache_max_age: None,
         }
     }
 }
 
+impl HttpConfig {
+    /// The User-Agent sent with every request: the configured one, else
+    /// Ceres' own, which names the project URL (as Wikimedia asks of API
+    /// clients) and the contact email if any.
+    pub fn user_agent(&self) -> String {
+        if let Some(user_agent) = &self.user_agent {
+            return user_agent.clone();
+        }
+        let contact = match &self.contact_email {
+            Some(email) => format!("; mailto:{}", email),
+            None => String::new(),
+        };
+        format!(
+            "Ceres/{} (https://github.com/AndreaBozzo/Ceres; semantic-search-bot{})",
+            env!("CARGO_PKG_VERSION"),
+            contact
+        )
+    }
+}
+
 /// Portal synchronization configuration.
 ///
-/// TODO(config): Support CLI arg `--concurrency` and env var `SYNC_CONCURRENCY`
-/// Optimal value depends on portal rate limits and system resources.
-/// Consider auto-tuning based on API response times.
+/// The optimal concurrency depends on portal rate limits and system
+/// resources.
+#[derive(Debug, Clone, PartialEq)]
 pub struct SyncConfig {
     pub concurrency: usize,
+    /// Upper bound of adaptive concurrency; `None` keeps `concurrency`
+    /// requests in flight to each portal throughout a harvest
+    pub max_concurrency: Option<usize>,
+    /// Metadata the content hash covers besides title and description
+    pub hash_fields: Vec<HashField>,
+    /// What a harvest does when another harvest of its portal is running
+    pub on_locked: LockPolicy,
 }
 
 impl Default for SyncConfig {
     fn default() -> Self {
-        // TODO(config): Read from SYNC_CONCURRENCY env var
-        Self { concurrency: 10 }
+        Self {
+            concurrency: 10,
+            max_concurrency: None,
+            hash_fields: Vec::new(),
+            on_locked: LockPolicy::Skip,
+        }
     }
 }
 
+/// What a harvest does when it finds its portal locked by another harvest.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum LockPolicy {
+    /// Leave the portal to the running harvest
+    Skip,
+    /// Wait for the running harvest to finish, then harvest
+    Wait,
+}
+
+// =============================================================================
+// Settings File (ceres.toml)
+// =============================================================================
+
+/// Settings file name, next to portals.toml.
+pub const SETTINGS_FILE_NAME: &str = "ceres.toml";
+
+/// Environment variable overriding the settings file path.
+pub const SETTINGS_PATH_VAR: &str = "CERES_SETTINGS";
+
+/// Returns the settings file path: `CERES_SETTINGS` when set, else
+/// `~/.config/ceres/ceres.toml`.
+pub fn default_settings_path() -> Option<PathBuf> {
+    match std::env::var_os(SETTINGS_PATH_VAR) {
+        Some(path) if !path.is_empty() => Some(PathBuf::from(path)),
+        _ => default_config_dir().map(|p| p.join(SETTINGS_FILE_NAME)),
+    }
+}
+
+/// Load the settings file at `path` as environment variable assignments.
+///
+/// Keys name the environment variable they set, in lowercase; tables
+/// prefix their keys, so `[http] timeout = 60` sets `HTTP_TIMEOUT`:
+///
+/// ```toml
+/// database_url = "postgresql://localhost/ceres"
+///
+/// [db]
+/// max_connections = 20
+///
+/// [sync]
+/// concurrency = 4
+///
+/// [embedding]
+/// provider = "ollama"
+/// model = "nomic-embed-text"
+/// ```
+///
+/// Returns no settings when the file does not exist.
+pub fn load_settings(path: &Path) -> Result<Vec<(String, String)>, AppError> {
+    let content = match std::fs::read_to_string(path) {
+        Ok(content) => content,
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => {
+            return Err(AppError::ConfigError(format!(
+                "Failed to read settings file '{}': {}",
+                path.display(),
+                e
+            )))
+        }
+    };
+    let table: toml::Table = toml::from_str(&content).map_err(|e| {
+        AppError::ConfigError(format!("Invalid settings file '{}': {}", path.display(), e))
+    })?;
+
+    let mut settings = Vec::new();
+    flatten_settings("", &table, &mut settings).map_err(|key| {
+        AppError::ConfigError(format!(
+            "Setting '{}' in '{}' must be a string, number or boolean",
+            key,
+            path.display()
+        ))
+    })?;
+    Ok(settings)
+}
+
+/// Appends the scalar values of `table` to `settings`; `Err` carries the key
+/// of the first value that is not a scalar.
+fn flatten_settings(
+    prefix: &str,
+    table: &toml::Table,
+    settings: &mut Vec<(String, String)>,
+) -> Result<(), String> {
+    for (key, value) in table {
+        let name = format!("{}{}", prefix, key.to_uppercase());
+        let value = match value {
+            toml::Value::String(s) => s.clone(),
+            toml::Value::Integer(n) => n.to_string(),
+            toml::Value::Float(n) => n.to_string(),
+            toml::Value::Boolean(b) => b.to_string(),
+            toml::Value::Table(table) => {
+                flatten_settings(&format!("{}_", name), table, settings)?;
+                continue;
+            }
+            toml::Value::Array(_) | toml::Value::Datetime(_) => return Err(name.to_lowercase()),
+        };
+        settings.push((name, value));
+    }
+    Ok(())
+}
+
 // =============================================================================
 // Portal Configuration (portals.toml)
 // =============================================================================
@@ -80,6 +250,26 @@ fn default_enabled() -> bool {
     true
 }
 
+/// Default webhook events: notify on every finished harvest.
+fn default_webhook_events() -> Vec<HarvestEvent> {
+    vec![HarvestEvent::Completed, HarvestEvent::Failed]
+}
+
+/// Default notification events: only what needs attention.
+fn default_notification_events() -> Vec<HarvestEvent> {
+    vec![HarvestEvent::Failed, HarvestEvent::Quarantined]
+}
+
+/// Default SMTP port: message submission with STARTTLS.
+fn default_smtp_port() -> u16 {
+    587
+}
+
+/// Default environment variable holding the SMTP password.
+fn default_smtp_password_env() -> String {
+    "CERES_SMTP_PASSWORD".to_string()
+}
+
 /// Root configuration structure for portals.toml.
 ///
 /// This structure represents the entire configuration file containing
@@ -98,11 +288,26 @@ fn default_enabled() -> bool {
 /// name = "milano"
 /// url = "https://dati.comune.milano.it"
 /// enabled = true
+///
+/// [[webhooks]]
+/// url = "https://hooks.example.com/ceres"
+///
+/// [[notifications]]
+/// type = "slack"
+/// webhook_url = "https://hooks.slack.com/services/..."
 /// ```
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct PortalsConfig {
     /// Array of portal configurations.
     pub portals: Vec<PortalEntry>,
+
+    /// Webhooks notified when a harvest finishes.
+    #[serde(default)]
+    pub webhooks: Vec<WebhookConfig>,
+
+    /// Slack channels and mailboxes alerted about harvests.
+    #[serde(default)]
+    pub notifications: Vec<NotificationConfig>,
 }
 
 impl PortalsConfig {
@@ -157,6 +362,199 @@ pub struct PortalEntry {
 
     /// Optional description of the portal.
     pub description: Option<String>,
+
+    /// Optional cron expression used by `ceres daemon` (evaluated in UTC).
+    ///
+    /// Example: "0 3 * * *" harvests every night at 03:00.
+    #[serde(default)]
+    pub schedule: Option<String>,
+
+    /// Optional embedding model override for this portal.
+    ///
+    /// Example: "gemini-embedding-001". Datasets are tagged with the model
+    /// that embedded them and re-embedded when it changes. Defaults to the
+    /// global model.
+    #[serde(default)]
+    pub embedding_model: Option<String>,
+
+    /// Whether to also embed each resource and long description part
+    /// (multi-vector mode, see [`crate::chunks`]).
+    ///
+    /// Improves recall for queries matching a single file at the cost of
+    /// one embedding call per resource. Defaults to `false`.
+    #[serde(default)]
+    pub chunk_embeddings: bool,
+
+    /// Harvest only datasets with at least one of these tags, spelled as on
+    /// the portal (see [`crate::harvest_filter`]).
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub include_tags: Vec<String>,
+
+    /// Skip datasets published by these organizations, by CKAN name (the
+    /// slug in the portal's URLs).
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub exclude_orgs: Vec<String>,
+
+    /// Harvest only datasets whose title matches this regular expression.
+    #[serde(default)]
+    pub include_title: Option<String>,
+
+    /// Skip datasets whose title matches this regular expression.
+    #[serde(default)]
+    pub exclude_title: Option<String>,
+}
+
+impl PortalEntry {
+    /// The parsed `schedule`, if the portal has one.
+    ///
+    /// # Errors
+    ///
+    /// Returns `AppError::ConfigError` if the schedule is not a valid cron
+    /// expression.
+    pub fn cron_schedule(&self) -> Result<Option<CronSchedule>, AppError> {
+        self.schedule
+            .as_deref()
+            .map(|expression| {
+                CronSchedule::parse(expression).map_err(|_| {
+                    AppError::ConfigError(format!(
+                        "Invalid schedule '{}' of portal '{}'",
+                        expression, self.name
+                    ))
+                })
+            })
+            .transpose()
+    }
+
+    /// The first harvest `ceres daemon` schedules for this portal after
+    /// `after`, or `None` if the portal is disabled, has no schedule of its
+    /// own or its schedule never fires again.
+    ///
+    /// # Errors
+    ///
+    /// Returns `AppError::ConfigError` if the schedule is not a valid cron
+    /// expression.
+    pub fn next_scheduled_run(
+        &self,
+        after: DateTime<Utc>,
+    ) -> Result<Option<DateTime<Utc>>, AppError> {
+        if !self.enabled {
+            return Ok(None);
+        }
+        Ok(self
+            .cron_schedule()?
+            .and_then(|schedule| schedule.next_after(after)))
+    }
+}
+
+/// A webhook notified after harvest runs.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WebhookConfig {
+    /// URL receiving the JSON payload via POST.
+    pub url: String,
+
+    /// Events that trigger this webhook.
+    ///
+    /// Defaults to both `harvest.completed` and `harvest.failed`.
+    #[serde(default = "default_webhook_events")]
+    pub events: Vec<HarvestEvent>,
+}
+
+impl WebhookConfig {
+    /// Returns true if this webhook subscribes to `event`.
+    pub fn wants(&self, event: HarvestEvent) -> bool {
+        self.events.contains(&event)
+    }
+}
+
+/// A Slack channel or mailbox alerted about harvests, so unattended runs of
+/// `ceres daemon` do not fail unnoticed.
+///
+/// Unlike webhooks, which receive the full JSON payload, sinks get the
+/// readable message of [`HarvestNotification::message`].
+///
+/// [`HarvestNotification::message`]: crate::notify::HarvestNotification::message
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NotificationConfig {
+    /// Where notifications go, by `type`.
+    #[serde(flatten)]
+    pub sink: NotificationSink,
+
+    /// Events that trigger a notification.
+    ///
+    /// Defaults to `harvest.failed` and `portal.quarantined`.
+    #[serde(default = "default_notification_events")]
+    pub events: Vec<HarvestEvent>,
+
+    /// Also notify when more than this share of the harvested datasets
+    /// failed (between 0 and 1), even if every portal was harvested.
+    #[serde(default)]
+    pub failure_rate_above: Option<f64>,
+}
+
+/// Where a notification is delivered.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(tag = "type", rename_all = "lowercase")]
+pub enum NotificationSink {
+    /// A Slack incoming webhook.
+    Slack {
+        /// Incoming webhook URL, `https://hooks.slack.com/services/...`
+        webhook_url: String,
+    },
+    /// An email sent over SMTP (requires the `email` feature).
+    Email(EmailConfig),
+}
+
+impl NotificationSink {
+    /// Short description for logs, without credentials.
+    pub fn describe(&self) -> String {
+        match self {
+            Self::Slack { .. } => "Slack webhook".to_string(),
+            Self::Email(email) => format!("email to {}", email.to.join(", ")),
+        }
+    }
+}
+
+/// SMTP settings of an email notification.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct EmailConfig {
+    /// SMTP server host name.
+    pub smtp_host: String,
+
+    /// SMTP server port. Defaults to 587.
+    #[serde(default = "default_smtp_port")]
+    pub smtp_port: u16,
+
+    /// How the connection is encrypted. Defaults to `starttls`.
+    #[serde(default)]
+    pub smtp_security: SmtpSecurity,
+
+    /// User name to log in with, if the server requires it.
+    #[serde(default)]
+    pub smtp_username: Option<String>,
+
+    /// Environment variable holding the password, so it stays out of the
+    /// file. Defaults to `CERES_SMTP_PASSWORD`.
+    #[serde(default = "default_smtp_password_env")]
+    pub smtp_password_env: String,
+
+    /// Sender address, e.g. "Ceres <ceres@example.org>".
+    pub from: String,
+
+    /// Recipient addresses.
+    pub to: Vec<String>,
+}
+
+/// Encryption of an SMTP connection.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum SmtpSecurity {
+    /// Upgrade a plain connection with STARTTLS (port 587)
+    #[default]
+    Starttls,
+    /// TLS from the start (port 465)
+    Tls,
+    /// No encryption, for relays on the local network only
+    None,
 }
 
 /// Default configuration file name.
@@ -188,6 +586,23 @@ const DEFAULT_CONFIG_TEMPLATE: &str = r#"# Ceres Portal Configuration
 #   ceres harvest https://...     # Harvest single URL (ignores this file)
 #
 # Set enabled = false to skip a portal during batch harvest.
+# Add schedule = "0 3 * * *" (cron, UTC) to harvest a portal with `ceres daemon`.
+# Harvest part of a portal with include_tags = ["ambiente"],
+# exclude_orgs = ["org-name"], include_title or exclude_title (regex).
+#
+# To get notified when a harvest finishes, add one or more webhooks:
+#
+# [[webhooks]]
+# url = "https://hooks.slack.com/services/..."
+# events = ["harvest.completed", "harvest.failed"]
+#
+# or a Slack message or email when harvests fail, a portal is quarantined or
+# too many datasets fail:
+#
+# [[notifications]]
+# type = "slack"
+# webhook_url = "https://hooks.slack.com/services/..."
+# failure_rate_above = 0.1
 
 # City of Milan open data
 [[portals]]
@@ -270,10 +685,392 @@ pub fn load_portals_config(path: Option<PathBuf>) -> Result<Option<PortalsConfig
             e
         ))
     })?;
+    for portal in &config.portals {
+        HarvestFilter::for_portal(portal)?;
+        portal.cron_schedule()?;
+    }
+    for notification in &config.notifications {
+        if let Some(rate) = notification.failure_rate_above {
+            if !(0.0..=1.0).contains(&rate) {
+                return Err(AppError::ConfigError(format!(
+                    "failure_rate_above must be between 0 and 1, got {}",
+                    rate
+                )));
+            }
+        }
+        if let NotificationSink::Email(email) = &notification.sink {
+            if email.to.is_empty() {
+                return Err(AppError::ConfigError(
+                    "Email notifications need at least one address in `to`".to_string(),
+                ));
+            }
+        }
+    }
 
     Ok(Some(config))
 }
 
+/// Rewrites the `url` of every portal entry pointing at `from` to `to`.
+///
+/// Trailing slashes are ignored when comparing URLs. Comments and formatting
+/// of the file are preserved. Returns the number of entries changed.
+///
+/// # Errors
+///
+/// Returns `AppError::ConfigError` if the file cannot be read, parsed or written.
+pub fn rewrite_portal_url(path: &Path, from: &str, to: &str) -> Result<usize, AppError> {
+    let content = std::fs::read_to_string(path).map_err(|e| {
+        AppError::ConfigError(format!(
+            "Failed to read config file '{}': {}",
+            path.display(),
+            e
+        ))
+    })?;
+
+    let (updated, changed) = rewrite_portal_url_in(&content, from, to)
+        .map_err(|e| AppError::ConfigError(format!("{} in '{}'", e, path.display())))?;
+
+    if changed > 0 {
+        write_config(path, &updated)?;
+    }
+    Ok(changed)
+}
+
+fn rewrite_portal_url_in(content: &str, from: &str, to: &str) -> Result<(String, usize), String> {
+    let mut doc: toml_edit::DocumentMut = content
+        .parse()
+        .map_err(|e| format!("Invalid TOML: {}", e))?;
+
+    let mut changed = 0;
+    if let Some(portals) = doc
+        .get_mut("portals")
+        .and_then(|p| p.as_array_of_tables_mut())
+    {
+        for portal in portals.iter_mut() {
+            let matches = portal
+                .get("url")
+                .and_then(|u| u.as_str())
+                .is_some_and(|url| url.trim_end_matches('/') == from.trim_end_matches('/'));
+            if matches {
+                portal["url"] = toml_edit::value(to);
+                changed += 1;
+            }
+        }
+    }
+
+    Ok((doc.to_string(), changed))
+}
+
+/// Result of merging portal entries into a configuration file.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct PortalMerge {
+    /// Names of the portals appended to the file
+    pub added: Vec<String>,
+    /// Names of the portals left out because their name or URL is already configured
+    pub skipped: Vec<String>,
+}
+
+/// Appends `portals` to the configuration file at `path`.
+///
+/// Portals whose name (case-insensitive) or URL (ignoring trailing slashes)
+/// is already configured are skipped, so existing entries and any local
+/// edits to them are never overwritten. The appended block is preceded by a
+/// `# From <source>` comment. The file is created if it does not exist;
+/// comments and formatting of an existing file are preserved.
+///
+/// # Errors
+///
+/// Returns `AppError::ConfigError` if the file cannot be read, parsed or written.
+pub fn merge_portals(
+    path: &Path,
+    portals: &[PortalEntry],
+    source: &str,
+) -> Result<PortalMerge, AppError> {
+    let content = read_config_or_empty(path)?;
+    let (updated, merge) = merge_portals_in(&content, portals, Some(source))
+        .map_err(|e| AppError::ConfigError(format!("{} in '{}'", e, path.display())))?;
+
+    if !merge.added.is_empty() {
+        write_config(path, &updated)?;
+    }
+    Ok(merge)
+}
+
+/// Adds a portal entry to the configuration file at `path`.
+///
+/// The file is created if it does not exist; comments and formatting of an
+/// existing file are preserved.
+///
+/// # Errors
+///
+/// Returns `AppError::ConfigError` if the URL is not an http(s) URL, if a
+/// portal with the same name (case-insensitive) or URL is already
+/// configured, or if the file cannot be read, parsed or written.
+pub fn add_portal(path: &Path, portal: &PortalEntry) -> Result<(), AppError> {
+    if !(portal.url.starts_with("http://") || portal.url.starts_with("https://")) {
+        return Err(AppError::ConfigError(format!(
+            "Portal URL must start with http:// or https://, got '{}'",
+            portal.url
+        )));
+    }
+
+    let content = read_config_or_empty(path)?;
+    let (updated, merge) = merge_portals_in(&content, std::slice::from_ref(portal), None)
+        .map_err(|e| AppError::ConfigError(format!("{} in '{}'", e, path.display())))?;
+    if merge.added.is_empty() {
+        return Err(AppError::ConfigError(format!(
+            "A portal named '{}' or with URL {} is already configured",
+            portal.name, portal.url
+        )));
+    }
+    write_config(path, &updated)
+}
+
+/// Removes the portal entry matching `name` (or its URL) from the
+/// configuration file at `path`. Returns the name of the removed portal.
+///
+/// Comments directly above the entry go with it; comments separated from it
+/// by a blank line (such as a file header) and the rest of the file are
+/// preserved.
+///
+/// # Errors
+///
+/// Returns `AppError::ConfigError` if no portal matches, or if the file
+/// cannot be read, parsed or written.
+pub fn remove_portal(path: &Path, name: &str) -> Result<String, AppError> {
+    edit_portal(path, name, |portals, index| {
+        let removed = portals
+            .get(index)
+            .and_then(|p| p.get("name"))
+            .and_then(|n| n.as_str())
+            .unwrap_or(name)
+            .to_string();
+        // The decor prefix holds everything between the previous entry and
+        // this one; keep the part up to the last blank line
+        let prefix = portals
+            .get(index)
+            .and_then(|p| p.decor().prefix())
+            .and_then(|p| p.as_str())
+            .unwrap_or_default()
+            .to_string();
+        let kept = prefix.rfind("\n\n").map_or("", |end| &prefix[..end + 1]);
+        if !kept.trim().is_empty() {
+            if let Some(next) = portals.get_mut(index + 1) {
+                let next_prefix = next
+                    .decor()
+                    .prefix()
+                    .and_then(|p| p.as_str())
+                    .unwrap_or_default()
+                    .to_string();
+                next.decor_mut()
+                    .set_prefix(format!("{}{}", kept, next_prefix));
+            }
+        }
+        portals.remove(index);
+        Some(removed)
+    })
+    .map(|removed| removed.unwrap_or_else(|| name.to_string()))
+}
+
+/// Sets the `enabled` flag of the portal entry matching `name` (or its URL)
+/// in the configuration file at `path`. Returns false if the portal
+/// already had that state, in which case the file is left untouched.
+///
+/// # Errors
+///
+/// Returns `AppError::ConfigError` if no portal matches, or if the file
+/// cannot be read, parsed or written.
+pub fn set_portal_enabled(path: &Path, name: &str, enabled: bool) -> Result<bool, AppError> {
+    edit_portal(path, name, |portals, index| {
+        let portal = portals.get_mut(index)?;
+        let current = portal
+            .get("enabled")
+            .and_then(|e| e.as_bool())
+            .unwrap_or(true);
+        if current == enabled {
+            return None;
+        }
+        portal["enabled"] = toml_edit::value(enabled);
+        Some(())
+    })
+    .map(|changed| changed.is_some())
+}
+
+/// Applies `edit` to the portal entry matching `name` and writes the file
+/// back if `edit` returns `Some`.
+fn edit_portal<T>(
+    path: &Path,
+    name: &str,
+    edit: impl FnOnce(&mut toml_edit::ArrayOfTables, usize) -> Option<T>,
+) -> Result<Option<T>, AppError> {
+    let content = std::fs::read_to_string(path).map_err(|e| {
+        AppError::ConfigError(format!(
+            "Failed to read config file '{}': {}",
+            path.display(),
+            e
+        ))
+    })?;
+    let mut doc: toml_edit::DocumentMut = content.parse().map_err(|e| {
+        AppError::ConfigError(format!("Invalid TOML in '{}': {}", path.display(), e))
+    })?;
+
+    let not_found =
+        || AppError::ConfigError(format!("Portal '{}' not found in configuration", name));
+    let portals = doc
+        .get_mut("portals")
+        .and_then(|p| p.as_array_of_tables_mut())
+        .ok_or_else(not_found)?;
+    let index = find_portal_index(portals, name).ok_or_else(not_found)?;
+
+    let result = edit(portals, index);
+    if result.is_some() {
+        write_config(path, &doc.to_string())?;
+    }
+    Ok(result)
+}
+
+/// Index of the entry whose name matches `name` (case-insensitive) or whose
+/// URL matches it (ignoring trailing slashes).
+fn find_portal_index(portals: &toml_edit::ArrayOfTables, name: &str) -> Option<usize> {
+    let field = |portal: &toml_edit::Table, key: &str| {
+        portal.get(key).and_then(|v| v.as_str()).map(str::to_string)
+    };
+    portals.iter().position(|portal| {
+        field(portal, "name").is_some_and(|n| n.eq_ignore_ascii_case(name))
+            || field(portal, "url")
+                .is_some_and(|u| u.trim_end_matches('/') == name.trim_end_matches('/'))
+    })
+}
+
+/// Reads the configuration file at `path`, or an empty string if it does
+/// not exist.
+fn read_config_or_empty(path: &Path) -> Result<String, AppError> {
+    match std::fs::read_to_string(path) {
+        Ok(content) => Ok(content),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(String::new()),
+        Err(e) => Err(AppError::ConfigError(format!(
+            "Failed to read config file '{}': {}",
+            path.display(),
+            e
+        ))),
+    }
+}
+
+/// Writes the configuration file atomically: the content goes to a
+/// temporary file next to `path`, which then replaces it, so an interrupted
+/// write never leaves a truncated file. Creates the parent directory.
+fn write_config(path: &Path, content: &str) -> Result<(), AppError> {
+    let write = || -> std::io::Result<()> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+        let file_name = path
+            .file_name()
+            .map(|n| n.to_string_lossy())
+            .unwrap_or_default();
+        let tmp = path.with_file_name(format!(".{}.tmp", file_name));
+        std::fs::write(&tmp, content)?;
+        std::fs::rename(&tmp, path).inspect_err(|_| {
+            let _ = std::fs::remove_file(&tmp);
+        })
+    };
+    write().map_err(|e| {
+        AppError::ConfigError(format!(
+            "Failed to write config file '{}': {}",
+            path.display(),
+            e
+        ))
+    })
+}
+
+fn merge_portals_in(
+    content: &str,
+    portals: &[PortalEntry],
+    source: Option<&str>,
+) -> Result<(String, PortalMerge), String> {
+    let mut doc: toml_edit::DocumentMut = content
+        .parse()
+        .map_err(|e| format!("Invalid TOML: {}", e))?;
+
+    let mut names = Vec::new();
+    let mut urls = Vec::new();
+    if let Some(existing) = doc.get("portals").and_then(|p| p.as_array_of_tables()) {
+        for portal in existing.iter() {
+            if let Some(name) = portal.get("name").and_then(|n| n.as_str()) {
+                names.push(name.to_lowercase());
+            }
+            if let Some(url) = portal.get("url").and_then(|u| u.as_str()) {
+                urls.push(url.trim_end_matches('/').to_string());
+            }
+        }
+    }
+
+    if !doc.contains_key("portals") {
+        doc.insert(
+            "portals",
+            toml_edit::Item::ArrayOfTables(toml_edit::ArrayOfTables::new()),
+        );
+    }
+    let tables = doc["portals"]
+        .as_array_of_tables_mut()
+        .ok_or_else(|| "`portals` is not an array of tables".to_string())?;
+
+    let mut merge = PortalMerge::default();
+    for portal in portals {
+        let url = portal.url.trim_end_matches('/');
+        if names.contains(&portal.name.to_lowercase()) || urls.iter().any(|u| u == url) {
+            merge.skipped.push(portal.name.clone());
+            continue;
+        }
+        names.push(portal.name.to_lowercase());
+        urls.push(url.to_string());
+
+        let mut table = toml_edit::Table::new();
+        match source {
+            Some(source) if merge.added.is_empty() => {
+                table
+                    .decor_mut()
+                    .set_prefix(format!("\n# From {}\n", source));
+            }
+            Some(_) => {}
+            None => table.decor_mut().set_prefix("\n"),
+        }
+        table["name"] = toml_edit::value(&portal.name);
+        table["url"] = toml_edit::value(&portal.url);
+        table["type"] = toml_edit::value(&portal.portal_type);
+        if !portal.enabled {
+            table["enabled"] = toml_edit::value(false);
+        }
+        if let Some(description) = &portal.description {
+            table["description"] = toml_edit::value(description);
+        }
+        if let Some(schedule) = &portal.schedule {
+            table["schedule"] = toml_edit::value(schedule);
+        }
+        if let Some(model) = &portal.embedding_model {
+            table["embedding_model"] = toml_edit::value(model);
+        }
+        let array =
+            |values: &[String]| toml_edit::value(values.iter().collect::<toml_edit::Array>());
+        if !portal.include_tags.is_empty() {
+            table["include_tags"] = array(&portal.include_tags);
+        }
+        if !portal.exclude_orgs.is_empty() {
+            table["exclude_orgs"] = array(&portal.exclude_orgs);
+        }
+        if let Some(pattern) = &portal.include_title {
+            table["include_title"] = toml_edit::value(pattern);
+        }
+        if let Some(pattern) = &portal.exclude_title {
+            table["exclude_title"] = toml_edit::value(pattern);
+        }
+        tables.push(table);
+        merge.added.push(portal.name.clone());
+    }
+
+    Ok((doc.to_string(), merge))
+}
+
 /// Create a default configuration file with a template.
 ///
 /// Creates the parent directory if it doesn't exist.
@@ -300,6 +1097,8 @@ mod tests {
     fn test_db_config_defaults() {
         let config = DbConfig::default();
         assert_eq!(config.max_connections, 5);
+        assert_eq!(config.connect_retries, 5);
+        assert_eq!(config.connect_retry_base_delay, Duration::from_secs(1));
     }
 
     #[test]
@@ -310,6 +1109,21 @@ mod tests {
         assert_eq!(config.retry_base_delay, Duration::from_millis(500));
     }
 
+    #[test]
+    fn test_http_config_user_agent() {
+        let mut config = HttpConfig::default();
+        assert!(config.user_agent().starts_with("Ceres/"));
+        assert!(config.user_agent().ends_with("semantic-search-bot)"));
+
+        config.contact_email = Some("opendata@example.org".to_string());
+        assert!(config
+            .user_agent()
+            .ends_with("semantic-search-bot; mailto:opendata@example.org)"));
+
+        config.user_agent = Some("RegioneBot/2.0".to_string());
+        assert_eq!(config.user_agent(), "RegioneBot/2.0");
+    }
+
     #[test]
     fn test_sync_config_defaults() {
         let config = SyncConfig::default();
@@ -385,6 +1199,176 @@ url = "https://dati.comune.milano.it"
         assert!(config.find_by_name("roma").is_none());
     }
 
+    #[test]
+    fn test_portals_config_with_schedule() {
+        let toml = r#"
+[[portals]]
+name = "nightly"
+url = "https://a.com"
+schedule = "0 3 * * *"
+
+[[portals]]
+name = "unscheduled"
+url = "https://b.com"
+"#;
+        let config: PortalsConfig = toml::from_str(toml).unwrap();
+        assert_eq!(config.portals[0].schedule.as_deref(), Some("0 3 * * *"));
+        assert!(config.portals[1].schedule.is_none());
+
+        let after = "2024-05-01T12:00:00Z".parse::<DateTime<Utc>>().unwrap();
+        assert_eq!(
+            config.portals[0].next_scheduled_run(after).unwrap(),
+            Some("2024-05-02T03:00:00Z".parse().unwrap())
+        );
+        assert_eq!(config.portals[1].next_scheduled_run(after).unwrap(), None);
+
+        let mut disabled = config.portals[0].clone();
+        disabled.enabled = false;
+        assert_eq!(disabled.next_scheduled_run(after).unwrap(), None);
+    }
+
+    #[test]
+    fn test_load_rejects_invalid_schedule() {
+        let mut file = NamedTempFile::new().unwrap();
+        writeln!(
+            file,
+            r#"
+[[portals]]
+name = "nightly"
+url = "https://a.com"
+schedule = "every night"
+"#
+        )
+        .unwrap();
+
+        let err = load_portals_config(Some(file.path().to_path_buf())).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("'every night' of portal 'nightly'"));
+    }
+
+    #[test]
+    fn test_portals_config_with_embedding_model() {
+        let toml = r#"
+[[portals]]
+name = "geo"
+url = "https://geo.example.com"
+embedding_model = "gemini-embedding-001"
+
+[[portals]]
+name = "default"
+url = "https://b.com"
+"#;
+        let config: PortalsConfig = toml::from_str(toml).unwrap();
+        assert_eq!(
+            config.portals[0].embedding_model.as_deref(),
+            Some("gemini-embedding-001")
+        );
+        assert!(config.portals[1].embedding_model.is_none());
+    }
+
+    #[test]
+    fn test_portals_config_with_webhooks() {
+        let toml = r#"
+[[portals]]
+name = "a"
+url = "https://a.com"
+
+[[webhooks]]
+url = "https://hooks.example.com/all"
+
+[[webhooks]]
+url = "https://hooks.example.com/failures"
+events = ["harvest.failed"]
+"#;
+        let config: PortalsConfig = toml::from_str(toml).unwrap();
+        assert_eq!(config.webhooks.len(), 2);
+        assert!(config.webhooks[0].wants(HarvestEvent::Completed));
+        assert!(config.webhooks[0].wants(HarvestEvent::Failed));
+        assert!(!config.webhooks[1].wants(HarvestEvent::Completed));
+        assert!(config.webhooks[1].wants(HarvestEvent::Failed));
+    }
+
+    #[test]
+    fn test_portals_config_with_notifications() {
+        let toml = r#"
+[[portals]]
+name = "a"
+url = "https://a.com"
+
+[[notifications]]
+type = "slack"
+webhook_url = "https://hooks.slack.com/services/T/B/x"
+failure_rate_above = 0.05
+
+[[notifications]]
+type = "email"
+smtp_host = "smtp.example.org"
+smtp_username = "ceres"
+from = "Ceres <ceres@example.org>"
+to = ["ops@example.org"]
+events = ["harvest.completed"]
+"#;
+        let config: PortalsConfig = toml::from_str(toml).unwrap();
+        assert_eq!(config.notifications.len(), 2);
+        let slack = &config.notifications[0];
+        assert!(matches!(
+            &slack.sink,
+            NotificationSink::Slack { webhook_url } if webhook_url.ends_with("/x")
+        ));
+        assert_eq!(
+            slack.events,
+            vec![HarvestEvent::Failed, HarvestEvent::Quarantined]
+        );
+        assert_eq!(slack.failure_rate_above, Some(0.05));
+
+        let NotificationSink::Email(email) = &config.notifications[1].sink else {
+            panic!("expected an email sink");
+        };
+        assert_eq!(email.smtp_port, 587);
+        assert_eq!(email.smtp_security, SmtpSecurity::Starttls);
+        assert_eq!(email.smtp_password_env, "CERES_SMTP_PASSWORD");
+        assert_eq!(
+            config.notifications[1].events,
+            vec![HarvestEvent::Completed]
+        );
+        assert_eq!(
+            config.notifications[1].sink.describe(),
+            "email to ops@example.org"
+        );
+    }
+
+    #[test]
+    fn test_load_rejects_invalid_notifications() {
+        let mut file = NamedTempFile::new().unwrap();
+        writeln!(
+            file,
+            r#"
+portals = []
+
+[[notifications]]
+type = "slack"
+webhook_url = "https://hooks.slack.com/services/T/B/x"
+failure_rate_above = 5
+"#
+        )
+        .unwrap();
+
+        let err = load_portals_config(Some(file.path().to_path_buf())).unwrap_err();
+        assert!(err.to_string().contains("failure_rate_above"));
+    }
+
+    #[test]
+    fn test_portals_config_without_webhooks() {
+        let toml = r#"
+[[portals]]
+name = "a"
+url = "https://a.com"
+"#;
+        let config: PortalsConfig = toml::from_str(toml).unwrap();
+        assert!(config.webhooks.is_empty());
+    }
+
     #[test]
     fn test_portals_config_with_description() {
         let toml = r#"
@@ -460,6 +1444,66 @@ url = "https://test.com"
         assert_eq!(config.portals[0].url, "https://test.com");
     }
 
+    #[test]
+    fn test_rewrite_portal_url_preserves_comments() {
+        let content = r#"# Portals
+[[portals]]
+name = "milano"   # city
+url = "https://dati.comune.milano.it/"
+
+[[portals]]
+name = "sicilia"
+url = "https://dati.regione.sicilia.it"
+"#;
+        let (updated, changed) = rewrite_portal_url_in(
+            content,
+            "https://dati.comune.milano.it",
+            "https://dati.milano.it",
+        )
+        .unwrap();
+
+        assert_eq!(changed, 1);
+        assert!(updated.starts_with("# Portals"));
+        assert!(updated.contains("# city"));
+        assert!(updated.contains(r#"url = "https://dati.milano.it""#));
+        assert!(updated.contains(r#"url = "https://dati.regione.sicilia.it""#));
+    }
+
+    #[test]
+    fn test_rewrite_portal_url_file() {
+        let mut file = NamedTempFile::new().unwrap();
+        writeln!(
+            file,
+            r#"
+[[portals]]
+name = "test"
+url = "https://old.example.com"
+"#
+        )
+        .unwrap();
+
+        let changed = rewrite_portal_url(
+            file.path(),
+            "https://old.example.com",
+            "https://new.example.com",
+        )
+        .unwrap();
+        assert_eq!(changed, 1);
+
+        let config = load_portals_config(Some(file.path().to_path_buf()))
+            .unwrap()
+            .unwrap();
+        assert_eq!(config.portals[0].url, "https://new.example.com");
+
+        let changed = rewrite_portal_url(
+            file.path(),
+            "https://old.example.com",
+            "https://new.example.com",
+        )
+        .unwrap();
+        assert_eq!(changed, 0);
+    }
+
     #[test]
     fn test_load_portals_config_custom_path_not_found() {
         let result = load_portals_config(Some("/nonexistent/path/to/config.toml".into()));
@@ -553,4 +1597,139 @@ description = "A fully configured portal"
         assert!(config.portals.is_empty());
         assert!(config.enabled_portals().is_empty());
     }
+
+    fn bundle_portal(name: &str, url: &str) -> PortalEntry {
+        PortalEntry {
+            name: name.to_string(),
+            url: url.to_string(),
+            portal_type: "ckan".to_string(),
+            enabled: true,
+            description: Some(format!("Portal {}", name)),
+            schedule: None,
+            embedding_model: None,
+            chunk_embeddings: false,
+            include_tags: Vec::new(),
+            exclude_orgs: Vec::new(),
+            include_title: None,
+            exclude_title: None,
+        }
+    }
+
+    #[test]
+    fn test_merge_portals_skips_existing() {
+        let content = r#"# Portals
+[[portals]]
+name = "milano"   # city
+url = "https://dati.comune.milano.it/"
+
+[[webhooks]]
+url = "https://hooks.example.com"
+"#;
+        let portals = vec![
+            bundle_portal("Milano", "https://other.example.com"),
+            bundle_portal("comune", "https://dati.comune.milano.it"),
+            bundle_portal("toscana", "https://dati.toscana.it"),
+        ];
+        let (updated, merge) =
+            merge_portals_in(content, &portals, Some("italy-regions@1.0.0")).unwrap();
+
+        assert_eq!(merge.added, vec!["toscana"]);
+        assert_eq!(merge.skipped, vec!["Milano", "comune"]);
+        assert!(updated.starts_with("# Portals"));
+        assert!(updated.contains("# city"));
+        assert!(updated.contains("# From italy-regions@1.0.0"));
+
+        let config: PortalsConfig = toml::from_str(&updated).unwrap();
+        assert_eq!(config.portals.len(), 2);
+        assert_eq!(config.portals[1].url, "https://dati.toscana.it");
+        assert_eq!(config.webhooks.len(), 1);
+    }
+
+    #[test]
+    fn test_add_remove_and_toggle_portal() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(CONFIG_FILE_NAME);
+        std::fs::write(
+            &path,
+            "# My portals\n\n# Milano\n[[portals]]\nname = \"milano\"\nurl = \"https://dati.comune.milano.it\"\n",
+        )
+        .unwrap();
+
+        add_portal(&path, &bundle_portal("toscana", "https://dati.toscana.it")).unwrap();
+        assert!(add_portal(&path, &bundle_portal("Toscana", "https://x.example.com")).is_err());
+        assert!(add_portal(&path, &bundle_portal("x", "dati.toscana.it")).is_err());
+
+        assert!(set_portal_enabled(&path, "TOSCANA", false).unwrap());
+        assert!(!set_portal_enabled(&path, "https://dati.toscana.it/", false).unwrap());
+        let config = load_portals_config(Some(path.clone())).unwrap().unwrap();
+        assert_eq!(config.portals.len(), 2);
+        assert!(!config.portals[1].enabled);
+
+        assert_eq!(remove_portal(&path, "MILANO").unwrap(), "milano");
+        assert!(remove_portal(&path, "milano").is_err());
+        let content = std::fs::read_to_string(&path).unwrap();
+        assert!(content.starts_with("# My portals"));
+        assert!(!content.contains("# Milano"));
+        let config: PortalsConfig = toml::from_str(&content).unwrap();
+        assert_eq!(config.portals.len(), 1);
+        assert_eq!(config.portals[0].name, "toscana");
+    }
+
+    #[test]
+    fn test_merge_portals_creates_file() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("ceres").join(CONFIG_FILE_NAME);
+        let portals = vec![bundle_portal("toscana", "https://dati.toscana.it")];
+
+        let merge = merge_portals(&path, &portals, "italy-regions@1.0.0").unwrap();
+        assert_eq!(merge.added.len(), 1);
+
+        let merge = merge_portals(&path, &portals, "italy-regions@1.0.0").unwrap();
+        assert!(merge.added.is_empty());
+        assert_eq!(merge.skipped, vec!["toscana"]);
+
+        let config = load_portals_config(Some(path)).unwrap().unwrap();
+        assert_eq!(config.portals.len(), 1);
+        assert_eq!(config.portals[0].portal_type, "ckan");
+    }
+
+    #[test]
+    fn test_load_settings() {
+        let mut file = NamedTempFile::new().unwrap();
+        writeln!(
+            file,
+            r#"
+database_url = "postgresql://localhost/ceres"
+
+[http]
+timeout = 60
+
+[embedding]
+provider = "ollama"
+"#
+        )
+        .unwrap();
+
+        let settings = load_settings(file.path()).unwrap();
+        assert_eq!(
+            settings,
+            vec![
+                (
+                    "DATABASE_URL".to_string(),
+                    "postgresql://localhost/ceres".to_string()
+                ),
+                ("EMBEDDING_PROVIDER".to_string(), "ollama".to_string()),
+                ("HTTP_TIMEOUT".to_string(), "60".to_string()),
+            ]
+        );
+
+        writeln!(file, "tags = [\"a\"]").unwrap();
+        let err = load_settings(file.path()).unwrap_err();
+        assert!(err.to_string().contains("'embedding_tags'"));
+
+        let dir = tempfile::tempdir().unwrap();
+        assert!(load_settings(&dir.path().join(SETTINGS_FILE_NAME))
+            .unwrap()
+            .is_empty());
+    }
 }
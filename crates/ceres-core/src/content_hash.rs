@@ -0,0 +1,186 @@
+//! Fields covered by the content hash used for delta detection.
+//!
+//! Harvests compare each dataset's content hash with the stored one and
+//! skip unchanged datasets. The hash always covers the title and
+//! description, which are what gets embedded; [`HashField`]s add metadata
+//! to it, so datasets whose resources, tags or license changed are stored
+//! again too. With no extra fields the hash is
+//! [`NewDataset::compute_content_hash`], as before fields were configurable.
+//!
+//! Changing the fields changes every hash. `ceres backfill-hashes --rehash`
+//! recomputes stored hashes from the stored metadata, so the next harvest
+//! only updates datasets that really changed; otherwise it updates them
+//! all, reusing cached embeddings.
+
+use std::fmt;
+
+use serde_json::Value;
+use sha2::{Digest, Sha256};
+
+use crate::models::NewDataset;
+use crate::organizations::DatasetOrganization;
+use crate::resources::dataset_resources;
+use crate::tags::dataset_tags;
+
+/// Metadata a content hash can cover besides title and description.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum HashField {
+    /// Resource names, formats, MIME types, sizes and URLs
+    Resources,
+    /// Normalized tags
+    Tags,
+    /// CKAN `license_id`
+    License,
+    /// Organization name
+    Organization,
+    /// CKAN `metadata_modified`, which changes on every edit
+    Modified,
+}
+
+impl HashField {
+    /// Canonical text of the field in `metadata`.
+    fn canonical(self, metadata: &Value) -> String {
+        match self {
+            Self::Resources => serde_json::to_string(&dataset_resources(metadata))
+                .expect("resources serialize to JSON"),
+            Self::Tags => dataset_tags(metadata).join("\u{1f}"),
+            Self::License => metadata["license_id"].as_str().unwrap_or("").to_string(),
+            Self::Organization => DatasetOrganization::from_metadata(metadata)
+                .map(|org| org.name)
+                .unwrap_or_default(),
+            Self::Modified => metadata["metadata_modified"]
+                .as_str()
+                .unwrap_or("")
+                .to_string(),
+        }
+    }
+}
+
+impl fmt::Display for HashField {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Self::Resources => "resources",
+            Self::Tags => "tags",
+            Self::License => "license",
+            Self::Organization => "organization",
+            Self::Modified => "modified",
+        })
+    }
+}
+
+/// SHA-256 content hash of a dataset over its title, description and
+/// `fields` of its CKAN `metadata`, as a 64-character hex string.
+///
+/// The order and repetition of `fields` do not matter.
+///
+/// # Examples
+///
+/// ```
+/// use ceres_core::content_hash::{content_hash, HashField};
+/// use ceres_core::NewDataset;
+/// use serde_json::json;
+///
+/// let metadata = json!({ "license_id": "cc-by" });
+/// assert_eq!(
+///     content_hash("Bus stops", None, &metadata, &[]),
+///     NewDataset::compute_content_hash("Bus stops", None)
+/// );
+/// assert_ne!(
+///     content_hash("Bus stops", None, &metadata, &[HashField::License]),
+///     content_hash("Bus stops", None, &json!({ "license_id": "odbl" }), &[HashField::License])
+/// );
+/// ```
+pub fn content_hash(
+    title: &str,
+    description: Option<&str>,
+    metadata: &Value,
+    fields: &[HashField],
+) -> String {
+    if fields.is_empty() {
+        return NewDataset::compute_content_hash(title, description);
+    }
+    let mut fields = fields.to_vec();
+    fields.sort();
+    fields.dedup();
+
+    let mut hasher = Sha256::new();
+    hasher.update(format!("{}\n{}", title, description.unwrap_or("")).as_bytes());
+    for field in fields {
+        // Named so that a value cannot pass for another field's
+        hasher.update(format!("\n{}={}", field, field.canonical(metadata)).as_bytes());
+    }
+    format!("{:x}", hasher.finalize())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn metadata() -> Value {
+        json!({
+            "tags": [{ "name": "Ambiente" }],
+            "license_id": "cc-by",
+            "organization": { "name": "arpa" },
+            "resources": [{ "format": "CSV", "url": "https://example.org/a.csv" }],
+            "metadata_modified": "2024-05-01T10:00:00",
+        })
+    }
+
+    #[test]
+    fn test_fields_change_the_hash() {
+        let all = [
+            HashField::Resources,
+            HashField::Tags,
+            HashField::License,
+            HashField::Organization,
+            HashField::Modified,
+        ];
+        let base = content_hash("Aria", Some("PM10"), &metadata(), &all);
+        assert_eq!(base.len(), 64);
+
+        let changes = [
+            (
+                "resources",
+                json!([{ "format": "JSON", "url": "https://example.org/a.csv" }]),
+            ),
+            ("tags", json!([{ "name": "Ambiente" }, { "name": "Aria" }])),
+            ("license_id", json!("odbl")),
+            ("organization", json!({ "name": "regione" })),
+            ("metadata_modified", json!("2024-06-01T10:00:00")),
+        ];
+        for (key, value) in changes {
+            let mut changed = metadata();
+            changed[key] = value;
+            assert_ne!(
+                content_hash("Aria", Some("PM10"), &changed, &all),
+                base,
+                "{}",
+                key
+            );
+            // Fields left out of the hash do not change it
+            assert_eq!(
+                content_hash("Aria", Some("PM10"), &changed, &[]),
+                content_hash("Aria", Some("PM10"), &metadata(), &[])
+            );
+        }
+    }
+
+    #[test]
+    fn test_field_order_does_not_matter() {
+        let a = content_hash(
+            "Aria",
+            None,
+            &metadata(),
+            &[HashField::Tags, HashField::License],
+        );
+        let b = content_hash(
+            "Aria",
+            None,
+            &metadata(),
+            &[HashField::License, HashField::Tags, HashField::Tags],
+        );
+        assert_eq!(a, b);
+        assert_ne!(a, content_hash("Aria", None, &metadata(), &[]));
+    }
+}
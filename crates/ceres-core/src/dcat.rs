@@ -0,0 +1,209 @@
+//! DCAT-AP catalog export (`ceres export --format dcat`).
+//!
+//! Ceres can republish its index as a DCAT-AP catalog in JSON-LD, so other
+//! harvesters (national portals, data.europa.eu) can use it as a single
+//! source for every portal it aggregates. Datasets are described from their
+//! stored fields and CKAN metadata: resources with a URL become
+//! distributions, tags become keywords and the organization the publisher.
+
+use chrono::{DateTime, Utc};
+use serde_json::{json, Map, Value};
+
+use crate::audit::parse_ckan_timestamp;
+use crate::facets::organization_name;
+use crate::language::iso_639_3;
+use crate::models::Dataset;
+use crate::resources::dataset_resources;
+use crate::spatial::dataset_bbox;
+use crate::tags::dataset_tags;
+
+/// Title of the exported catalog.
+pub const CATALOG_TITLE: &str = "Ceres";
+
+/// Description of the exported catalog.
+pub const CATALOG_DESCRIPTION: &str = "Open data datasets aggregated by Ceres from CKAN portals";
+
+/// Base of the EU language vocabulary used for `dct:language`.
+const LANGUAGE_AUTHORITY: &str = "http://publications.europa.eu/resource/authority/language/";
+
+/// JSON-LD context declaring the vocabulary prefixes used in the catalog.
+pub fn context() -> Value {
+    json!({
+        "dcat": "http://www.w3.org/ns/dcat#",
+        "dct": "http://purl.org/dc/terms/",
+        "foaf": "http://xmlns.com/foaf/0.1/",
+        "gsp": "http://www.opengis.net/ont/geosparql#",
+        "xsd": "http://www.w3.org/2001/XMLSchema#"
+    })
+}
+
+/// Catalog node, with its context but without `dcat:dataset`.
+pub fn catalog(modified: DateTime<Utc>) -> Value {
+    json!({
+        "@context": context(),
+        "@type": "dcat:Catalog",
+        "dct:title": CATALOG_TITLE,
+        "dct:description": CATALOG_DESCRIPTION,
+        "dct:publisher": agent(CATALOG_TITLE),
+        "dct:modified": date_time(modified),
+    })
+}
+
+/// DCAT-AP description of a dataset, identified by its landing page.
+pub fn dataset_node(dataset: &Dataset) -> Value {
+    let metadata = &dataset.metadata.0;
+    let mut node = Map::new();
+    node.insert("@id".into(), json!(dataset.url));
+    node.insert("@type".into(), json!("dcat:Dataset"));
+    node.insert("dct:identifier".into(), json!(dataset.original_id));
+    node.insert("dct:title".into(), json!(dataset.title));
+    if let Some(description) = &dataset.description {
+        node.insert("dct:description".into(), json!(description));
+    }
+    node.insert("dcat:landingPage".into(), json!({"@id": dataset.url}));
+
+    let keywords = dataset_tags(metadata);
+    if !keywords.is_empty() {
+        node.insert("dcat:keyword".into(), json!(keywords));
+    }
+    if let Some(publisher) = organization_name(metadata) {
+        node.insert("dct:publisher".into(), agent(publisher));
+    }
+    if let Some(code) = dataset.language.as_deref().and_then(iso_639_3) {
+        let uri = format!("{}{}", LANGUAGE_AUTHORITY, code.to_uppercase());
+        node.insert("dct:language".into(), json!({"@id": uri}));
+    }
+    if let Some(issued) = metadata["metadata_created"]
+        .as_str()
+        .and_then(parse_ckan_timestamp)
+    {
+        node.insert("dct:issued".into(), date_time(issued));
+    }
+    if let Some(modified) = dataset.modified_at {
+        node.insert("dct:modified".into(), date_time(modified));
+    }
+    if let Some(bbox) = dataset_bbox(metadata) {
+        let wkt = format!(
+            "POLYGON(({0} {1}, {2} {1}, {2} {3}, {0} {3}, {0} {1}))",
+            bbox.min_lon, bbox.min_lat, bbox.max_lon, bbox.max_lat
+        );
+        node.insert(
+            "dct:spatial".into(),
+            json!({
+                "@type": "dct:Location",
+                "dcat:bbox": {"@value": wkt, "@type": "gsp:wktLiteral"},
+            }),
+        );
+    }
+
+    let license = metadata["license_url"]
+        .as_str()
+        .filter(|url| url.starts_with("http://") || url.starts_with("https://"));
+    let distributions: Vec<Value> = dataset_resources(metadata)
+        .into_iter()
+        .filter_map(|resource| {
+            let mut distribution = Map::new();
+            distribution.insert("@type".into(), json!("dcat:Distribution"));
+            distribution.insert("dcat:accessURL".into(), json!({"@id": resource.url?}));
+            if let Some(name) = resource.name {
+                distribution.insert("dct:title".into(), json!(name));
+            }
+            if let Some(format) = resource.format {
+                distribution.insert("dct:format".into(), json!(format));
+            }
+            if let Some(mimetype) = resource.mimetype {
+                distribution.insert("dcat:mediaType".into(), json!(mimetype));
+            }
+            if let Some(size) = resource.size {
+                distribution.insert(
+                    "dcat:byteSize".into(),
+                    json!({"@value": size.to_string(), "@type": "xsd:nonNegativeInteger"}),
+                );
+            }
+            if let Some(license) = license {
+                distribution.insert("dct:license".into(), json!({"@id": license}));
+            }
+            Some(Value::Object(distribution))
+        })
+        .collect();
+    if !distributions.is_empty() {
+        node.insert("dcat:distribution".into(), json!(distributions));
+    }
+
+    Value::Object(node)
+}
+
+fn agent(name: &str) -> Value {
+    json!({"@type": "foaf:Agent", "foaf:name": name})
+}
+
+fn date_time(value: DateTime<Utc>) -> Value {
+    json!({"@value": value.to_rfc3339(), "@type": "xsd:dateTime"})
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use sqlx::types::Json;
+    use uuid::Uuid;
+
+    #[test]
+    fn test_dataset_node() {
+        let dataset = Dataset {
+            id: Uuid::new_v4(),
+            original_id: "fermate".to_string(),
+            source_portal: "https://dati.comune.milano.it".to_string(),
+            url: "https://dati.comune.milano.it/dataset/fermate".to_string(),
+            title: "Fermate autobus".to_string(),
+            description: Some("Elenco delle fermate".to_string()),
+            embedding: None,
+            metadata: Json(json!({
+                "organization": {"name": "comune-di-milano", "title": "Comune di Milano"},
+                "tags": [{"name": "Trasporti"}],
+                "metadata_created": "2023-01-10T08:00:00.123456",
+                "license_url": "https://creativecommons.org/licenses/by/4.0/",
+                "resources": [
+                    {"name": "Fermate", "format": "CSV", "size": 2048, "url": "https://example.com/f.csv"},
+                    {"name": "Senza URL", "format": "PDF"}
+                ]
+            })),
+            formats: vec!["CSV".to_string()],
+            first_seen_at: Utc::now(),
+            last_updated_at: Utc::now(),
+            content_hash: None,
+            embedding_model: None,
+            embedded_at: None,
+            modified_at: Some(Utc.with_ymd_and_hms(2024, 5, 1, 0, 0, 0).unwrap()),
+            language: Some("it".to_string()),
+            quality: None,
+        };
+
+        let node = dataset_node(&dataset);
+        assert_eq!(node["@id"], dataset.url);
+        assert_eq!(node["dct:publisher"]["foaf:name"], "Comune di Milano");
+        assert_eq!(node["dcat:keyword"], json!(["trasporti"]));
+        assert_eq!(
+            node["dct:language"]["@id"],
+            "http://publications.europa.eu/resource/authority/language/ITA"
+        );
+        assert_eq!(
+            node["dct:issued"]["@value"],
+            "2023-01-10T08:00:00.123456+00:00"
+        );
+        assert_eq!(node["dct:modified"]["@value"], "2024-05-01T00:00:00+00:00");
+
+        let distributions = node["dcat:distribution"].as_array().unwrap();
+        assert_eq!(distributions.len(), 1);
+        assert_eq!(
+            distributions[0]["dcat:accessURL"]["@id"],
+            "https://example.com/f.csv"
+        );
+        assert_eq!(distributions[0]["dcat:byteSize"]["@value"], "2048");
+        assert_eq!(
+            distributions[0]["dct:license"]["@id"],
+            "https://creativecommons.org/licenses/by/4.0/"
+        );
+        assert!(node.get("dct:spatial").is_none());
+    }
+}
@@ -0,0 +1,203 @@
+//! Linking publishers and places in dataset metadata to Wikidata.
+//!
+//! `ceres enrich` extracts organization and place names from the stored CKAN
+//! metadata, resolves them to Wikidata QIDs and stores the links, so datasets
+//! of the same publisher can be joined across portals. Lookups are cached per
+//! label, including misses, so each distinct name is searched once.
+
+use std::collections::BTreeSet;
+
+use serde_json::Value;
+use uuid::Uuid;
+
+/// Days after which a label without a confident match is searched again.
+pub const MISS_RETRY_DAYS: i64 = 30;
+
+/// CKAN extras naming the publisher (DCAT-AP and DCAT-AP_IT).
+const PUBLISHER_EXTRAS: [&str; 2] = ["publisher_name", "holder_name"];
+
+/// CKAN extras naming the area a dataset covers.
+const PLACE_EXTRAS: [&str; 2] = ["geographical_name", "spatial_text"];
+
+/// How an entity relates to a dataset.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum EntityRole {
+    /// Organization publishing or holding the dataset
+    Publisher,
+    /// Area the dataset covers
+    Place,
+}
+
+impl EntityRole {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            EntityRole::Publisher => "publisher",
+            EntityRole::Place => "place",
+        }
+    }
+}
+
+/// A name found in dataset metadata.
+#[derive(Debug, Clone, PartialEq, Eq, PartialOrd, Ord)]
+pub struct EntityMention {
+    pub role: EntityRole,
+    /// Name as written in the metadata
+    pub label: String,
+}
+
+/// A Wikidata item matching a label.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct WikidataEntity {
+    /// Item identifier, e.g. `Q490`
+    pub qid: String,
+    pub label: String,
+    pub description: Option<String>,
+}
+
+/// A dataset whose entity links are missing or stale.
+#[derive(Debug, Clone)]
+pub struct PendingDataset {
+    pub id: Uuid,
+    pub metadata: Value,
+    /// Content hash the links will be computed from
+    pub content_hash: Option<String>,
+}
+
+/// A Wikidata item linked from datasets of several portals.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct SharedEntity {
+    pub qid: String,
+    pub label: String,
+    pub portals: i64,
+    pub datasets: i64,
+}
+
+/// Normalizes a label for matching and caching: trimmed, whitespace
+/// collapsed, lowercased.
+///
+/// # Examples
+///
+/// ```
+/// use ceres_core::enrichment::label_key;
+///
+/// assert_eq!(label_key("  Comune di   Milano "), "comune di milano");
+/// ```
+pub fn label_key(label: &str) -> String {
+    label
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase()
+}
+
+/// Returns the canonical URI of a Wikidata item, for linked-data exports.
+pub fn entity_uri(qid: &str) -> String {
+    format!("http://www.wikidata.org/entity/{}", qid)
+}
+
+/// Extracts publisher and place names from CKAN metadata.
+///
+/// Publishers come from the dataset's organization (its title, or its name
+/// if untitled) and the `publisher_name`/`holder_name` extras; places from
+/// the `geographical_name` and `spatial_text` extras. Extras are read both
+/// from CKAN's `extras` list and as top-level fields, as ckanext-dcatapit
+/// flattens them. URIs and duplicates (by [`label_key`]) are skipped.
+pub fn extract_mentions(metadata: &Value) -> Vec<EntityMention> {
+    let mut mentions = Vec::new();
+    let mut seen = BTreeSet::new();
+    let mut push = |role: EntityRole, label: Option<&str>| {
+        let Some(label) = label.map(str::trim) else {
+            return;
+        };
+        if label.is_empty() || label.starts_with("http://") || label.starts_with("https://") {
+            return;
+        }
+        if seen.insert((role, label_key(label))) {
+            mentions.push(EntityMention {
+                role,
+                label: label.to_string(),
+            });
+        }
+    };
+
+    let organization = &metadata["organization"];
+    push(
+        EntityRole::Publisher,
+        organization["title"]
+            .as_str()
+            .filter(|t| !t.trim().is_empty())
+            .or(organization["name"].as_str()),
+    );
+
+    let extras = metadata["extras"].as_array();
+    let extra = |key: &str| -> Option<String> {
+        let listed = extras.and_then(|extras| {
+            extras
+                .iter()
+                .find(|e| e["key"] == key)
+                .and_then(|e| e["value"].as_str())
+        });
+        listed.or(metadata[key].as_str()).map(str::to_string)
+    };
+
+    for key in PUBLISHER_EXTRAS {
+        push(EntityRole::Publisher, extra(key).as_deref());
+    }
+    for key in PLACE_EXTRAS {
+        push(EntityRole::Place, extra(key).as_deref());
+    }
+
+    mentions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_extract_mentions() {
+        let metadata = json!({
+            "organization": {"name": "comune-di-milano", "title": "Comune di Milano"},
+            "extras": [
+                {"key": "holder_name", "value": "Comune di  Milano"},
+                {"key": "geographical_name", "value": "Milano"},
+                {"key": "spatial_text", "value": "http://publications.europa.eu/resource/authority/place/ITA_MIL"}
+            ],
+            "publisher_name": "Regione Lombardia"
+        });
+
+        assert_eq!(
+            extract_mentions(&metadata),
+            vec![
+                EntityMention {
+                    role: EntityRole::Publisher,
+                    label: "Comune di Milano".to_string()
+                },
+                EntityMention {
+                    role: EntityRole::Publisher,
+                    label: "Regione Lombardia".to_string()
+                },
+                EntityMention {
+                    role: EntityRole::Place,
+                    label: "Milano".to_string()
+                },
+            ]
+        );
+    }
+
+    #[test]
+    fn test_extract_mentions_falls_back_to_organization_name() {
+        let metadata = json!({"organization": {"name": "arpa", "title": " "}});
+        let mentions = extract_mentions(&metadata);
+        assert_eq!(mentions.len(), 1);
+        assert_eq!(mentions[0].label, "arpa");
+
+        assert!(extract_mentions(&json!({})).is_empty());
+    }
+
+    #[test]
+    fn test_entity_uri() {
+        assert_eq!(entity_uri("Q490"), "http://www.wikidata.org/entity/Q490");
+    }
+}
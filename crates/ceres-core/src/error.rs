@@ -100,6 +100,13 @@ pub enum AppError {
     #[error("Gemini error: {0}")]
     GeminiError(GeminiErrorDetails),
 
+    /// Embedding provider call failed.
+    ///
+    /// This error occurs when a non-Gemini embedding provider rejects a
+    /// request or returns vectors that cannot be stored.
+    #[error("Embedding error: {0}")]
+    EmbeddingError(String),
+
     /// JSON serialization or deserialization failed.
     ///
     /// This error occurs when converting between Rust types and JSON,
@@ -153,6 +160,24 @@ pub enum AppError {
     #[error("Rate limit exceeded. Please wait and try again.")]
     RateLimitExceeded,
 
+    /// Schema migration failed.
+    ///
+    /// This error occurs when a migration cannot be applied, or when an
+    /// applied migration no longer matches the one shipped with Ceres.
+    #[error("Migration error: {0}")]
+    MigrationError(String),
+
+    /// Another harvest of the portal is running.
+    ///
+    /// This error occurs when a harvest finds its portal locked and is
+    /// configured to skip it rather than wait.
+    #[error("Another harvest of {0} is running")]
+    PortalLocked(String),
+
+    /// The pgvector extension is not available on the PostgreSQL server.
+    #[error("The PostgreSQL server does not have the pgvector extension")]
+    PgvectorMissing,
+
     /// Configuration file error.
     ///
     /// This error occurs when reading or parsing the configuration file fails,
@@ -173,7 +198,7 @@ impl AppError {
     pub fn user_message(&self) -> String {
         match self {
             AppError::DatabaseError(e) => {
-                if e.to_string().contains("connection") {
+                if is_connection_error(e) || e.to_string().contains("connection") {
                     "Cannot connect to database. Is PostgreSQL running?\n   Try: docker-compose up -d".to_string()
                 } else {
                     format!("Database error: {}", e)
@@ -216,6 +241,12 @@ impl AppError {
                     format!("Gemini error: {}", details.message)
                 }
             },
+            AppError::EmbeddingError(msg) => {
+                format!(
+                    "Embedding provider error: {}\n   Check EMBEDDING_BASE_URL and EMBEDDING_MODEL.",
+                    msg
+                )
+            }
             AppError::InvalidPortalUrl(url) => {
                 format!(
                     "Invalid portal URL: {}\n   Example: https://dati.comune.milano.it",
@@ -240,6 +271,15 @@ impl AppError {
                     msg
                 )
             }
+            AppError::PortalLocked(url) => {
+                format!(
+                    "Another harvest of {} is running, skipped.\n   Use --on-locked wait to wait for it instead.",
+                    url
+                )
+            }
+            AppError::PgvectorMissing => {
+                "The pgvector extension is not installed on this PostgreSQL server.\n   Install it (https://github.com/pgvector/pgvector#installation) or run the pgvector/pgvector Docker image.".to_string()
+            }
             _ => self.to_string(),
         }
     }
@@ -280,6 +320,17 @@ impl AppError {
     }
 }
 
+/// Returns true if `error` means the database could not be reached, or is
+/// still starting up, rather than that it refused a query.
+pub fn is_connection_error(error: &sqlx::Error) -> bool {
+    match error {
+        sqlx::Error::Io(_) | sqlx::Error::Tls(_) | sqlx::Error::PoolTimedOut => true,
+        // cannot_connect_now: the server is starting up or shutting down
+        sqlx::Error::Database(e) => e.code().as_deref() == Some("57P03"),
+        _ => false,
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -399,6 +450,16 @@ mod tests {
         assert!(msg.contains("Cannot connect to database") || msg.contains("Database error"));
     }
 
+    #[test]
+    fn test_user_message_connection_refused() {
+        let refused = std::io::Error::from(std::io::ErrorKind::ConnectionRefused);
+        let err = AppError::DatabaseError(sqlx::Error::Io(refused));
+        assert!(err.user_message().contains("Cannot connect to database"));
+        assert!(AppError::PgvectorMissing
+            .user_message()
+            .contains("pgvector"));
+    }
+
     #[test]
     fn test_is_retryable() {
         assert!(AppError::NetworkError("timeout".to_string()).is_retryable());
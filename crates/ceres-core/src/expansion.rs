@@ -0,0 +1,194 @@
+//! Query expansion for recall.
+//!
+//! Terse queries such as "PM10" embed poorly: the vector of a bare code
+//! lands far from descriptions that spell it out ("polveri sottili"). With
+//! expansion, search also embeds a few paraphrases of the query and merges
+//! the result sets (see [`merge_result_sets`](crate::search::merge_result_sets)).
+//!
+//! Paraphrases come from a generative model or from a [`SynonymTable`]: a
+//! built-in list of open data terms, optionally extended from a TOML file:
+//!
+//! ```toml
+//! groups = [
+//!     ["ztl", "zona a traffico limitato", "limited traffic zone"],
+//! ]
+//! ```
+
+use std::path::Path;
+
+use serde::Deserialize;
+
+use crate::error::AppError;
+
+/// Paraphrases searched in addition to the original query.
+pub const MAX_EXPANSIONS: usize = 3;
+
+/// Built-in synonym groups: common Italian open data terms and their
+/// English equivalents.
+const BUILTIN_GROUPS: &[&[&str]] = &[
+    &[
+        "pm10",
+        "polveri sottili",
+        "particelle sospese",
+        "particulate matter",
+    ],
+    &[
+        "pm2.5",
+        "particolato fine",
+        "polveri fini",
+        "fine particulate matter",
+    ],
+    &[
+        "qualità dell'aria",
+        "inquinamento atmosferico",
+        "air quality",
+    ],
+    &[
+        "tpl",
+        "trasporto pubblico locale",
+        "autobus",
+        "public transport",
+    ],
+    &["bilancio", "rendiconto", "spesa pubblica", "budget"],
+    &["popolazione", "residenti", "demografia", "population"],
+    &["rifiuti", "raccolta differenziata", "waste collection"],
+    &["piste ciclabili", "percorsi ciclabili", "cycle lanes"],
+    &["parcheggi", "aree di sosta", "parking"],
+    &["incidenti stradali", "sinistri stradali", "road accidents"],
+    &["scuole", "istituti scolastici", "schools"],
+    &["wifi", "hotspot wi-fi", "free wifi"],
+];
+
+/// Groups of interchangeable search terms.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize)]
+pub struct SynonymTable {
+    /// Each group lists terms with the same meaning, lowercase
+    pub groups: Vec<Vec<String>>,
+}
+
+impl SynonymTable {
+    /// Returns the built-in table.
+    pub fn builtin() -> Self {
+        Self {
+            groups: BUILTIN_GROUPS
+                .iter()
+                .map(|group| group.iter().map(|t| t.to_string()).collect())
+                .collect(),
+        }
+    }
+
+    /// Parses a table from TOML with a `groups` array of term arrays.
+    ///
+    /// # Errors
+    ///
+    /// Returns `AppError::ConfigError` if the TOML is invalid.
+    pub fn parse(content: &str) -> Result<Self, AppError> {
+        let table: Self = toml::from_str(content)
+            .map_err(|e| AppError::ConfigError(format!("Invalid synonym table: {}", e)))?;
+        Ok(Self {
+            groups: table
+                .groups
+                .into_iter()
+                .map(|group| group.iter().map(|t| t.trim().to_lowercase()).collect())
+                .collect(),
+        })
+    }
+
+    /// Returns the built-in table extended with the groups in `path`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `AppError::ConfigError` if the file cannot be read or parsed.
+    pub fn builtin_with_file(path: &Path) -> Result<Self, AppError> {
+        let content = std::fs::read_to_string(path).map_err(|e| {
+            AppError::ConfigError(format!("Failed to read {}: {}", path.display(), e))
+        })?;
+        let mut table = Self::builtin();
+        table.groups.extend(Self::parse(&content)?.groups);
+        Ok(table)
+    }
+
+    /// Returns up to `max` paraphrases of `query`, replacing a term found in
+    /// the query with the other terms of its group.
+    ///
+    /// Terms match whole words, ignoring case; paraphrases are lowercase.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ceres_core::expansion::SynonymTable;
+    ///
+    /// let table = SynonymTable::builtin();
+    /// assert_eq!(
+    ///     table.expand("PM10 Milano", 2),
+    ///     vec!["polveri sottili milano", "particelle sospese milano"]
+    /// );
+    /// assert!(table.expand("catasto", 3).is_empty());
+    /// ```
+    pub fn expand(&self, query: &str, max: usize) -> Vec<String> {
+        let query = query.trim().to_lowercase();
+        let mut paraphrases: Vec<String> = Vec::new();
+
+        for group in &self.groups {
+            let Some((term, start)) = group
+                .iter()
+                .find_map(|term| find_word(&query, term).map(|start| (term, start)))
+            else {
+                continue;
+            };
+            let end = start + term.len();
+            for other in group.iter().filter(|t| *t != term) {
+                if paraphrases.len() == max {
+                    return paraphrases;
+                }
+                let paraphrase = format!("{}{}{}", &query[..start], other, &query[end..]);
+                if paraphrase != query && !paraphrases.contains(&paraphrase) {
+                    paraphrases.push(paraphrase);
+                }
+            }
+        }
+        paraphrases.truncate(max);
+        paraphrases
+    }
+}
+
+/// Byte offset of the first occurrence of `term` in `text` as whole words.
+fn find_word(text: &str, term: &str) -> Option<usize> {
+    if term.is_empty() {
+        return None;
+    }
+    text.match_indices(term).map(|(i, _)| i).find(|&i| {
+        let before = text[..i].chars().next_back();
+        let after = text[i + term.len()..].chars().next();
+        !before.is_some_and(char::is_alphanumeric) && !after.is_some_and(char::is_alphanumeric)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_expand_matches_whole_words() {
+        let table = SynonymTable::parse(r#"groups = [["Bus", "autobus"]]"#).unwrap();
+        assert_eq!(table.expand("orari bus", 3), vec!["orari autobus"]);
+        assert_eq!(table.expand("orari autobus", 3), vec!["orari bus"]);
+        assert!(table.expand("busta paga", 3).is_empty());
+    }
+
+    #[test]
+    fn test_expand_caps_paraphrases() {
+        let table = SynonymTable::builtin();
+        assert_eq!(table.expand("pm10", 5).len(), 3);
+        assert_eq!(table.expand("PM10 bilancio", 5).len(), 5);
+        assert!(table.expand("pm10", 0).is_empty());
+    }
+
+    #[test]
+    fn test_parse_rejects_invalid_table() {
+        assert!(matches!(
+            SynonymTable::parse("groups = \"pm10\""),
+            Err(AppError::ConfigError(_))
+        ));
+    }
+}
@@ -0,0 +1,188 @@
+//! Facet counts over search results.
+//!
+//! A broad query matches datasets from many portals, publishers and years.
+//! Counting the top matches by each of these shows how to narrow the query
+//! (`--portal`, `--format`, `--tag`, `--updated-after`, ...).
+
+use std::collections::HashMap;
+
+use chrono::Datelike;
+use serde::Serialize;
+
+use crate::models::Dataset;
+use crate::tags::dataset_tags;
+
+/// Top matches counted into facets, regardless of how many are shown.
+pub const FACET_CANDIDATES: usize = 100;
+
+/// Values listed per facet; rarer values are dropped.
+pub const MAX_FACET_VALUES: usize = 8;
+
+/// Number of matches sharing a facet value.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct FacetCount {
+    pub value: String,
+    pub count: usize,
+}
+
+/// Facet counts of a set of matches, most common values first.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct SearchFacets {
+    /// Source portal URL
+    pub portal: Vec<FacetCount>,
+    /// Normalized resource format; a dataset counts once per format
+    pub format: Vec<FacetCount>,
+    /// CKAN organization title (or name)
+    pub organization: Vec<FacetCount>,
+    /// Normalized tag; a dataset counts once per tag
+    pub tag: Vec<FacetCount>,
+    /// Year of the last upstream modification
+    pub year: Vec<FacetCount>,
+}
+
+impl SearchFacets {
+    /// Returns true if no facet has a value.
+    pub fn is_empty(&self) -> bool {
+        self.portal.is_empty()
+            && self.format.is_empty()
+            && self.organization.is_empty()
+            && self.tag.is_empty()
+            && self.year.is_empty()
+    }
+}
+
+/// Counts `datasets` by portal, format, organization, tag and year.
+///
+/// Datasets without an organization or modification time are left out of
+/// those facets.
+pub fn compute_facets<'a>(datasets: impl IntoIterator<Item = &'a Dataset>) -> SearchFacets {
+    let mut portal = HashMap::new();
+    let mut format = HashMap::new();
+    let mut organization = HashMap::new();
+    let mut tag = HashMap::new();
+    let mut year = HashMap::new();
+
+    for dataset in datasets {
+        *portal.entry(dataset.source_portal.clone()).or_default() += 1;
+        for f in &dataset.formats {
+            *format.entry(f.clone()).or_default() += 1;
+        }
+        if let Some(name) = organization_name(&dataset.metadata) {
+            *organization.entry(name.to_string()).or_default() += 1;
+        }
+        for t in dataset_tags(&dataset.metadata) {
+            *tag.entry(t).or_default() += 1;
+        }
+        if let Some(modified_at) = dataset.modified_at {
+            *year.entry(modified_at.year().to_string()).or_default() += 1;
+        }
+    }
+
+    SearchFacets {
+        portal: top_values(portal),
+        format: top_values(format),
+        organization: top_values(organization),
+        tag: top_values(tag),
+        year: top_values(year),
+    }
+}
+
+/// The CKAN organization's title, or its name if untitled.
+pub fn organization_name(metadata: &serde_json::Value) -> Option<&str> {
+    let organization = &metadata["organization"];
+    [&organization["title"], &organization["name"]]
+        .into_iter()
+        .filter_map(|v| v.as_str())
+        .map(str::trim)
+        .find(|s| !s.is_empty())
+}
+
+/// Sorts counts by frequency, then value, keeping [`MAX_FACET_VALUES`].
+fn top_values(counts: HashMap<String, usize>) -> Vec<FacetCount> {
+    let mut values: Vec<FacetCount> = counts
+        .into_iter()
+        .map(|(value, count)| FacetCount { value, count })
+        .collect();
+    values.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.value.cmp(&b.value)));
+    values.truncate(MAX_FACET_VALUES);
+    values
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeZone, Utc};
+    use serde_json::json;
+    use sqlx::types::Json;
+    use uuid::Uuid;
+
+    fn dataset(portal: &str, formats: &[&str], metadata: serde_json::Value) -> Dataset {
+        Dataset {
+            id: Uuid::new_v4(),
+            original_id: "x".to_string(),
+            source_portal: portal.to_string(),
+            url: portal.to_string(),
+            title: "x".to_string(),
+            description: None,
+            embedding: None,
+            metadata: Json(metadata),
+            formats: formats.iter().map(|f| f.to_string()).collect(),
+            first_seen_at: Utc::now(),
+            last_updated_at: Utc::now(),
+            content_hash: None,
+            embedding_model: None,
+            embedded_at: None,
+            modified_at: None,
+            language: None,
+            quality: None,
+        }
+    }
+
+    #[test]
+    fn test_compute_facets() {
+        let milano = "https://dati.comune.milano.it";
+        let mut a = dataset(
+            milano,
+            &["CSV", "JSON"],
+            json!({
+                "organization": {"name": "comune-di-milano", "title": "Comune di Milano"},
+                "tags": [{"name": "Ambiente"}, {"name": "aria"}]
+            }),
+        );
+        a.modified_at = Some(Utc.with_ymd_and_hms(2024, 5, 1, 0, 0, 0).unwrap());
+        let b = dataset(
+            milano,
+            &["CSV"],
+            json!({"organization": {"name": "arpa", "title": " "}, "tags": [{"name": "ambiente"}]}),
+        );
+        let c = dataset("https://dati.gov.it", &[], json!({}));
+
+        let facets = compute_facets([&a, &b, &c]);
+        let pairs = |values: &[FacetCount]| -> Vec<(String, usize)> {
+            values.iter().map(|f| (f.value.clone(), f.count)).collect()
+        };
+        assert_eq!(
+            pairs(&facets.portal),
+            vec![
+                (milano.to_string(), 2),
+                ("https://dati.gov.it".to_string(), 1)
+            ]
+        );
+        assert_eq!(
+            pairs(&facets.format),
+            vec![("CSV".to_string(), 2), ("JSON".to_string(), 1)]
+        );
+        assert_eq!(
+            pairs(&facets.organization),
+            vec![("Comune di Milano".to_string(), 1), ("arpa".to_string(), 1)]
+        );
+        assert_eq!(
+            pairs(&facets.tag),
+            vec![("ambiente".to_string(), 2), ("aria".to_string(), 1)]
+        );
+        assert_eq!(pairs(&facets.year), vec![("2024".to_string(), 1)]);
+        assert!(compute_facets([]).is_empty());
+    }
+}
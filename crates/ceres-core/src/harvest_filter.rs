@@ -0,0 +1,200 @@
+//! Per-portal filters selecting the datasets a harvest keeps.
+//!
+//! Crowded national portals are often harvested for a few topics only.
+//! Portals in portals.toml can set `include_tags`, `exclude_orgs`,
+//! `include_title` and `exclude_title`; harvests push the tag and
+//! organization filters into the `fq` of `package_search` so excluded
+//! datasets are never fetched, then check every fetched dataset against all
+//! filters, which also covers portals listed through `package_list`.
+
+use regex::Regex;
+
+use crate::config::PortalEntry;
+use crate::error::AppError;
+use crate::models::NewDataset;
+use crate::organizations::DatasetOrganization;
+
+/// The filters a portal sets, ready to test datasets against.
+#[derive(Debug, Clone)]
+pub struct HarvestFilter {
+    include_tags: Vec<String>,
+    exclude_orgs: Vec<String>,
+    include_title: Option<Regex>,
+    exclude_title: Option<Regex>,
+}
+
+impl HarvestFilter {
+    /// The filters of `portal`, or `None` if it sets none.
+    ///
+    /// # Errors
+    ///
+    /// Returns `AppError::ConfigError` if a title pattern is not a valid
+    /// regular expression.
+    pub fn for_portal(portal: &PortalEntry) -> Result<Option<Self>, AppError> {
+        let regex = |key: &str, pattern: &Option<String>| {
+            pattern
+                .as_deref()
+                .map(|pattern| {
+                    Regex::new(pattern).map_err(|e| {
+                        AppError::ConfigError(format!(
+                            "Invalid {} of portal '{}': {}",
+                            key, portal.name, e
+                        ))
+                    })
+                })
+                .transpose()
+        };
+        let filter = Self {
+            include_tags: portal.include_tags.clone(),
+            exclude_orgs: portal.exclude_orgs.clone(),
+            include_title: regex("include_title", &portal.include_title)?,
+            exclude_title: regex("exclude_title", &portal.exclude_title)?,
+        };
+        let empty = filter.include_tags.is_empty()
+            && filter.exclude_orgs.is_empty()
+            && filter.include_title.is_none()
+            && filter.exclude_title.is_none();
+        Ok((!empty).then_some(filter))
+    }
+
+    /// Returns true if `dataset` passes every filter.
+    ///
+    /// Tags and organization names are compared as the portal spells them
+    /// (CKAN `tags[].name` and `organization.name`), as `package_search`
+    /// compares them.
+    pub fn matches(&self, dataset: &NewDataset) -> bool {
+        if !self.include_tags.is_empty() {
+            let tags = dataset.metadata["tags"].as_array();
+            let tagged = tags.is_some_and(|tags| {
+                tags.iter()
+                    .filter_map(|tag| tag["name"].as_str())
+                    .any(|name| self.include_tags.iter().any(|t| t == name))
+            });
+            if !tagged {
+                return false;
+            }
+        }
+        if let Some(organization) = DatasetOrganization::from_metadata(&dataset.metadata) {
+            if self.exclude_orgs.contains(&organization.name) {
+                return false;
+            }
+        }
+        self.include_title
+            .as_ref()
+            .is_none_or(|re| re.is_match(&dataset.title))
+            && !self
+                .exclude_title
+                .as_ref()
+                .is_some_and(|re| re.is_match(&dataset.title))
+    }
+
+    /// The tag and organization filters as a Solr filter query for CKAN's
+    /// `package_search`, or `None` if the portal sets neither. Title
+    /// patterns have no Solr equivalent and are only checked by
+    /// [`HarvestFilter::matches`].
+    pub fn solr_query(&self) -> Option<String> {
+        let any_of = |values: &[String]| {
+            let quoted: Vec<String> = values
+                .iter()
+                .map(|v| format!("\"{}\"", v.replace('\\', "\\\\").replace('"', "\\\"")))
+                .collect();
+            format!("({})", quoted.join(" OR "))
+        };
+        let mut clauses = Vec::new();
+        if !self.include_tags.is_empty() {
+            clauses.push(format!("tags:{}", any_of(&self.include_tags)));
+        }
+        if !self.exclude_orgs.is_empty() {
+            clauses.push(format!("-organization:{}", any_of(&self.exclude_orgs)));
+        }
+        (!clauses.is_empty()).then(|| clauses.join(" AND "))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn portal() -> PortalEntry {
+        toml::from_str(
+            r#"
+            name = "dati-gov"
+            url = "https://www.dati.gov.it/opendata"
+            include_tags = ["ambiente", "Qualità dell'aria"]
+            exclude_orgs = ["regione-test"]
+            exclude_title = "(?i)^test"
+            "#,
+        )
+        .unwrap()
+    }
+
+    fn dataset(title: &str, tags: &[&str], org: &str) -> NewDataset {
+        let tags: Vec<_> = tags.iter().map(|t| json!({ "name": t })).collect();
+        NewDataset::from_metadata(
+            "id".to_string(),
+            "https://www.dati.gov.it/opendata".to_string(),
+            "https://www.dati.gov.it/opendata/dataset/id".to_string(),
+            title.to_string(),
+            None,
+            json!({ "tags": tags, "organization": { "name": org } }),
+            &[],
+        )
+    }
+
+    #[test]
+    fn test_matches() {
+        let filter = HarvestFilter::for_portal(&portal()).unwrap().unwrap();
+        assert!(filter.matches(&dataset("PM10 Milano", &["ambiente"], "arpa")));
+        // Tags are compared as spelled on the portal
+        assert!(!filter.matches(&dataset("PM10 Milano", &["Ambiente"], "arpa")));
+        assert!(!filter.matches(&dataset("PM10 Milano", &[], "arpa")));
+        assert!(!filter.matches(&dataset("PM10", &["ambiente"], "regione-test")));
+        assert!(!filter.matches(&dataset("Test PM10", &["ambiente"], "arpa")));
+
+        let include_title = PortalEntry {
+            include_title: Some("bilancio".to_string()),
+            ..portal()
+        };
+        let filter = HarvestFilter::for_portal(&include_title).unwrap().unwrap();
+        assert!(!filter.matches(&dataset("PM10 Milano", &["ambiente"], "arpa")));
+        assert!(filter.matches(&dataset("bilancio 2024", &["ambiente"], "arpa")));
+    }
+
+    #[test]
+    fn test_for_portal() {
+        let unfiltered = PortalEntry {
+            include_tags: Vec::new(),
+            exclude_orgs: Vec::new(),
+            exclude_title: None,
+            ..portal()
+        };
+        assert!(HarvestFilter::for_portal(&unfiltered).unwrap().is_none());
+
+        let invalid = PortalEntry {
+            include_title: Some("(unclosed".to_string()),
+            ..portal()
+        };
+        let err = HarvestFilter::for_portal(&invalid).unwrap_err();
+        assert!(err
+            .to_string()
+            .contains("include_title of portal 'dati-gov'"));
+    }
+
+    #[test]
+    fn test_solr_query() {
+        let filter = HarvestFilter::for_portal(&portal()).unwrap().unwrap();
+        assert_eq!(
+            filter.solr_query().unwrap(),
+            r#"tags:("ambiente" OR "Qualità dell'aria") AND -organization:("regione-test")"#
+        );
+
+        let titles_only = PortalEntry {
+            include_tags: Vec::new(),
+            exclude_orgs: Vec::new(),
+            ..portal()
+        };
+        let filter = HarvestFilter::for_portal(&titles_only).unwrap().unwrap();
+        assert_eq!(filter.solr_query(), None);
+    }
+}
@@ -0,0 +1,301 @@
+//! Portal health tracking and quarantine.
+//!
+//! Portals that fail repeatedly are quarantined: batch harvests and the
+//! daemon skip them until the quarantine is cleared manually
+//! (`ceres portals unquarantine <name>`) or its cool-down expires.
+
+use std::collections::HashMap;
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+
+use crate::config::{PortalEntry, PortalsConfig};
+use crate::error::AppError;
+
+/// When to quarantine a failing portal and for how long.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct QuarantinePolicy {
+    /// Consecutive failed harvests before a portal is quarantined
+    pub failure_threshold: u32,
+    /// How long a quarantine lasts before it clears on its own
+    pub cooldown: Duration,
+}
+
+impl Default for QuarantinePolicy {
+    fn default() -> Self {
+        Self {
+            failure_threshold: 3,
+            cooldown: Duration::from_secs(24 * 60 * 60),
+        }
+    }
+}
+
+/// Harvest health of a single portal, keyed by portal URL.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct PortalHealth {
+    /// Base URL of the portal
+    pub portal_url: String,
+    /// Failed harvests since the last success
+    pub consecutive_failures: u32,
+    /// Error message of the most recent failure
+    pub last_error: Option<String>,
+    /// Time of the most recent failure
+    pub last_failure_at: Option<DateTime<Utc>>,
+    /// Time of the most recent successful harvest
+    pub last_success_at: Option<DateTime<Utc>>,
+    /// End of the current quarantine, if any
+    pub quarantined_until: Option<DateTime<Utc>>,
+    /// Harvests recorded since tracking began
+    pub harvests: u32,
+    /// Failed harvests among them
+    pub failed_harvests: u32,
+}
+
+impl PortalHealth {
+    /// Creates a healthy record for a portal without history.
+    pub fn new(portal_url: &str) -> Self {
+        Self {
+            portal_url: portal_url.to_string(),
+            consecutive_failures: 0,
+            last_error: None,
+            last_failure_at: None,
+            last_success_at: None,
+            quarantined_until: None,
+            harvests: 0,
+            failed_harvests: 0,
+        }
+    }
+
+    /// Returns true if the portal is quarantined at `now`.
+    pub fn is_quarantined(&self, now: DateTime<Utc>) -> bool {
+        self.quarantined_until.is_some_and(|until| until > now)
+    }
+
+    /// Records a successful harvest, clearing failures and any quarantine.
+    pub fn record_success(&mut self, now: DateTime<Utc>) {
+        self.harvests += 1;
+        self.consecutive_failures = 0;
+        self.last_success_at = Some(now);
+        self.quarantined_until = None;
+    }
+
+    /// Records a failed harvest. Returns true if this failure started a quarantine.
+    pub fn record_failure(
+        &mut self,
+        now: DateTime<Utc>,
+        error: &str,
+        policy: &QuarantinePolicy,
+    ) -> bool {
+        self.harvests += 1;
+        self.failed_harvests += 1;
+        self.consecutive_failures += 1;
+        self.last_error = Some(error.to_string());
+        self.last_failure_at = Some(now);
+
+        if self.consecutive_failures >= policy.failure_threshold && !self.is_quarantined(now) {
+            let cooldown = chrono::Duration::from_std(policy.cooldown).unwrap_or_default();
+            self.quarantined_until = Some(now + cooldown);
+            return true;
+        }
+        false
+    }
+
+    /// Clears the quarantine and failure streak.
+    pub fn clear_quarantine(&mut self) {
+        self.consecutive_failures = 0;
+        self.quarantined_until = None;
+    }
+}
+
+/// Why a configured portal is left out of a batch run.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum SkipReason {
+    /// `enabled = false` in the configuration
+    Disabled,
+    /// Quarantined until the given time
+    Quarantined(DateTime<Utc>),
+}
+
+/// Portals chosen for a batch run, plus the ones skipped and why.
+#[derive(Debug, Clone)]
+pub struct PortalSelection<'a> {
+    pub selected: Vec<&'a PortalEntry>,
+    pub skipped: Vec<(&'a PortalEntry, SkipReason)>,
+}
+
+/// Chooses the portals a batch harvest touches.
+///
+/// Without `only`, every enabled portal is selected. With `only`, exactly the
+/// named portals are selected (case-insensitive), including disabled ones.
+/// Quarantined portals are skipped unless `include_quarantined` is set.
+///
+/// # Errors
+///
+/// Returns `AppError::ConfigError` if a name in `only` is not configured.
+pub fn select_portals<'a>(
+    config: &'a PortalsConfig,
+    only: &[String],
+    include_quarantined: bool,
+    health: &HashMap<String, PortalHealth>,
+    now: DateTime<Utc>,
+) -> Result<PortalSelection<'a>, AppError> {
+    let candidates: Vec<&PortalEntry> = if only.is_empty() {
+        config.portals.iter().collect()
+    } else {
+        only.iter()
+            .map(|name| {
+                config.find_by_name(name).ok_or_else(|| {
+                    AppError::ConfigError(format!("Portal '{}' not found in configuration", name))
+                })
+            })
+            .collect::<Result<_, _>>()?
+    };
+
+    let mut selection = PortalSelection {
+        selected: Vec::new(),
+        skipped: Vec::new(),
+    };
+    for portal in candidates {
+        if only.is_empty() && !portal.enabled {
+            selection.skipped.push((portal, SkipReason::Disabled));
+            continue;
+        }
+        if !include_quarantined {
+            if let Some(until) = health
+                .get(&portal.url)
+                .filter(|h| h.is_quarantined(now))
+                .and_then(|h| h.quarantined_until)
+            {
+                selection
+                    .skipped
+                    .push((portal, SkipReason::Quarantined(until)));
+                continue;
+            }
+        }
+        selection.selected.push(portal);
+    }
+
+    Ok(selection)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn now() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2025, 3, 1, 12, 0, 0).unwrap()
+    }
+
+    fn config() -> PortalsConfig {
+        toml::from_str(
+            r#"
+[[portals]]
+name = "milano"
+url = "https://dati.comune.milano.it"
+
+[[portals]]
+name = "sicilia"
+url = "https://dati.regione.sicilia.it"
+
+[[portals]]
+name = "off"
+url = "https://off.example.com"
+enabled = false
+"#,
+        )
+        .unwrap()
+    }
+
+    fn quarantined(url: &str) -> HashMap<String, PortalHealth> {
+        let mut health = PortalHealth::new(url);
+        health.quarantined_until = Some(now() + chrono::Duration::hours(1));
+        HashMap::from([(url.to_string(), health)])
+    }
+
+    #[test]
+    fn test_quarantine_after_threshold() {
+        let policy = QuarantinePolicy::default();
+        let mut health = PortalHealth::new("https://a.com");
+
+        assert!(!health.record_failure(now(), "timeout", &policy));
+        assert!(!health.record_failure(now(), "timeout", &policy));
+        assert!(health.record_failure(now(), "timeout", &policy));
+        assert!(health.is_quarantined(now()));
+        assert_eq!(health.consecutive_failures, 3);
+
+        // Further failures don't restart the quarantine
+        assert!(!health.record_failure(now(), "timeout", &policy));
+    }
+
+    #[test]
+    fn test_quarantine_expires_after_cooldown() {
+        let policy = QuarantinePolicy::default();
+        let mut health = PortalHealth::new("https://a.com");
+        for _ in 0..3 {
+            health.record_failure(now(), "timeout", &policy);
+        }
+        let later = now() + chrono::Duration::hours(25);
+        assert!(!health.is_quarantined(later));
+
+        // A failure after the cool-down quarantines again immediately
+        assert!(health.record_failure(later, "timeout", &policy));
+    }
+
+    #[test]
+    fn test_success_clears_quarantine() {
+        let policy = QuarantinePolicy::default();
+        let mut health = PortalHealth::new("https://a.com");
+        for _ in 0..3 {
+            health.record_failure(now(), "timeout", &policy);
+        }
+        health.record_success(now());
+        assert!(!health.is_quarantined(now()));
+        assert_eq!(health.consecutive_failures, 0);
+        assert_eq!(health.last_success_at, Some(now()));
+        assert_eq!((health.harvests, health.failed_harvests), (4, 3));
+    }
+
+    #[test]
+    fn test_select_enabled_skips_quarantined() {
+        let config = config();
+        let health = quarantined("https://dati.regione.sicilia.it");
+        let selection = select_portals(&config, &[], false, &health, now()).unwrap();
+
+        let names: Vec<&str> = selection.selected.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, vec!["milano"]);
+        assert_eq!(selection.skipped.len(), 2);
+        assert!(selection
+            .skipped
+            .iter()
+            .any(|(p, r)| p.name == "off" && *r == SkipReason::Disabled));
+    }
+
+    #[test]
+    fn test_select_include_quarantined() {
+        let config = config();
+        let health = quarantined("https://dati.regione.sicilia.it");
+        let selection = select_portals(&config, &[], true, &health, now()).unwrap();
+        assert_eq!(selection.selected.len(), 2);
+    }
+
+    #[test]
+    fn test_select_only_named() {
+        let config = config();
+        let only = vec!["OFF".to_string(), "milano".to_string()];
+        let selection = select_portals(&config, &only, false, &HashMap::new(), now()).unwrap();
+
+        let names: Vec<&str> = selection.selected.iter().map(|p| p.name.as_str()).collect();
+        assert_eq!(names, vec!["off", "milano"]);
+        assert!(selection.skipped.is_empty());
+    }
+
+    #[test]
+    fn test_select_only_unknown_name() {
+        let config = config();
+        let only = vec!["roma".to_string()];
+        let result = select_portals(&config, &only, false, &HashMap::new(), now());
+        assert!(matches!(result, Err(AppError::ConfigError(_))));
+    }
+}
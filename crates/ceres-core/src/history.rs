@@ -0,0 +1,147 @@
+//! Search history.
+//!
+//! Searches run with `ceres search`, the HTTP API and the gRPC service are
+//! recorded in the `search_history` table with their filters, result count
+//! and top score. `ceres history search` lists them, and they are the raw
+//! material for query analytics and evaluation sets.
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use serde_json::{Map, Value};
+
+use crate::models::SearchResult;
+use crate::search::SearchFilters;
+
+/// Searches listed by `ceres history search` without `--limit`.
+pub const DEFAULT_HISTORY_LIMIT: usize = 20;
+
+/// Where a search was run from.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SearchSource {
+    /// `ceres search`
+    Cli,
+    /// The HTTP API of `ceres serve`
+    Api,
+    /// The gRPC service of `ceres serve`
+    Grpc,
+}
+
+impl SearchSource {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            SearchSource::Cli => "cli",
+            SearchSource::Api => "api",
+            SearchSource::Grpc => "grpc",
+        }
+    }
+}
+
+/// A search as kept in the `search_history` table.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct SearchRecord {
+    pub query: String,
+    /// Ranking mode: semantic, hybrid or text
+    pub mode: String,
+    /// Filters set besides the query, by name (see [`filters_json`])
+    pub filters: Value,
+    pub result_count: u32,
+    /// Score of the best result; `None` without results
+    pub top_score: Option<f32>,
+    /// [`SearchSource`] as text
+    pub source: String,
+    pub searched_at: DateTime<Utc>,
+}
+
+impl SearchRecord {
+    /// A search for `query` that returned `results`, now.
+    pub fn new(
+        query: &str,
+        mode: &str,
+        filters: &SearchFilters,
+        results: &[SearchResult],
+        source: SearchSource,
+    ) -> Self {
+        Self {
+            query: query.to_string(),
+            mode: mode.to_string(),
+            filters: filters_json(filters),
+            result_count: results.len() as u32,
+            top_score: results.iter().map(|r| r.similarity_score).reduce(f32::max),
+            source: source.as_str().to_string(),
+            searched_at: Utc::now(),
+        }
+    }
+}
+
+/// The filters that are set, as a JSON object keyed by filter name.
+///
+/// Dates are RFC 3339, metadata filters are written as given to `--where`
+/// and the bounding box is `[min_lon, min_lat, max_lon, max_lat]`.
+pub fn filters_json(filters: &SearchFilters) -> Value {
+    let mut object = Map::new();
+    let text = [
+        ("portal", &filters.portal),
+        ("theme", &filters.theme),
+        ("embedding_model", &filters.embedding_model),
+        ("format", &filters.format),
+        ("license", &filters.license),
+        ("language", &filters.language),
+        ("tag", &filters.tag),
+        ("organization", &filters.organization),
+    ];
+    for (name, value) in text {
+        if let Some(value) = value {
+            object.insert(name.to_string(), Value::from(value.as_str()));
+        }
+    }
+    let dates = [
+        ("updated_after", filters.updated_after),
+        ("updated_before", filters.updated_before),
+    ];
+    for (name, value) in dates {
+        if let Some(value) = value {
+            object.insert(name.to_string(), Value::from(value.to_rfc3339()));
+        }
+    }
+    if !filters.metadata.is_empty() {
+        let metadata = filters.metadata.iter().map(|f| Value::from(f.to_string()));
+        object.insert("where".to_string(), metadata.collect());
+    }
+    if let Some(b) = filters.bbox {
+        object.insert(
+            "bbox".to_string(),
+            Value::from(vec![b.min_lon, b.min_lat, b.max_lon, b.max_lat]),
+        );
+    }
+    Value::Object(object)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::metadata_filter::parse_metadata_filter;
+    use chrono::TimeZone;
+    use serde_json::json;
+
+    #[test]
+    fn test_filters_json() {
+        assert_eq!(filters_json(&SearchFilters::default()), json!({}));
+
+        let filters = SearchFilters {
+            portal: Some("https://dati.comune.milano.it".to_string()),
+            tag: Some("mobilita".to_string()),
+            updated_after: Some(Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap()),
+            metadata: vec![parse_metadata_filter("organization.name=comune-di-milano").unwrap()],
+            ..Default::default()
+        };
+        assert_eq!(
+            filters_json(&filters),
+            json!({
+                "portal": "https://dati.comune.milano.it",
+                "tag": "mobilita",
+                "updated_after": "2024-01-01T00:00:00+00:00",
+                "where": ["organization.name=comune-di-milano"],
+            })
+        );
+    }
+}
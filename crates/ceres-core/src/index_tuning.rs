@@ -0,0 +1,391 @@
+//! ANN index tuning for the pgvector embedding index.
+//!
+//! This module holds the pure logic behind `ceres index tune`: which
+//! HNSW/IVFFlat parameter combinations to benchmark for a given corpus size,
+//! how recall is measured against exact search, and how the best setting is
+//! picked. The database layer runs the actual benchmark. It also checks
+//! the dimensions `ceres index resize` may migrate the embedding column to.
+
+use std::fmt;
+use std::time::Duration;
+
+use crate::error::AppError;
+
+/// Name of the ANN index on `datasets.embedding`.
+///
+/// Matches the name PostgreSQL generates for the index created by the
+/// initial migration, so tuning replaces it in place.
+pub const EMBEDDING_INDEX_NAME: &str = "datasets_embedding_idx";
+
+/// Default recall target used when recommending a configuration.
+pub const DEFAULT_TARGET_RECALL: f64 = 0.95;
+
+/// Largest `vector` dimension pgvector can index with HNSW or IVFFlat.
+pub const MAX_INDEXED_DIMENSION: usize = 2000;
+
+/// Checks that the embedding column can be migrated to `dimension`.
+///
+/// # Errors
+///
+/// Returns `AppError::ConfigError` for zero, or for dimensions above
+/// [`MAX_INDEXED_DIMENSION`], which would leave search without an index.
+pub fn check_indexable_dimension(dimension: usize) -> Result<(), AppError> {
+    if dimension == 0 || dimension > MAX_INDEXED_DIMENSION {
+        return Err(AppError::ConfigError(format!(
+            "Cannot store {}-dimensional embeddings: the ANN index supports 1 to {} dimensions. \
+             Request fewer dimensions from the model (e.g. EMBEDDING_DIMENSION=1536 for \
+             text-embedding-3-large)",
+            dimension, MAX_INDEXED_DIMENSION
+        )));
+    }
+    Ok(())
+}
+
+/// Build-time parameters of an ANN index.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IndexBuildParams {
+    /// HNSW graph index.
+    Hnsw { m: u32, ef_construction: u32 },
+    /// IVFFlat inverted-list index.
+    IvfFlat { lists: u32 },
+}
+
+impl IndexBuildParams {
+    /// Returns the `USING ... WITH (...)` clause for `CREATE INDEX`.
+    pub fn using_clause(&self) -> String {
+        match self {
+            IndexBuildParams::Hnsw { m, ef_construction } => format!(
+                "USING hnsw (embedding vector_cosine_ops) WITH (m = {}, ef_construction = {})",
+                m, ef_construction
+            ),
+            IndexBuildParams::IvfFlat { lists } => format!(
+                "USING ivfflat (embedding vector_cosine_ops) WITH (lists = {})",
+                lists
+            ),
+        }
+    }
+
+    /// Returns the session statement that sets the query-time parameter.
+    ///
+    /// `value` is `hnsw.ef_search` for HNSW and `ivfflat.probes` for IVFFlat.
+    pub fn query_setting(&self, value: u32) -> String {
+        match self {
+            IndexBuildParams::Hnsw { .. } => format!("SET LOCAL hnsw.ef_search = {}", value),
+            IndexBuildParams::IvfFlat { .. } => format!("SET LOCAL ivfflat.probes = {}", value),
+        }
+    }
+
+    /// Name of the query-time parameter for this index type.
+    pub fn query_param_name(&self) -> &'static str {
+        match self {
+            IndexBuildParams::Hnsw { .. } => "hnsw.ef_search",
+            IndexBuildParams::IvfFlat { .. } => "ivfflat.probes",
+        }
+    }
+}
+
+impl fmt::Display for IndexBuildParams {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            IndexBuildParams::Hnsw { m, ef_construction } => {
+                write!(f, "hnsw(m={}, ef_construction={})", m, ef_construction)
+            }
+            IndexBuildParams::IvfFlat { lists } => write!(f, "ivfflat(lists={})", lists),
+        }
+    }
+}
+
+/// Which index families to include in the tuning grid.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IndexFamily {
+    Hnsw,
+    IvfFlat,
+    All,
+}
+
+/// One build configuration with the query-time values to try against it.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TuningCandidate {
+    pub build: IndexBuildParams,
+    pub query_values: Vec<u32>,
+}
+
+/// Builds the grid of candidates to benchmark for a corpus of `rows` embeddings.
+///
+/// pgvector defaults (m=16, ef_construction=64, ef_search=40) are always
+/// included as the baseline. Small corpora get cheaper graphs, large ones
+/// denser graphs; IVFFlat list counts follow the pgvector guidance of
+/// `rows / 1000` up to 1M rows and `sqrt(rows)` beyond. IVFFlat is skipped
+/// for corpora too small to train meaningful lists.
+///
+/// # Arguments
+/// * `rows` - Number of datasets with embeddings
+/// * `k` - Number of neighbours requested per query (lower bound for ef_search)
+/// * `family` - Which index types to include
+pub fn tuning_grid(rows: u64, k: u32, family: IndexFamily) -> Vec<TuningCandidate> {
+    let mut grid = Vec::new();
+
+    if matches!(family, IndexFamily::Hnsw | IndexFamily::All) {
+        let mut builds = vec![(16, 64)];
+        if rows < 50_000 {
+            builds.insert(0, (8, 32));
+        }
+        if rows >= 100_000 {
+            builds.push((24, 100));
+        }
+        if rows >= 1_000_000 {
+            builds.push((32, 128));
+        }
+
+        let ef_values: Vec<u32> = dedup_sorted(
+            [20, 40, 80, 160, 320]
+                .into_iter()
+                .map(|ef: u32| ef.max(k))
+                .collect(),
+        );
+
+        for (m, ef_construction) in builds {
+            grid.push(TuningCandidate {
+                build: IndexBuildParams::Hnsw { m, ef_construction },
+                query_values: ef_values.clone(),
+            });
+        }
+    }
+
+    if matches!(family, IndexFamily::IvfFlat | IndexFamily::All) && rows >= 1_000 {
+        let base_lists = if rows <= 1_000_000 {
+            (rows / 1_000).max(1)
+        } else {
+            (rows as f64).sqrt() as u64
+        } as u32;
+
+        let lists_values = dedup_sorted(vec![(base_lists / 2).max(1), base_lists, base_lists * 2]);
+
+        for lists in lists_values {
+            let sqrt = (lists as f64).sqrt().round().max(1.0) as u32;
+            let probes = dedup_sorted(
+                [1, sqrt / 2, sqrt, sqrt * 2]
+                    .into_iter()
+                    .map(|p| p.clamp(1, lists))
+                    .collect(),
+            );
+            grid.push(TuningCandidate {
+                build: IndexBuildParams::IvfFlat { lists },
+                query_values: probes,
+            });
+        }
+    }
+
+    grid
+}
+
+fn dedup_sorted(mut values: Vec<u32>) -> Vec<u32> {
+    values.sort_unstable();
+    values.dedup();
+    values
+}
+
+/// Computes recall@k of an approximate result list against exact ground truth.
+///
+/// Returns 1.0 when the ground truth is empty (nothing could be missed).
+pub fn recall_at_k<T: PartialEq>(truth: &[T], approx: &[T]) -> f64 {
+    if truth.is_empty() {
+        return 1.0;
+    }
+    let hits = truth.iter().filter(|t| approx.contains(t)).count();
+    hits as f64 / truth.len() as f64
+}
+
+/// Returns the given percentile (0-100) of a set of latencies.
+///
+/// Uses the nearest-rank method. Returns zero for an empty slice.
+pub fn latency_percentile(latencies: &[Duration], percentile: f64) -> Duration {
+    if latencies.is_empty() {
+        return Duration::ZERO;
+    }
+    let mut sorted = latencies.to_vec();
+    sorted.sort_unstable();
+    let rank = ((percentile / 100.0) * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+/// Benchmark result for one build/query parameter combination.
+#[derive(Debug, Clone)]
+pub struct TuningMeasurement {
+    /// Index build parameters
+    pub build: IndexBuildParams,
+    /// Query-time parameter value (ef_search or probes)
+    pub query_value: u32,
+    /// Mean recall@k over the sampled queries
+    pub recall: f64,
+    /// Median query latency
+    pub p50: Duration,
+    /// 95th percentile query latency
+    pub p95: Duration,
+    /// Time spent building the index
+    pub build_time: Duration,
+}
+
+/// Picks the recommended configuration from benchmark results.
+///
+/// Among measurements reaching `target_recall`, the one with the lowest p95
+/// latency wins. If none reaches the target, the highest-recall measurement
+/// is returned so the operator still gets the best available trade-off.
+pub fn recommend(
+    measurements: &[TuningMeasurement],
+    target_recall: f64,
+) -> Option<&TuningMeasurement> {
+    let meeting_target = measurements
+        .iter()
+        .filter(|m| m.recall >= target_recall)
+        .min_by(|a, b| a.p95.cmp(&b.p95).then(b.recall.total_cmp(&a.recall)));
+
+    meeting_target.or_else(|| {
+        measurements
+            .iter()
+            .max_by(|a, b| a.recall.total_cmp(&b.recall).then(b.p95.cmp(&a.p95)))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn measurement(recall: f64, p95_ms: u64) -> TuningMeasurement {
+        TuningMeasurement {
+            build: IndexBuildParams::Hnsw {
+                m: 16,
+                ef_construction: 64,
+            },
+            query_value: 40,
+            recall,
+            p50: Duration::from_millis(p95_ms / 2),
+            p95: Duration::from_millis(p95_ms),
+            build_time: Duration::ZERO,
+        }
+    }
+
+    #[test]
+    fn test_check_indexable_dimension() {
+        assert!(check_indexable_dimension(768).is_ok());
+        assert!(check_indexable_dimension(MAX_INDEXED_DIMENSION).is_ok());
+        assert!(check_indexable_dimension(0).is_err());
+        assert!(matches!(
+            check_indexable_dimension(3072),
+            Err(AppError::ConfigError(msg)) if msg.contains("3072")
+        ));
+    }
+
+    #[test]
+    fn test_using_clause() {
+        let hnsw = IndexBuildParams::Hnsw {
+            m: 16,
+            ef_construction: 64,
+        };
+        assert_eq!(
+            hnsw.using_clause(),
+            "USING hnsw (embedding vector_cosine_ops) WITH (m = 16, ef_construction = 64)"
+        );
+        assert_eq!(hnsw.query_setting(40), "SET LOCAL hnsw.ef_search = 40");
+
+        let ivf = IndexBuildParams::IvfFlat { lists: 100 };
+        assert!(ivf.using_clause().contains("lists = 100"));
+        assert_eq!(ivf.query_setting(10), "SET LOCAL ivfflat.probes = 10");
+    }
+
+    #[test]
+    fn test_grid_small_corpus_skips_ivfflat() {
+        let grid = tuning_grid(500, 10, IndexFamily::All);
+        assert!(grid
+            .iter()
+            .all(|c| matches!(c.build, IndexBuildParams::Hnsw { .. })));
+        // Small corpora also try a cheaper graph
+        assert!(grid.iter().any(|c| c.build
+            == IndexBuildParams::Hnsw {
+                m: 8,
+                ef_construction: 32
+            }));
+    }
+
+    #[test]
+    fn test_grid_includes_pgvector_defaults() {
+        let grid = tuning_grid(20_000, 10, IndexFamily::Hnsw);
+        let default = grid
+            .iter()
+            .find(|c| {
+                c.build
+                    == IndexBuildParams::Hnsw {
+                        m: 16,
+                        ef_construction: 64,
+                    }
+            })
+            .unwrap();
+        assert!(default.query_values.contains(&40));
+    }
+
+    #[test]
+    fn test_grid_ef_search_at_least_k() {
+        let grid = tuning_grid(20_000, 50, IndexFamily::Hnsw);
+        for candidate in grid {
+            assert!(candidate.query_values.iter().all(|&ef| ef >= 50));
+        }
+    }
+
+    #[test]
+    fn test_grid_ivfflat_lists_scale_with_rows() {
+        let grid = tuning_grid(200_000, 10, IndexFamily::IvfFlat);
+        let lists: Vec<u32> = grid
+            .iter()
+            .map(|c| match c.build {
+                IndexBuildParams::IvfFlat { lists } => lists,
+                _ => panic!("expected ivfflat"),
+            })
+            .collect();
+        assert_eq!(lists, vec![100, 200, 400]);
+        for candidate in &grid {
+            if let IndexBuildParams::IvfFlat { lists } = candidate.build {
+                assert!(candidate.query_values.iter().all(|&p| p >= 1 && p <= lists));
+            }
+        }
+    }
+
+    #[test]
+    fn test_recall_at_k() {
+        assert_eq!(recall_at_k(&[1, 2, 3, 4], &[1, 2, 3, 4]), 1.0);
+        assert_eq!(recall_at_k(&[1, 2, 3, 4], &[1, 2, 9, 8]), 0.5);
+        assert_eq!(recall_at_k::<i32>(&[], &[1]), 1.0);
+    }
+
+    #[test]
+    fn test_latency_percentile() {
+        let latencies: Vec<Duration> = (1..=100).map(Duration::from_millis).collect();
+        assert_eq!(
+            latency_percentile(&latencies, 50.0),
+            Duration::from_millis(50)
+        );
+        assert_eq!(
+            latency_percentile(&latencies, 95.0),
+            Duration::from_millis(95)
+        );
+        assert_eq!(latency_percentile(&[], 95.0), Duration::ZERO);
+    }
+
+    #[test]
+    fn test_recommend_prefers_fastest_meeting_target() {
+        let results = vec![
+            measurement(0.99, 30),
+            measurement(0.96, 10),
+            measurement(0.90, 5),
+        ];
+        let best = recommend(&results, 0.95).unwrap();
+        assert_eq!(best.p95, Duration::from_millis(10));
+    }
+
+    #[test]
+    fn test_recommend_falls_back_to_best_recall() {
+        let results = vec![measurement(0.80, 10), measurement(0.85, 20)];
+        let best = recommend(&results, 0.95).unwrap();
+        assert_eq!(best.recall, 0.85);
+        assert!(recommend(&[], 0.95).is_none());
+    }
+}
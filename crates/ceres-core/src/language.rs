@@ -0,0 +1,224 @@
+//! Dataset language detection.
+//!
+//! Multilingual portals mix languages in one index (dati.gov.it lists
+//! Italian, German and English datasets), and similarity-only ranking
+//! confuses them: a translated title can outrank a relevant dataset in the
+//! query's language. The language of each dataset is detected from its
+//! title and description at harvest and stored as an ISO 639-1 code, so
+//! search can be restricted to it and `stats` can break the index down.
+
+use whatlang::{Detector, Lang};
+
+use crate::error::AppError;
+
+/// Detections below this confidence are discarded.
+pub const MIN_LANGUAGE_CONFIDENCE: f64 = 0.5;
+
+/// Texts with fewer letters than this are too short to detect reliably.
+const MIN_LANGUAGE_LETTERS: usize = 10;
+
+/// Detects the language of a dataset from its title and description.
+///
+/// Returns the ISO 639-1 code (`"it"`, `"en"`, ...), or `None` if the text
+/// is too short or the detection is not confident enough.
+///
+/// # Examples
+///
+/// ```
+/// use ceres_core::language::detect_language;
+///
+/// let language = detect_language(
+///     "Qualità dell'aria",
+///     Some("Concentrazioni giornaliere di PM10 misurate dalle centraline della città"),
+/// );
+/// assert_eq!(language.as_deref(), Some("it"));
+/// assert_eq!(detect_language("PM10", None), None);
+/// ```
+pub fn detect_language(title: &str, description: Option<&str>) -> Option<String> {
+    let text = match description.map(str::trim).filter(|d| !d.is_empty()) {
+        Some(description) => format!("{}. {}", title.trim(), description),
+        None => title.trim().to_string(),
+    };
+    if text.chars().filter(|c| c.is_alphabetic()).count() < MIN_LANGUAGE_LETTERS {
+        return None;
+    }
+    let info = Detector::new().detect(&text)?;
+    (info.confidence() >= MIN_LANGUAGE_CONFIDENCE).then(|| iso_639_1(info.lang()).to_string())
+}
+
+/// Parses a language given as an ISO 639-1 or 639-3 code into the stored
+/// ISO 639-1 form.
+///
+/// # Errors
+///
+/// Returns `AppError::ConfigError` for unknown codes.
+///
+/// # Examples
+///
+/// ```
+/// use ceres_core::language::parse_language;
+///
+/// assert_eq!(parse_language("IT").unwrap(), "it");
+/// assert_eq!(parse_language("deu").unwrap(), "de");
+/// assert!(parse_language("xx").is_err());
+/// ```
+pub fn parse_language(code: &str) -> Result<String, AppError> {
+    let code = code.trim().to_lowercase();
+    let lang = match code.len() {
+        2 => Lang::all().iter().copied().find(|l| iso_639_1(*l) == code),
+        3 => Lang::from_code(code.as_str()),
+        _ => None,
+    };
+    lang.map(|l| iso_639_1(l).to_string()).ok_or_else(|| {
+        AppError::ConfigError(format!(
+            "Unknown language '{}': expected an ISO 639-1 code such as 'it' or 'en'",
+            code
+        ))
+    })
+}
+
+/// ISO 639-3 code of a stored ISO 639-1 code (`"it"` → `"ita"`), as used
+/// by the EU language vocabulary.
+///
+/// # Examples
+///
+/// ```
+/// use ceres_core::language::iso_639_3;
+///
+/// assert_eq!(iso_639_3("de"), Some("deu"));
+/// assert_eq!(iso_639_3("xx"), None);
+/// ```
+pub fn iso_639_3(code: &str) -> Option<&'static str> {
+    Lang::all()
+        .iter()
+        .find(|lang| iso_639_1(**lang) == code)
+        .map(|lang| lang.code())
+}
+
+/// ISO 639-1 code of a detected language.
+fn iso_639_1(lang: Lang) -> &'static str {
+    match lang {
+        Lang::Epo => "eo",
+        Lang::Eng => "en",
+        Lang::Rus => "ru",
+        Lang::Cmn => "zh",
+        Lang::Spa => "es",
+        Lang::Por => "pt",
+        Lang::Ita => "it",
+        Lang::Ben => "bn",
+        Lang::Fra => "fr",
+        Lang::Deu => "de",
+        Lang::Ukr => "uk",
+        Lang::Kat => "ka",
+        Lang::Ara => "ar",
+        Lang::Hin => "hi",
+        Lang::Jpn => "ja",
+        Lang::Heb => "he",
+        Lang::Yid => "yi",
+        Lang::Pol => "pl",
+        Lang::Amh => "am",
+        Lang::Jav => "jv",
+        Lang::Kor => "ko",
+        Lang::Nob => "nb",
+        Lang::Dan => "da",
+        Lang::Swe => "sv",
+        Lang::Fin => "fi",
+        Lang::Tur => "tr",
+        Lang::Nld => "nl",
+        Lang::Hun => "hu",
+        Lang::Ces => "cs",
+        Lang::Ell => "el",
+        Lang::Bul => "bg",
+        Lang::Bel => "be",
+        Lang::Mar => "mr",
+        Lang::Kan => "kn",
+        Lang::Ron => "ro",
+        Lang::Slv => "sl",
+        Lang::Hrv => "hr",
+        Lang::Srp => "sr",
+        Lang::Mkd => "mk",
+        Lang::Lit => "lt",
+        Lang::Lav => "lv",
+        Lang::Est => "et",
+        Lang::Tam => "ta",
+        Lang::Vie => "vi",
+        Lang::Urd => "ur",
+        Lang::Tha => "th",
+        Lang::Guj => "gu",
+        Lang::Uzb => "uz",
+        Lang::Pan => "pa",
+        Lang::Aze => "az",
+        Lang::Ind => "id",
+        Lang::Tel => "te",
+        Lang::Pes => "fa",
+        Lang::Mal => "ml",
+        Lang::Ori => "or",
+        Lang::Mya => "my",
+        Lang::Nep => "ne",
+        Lang::Sin => "si",
+        Lang::Khm => "km",
+        Lang::Tuk => "tk",
+        Lang::Aka => "ak",
+        Lang::Zul => "zu",
+        Lang::Sna => "sn",
+        Lang::Afr => "af",
+        Lang::Lat => "la",
+        Lang::Slk => "sk",
+        Lang::Cat => "ca",
+        Lang::Tgl => "tl",
+        Lang::Hye => "hy",
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_detect_language() {
+        let cases = [
+            (
+                "Orari delle linee di autobus",
+                Some("Fermate e orari del trasporto pubblico locale"),
+                "it",
+            ),
+            (
+                "Bus timetables",
+                Some("Stops and timetables of the local public transport network"),
+                "en",
+            ),
+            (
+                "Fahrpläne der Buslinien",
+                Some("Haltestellen und Fahrpläne des öffentlichen Nahverkehrs"),
+                "de",
+            ),
+            (
+                "Horaires des bus",
+                Some("Arrêts et horaires du réseau de transport public"),
+                "fr",
+            ),
+        ];
+        for (title, description, expected) in cases {
+            assert_eq!(
+                detect_language(title, description).as_deref(),
+                Some(expected),
+                "{}",
+                title
+            );
+        }
+    }
+
+    #[test]
+    fn test_detect_language_skips_short_text() {
+        assert_eq!(detect_language("DCIS 2024", Some(" ")), None);
+        assert_eq!(detect_language("", None), None);
+    }
+
+    #[test]
+    fn test_iso_639_1_codes_are_unique() {
+        let mut codes: Vec<&str> = Lang::all().iter().map(|l| iso_639_1(*l)).collect();
+        codes.sort_unstable();
+        codes.dedup();
+        assert_eq!(codes.len(), Lang::all().len());
+    }
+}
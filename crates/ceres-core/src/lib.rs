@@ -1,17 +1,53 @@
 //! Ceres Core - Domain types, error handling, and configuration.
 
+pub mod ask;
+pub mod audit;
+pub mod chunks;
+pub mod clustering;
 pub mod config;
+pub mod content_hash;
+pub mod dcat;
+pub mod enrichment;
 pub mod error;
+pub mod expansion;
+pub mod facets;
+pub mod harvest_filter;
+pub mod health;
+pub mod history;
+pub mod index_tuning;
+pub mod language;
+pub mod maintenance;
+pub mod metadata_filter;
 pub mod models;
+pub mod notify;
+pub mod organizations;
+pub mod portal_check;
+pub mod quality;
+pub mod registry;
+pub mod resources;
+pub mod schedule;
+pub mod search;
+pub mod snippet;
+pub mod spatial;
 pub mod sync;
+pub mod tags;
+pub mod watch;
 
 pub use config::{
-    default_config_path, load_portals_config, DbConfig, HttpConfig, PortalEntry, PortalsConfig,
-    SyncConfig,
+    add_portal, default_config_path, default_settings_path, load_portals_config, load_settings,
+    merge_portals, remove_portal, rewrite_portal_url, set_portal_enabled, DbConfig, EmailConfig,
+    HttpConfig, LockPolicy, NotificationConfig, NotificationSink, PortalEntry, PortalMerge,
+    PortalsConfig, Settings, SmtpSecurity, SyncConfig, WebhookConfig,
 };
 pub use error::AppError;
-pub use models::{DatabaseStats, Dataset, NewDataset, Portal, SearchResult};
+pub use models::{
+    DatabaseStats, Dataset, EmbeddingModelCount, FormatCount, LanguageCount, NewDataset, Portal,
+    PortalMigration, PortalQuality, PortalStats, SchemaMigration, SearchResult, TagCount,
+};
+pub use notify::{HarvestEvent, HarvestNotification};
 pub use sync::{
-    needs_reprocessing, BatchHarvestSummary, PortalHarvestResult, ReprocessingDecision,
-    SyncOutcome, SyncStats,
+    needs_reprocessing, BatchHarvestSummary, DatasetOutcomeRecord, FailureCounts, FailureKind,
+    FailureStage, HarvestCheckpoint, HarvestFailure, HarvestReport, HarvestSample,
+    PortalHarvestResult, PortalHarvestStatus, ReprocessingDecision, SampleMethod, StageDurations,
+    SyncOutcome, SyncStats, UpsertOutcome,
 };
@@ -0,0 +1,397 @@
+//! Disk budget reporting and retention for `ceres maintain` and `ceres prune`.
+//!
+//! This module holds the pure logic: parsing budgets, projecting growth
+//! from harvest history and deciding which retention policies to suggest.
+//! The database layer measures sizes and performs the pruning.
+
+use chrono::{DateTime, Duration, Utc};
+
+use crate::error::AppError;
+
+/// Share of the budget above which usage is reported as near the limit.
+pub const BUDGET_WARNING_RATIO: f64 = 0.8;
+
+/// How far back harvest history is used to estimate growth.
+pub const GROWTH_WINDOW_DAYS: i64 = 180;
+
+/// Minimum history a portal needs before its growth is extrapolated.
+const MIN_GROWTH_HISTORY_DAYS: f64 = 7.0;
+
+const DAYS_PER_MONTH: f64 = 30.44;
+
+/// Projections further out than this are reported as "not within"
+const PROJECTION_HORIZON_MONTHS: f64 = 120.0;
+
+/// Parses a size such as `50GB`, `512 MiB` or `1073741824`.
+///
+/// Units are binary (1 GB = 1024³ bytes), matching PostgreSQL's
+/// `pg_size_pretty`; `GiB` style suffixes are accepted as synonyms.
+///
+/// # Examples
+///
+/// ```
+/// use ceres_core::maintenance::parse_size;
+///
+/// assert_eq!(parse_size("50GB").unwrap(), 50 * 1024 * 1024 * 1024);
+/// assert_eq!(parse_size("1.5 MB").unwrap(), 1572864);
+/// ```
+///
+/// # Errors
+///
+/// Returns `AppError::ConfigError` for malformed sizes or unknown units.
+pub fn parse_size(input: &str) -> Result<u64, AppError> {
+    let input = input.trim();
+    let split = input
+        .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+        .unwrap_or(input.len());
+    let (number, unit) = input.split_at(split);
+
+    let invalid =
+        || AppError::ConfigError(format!("Invalid size '{}' (expected e.g. 50GB)", input));
+    let number: f64 = number.parse().map_err(|_| invalid())?;
+    let exponent = match unit.trim().to_ascii_uppercase().as_str() {
+        "" | "B" => 0,
+        "K" | "KB" | "KIB" => 1,
+        "M" | "MB" | "MIB" => 2,
+        "G" | "GB" | "GIB" => 3,
+        "T" | "TB" | "TIB" => 4,
+        _ => return Err(invalid()),
+    };
+
+    Ok((number * 1024_f64.powi(exponent)) as u64)
+}
+
+/// Parses an age such as `180d`, `12w` or `36h`.
+///
+/// # Examples
+///
+/// ```
+/// use ceres_core::maintenance::parse_age;
+///
+/// assert_eq!(parse_age("180d").unwrap().num_days(), 180);
+/// assert_eq!(parse_age("2w").unwrap().num_days(), 14);
+/// ```
+///
+/// # Errors
+///
+/// Returns `AppError::ConfigError` for malformed ages, unknown units and
+/// ages of zero.
+pub fn parse_age(input: &str) -> Result<Duration, AppError> {
+    let input = input.trim();
+    let split = input
+        .find(|c: char| !c.is_ascii_digit())
+        .unwrap_or(input.len());
+    let (number, unit) = input.split_at(split);
+
+    let invalid = || {
+        AppError::ConfigError(format!(
+            "Invalid age '{}' (expected e.g. 180d, 12w or 36h)",
+            input
+        ))
+    };
+    let number: i64 = number.parse().ok().filter(|&n| n > 0).ok_or_else(invalid)?;
+    let hours = match unit.trim().to_ascii_lowercase().as_str() {
+        "h" => 1,
+        "d" => 24,
+        "w" => 24 * 7,
+        _ => return Err(invalid()),
+    };
+
+    number
+        .checked_mul(hours)
+        .and_then(Duration::try_hours)
+        .ok_or_else(invalid)
+}
+
+/// Formats a byte count with a binary unit, e.g. `1.5 GB`.
+pub fn format_size(bytes: u64) -> String {
+    const UNITS: [&str; 5] = ["B", "KB", "MB", "GB", "TB"];
+    let mut value = bytes as f64;
+    let mut unit = 0;
+    while value >= 1024.0 && unit < UNITS.len() - 1 {
+        value /= 1024.0;
+        unit += 1;
+    }
+    if unit == 0 {
+        format!("{} B", bytes)
+    } else {
+        format!("{:.1} {}", value, UNITS[unit])
+    }
+}
+
+/// On-disk size of one table.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TableSize {
+    pub table: String,
+    /// Heap and TOAST size
+    pub data_bytes: u64,
+    pub index_bytes: u64,
+}
+
+impl TableSize {
+    pub fn total_bytes(&self) -> u64 {
+        self.data_bytes + self.index_bytes
+    }
+}
+
+/// Datasets a portal gained since its initial harvest, within the growth window.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PortalGrowth {
+    pub portal: String,
+    /// Start of the measured period
+    pub since: DateTime<Utc>,
+    /// Datasets first seen after `since`
+    pub added: i64,
+}
+
+/// Estimated datasets added per month across all portals.
+///
+/// Each portal's rate is measured from `since` to `now`; portals with less
+/// than a week of history are left out, as their initial import says
+/// nothing about ongoing growth.
+pub fn datasets_per_month(growth: &[PortalGrowth], now: DateTime<Utc>) -> f64 {
+    growth
+        .iter()
+        .filter_map(|g| {
+            let days = (now - g.since).num_seconds() as f64 / 86_400.0;
+            (days >= MIN_GROWTH_HISTORY_DAYS).then(|| g.added as f64 / days * DAYS_PER_MONTH)
+        })
+        .sum()
+}
+
+/// Disk growth extrapolated from recent harvests.
+#[derive(Debug, Clone, PartialEq)]
+pub struct GrowthProjection {
+    pub datasets_per_month: f64,
+    /// Average on-disk cost of a dataset, indexes included
+    pub bytes_per_dataset: u64,
+    pub bytes_per_month: u64,
+}
+
+impl GrowthProjection {
+    /// Projects growth from the size of the `datasets` table.
+    pub fn new(datasets_bytes: u64, datasets: i64, datasets_per_month: f64) -> Self {
+        let bytes_per_dataset = if datasets > 0 {
+            datasets_bytes / datasets as u64
+        } else {
+            0
+        };
+        Self {
+            datasets_per_month,
+            bytes_per_dataset,
+            bytes_per_month: (datasets_per_month * bytes_per_dataset as f64) as u64,
+        }
+    }
+
+    /// Months until `used` bytes grow to `budget`.
+    ///
+    /// Returns `Some(0.0)` if the budget is already exceeded and `None` if it
+    /// is not reached within ten years at the current rate.
+    pub fn months_until(&self, used: u64, budget: u64) -> Option<f64> {
+        if used >= budget {
+            return Some(0.0);
+        }
+        if self.bytes_per_month == 0 {
+            return None;
+        }
+        let months = (budget - used) as f64 / self.bytes_per_month as f64;
+        (months <= PROJECTION_HORIZON_MONTHS).then_some(months)
+    }
+}
+
+/// Disk usage relative to the budget.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BudgetStatus {
+    Within,
+    /// At least [`BUDGET_WARNING_RATIO`] of the budget is used
+    Near,
+    Exceeded,
+}
+
+impl BudgetStatus {
+    pub fn of(used: u64, budget: u64) -> Self {
+        if used >= budget {
+            BudgetStatus::Exceeded
+        } else if used as f64 >= budget as f64 * BUDGET_WARNING_RATIO {
+            BudgetStatus::Near
+        } else {
+            BudgetStatus::Within
+        }
+    }
+}
+
+/// A retention policy that would free disk space.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RetentionSuggestion {
+    /// Delete datasets missing from every harvest of their portal since `before`
+    PruneStaleDatasets {
+        before: DateTime<Utc>,
+        datasets: i64,
+        bytes: u64,
+    },
+    /// Delete failed watch deliveries older than `before`
+    PruneFailedDeliveries {
+        before: DateTime<Utc>,
+        deliveries: i64,
+        bytes: u64,
+    },
+    /// Store embeddings as half-precision `halfvec` (pgvector 0.7+)
+    QuantizeEmbeddings { bytes: u64 },
+}
+
+impl RetentionSuggestion {
+    /// Estimated bytes freed.
+    pub fn bytes(&self) -> u64 {
+        match self {
+            RetentionSuggestion::PruneStaleDatasets { bytes, .. }
+            | RetentionSuggestion::PruneFailedDeliveries { bytes, .. }
+            | RetentionSuggestion::QuantizeEmbeddings { bytes } => *bytes,
+        }
+    }
+
+    /// Returns true if `ceres maintain --apply` performs this suggestion.
+    ///
+    /// Quantization changes the column type and index operator class, so it
+    /// is left to a manual migration.
+    pub fn is_automatic(&self) -> bool {
+        !matches!(self, RetentionSuggestion::QuantizeEmbeddings { .. })
+    }
+}
+
+/// Start of the retention period: `months` months before `now`.
+pub fn retention_cutoff(now: DateTime<Utc>, months: u32) -> DateTime<Utc> {
+    now - Duration::seconds((months as f64 * DAYS_PER_MONTH * 86_400.0) as i64)
+}
+
+/// Builds the suggestions worth showing, largest savings first.
+///
+/// `stale` and `failed_deliveries` are row counts and sizes of what pruning
+/// at `before` would delete. `embedding_bytes` is the stored size of all
+/// embeddings, about half of which half-precision storage saves.
+/// Suggestions that free nothing are dropped.
+pub fn suggest_retention(
+    stale: (i64, u64),
+    failed_deliveries: (i64, u64),
+    embedding_bytes: u64,
+    before: DateTime<Utc>,
+) -> Vec<RetentionSuggestion> {
+    let mut suggestions = vec![
+        RetentionSuggestion::PruneStaleDatasets {
+            before,
+            datasets: stale.0,
+            bytes: stale.1,
+        },
+        RetentionSuggestion::PruneFailedDeliveries {
+            before,
+            deliveries: failed_deliveries.0,
+            bytes: failed_deliveries.1,
+        },
+        RetentionSuggestion::QuantizeEmbeddings {
+            bytes: embedding_bytes / 2,
+        },
+    ];
+    suggestions.retain(|s| s.bytes() > 0);
+    suggestions.sort_by_key(|s| std::cmp::Reverse(s.bytes()));
+    suggestions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn now() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2025, 6, 1, 0, 0, 0).unwrap()
+    }
+
+    #[test]
+    fn test_parse_size() {
+        assert_eq!(parse_size("50GB").unwrap(), 50 * 1024 * 1024 * 1024);
+        assert_eq!(parse_size("50 gib").unwrap(), 50 * 1024 * 1024 * 1024);
+        assert_eq!(parse_size("512M").unwrap(), 512 * 1024 * 1024);
+        assert_eq!(parse_size("1.5TB").unwrap(), 1536 * 1024 * 1024 * 1024);
+        assert_eq!(parse_size("4096").unwrap(), 4096);
+        assert!(parse_size("GB").is_err());
+        assert!(parse_size("50 PB").is_err());
+        assert!(parse_size("").is_err());
+    }
+
+    #[test]
+    fn test_parse_age() {
+        assert_eq!(parse_age("180d").unwrap(), Duration::days(180));
+        assert_eq!(parse_age("12W").unwrap(), Duration::weeks(12));
+        assert_eq!(parse_age("36h").unwrap(), Duration::hours(36));
+        assert!(parse_age("180").is_err());
+        assert!(parse_age("0d").is_err());
+        assert!(parse_age("6mo").is_err());
+        assert!(parse_age("d").is_err());
+    }
+
+    #[test]
+    fn test_format_size() {
+        assert_eq!(format_size(512), "512 B");
+        assert_eq!(format_size(1536), "1.5 KB");
+        assert_eq!(format_size(50 * 1024 * 1024 * 1024), "50.0 GB");
+    }
+
+    #[test]
+    fn test_datasets_per_month_skips_young_portals() {
+        let growth = vec![
+            PortalGrowth {
+                portal: "https://a.com".to_string(),
+                since: now() - Duration::days(61),
+                added: 200,
+            },
+            PortalGrowth {
+                portal: "https://new.com".to_string(),
+                since: now() - Duration::days(2),
+                added: 5000,
+            },
+        ];
+        let rate = datasets_per_month(&growth, now());
+        assert!((rate - 99.8).abs() < 0.5, "rate = {}", rate);
+    }
+
+    #[test]
+    fn test_projection_months_until() {
+        let gb = 1024 * 1024 * 1024;
+        // 1 KB per dataset, 1M datasets per month
+        let projection = GrowthProjection::new(10 * gb, 10 * 1024 * 1024, 1024.0 * 1024.0);
+        assert_eq!(projection.bytes_per_dataset, 1024);
+        assert_eq!(projection.bytes_per_month, gb);
+
+        let months = projection.months_until(40 * gb, 50 * gb).unwrap();
+        assert!((months - 10.0).abs() < 0.01);
+        assert_eq!(projection.months_until(60 * gb, 50 * gb), Some(0.0));
+
+        let flat = GrowthProjection::new(10 * gb, 10 * 1024 * 1024, 0.0);
+        assert_eq!(flat.months_until(40 * gb, 50 * gb), None);
+    }
+
+    #[test]
+    fn test_budget_status() {
+        assert_eq!(BudgetStatus::of(10, 100), BudgetStatus::Within);
+        assert_eq!(BudgetStatus::of(80, 100), BudgetStatus::Near);
+        assert_eq!(BudgetStatus::of(100, 100), BudgetStatus::Exceeded);
+    }
+
+    #[test]
+    fn test_suggest_retention_orders_by_savings() {
+        let before = retention_cutoff(now(), 12);
+        let suggestions = suggest_retention((10, 1000), (0, 0), 4000, before);
+
+        assert_eq!(suggestions.len(), 2);
+        assert_eq!(
+            suggestions[0],
+            RetentionSuggestion::QuantizeEmbeddings { bytes: 2000 }
+        );
+        assert!(!suggestions[0].is_automatic());
+        assert!(suggestions[1].is_automatic());
+    }
+
+    #[test]
+    fn test_retention_cutoff() {
+        let cutoff = retention_cutoff(now(), 12);
+        assert_eq!((now() - cutoff).num_days(), 365);
+    }
+}
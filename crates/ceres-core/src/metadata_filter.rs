@@ -0,0 +1,311 @@
+//! Filters on dataset metadata (`--where`).
+//!
+//! CKAN metadata carries much more than title and description: publisher,
+//! tags, license, temporal coverage and portal-specific extras. A filter
+//! such as `organization.name=comune-di-milano` selects datasets by any of
+//! these fields. It compiles to a SQL/JSON path predicate, matched with the
+//! `@@` operator that the `jsonb_path_ops` index on `metadata` supports.
+//!
+//! Supported forms:
+//!
+//! | Filter            | Matches datasets where                           |
+//! |-------------------|--------------------------------------------------|
+//! | `path=value`      | the field equals `value`                         |
+//! | `path!=value`     | the field is missing or differs from `value`     |
+//! | `path~value`      | the field contains `value`, ignoring case        |
+//! | `path`            | the field exists                                 |
+//!
+//! Paths are dot-separated keys, optionally prefixed with `metadata.`.
+//! Arrays along the path are searched element by element, so
+//! `tags.name=ambiente` matches a dataset with any tag named `ambiente`.
+
+use std::fmt;
+
+use serde_json::Value;
+
+use crate::error::AppError;
+
+/// Comparison of a [`MetadataFilter`].
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MetadataOp {
+    /// The field equals the value
+    Eq,
+    /// The field is missing or differs from the value
+    NotEq,
+    /// The field contains the value as a substring, ignoring case
+    Contains,
+    /// The field exists
+    Exists,
+}
+
+/// A condition on a dataset's JSON metadata.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct MetadataFilter {
+    /// Keys from the metadata root to the field
+    pub path: Vec<String>,
+    pub op: MetadataOp,
+    /// Compared value; empty for [`MetadataOp::Exists`]
+    pub value: String,
+}
+
+impl MetadataFilter {
+    /// Parses a filter such as `organization.name=comune-di-milano`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `AppError::ConfigError` if the path is empty or has an empty
+    /// key.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ceres_core::metadata_filter::{MetadataFilter, MetadataOp};
+    ///
+    /// let filter = MetadataFilter::parse("metadata.organization.name=comune-di-milano").unwrap();
+    /// assert_eq!(filter.path, vec!["organization", "name"]);
+    /// assert_eq!(filter.op, MetadataOp::Eq);
+    /// assert_eq!(filter.jsonpath(), r#"$."organization"."name" == "comune-di-milano""#);
+    /// ```
+    pub fn parse(input: &str) -> Result<Self, AppError> {
+        let (path, op, value) = match input.find(['=', '!', '~']) {
+            Some(i) if input[i..].starts_with("!=") => {
+                (&input[..i], MetadataOp::NotEq, &input[i + 2..])
+            }
+            Some(i) if input[i..].starts_with('=') => {
+                (&input[..i], MetadataOp::Eq, &input[i + 1..])
+            }
+            Some(i) if input[i..].starts_with('~') => {
+                (&input[..i], MetadataOp::Contains, &input[i + 1..])
+            }
+            Some(_) => {
+                return Err(AppError::ConfigError(format!(
+                    "Invalid metadata filter '{}': use path=value, path!=value, path~value or path",
+                    input
+                )))
+            }
+            None => (input, MetadataOp::Exists, ""),
+        };
+
+        let path = path.trim();
+        let path = path.strip_prefix("metadata.").unwrap_or(path);
+        let keys: Vec<String> = path.split('.').map(|k| k.trim().to_string()).collect();
+        if keys.iter().any(String::is_empty) {
+            return Err(AppError::ConfigError(format!(
+                "Invalid metadata filter '{}': expected a dot-separated path such as organization.name",
+                input
+            )));
+        }
+
+        Ok(Self {
+            path: keys,
+            op,
+            value: value.trim().to_string(),
+        })
+    }
+
+    /// SQL/JSON path predicate testing the field.
+    ///
+    /// For [`MetadataOp::NotEq`] this is the equality predicate, which the
+    /// caller negates (see [`negated`](Self::negated)), so datasets without
+    /// the field match too.
+    pub fn jsonpath(&self) -> String {
+        let path: String = self
+            .path
+            .iter()
+            .map(|key| format!(".{}", quote(key)))
+            .collect();
+        let path = format!("${}", path);
+
+        match self.op {
+            MetadataOp::Exists => format!("exists({})", path),
+            MetadataOp::Contains => {
+                format!("{} like_regex {} flag \"iq\"", path, quote(&self.value))
+            }
+            MetadataOp::Eq | MetadataOp::NotEq => {
+                let mut predicate = format!("{} == {}", path, quote(&self.value));
+                // Unquoted numbers and booleans in the metadata compare as such
+                if let Some(literal) = json_literal(&self.value) {
+                    predicate.push_str(&format!(" || {} == {}", path, literal));
+                }
+                predicate
+            }
+        }
+    }
+
+    /// Returns true if datasets matching [`jsonpath`](Self::jsonpath) are
+    /// excluded rather than selected.
+    pub fn negated(&self) -> bool {
+        self.op == MetadataOp::NotEq
+    }
+
+    /// Tests `metadata` in process, for stores without SQL/JSON paths.
+    ///
+    /// Follows [`jsonpath`](Self::jsonpath) in lax mode: arrays along the
+    /// path are searched element by element, and [`MetadataOp::NotEq`]
+    /// matches when no element equals the value.
+    pub fn matches(&self, metadata: &Value) -> bool {
+        let mut fields = vec![metadata];
+        for key in &self.path {
+            fields = fields
+                .into_iter()
+                .flat_map(elements)
+                .filter_map(|field| field.get(key))
+                .collect();
+        }
+        if self.op == MetadataOp::Exists {
+            return !fields.is_empty();
+        }
+
+        let mut leaves = fields.into_iter().flat_map(elements);
+        match self.op {
+            MetadataOp::Eq => leaves.any(|leaf| self.equals(leaf)),
+            MetadataOp::NotEq => !leaves.any(|leaf| self.equals(leaf)),
+            MetadataOp::Contains => {
+                let needle = self.value.to_lowercase();
+                leaves.any(|leaf| {
+                    leaf.as_str()
+                        .is_some_and(|s| s.to_lowercase().contains(&needle))
+                })
+            }
+            MetadataOp::Exists => unreachable!("handled above"),
+        }
+    }
+
+    /// Returns true if a field equals the value, as a string or as the
+    /// number or boolean the value spells.
+    fn equals(&self, field: &Value) -> bool {
+        match field {
+            Value::String(s) => *s == self.value,
+            Value::Number(n) => self
+                .value
+                .parse::<f64>()
+                .is_ok_and(|v| n.as_f64() == Some(v)),
+            Value::Bool(b) => self.value.parse::<bool>() == Ok(*b),
+            _ => false,
+        }
+    }
+}
+
+/// The elements of an array, or the value itself.
+fn elements(value: &Value) -> Vec<&Value> {
+    match value {
+        Value::Array(items) => items.iter().collect(),
+        other => vec![other],
+    }
+}
+
+impl fmt::Display for MetadataFilter {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let path = self.path.join(".");
+        match self.op {
+            MetadataOp::Eq => write!(f, "{}={}", path, self.value),
+            MetadataOp::NotEq => write!(f, "{}!={}", path, self.value),
+            MetadataOp::Contains => write!(f, "{}~{}", path, self.value),
+            MetadataOp::Exists => write!(f, "{}", path),
+        }
+    }
+}
+
+/// Parses a filter for clap's `value_parser`.
+pub fn parse_metadata_filter(input: &str) -> Result<MetadataFilter, AppError> {
+    MetadataFilter::parse(input)
+}
+
+/// Quotes a key or value as a SQL/JSON path string literal.
+fn quote(text: &str) -> String {
+    let mut quoted = String::with_capacity(text.len() + 2);
+    quoted.push('"');
+    for c in text.chars() {
+        match c {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\t' => quoted.push_str("\\t"),
+            c => quoted.push(c),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// The value as a JSON number or boolean literal, if it is one.
+fn json_literal(value: &str) -> Option<String> {
+    match serde_json::from_str::<serde_json::Value>(value) {
+        Ok(v @ (serde_json::Value::Number(_) | serde_json::Value::Bool(_))) => Some(v.to_string()),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_parse_operators() {
+        let parse = |s: &str| MetadataFilter::parse(s).unwrap();
+        assert_eq!(parse("license_id!=cc-by").op, MetadataOp::NotEq);
+        assert_eq!(parse("notes~PM10").op, MetadataOp::Contains);
+        let exists = parse("metadata.extras");
+        assert_eq!(
+            (exists.op, exists.path),
+            (MetadataOp::Exists, vec!["extras".to_string()])
+        );
+
+        // The first operator splits; later ones belong to the value
+        let filter = parse("url=https://x.it/?a=b");
+        assert_eq!(filter.value, "https://x.it/?a=b");
+        assert_eq!(filter.to_string(), "url=https://x.it/?a=b");
+    }
+
+    #[test]
+    fn test_parse_rejects_bad_paths() {
+        for input in ["=x", "organization..name=x", "name!x", ""] {
+            assert!(
+                matches!(MetadataFilter::parse(input), Err(AppError::ConfigError(_))),
+                "{}",
+                input
+            );
+        }
+    }
+
+    #[test]
+    fn test_jsonpath_escapes_input() {
+        let filter = MetadataFilter::parse(r#"a"b=say "hi" \ bye"#).unwrap();
+        assert_eq!(filter.jsonpath(), r#"$."a\"b" == "say \"hi\" \\ bye""#);
+
+        let filter = MetadataFilter::parse("num_resources=3").unwrap();
+        assert_eq!(
+            filter.jsonpath(),
+            r#"$."num_resources" == "3" || $."num_resources" == 3"#
+        );
+        let filter = MetadataFilter::parse("notes~a.b").unwrap();
+        assert_eq!(filter.jsonpath(), r#"$."notes" like_regex "a.b" flag "iq""#);
+        assert_eq!(
+            MetadataFilter::parse("extras").unwrap().jsonpath(),
+            r#"exists($."extras")"#
+        );
+    }
+
+    #[test]
+    fn test_matches_in_process() {
+        let metadata = serde_json::json!({
+            "organization": {"name": "comune-di-milano"},
+            "tags": [{"name": "ambiente"}, {"name": "aria"}],
+            "num_resources": 3,
+            "private": false,
+            "notes": "Rilevazioni PM10"
+        });
+        let matches = |s: &str| MetadataFilter::parse(s).unwrap().matches(&metadata);
+        assert!(matches("organization.name=comune-di-milano"));
+        assert!(matches("tags.name=aria"));
+        assert!(!matches("tags.name=rumore"));
+        assert!(matches("tags.name!=rumore"));
+        assert!(!matches("tags.name!=aria"));
+        assert!(matches("license_id!=cc-by"));
+        assert!(matches("num_resources=3"));
+        assert!(matches("private=false"));
+        assert!(matches("notes~pm10"));
+        assert!(matches("tags"));
+        assert!(!matches("extras"));
+    }
+}
@@ -6,6 +6,15 @@ use sqlx::prelude::FromRow;
 use sqlx::types::Json;
 use uuid::Uuid;
 
+use crate::audit::parse_ckan_timestamp;
+use crate::content_hash::{content_hash, HashField};
+use crate::language::detect_language;
+use crate::organizations::DatasetOrganization;
+use crate::quality::quality_score;
+use crate::resources::{dataset_resources, DatasetResource};
+use crate::spatial::{dataset_bbox, BoundingBox};
+use crate::tags::dataset_tags;
+
 /// Complete representation of a row from the 'datasets' table.
 ///
 /// This structure represents a persisted dataset with all database fields,
@@ -20,10 +29,17 @@ use uuid::Uuid;
 /// * `url` - Public landing page URL for the dataset
 /// * `title` - Human-readable dataset title
 /// * `description` - Optional detailed description
-/// * `embedding` - Optional 1536-dimensional vector for semantic search
+/// * `embedding` - Optional vector for semantic search (768 dimensions unless resized)
 /// * `metadata` - Additional metadata stored as JSONB
+/// * `formats` - Distinct normalized resource formats (e.g. `CSV`, `JSON`)
 /// * `first_seen_at` - Timestamp when the dataset was first indexed
 /// * `last_updated_at` - Timestamp of the most recent update
+/// * `content_hash` - SHA-256 content hash for delta detection
+/// * `embedding_model` - Embedding model that produced `embedding`
+/// * `embedded_at` - Timestamp when `embedding` was generated
+/// * `modified_at` - Last modification reported by the source portal
+/// * `language` - ISO 639-1 code detected from title and description
+/// * `quality` - Metadata quality score from 0 to 1
 #[derive(Debug, FromRow, Serialize, Clone)]
 pub struct Dataset {
     /// Unique identifier (UUID) generated by the database
@@ -39,18 +55,30 @@ pub struct Dataset {
     /// Optional detailed description
     pub description: Option<String>,
 
-    /// Optional 1536-dimensional vector for semantic search (pgvector type)
+    /// Optional vector for semantic search (pgvector type, 768 dimensions unless resized)
     pub embedding: Option<Vector>,
 
     /// Additional metadata stored as JSONB
     pub metadata: Json<serde_json::Value>,
+    /// Distinct normalized resource formats (e.g. `CSV`, `JSON`)
+    pub formats: Vec<String>,
 
     /// Timestamp when the dataset was first indexed
     pub first_seen_at: DateTime<Utc>,
     /// Timestamp of the most recent update
     pub last_updated_at: DateTime<Utc>,
-    /// SHA-256 hash of title + description for delta detection
+    /// SHA-256 content hash for delta detection (see [`crate::content_hash`])
     pub content_hash: Option<String>,
+    /// Embedding model that produced `embedding`
+    pub embedding_model: Option<String>,
+    /// Timestamp when `embedding` was generated
+    pub embedded_at: Option<DateTime<Utc>>,
+    /// Last modification reported by the source portal (CKAN `metadata_modified`)
+    pub modified_at: Option<DateTime<Utc>>,
+    /// ISO 639-1 code detected from title and description (see [`crate::language`])
+    pub language: Option<String>,
+    /// Metadata quality score from 0 to 1 (see [`crate::quality`])
+    pub quality: Option<f32>,
 }
 
 /// Data Transfer Object for inserting or updating datasets.
@@ -76,8 +104,17 @@ pub struct Dataset {
 ///     title: title.to_string(),
 ///     description,
 ///     embedding: None,
+///     embedding_model: None,
 ///     metadata: json!({"tags": ["open-data", "italy"]}),
+///     formats: vec!["CSV".to_string()],
 ///     content_hash,
+///     modified_at: None,
+///     language: Some("en".to_string()),
+///     bbox: None,
+///     quality: None,
+///     tags: vec!["open data".to_string()],
+///     organization: None,
+///     resources: Vec::new(),
 /// };
 ///
 /// assert_eq!(dataset.title, "My Dataset");
@@ -93,8 +130,17 @@ pub struct Dataset {
 /// * `title` - Human-readable dataset title
 /// * `description` - Optional detailed description
 /// * `embedding` - Optional vector of 768 floats (pgvector)
+/// * `embedding_model` - Model that produced `embedding`
 /// * `metadata` - Additional metadata as JSON
-/// * `content_hash` - SHA-256 hash of title + description for delta detection
+/// * `formats` - Distinct normalized resource formats
+/// * `content_hash` - SHA-256 hash of title, description and the configured
+///   metadata fields for delta detection
+/// * `modified_at` - Last modification reported by the source portal
+/// * `language` - ISO 639-1 code detected from title and description
+/// * `bbox` - Geographic extent read from the `spatial` extra
+/// * `quality` - Metadata quality score from 0 to 1
+/// * `tags` - Normalized CKAN tags
+/// * `organization` - CKAN organization (publisher)
 #[derive(Debug, Serialize, Clone)]
 pub struct NewDataset {
     /// Original identifier from the source portal
@@ -109,17 +155,90 @@ pub struct NewDataset {
     pub description: Option<String>,
     /// Optional vector of 768 floats (converted to pgvector on storage)
     pub embedding: Option<Vector>,
+    /// Embedding model that produced `embedding` (set together with it)
+    pub embedding_model: Option<String>,
     /// Additional metadata as JSON
     pub metadata: serde_json::Value,
-    /// SHA-256 hash of title + description for delta detection
+    /// Distinct normalized resource formats (see [`NewDataset::extract_formats`])
+    pub formats: Vec<String>,
+    /// SHA-256 hash of title, description and the metadata fields of
+    /// [`SyncConfig::hash_fields`](crate::SyncConfig) for delta detection
+    /// (see [`crate::content_hash`])
     pub content_hash: String,
+    /// Last modification reported by the source portal (CKAN `metadata_modified`)
+    pub modified_at: Option<DateTime<Utc>>,
+    /// ISO 639-1 code detected from title and description (see [`crate::language`])
+    pub language: Option<String>,
+    /// Geographic extent read from the `spatial` extra (see [`crate::spatial`])
+    pub bbox: Option<BoundingBox>,
+    /// Metadata quality score from 0 to 1 (see [`crate::quality`])
+    pub quality: Option<f32>,
+    /// Normalized CKAN tags (see [`crate::tags`])
+    pub tags: Vec<String>,
+    /// CKAN organization (publisher)
+    pub organization: Option<DatasetOrganization>,
+    /// CKAN resources, in package order
+    pub resources: Vec<DatasetResource>,
 }
 
 impl NewDataset {
+    /// Builds a dataset from its identity, text and CKAN package metadata,
+    /// deriving formats, tags, organization, resources, spatial extent,
+    /// language, quality and content hash as harvests do, the hash covering
+    /// `hash_fields` besides title and description. The dataset has no
+    /// embedding.
+    pub fn from_metadata(
+        original_id: String,
+        source_portal: String,
+        url: String,
+        title: String,
+        description: Option<String>,
+        metadata: serde_json::Value,
+        hash_fields: &[HashField],
+    ) -> Self {
+        let formats = Self::extract_formats(&metadata);
+        let modified_at = metadata["metadata_modified"]
+            .as_str()
+            .and_then(parse_ckan_timestamp);
+
+        let language = detect_language(&title, description.as_deref());
+        let bbox = dataset_bbox(&metadata);
+        let tags = dataset_tags(&metadata);
+        let organization = DatasetOrganization::from_metadata(&metadata);
+        let resources = dataset_resources(&metadata);
+        let quality = quality_score(description.as_deref(), &metadata, modified_at);
+
+        // Compute content hash for delta detection
+        let content_hash = content_hash(&title, description.as_deref(), &metadata, hash_fields);
+
+        Self {
+            original_id,
+            source_portal,
+            url,
+            title,
+            description,
+            embedding: None,
+            embedding_model: None,
+            metadata,
+            formats,
+            content_hash,
+            modified_at,
+            language,
+            bbox,
+            quality: Some(quality),
+            tags,
+            organization,
+            resources,
+        }
+    }
+
     /// Computes a SHA-256 hash of the content (title + description) for delta detection.
     ///
     /// This hash is used to determine if the dataset content has changed since
-    /// the last harvest, avoiding unnecessary embedding regeneration.
+    /// the last harvest, avoiding unnecessary embedding regeneration. It also
+    /// keys the embedding cache, as it covers exactly the embedded text;
+    /// harvests configured to hash more fields use
+    /// [`content_hash`](crate::content_hash::content_hash) for delta detection.
     ///
     /// # Arguments
     ///
@@ -136,6 +255,46 @@ impl NewDataset {
         hasher.update(content.as_bytes());
         format!("{:x}", hasher.finalize())
     }
+
+    /// Normalizes a resource format: trimmed, MIME subtype only, no leading
+    /// dot, upper case. Returns `None` for blank formats.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ceres_core::NewDataset;
+    ///
+    /// assert_eq!(NewDataset::normalize_format(" .csv").as_deref(), Some("CSV"));
+    /// assert_eq!(NewDataset::normalize_format("application/json").as_deref(), Some("JSON"));
+    /// assert_eq!(NewDataset::normalize_format("  "), None);
+    /// ```
+    pub fn normalize_format(format: &str) -> Option<String> {
+        // Must match the backfill in migrations/202601220001_dataset_formats.sql
+        let format = format
+            .trim()
+            .rsplit('/')
+            .next()
+            .unwrap_or_default()
+            .trim_start_matches('.')
+            .to_uppercase();
+        (!format.is_empty()).then_some(format)
+    }
+
+    /// Extracts the distinct normalized formats of a CKAN package's
+    /// `resources`, sorted.
+    pub fn extract_formats(metadata: &serde_json::Value) -> Vec<String> {
+        let mut formats: Vec<String> = metadata
+            .get("resources")
+            .and_then(|r| r.as_array())
+            .into_iter()
+            .flatten()
+            .filter_map(|resource| resource.get("format")?.as_str())
+            .filter_map(Self::normalize_format)
+            .collect();
+        formats.sort();
+        formats.dedup();
+        formats
+    }
 }
 
 /// Result of a semantic search with similarity score.
@@ -171,6 +330,7 @@ pub struct SearchResult {
 /// Provides an overview of the database state, useful for dashboards
 /// and monitoring systems.
 #[derive(Debug, Serialize, Clone)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
 pub struct DatabaseStats {
     /// Total number of datasets in the database
     pub total_datasets: i64,
@@ -180,6 +340,137 @@ pub struct DatabaseStats {
     pub total_portals: i64,
     /// Timestamp of the last update
     pub last_update: Option<DateTime<Utc>>,
+    /// Most common resource formats, most frequent first
+    pub formats: Vec<FormatCount>,
+    /// Datasets per detected language, most frequent first
+    pub languages: Vec<LanguageCount>,
+    /// Metadata quality per portal, lowest average first
+    pub quality: Vec<PortalQuality>,
+    /// Embedded datasets per embedding model, most used first
+    pub embedding_models: Vec<EmbeddingModelCount>,
+    /// Per-portal breakdown, least recently harvested first
+    pub portals: Vec<PortalStats>,
+}
+
+/// Dataset counts and harvest history of a portal.
+#[derive(Debug, Serialize, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct PortalStats {
+    /// Source portal URL
+    pub portal: String,
+    /// Datasets indexed from the portal
+    pub datasets: i64,
+    /// Datasets with an embedding
+    pub with_embeddings: i64,
+    /// Most recent dataset update
+    pub last_update: Option<DateTime<Utc>>,
+    /// Most recent successful harvest, if harvest health is tracked
+    pub last_harvest_at: Option<DateTime<Utc>>,
+    /// Harvests recorded for the portal
+    pub harvests: i64,
+    /// Failed harvests among them
+    pub failed_harvests: i64,
+}
+
+impl PortalStats {
+    /// Share of datasets with an embedding, from 0 to 1.
+    pub fn embedding_coverage(&self) -> f64 {
+        if self.datasets == 0 {
+            0.0
+        } else {
+            self.with_embeddings as f64 / self.datasets as f64
+        }
+    }
+
+    /// Share of recorded harvests that failed, or `None` without harvests.
+    pub fn failure_rate(&self) -> Option<f64> {
+        (self.harvests > 0).then(|| self.failed_harvests as f64 / self.harvests as f64)
+    }
+}
+
+/// Number of datasets and resources in a resource format.
+#[derive(Debug, Serialize, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct FormatCount {
+    /// Normalized format (e.g. `CSV`)
+    pub format: String,
+    /// Datasets with at least one resource in this format
+    pub datasets: i64,
+    /// Resources in this format
+    pub resources: i64,
+    /// Total size in bytes of the resources reporting one
+    pub total_size: i64,
+}
+
+/// Number of datasets in a language.
+#[derive(Debug, Serialize, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct LanguageCount {
+    /// ISO 639-1 code, or `None` for datasets without a detected language
+    pub language: Option<String>,
+    /// Datasets in this language
+    pub datasets: i64,
+}
+
+/// Number of datasets with a tag.
+#[derive(Debug, Serialize, Clone, PartialEq, Eq)]
+pub struct TagCount {
+    /// Normalized tag
+    pub tag: String,
+    /// Datasets with this tag
+    pub datasets: i64,
+}
+
+/// Metadata quality of a portal's datasets.
+#[derive(Debug, Serialize, Clone, PartialEq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct PortalQuality {
+    /// Source portal URL
+    pub portal: String,
+    /// Datasets with a quality score
+    pub datasets: i64,
+    /// Average quality score, from 0 to 1
+    pub average: f64,
+    /// Datasets scoring below [`LOW_QUALITY`](crate::quality::LOW_QUALITY)
+    pub low: i64,
+}
+
+/// Number of datasets embedded by a model.
+#[derive(Debug, Serialize, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "openapi", derive(utoipa::ToSchema))]
+pub struct EmbeddingModelCount {
+    /// Embedding model identifier (e.g. `text-embedding-004`)
+    pub model: String,
+    /// Datasets whose embedding this model produced
+    pub datasets: i64,
+    /// Portals with at least one such dataset
+    pub portals: i64,
+    /// When the oldest of these embeddings was generated
+    pub first_embedded_at: Option<DateTime<Utc>>,
+    /// When the newest of these embeddings was generated
+    pub last_embedded_at: Option<DateTime<Utc>>,
+}
+
+/// Rows rewritten by a portal URL migration.
+#[derive(Debug, Serialize, Clone, Default, PartialEq, Eq)]
+pub struct PortalMigration {
+    /// Datasets moved to the new portal URL
+    pub datasets: u64,
+    /// Dataset landing page URLs rewritten to the new domain
+    pub dataset_urls: u64,
+    /// Watches restricted to the portal
+    pub watches: u64,
+    /// Whether the portal's health record was moved
+    pub health_moved: bool,
+}
+
+/// A schema migration shipped with Ceres.
+#[derive(Debug, Serialize, Clone, PartialEq, Eq)]
+pub struct SchemaMigration {
+    /// Version, the timestamp prefix of the migration file
+    pub version: i64,
+    /// Description, from the rest of the file name
+    pub description: String,
 }
 
 /// Portal configured in portals.toml.
@@ -229,6 +520,24 @@ fn default_enabled() -> bool {
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_portal_stats_rates() {
+        let mut stats = PortalStats {
+            portal: "https://dati.gov.it".to_string(),
+            datasets: 4,
+            with_embeddings: 3,
+            last_update: None,
+            last_harvest_at: None,
+            harvests: 0,
+            failed_harvests: 0,
+        };
+        assert_eq!(stats.embedding_coverage(), 0.75);
+        assert_eq!(stats.failure_rate(), None);
+        stats.harvests = 8;
+        stats.failed_harvests = 2;
+        assert_eq!(stats.failure_rate(), Some(0.25));
+    }
+
     #[test]
     fn test_portal_default_enabled() {
         let json = r#"{
@@ -254,8 +563,17 @@ mod tests {
             title: title.to_string(),
             description,
             embedding: None,
+            embedding_model: None,
             metadata: serde_json::json!({"key": "value"}),
+            formats: Vec::new(),
             content_hash,
+            modified_at: None,
+            language: None,
+            bbox: None,
+            quality: None,
+            tags: Vec::new(),
+            organization: None,
+            resources: Vec::new(),
         };
 
         assert_eq!(dataset.original_id, "test-123");
@@ -293,4 +611,30 @@ mod tests {
         let hash2 = NewDataset::compute_content_hash("A", Some("BC"));
         assert_ne!(hash1, hash2);
     }
+
+    #[test]
+    fn test_extract_formats() {
+        let metadata = serde_json::json!({
+            "resources": [
+                {"format": "csv"},
+                {"format": "CSV"},
+                {"format": "text/csv"},
+                {"format": ".zip"},
+                {"format": "WMS "},
+                {"format": ""},
+                {"format": null},
+                {"url": "https://example.com/no-format"}
+            ]
+        });
+        assert_eq!(
+            NewDataset::extract_formats(&metadata),
+            vec!["CSV", "WMS", "ZIP"]
+        );
+    }
+
+    #[test]
+    fn test_extract_formats_without_resources() {
+        assert!(NewDataset::extract_formats(&serde_json::json!({})).is_empty());
+        assert!(NewDataset::extract_formats(&serde_json::json!({"resources": "x"})).is_empty());
+    }
 }
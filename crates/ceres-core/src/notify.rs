@@ -0,0 +1,274 @@
+//! Harvest notifications delivered to webhooks and notification sinks.
+//!
+//! When a harvest finishes, Ceres can POST a JSON payload describing the run
+//! to the webhook URLs listed in `portals.toml`:
+//!
+//! ```toml
+//! [[webhooks]]
+//! url = "https://hooks.slack.com/services/..."
+//! events = ["harvest.failed"]   # default: harvest.completed and harvest.failed
+//! ```
+//!
+//! The payload carries a human-readable `text` field, so Slack and Teams
+//! incoming webhooks can render it directly, plus the full serialized
+//! [`BatchHarvestSummary`] for downstream pipelines.
+//!
+//! Notification sinks (`[[notifications]]`, see
+//! [`NotificationConfig`](crate::config::NotificationConfig)) send
+//! [`HarvestNotification::message`] to a Slack channel or by email instead,
+//! and can also fire when too many datasets of a run failed.
+
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::sync::BatchHarvestSummary;
+
+/// Harvest lifecycle events that can trigger a webhook.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum HarvestEvent {
+    /// Every portal in the run was harvested successfully
+    #[serde(rename = "harvest.completed")]
+    Completed,
+    /// At least one portal in the run failed
+    #[serde(rename = "harvest.failed")]
+    Failed,
+    /// The run quarantined a portal that failed too many times in a row
+    #[serde(rename = "portal.quarantined")]
+    Quarantined,
+}
+
+impl HarvestEvent {
+    /// Classifies a finished harvest run as completed or failed.
+    pub fn from_summary(summary: &BatchHarvestSummary) -> Self {
+        if summary.failed_count() > 0 {
+            HarvestEvent::Failed
+        } else {
+            HarvestEvent::Completed
+        }
+    }
+}
+
+/// JSON payload POSTed to webhooks when a harvest finishes.
+#[derive(Debug, Clone, Serialize)]
+pub struct HarvestNotification {
+    /// What happened
+    pub event: HarvestEvent,
+    /// When the run finished
+    pub timestamp: DateTime<Utc>,
+    /// One-line summary for chat integrations
+    pub text: String,
+    /// Number of portals harvested successfully
+    pub successful: usize,
+    /// Number of portals that failed
+    pub failed: usize,
+    /// Datasets processed across all portals
+    pub total_datasets: usize,
+    /// Per-portal results
+    pub summary: BatchHarvestSummary,
+}
+
+impl HarvestNotification {
+    /// Builds the notification for a finished harvest run.
+    pub fn new(summary: BatchHarvestSummary, timestamp: DateTime<Utc>) -> Self {
+        let event = HarvestEvent::from_summary(&summary);
+        let successful = summary.successful_count();
+        let failed = summary.failed_count();
+        let total_datasets = summary.total_datasets();
+
+        let mut text = match event {
+            HarvestEvent::Completed => format!(
+                "Ceres harvest completed: {} portal(s), {} datasets",
+                successful, total_datasets
+            ),
+            // Runs are classified as completed or failed; quarantines
+            // come with failures
+            HarvestEvent::Failed | HarvestEvent::Quarantined => format!(
+                "Ceres harvest failed for {} of {} portal(s)",
+                failed,
+                summary.total_portals()
+            ),
+        };
+        let failed_names: Vec<&str> = summary
+            .results
+            .iter()
+            .filter(|r| r.is_failure())
+            .map(|r| r.portal_name.as_str())
+            .collect();
+        if !failed_names.is_empty() {
+            text.push_str(&format!(" ({})", failed_names.join(", ")));
+        }
+
+        Self {
+            event,
+            timestamp,
+            text,
+            successful,
+            failed,
+            total_datasets,
+            summary,
+        }
+    }
+
+    /// Returns true if a target subscribed to `events`, and alerted when more
+    /// than `failure_rate_above` of the datasets fail, should receive this
+    /// notification.
+    pub fn triggers(&self, events: &[HarvestEvent], failure_rate_above: Option<f64>) -> bool {
+        let quarantined = self
+            .summary
+            .results
+            .iter()
+            .any(|r| r.quarantined_until.is_some());
+        let failure_rate_exceeded = failure_rate_above
+            .zip(self.summary.dataset_failure_rate())
+            .is_some_and(|(threshold, rate)| rate > threshold);
+        events.contains(&self.event)
+            || (quarantined && events.contains(&HarvestEvent::Quarantined))
+            || failure_rate_exceeded
+    }
+
+    /// The notification as plain text for chat messages and emails: the
+    /// summary line, then one line per portal.
+    pub fn message(&self) -> String {
+        let mut lines = vec![self.text.clone()];
+        for result in &self.summary.results {
+            let stats = &result.stats;
+            let mut line = match &result.error {
+                Some(error) => format!("- {}: failed: {}", result.portal_name, error),
+                None => format!(
+                    "- {}: {} datasets ({} created, {} updated, {} unchanged, {} failed)",
+                    result.portal_name,
+                    stats.total(),
+                    stats.created,
+                    stats.updated,
+                    stats.unchanged,
+                    stats.failed
+                ),
+            };
+            if let Some(until) = result.quarantined_until {
+                line.push_str(&format!(
+                    "; quarantined until {}",
+                    until.format("%Y-%m-%d %H:%M UTC")
+                ));
+            }
+            lines.push(line);
+        }
+        if let Some(rate) = self.summary.dataset_failure_rate().filter(|r| *r > 0.0) {
+            let mut line = format!("{:.1}% of datasets failed", rate * 100.0);
+            let kinds: Vec<String> = self
+                .summary
+                .failed_by_kind()
+                .nonzero()
+                .iter()
+                .map(|(kind, count)| format!("{} {}", count, kind.as_str()))
+                .collect();
+            if !kinds.is_empty() {
+                line.push_str(&format!(" ({})", kinds.join(", ")));
+            }
+            lines.push(line);
+        }
+        lines.join("\n")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::sync::{FailureKind, PortalHarvestResult, SyncStats};
+
+    fn summary_with_failure() -> BatchHarvestSummary {
+        let mut summary = BatchHarvestSummary::new();
+        summary.add(PortalHarvestResult::success(
+            "milano".to_string(),
+            "https://dati.comune.milano.it".to_string(),
+            SyncStats {
+                unchanged: 10,
+                updated: 2,
+                created: 3,
+                failed: 0,
+                ..Default::default()
+            },
+        ));
+        summary.add(PortalHarvestResult::failure(
+            "sicilia".to_string(),
+            "https://dati.regione.sicilia.it".to_string(),
+            "timeout".to_string(),
+        ));
+        summary
+    }
+
+    #[test]
+    fn test_event_from_summary() {
+        assert_eq!(
+            HarvestEvent::from_summary(&BatchHarvestSummary::new()),
+            HarvestEvent::Completed
+        );
+        assert_eq!(
+            HarvestEvent::from_summary(&summary_with_failure()),
+            HarvestEvent::Failed
+        );
+    }
+
+    #[test]
+    fn test_notification_payload() {
+        let notification = HarvestNotification::new(summary_with_failure(), Utc::now());
+        assert_eq!(notification.successful, 1);
+        assert_eq!(notification.failed, 1);
+        assert_eq!(notification.total_datasets, 15);
+        assert!(notification.text.contains("sicilia"));
+
+        let json = serde_json::to_value(&notification).unwrap();
+        assert_eq!(json["event"], "harvest.failed");
+        assert_eq!(json["summary"]["results"][0]["portal_name"], "milano");
+        assert_eq!(json["summary"]["results"][0]["stats"]["created"], 3);
+        assert_eq!(json["summary"]["results"][1]["error"], "timeout");
+    }
+
+    #[test]
+    fn test_event_names_deserialize() {
+        let events: Vec<HarvestEvent> =
+            serde_json::from_str(r#"["harvest.completed", "harvest.failed"]"#).unwrap();
+        assert_eq!(events, vec![HarvestEvent::Completed, HarvestEvent::Failed]);
+        let events: Vec<HarvestEvent> = serde_json::from_str(r#"["portal.quarantined"]"#).unwrap();
+        assert_eq!(events, vec![HarvestEvent::Quarantined]);
+    }
+
+    #[test]
+    fn test_triggers() {
+        let mut stats = SyncStats {
+            unchanged: 90,
+            ..Default::default()
+        };
+        for _ in 0..10 {
+            stats.record_failure(FailureKind::Fetch);
+        }
+        let mut summary = BatchHarvestSummary::new();
+        summary.add(PortalHarvestResult::success(
+            "milano".to_string(),
+            "https://dati.comune.milano.it".to_string(),
+            stats,
+        ));
+        let notification = HarvestNotification::new(summary.clone(), Utc::now());
+        assert!(notification.triggers(&[HarvestEvent::Completed], None));
+        assert!(!notification.triggers(&[HarvestEvent::Failed], None));
+        // 10% of the datasets failed
+        assert!(notification.triggers(&[], Some(0.05)));
+        assert!(!notification.triggers(&[], Some(0.2)));
+        assert!(notification
+            .message()
+            .ends_with("10.0% of datasets failed (10 fetch)"));
+
+        let mut quarantined = PortalHarvestResult::failure(
+            "sicilia".to_string(),
+            "https://dati.regione.sicilia.it".to_string(),
+            "timeout".to_string(),
+        );
+        quarantined.quarantined_until = Some(Utc::now());
+        summary.add(quarantined);
+        let notification = HarvestNotification::new(summary, Utc::now());
+        assert!(notification.triggers(&[HarvestEvent::Quarantined], None));
+        assert!(!notification.triggers(&[HarvestEvent::Completed], None));
+        let message = notification.message();
+        assert!(message.starts_with("Ceres harvest failed for 1 of 2 portal(s) (sicilia)"));
+        assert!(message.contains("- sicilia: failed: timeout; quarantined until"));
+    }
+}
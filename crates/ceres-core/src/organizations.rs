@@ -0,0 +1,69 @@
+//! Publishing organizations.
+//!
+//! CKAN attaches each dataset to an organization (its publisher). Harvests
+//! store organizations in their own table, keyed by portal and CKAN name,
+//! so datasets can be filtered by publisher (`--org`) and publishers
+//! compared (`ceres orgs list`).
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use serde_json::Value;
+
+/// Organization of a dataset, as embedded in CKAN package metadata.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DatasetOrganization {
+    /// CKAN organization name (URL slug), unique within a portal
+    pub name: String,
+    /// Human-readable title
+    pub title: Option<String>,
+    /// CKAN organization ID
+    pub ckan_id: Option<String>,
+}
+
+impl DatasetOrganization {
+    /// Reads the `organization` object of CKAN package metadata.
+    ///
+    /// Returns `None` for datasets without an organization name.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ceres_core::organizations::DatasetOrganization;
+    /// use serde_json::json;
+    ///
+    /// let metadata = json!({"organization": {"name": "comune-di-milano", "title": "Comune di Milano"}});
+    /// let organization = DatasetOrganization::from_metadata(&metadata).unwrap();
+    /// assert_eq!(organization.title.as_deref(), Some("Comune di Milano"));
+    /// assert!(DatasetOrganization::from_metadata(&json!({"organization": null})).is_none());
+    /// ```
+    pub fn from_metadata(metadata: &Value) -> Option<Self> {
+        let organization = &metadata["organization"];
+        let field = |key: &str| {
+            organization[key]
+                .as_str()
+                .map(str::trim)
+                .filter(|s| !s.is_empty())
+                .map(str::to_string)
+        };
+        Some(Self {
+            name: field("name")?,
+            title: field("title"),
+            ckan_id: field("id"),
+        })
+    }
+}
+
+/// An organization and how many datasets it publishes.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct OrganizationSummary {
+    /// Portal the organization publishes on
+    pub source_portal: String,
+    /// CKAN organization name
+    pub name: String,
+    /// Human-readable title
+    pub title: Option<String>,
+    /// Datasets linked to the organization
+    pub datasets: i64,
+    /// Latest upstream modification among its datasets
+    pub last_modified: Option<DateTime<Utc>>,
+}
@@ -0,0 +1,184 @@
+//! Pre-harvest checks of portal APIs, run by `ceres check`.
+//!
+//! A check probes a portal's CKAN API without harvesting anything, then
+//! [`assess`] flags what would make a batch harvest of it fail or crawl.
+//! Large portals are not flagged: harvests page through `package_search`
+//! for them.
+
+use serde::Serialize;
+
+/// `site_read` slower than this (milliseconds) is flagged as slow.
+pub const SLOW_RESPONSE_MS: u64 = 5_000;
+
+/// What the probes of a portal found.
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+pub struct PortalProbe {
+    /// Time `site_read` took to answer, if it did
+    pub response_ms: Option<u64>,
+    /// Why `site_read` failed
+    pub site_read_error: Option<String>,
+    /// CKAN version from `status_show`
+    pub ckan_version: Option<String>,
+    /// Datasets counted by `package_search`
+    pub dataset_count: Option<usize>,
+    /// Action a harvest would list datasets with, if any works
+    pub listing: Option<String>,
+    /// Why `package_list` failed
+    pub package_list_error: Option<String>,
+    /// Why `package_search` failed
+    pub package_search_error: Option<String>,
+}
+
+/// Overall outcome of a portal check, from best to worst.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CheckStatus {
+    /// Ready to harvest
+    Ok,
+    /// Harvestable, but slow, degraded or empty
+    Warning,
+    /// A batch harvest would likely fail
+    Failing,
+}
+
+/// A checked portal, as reported by `ceres check`.
+#[derive(Debug, Clone, Serialize)]
+pub struct PortalCheck {
+    /// Name in portals.toml, if the portal came from it
+    pub name: Option<String>,
+    pub url: String,
+    pub status: CheckStatus,
+    /// Why the portal is not `ok`
+    pub issues: Vec<String>,
+    #[serde(flatten)]
+    pub probe: PortalProbe,
+}
+
+impl PortalCheck {
+    /// Assesses `probe` of the portal at `url`.
+    pub fn new(name: Option<String>, url: String, probe: PortalProbe) -> Self {
+        let (status, issues) = assess(&probe);
+        Self {
+            name,
+            url,
+            status,
+            issues,
+            probe,
+        }
+    }
+}
+
+/// Flags what in `probe` would make a harvest fail (an unreachable API, no
+/// way to list datasets) or struggle (slow answers, no `package_list`,
+/// empty catalogs).
+pub fn assess(probe: &PortalProbe) -> (CheckStatus, Vec<String>) {
+    let mut status = CheckStatus::Ok;
+    let mut issues = Vec::new();
+    let mut flag = |level: CheckStatus, issue: String| {
+        status = status.max(level);
+        issues.push(issue);
+    };
+
+    if let Some(error) = &probe.site_read_error {
+        flag(CheckStatus::Failing, format!("API unreachable: {}", error));
+        return (status, issues);
+    }
+    match (&probe.listing, &probe.package_list_error) {
+        (None, _) => flag(
+            CheckStatus::Failing,
+            format!(
+                "cannot list datasets: package_list failed ({}), package_search failed ({})",
+                probe.package_list_error.as_deref().unwrap_or("unknown"),
+                probe.package_search_error.as_deref().unwrap_or("unknown")
+            ),
+        ),
+        (Some(_), Some(error)) => flag(
+            CheckStatus::Warning,
+            format!(
+                "package_list failed ({}); harvests page through package_search",
+                error
+            ),
+        ),
+        (Some(_), None) => {}
+    }
+    if let Some(ms) = probe.response_ms.filter(|&ms| ms > SLOW_RESPONSE_MS) {
+        flag(
+            CheckStatus::Warning,
+            format!("slow API: site_read took {:.1} s", ms as f64 / 1000.0),
+        );
+    }
+    if probe.dataset_count == Some(0) {
+        flag(CheckStatus::Warning, "no public datasets".to_string());
+    }
+    (status, issues)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn healthy() -> PortalProbe {
+        PortalProbe {
+            response_ms: Some(120),
+            ckan_version: Some("2.10.4".to_string()),
+            dataset_count: Some(1_200),
+            listing: Some("package_list".to_string()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_assess_healthy() {
+        assert_eq!(assess(&healthy()), (CheckStatus::Ok, vec![]));
+        // Some portals hide status_show; that alone is not a problem
+        let probe = PortalProbe {
+            ckan_version: None,
+            ..healthy()
+        };
+        assert_eq!(assess(&probe).0, CheckStatus::Ok);
+    }
+
+    #[test]
+    fn test_assess_warnings() {
+        let probe = PortalProbe {
+            response_ms: Some(SLOW_RESPONSE_MS + 1_000),
+            dataset_count: Some(0),
+            ..healthy()
+        };
+        let (status, issues) = assess(&probe);
+        assert_eq!(status, CheckStatus::Warning);
+        assert_eq!(issues.len(), 2);
+        assert!(issues[0].starts_with("slow API"));
+
+        // Portals hiding package_list are still harvested, more slowly
+        let probe = PortalProbe {
+            listing: Some("package_search".to_string()),
+            package_list_error: Some("HTTP 403".to_string()),
+            ..healthy()
+        };
+        assert_eq!(assess(&probe).0, CheckStatus::Warning);
+    }
+
+    #[test]
+    fn test_assess_failing() {
+        let probe = PortalProbe {
+            response_ms: Some(SLOW_RESPONSE_MS + 1_000),
+            listing: None,
+            package_list_error: Some("HTTP 403".to_string()),
+            package_search_error: Some("HTTP 404".to_string()),
+            ..healthy()
+        };
+        let (status, issues) = assess(&probe);
+        assert_eq!(status, CheckStatus::Failing);
+        assert_eq!(
+            issues[0],
+            "cannot list datasets: package_list failed (HTTP 403), package_search failed (HTTP 404)"
+        );
+
+        let probe = PortalProbe {
+            site_read_error: Some("Connection refused".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(assess(&probe).0, CheckStatus::Failing);
+    }
+}
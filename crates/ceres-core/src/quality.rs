@@ -0,0 +1,173 @@
+//! Metadata quality scores.
+//!
+//! A dataset is more useful when it is described, licensed, downloadable
+//! and kept up to date. Each of these is scored from 0 to 1 at harvest and
+//! combined into a stored quality score, which `ceres search
+//! --quality-weight` can fold into ranking and `ceres stats` reports per
+//! portal, pointing open data officers at datasets worth improving.
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::error::AppError;
+
+/// Description length, in characters, that scores full marks.
+pub const COMPLETE_DESCRIPTION_CHARS: usize = 200;
+
+/// Age in days up to which a dataset counts as fully up to date.
+pub const RECENT_DAYS: i64 = 365;
+
+/// Age in days from which a dataset scores nothing for recency.
+pub const STALE_DAYS: i64 = 5 * 365;
+
+/// Scores below this are reported as low quality.
+pub const LOW_QUALITY: f32 = 0.5;
+
+/// Score assumed when ranking datasets that have not been scored.
+pub const NEUTRAL_QUALITY: f32 = 0.5;
+
+/// CKAN license IDs that do not grant a license.
+const NO_LICENSE: &[&str] = &["notspecified", "other-closed"];
+
+/// Per-criterion scores of a dataset's metadata, each from 0 to 1.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct QualityScore {
+    /// Description length, up to [`COMPLETE_DESCRIPTION_CHARS`]
+    pub description: f32,
+    /// Whether a license is declared
+    pub license: f32,
+    /// Share of resources with a download URL and a format; 0 without resources
+    pub resources: f32,
+    /// Recency of the last upstream modification, decaying from
+    /// [`RECENT_DAYS`] to [`STALE_DAYS`]
+    pub recency: f32,
+}
+
+impl QualityScore {
+    /// Scores a dataset's metadata as of `now`.
+    pub fn compute(
+        description: Option<&str>,
+        metadata: &Value,
+        modified_at: Option<DateTime<Utc>>,
+        now: DateTime<Utc>,
+    ) -> Self {
+        let description_chars = description.map_or(0, |d| d.trim().chars().count());
+        let license = metadata["license_id"]
+            .as_str()
+            .map(str::trim)
+            .is_some_and(|id| !id.is_empty() && !NO_LICENSE.contains(&id));
+
+        let resources = metadata["resources"].as_array().map_or(0.0, |resources| {
+            if resources.is_empty() {
+                return 0.0;
+            }
+            let usable = resources
+                .iter()
+                .filter(|r| has_text(&r["url"]) && has_text(&r["format"]))
+                .count();
+            usable as f32 / resources.len() as f32
+        });
+
+        let recency = modified_at.map_or(0.0, |modified_at| {
+            let age = (now - modified_at).num_days();
+            let decay = (age - RECENT_DAYS) as f32 / (STALE_DAYS - RECENT_DAYS) as f32;
+            1.0 - decay.clamp(0.0, 1.0)
+        });
+
+        Self {
+            description: (description_chars as f32 / COMPLETE_DESCRIPTION_CHARS as f32).min(1.0),
+            license: if license { 1.0 } else { 0.0 },
+            resources,
+            recency,
+        }
+    }
+
+    /// Weighted overall score from 0 to 1.
+    ///
+    /// Descriptions and resources weigh 30% each, license and recency 20%.
+    pub fn total(&self) -> f32 {
+        0.3 * self.description + 0.2 * self.license + 0.3 * self.resources + 0.2 * self.recency
+    }
+}
+
+/// Overall quality score of a dataset's metadata as of now.
+pub fn quality_score(
+    description: Option<&str>,
+    metadata: &Value,
+    modified_at: Option<DateTime<Utc>>,
+) -> f32 {
+    QualityScore::compute(description, metadata, modified_at, Utc::now()).total()
+}
+
+/// Parses a ranking weight from 0 to 1 for `--quality-weight`.
+///
+/// # Errors
+///
+/// Returns `AppError::ConfigError` for non-numbers and values outside 0..1.
+pub fn parse_quality_weight(input: &str) -> Result<f32, AppError> {
+    input
+        .trim()
+        .parse::<f32>()
+        .ok()
+        .filter(|w| (0.0..=1.0).contains(w))
+        .ok_or_else(|| {
+            AppError::ConfigError(format!(
+                "Invalid quality weight '{}': expected a number from 0 to 1",
+                input
+            ))
+        })
+}
+
+fn has_text(value: &Value) -> bool {
+    value.as_str().is_some_and(|s| !s.trim().is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Duration;
+    use serde_json::json;
+
+    #[test]
+    fn test_complete_dataset_scores_full_marks() {
+        let now = Utc::now();
+        let metadata = json!({
+            "license_id": "cc-by",
+            "resources": [{"url": "https://example.com/a.csv", "format": "CSV"}]
+        });
+        let score = QualityScore::compute(Some(&"x".repeat(300)), &metadata, Some(now), now);
+        assert_eq!(score.total(), 1.0);
+    }
+
+    #[test]
+    fn test_partial_scores() {
+        let now = Utc::now();
+        let metadata = json!({
+            "license_id": "notspecified",
+            "resources": [
+                {"url": "https://example.com/a.csv", "format": "CSV"},
+                {"url": "https://example.com/b", "format": " "}
+            ]
+        });
+        let three_years_ago = now - Duration::days(RECENT_DAYS + (STALE_DAYS - RECENT_DAYS) / 2);
+        let score = QualityScore::compute(Some("Short."), &metadata, Some(three_years_ago), now);
+        assert_eq!(score.description, 6.0 / COMPLETE_DESCRIPTION_CHARS as f32);
+        assert_eq!(score.license, 0.0);
+        assert_eq!(score.resources, 0.5);
+        assert!((score.recency - 0.5).abs() < 0.01);
+    }
+
+    #[test]
+    fn test_parse_quality_weight() {
+        assert_eq!(parse_quality_weight("0.3").unwrap(), 0.3);
+        assert!(parse_quality_weight("1.5").is_err());
+        assert!(parse_quality_weight("high").is_err());
+    }
+
+    #[test]
+    fn test_empty_metadata_scores_zero() {
+        let score = QualityScore::compute(None, &json!({"resources": []}), None, Utc::now());
+        assert_eq!(score.total(), 0.0);
+    }
+}
@@ -0,0 +1,276 @@
+//! Curated portal bundles (`ceres portals install`).
+//!
+//! A registry is a static HTTP directory:
+//!
+//! - `index.json`: the bundle catalog, listing each bundle's versions with
+//!   the path and SHA-256 checksum of their file
+//! - `index.json.sig`: base64 Ed25519 signature of `index.json`
+//! - bundle files: `portals.toml` fragments containing `[[portals]]` entries
+//!
+//! Bundles are verified against the checksum in the index, and the index
+//! itself against its signature, which makes the checksums, and therefore
+//! the bundles, tamper-evident. The default registry is checked against
+//! [`DEFAULT_REGISTRY_PUBLIC_KEY`]; other registries need their own key,
+//! or an explicit opt-out of verification.
+//!
+//! ```json
+//! {
+//!   "bundles": {
+//!     "italy-regions": {
+//!       "description": "Italian regional open data portals",
+//!       "latest": "1.0.0",
+//!       "versions": {
+//!         "1.0.0": { "path": "italy-regions/1.0.0.toml", "sha256": "9f2c..." }
+//!       }
+//!     }
+//!   }
+//! }
+//! ```
+
+use std::collections::BTreeMap;
+
+use base64::engine::general_purpose::STANDARD;
+use base64::Engine;
+use ring::signature::{UnparsedPublicKey, ED25519};
+use serde::Deserialize;
+use sha2::{Digest, Sha256};
+
+use crate::config::{PortalEntry, PortalsConfig};
+use crate::error::AppError;
+
+/// Registry used when none is configured.
+pub const DEFAULT_REGISTRY_URL: &str =
+    "https://raw.githubusercontent.com/AndreaBozzo/Ceres/master/registry";
+
+/// Base64 Ed25519 public key the index of [`DEFAULT_REGISTRY_URL`] is
+/// signed with.
+pub const DEFAULT_REGISTRY_PUBLIC_KEY: &str = "N8mFSQci2jiEd0GCyTX96NcIMQiTgoq0QyOhpZk27Jw=";
+
+/// Returns the built-in public key of the registry at `url`, if it is the
+/// default registry.
+pub fn default_public_key(url: &str) -> Option<&'static str> {
+    (url.trim_end_matches('/') == DEFAULT_REGISTRY_URL).then_some(DEFAULT_REGISTRY_PUBLIC_KEY)
+}
+
+/// The bundle catalog (`index.json`).
+#[derive(Debug, Clone, Deserialize)]
+pub struct RegistryIndex {
+    pub bundles: BTreeMap<String, BundleEntry>,
+}
+
+/// All published versions of a bundle.
+#[derive(Debug, Clone, Deserialize)]
+pub struct BundleEntry {
+    #[serde(default)]
+    pub description: Option<String>,
+    /// Version installed when none is requested
+    pub latest: String,
+    pub versions: BTreeMap<String, BundleVersion>,
+}
+
+/// Location and checksum of one bundle version.
+#[derive(Debug, Clone, Deserialize)]
+pub struct BundleVersion {
+    /// Path of the bundle file, relative to the registry URL
+    pub path: String,
+    /// Hex-encoded SHA-256 of the bundle file
+    pub sha256: String,
+}
+
+/// A bundle requested as `name` or `name@version`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct BundleRef {
+    pub name: String,
+    pub version: Option<String>,
+}
+
+impl BundleRef {
+    /// Parses `name` or `name@version`.
+    pub fn parse(spec: &str) -> Self {
+        match spec.split_once('@') {
+            Some((name, version)) => Self {
+                name: name.to_string(),
+                version: Some(version.to_string()),
+            },
+            None => Self {
+                name: spec.to_string(),
+                version: None,
+            },
+        }
+    }
+}
+
+impl RegistryIndex {
+    /// Parses `index.json`.
+    pub fn parse(bytes: &[u8]) -> Result<Self, AppError> {
+        serde_json::from_slice(bytes)
+            .map_err(|e| AppError::ConfigError(format!("Invalid registry index: {}", e)))
+    }
+
+    /// Resolves a requested bundle to its version and file.
+    ///
+    /// # Errors
+    ///
+    /// Returns `AppError::ConfigError` if the bundle or version is not published.
+    pub fn resolve(&self, bundle: &BundleRef) -> Result<(String, &BundleVersion), AppError> {
+        let entry = self.bundles.get(&bundle.name).ok_or_else(|| {
+            let available: Vec<&str> = self.bundles.keys().map(String::as_str).collect();
+            AppError::ConfigError(format!(
+                "Bundle '{}' not found in registry (available: {})",
+                bundle.name,
+                available.join(", ")
+            ))
+        })?;
+
+        let version = bundle.version.as_deref().unwrap_or(&entry.latest);
+        let file = entry.versions.get(version).ok_or_else(|| {
+            let available: Vec<&str> = entry.versions.keys().map(String::as_str).collect();
+            AppError::ConfigError(format!(
+                "Version '{}' of bundle '{}' not found (available: {})",
+                version,
+                bundle.name,
+                available.join(", ")
+            ))
+        })?;
+        Ok((version.to_string(), file))
+    }
+}
+
+/// Checks that `bytes` hash to the hex-encoded SHA-256 `expected`.
+///
+/// `what` names the checked file in the error message.
+pub fn verify_checksum(what: &str, bytes: &[u8], expected: &str) -> Result<(), AppError> {
+    let actual = format!("{:x}", Sha256::digest(bytes));
+    if actual.eq_ignore_ascii_case(expected.trim()) {
+        Ok(())
+    } else {
+        Err(AppError::ConfigError(format!(
+            "Checksum mismatch for {}: expected {}, got {}",
+            what, expected, actual
+        )))
+    }
+}
+
+/// Checks a base64 Ed25519 `signature` of `message` against a base64 public key.
+pub fn verify_signature(message: &[u8], signature: &str, public_key: &str) -> Result<(), AppError> {
+    let decode = |what: &str, value: &str| {
+        STANDARD
+            .decode(value.trim())
+            .map_err(|e| AppError::ConfigError(format!("Invalid registry {}: {}", what, e)))
+    };
+    let public_key = decode("public key", public_key)?;
+    let signature = decode("signature", signature)?;
+
+    UnparsedPublicKey::new(&ED25519, public_key)
+        .verify(message, &signature)
+        .map_err(|_| AppError::ConfigError("Registry index signature is invalid".to_string()))
+}
+
+/// Parses a bundle file into portal entries.
+pub fn parse_bundle(content: &str) -> Result<Vec<PortalEntry>, AppError> {
+    let bundle: PortalsConfig = toml::from_str(content)
+        .map_err(|e| AppError::ConfigError(format!("Invalid bundle: {}", e)))?;
+    Ok(bundle.portals)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ring::rand::SystemRandom;
+    use ring::signature::{Ed25519KeyPair, KeyPair};
+
+    const INDEX: &str = r#"{
+        "bundles": {
+            "italy-regions": {
+                "description": "Italian regions",
+                "latest": "1.1.0",
+                "versions": {
+                    "1.0.0": { "path": "italy-regions/1.0.0.toml", "sha256": "aa" },
+                    "1.1.0": { "path": "italy-regions/1.1.0.toml", "sha256": "bb" }
+                }
+            }
+        }
+    }"#;
+
+    #[test]
+    fn test_bundle_ref_parse() {
+        assert_eq!(
+            BundleRef::parse("italy-regions@1.0.0"),
+            BundleRef {
+                name: "italy-regions".to_string(),
+                version: Some("1.0.0".to_string())
+            }
+        );
+        assert_eq!(BundleRef::parse("italy-regions").version, None);
+    }
+
+    #[test]
+    fn test_resolve_latest_and_pinned() {
+        let index = RegistryIndex::parse(INDEX.as_bytes()).unwrap();
+
+        let (version, file) = index.resolve(&BundleRef::parse("italy-regions")).unwrap();
+        assert_eq!(version, "1.1.0");
+        assert_eq!(file.path, "italy-regions/1.1.0.toml");
+
+        let (version, _) = index
+            .resolve(&BundleRef::parse("italy-regions@1.0.0"))
+            .unwrap();
+        assert_eq!(version, "1.0.0");
+    }
+
+    #[test]
+    fn test_resolve_unknown() {
+        let index = RegistryIndex::parse(INDEX.as_bytes()).unwrap();
+        assert!(
+            matches!(index.resolve(&BundleRef::parse("france")), Err(AppError::ConfigError(msg)) if msg.contains("italy-regions"))
+        );
+        assert!(index
+            .resolve(&BundleRef::parse("italy-regions@9.9.9"))
+            .is_err());
+    }
+
+    #[test]
+    fn test_verify_checksum() {
+        // SHA-256("abc")
+        let expected = "ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad";
+        assert!(verify_checksum("abc", b"abc", expected).is_ok());
+        assert!(verify_checksum("abd", b"abd", expected).is_err());
+    }
+
+    #[test]
+    fn test_verify_signature() {
+        let pkcs8 = Ed25519KeyPair::generate_pkcs8(&SystemRandom::new()).unwrap();
+        let key_pair = Ed25519KeyPair::from_pkcs8(pkcs8.as_ref()).unwrap();
+        let public_key = STANDARD.encode(key_pair.public_key().as_ref());
+        let signature = STANDARD.encode(key_pair.sign(INDEX.as_bytes()).as_ref());
+
+        assert!(verify_signature(INDEX.as_bytes(), &signature, &public_key).is_ok());
+        assert!(verify_signature(b"tampered", &signature, &public_key).is_err());
+    }
+
+    #[test]
+    fn test_default_registry_is_signed() {
+        let index = include_bytes!("../../../registry/index.json");
+        let signature = include_str!("../../../registry/index.json.sig");
+        assert!(verify_signature(index, signature, DEFAULT_REGISTRY_PUBLIC_KEY).is_ok());
+
+        let url = format!("{}/", DEFAULT_REGISTRY_URL);
+        assert_eq!(default_public_key(&url), Some(DEFAULT_REGISTRY_PUBLIC_KEY));
+        assert_eq!(default_public_key("https://registry.example.com"), None);
+    }
+
+    #[test]
+    fn test_parse_bundle() {
+        let portals = parse_bundle(
+            r#"
+[[portals]]
+name = "toscana"
+url = "https://dati.toscana.it"
+description = "Open data della Regione Toscana"
+"#,
+        )
+        .unwrap();
+        assert_eq!(portals.len(), 1);
+        assert_eq!(portals[0].portal_type, "ckan");
+    }
+}
@@ -0,0 +1,107 @@
+//! Dataset resources.
+//!
+//! Each CKAN package lists its downloadable resources (files, APIs, map
+//! services). Harvests store them in the `resources` table, one row per
+//! resource, so format statistics in `ceres stats` count resources and
+//! their sizes rather than only the datasets offering a format.
+
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::models::NewDataset;
+
+/// A resource of a dataset, as listed in CKAN package metadata.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct DatasetResource {
+    /// CKAN resource ID
+    pub ckan_id: Option<String>,
+    /// Resource name
+    pub name: Option<String>,
+    /// Normalized format (see [`NewDataset::normalize_format`])
+    pub format: Option<String>,
+    /// MIME type declared by the portal
+    pub mimetype: Option<String>,
+    /// Size in bytes, when the portal reports it
+    pub size: Option<i64>,
+    /// Download URL
+    pub url: Option<String>,
+}
+
+impl DatasetResource {
+    /// Reads a resource object of CKAN package metadata.
+    ///
+    /// Blank strings count as missing. Sizes are accepted as numbers or
+    /// numeric strings; negative sizes are dropped.
+    ///
+    /// # Examples
+    ///
+    /// ```
+    /// use ceres_core::resources::DatasetResource;
+    /// use serde_json::json;
+    ///
+    /// let resource = DatasetResource::from_value(&json!({
+    ///     "name": "Fermate", "format": ".csv", "size": "2048", "url": "https://example.com/f.csv"
+    /// }));
+    /// assert_eq!(resource.format.as_deref(), Some("CSV"));
+    /// assert_eq!(resource.size, Some(2048));
+    /// ```
+    pub fn from_value(resource: &Value) -> Self {
+        let field = |key: &str| {
+            resource[key]
+                .as_str()
+                .map(str::trim)
+                .filter(|s| !s.is_empty())
+                .map(str::to_string)
+        };
+        let size = match &resource["size"] {
+            Value::Number(n) => n.as_i64(),
+            Value::String(s) => s.trim().parse().ok(),
+            _ => None,
+        };
+        Self {
+            ckan_id: field("id"),
+            name: field("name"),
+            format: resource["format"]
+                .as_str()
+                .and_then(NewDataset::normalize_format),
+            mimetype: field("mimetype"),
+            size: size.filter(|size| *size >= 0),
+            url: field("url"),
+        }
+    }
+}
+
+/// Resources of a dataset, in the order CKAN lists them.
+pub fn dataset_resources(metadata: &Value) -> Vec<DatasetResource> {
+    metadata["resources"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter(|resource| resource.is_object())
+        .map(DatasetResource::from_value)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_dataset_resources() {
+        let metadata = json!({"resources": [
+            {"id": "r1", "name": " ", "format": "application/json", "mimetype": "application/json", "size": 10},
+            {"format": "", "size": -1, "url": "https://example.com/x"},
+            "not a resource"
+        ]});
+        let resources = dataset_resources(&metadata);
+        assert_eq!(resources.len(), 2);
+        assert_eq!(resources[0].ckan_id.as_deref(), Some("r1"));
+        assert_eq!(resources[0].name, None);
+        assert_eq!(resources[0].format.as_deref(), Some("JSON"));
+        assert_eq!(resources[0].size, Some(10));
+        assert_eq!(resources[1].format, None);
+        assert_eq!(resources[1].size, None);
+        assert!(dataset_resources(&json!({"resources": "x"})).is_empty());
+    }
+}
@@ -0,0 +1,154 @@
+//! Cron scheduling for daemon mode.
+//!
+//! Portal entries may carry a standard 5-field cron expression
+//! (`minute hour day-of-month month day-of-week`, optionally with a leading
+//! seconds field). Schedules are evaluated in UTC.
+
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use croner::Cron;
+use sha2::{Digest, Sha256};
+
+use crate::error::AppError;
+
+/// A parsed cron schedule.
+///
+/// # Examples
+///
+/// ```
+/// use ceres_core::schedule::CronSchedule;
+/// use chrono::{TimeZone, Utc};
+///
+/// let schedule = CronSchedule::parse("0 3 * * *").unwrap();
+/// let now = Utc.with_ymd_and_hms(2025, 1, 1, 12, 0, 0).unwrap();
+/// let next = schedule.next_after(now).unwrap();
+/// assert_eq!(next, Utc.with_ymd_and_hms(2025, 1, 2, 3, 0, 0).unwrap());
+/// ```
+#[derive(Debug, Clone)]
+pub struct CronSchedule {
+    expression: String,
+    cron: Cron,
+}
+
+impl CronSchedule {
+    /// Parses a cron expression.
+    ///
+    /// # Errors
+    ///
+    /// Returns `AppError::ConfigError` if the expression is invalid.
+    pub fn parse(expression: &str) -> Result<Self, AppError> {
+        let cron = Cron::new(expression)
+            .with_seconds_optional()
+            .parse()
+            .map_err(|e| {
+                AppError::ConfigError(format!("Invalid cron expression '{}': {}", expression, e))
+            })?;
+
+        Ok(Self {
+            expression: expression.to_string(),
+            cron,
+        })
+    }
+
+    /// Returns the original cron expression.
+    pub fn expression(&self) -> &str {
+        &self.expression
+    }
+
+    /// Returns the first occurrence strictly after `after`, if any.
+    pub fn next_after(&self, after: DateTime<Utc>) -> Option<DateTime<Utc>> {
+        self.cron.find_next_occurrence(&after, false).ok()
+    }
+}
+
+/// Computes the start delay added to a scheduled run.
+///
+/// The jitter is derived from the portal name and the scheduled time, so
+/// portals sharing a schedule spread out instead of hitting the embedding
+/// provider at the same instant, while each individual run stays reproducible.
+/// Always strictly less than `max` (zero when `max` is zero).
+pub fn jitter_for(key: &str, scheduled: DateTime<Utc>, max: Duration) -> Duration {
+    let max_ms = max.as_millis() as u64;
+    if max_ms == 0 {
+        return Duration::ZERO;
+    }
+
+    let mut hasher = Sha256::new();
+    hasher.update(key.as_bytes());
+    hasher.update(scheduled.timestamp().to_be_bytes());
+    let digest = hasher.finalize();
+
+    let mut bytes = [0u8; 8];
+    bytes.copy_from_slice(&digest[..8]);
+    Duration::from_millis(u64::from_be_bytes(bytes) % max_ms)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+
+    #[test]
+    fn test_parse_valid_expression() {
+        let schedule = CronSchedule::parse("*/15 * * * *").unwrap();
+        assert_eq!(schedule.expression(), "*/15 * * * *");
+
+        let now = Utc.with_ymd_and_hms(2025, 6, 1, 10, 7, 0).unwrap();
+        assert_eq!(
+            schedule.next_after(now).unwrap(),
+            Utc.with_ymd_and_hms(2025, 6, 1, 10, 15, 0).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_parse_invalid_expression() {
+        let result = CronSchedule::parse("not a cron");
+        assert!(matches!(result, Err(AppError::ConfigError(_))));
+    }
+
+    #[test]
+    fn test_next_after_is_strict() {
+        let schedule = CronSchedule::parse("0 3 * * *").unwrap();
+        let at = Utc.with_ymd_and_hms(2025, 1, 1, 3, 0, 0).unwrap();
+        assert_eq!(
+            schedule.next_after(at).unwrap(),
+            Utc.with_ymd_and_hms(2025, 1, 2, 3, 0, 0).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_weekly_schedule() {
+        // Sundays at 04:30
+        let schedule = CronSchedule::parse("30 4 * * 0").unwrap();
+        // 2025-01-01 is a Wednesday
+        let now = Utc.with_ymd_and_hms(2025, 1, 1, 0, 0, 0).unwrap();
+        assert_eq!(
+            schedule.next_after(now).unwrap(),
+            Utc.with_ymd_and_hms(2025, 1, 5, 4, 30, 0).unwrap()
+        );
+    }
+
+    #[test]
+    fn test_jitter_bounds_and_determinism() {
+        let at = Utc.with_ymd_and_hms(2025, 1, 1, 3, 0, 0).unwrap();
+        let max = Duration::from_secs(300);
+
+        let a = jitter_for("milano", at, max);
+        let b = jitter_for("milano", at, max);
+        assert_eq!(a, b);
+        assert!(a < max);
+
+        assert_eq!(jitter_for("milano", at, Duration::ZERO), Duration::ZERO);
+    }
+
+    #[test]
+    fn test_jitter_differs_between_portals() {
+        let at = Utc.with_ymd_and_hms(2025, 1, 1, 3, 0, 0).unwrap();
+        let max = Duration::from_secs(3600);
+        assert_ne!(
+            jitter_for("milano", at, max),
+            jitter_for("sicilia", at, max)
+        );
+    }
+}
@@ -0,0 +1,601 @@
+//! Search filters and retrieval planning.
+//!
+//! Semantic search can run in two ways:
+//!
+//! - **Direct**: ANN index scan ordered by vector distance. Fast on any corpus
+//!   size, but filters are applied after the index scan, so selective filters
+//!   can return short result pages.
+//! - **Two-stage**: a cheap prefilter (portal, theme, keywords) selects a
+//!   bounded candidate set, which is then scored exactly by vector distance.
+//!   Keeps latency bounded on million-scale corpora and returns full pages for
+//!   selective filters.
+//!
+//! [`plan_search`] picks between them from corpus size and filter selectivity.
+//!
+//! Hybrid search additionally ranks datasets by full-text match and fuses
+//! both rankings with [`reciprocal_rank_fusion`], so exact terms such as
+//! dataset codes are found even when the embedding misses them.
+//!
+//! Results can finally be reranked: a model reading the query together with
+//! each candidate's [`rerank_document`] re-scores them with
+//! [`apply_rerank_scores`].
+
+use std::collections::HashMap;
+
+use chrono::{DateTime, NaiveDate, Utc};
+
+use uuid::Uuid;
+
+use crate::error::AppError;
+use crate::metadata_filter::MetadataFilter;
+use crate::models::{Dataset, SearchResult};
+use crate::quality::NEUTRAL_QUALITY;
+use crate::spatial::BoundingBox;
+
+/// Maximum number of candidates scored exactly in the second stage.
+pub const TWO_STAGE_MAX_CANDIDATES: u64 = 20_000;
+
+/// Corpus size from which keyword pruning is considered for unfiltered queries.
+pub const TWO_STAGE_MIN_CORPUS: u64 = 100_000;
+
+/// Constant `k` of reciprocal rank fusion; larger values flatten the
+/// advantage of top ranks.
+pub const RRF_K: f32 = 60.0;
+
+/// Candidates taken from each ranking per requested hybrid search result.
+pub const HYBRID_CANDIDATES_PER_RESULT: usize = 4;
+
+/// Default number of top candidates re-scored by a reranker.
+pub const DEFAULT_RERANK_CANDIDATES: usize = 50;
+
+/// Longest description excerpt, in characters, sent to a reranker.
+const RERANK_DESCRIPTION_CHARS: usize = 1000;
+
+/// Structured filters applied to search queries.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct SearchFilters {
+    /// Restrict results to a single source portal URL
+    pub portal: Option<String>,
+    /// Restrict results to datasets in a CKAN group/theme (by group name)
+    pub theme: Option<String>,
+    /// Restrict results to datasets embedded with this model.
+    ///
+    /// Vectors from different models live in different spaces, so a query
+    /// should only be compared against datasets embedded with its own model.
+    pub embedding_model: Option<String>,
+    /// Restrict results to datasets offering a resource format (normalized, e.g. `CSV`)
+    pub format: Option<String>,
+    /// Restrict results to datasets under a license (CKAN `license_id`, e.g. `cc-by`)
+    pub license: Option<String>,
+    /// Restrict results to datasets modified upstream at or after this time
+    pub updated_after: Option<DateTime<Utc>>,
+    /// Restrict results to datasets modified upstream before this time
+    pub updated_before: Option<DateTime<Utc>>,
+    /// Restrict results to datasets in a detected language (ISO 639-1, e.g. `it`)
+    pub language: Option<String>,
+    /// Restrict results to datasets with a normalized tag (see [`crate::tags`])
+    pub tag: Option<String>,
+    /// Restrict results to datasets of an organization, by CKAN name or title
+    pub organization: Option<String>,
+    /// Restrict results to datasets whose metadata passes every filter
+    pub metadata: Vec<MetadataFilter>,
+    /// Restrict results to datasets whose extent overlaps this box
+    pub bbox: Option<BoundingBox>,
+}
+
+impl SearchFilters {
+    /// Returns true if no filter is set.
+    pub fn is_empty(&self) -> bool {
+        self.portal.is_none()
+            && self.theme.is_none()
+            && self.embedding_model.is_none()
+            && self.format.is_none()
+            && self.license.is_none()
+            && self.updated_after.is_none()
+            && self.updated_before.is_none()
+            && self.language.is_none()
+            && self.tag.is_none()
+            && self.organization.is_none()
+            && self.metadata.is_empty()
+            && self.bbox.is_none()
+    }
+}
+
+/// Retrieval strategy requested by the user.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum SearchStrategy {
+    /// Choose automatically from corpus size and filter selectivity
+    #[default]
+    Auto,
+    /// Always use the ANN index directly
+    Direct,
+    /// Always prefilter, then score candidates exactly
+    TwoStage,
+}
+
+/// Which predicates define the candidate set of a two-stage search.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum CandidateSet {
+    /// Structured filters only (exact semantics)
+    Filters,
+    /// Structured filters plus at least one query keyword (lossy pruning)
+    FiltersAndKeywords,
+}
+
+/// Concrete execution plan for a search.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SearchPlan {
+    Direct,
+    TwoStage(CandidateSet),
+}
+
+/// Candidate counts gathered by the storage layer before planning.
+///
+/// Counts are bounded: any value above [`TWO_STAGE_MAX_CANDIDATES`] only
+/// means "too many".
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct CandidateEstimate {
+    /// Approximate number of datasets with embeddings
+    pub corpus: u64,
+    /// Datasets matching the structured filters
+    pub filtered: u64,
+    /// Datasets matching filters and keywords, if it was counted
+    pub keyword: Option<u64>,
+}
+
+/// Returns true if a keyword count should be gathered before planning.
+pub fn needs_keyword_estimate(
+    strategy: SearchStrategy,
+    estimate_corpus: u64,
+    filtered: u64,
+) -> bool {
+    if filtered <= TWO_STAGE_MAX_CANDIDATES {
+        return false;
+    }
+    match strategy {
+        SearchStrategy::Direct => false,
+        SearchStrategy::TwoStage => true,
+        SearchStrategy::Auto => estimate_corpus >= TWO_STAGE_MIN_CORPUS,
+    }
+}
+
+/// Chooses the execution plan for a search.
+///
+/// In automatic mode, a filtered query whose candidate set fits the second
+/// stage is always scored exactly. Large unfiltered (or weakly filtered)
+/// queries are pruned by keywords only when that leaves at least `limit` and
+/// at most [`TWO_STAGE_MAX_CANDIDATES`] candidates; otherwise the ANN index is
+/// used directly.
+pub fn plan_search(
+    strategy: SearchStrategy,
+    has_filters: bool,
+    estimate: CandidateEstimate,
+    limit: u64,
+) -> SearchPlan {
+    let fits = |count: u64| count <= TWO_STAGE_MAX_CANDIDATES;
+
+    match strategy {
+        SearchStrategy::Direct => SearchPlan::Direct,
+        SearchStrategy::TwoStage => {
+            if fits(estimate.filtered) {
+                SearchPlan::TwoStage(CandidateSet::Filters)
+            } else if estimate.keyword.is_some_and(|k| k > 0 && fits(k)) {
+                SearchPlan::TwoStage(CandidateSet::FiltersAndKeywords)
+            } else {
+                SearchPlan::TwoStage(CandidateSet::Filters)
+            }
+        }
+        SearchStrategy::Auto => {
+            if has_filters && fits(estimate.filtered) {
+                SearchPlan::TwoStage(CandidateSet::Filters)
+            } else if estimate.corpus < TWO_STAGE_MIN_CORPUS {
+                SearchPlan::Direct
+            } else if estimate.keyword.is_some_and(|k| k >= limit && fits(k)) {
+                SearchPlan::TwoStage(CandidateSet::FiltersAndKeywords)
+            } else {
+                SearchPlan::Direct
+            }
+        }
+    }
+}
+
+/// Builds an OR-combined `to_tsquery('simple', ...)` expression from query terms.
+///
+/// Terms are reduced to alphanumeric characters, lowercased, and terms shorter
+/// than three characters are dropped. Returns `None` if no usable term remains.
+///
+/// # Examples
+///
+/// ```
+/// use ceres_core::search::keyword_tsquery;
+///
+/// assert_eq!(
+///     keyword_tsquery("Air quality (PM10)").as_deref(),
+///     Some("air | quality | pm10")
+/// );
+/// assert_eq!(keyword_tsquery("a b"), None);
+/// ```
+pub fn keyword_tsquery(query: &str) -> Option<String> {
+    let terms = keyword_terms(query);
+    if terms.is_empty() {
+        None
+    } else {
+        Some(terms.join(" | "))
+    }
+}
+
+/// Lowercased alphanumeric query terms of at least three characters, as
+/// used by [`keyword_tsquery`].
+pub fn keyword_terms(query: &str) -> Vec<String> {
+    let mut terms: Vec<String> = query
+        .split(|c: char| !c.is_alphanumeric())
+        .filter(|t| t.chars().count() >= 3)
+        .map(|t| t.to_lowercase())
+        .collect();
+    terms.dedup();
+    terms
+}
+
+/// Fuses rankings (best first) with reciprocal rank fusion.
+///
+/// Each dataset scores the sum of `1 / (RRF_K + rank)` over the rankings it
+/// appears in, scaled so that a dataset ranked first by every ranking
+/// scores 1.0. Returns datasets best first.
+///
+/// # Examples
+///
+/// ```
+/// use ceres_core::search::reciprocal_rank_fusion;
+/// use uuid::Uuid;
+///
+/// let (a, b) = (Uuid::new_v4(), Uuid::new_v4());
+/// let fused = reciprocal_rank_fusion(&[vec![a, b], vec![a]]);
+/// assert_eq!(fused[0], (a, 1.0));
+/// assert_eq!(fused[1].0, b);
+/// ```
+pub fn reciprocal_rank_fusion(rankings: &[Vec<Uuid>]) -> Vec<(Uuid, f32)> {
+    let mut scores: HashMap<Uuid, f32> = HashMap::new();
+    for ranking in rankings {
+        for (rank, id) in ranking.iter().enumerate() {
+            *scores.entry(*id).or_default() += 1.0 / (RRF_K + rank as f32 + 1.0);
+        }
+    }
+
+    let best = rankings.len() as f32 / (RRF_K + 1.0);
+    let mut fused: Vec<(Uuid, f32)> = scores
+        .into_iter()
+        .map(|(id, score)| (id, score / best))
+        .collect();
+    fused.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(&b.0)));
+    fused
+}
+
+/// Text of a dataset read by a reranker: title, then the start of the
+/// description.
+pub fn rerank_document(dataset: &Dataset) -> String {
+    match dataset.description.as_deref().map(str::trim) {
+        Some(description) if !description.is_empty() => format!(
+            "{}. {}",
+            dataset.title.trim(),
+            description
+                .chars()
+                .take(RERANK_DESCRIPTION_CHARS)
+                .collect::<String>()
+        ),
+        _ => dataset.title.trim().to_string(),
+    }
+}
+
+/// Replaces result scores with reranker scores (aligned with `results`)
+/// and returns the best `limit`, best first.
+///
+/// Ties keep their previous order.
+pub fn apply_rerank_scores(
+    results: Vec<SearchResult>,
+    scores: &[f32],
+    limit: usize,
+) -> Vec<SearchResult> {
+    let mut reranked: Vec<SearchResult> = results
+        .into_iter()
+        .zip(scores)
+        .map(|(result, score)| SearchResult {
+            similarity_score: *score,
+            ..result
+        })
+        .collect();
+    reranked.sort_by(|a, b| b.similarity_score.total_cmp(&a.similarity_score));
+    reranked.truncate(limit);
+    reranked
+}
+
+/// Weighs result scores by metadata quality and re-sorts them, best first.
+///
+/// With `weight` w, a score becomes `score * (1 - w + w * quality)`: a
+/// dataset of perfect quality keeps its score, one of zero quality loses
+/// w of it. Unscored datasets count as [`NEUTRAL_QUALITY`]. Ties keep
+/// their previous order.
+pub fn apply_quality_weight(results: Vec<SearchResult>, weight: f32) -> Vec<SearchResult> {
+    let mut weighted: Vec<SearchResult> = results
+        .into_iter()
+        .map(|result| {
+            let quality = result.dataset.quality.unwrap_or(NEUTRAL_QUALITY);
+            SearchResult {
+                similarity_score: result.similarity_score * (1.0 - weight + weight * quality),
+                ..result
+            }
+        })
+        .collect();
+    weighted.sort_by(|a, b| b.similarity_score.total_cmp(&a.similarity_score));
+    weighted
+}
+
+/// Merges the result sets of several queries (e.g. paraphrases from
+/// [`expansion`](crate::expansion)), scoring each dataset by its best
+/// score, and returns the best `limit`.
+pub fn merge_result_sets(sets: Vec<Vec<SearchResult>>, limit: usize) -> Vec<SearchResult> {
+    let mut best: HashMap<Uuid, SearchResult> = HashMap::new();
+    for result in sets.into_iter().flatten() {
+        match best.get(&result.dataset.id) {
+            Some(kept) if kept.similarity_score >= result.similarity_score => {}
+            _ => {
+                best.insert(result.dataset.id, result);
+            }
+        }
+    }
+    let mut merged: Vec<SearchResult> = best.into_values().collect();
+    merged.sort_by(|a, b| {
+        b.similarity_score
+            .total_cmp(&a.similarity_score)
+            .then(a.dataset.id.cmp(&b.dataset.id))
+    });
+    merged.truncate(limit);
+    merged
+}
+
+/// Parses a date filter bound: a date (`2024-06-30`, midnight UTC) or an
+/// RFC 3339 timestamp.
+///
+/// # Examples
+///
+/// ```
+/// use ceres_core::search::parse_date_bound;
+///
+/// let bound = parse_date_bound("2024-06-30").unwrap();
+/// assert_eq!(bound.to_rfc3339(), "2024-06-30T00:00:00+00:00");
+/// assert!(parse_date_bound("last week").is_err());
+/// ```
+pub fn parse_date_bound(input: &str) -> Result<DateTime<Utc>, AppError> {
+    let input = input.trim();
+    NaiveDate::parse_from_str(input, "%Y-%m-%d")
+        .ok()
+        .and_then(|date| date.and_hms_opt(0, 0, 0))
+        .map(|dt| dt.and_utc())
+        .or_else(|| {
+            DateTime::parse_from_rfc3339(input)
+                .ok()
+                .map(|dt| dt.with_timezone(&Utc))
+        })
+        .ok_or_else(|| {
+            AppError::ConfigError(format!(
+                "Invalid date '{}' (expected YYYY-MM-DD or RFC 3339)",
+                input
+            ))
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn estimate(corpus: u64, filtered: u64, keyword: Option<u64>) -> CandidateEstimate {
+        CandidateEstimate {
+            corpus,
+            filtered,
+            keyword,
+        }
+    }
+
+    #[test]
+    fn test_filters_is_empty() {
+        assert!(SearchFilters::default().is_empty());
+        let filters = SearchFilters {
+            portal: Some("https://dati.gov.it".to_string()),
+            ..Default::default()
+        };
+        assert!(!filters.is_empty());
+        let filters = SearchFilters {
+            updated_before: Some(parse_date_bound("2024-01-01").unwrap()),
+            ..Default::default()
+        };
+        assert!(!filters.is_empty());
+    }
+
+    #[test]
+    fn test_parse_date_bound() {
+        let midnight = parse_date_bound("2024-06-30").unwrap();
+        assert_eq!(
+            parse_date_bound("2024-06-30T02:00:00+02:00").unwrap(),
+            midnight
+        );
+        assert!(parse_date_bound("2024-02-30").is_err());
+        assert!(parse_date_bound("").is_err());
+    }
+
+    #[test]
+    fn test_plan_direct_is_respected() {
+        let plan = plan_search(SearchStrategy::Direct, true, estimate(10, 5, None), 10);
+        assert_eq!(plan, SearchPlan::Direct);
+    }
+
+    #[test]
+    fn test_plan_small_unfiltered_corpus_is_direct() {
+        let plan = plan_search(
+            SearchStrategy::Auto,
+            false,
+            estimate(5_000, 5_000, None),
+            10,
+        );
+        assert_eq!(plan, SearchPlan::Direct);
+    }
+
+    #[test]
+    fn test_plan_selective_filter_is_two_stage() {
+        let plan = plan_search(
+            SearchStrategy::Auto,
+            true,
+            estimate(2_000_000, 3_000, None),
+            10,
+        );
+        assert_eq!(plan, SearchPlan::TwoStage(CandidateSet::Filters));
+    }
+
+    #[test]
+    fn test_plan_large_corpus_uses_keyword_pruning() {
+        let plan = plan_search(
+            SearchStrategy::Auto,
+            false,
+            estimate(2_000_000, 2_000_000, Some(8_000)),
+            10,
+        );
+        assert_eq!(plan, SearchPlan::TwoStage(CandidateSet::FiltersAndKeywords));
+    }
+
+    #[test]
+    fn test_plan_keyword_pruning_needs_full_page() {
+        let plan = plan_search(
+            SearchStrategy::Auto,
+            false,
+            estimate(2_000_000, 2_000_000, Some(3)),
+            10,
+        );
+        assert_eq!(plan, SearchPlan::Direct);
+    }
+
+    #[test]
+    fn test_plan_unselective_keywords_fall_back_to_direct() {
+        let plan = plan_search(
+            SearchStrategy::Auto,
+            false,
+            estimate(2_000_000, 2_000_000, Some(TWO_STAGE_MAX_CANDIDATES + 1)),
+            10,
+        );
+        assert_eq!(plan, SearchPlan::Direct);
+    }
+
+    #[test]
+    fn test_needs_keyword_estimate() {
+        assert!(!needs_keyword_estimate(
+            SearchStrategy::Auto,
+            2_000_000,
+            100
+        ));
+        assert!(needs_keyword_estimate(
+            SearchStrategy::Auto,
+            2_000_000,
+            2_000_000
+        ));
+        assert!(!needs_keyword_estimate(
+            SearchStrategy::Auto,
+            50_000,
+            50_000
+        ));
+        assert!(!needs_keyword_estimate(
+            SearchStrategy::Direct,
+            2_000_000,
+            2_000_000
+        ));
+    }
+
+    #[test]
+    fn test_reciprocal_rank_fusion() {
+        let (a, b, c) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        // `b` is second in both rankings, `a` and `c` first in one each
+        let fused = reciprocal_rank_fusion(&[vec![a, b], vec![c, b, a]]);
+        let order: Vec<Uuid> = fused.iter().map(|(id, _)| *id).collect();
+        assert_eq!(order, vec![a, b, c]);
+        assert!(fused.iter().all(|(_, score)| *score > 0.0 && *score < 1.0));
+
+        assert!(reciprocal_rank_fusion(&[]).is_empty());
+    }
+
+    fn result(title: &str, score: f32) -> SearchResult {
+        SearchResult {
+            dataset: Dataset {
+                id: Uuid::new_v4(),
+                original_id: title.to_string(),
+                source_portal: "https://example.com".to_string(),
+                url: "https://example.com".to_string(),
+                title: title.to_string(),
+                description: Some("  ".to_string()),
+                embedding: None,
+                metadata: sqlx::types::Json(serde_json::json!({})),
+                formats: Vec::new(),
+                first_seen_at: Utc::now(),
+                last_updated_at: Utc::now(),
+                content_hash: None,
+                embedding_model: None,
+                embedded_at: None,
+                modified_at: None,
+                language: None,
+                quality: None,
+            },
+            similarity_score: score,
+        }
+    }
+
+    #[test]
+    fn test_apply_rerank_scores() {
+        let results = vec![result("a", 0.9), result("b", 0.8), result("c", 0.7)];
+        assert_eq!(rerank_document(&results[0].dataset), "a");
+
+        let reranked = apply_rerank_scores(results, &[0.2, 0.95, 0.2], 2);
+        let titles: Vec<&str> = reranked.iter().map(|r| r.dataset.title.as_str()).collect();
+        assert_eq!(titles, vec!["b", "a"]);
+        assert_eq!(reranked[0].similarity_score, 0.95);
+    }
+
+    #[test]
+    fn test_apply_quality_weight() {
+        let mut sparse = result("sparse", 0.8);
+        sparse.dataset.quality = Some(0.1);
+        let mut documented = result("documented", 0.7);
+        documented.dataset.quality = Some(1.0);
+        let unscored = result("unscored", 0.75);
+
+        let weighted = apply_quality_weight(vec![sparse, unscored, documented], 0.5);
+        let titles: Vec<&str> = weighted.iter().map(|r| r.dataset.title.as_str()).collect();
+        assert_eq!(titles, vec!["documented", "unscored", "sparse"]);
+        assert_eq!(weighted[0].similarity_score, 0.7);
+        assert!((weighted[2].similarity_score - 0.8 * 0.55).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_merge_result_sets_keeps_best_score() {
+        let (a, b) = (result("a", 0.6), result("b", 0.7));
+        let a_better = SearchResult {
+            similarity_score: 0.9,
+            ..a.clone()
+        };
+        let merged = merge_result_sets(vec![vec![b, a], vec![a_better]], 5);
+        let scores: Vec<(&str, f32)> = merged
+            .iter()
+            .map(|r| (r.dataset.title.as_str(), r.similarity_score))
+            .collect();
+        assert_eq!(scores, vec![("a", 0.9), ("b", 0.7)]);
+    }
+
+    #[test]
+    fn test_keyword_tsquery() {
+        assert_eq!(
+            keyword_tsquery("trasporto pubblico").as_deref(),
+            Some("trasporto | pubblico")
+        );
+        assert_eq!(keyword_tsquery("mobilità").as_deref(), Some("mobilità"));
+        assert_eq!(keyword_tsquery("   "), None);
+        // Operators cannot be injected into the tsquery
+        assert_eq!(
+            keyword_tsquery("foo & !bar | baz").as_deref(),
+            Some("foo | bar | baz")
+        );
+    }
+}
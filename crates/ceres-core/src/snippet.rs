@@ -0,0 +1,256 @@
+//! Query-focused snippets of dataset descriptions.
+//!
+//! Descriptions often open with boilerplate ("Il dataset contiene i dati
+//! relativi a..."), so search results show the sentences that share the
+//! most words with the query instead of the first characters. Words are
+//! compared lowercased and without accents, and long words also match on a
+//! common stem, so "trasporti" finds "trasporto". Matching is by keyword
+//! rather than by embedding, which would take an embedding call per
+//! sentence of every result.
+
+use std::cmp::Reverse;
+use std::ops::Range;
+
+use crate::clustering::STOPWORDS;
+use crate::tags::fold_char;
+
+/// Shortest query word looked for in descriptions.
+const MIN_TERM_CHARS: usize = 3;
+
+/// Shortest words that match on a common stem rather than exactly.
+const MIN_STEM_CHARS: usize = 5;
+
+/// Part of a description to show with a search result.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Snippet {
+    pub text: String,
+    /// Byte ranges of `text` holding words of the query, in order
+    pub highlights: Vec<Range<usize>>,
+}
+
+/// A word of a text and its folded form.
+struct Word {
+    range: Range<usize>,
+    folded: String,
+}
+
+/// Returns at most about `max_chars` characters of `text`, from the
+/// sentence matching the most words of `query` on, with the matches marked.
+///
+/// Without any match, the snippet is the start of `text`. Cut ends are
+/// marked with "...".
+///
+/// # Examples
+///
+/// ```
+/// use ceres_core::snippet::snippet;
+///
+/// let description = "Il dataset contiene dati aperti. Fermate degli autobus urbani.";
+/// let snippet = snippet(description, "fermate autobus", 80);
+/// assert_eq!(snippet.text, "...Fermate degli autobus urbani.");
+/// assert_eq!(&snippet.text[snippet.highlights[0].clone()], "Fermate");
+/// ```
+pub fn snippet(text: &str, query: &str, max_chars: usize) -> Snippet {
+    let text = text.split_whitespace().collect::<Vec<_>>().join(" ");
+    let terms: Vec<String> = words(query)
+        .into_iter()
+        .map(|word| word.folded)
+        .filter(|term| {
+            term.chars().count() >= MIN_TERM_CHARS && !STOPWORDS.contains(&term.as_str())
+        })
+        .collect();
+    let matches: Vec<(Range<usize>, usize)> = words(&text)
+        .into_iter()
+        .filter_map(|word| {
+            let term = terms
+                .iter()
+                .position(|term| matches_term(&word.folded, term))?;
+            Some((word.range, term))
+        })
+        .collect();
+
+    // Sentence sharing the most distinct words with the query, then the most words
+    let sentences = sentences(&text);
+    let start = sentences
+        .iter()
+        .map(|sentence| {
+            let mut found: Vec<usize> = matches
+                .iter()
+                .filter(|(range, _)| sentence.contains(&range.start))
+                .map(|&(_, term)| term)
+                .collect();
+            let total = found.len();
+            found.sort_unstable();
+            found.dedup();
+            (found.len(), total, sentence.start)
+        })
+        .filter(|&(distinct, _, _)| distinct > 0)
+        // Ties go to the earlier sentence
+        .max_by_key(|&(distinct, total, start)| (distinct, total, Reverse(start)))
+        .map_or(0, |(_, _, start)| start);
+
+    // A sentence too long to show whole is cut to begin near its first match
+    let first_match = matches
+        .iter()
+        .map(|(range, _)| range.start)
+        .find(|&i| i >= start);
+    let start = match first_match {
+        Some(i)
+            if text[start..].chars().count() > max_chars
+                && text[start..i].chars().count() > max_chars / 3 =>
+        {
+            let lead = text[..i]
+                .char_indices()
+                .rev()
+                .nth(max_chars / 4)
+                .map_or(0, |(j, _)| j);
+            // Start at a word
+            text[lead..i].find(' ').map_or(i, |space| lead + space + 1)
+        }
+        _ => start,
+    };
+    let end = cut(&text, start, max_chars);
+
+    let prefix = if start > 0 { "..." } else { "" };
+    let suffix = if end < text.len() { "..." } else { "" };
+    let highlights = matches
+        .iter()
+        .filter(|(range, _)| range.start >= start && range.end <= end)
+        .map(|(range, _)| range.start - start + prefix.len()..range.end - start + prefix.len())
+        .collect();
+    Snippet {
+        text: format!("{}{}{}", prefix, &text[start..end], suffix),
+        highlights,
+    }
+}
+
+/// End of the longest run of whole words from `start` within `max_chars`.
+fn cut(text: &str, start: usize, max_chars: usize) -> usize {
+    let rest = &text[start..];
+    let Some((limit, _)) = rest.char_indices().nth(max_chars) else {
+        return text.len();
+    };
+    match rest[..limit].rfind(' ') {
+        Some(space) if space > 0 => start + space,
+        _ => start + limit,
+    }
+}
+
+/// Byte ranges of the sentences of `text`, each ending after its `.`, `!`,
+/// `?` or `;` and the space following it.
+fn sentences(text: &str) -> Vec<Range<usize>> {
+    let mut sentences = Vec::new();
+    let mut start = 0;
+    let mut chars = text.char_indices().peekable();
+    while let Some((i, c)) = chars.next() {
+        if matches!(c, '.' | '!' | '?' | ';') && chars.peek().is_some_and(|&(_, next)| next == ' ')
+        {
+            sentences.push(start..i + 2);
+            start = i + 2;
+        }
+    }
+    if start < text.len() {
+        sentences.push(start..text.len());
+    }
+    sentences
+}
+
+/// The words of `text`, folded like normalized tags.
+fn words(text: &str) -> Vec<Word> {
+    let mut words = Vec::new();
+    let mut start = None;
+    for (i, c) in text.char_indices().chain([(text.len(), ' ')]) {
+        match (c.is_alphanumeric(), start) {
+            (true, None) => start = Some(i),
+            (false, Some(s)) => {
+                words.push(Word {
+                    range: s..i,
+                    folded: text[s..i].chars().flat_map(fold_char).collect(),
+                });
+                start = None;
+            }
+            _ => {}
+        }
+    }
+    words
+}
+
+/// Returns true if `word` is `term`, or both are long words sharing all but
+/// their last two characters.
+fn matches_term(word: &str, term: &str) -> bool {
+    if word == term {
+        return true;
+    }
+    let shorter = word.chars().count().min(term.chars().count());
+    if shorter < MIN_STEM_CHARS {
+        return false;
+    }
+    let common = word
+        .chars()
+        .zip(term.chars())
+        .take_while(|(a, b)| a == b)
+        .count();
+    common >= shorter - 2
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn highlighted(snippet: &Snippet) -> Vec<&str> {
+        snippet
+            .highlights
+            .iter()
+            .map(|range| &snippet.text[range.clone()])
+            .collect()
+    }
+
+    #[test]
+    fn test_snippet_picks_the_best_sentence() {
+        let description = "Il dataset contiene i dati pubblicati dal Comune. \
+            Aggiornamento mensile. Qualità dell'aria: concentrazioni di PM10 e PM2.5 \
+            rilevate dalle centraline.";
+        let snippet = snippet(description, "qualita aria pm10", 200);
+        assert_eq!(
+            snippet.text,
+            "...Qualità dell'aria: concentrazioni di PM10 e PM2.5 rilevate dalle centraline."
+        );
+        assert_eq!(highlighted(&snippet), vec!["Qualità", "aria", "PM10"]);
+    }
+
+    #[test]
+    fn test_snippet_matches_stems() {
+        let snippet = snippet(
+            "Orari dei trasporti pubblici locali.",
+            "trasporto pubblico",
+            80,
+        );
+        assert_eq!(highlighted(&snippet), vec!["trasporti", "pubblici"]);
+        assert!(!matches_term("bus", "buses"));
+    }
+
+    #[test]
+    fn test_snippet_without_matches_starts_at_the_beginning() {
+        let snippet = snippet(
+            "Elenco delle   scuole\nprimarie della città.",
+            "ospedali",
+            20,
+        );
+        assert_eq!(snippet.text, "Elenco delle scuole...");
+        assert!(snippet.highlights.is_empty());
+    }
+
+    #[test]
+    fn test_snippet_cuts_long_sentences_near_the_match() {
+        let description = format!(
+            "{} parcheggi di interscambio {}",
+            "parola ".repeat(40),
+            "fine ".repeat(40)
+        );
+        let snippet = snippet(&description, "parcheggi", 60);
+        assert!(snippet.text.starts_with("...parola"));
+        assert!(snippet.text.ends_with("..."));
+        assert!(snippet.text.chars().count() <= 66);
+        assert_eq!(highlighted(&snippet), vec!["parcheggi"]);
+    }
+}
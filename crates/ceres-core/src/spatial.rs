@@ -0,0 +1,306 @@
+//! Geographic extents of datasets.
+//!
+//! Many CKAN portals (those running ckanext-spatial, or DCAT-AP profiles)
+//! describe where a dataset applies with a `spatial` extra holding GeoJSON.
+//! Its bounding box is stored at harvest, so `ceres search --bbox` and
+//! `--near` can select datasets whose extent overlaps an area.
+//!
+//! Coordinates are WGS 84 longitude/latitude in degrees, as in GeoJSON.
+
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::error::AppError;
+
+/// Kilometers per degree of latitude (and of longitude at the equator).
+const KM_PER_DEGREE: f64 = 111.32;
+
+/// A longitude/latitude rectangle.
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub struct BoundingBox {
+    pub min_lon: f64,
+    pub min_lat: f64,
+    pub max_lon: f64,
+    pub max_lat: f64,
+}
+
+impl BoundingBox {
+    /// Creates a box, checking that coordinates are in range and ordered.
+    ///
+    /// # Errors
+    ///
+    /// Returns `AppError::ConfigError` if a longitude is outside -180..180,
+    /// a latitude outside -90..90, or a minimum exceeds its maximum.
+    pub fn new(min_lon: f64, min_lat: f64, max_lon: f64, max_lat: f64) -> Result<Self, AppError> {
+        let lon_ok = |v: f64| (-180.0..=180.0).contains(&v);
+        let lat_ok = |v: f64| (-90.0..=90.0).contains(&v);
+        if !(lon_ok(min_lon) && lon_ok(max_lon) && lat_ok(min_lat) && lat_ok(max_lat)) {
+            return Err(AppError::ConfigError(
+                "Coordinates out of range: longitudes must be within -180..180, latitudes within -90..90"
+                    .to_string(),
+            ));
+        }
+        if min_lon > max_lon || min_lat > max_lat {
+            return Err(AppError::ConfigError(
+                "Invalid bounding box: expected minx,miny,maxx,maxy with min <= max".to_string(),
+            ));
+        }
+        Ok(Self {
+            min_lon,
+            min_lat,
+            max_lon,
+            max_lat,
+        })
+    }
+
+    /// The box around a point extending `km` in each direction, clamped to
+    /// valid coordinates.
+    pub fn around(lat: f64, lon: f64, km: f64) -> Self {
+        let d_lat = km / KM_PER_DEGREE;
+        let cos_lat = lat.to_radians().cos();
+        let d_lon = if cos_lat > 1e-6 {
+            km / (KM_PER_DEGREE * cos_lat)
+        } else {
+            180.0
+        };
+        Self {
+            min_lon: (lon - d_lon).max(-180.0),
+            min_lat: (lat - d_lat).max(-90.0),
+            max_lon: (lon + d_lon).min(180.0),
+            max_lat: (lat + d_lat).min(90.0),
+        }
+    }
+
+    /// Returns true if the boxes share at least one point (edges included),
+    /// like PostgreSQL's `&&` on boxes.
+    pub fn overlaps(&self, other: &BoundingBox) -> bool {
+        self.min_lon <= other.max_lon
+            && other.min_lon <= self.max_lon
+            && self.min_lat <= other.max_lat
+            && other.min_lat <= self.max_lat
+    }
+
+    /// Bounding box of a GeoJSON geometry, feature or feature collection.
+    ///
+    /// A `bbox` member is used as is; otherwise the box spans every
+    /// position. Returns `None` if there is no valid position.
+    pub fn from_geojson(geojson: &Value) -> Option<Self> {
+        if let Some(bbox) = geojson["bbox"].as_array() {
+            let numbers: Vec<f64> = bbox.iter().filter_map(Value::as_f64).collect();
+            if let [min_lon, min_lat, max_lon, max_lat] = numbers[..] {
+                return Self::new(min_lon, min_lat, max_lon, max_lat).ok();
+            }
+        }
+        let mut extent: Option<Self> = None;
+        collect_positions(geojson, &mut extent);
+        extent
+    }
+
+    fn include(extent: &mut Option<Self>, lon: f64, lat: f64) {
+        *extent = Some(match *extent {
+            Some(b) => Self {
+                min_lon: b.min_lon.min(lon),
+                min_lat: b.min_lat.min(lat),
+                max_lon: b.max_lon.max(lon),
+                max_lat: b.max_lat.max(lat),
+            },
+            None => Self {
+                min_lon: lon,
+                min_lat: lat,
+                max_lon: lon,
+                max_lat: lat,
+            },
+        });
+    }
+}
+
+/// Adds the positions of a GeoJSON object to `extent`.
+fn collect_positions(geojson: &Value, extent: &mut Option<BoundingBox>) {
+    match geojson["type"].as_str() {
+        Some("Feature") => collect_positions(&geojson["geometry"], extent),
+        Some("FeatureCollection") => {
+            for feature in geojson["features"].as_array().into_iter().flatten() {
+                collect_positions(feature, extent);
+            }
+        }
+        Some("GeometryCollection") => {
+            for geometry in geojson["geometries"].as_array().into_iter().flatten() {
+                collect_positions(geometry, extent);
+            }
+        }
+        _ => collect_coordinates(&geojson["coordinates"], extent),
+    }
+}
+
+/// Adds the positions in nested coordinate arrays to `extent`.
+fn collect_coordinates(coordinates: &Value, extent: &mut Option<BoundingBox>) {
+    let Some(items) = coordinates.as_array() else {
+        return;
+    };
+    match (
+        items.first().and_then(Value::as_f64),
+        items.get(1).and_then(Value::as_f64),
+    ) {
+        (Some(lon), Some(lat)) => {
+            if (-180.0..=180.0).contains(&lon) && (-90.0..=90.0).contains(&lat) {
+                BoundingBox::include(extent, lon, lat);
+            }
+        }
+        _ => {
+            for item in items {
+                collect_coordinates(item, extent);
+            }
+        }
+    }
+}
+
+/// Extracts a dataset's extent from its CKAN metadata.
+///
+/// Reads the GeoJSON in a top-level `spatial` field or a `spatial` entry of
+/// `extras`, given either as an object or as a JSON string.
+///
+/// # Examples
+///
+/// ```
+/// use ceres_core::spatial::dataset_bbox;
+/// use serde_json::json;
+///
+/// let metadata = json!({"extras": [
+///     {"key": "spatial", "value": "{\"type\": \"Point\", \"coordinates\": [9.19, 45.46]}"}
+/// ]});
+/// let bbox = dataset_bbox(&metadata).unwrap();
+/// assert_eq!((bbox.min_lon, bbox.max_lat), (9.19, 45.46));
+/// ```
+pub fn dataset_bbox(metadata: &Value) -> Option<BoundingBox> {
+    let extra = metadata["extras"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .find(|e| e["key"].as_str() == Some("spatial"))
+        .map(|e| &e["value"]);
+    let spatial = match &metadata["spatial"] {
+        Value::Null => extra?,
+        value => value,
+    };
+    match spatial {
+        Value::String(text) => BoundingBox::from_geojson(&serde_json::from_str(text).ok()?),
+        value => BoundingBox::from_geojson(value),
+    }
+}
+
+/// Parses `minx,miny,maxx,maxy` (longitudes and latitudes) for `--bbox`.
+///
+/// # Errors
+///
+/// Returns `AppError::ConfigError` for malformed or invalid boxes.
+pub fn parse_bbox(input: &str) -> Result<BoundingBox, AppError> {
+    match parse_numbers(input)?[..] {
+        [min_lon, min_lat, max_lon, max_lat] => {
+            BoundingBox::new(min_lon, min_lat, max_lon, max_lat)
+        }
+        _ => Err(AppError::ConfigError(format!(
+            "Invalid bounding box '{}': expected minx,miny,maxx,maxy",
+            input
+        ))),
+    }
+}
+
+/// Parses `lat,lon,km` for `--near` into the box around that point.
+///
+/// # Errors
+///
+/// Returns `AppError::ConfigError` for malformed input, coordinates out of
+/// range or a non-positive distance.
+pub fn parse_near(input: &str) -> Result<BoundingBox, AppError> {
+    match parse_numbers(input)?[..] {
+        [lat, lon, km] if km > 0.0 => {
+            BoundingBox::new(lon, lat, lon, lat)?;
+            Ok(BoundingBox::around(lat, lon, km))
+        }
+        _ => Err(AppError::ConfigError(format!(
+            "Invalid location '{}': expected lat,lon,km with a positive distance",
+            input
+        ))),
+    }
+}
+
+fn parse_numbers(input: &str) -> Result<Vec<f64>, AppError> {
+    input
+        .split(',')
+        .map(|part| {
+            part.trim()
+                .parse::<f64>()
+                .ok()
+                .filter(|v| v.is_finite())
+                .ok_or_else(|| AppError::ConfigError(format!("Invalid number '{}'", part.trim())))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_from_geojson() {
+        let polygon = json!({"type": "Polygon", "coordinates": [[
+            [9.04, 45.38], [9.28, 45.38], [9.28, 45.54], [9.04, 45.54], [9.04, 45.38]
+        ]]});
+        assert_eq!(
+            BoundingBox::from_geojson(&polygon),
+            Some(BoundingBox::new(9.04, 45.38, 9.28, 45.54).unwrap())
+        );
+
+        let collection = json!({"type": "FeatureCollection", "features": [
+            {"type": "Feature", "geometry": {"type": "Point", "coordinates": [7.68, 45.07]}},
+            {"type": "Feature", "geometry": {"type": "MultiPoint", "coordinates": [[12.49, 41.9]]}}
+        ]});
+        assert_eq!(
+            BoundingBox::from_geojson(&collection),
+            Some(BoundingBox::new(7.68, 41.9, 12.49, 45.07).unwrap())
+        );
+
+        let with_bbox =
+            json!({"type": "Polygon", "bbox": [6.6, 35.5, 18.5, 47.1], "coordinates": []});
+        assert_eq!(BoundingBox::from_geojson(&with_bbox).unwrap().max_lon, 18.5);
+        assert_eq!(BoundingBox::from_geojson(&json!({"type": "Point"})), None);
+    }
+
+    #[test]
+    fn test_overlaps() {
+        let milan = BoundingBox::new(9.04, 45.38, 9.28, 45.54).unwrap();
+        assert!(milan.overlaps(&BoundingBox::around(45.46, 9.19, 1.0)));
+        assert!(milan.overlaps(&BoundingBox::new(9.28, 45.54, 10.0, 46.0).unwrap()));
+        assert!(!milan.overlaps(&BoundingBox::new(12.4, 41.8, 12.6, 42.0).unwrap()));
+    }
+
+    #[test]
+    fn test_dataset_bbox_sources() {
+        let point = json!({"type": "Point", "coordinates": [9.19, 45.46]});
+        assert!(dataset_bbox(&json!({ "spatial": point })).is_some());
+        assert!(dataset_bbox(&json!({ "spatial": point.to_string() })).is_some());
+        assert!(
+            dataset_bbox(&json!({"extras": [{"key": "spatial", "value": "not json"}]})).is_none()
+        );
+        assert!(dataset_bbox(&json!({})).is_none());
+    }
+
+    #[test]
+    fn test_parse_bbox_and_near() {
+        assert_eq!(
+            parse_bbox("9.0, 45.3, 9.3, 45.6").unwrap(),
+            BoundingBox::new(9.0, 45.3, 9.3, 45.6).unwrap()
+        );
+        assert!(parse_bbox("9.3,45.3,9.0,45.6").is_err());
+        assert!(parse_bbox("9,45,200,46").is_err());
+        assert!(parse_bbox("9,45,10").is_err());
+
+        let near = parse_near("45.46,9.19,10").unwrap();
+        assert!((near.max_lat - near.min_lat - 2.0 * 10.0 / KM_PER_DEGREE).abs() < 1e-9);
+        // Longitude degrees shrink away from the equator
+        assert!(near.max_lon - near.min_lon > near.max_lat - near.min_lat);
+        assert!(parse_near("45.46,9.19,0").is_err());
+        assert!(parse_near("95,9.19,10").is_err());
+    }
+}
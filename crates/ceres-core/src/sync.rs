@@ -3,8 +3,18 @@
 //! This module provides pure business logic for delta detection and sync statistics,
 //! decoupled from I/O operations and CLI orchestration.
 
+use std::collections::BTreeSet;
+
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+use uuid::Uuid;
+
+use crate::error::AppError;
+
 /// Outcome of processing a single dataset during sync.
-#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
 pub enum SyncOutcome {
     /// Dataset content hash matches existing - no changes needed
     Unchanged,
@@ -16,13 +26,48 @@ pub enum SyncOutcome {
     Failed,
 }
 
+/// What an upsert did in the database, with the dataset's UUID.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UpsertOutcome {
+    /// A new dataset row was inserted
+    Created(Uuid),
+    /// An existing dataset row was updated
+    Updated(Uuid),
+}
+
+impl UpsertOutcome {
+    /// The dataset's UUID.
+    pub fn id(self) -> Uuid {
+        match self {
+            Self::Created(id) | Self::Updated(id) => id,
+        }
+    }
+
+    /// The sync outcome to record for a dataset stored this way.
+    pub fn sync_outcome(self) -> SyncOutcome {
+        match self {
+            Self::Created(_) => SyncOutcome::Created,
+            Self::Updated(_) => SyncOutcome::Updated,
+        }
+    }
+}
+
 /// Statistics for a portal sync operation.
-#[derive(Debug, Default, Clone)]
+#[derive(Debug, Default, Clone, Serialize)]
 pub struct SyncStats {
     pub unchanged: usize,
     pub updated: usize,
     pub created: usize,
     pub failed: usize,
+    /// Failed datasets by kind of error
+    pub failed_by_kind: FailureCounts,
+    /// Embeddings reused from the embedding cache
+    pub cache_hits: usize,
+    /// Embeddings computed by the provider
+    pub cache_misses: usize,
+    /// Whether a shutdown stopped the harvest before it reached every
+    /// dataset
+    pub interrupted: bool,
 }
 
 impl SyncStats {
@@ -41,6 +86,12 @@ impl SyncStats {
         }
     }
 
+    /// Records a failed dataset with the kind of error that failed it.
+    pub fn record_failure(&mut self, kind: FailureKind) {
+        self.failed += 1;
+        self.failed_by_kind.add(kind, 1);
+    }
+
     /// Returns the total number of processed datasets.
     pub fn total(&self) -> usize {
         self.unchanged + self.updated + self.created + self.failed
@@ -50,6 +101,13 @@ impl SyncStats {
     pub fn successful(&self) -> usize {
         self.unchanged + self.updated + self.created
     }
+
+    /// Returns the share of needed embeddings served from the cache, or
+    /// `None` if nothing was embedded.
+    pub fn cache_hit_rate(&self) -> Option<f64> {
+        let lookups = self.cache_hits + self.cache_misses;
+        (lookups > 0).then(|| self.cache_hits as f64 / lookups as f64)
+    }
 }
 
 /// Result of delta detection for a dataset.
@@ -64,6 +122,15 @@ pub struct ReprocessingDecision {
 }
 
 impl ReprocessingDecision {
+    /// Decision for a dataset whose content hash matches the stored one.
+    pub fn unchanged() -> Self {
+        Self {
+            needs_embedding: false,
+            outcome: SyncOutcome::Unchanged,
+            reason: "content hash matches",
+        }
+    }
+
     /// Returns true if this is a legacy record update (existing record without hash).
     pub fn is_legacy(&self) -> bool {
         self.reason == "legacy record without hash"
@@ -84,14 +151,8 @@ pub fn needs_reprocessing(
     new_hash: &str,
 ) -> ReprocessingDecision {
     match existing_hash {
-        Some(Some(hash)) if hash == new_hash => {
-            // Hash matches - content unchanged
-            ReprocessingDecision {
-                needs_embedding: false,
-                outcome: SyncOutcome::Unchanged,
-                reason: "content hash matches",
-            }
-        }
+        // Hash matches - content unchanged
+        Some(Some(hash)) if hash == new_hash => ReprocessingDecision::unchanged(),
         Some(Some(_)) => {
             // Hash exists but differs - content updated
             ReprocessingDecision {
@@ -119,21 +180,270 @@ pub fn needs_reprocessing(
     }
 }
 
+// =============================================================================
+// Per-dataset Outcome Log
+// =============================================================================
+
+/// Time spent in each stage of processing a dataset, in milliseconds.
+///
+/// Stages that did not run (e.g. embedding an unchanged dataset) are `None`.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct StageDurations {
+    /// Fetching the dataset from the portal
+    pub fetch_ms: Option<u64>,
+    /// Generating the embedding
+    pub embed_ms: Option<u64>,
+    /// Writing to the database
+    pub store_ms: Option<u64>,
+    /// Wall-clock time for the whole dataset
+    pub total_ms: u64,
+}
+
+/// One line of the outcome log written by `ceres harvest --outcome-log`.
+#[derive(Debug, Clone, Serialize)]
+pub struct DatasetOutcomeRecord {
+    pub timestamp: DateTime<Utc>,
+    /// Portal base URL
+    pub portal: String,
+    /// Dataset identifier on the portal
+    pub id: String,
+    pub outcome: SyncOutcome,
+    pub durations: StageDurations,
+    /// Error message for failed datasets
+    pub error: Option<String>,
+}
+
+/// Stage of a harvest at which a dataset failed.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FailureStage {
+    /// Fetching the dataset from the portal
+    Fetch,
+    /// Looking up its stored content hash
+    Detect,
+    /// Generating its embedding
+    Embed,
+    /// Storing it in the database
+    Store,
+}
+
+impl FailureStage {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FailureStage::Fetch => "fetch",
+            FailureStage::Detect => "detect",
+            FailureStage::Embed => "embed",
+            FailureStage::Store => "store",
+        }
+    }
+}
+
+/// A dataset a harvest failed to process, kept in the `harvest_failures`
+/// dead-letter table until a later harvest gets past it.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct HarvestFailure {
+    /// Portal base URL
+    pub portal_url: String,
+    /// Dataset identifier on the portal
+    pub dataset_id: String,
+    /// [`FailureStage`] of the latest failure, as text
+    pub stage: String,
+    pub error: String,
+    /// Harvests that failed the dataset since it was last processed
+    pub attempts: u32,
+    pub failed_at: DateTime<Utc>,
+}
+
+impl HarvestFailure {
+    /// A failure of `dataset_id` at `stage`, now.
+    pub fn new(portal_url: &str, dataset_id: &str, stage: FailureStage, error: String) -> Self {
+        Self {
+            portal_url: portal_url.to_string(),
+            dataset_id: dataset_id.to_string(),
+            stage: stage.as_str().to_string(),
+            error,
+            attempts: 1,
+            failed_at: Utc::now(),
+        }
+    }
+}
+
+/// How far an interrupted harvest of a portal got, saved as it goes so the
+/// next harvest resumes there rather than from the start.
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct HarvestCheckpoint {
+    /// Portal base URL
+    pub portal_url: String,
+    /// Datasets from the start of the portal's listing the harvest finished
+    pub offset: usize,
+    /// Start of the interrupted harvest, before which failures are resolved
+    /// once a resumed harvest completes
+    pub started_at: DateTime<Utc>,
+}
+
+/// The datasets of a harvest finished so far, by position in the portal's
+/// listing. Datasets finish out of order, so the checkpoint offset is the
+/// run of finished ones from the start.
+#[derive(Debug, Clone, Default)]
+pub struct ListingProgress {
+    offset: usize,
+    /// Finished positions past `offset`
+    ahead: BTreeSet<usize>,
+}
+
+impl ListingProgress {
+    /// Progress of a harvest resuming after the first `offset` datasets.
+    pub fn new(offset: usize) -> Self {
+        Self {
+            offset,
+            ahead: BTreeSet::new(),
+        }
+    }
+
+    /// Marks the dataset at `position` finished and returns the offset.
+    pub fn finish(&mut self, position: usize) -> usize {
+        if position >= self.offset {
+            self.ahead.insert(position);
+        }
+        while self.ahead.remove(&self.offset) {
+            self.offset += 1;
+        }
+        self.offset
+    }
+
+    /// Datasets from the start of the listing finished so far.
+    pub fn offset(&self) -> usize {
+        self.offset
+    }
+}
+
+/// Kind of error that failed a dataset, for the breakdown in harvest
+/// summaries.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum FailureKind {
+    /// The portal could not be reached or refused the request
+    Fetch,
+    /// The portal answered with a response that is not a CKAN dataset
+    Parse,
+    /// The embedding provider failed
+    Embedding,
+    /// The database failed
+    Database,
+}
+
+impl FailureKind {
+    /// Every kind, in the order summaries list them.
+    pub const ALL: [FailureKind; 4] = [
+        FailureKind::Fetch,
+        FailureKind::Parse,
+        FailureKind::Embedding,
+        FailureKind::Database,
+    ];
+
+    /// The kind of `error`, raised at `stage`.
+    ///
+    /// Fetches fail on the network or on the response; every later stage
+    /// fails in its own service.
+    pub fn of(stage: FailureStage, error: &AppError) -> Self {
+        match stage {
+            FailureStage::Fetch => match error {
+                AppError::SerializationError(_) => FailureKind::Parse,
+                _ => FailureKind::Fetch,
+            },
+            FailureStage::Embed => FailureKind::Embedding,
+            FailureStage::Detect | FailureStage::Store => FailureKind::Database,
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            FailureKind::Fetch => "fetch",
+            FailureKind::Parse => "parse",
+            FailureKind::Embedding => "embedding",
+            FailureKind::Database => "database",
+        }
+    }
+}
+
+/// Failed datasets counted by [`FailureKind`].
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct FailureCounts {
+    pub fetch: usize,
+    pub parse: usize,
+    pub embedding: usize,
+    pub database: usize,
+}
+
+impl FailureCounts {
+    /// Adds `count` failures of `kind`.
+    pub fn add(&mut self, kind: FailureKind, count: usize) {
+        *self.count_mut(kind) += count;
+    }
+
+    /// The failures of `kind`.
+    pub fn get(&self, kind: FailureKind) -> usize {
+        match kind {
+            FailureKind::Fetch => self.fetch,
+            FailureKind::Parse => self.parse,
+            FailureKind::Embedding => self.embedding,
+            FailureKind::Database => self.database,
+        }
+    }
+
+    /// The kinds with failures and their counts, in [`FailureKind::ALL`]
+    /// order.
+    pub fn nonzero(&self) -> Vec<(FailureKind, usize)> {
+        FailureKind::ALL
+            .into_iter()
+            .map(|kind| (kind, self.get(kind)))
+            .filter(|(_, count)| *count > 0)
+            .collect()
+    }
+
+    fn count_mut(&mut self, kind: FailureKind) -> &mut usize {
+        match kind {
+            FailureKind::Fetch => &mut self.fetch,
+            FailureKind::Parse => &mut self.parse,
+            FailureKind::Embedding => &mut self.embedding,
+            FailureKind::Database => &mut self.database,
+        }
+    }
+}
+
 // =============================================================================
 // Batch Harvest Types
 // =============================================================================
 
+/// How the harvest of a single portal in batch mode ended.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PortalHarvestStatus {
+    /// The portal was harvested.
+    Success,
+    /// The harvest failed.
+    Failed,
+    /// The portal was not harvested, e.g. because another harvest held
+    /// its lock.
+    Skipped,
+}
+
 /// Result of harvesting a single portal in batch mode.
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize)]
 pub struct PortalHarvestResult {
     /// Portal name identifier.
     pub portal_name: String,
     /// Portal URL.
     pub portal_url: String,
+    /// How the harvest ended.
+    pub status: PortalHarvestStatus,
     /// Sync statistics for this portal.
     pub stats: SyncStats,
-    /// Error message if harvest failed, None if successful.
+    /// Why the harvest failed or was skipped, None if successful.
     pub error: Option<String>,
+    /// End of the quarantine the harvest put the portal in, if it failed
+    /// once too often.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub quarantined_until: Option<DateTime<Utc>>,
 }
 
 impl PortalHarvestResult {
@@ -142,8 +452,10 @@ impl PortalHarvestResult {
         Self {
             portal_name: name,
             portal_url: url,
+            status: PortalHarvestStatus::Success,
             stats,
             error: None,
+            quarantined_until: None,
         }
     }
 
@@ -152,19 +464,34 @@ impl PortalHarvestResult {
         Self {
             portal_name: name,
             portal_url: url,
+            status: PortalHarvestStatus::Failed,
             stats: SyncStats::default(),
             error: Some(error),
+            quarantined_until: None,
+        }
+    }
+
+    /// Creates the result of a portal left unharvested for `reason`.
+    pub fn skipped(name: String, url: String, reason: String) -> Self {
+        Self {
+            status: PortalHarvestStatus::Skipped,
+            ..Self::failure(name, url, reason)
         }
     }
 
     /// Returns true if the harvest was successful.
     pub fn is_success(&self) -> bool {
-        self.error.is_none()
+        self.status == PortalHarvestStatus::Success
+    }
+
+    /// Returns true if the harvest failed.
+    pub fn is_failure(&self) -> bool {
+        self.status == PortalHarvestStatus::Failed
     }
 }
 
 /// Aggregated results from batch harvesting multiple portals.
-#[derive(Debug, Clone, Default)]
+#[derive(Debug, Clone, Default, Serialize)]
 pub struct BatchHarvestSummary {
     /// Results for each portal.
     pub results: Vec<PortalHarvestResult>,
@@ -188,7 +515,20 @@ impl BatchHarvestSummary {
 
     /// Returns the count of failed harvests.
     pub fn failed_count(&self) -> usize {
-        self.results.iter().filter(|r| !r.is_success()).count()
+        self.results.iter().filter(|r| r.is_failure()).count()
+    }
+
+    /// Returns the count of portals left unharvested.
+    pub fn skipped_count(&self) -> usize {
+        self.results
+            .iter()
+            .filter(|r| r.status == PortalHarvestStatus::Skipped)
+            .count()
+    }
+
+    /// Returns true if a shutdown stopped a portal's harvest part way.
+    pub fn interrupted(&self) -> bool {
+        self.results.iter().any(|r| r.stats.interrupted)
     }
 
     /// Returns the total number of datasets across all successful portals.
@@ -196,16 +536,151 @@ impl BatchHarvestSummary {
         self.results.iter().map(|r| r.stats.total()).sum()
     }
 
-    /// Returns the total number of portals processed.
+    /// Returns the total number of portals processed, skipped ones included.
     pub fn total_portals(&self) -> usize {
         self.results.len()
     }
+
+    /// Returns the share of processed datasets that failed, or `None` if no
+    /// dataset was processed.
+    pub fn dataset_failure_rate(&self) -> Option<f64> {
+        let total = self.total_datasets();
+        let failed: usize = self.results.iter().map(|r| r.stats.failed).sum();
+        (total > 0).then(|| failed as f64 / total as f64)
+    }
+
+    /// Returns the failed datasets of every portal by kind of error.
+    pub fn failed_by_kind(&self) -> FailureCounts {
+        let mut counts = FailureCounts::default();
+        for result in &self.results {
+            for (kind, count) in result.stats.failed_by_kind.nonzero() {
+                counts.add(kind, count);
+            }
+        }
+        counts
+    }
+
+    /// Returns the statistics of every portal added together.
+    pub fn total_stats(&self) -> SyncStats {
+        let mut total = SyncStats::default();
+        for stats in self.results.iter().map(|r| &r.stats) {
+            total.unchanged += stats.unchanged;
+            total.updated += stats.updated;
+            total.created += stats.created;
+            total.failed += stats.failed;
+            for (kind, count) in stats.failed_by_kind.nonzero() {
+                total.failed_by_kind.add(kind, count);
+            }
+            total.cache_hits += stats.cache_hits;
+            total.cache_misses += stats.cache_misses;
+            total.interrupted |= stats.interrupted;
+        }
+        total
+    }
+}
+
+/// Machine-readable summary of a harvest run, printed on stdout as one
+/// JSON line when it ends.
+#[derive(Debug, Clone, Serialize)]
+pub struct HarvestReport {
+    pub started_at: DateTime<Utc>,
+    pub finished_at: DateTime<Utc>,
+    pub duration_ms: u64,
+    /// Datasets of every portal added together
+    pub stats: SyncStats,
+    /// Portals that succeeded, failed and were skipped
+    pub successful: usize,
+    pub failed: usize,
+    pub skipped: usize,
+    /// Per-portal results
+    pub portals: Vec<PortalHarvestResult>,
+}
+
+impl HarvestReport {
+    /// Builds the report of a run started at `started_at` and finished at
+    /// `finished_at`.
+    pub fn new(
+        summary: BatchHarvestSummary,
+        started_at: DateTime<Utc>,
+        finished_at: DateTime<Utc>,
+    ) -> Self {
+        Self {
+            started_at,
+            finished_at,
+            duration_ms: (finished_at - started_at).num_milliseconds().max(0) as u64,
+            stats: summary.total_stats(),
+            successful: summary.successful_count(),
+            failed: summary.failed_count(),
+            skipped: summary.skipped_count(),
+            portals: summary.results,
+        }
+    }
+}
+
+/// How a harvest sample picks its datasets.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SampleMethod {
+    /// The first datasets the portal lists
+    First,
+    /// Datasets picked at random; the same seed picks the same ones
+    Random { seed: u64 },
+}
+
+/// A subset of a portal's datasets harvested instead of all of them, to
+/// evaluate a new portal before a full harvest.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct HarvestSample {
+    /// Most datasets harvested per portal
+    pub limit: usize,
+    pub method: SampleMethod,
+}
+
+impl HarvestSample {
+    /// Picks at most `limit` of `ids`, keeping them in listing order.
+    ///
+    /// A random sample takes the IDs ranking lowest by SHA-256 of the seed
+    /// and the ID, so it stays stable as the portal grows.
+    pub fn apply(&self, mut ids: Vec<String>) -> Vec<String> {
+        if ids.len() <= self.limit {
+            return ids;
+        }
+        match self.method {
+            SampleMethod::First => ids.truncate(self.limit),
+            SampleMethod::Random { seed } => {
+                let rank = |id: &str| {
+                    let mut hasher = Sha256::new();
+                    hasher.update(seed.to_be_bytes());
+                    hasher.update(id.as_bytes());
+                    hasher.finalize()
+                };
+                let mut ranked: Vec<_> = ids.iter().map(|id| rank(id)).collect();
+                ranked.sort_unstable();
+                let cutoff = ranked[self.limit - 1];
+                ids.retain(|id| rank(id) <= cutoff);
+                // IDs listed twice could share the cutoff rank
+                ids.truncate(self.limit);
+            }
+        }
+        ids
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_listing_progress_offset() {
+        let mut progress = ListingProgress::new(10);
+        assert_eq!(progress.finish(11), 10);
+        assert_eq!(progress.finish(13), 10);
+        assert_eq!(progress.finish(10), 12);
+        assert_eq!(progress.finish(12), 14);
+        // Positions before the resumed offset were finished already
+        assert_eq!(progress.finish(3), 14);
+        assert_eq!(progress.offset(), 14);
+    }
+
     #[test]
     fn test_sync_stats_default() {
         let stats = SyncStats::new();
@@ -229,6 +704,82 @@ mod tests {
         assert_eq!(stats.failed, 1);
     }
 
+    #[test]
+    fn test_failure_kinds() {
+        let parse_error = serde_json::from_str::<u32>("{").unwrap_err();
+        let cases = [
+            (
+                FailureStage::Fetch,
+                AppError::Timeout(30),
+                FailureKind::Fetch,
+            ),
+            (
+                FailureStage::Fetch,
+                AppError::SerializationError(parse_error),
+                FailureKind::Parse,
+            ),
+            (
+                FailureStage::Embed,
+                AppError::RateLimitExceeded,
+                FailureKind::Embedding,
+            ),
+            (
+                FailureStage::Detect,
+                AppError::Generic("pool timed out".into()),
+                FailureKind::Database,
+            ),
+        ];
+        for (stage, error, kind) in cases {
+            assert_eq!(FailureKind::of(stage, &error), kind, "{}", error);
+        }
+
+        let mut stats = SyncStats::new();
+        stats.record_failure(FailureKind::Fetch);
+        stats.record_failure(FailureKind::Fetch);
+        stats.record_failure(FailureKind::Embedding);
+        assert_eq!(stats.failed, 3);
+        assert_eq!(
+            stats.failed_by_kind.nonzero(),
+            vec![(FailureKind::Fetch, 2), (FailureKind::Embedding, 1)]
+        );
+
+        let mut summary = BatchHarvestSummary::new();
+        for url in ["https://a.com", "https://b.com"] {
+            summary.add(PortalHarvestResult::success(
+                url.into(),
+                url.into(),
+                stats.clone(),
+            ));
+        }
+        assert_eq!(summary.failed_by_kind().fetch, 4);
+        assert_eq!(summary.failed_by_kind().embedding, 2);
+        let json = serde_json::to_value(&stats).unwrap();
+        assert_eq!(json["failed_by_kind"]["fetch"], 2);
+    }
+
+    #[test]
+    fn test_outcome_record_serialization() {
+        let record = DatasetOutcomeRecord {
+            timestamp: Utc::now(),
+            portal: "https://dati.comune.milano.it".to_string(),
+            id: "ds_air".to_string(),
+            outcome: SyncOutcome::Failed,
+            durations: StageDurations {
+                fetch_ms: Some(120),
+                embed_ms: Some(3000),
+                store_ms: None,
+                total_ms: 3121,
+            },
+            error: Some("Rate limit exceeded".to_string()),
+        };
+
+        let json = serde_json::to_value(&record).unwrap();
+        assert_eq!(json["outcome"], "failed");
+        assert_eq!(json["durations"]["embed_ms"], 3000);
+        assert!(json["durations"]["store_ms"].is_null());
+        assert_eq!(json["error"], "Rate limit exceeded");
+    }
+
     #[test]
     fn test_sync_stats_total() {
         let mut stats = SyncStats::new();
@@ -251,6 +802,16 @@ mod tests {
         assert_eq!(stats.successful(), 18);
     }
 
+    #[test]
+    fn test_sync_stats_cache_hit_rate() {
+        let mut stats = SyncStats::new();
+        assert_eq!(stats.cache_hit_rate(), None);
+
+        stats.cache_hits = 3;
+        stats.cache_misses = 1;
+        assert_eq!(stats.cache_hit_rate(), Some(0.75));
+    }
+
     #[test]
     fn test_needs_reprocessing_unchanged() {
         let hash = "abc123".to_string();
@@ -323,6 +884,7 @@ mod tests {
             updated: 3,
             created: 2,
             failed: 0,
+            ..Default::default()
         };
         let result = PortalHarvestResult::success(
             "test".to_string(),
@@ -348,6 +910,19 @@ mod tests {
         assert_eq!(result.stats.total(), 0);
     }
 
+    #[test]
+    fn test_portal_harvest_result_skipped() {
+        let result = PortalHarvestResult::skipped(
+            "test".to_string(),
+            "https://example.com".to_string(),
+            "locked".to_string(),
+        );
+        assert!(!result.is_success());
+        assert!(!result.is_failure());
+        assert_eq!(result.status, PortalHarvestStatus::Skipped);
+        assert_eq!(result.error, Some("locked".to_string()));
+    }
+
     // =========================================================================
     // BatchHarvestSummary tests
     // =========================================================================
@@ -370,6 +945,7 @@ mod tests {
             updated: 5,
             created: 3,
             failed: 2,
+            ..Default::default()
         };
         summary.add(PortalHarvestResult::success(
             "a".into(),
@@ -388,6 +964,7 @@ mod tests {
             updated: 0,
             created: 0,
             failed: 0,
+            ..Default::default()
         };
         summary.add(PortalHarvestResult::success(
             "c".into(),
@@ -399,6 +976,51 @@ mod tests {
         assert_eq!(summary.successful_count(), 2);
         assert_eq!(summary.failed_count(), 1);
         assert_eq!(summary.total_datasets(), 40); // 20 + 20 + 0 (failed portal has 0)
+
+        let total = summary.total_stats();
+        assert_eq!(total.unchanged, 30);
+        assert_eq!(total.failed, 2);
+        assert_eq!(total.total(), 40);
+    }
+
+    #[test]
+    fn test_harvest_report() {
+        let mut summary = BatchHarvestSummary::new();
+        summary.add(PortalHarvestResult::success(
+            "a".into(),
+            "https://a.com".into(),
+            SyncStats {
+                created: 3,
+                ..Default::default()
+            },
+        ));
+        summary.add(PortalHarvestResult::failure(
+            "b".into(),
+            "https://b.com".into(),
+            "error".into(),
+        ));
+        summary.add(PortalHarvestResult::skipped(
+            "c".into(),
+            "https://c.com".into(),
+            "locked".into(),
+        ));
+        let started_at = "2024-05-01T03:00:00Z".parse::<DateTime<Utc>>().unwrap();
+        let finished_at = "2024-05-01T03:01:30Z".parse::<DateTime<Utc>>().unwrap();
+
+        let report = HarvestReport::new(summary, started_at, finished_at);
+        assert_eq!(report.duration_ms, 90_000);
+        assert_eq!(
+            (report.successful, report.failed, report.skipped),
+            (1, 1, 1)
+        );
+
+        let line = serde_json::to_string(&report).unwrap();
+        assert!(!line.contains('\n'));
+        let json: serde_json::Value = serde_json::from_str(&line).unwrap();
+        assert_eq!(json["stats"]["created"], 3);
+        assert_eq!(json["portals"][1]["error"], "error");
+        assert_eq!(json["portals"][1]["status"], "failed");
+        assert_eq!(json["portals"][2]["status"], "skipped");
     }
 
     #[test]
@@ -410,6 +1032,7 @@ mod tests {
             updated: 0,
             created: 5,
             failed: 0,
+            ..Default::default()
         };
         summary.add(PortalHarvestResult::success(
             "portal1".into(),
@@ -442,4 +1065,32 @@ mod tests {
         assert_eq!(summary.total_datasets(), 0);
         assert_eq!(summary.total_portals(), 2);
     }
+
+    #[test]
+    fn test_harvest_sample() {
+        let ids: Vec<String> = (0..100).map(|i| format!("dataset-{}", i)).collect();
+        let first = HarvestSample {
+            limit: 3,
+            method: SampleMethod::First,
+        };
+        assert_eq!(first.apply(ids.clone()), ids[..3]);
+
+        let random = |seed| HarvestSample {
+            limit: 10,
+            method: SampleMethod::Random { seed },
+        };
+        let sample = random(42).apply(ids.clone());
+        assert_eq!(sample.len(), 10);
+        assert_ne!(sample, ids[..10]);
+        // Same seed, same sample, in listing order
+        assert_eq!(random(42).apply(ids.clone()), sample);
+        assert!(sample.windows(2).all(|w| {
+            let position = |id| ids.iter().position(|i| i == id);
+            position(&w[0]) < position(&w[1])
+        }));
+        assert_ne!(random(7).apply(ids.clone()), sample);
+
+        // Smaller portals are harvested whole
+        assert_eq!(random(42).apply(ids[..5].to_vec()), ids[..5]);
+    }
 }
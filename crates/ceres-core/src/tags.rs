@@ -0,0 +1,141 @@
+//! Normalized dataset tags.
+//!
+//! CKAN tags are free text, so the same keyword shows up as "Mobilità",
+//! "mobilita" and "MOBILITÀ ". Tags are normalized at harvest (lowercase,
+//! accents removed, English plurals singularized) and stored in the `tags`
+//! and `dataset_tags` tables, backing `ceres search --tag`, the tag facet
+//! and `ceres top-tags`.
+
+use serde_json::Value;
+
+use crate::error::AppError;
+
+/// Plural-looking words that are already singular.
+const SINGULAR_EXCEPTIONS: &[&str] = &["news", "series", "species", "gas", "bus", "census"];
+
+/// Normalizes a tag: lowercase, without accents, words separated by single
+/// spaces, English plurals singularized.
+///
+/// Hyphens and underscores separate words too. Returns `None` for tags
+/// without letters or digits.
+///
+/// # Examples
+///
+/// ```
+/// use ceres_core::tags::normalize_tag;
+///
+/// assert_eq!(normalize_tag(" Mobilità_Sostenibile "), Some("mobilita sostenibile".to_string()));
+/// assert_eq!(normalize_tag("Bike-Lanes"), Some("bike lane".to_string()));
+/// assert_eq!(normalize_tag("--"), None);
+/// ```
+pub fn normalize_tag(tag: &str) -> Option<String> {
+    let folded: String = tag.chars().flat_map(fold_char).collect();
+    let words: Vec<String> = folded
+        .split(|c: char| c.is_whitespace() || c == '-' || c == '_')
+        .filter(|word| word.chars().any(char::is_alphanumeric))
+        .map(singularize)
+        .collect();
+    (!words.is_empty()).then(|| words.join(" "))
+}
+
+/// Normalized, deduplicated tags of a dataset, sorted.
+///
+/// Reads CKAN `tags`, given as objects with a `name` or as plain strings.
+pub fn dataset_tags(metadata: &Value) -> Vec<String> {
+    let mut tags: Vec<String> = metadata["tags"]
+        .as_array()
+        .into_iter()
+        .flatten()
+        .filter_map(|tag| tag["name"].as_str().or_else(|| tag.as_str()))
+        .filter_map(normalize_tag)
+        .collect();
+    tags.sort();
+    tags.dedup();
+    tags
+}
+
+/// Parses a tag given on the command line, normalizing it.
+///
+/// # Errors
+///
+/// Returns `AppError::ConfigError` for tags without letters or digits.
+pub fn parse_tag(input: &str) -> Result<String, AppError> {
+    normalize_tag(input).ok_or_else(|| AppError::ConfigError(format!("Invalid tag '{}'", input)))
+}
+
+/// Lowercases a character and strips its accent.
+pub(crate) fn fold_char(c: char) -> Vec<char> {
+    let base = match c {
+        'à' | 'á' | 'â' | 'ã' | 'ä' | 'å' | 'À' | 'Á' | 'Â' | 'Ã' | 'Ä' | 'Å' => 'a',
+        'è' | 'é' | 'ê' | 'ë' | 'È' | 'É' | 'Ê' | 'Ë' => 'e',
+        'ì' | 'í' | 'î' | 'ï' | 'Ì' | 'Í' | 'Î' | 'Ï' => 'i',
+        'ò' | 'ó' | 'ô' | 'õ' | 'ö' | 'Ò' | 'Ó' | 'Ô' | 'Õ' | 'Ö' => 'o',
+        'ù' | 'ú' | 'û' | 'ü' | 'Ù' | 'Ú' | 'Û' | 'Ü' => 'u',
+        'ç' | 'Ç' => 'c',
+        'ñ' | 'Ñ' => 'n',
+        'ß' => return vec!['s', 's'],
+        _ => return c.to_lowercase().collect(),
+    };
+    vec![base]
+}
+
+/// Singular of an English plural; other words are returned unchanged.
+fn singularize(word: &str) -> String {
+    let is_plural = word.len() > 3
+        && word.ends_with('s')
+        && !["ss", "us", "is"].iter().any(|end| word.ends_with(end))
+        && !SINGULAR_EXCEPTIONS.contains(&word);
+    if !is_plural {
+        word.to_string()
+    } else if let Some(stem) = word.strip_suffix("ies") {
+        format!("{}y", stem)
+    } else if ["ches", "shes", "xes", "sses"]
+        .iter()
+        .any(|end| word.ends_with(end))
+    {
+        word[..word.len() - 2].to_string()
+    } else {
+        word[..word.len() - 1].to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    #[test]
+    fn test_normalize_tag() {
+        assert_eq!(normalize_tag("MOBILITÀ"), Some("mobilita".to_string()));
+        assert_eq!(normalize_tag("Straße"), Some("strasse".to_string()));
+        assert_eq!(
+            normalize_tag("air  quality"),
+            Some("air quality".to_string())
+        );
+        assert_eq!(normalize_tag(""), None);
+    }
+
+    #[test]
+    fn test_singularize() {
+        assert_eq!(singularize("cities"), "city");
+        assert_eq!(singularize("addresses"), "address");
+        assert_eq!(singularize("boxes"), "box");
+        assert_eq!(singularize("schools"), "school");
+        assert_eq!(singularize("census"), "census");
+        assert_eq!(singularize("analysis"), "analysis");
+        assert_eq!(singularize("news"), "news");
+        assert_eq!(singularize("rifiuti"), "rifiuti");
+    }
+
+    #[test]
+    fn test_dataset_tags() {
+        let metadata = json!({"tags": [
+            {"name": "Trasporti", "display_name": "Trasporti"},
+            {"name": "trasporti"},
+            "Bus Stops",
+            {"id": "x"}
+        ]});
+        assert_eq!(dataset_tags(&metadata), vec!["bus stop", "trasporti"]);
+        assert!(dataset_tags(&json!({})).is_empty());
+    }
+}
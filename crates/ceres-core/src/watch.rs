@@ -0,0 +1,291 @@
+//! Watched topics: saved searches that notify a webhook about new matches.
+//!
+//! A watch stores a query embedding, a similarity threshold and a webhook
+//! URL. After each harvest, datasets first seen since the watch was created
+//! that match it and have not been delivered yet are POSTed to the webhook
+//! in a single signed request.
+//!
+//! # Signatures
+//!
+//! Each delivery carries two headers:
+//!
+//! - `X-Ceres-Timestamp`: Unix timestamp (seconds) of the delivery
+//! - `X-Ceres-Signature`: `sha256=<hex>`, the HMAC-SHA256 of
+//!   `"{timestamp}.{body}"` keyed with the watch secret
+//!
+//! Receivers should recompute the signature over the raw body and reject
+//! stale timestamps to prevent replays.
+
+use std::net::IpAddr;
+
+use chrono::{DateTime, Utc};
+use hmac::{Hmac, Mac};
+use serde::Serialize;
+use sha2::Sha256;
+use uuid::Uuid;
+
+/// Header carrying the delivery timestamp (header names are case-insensitive).
+pub const TIMESTAMP_HEADER: &str = "x-ceres-timestamp";
+
+/// Header carrying the payload signature.
+pub const SIGNATURE_HEADER: &str = "x-ceres-signature";
+
+/// Default minimum similarity for a dataset to match a watch.
+pub const DEFAULT_MIN_SCORE: f32 = 0.75;
+
+/// Maximum number of datasets delivered per watch and harvest.
+pub const MAX_MATCHES_PER_DELIVERY: usize = 100;
+
+/// A saved search bound to a webhook.
+#[derive(Debug, Clone)]
+pub struct Watch {
+    pub id: Uuid,
+    /// Unique, user-chosen name
+    pub name: String,
+    /// Query text the embedding was computed from
+    pub query: String,
+    /// Minimum cosine similarity for a match
+    pub min_score: f32,
+    /// Optional source portal restriction
+    pub portal: Option<String>,
+    /// Embedding model of the query vector; only datasets embedded with it match
+    pub embedding_model: String,
+    /// URL receiving signed deliveries
+    pub webhook_url: String,
+    /// HMAC secret shared with the receiver
+    pub secret: String,
+    pub created_at: DateTime<Utc>,
+}
+
+/// A dataset newly matching a watch.
+#[derive(Debug, Clone, Serialize)]
+pub struct WatchMatch {
+    pub id: Uuid,
+    pub title: String,
+    pub url: String,
+    pub source_portal: String,
+    pub similarity_score: f32,
+}
+
+/// JSON payload delivered to a watch webhook.
+#[derive(Debug, Clone, Serialize)]
+pub struct WatchNotification {
+    /// Always `"watch.matched"`
+    pub event: &'static str,
+    /// Unique delivery identifier (also stored in the delivery log)
+    pub delivery_id: Uuid,
+    pub timestamp: DateTime<Utc>,
+    /// Watch name
+    pub watch: String,
+    /// Watch query text
+    pub query: String,
+    /// One-line summary for chat integrations
+    pub text: String,
+    /// Newly matching datasets, best match first
+    pub datasets: Vec<WatchMatch>,
+}
+
+impl WatchNotification {
+    /// Builds the notification for a batch of new matches.
+    pub fn new(watch: &Watch, datasets: Vec<WatchMatch>, timestamp: DateTime<Utc>) -> Self {
+        let text = format!(
+            "{} new dataset(s) match watch '{}' (\"{}\")",
+            datasets.len(),
+            watch.name,
+            watch.query
+        );
+        Self {
+            event: "watch.matched",
+            delivery_id: Uuid::new_v4(),
+            timestamp,
+            watch: watch.name.clone(),
+            query: watch.query.clone(),
+            text,
+            datasets,
+        }
+    }
+}
+
+/// Outcome of a webhook delivery, as stored in the delivery log.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DeliveryStatus {
+    Delivered,
+    Failed,
+}
+
+impl DeliveryStatus {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            DeliveryStatus::Delivered => "delivered",
+            DeliveryStatus::Failed => "failed",
+        }
+    }
+}
+
+/// One entry of the watch delivery log.
+#[derive(Debug, Clone)]
+pub struct WatchDelivery {
+    pub id: Uuid,
+    pub status: String,
+    pub dataset_count: usize,
+    pub error: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Generates a random secret for signing deliveries.
+pub fn generate_secret() -> String {
+    format!(
+        "whsec_{}{}",
+        Uuid::new_v4().simple(),
+        Uuid::new_v4().simple()
+    )
+}
+
+/// Returns true if `ip` is reachable on the public internet, so a webhook
+/// pointing at it cannot reach the harvester's own host or network:
+/// loopback, private, link-local (cloud metadata endpoints), shared,
+/// documentation, multicast and unspecified addresses are not.
+///
+/// # Examples
+///
+/// ```
+/// use ceres_core::watch::is_public_address;
+///
+/// assert!(is_public_address("93.184.215.14".parse().unwrap()));
+/// assert!(!is_public_address("169.254.169.254".parse().unwrap()));
+/// ```
+pub fn is_public_address(ip: IpAddr) -> bool {
+    match ip {
+        IpAddr::V4(ip) => {
+            let [a, b, ..] = ip.octets();
+            !(ip.is_loopback()
+                || ip.is_private()
+                || ip.is_link_local()
+                || ip.is_unspecified()
+                || ip.is_broadcast()
+                || ip.is_multicast()
+                || ip.is_documentation()
+                // Shared address space (RFC 6598)
+                || (a == 100 && (64..128).contains(&b))
+                || a == 0)
+        }
+        IpAddr::V6(ip) => match ip.to_ipv4_mapped() {
+            Some(ip) => is_public_address(IpAddr::V4(ip)),
+            None => {
+                !(ip.is_loopback()
+                    || ip.is_unspecified()
+                    || ip.is_multicast()
+                    || ip.is_unique_local()
+                    || ip.is_unicast_link_local())
+            }
+        },
+    }
+}
+
+/// Computes the `X-Ceres-Signature` header value for a delivery.
+///
+/// # Examples
+///
+/// ```
+/// use ceres_core::watch::sign_payload;
+///
+/// let signature = sign_payload("secret", 1700000000, br#"{"event":"watch.matched"}"#);
+/// assert!(signature.starts_with("sha256="));
+/// assert_eq!(signature.len(), "sha256=".len() + 64);
+/// ```
+pub fn sign_payload(secret: &str, timestamp: i64, body: &[u8]) -> String {
+    let mut mac =
+        Hmac::<Sha256>::new_from_slice(secret.as_bytes()).expect("HMAC accepts keys of any size");
+    mac.update(timestamp.to_string().as_bytes());
+    mac.update(b".");
+    mac.update(body);
+    let digest = mac.finalize().into_bytes();
+
+    let hex: String = digest.iter().map(|b| format!("{:02x}", b)).collect();
+    format!("sha256={}", hex)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_public_address() {
+        for ip in ["93.184.215.14", "2a00:1450:4002:402::200e"] {
+            assert!(is_public_address(ip.parse().unwrap()), "{}", ip);
+        }
+        for ip in [
+            "127.0.0.1",
+            "10.1.2.3",
+            "172.16.0.1",
+            "192.168.1.1",
+            "169.254.169.254",
+            "100.64.0.1",
+            "0.0.0.0",
+            "::1",
+            "fd00::1",
+            "fe80::1",
+            "::ffff:127.0.0.1",
+        ] {
+            assert!(!is_public_address(ip.parse().unwrap()), "{}", ip);
+        }
+    }
+
+    fn watch() -> Watch {
+        Watch {
+            id: Uuid::new_v4(),
+            name: "air-quality".to_string(),
+            query: "qualità dell'aria".to_string(),
+            min_score: DEFAULT_MIN_SCORE,
+            portal: None,
+            embedding_model: "text-embedding-004".to_string(),
+            webhook_url: "https://hooks.example.com".to_string(),
+            secret: generate_secret(),
+            created_at: Utc::now(),
+        }
+    }
+
+    #[test]
+    fn test_sign_payload_known_vector() {
+        // HMAC-SHA256(key = "key", message = "1.{}")
+        assert_eq!(
+            sign_payload("key", 1, b"{}"),
+            "sha256=1ba6b8171186efc613e8bcc0cbdab2748f24984d7c5a84faa2637afa0e40d224"
+        );
+    }
+
+    #[test]
+    fn test_sign_payload_depends_on_all_inputs() {
+        let base = sign_payload("key", 1, b"{}");
+        assert_ne!(base, sign_payload("other", 1, b"{}"));
+        assert_ne!(base, sign_payload("key", 2, b"{}"));
+        assert_ne!(base, sign_payload("key", 1, b"[]"));
+    }
+
+    #[test]
+    fn test_generate_secret() {
+        let a = generate_secret();
+        let b = generate_secret();
+        assert!(a.starts_with("whsec_"));
+        assert_eq!(a.len(), "whsec_".len() + 64);
+        assert_ne!(a, b);
+    }
+
+    #[test]
+    fn test_notification_payload() {
+        let datasets = vec![WatchMatch {
+            id: Uuid::new_v4(),
+            title: "Centraline qualità aria".to_string(),
+            url: "https://dati.comune.milano.it/dataset/aria".to_string(),
+            source_portal: "https://dati.comune.milano.it".to_string(),
+            similarity_score: 0.82,
+        }];
+        let notification = WatchNotification::new(&watch(), datasets, Utc::now());
+        let json = serde_json::to_value(&notification).unwrap();
+
+        assert_eq!(json["event"], "watch.matched");
+        assert_eq!(json["watch"], "air-quality");
+        assert_eq!(json["datasets"][0]["title"], "Centraline qualità aria");
+        assert!(notification.text.starts_with("1 new dataset(s)"));
+    }
+}
@@ -0,0 +1,85 @@
+//! Persistence for the checkpoints of interrupted harvests.
+
+use ceres_core::error::AppError;
+use ceres_core::sync::HarvestCheckpoint;
+use chrono::{DateTime, Utc};
+
+use crate::DatasetRepository;
+
+/// Helper struct for deserializing `harvest_checkpoints` rows
+#[derive(sqlx::FromRow)]
+struct HarvestCheckpointRow {
+    portal_url: String,
+    listing_offset: i32,
+    started_at: DateTime<Utc>,
+}
+
+impl From<HarvestCheckpointRow> for HarvestCheckpoint {
+    fn from(row: HarvestCheckpointRow) -> Self {
+        HarvestCheckpoint {
+            portal_url: row.portal_url,
+            offset: row.listing_offset.max(0) as usize,
+            started_at: row.started_at,
+        }
+    }
+}
+
+impl DatasetRepository {
+    /// Returns the checkpoint an interrupted harvest of a portal left, if
+    /// any.
+    pub async fn get_harvest_checkpoint(
+        &self,
+        portal_url: &str,
+    ) -> Result<Option<HarvestCheckpoint>, AppError> {
+        let row: Option<HarvestCheckpointRow> = sqlx::query_as(
+            r#"
+            SELECT portal_url, listing_offset, started_at
+            FROM harvest_checkpoints
+            WHERE portal_url = $1
+            "#,
+        )
+        .bind(portal_url)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(AppError::DatabaseError)?;
+
+        Ok(row.map(HarvestCheckpoint::from))
+    }
+
+    /// Inserts or replaces the checkpoint of a portal's harvest.
+    pub async fn save_harvest_checkpoint(
+        &self,
+        checkpoint: &HarvestCheckpoint,
+    ) -> Result<(), AppError> {
+        sqlx::query(
+            r#"
+            INSERT INTO harvest_checkpoints (portal_url, listing_offset, started_at, updated_at)
+            VALUES ($1, $2, $3, NOW())
+            ON CONFLICT (portal_url)
+            DO UPDATE SET
+                listing_offset = EXCLUDED.listing_offset,
+                started_at = EXCLUDED.started_at,
+                updated_at = NOW()
+            "#,
+        )
+        .bind(&checkpoint.portal_url)
+        .bind(checkpoint.offset as i32)
+        .bind(checkpoint.started_at)
+        .execute(&self.pool)
+        .await
+        .map_err(AppError::DatabaseError)?;
+
+        Ok(())
+    }
+
+    /// Deletes the checkpoint of a portal's harvest, if any.
+    pub async fn clear_harvest_checkpoint(&self, portal_url: &str) -> Result<(), AppError> {
+        sqlx::query("DELETE FROM harvest_checkpoints WHERE portal_url = $1")
+            .bind(portal_url)
+            .execute(&self.pool)
+            .await
+            .map_err(AppError::DatabaseError)?;
+
+        Ok(())
+    }
+}
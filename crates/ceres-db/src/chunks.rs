@@ -0,0 +1,207 @@
+//! Chunk embeddings and multi-vector search.
+
+use std::collections::HashMap;
+
+use ceres_core::chunks::{aggregate_scores, DatasetChunk, CHUNK_CANDIDATES_PER_RESULT};
+use ceres_core::error::AppError;
+use ceres_core::models::SearchResult;
+use ceres_core::search::{SearchFilters, SearchStrategy};
+use pgvector::Vector;
+use sqlx::{Postgres, QueryBuilder};
+use uuid::Uuid;
+
+use crate::repository::push_search_filters;
+use crate::DatasetRepository;
+
+/// Helper struct for deserializing chunk hash lookup rows
+#[derive(sqlx::FromRow)]
+struct ChunkHashRow {
+    original_id: String,
+    id: Uuid,
+    chunks_hash: Option<String>,
+}
+
+/// Helper struct for deserializing chunk search hits
+#[derive(sqlx::FromRow)]
+struct ChunkHitRow {
+    dataset_id: Uuid,
+    score: f64,
+}
+
+impl DatasetRepository {
+    /// Returns a map of original_id → (id, chunks_hash) for all datasets
+    /// from a portal.
+    ///
+    /// Datasets with chunks not embedded by `embedding_model` are reported
+    /// without a hash, so the next sync re-embeds their chunks.
+    pub async fn get_chunk_hashes_for_portal(
+        &self,
+        portal_url: &str,
+        embedding_model: &str,
+    ) -> Result<HashMap<String, (Uuid, Option<String>)>, AppError> {
+        let rows: Vec<ChunkHashRow> = sqlx::query_as(
+            r#"
+            SELECT
+                original_id,
+                id,
+                CASE WHEN EXISTS (
+                    SELECT 1 FROM dataset_chunks c
+                    WHERE c.dataset_id = d.id AND c.embedding_model <> $2
+                ) THEN NULL ELSE chunks_hash END AS chunks_hash
+            FROM datasets d
+            WHERE source_portal = $1
+            "#,
+        )
+        .bind(portal_url)
+        .bind(embedding_model)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(AppError::DatabaseError)?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| (row.original_id, (row.id, row.chunks_hash)))
+            .collect())
+    }
+
+    /// Replaces a dataset's chunks and records the hash they were built from.
+    pub async fn replace_dataset_chunks(
+        &self,
+        dataset_id: Uuid,
+        chunks: &[(DatasetChunk, Vector)],
+        embedding_model: &str,
+        chunks_hash: Option<&str>,
+    ) -> Result<(), AppError> {
+        let mut tx = self.pool.begin().await.map_err(AppError::DatabaseError)?;
+
+        sqlx::query("DELETE FROM dataset_chunks WHERE dataset_id = $1")
+            .bind(dataset_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(AppError::DatabaseError)?;
+
+        for (position, (chunk, embedding)) in chunks.iter().enumerate() {
+            sqlx::query(
+                r#"
+                INSERT INTO dataset_chunks (dataset_id, position, kind, text, embedding, embedding_model)
+                VALUES ($1, $2, $3, $4, $5, $6)
+                "#,
+            )
+            .bind(dataset_id)
+            .bind(position as i32)
+            .bind(chunk.kind.as_str())
+            .bind(&chunk.text)
+            .bind(embedding)
+            .bind(embedding_model)
+            .execute(&mut *tx)
+            .await
+            .map_err(AppError::DatabaseError)?;
+        }
+
+        sqlx::query("UPDATE datasets SET chunks_hash = $2 WHERE id = $1")
+            .bind(dataset_id)
+            .bind(chunks_hash)
+            .execute(&mut *tx)
+            .await
+            .map_err(AppError::DatabaseError)?;
+
+        tx.commit().await.map_err(AppError::DatabaseError)?;
+        Ok(())
+    }
+
+    /// Returns the best-matching chunks as (dataset ID, similarity score).
+    ///
+    /// Only chunks embedded with `filters.embedding_model` are compared; the
+    /// other filters apply to the chunks' datasets.
+    pub async fn search_chunks(
+        &self,
+        query_vector: Vector,
+        limit: usize,
+        filters: &SearchFilters,
+    ) -> Result<Vec<(Uuid, f32)>, AppError> {
+        let mut builder = QueryBuilder::<Postgres>::new("SELECT dataset_id, 1 - (embedding <=> ");
+        builder.push_bind(query_vector.clone());
+        builder.push(") AS score FROM dataset_chunks WHERE TRUE");
+        if let Some(model) = &filters.embedding_model {
+            builder.push(" AND embedding_model = ");
+            builder.push_bind(model.clone());
+        }
+
+        let dataset_filters = SearchFilters {
+            embedding_model: None,
+            ..filters.clone()
+        };
+        if !dataset_filters.is_empty() {
+            builder.push(" AND dataset_id IN (SELECT id FROM datasets WHERE TRUE");
+            push_search_filters(&mut builder, &dataset_filters);
+            builder.push(")");
+        }
+        builder.push(" ORDER BY embedding <=> ");
+        builder.push_bind(query_vector);
+        builder.push(" LIMIT ");
+        builder.push_bind(limit as i64);
+
+        let rows: Vec<ChunkHitRow> = builder
+            .build_query_as()
+            .fetch_all(&self.pool)
+            .await
+            .map_err(AppError::DatabaseError)?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| (row.dataset_id, row.score as f32))
+            .collect())
+    }
+
+    /// Semantic search over dataset and chunk embeddings.
+    ///
+    /// Each dataset is scored by its best-matching vector, its own or one
+    /// of its chunks', so datasets without chunks rank as in
+    /// [`search_with_filters`](Self::search_with_filters).
+    pub async fn search_with_chunks(
+        &self,
+        query_vector: Vector,
+        query_text: &str,
+        limit: usize,
+        filters: &SearchFilters,
+        strategy: SearchStrategy,
+    ) -> Result<Vec<SearchResult>, AppError> {
+        let direct = self
+            .search_with_filters(query_vector.clone(), query_text, limit, filters, strategy)
+            .await?;
+        let chunk_hits = self
+            .search_chunks(query_vector, limit * CHUNK_CANDIDATES_PER_RESULT, filters)
+            .await?;
+
+        let scores = aggregate_scores(
+            direct
+                .iter()
+                .map(|r| (r.dataset.id, r.similarity_score))
+                .chain(chunk_hits),
+        );
+        let top: Vec<(Uuid, f32)> = scores.into_iter().take(limit).collect();
+
+        let mut datasets: HashMap<Uuid, _> = direct
+            .into_iter()
+            .map(|r| (r.dataset.id, r.dataset))
+            .collect();
+        let missing: Vec<Uuid> = top
+            .iter()
+            .map(|(id, _)| *id)
+            .filter(|id| !datasets.contains_key(id))
+            .collect();
+        for dataset in self.get_many(&missing).await?.into_iter().flatten() {
+            datasets.insert(dataset.id, dataset);
+        }
+
+        Ok(top
+            .into_iter()
+            .filter_map(|(id, score)| {
+                datasets.remove(&id).map(|dataset| SearchResult {
+                    dataset,
+                    similarity_score: score,
+                })
+            })
+            .collect())
+    }
+}
@@ -0,0 +1,88 @@
+//! Topic clusters found by `ceres cluster`.
+
+use ceres_core::clustering::Topic;
+use ceres_core::error::AppError;
+use pgvector::Vector;
+use uuid::Uuid;
+
+use crate::DatasetRepository;
+
+impl DatasetRepository {
+    /// Returns the ID, title and embedding of every dataset embedded with
+    /// `embedding_model`, optionally from a single portal.
+    pub async fn cluster_candidates(
+        &self,
+        embedding_model: &str,
+        portal: Option<&str>,
+    ) -> Result<Vec<(Uuid, String, Vector)>, AppError> {
+        sqlx::query_as(
+            r#"
+            SELECT id, title, embedding
+            FROM datasets
+            WHERE embedding IS NOT NULL
+              AND embedding_model = $1
+              AND ($2::text IS NULL OR source_portal = $2)
+            ORDER BY id
+            "#,
+        )
+        .bind(embedding_model)
+        .bind(portal)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(AppError::DatabaseError)
+    }
+
+    /// Replaces the stored clustering with `topics` and the cluster of
+    /// each dataset.
+    pub async fn replace_clusters(
+        &self,
+        topics: &[Topic],
+        assignments: &[(Uuid, usize)],
+        embedding_model: &str,
+        portal: Option<&str>,
+    ) -> Result<(), AppError> {
+        let mut tx = self.pool.begin().await.map_err(AppError::DatabaseError)?;
+
+        // Assignments go with their clusters (ON DELETE CASCADE)
+        sqlx::query("DELETE FROM clusters")
+            .execute(&mut *tx)
+            .await
+            .map_err(AppError::DatabaseError)?;
+
+        for topic in topics {
+            sqlx::query(
+                r#"
+                INSERT INTO clusters (cluster, keywords, datasets, source_portal, embedding_model)
+                VALUES ($1, $2, $3, $4, $5)
+                "#,
+            )
+            .bind(topic.cluster as i32)
+            .bind(&topic.keywords)
+            .bind(topic.datasets as i32)
+            .bind(portal)
+            .bind(embedding_model)
+            .execute(&mut *tx)
+            .await
+            .map_err(AppError::DatabaseError)?;
+        }
+
+        let (ids, clusters): (Vec<Uuid>, Vec<i32>) = assignments
+            .iter()
+            .map(|(id, cluster)| (*id, *cluster as i32))
+            .unzip();
+        sqlx::query(
+            r#"
+            INSERT INTO dataset_clusters (dataset_id, cluster)
+            SELECT * FROM unnest($1::uuid[], $2::int[])
+            "#,
+        )
+        .bind(&ids)
+        .bind(&clusters)
+        .execute(&mut *tx)
+        .await
+        .map_err(AppError::DatabaseError)?;
+
+        tx.commit().await.map_err(AppError::DatabaseError)?;
+        Ok(())
+    }
+}
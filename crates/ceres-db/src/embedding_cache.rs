@@ -0,0 +1,49 @@
+//! Embedding cache keyed by content hash and model.
+
+use ceres_core::error::AppError;
+use pgvector::Vector;
+
+use crate::DatasetRepository;
+
+impl DatasetRepository {
+    /// Returns the cached embedding of the text hashing to `content_hash`,
+    /// computed with `model`.
+    pub async fn cached_embedding(
+        &self,
+        content_hash: &str,
+        model: &str,
+    ) -> Result<Option<Vector>, AppError> {
+        sqlx::query_scalar(
+            "SELECT embedding FROM embedding_cache WHERE content_hash = $1 AND embedding_model = $2",
+        )
+        .bind(content_hash)
+        .bind(model)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(AppError::DatabaseError)
+    }
+
+    /// Stores an embedding for reuse by datasets with the same content.
+    pub async fn cache_embedding(
+        &self,
+        content_hash: &str,
+        model: &str,
+        embedding: &Vector,
+    ) -> Result<(), AppError> {
+        sqlx::query(
+            r#"
+            INSERT INTO embedding_cache (content_hash, embedding_model, embedding)
+            VALUES ($1, $2, $3)
+            ON CONFLICT (content_hash, embedding_model) DO NOTHING
+            "#,
+        )
+        .bind(content_hash)
+        .bind(model)
+        .bind(embedding)
+        .execute(&self.pool)
+        .await
+        .map_err(AppError::DatabaseError)?;
+
+        Ok(())
+    }
+}
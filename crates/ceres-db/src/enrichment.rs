@@ -0,0 +1,220 @@
+//! Persistence for Wikidata enrichment (`ceres enrich`).
+
+use std::collections::HashMap;
+
+use ceres_core::enrichment::{
+    EntityRole, PendingDataset, SharedEntity, WikidataEntity, MISS_RETRY_DAYS,
+};
+use ceres_core::error::AppError;
+use uuid::Uuid;
+
+use crate::DatasetRepository;
+
+/// Selects datasets never enriched, or enriched from an older content hash.
+const PENDING_FILTER: &str = r#"
+    (entities_resolved_at IS NULL OR entities_hash IS DISTINCT FROM content_hash)
+    AND ($1::text IS NULL OR source_portal = $1)
+"#;
+
+/// Helper struct for deserializing pending datasets
+#[derive(sqlx::FromRow)]
+struct PendingRow {
+    id: Uuid,
+    metadata: sqlx::types::Json<serde_json::Value>,
+    content_hash: Option<String>,
+}
+
+/// Helper struct for deserializing shared entity rows
+#[derive(sqlx::FromRow)]
+struct SharedEntityRow {
+    qid: String,
+    label: Option<String>,
+    portals: i64,
+    datasets: i64,
+}
+
+impl DatasetRepository {
+    /// Counts datasets whose entity links are missing or stale.
+    pub async fn count_pending_enrichment(&self, portal: Option<&str>) -> Result<i64, AppError> {
+        let query = format!("SELECT COUNT(*) FROM datasets WHERE {}", PENDING_FILTER);
+        sqlx::query_scalar(&query)
+            .bind(portal)
+            .fetch_one(&self.pool)
+            .await
+            .map_err(AppError::DatabaseError)
+    }
+
+    /// Returns up to `limit` datasets needing enrichment, ordered by ID.
+    ///
+    /// Pass the last ID of the previous page as `after` to continue; datasets
+    /// that failed to resolve stay pending without being returned again.
+    pub async fn pending_enrichment(
+        &self,
+        portal: Option<&str>,
+        after: Option<Uuid>,
+        limit: usize,
+    ) -> Result<Vec<PendingDataset>, AppError> {
+        let query = format!(
+            "SELECT id, metadata, content_hash FROM datasets WHERE {} \
+             AND ($2::uuid IS NULL OR id > $2) ORDER BY id LIMIT $3",
+            PENDING_FILTER
+        );
+        let rows: Vec<PendingRow> = sqlx::query_as(&query)
+            .bind(portal)
+            .bind(after)
+            .bind(limit as i64)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(AppError::DatabaseError)?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| PendingDataset {
+                id: row.id,
+                metadata: row.metadata.0,
+                content_hash: row.content_hash,
+            })
+            .collect())
+    }
+
+    /// Looks up cached resolutions of normalized labels.
+    ///
+    /// Returns the QID for resolved labels and `None` for recent misses;
+    /// labels never searched, and misses older than [`MISS_RETRY_DAYS`],
+    /// are absent.
+    pub async fn cached_wikidata_labels(
+        &self,
+        language: &str,
+        label_keys: &[String],
+    ) -> Result<HashMap<String, Option<String>>, AppError> {
+        let rows: Vec<(String, Option<String>)> = sqlx::query_as(
+            r#"
+            SELECT label_key, qid FROM wikidata_labels
+            WHERE language = $1 AND label_key = ANY($2)
+              AND (qid IS NOT NULL OR resolved_at > NOW() - make_interval(days => $3))
+            "#,
+        )
+        .bind(language)
+        .bind(label_keys)
+        .bind(MISS_RETRY_DAYS as i32)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(AppError::DatabaseError)?;
+
+        Ok(rows.into_iter().collect())
+    }
+
+    /// Caches the resolution of a normalized label; `None` records a miss.
+    pub async fn cache_wikidata_label(
+        &self,
+        language: &str,
+        label_key: &str,
+        entity: Option<&WikidataEntity>,
+    ) -> Result<(), AppError> {
+        sqlx::query(
+            r#"
+            INSERT INTO wikidata_labels (language, label_key, qid, label, description, resolved_at)
+            VALUES ($1, $2, $3, $4, $5, NOW())
+            ON CONFLICT (language, label_key) DO UPDATE SET
+                qid = EXCLUDED.qid,
+                label = EXCLUDED.label,
+                description = EXCLUDED.description,
+                resolved_at = EXCLUDED.resolved_at
+            "#,
+        )
+        .bind(language)
+        .bind(label_key)
+        .bind(entity.map(|e| &e.qid))
+        .bind(entity.map(|e| &e.label))
+        .bind(entity.and_then(|e| e.description.as_ref()))
+        .execute(&self.pool)
+        .await
+        .map_err(AppError::DatabaseError)?;
+
+        Ok(())
+    }
+
+    /// Replaces a dataset's entity links and records the content hash they
+    /// were computed from.
+    pub async fn set_dataset_entities(
+        &self,
+        dataset_id: Uuid,
+        links: &[(EntityRole, String)],
+        content_hash: Option<&str>,
+    ) -> Result<(), AppError> {
+        let mut tx = self.pool.begin().await.map_err(AppError::DatabaseError)?;
+
+        sqlx::query("DELETE FROM dataset_entities WHERE dataset_id = $1")
+            .bind(dataset_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(AppError::DatabaseError)?;
+
+        for (role, qid) in links {
+            sqlx::query(
+                r#"
+                INSERT INTO dataset_entities (dataset_id, role, qid)
+                VALUES ($1, $2, $3)
+                ON CONFLICT DO NOTHING
+                "#,
+            )
+            .bind(dataset_id)
+            .bind(role.as_str())
+            .bind(qid)
+            .execute(&mut *tx)
+            .await
+            .map_err(AppError::DatabaseError)?;
+        }
+
+        sqlx::query(
+            "UPDATE datasets SET entities_hash = $2, entities_resolved_at = NOW() WHERE id = $1",
+        )
+        .bind(dataset_id)
+        .bind(content_hash)
+        .execute(&mut *tx)
+        .await
+        .map_err(AppError::DatabaseError)?;
+
+        tx.commit().await.map_err(AppError::DatabaseError)?;
+        Ok(())
+    }
+
+    /// Returns entities linked from datasets of more than one portal, most
+    /// widespread first.
+    pub async fn shared_entities(
+        &self,
+        role: EntityRole,
+        limit: usize,
+    ) -> Result<Vec<SharedEntity>, AppError> {
+        let rows: Vec<SharedEntityRow> = sqlx::query_as(
+            r#"
+            SELECT e.qid,
+                   (SELECT MIN(l.label) FROM wikidata_labels l WHERE l.qid = e.qid) AS label,
+                   COUNT(DISTINCT d.source_portal) AS portals,
+                   COUNT(*) AS datasets
+            FROM dataset_entities e
+            JOIN datasets d ON d.id = e.dataset_id
+            WHERE e.role = $1
+            GROUP BY e.qid
+            HAVING COUNT(DISTINCT d.source_portal) > 1
+            ORDER BY portals DESC, datasets DESC, e.qid
+            LIMIT $2
+            "#,
+        )
+        .bind(role.as_str())
+        .bind(limit as i64)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(AppError::DatabaseError)?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| SharedEntity {
+                label: row.label.unwrap_or_else(|| row.qid.clone()),
+                qid: row.qid,
+                portals: row.portals,
+                datasets: row.datasets,
+            })
+            .collect())
+    }
+}
@@ -0,0 +1,124 @@
+//! Persistence for the `harvest_failures` dead-letter table.
+
+use std::collections::HashMap;
+
+use ceres_core::error::AppError;
+use ceres_core::sync::HarvestFailure;
+use chrono::{DateTime, Utc};
+
+use crate::DatasetRepository;
+
+/// Helper struct for deserializing `harvest_failures` rows
+#[derive(sqlx::FromRow)]
+struct HarvestFailureRow {
+    portal_url: String,
+    dataset_id: String,
+    stage: String,
+    error: String,
+    attempts: i32,
+    failed_at: DateTime<Utc>,
+}
+
+impl From<HarvestFailureRow> for HarvestFailure {
+    fn from(row: HarvestFailureRow) -> Self {
+        HarvestFailure {
+            portal_url: row.portal_url,
+            dataset_id: row.dataset_id,
+            stage: row.stage,
+            error: row.error,
+            attempts: row.attempts.max(0) as u32,
+            failed_at: row.failed_at,
+        }
+    }
+}
+
+impl DatasetRepository {
+    /// Records failed datasets, replacing the earlier failure of each and
+    /// counting the attempt.
+    pub async fn record_harvest_failures(
+        &self,
+        failures: &[HarvestFailure],
+    ) -> Result<(), AppError> {
+        // ON CONFLICT cannot update a row twice in one statement
+        let mut latest: HashMap<(&str, &str), &HarvestFailure> = HashMap::new();
+        for failure in failures {
+            latest.insert((&failure.portal_url, &failure.dataset_id), failure);
+        }
+        if latest.is_empty() {
+            return Ok(());
+        }
+        let unique: Vec<&HarvestFailure> = latest.into_values().collect();
+        let text = |f: fn(&HarvestFailure) -> &str| -> Vec<&str> {
+            unique.iter().map(|failure| f(failure)).collect()
+        };
+        let failed_at: Vec<DateTime<Utc>> = unique.iter().map(|f| f.failed_at).collect();
+
+        sqlx::query(
+            r#"
+            INSERT INTO harvest_failures (portal_url, dataset_id, stage, error, failed_at)
+            SELECT * FROM unnest($1::text[], $2::text[], $3::text[], $4::text[], $5::timestamptz[])
+            ON CONFLICT (portal_url, dataset_id)
+            DO UPDATE SET
+                stage = EXCLUDED.stage,
+                error = EXCLUDED.error,
+                failed_at = EXCLUDED.failed_at,
+                attempts = harvest_failures.attempts + 1
+            "#,
+        )
+        .bind(text(|f| &f.portal_url))
+        .bind(text(|f| &f.dataset_id))
+        .bind(text(|f| &f.stage))
+        .bind(text(|f| &f.error))
+        .bind(failed_at)
+        .execute(&self.pool)
+        .await
+        .map_err(AppError::DatabaseError)?;
+
+        Ok(())
+    }
+
+    /// Returns the recorded failures, optionally of one portal, by portal
+    /// and latest first.
+    pub async fn list_harvest_failures(
+        &self,
+        portal_url: Option<&str>,
+    ) -> Result<Vec<HarvestFailure>, AppError> {
+        let rows: Vec<HarvestFailureRow> = sqlx::query_as(
+            r#"
+            SELECT portal_url, dataset_id, stage, error, attempts, failed_at
+            FROM harvest_failures
+            WHERE $1::text IS NULL OR rtrim(portal_url, '/') = $1
+            ORDER BY portal_url, failed_at DESC, dataset_id
+            "#,
+        )
+        .bind(portal_url.map(|url| url.trim_end_matches('/')))
+        .fetch_all(&self.pool)
+        .await
+        .map_err(AppError::DatabaseError)?;
+
+        Ok(rows.into_iter().map(HarvestFailure::from).collect())
+    }
+
+    /// Deletes the failures recorded before `before`, optionally of one
+    /// portal. Returns the number deleted.
+    pub async fn resolve_harvest_failures(
+        &self,
+        portal_url: Option<&str>,
+        before: DateTime<Utc>,
+    ) -> Result<u64, AppError> {
+        let result = sqlx::query(
+            r#"
+            DELETE FROM harvest_failures
+            WHERE failed_at < $1
+              AND ($2::text IS NULL OR rtrim(portal_url, '/') = $2)
+            "#,
+        )
+        .bind(before)
+        .bind(portal_url.map(|url| url.trim_end_matches('/')))
+        .execute(&self.pool)
+        .await
+        .map_err(AppError::DatabaseError)?;
+
+        Ok(result.rows_affected())
+    }
+}
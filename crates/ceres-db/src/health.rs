@@ -0,0 +1,112 @@
+//! Persistence for portal health and quarantine state.
+
+use ceres_core::error::AppError;
+use ceres_core::health::PortalHealth;
+use chrono::{DateTime, Utc};
+
+use crate::DatasetRepository;
+
+/// Helper struct for deserializing `portal_health` rows
+#[derive(sqlx::FromRow)]
+struct PortalHealthRow {
+    portal_url: String,
+    consecutive_failures: i32,
+    last_error: Option<String>,
+    last_failure_at: Option<DateTime<Utc>>,
+    last_success_at: Option<DateTime<Utc>>,
+    quarantined_until: Option<DateTime<Utc>>,
+    harvests: i32,
+    failed_harvests: i32,
+}
+
+impl From<PortalHealthRow> for PortalHealth {
+    fn from(row: PortalHealthRow) -> Self {
+        PortalHealth {
+            portal_url: row.portal_url,
+            consecutive_failures: row.consecutive_failures.max(0) as u32,
+            last_error: row.last_error,
+            last_failure_at: row.last_failure_at,
+            last_success_at: row.last_success_at,
+            quarantined_until: row.quarantined_until,
+            harvests: row.harvests.max(0) as u32,
+            failed_harvests: row.failed_harvests.max(0) as u32,
+        }
+    }
+}
+
+impl DatasetRepository {
+    /// Returns the health record of every portal that has been harvested.
+    pub async fn list_portal_health(&self) -> Result<Vec<PortalHealth>, AppError> {
+        let rows: Vec<PortalHealthRow> = sqlx::query_as(
+            r#"
+            SELECT portal_url, consecutive_failures, last_error,
+                   last_failure_at, last_success_at, quarantined_until,
+                   harvests, failed_harvests
+            FROM portal_health
+            ORDER BY portal_url
+            "#,
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(AppError::DatabaseError)?;
+
+        Ok(rows.into_iter().map(PortalHealth::from).collect())
+    }
+
+    /// Returns the health record of a portal, or a fresh one if it has none.
+    pub async fn get_portal_health(&self, portal_url: &str) -> Result<PortalHealth, AppError> {
+        let row: Option<PortalHealthRow> = sqlx::query_as(
+            r#"
+            SELECT portal_url, consecutive_failures, last_error,
+                   last_failure_at, last_success_at, quarantined_until,
+                   harvests, failed_harvests
+            FROM portal_health
+            WHERE portal_url = $1
+            "#,
+        )
+        .bind(portal_url)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(AppError::DatabaseError)?;
+
+        Ok(row
+            .map(PortalHealth::from)
+            .unwrap_or_else(|| PortalHealth::new(portal_url)))
+    }
+
+    /// Inserts or replaces a portal health record.
+    pub async fn save_portal_health(&self, health: &PortalHealth) -> Result<(), AppError> {
+        sqlx::query(
+            r#"
+            INSERT INTO portal_health (
+                portal_url, consecutive_failures, last_error,
+                last_failure_at, last_success_at, quarantined_until,
+                harvests, failed_harvests
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+            ON CONFLICT (portal_url)
+            DO UPDATE SET
+                consecutive_failures = EXCLUDED.consecutive_failures,
+                last_error = EXCLUDED.last_error,
+                last_failure_at = EXCLUDED.last_failure_at,
+                last_success_at = EXCLUDED.last_success_at,
+                quarantined_until = EXCLUDED.quarantined_until,
+                harvests = EXCLUDED.harvests,
+                failed_harvests = EXCLUDED.failed_harvests
+            "#,
+        )
+        .bind(&health.portal_url)
+        .bind(health.consecutive_failures as i32)
+        .bind(&health.last_error)
+        .bind(health.last_failure_at)
+        .bind(health.last_success_at)
+        .bind(health.quarantined_until)
+        .bind(health.harvests as i32)
+        .bind(health.failed_harvests as i32)
+        .execute(&self.pool)
+        .await
+        .map_err(AppError::DatabaseError)?;
+
+        Ok(())
+    }
+}
@@ -0,0 +1,87 @@
+//! Persistence for the `search_history` query log.
+
+use ceres_core::error::AppError;
+use ceres_core::history::SearchRecord;
+use chrono::{DateTime, Utc};
+use sqlx::types::Json;
+
+use crate::DatasetRepository;
+
+/// Helper struct for deserializing `search_history` rows
+#[derive(sqlx::FromRow)]
+struct SearchRecordRow {
+    query: String,
+    mode: String,
+    filters: Json<serde_json::Value>,
+    result_count: i32,
+    top_score: Option<f32>,
+    source: String,
+    searched_at: DateTime<Utc>,
+}
+
+impl From<SearchRecordRow> for SearchRecord {
+    fn from(row: SearchRecordRow) -> Self {
+        SearchRecord {
+            query: row.query,
+            mode: row.mode,
+            filters: row.filters.0,
+            result_count: row.result_count.max(0) as u32,
+            top_score: row.top_score,
+            source: row.source,
+            searched_at: row.searched_at,
+        }
+    }
+}
+
+impl DatasetRepository {
+    /// Adds a search to the history.
+    pub async fn record_search(&self, search: &SearchRecord) -> Result<(), AppError> {
+        sqlx::query(
+            r#"
+            INSERT INTO search_history (query, mode, filters, result_count, top_score, source, searched_at)
+            VALUES ($1, $2, $3, $4, $5, $6, $7)
+            "#,
+        )
+        .bind(&search.query)
+        .bind(&search.mode)
+        .bind(Json(&search.filters))
+        .bind(search.result_count as i32)
+        .bind(search.top_score)
+        .bind(&search.source)
+        .bind(search.searched_at)
+        .execute(&self.pool)
+        .await
+        .map_err(AppError::DatabaseError)?;
+
+        Ok(())
+    }
+
+    /// Returns the latest `limit` searches, latest first.
+    pub async fn list_searches(&self, limit: usize) -> Result<Vec<SearchRecord>, AppError> {
+        let rows: Vec<SearchRecordRow> = sqlx::query_as(
+            r#"
+            SELECT query, mode, filters, result_count, top_score, source, searched_at
+            FROM search_history
+            ORDER BY searched_at DESC, id DESC
+            LIMIT $1
+            "#,
+        )
+        .bind(limit as i64)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(AppError::DatabaseError)?;
+
+        Ok(rows.into_iter().map(SearchRecord::from).collect())
+    }
+
+    /// Deletes the whole search history. Returns the number of searches
+    /// deleted.
+    pub async fn clear_search_history(&self) -> Result<u64, AppError> {
+        let result = sqlx::query("DELETE FROM search_history")
+            .execute(&self.pool)
+            .await
+            .map_err(AppError::DatabaseError)?;
+
+        Ok(result.rows_affected())
+    }
+}
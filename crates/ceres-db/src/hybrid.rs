@@ -0,0 +1,151 @@
+//! Full-text and hybrid (lexical + vector) search.
+
+use std::collections::HashMap;
+
+use ceres_core::error::AppError;
+use ceres_core::models::SearchResult;
+use ceres_core::search::{
+    keyword_tsquery, reciprocal_rank_fusion, SearchFilters, SearchStrategy,
+    HYBRID_CANDIDATES_PER_RESULT,
+};
+use pgvector::Vector;
+use sqlx::{Postgres, QueryBuilder};
+use uuid::Uuid;
+
+use crate::repository::{push_search_filters, FTS_DOCUMENT};
+use crate::DatasetRepository;
+
+/// Helper struct for deserializing full-text hits
+#[derive(sqlx::FromRow)]
+struct LexicalHitRow {
+    id: Uuid,
+    score: f32,
+}
+
+impl DatasetRepository {
+    /// Returns datasets matching `query_text` as (dataset ID, score), best
+    /// match first.
+    ///
+    /// Datasets match any query term (see [`keyword_tsquery`]) and are ranked
+    /// by `ts_rank_cd`, weighting name and title above the description and
+    /// normalizing by document length. Scores are scaled into 0..1. Returns
+    /// nothing if the query has no usable term.
+    pub async fn lexical_search(
+        &self,
+        query_text: &str,
+        limit: usize,
+        filters: &SearchFilters,
+    ) -> Result<Vec<(Uuid, f32)>, AppError> {
+        let Some(tsquery) = keyword_tsquery(query_text) else {
+            return Ok(Vec::new());
+        };
+
+        // Normalization 1 divides by 1 + log(document length), 32 maps the
+        // rank to rank / (rank + 1)
+        let mut builder = QueryBuilder::<Postgres>::new(format!(
+            "SELECT id, ts_rank_cd({}, query, 1 | 32) AS score \
+             FROM datasets, to_tsquery('simple', ",
+            FTS_DOCUMENT
+        ));
+        builder.push_bind(tsquery);
+        builder.push(format!(") AS query WHERE {} @@ query", FTS_DOCUMENT));
+        push_search_filters(&mut builder, filters);
+        builder.push(" ORDER BY score DESC, id LIMIT ");
+        builder.push_bind(limit as i64);
+
+        let rows: Vec<LexicalHitRow> = builder
+            .build_query_as()
+            .fetch_all(&self.pool)
+            .await
+            .map_err(AppError::DatabaseError)?;
+
+        Ok(rows.into_iter().map(|row| (row.id, row.score)).collect())
+    }
+
+    /// Full-text search without embeddings, for when no embedding provider
+    /// is available.
+    ///
+    /// Results carry their [`lexical_search`](Self::lexical_search) score
+    /// as `similarity_score`. The embedding model filter is ignored.
+    pub async fn text_search(
+        &self,
+        query_text: &str,
+        limit: usize,
+        filters: &SearchFilters,
+    ) -> Result<Vec<SearchResult>, AppError> {
+        let filters = SearchFilters {
+            embedding_model: None,
+            ..filters.clone()
+        };
+        let hits = self.lexical_search(query_text, limit, &filters).await?;
+        let ids: Vec<Uuid> = hits.iter().map(|(id, _)| *id).collect();
+        let datasets = self.get_many(&ids).await?;
+
+        Ok(hits
+            .into_iter()
+            .zip(datasets)
+            .filter_map(|((_, score), dataset)| {
+                dataset.map(|dataset| SearchResult {
+                    dataset,
+                    similarity_score: score,
+                })
+            })
+            .collect())
+    }
+
+    /// Hybrid search: fuses the semantic ranking of
+    /// [`search_with_filters`](Self::search_with_filters) with the full-text
+    /// ranking of [`lexical_search`](Self::lexical_search).
+    ///
+    /// Both run concurrently; results carry their fused score (see
+    /// [`reciprocal_rank_fusion`]) as `similarity_score`. The embedding
+    /// model filter only applies to the semantic ranking, as text matches
+    /// don't depend on it.
+    pub async fn hybrid_search(
+        &self,
+        query_vector: Vector,
+        query_text: &str,
+        limit: usize,
+        filters: &SearchFilters,
+        strategy: SearchStrategy,
+    ) -> Result<Vec<SearchResult>, AppError> {
+        let candidates = limit * HYBRID_CANDIDATES_PER_RESULT;
+        let lexical_filters = SearchFilters {
+            embedding_model: None,
+            ..filters.clone()
+        };
+        let (semantic, lexical) = futures::try_join!(
+            self.search_with_filters(query_vector, query_text, candidates, filters, strategy),
+            self.lexical_search(query_text, candidates, &lexical_filters),
+        )?;
+
+        let fused = reciprocal_rank_fusion(&[
+            semantic.iter().map(|r| r.dataset.id).collect(),
+            lexical.into_iter().map(|(id, _)| id).collect(),
+        ]);
+        let top: Vec<(Uuid, f32)> = fused.into_iter().take(limit).collect();
+
+        let mut datasets: HashMap<Uuid, _> = semantic
+            .into_iter()
+            .map(|r| (r.dataset.id, r.dataset))
+            .collect();
+        let missing: Vec<Uuid> = top
+            .iter()
+            .map(|(id, _)| *id)
+            .filter(|id| !datasets.contains_key(id))
+            .collect();
+        for dataset in self.get_many(&missing).await?.into_iter().flatten() {
+            datasets.insert(dataset.id, dataset);
+        }
+
+        Ok(top
+            .into_iter()
+            .filter_map(|(id, score)| {
+                datasets.remove(&id).map(|dataset| SearchResult {
+                    dataset,
+                    similarity_score: score,
+                })
+            })
+            .collect())
+    }
+}
@@ -0,0 +1,303 @@
+//! ANN index benchmarking and maintenance for `ceres index tune`.
+//!
+//! Candidate indexes are built inside a transaction that is always rolled
+//! back, so benchmarking never leaves a half-tuned schema behind. Note that
+//! dropping the live index takes an exclusive lock on `datasets` for the
+//! duration of each benchmark, so tuning should run outside harvest windows.
+//!
+//! It also reads and migrates the dimension of the embedding columns for
+//! `ceres index resize`.
+
+use std::time::{Duration, Instant};
+
+use ceres_core::error::AppError;
+use ceres_core::index_tuning::{
+    latency_percentile, recall_at_k, IndexBuildParams, TuningCandidate, TuningMeasurement,
+    EMBEDDING_INDEX_NAME,
+};
+use pgvector::Vector;
+use uuid::Uuid;
+
+use crate::DatasetRepository;
+
+/// k-NN query used for both exact and approximate runs.
+///
+/// The query point itself is excluded so sampled embeddings don't trivially
+/// match themselves.
+const KNN_QUERY: &str = "SELECT id FROM datasets WHERE embedding IS NOT NULL AND id <> $2 ORDER BY embedding <=> $1 LIMIT $3";
+
+impl DatasetRepository {
+    /// Returns a random sample of stored embeddings to use as benchmark queries.
+    pub async fn sample_embeddings(&self, n: usize) -> Result<Vec<(Uuid, Vector)>, AppError> {
+        let rows: Vec<(Uuid, Vector)> = sqlx::query_as(
+            r#"
+            SELECT id, embedding
+            FROM datasets
+            WHERE embedding IS NOT NULL
+            ORDER BY random()
+            LIMIT $1
+            "#,
+        )
+        .bind(n as i64)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(AppError::DatabaseError)?;
+
+        Ok(rows)
+    }
+
+    /// Computes exact k nearest neighbours for each query with index scans disabled.
+    ///
+    /// The results serve as ground truth for recall measurement.
+    pub async fn exact_neighbors(
+        &self,
+        queries: &[(Uuid, Vector)],
+        k: usize,
+    ) -> Result<Vec<Vec<Uuid>>, AppError> {
+        let mut tx = self.pool.begin().await.map_err(AppError::DatabaseError)?;
+
+        sqlx::query("SET LOCAL enable_indexscan = off")
+            .execute(&mut *tx)
+            .await
+            .map_err(AppError::DatabaseError)?;
+        sqlx::query("SET LOCAL enable_bitmapscan = off")
+            .execute(&mut *tx)
+            .await
+            .map_err(AppError::DatabaseError)?;
+
+        let mut truth = Vec::with_capacity(queries.len());
+        for (id, vector) in queries {
+            let ids: Vec<(Uuid,)> = sqlx::query_as(KNN_QUERY)
+                .bind(vector)
+                .bind(id)
+                .bind(k as i64)
+                .fetch_all(&mut *tx)
+                .await
+                .map_err(AppError::DatabaseError)?;
+            truth.push(ids.into_iter().map(|(id,)| id).collect());
+        }
+
+        tx.rollback().await.map_err(AppError::DatabaseError)?;
+        Ok(truth)
+    }
+
+    /// Builds a candidate index and measures recall and latency for each query value.
+    ///
+    /// The index is built in a transaction that is rolled back afterwards,
+    /// restoring the original index.
+    pub async fn benchmark_index(
+        &self,
+        candidate: &TuningCandidate,
+        queries: &[(Uuid, Vector)],
+        truth: &[Vec<Uuid>],
+        k: usize,
+    ) -> Result<Vec<TuningMeasurement>, AppError> {
+        let mut tx = self.pool.begin().await.map_err(AppError::DatabaseError)?;
+
+        sqlx::query(&format!("DROP INDEX IF EXISTS {}", EMBEDDING_INDEX_NAME))
+            .execute(&mut *tx)
+            .await
+            .map_err(AppError::DatabaseError)?;
+
+        let build_start = Instant::now();
+        sqlx::query(&format!(
+            "CREATE INDEX {} ON datasets {}",
+            EMBEDDING_INDEX_NAME,
+            candidate.build.using_clause()
+        ))
+        .execute(&mut *tx)
+        .await
+        .map_err(AppError::DatabaseError)?;
+        let build_time = build_start.elapsed();
+
+        // Force the planner onto the index; small tables would otherwise seq scan.
+        sqlx::query("SET LOCAL enable_seqscan = off")
+            .execute(&mut *tx)
+            .await
+            .map_err(AppError::DatabaseError)?;
+
+        let mut measurements = Vec::with_capacity(candidate.query_values.len());
+        for &value in &candidate.query_values {
+            sqlx::query(&candidate.build.query_setting(value))
+                .execute(&mut *tx)
+                .await
+                .map_err(AppError::DatabaseError)?;
+
+            let mut latencies: Vec<Duration> = Vec::with_capacity(queries.len());
+            let mut recall_sum = 0.0;
+
+            for ((id, vector), expected) in queries.iter().zip(truth) {
+                let start = Instant::now();
+                let ids: Vec<(Uuid,)> = sqlx::query_as(KNN_QUERY)
+                    .bind(vector)
+                    .bind(id)
+                    .bind(k as i64)
+                    .fetch_all(&mut *tx)
+                    .await
+                    .map_err(AppError::DatabaseError)?;
+                latencies.push(start.elapsed());
+
+                let approx: Vec<Uuid> = ids.into_iter().map(|(id,)| id).collect();
+                recall_sum += recall_at_k(expected, &approx);
+            }
+
+            measurements.push(TuningMeasurement {
+                build: candidate.build,
+                query_value: value,
+                recall: recall_sum / queries.len().max(1) as f64,
+                p50: latency_percentile(&latencies, 50.0),
+                p95: latency_percentile(&latencies, 95.0),
+                build_time,
+            });
+        }
+
+        tx.rollback().await.map_err(AppError::DatabaseError)?;
+        Ok(measurements)
+    }
+
+    /// Rebuilds the embedding index with the given build parameters.
+    pub async fn rebuild_embedding_index(&self, build: IndexBuildParams) -> Result<(), AppError> {
+        let mut tx = self.pool.begin().await.map_err(AppError::DatabaseError)?;
+
+        sqlx::query(&format!("DROP INDEX IF EXISTS {}", EMBEDDING_INDEX_NAME))
+            .execute(&mut *tx)
+            .await
+            .map_err(AppError::DatabaseError)?;
+        sqlx::query(&format!(
+            "CREATE INDEX {} ON datasets {}",
+            EMBEDDING_INDEX_NAME,
+            build.using_clause()
+        ))
+        .execute(&mut *tx)
+        .await
+        .map_err(AppError::DatabaseError)?;
+
+        tx.commit().await.map_err(AppError::DatabaseError)?;
+        Ok(())
+    }
+
+    /// Persists the query-time parameter as a database-level default.
+    ///
+    /// Requires ownership of the database; callers should surface the
+    /// setting to the operator if this fails.
+    pub async fn persist_index_query_setting(
+        &self,
+        build: IndexBuildParams,
+        value: u32,
+    ) -> Result<(), AppError> {
+        let statement = format!(
+            "DO $$ BEGIN EXECUTE format('ALTER DATABASE %I SET {} = {}', current_database()); END $$",
+            build.query_param_name(),
+            value
+        );
+        sqlx::query(&statement)
+            .execute(&self.pool)
+            .await
+            .map_err(AppError::DatabaseError)?;
+
+        Ok(())
+    }
+
+    /// Returns the dimension of `datasets.embedding`, or `None` if the
+    /// column is an unconstrained `vector`.
+    pub async fn embedding_dimension(&self) -> Result<Option<usize>, AppError> {
+        // pgvector stores the dimension as the column's type modifier
+        let typmod: i32 = sqlx::query_scalar(
+            r#"
+            SELECT atttypmod FROM pg_attribute
+            WHERE attrelid = 'datasets'::regclass AND attname = 'embedding'
+            "#,
+        )
+        .fetch_one(&self.pool)
+        .await
+        .map_err(AppError::DatabaseError)?;
+
+        Ok((typmod > 0).then_some(typmod as usize))
+    }
+
+    /// Counts datasets that have an embedding.
+    pub async fn count_embeddings(&self) -> Result<i64, AppError> {
+        sqlx::query_scalar("SELECT COUNT(embedding) FROM datasets")
+            .fetch_one(&self.pool)
+            .await
+            .map_err(AppError::DatabaseError)
+    }
+
+    /// Migrates the embedding columns to `dimension`, in one transaction.
+    ///
+    /// Dataset embeddings cannot be converted between models, so they are
+    /// cleared and their model reset, which makes the next harvest re-embed
+    /// every dataset; chunk embeddings and the embedding cache are emptied
+    /// for the same reason.
+    /// Watches keep working: `watch_embeddings` must hold a new query
+    /// embedding, computed with `model`, for every watch. Indexes on the
+    /// columns are rebuilt by PostgreSQL. Returns the number of cleared
+    /// dataset embeddings.
+    ///
+    /// # Errors
+    ///
+    /// Fails (and changes nothing) if a watch has no new embedding or an
+    /// embedding has the wrong dimension.
+    pub async fn resize_embeddings(
+        &self,
+        dimension: usize,
+        watch_embeddings: &[(Uuid, Vector)],
+        model: &str,
+    ) -> Result<u64, AppError> {
+        let mut tx = self.pool.begin().await.map_err(AppError::DatabaseError)?;
+
+        let statements = [
+            format!(
+                "ALTER TABLE datasets ALTER COLUMN embedding TYPE vector({}) USING NULL",
+                dimension
+            ),
+            "TRUNCATE embedding_cache, dataset_chunks".to_string(),
+            format!(
+                "ALTER TABLE dataset_chunks ALTER COLUMN embedding TYPE vector({})",
+                dimension
+            ),
+            format!(
+                "ALTER TABLE embedding_cache ALTER COLUMN embedding TYPE vector({})",
+                dimension
+            ),
+            "ALTER TABLE watches ALTER COLUMN embedding DROP NOT NULL".to_string(),
+            format!(
+                "ALTER TABLE watches ALTER COLUMN embedding TYPE vector({}) USING NULL",
+                dimension
+            ),
+        ];
+        for statement in &statements {
+            sqlx::query(statement)
+                .execute(&mut *tx)
+                .await
+                .map_err(AppError::DatabaseError)?;
+        }
+
+        let cleared = sqlx::query(
+            "UPDATE datasets SET embedding_model = NULL, embedded_at = NULL, chunks_hash = NULL \
+             WHERE embedding_model IS NOT NULL OR chunks_hash IS NOT NULL",
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(AppError::DatabaseError)?
+        .rows_affected();
+
+        for (id, embedding) in watch_embeddings {
+            sqlx::query("UPDATE watches SET embedding = $2, embedding_model = $3 WHERE id = $1")
+                .bind(id)
+                .bind(embedding)
+                .bind(model)
+                .execute(&mut *tx)
+                .await
+                .map_err(AppError::DatabaseError)?;
+        }
+
+        sqlx::query("ALTER TABLE watches ALTER COLUMN embedding SET NOT NULL")
+            .execute(&mut *tx)
+            .await
+            .map_err(AppError::DatabaseError)?;
+
+        tx.commit().await.map_err(AppError::DatabaseError)?;
+        Ok(cleared)
+    }
+}
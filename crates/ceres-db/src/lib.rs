@@ -10,7 +10,61 @@
 //! - Retrieving datasets by ID
 //! - Semantic search using vector similarity
 //! - Database statistics
+//! - ANN index benchmarking and tuning
+//! - Portal health and quarantine state
+//! - Datasets harvests failed to process (dead-letter table)
+//! - Searches run, as a query log
+//! - Advisory locks keeping harvests of a portal from overlapping
+//! - Moving a portal to a new base URL
+//! - Watched topics and their webhook delivery log
+//! - Wikidata links for publishers and places
+//! - Embeddings cached by content hash and model
+//! - Chunk embeddings for multi-vector search
+//! - Topic clusters of dataset embeddings
+//! - Normalized tags
+//! - Publishing organizations
+//! - Dataset resources
+//! - Embedded schema migrations
+//!
+//! Harvest, search, export and stats go through the [`DatasetStore`]
+//! trait, which is also implemented by `SqliteRepository` (feature
+//! `sqlite`) for running without PostgreSQL, with embeddings indexed by
+//! sqlite-vec when the extension is loaded, and by `QdrantStore`
+//! (feature `qdrant`), which indexes embeddings in Qdrant on top of
+//! either. `MemoryStore` implements it in process, as a fake for tests.
 
+mod checkpoints;
+mod chunks;
+mod clusters;
+mod embedding_cache;
+mod enrichment;
+mod failures;
+mod health;
+mod history;
+mod hybrid;
+mod index;
+mod lock;
+mod maintenance;
+mod memory;
+mod migrations;
+mod organizations;
+mod portal;
+#[cfg(feature = "qdrant")]
+mod qdrant;
 mod repository;
+mod resources;
+#[cfg(feature = "sqlite")]
+mod sqlite;
+mod store;
+mod tags;
+mod watch;
 
+pub use lock::PortalLock;
+pub use memory::MemoryStore;
+pub use migrations::MIGRATOR;
+#[cfg(feature = "qdrant")]
+pub use qdrant::{QdrantStore, DEFAULT_QDRANT_COLLECTION};
 pub use repository::DatasetRepository;
+#[cfg(feature = "sqlite")]
+pub use sqlite::{SqliteRepository, SQLITE_MIGRATOR};
+pub use store::{is_sqlite_url, DatasetStore};
@@ -0,0 +1,112 @@
+//! Advisory locks keeping two harvests of a portal from running at once.
+//!
+//! A harvest holds a session-level PostgreSQL advisory lock keyed by the
+//! portal URL on a connection of its own, so harvests started by other
+//! processes (cron, `ceres daemon`, the gRPC server) see it. Session locks
+//! outlive transactions and are released with the session, so a harvest
+//! that crashes never leaves its portal locked.
+
+use ceres_core::error::AppError;
+use sqlx::pool::PoolConnection;
+use sqlx::Postgres;
+
+use crate::DatasetRepository;
+
+/// Lock key of a portal; URLs with and without a trailing slash share it.
+fn lock_key(portal_url: &str) -> String {
+    format!("ceres:harvest:{}", portal_url.trim_end_matches('/'))
+}
+
+/// A portal locked for a harvest, unlocked by [`PortalLock::release`].
+///
+/// Dropping the lock without releasing it closes its connection, which
+/// releases it too.
+#[derive(Debug)]
+pub struct PortalLock {
+    held: Option<(PoolConnection<Postgres>, String)>,
+}
+
+impl PortalLock {
+    /// A lock that guards nothing, for stores that cannot be shared between
+    /// processes.
+    pub fn unshared() -> Self {
+        Self { held: None }
+    }
+
+    /// Unlocks the portal and returns the connection to the pool.
+    ///
+    /// # Errors
+    ///
+    /// Returns `AppError::DatabaseError` if the unlock fails; the
+    /// connection is closed then, releasing the lock anyway.
+    pub async fn release(mut self) -> Result<(), AppError> {
+        let Some((mut conn, key)) = self.held.take() else {
+            return Ok(());
+        };
+        let result = sqlx::query("SELECT pg_advisory_unlock(hashtextextended($1, 0))")
+            .bind(&key)
+            .execute(&mut *conn)
+            .await;
+        if result.is_err() {
+            conn.close_on_drop();
+        }
+        result.map(|_| ()).map_err(AppError::DatabaseError)
+    }
+}
+
+impl Drop for PortalLock {
+    fn drop(&mut self) {
+        if let Some((conn, _)) = &mut self.held {
+            conn.close_on_drop();
+        }
+    }
+}
+
+impl DatasetRepository {
+    /// Locks a portal for a harvest, or returns `None` at once if another
+    /// harvest holds it.
+    pub async fn try_lock_portal(&self, portal_url: &str) -> Result<Option<PortalLock>, AppError> {
+        let mut conn = self.pool.acquire().await.map_err(AppError::DatabaseError)?;
+        let key = lock_key(portal_url);
+        let locked: bool =
+            sqlx::query_scalar("SELECT pg_try_advisory_lock(hashtextextended($1, 0))")
+                .bind(&key)
+                .fetch_one(&mut *conn)
+                .await
+                .map_err(AppError::DatabaseError)?;
+
+        Ok(locked.then(|| PortalLock {
+            held: Some((conn, key)),
+        }))
+    }
+
+    /// Locks a portal for a harvest, waiting for any other harvest holding
+    /// it to finish.
+    pub async fn lock_portal(&self, portal_url: &str) -> Result<PortalLock, AppError> {
+        let mut conn = self.pool.acquire().await.map_err(AppError::DatabaseError)?;
+        let key = lock_key(portal_url);
+        sqlx::query("SELECT pg_advisory_lock(hashtextextended($1, 0))")
+            .bind(&key)
+            .execute(&mut *conn)
+            .await
+            .map_err(AppError::DatabaseError)?;
+
+        Ok(PortalLock {
+            held: Some((conn, key)),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_lock_key_ignores_trailing_slash() {
+        assert_eq!(
+            lock_key("https://dati.comune.milano.it/"),
+            lock_key("https://dati.comune.milano.it")
+        );
+        assert_ne!(lock_key("https://a.com"), lock_key("https://b.com"));
+    }
+}
@@ -0,0 +1,254 @@
+//! Disk usage measurement and retention for `ceres maintain`.
+
+use ceres_core::error::AppError;
+use ceres_core::maintenance::{PortalGrowth, TableSize, GROWTH_WINDOW_DAYS};
+use chrono::{DateTime, Utc};
+use uuid::Uuid;
+
+use crate::DatasetRepository;
+
+/// Helper struct for deserializing table size rows
+#[derive(sqlx::FromRow)]
+struct TableSizeRow {
+    table_name: String,
+    data_bytes: i64,
+    index_bytes: i64,
+}
+
+/// Helper struct for deserializing per-portal growth rows
+#[derive(sqlx::FromRow)]
+struct PortalGrowthRow {
+    portal: String,
+    since: DateTime<Utc>,
+    added: i64,
+}
+
+impl DatasetRepository {
+    /// Returns the total on-disk size of the database in bytes.
+    pub async fn database_size(&self) -> Result<u64, AppError> {
+        let bytes: i64 = sqlx::query_scalar("SELECT pg_database_size(current_database())")
+            .fetch_one(&self.pool)
+            .await
+            .map_err(AppError::DatabaseError)?;
+
+        Ok(bytes.max(0) as u64)
+    }
+
+    /// Returns the size of every table in the current schema, largest first.
+    pub async fn table_sizes(&self) -> Result<Vec<TableSize>, AppError> {
+        let rows: Vec<TableSizeRow> = sqlx::query_as(
+            r#"
+            SELECT c.relname::text AS table_name,
+                   pg_table_size(c.oid) AS data_bytes,
+                   pg_indexes_size(c.oid) AS index_bytes
+            FROM pg_class c
+            JOIN pg_namespace n ON n.oid = c.relnamespace
+            WHERE c.relkind = 'r' AND n.nspname = current_schema()
+            ORDER BY pg_total_relation_size(c.oid) DESC
+            "#,
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(AppError::DatabaseError)?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| TableSize {
+                table: row.table_name,
+                data_bytes: row.data_bytes.max(0) as u64,
+                index_bytes: row.index_bytes.max(0) as u64,
+            })
+            .collect())
+    }
+
+    /// Returns how many datasets each portal gained recently.
+    ///
+    /// The first day after a portal's earliest dataset is skipped, so the
+    /// initial import doesn't count as growth, and history is limited to
+    /// the last [`GROWTH_WINDOW_DAYS`] days.
+    pub async fn portal_growth(&self) -> Result<Vec<PortalGrowth>, AppError> {
+        let rows: Vec<PortalGrowthRow> = sqlx::query_as(
+            r#"
+            SELECT source_portal AS portal, since,
+                   COUNT(*) FILTER (WHERE first_seen_at > since) AS added
+            FROM (
+                SELECT source_portal, first_seen_at,
+                       GREATEST(
+                           MIN(first_seen_at) OVER (PARTITION BY source_portal) + INTERVAL '1 day',
+                           NOW() - make_interval(days => $1)
+                       ) AS since
+                FROM datasets
+            ) d
+            GROUP BY source_portal, since
+            ORDER BY source_portal
+            "#,
+        )
+        .bind(GROWTH_WINDOW_DAYS as i32)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(AppError::DatabaseError)?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| PortalGrowth {
+                portal: row.portal,
+                since: row.since,
+                added: row.added,
+            })
+            .collect())
+    }
+
+    /// Returns the stored size of all embeddings in bytes.
+    pub async fn embedding_bytes(&self) -> Result<u64, AppError> {
+        let bytes: i64 = sqlx::query_scalar(
+            "SELECT COALESCE(SUM(pg_column_size(embedding)), 0)::bigint FROM datasets",
+        )
+        .fetch_one(&self.pool)
+        .await
+        .map_err(AppError::DatabaseError)?;
+
+        Ok(bytes.max(0) as u64)
+    }
+
+    /// Counts stale datasets and their row size in bytes.
+    ///
+    /// A dataset is stale if it has not been seen since `before` although
+    /// its portal was harvested successfully after that, i.e. it was removed
+    /// upstream. Portals that stopped being harvested keep their datasets.
+    pub async fn count_stale_datasets(
+        &self,
+        before: DateTime<Utc>,
+    ) -> Result<(i64, u64), AppError> {
+        let (count, bytes): (i64, i64) = sqlx::query_as(
+            r#"
+            SELECT COUNT(*), COALESCE(SUM(pg_column_size(d.*)), 0)::bigint
+            FROM datasets d
+            JOIN portal_health h ON h.portal_url = d.source_portal
+            WHERE d.last_updated_at < $1 AND h.last_success_at > $1
+            "#,
+        )
+        .bind(before)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(AppError::DatabaseError)?;
+
+        Ok((count, bytes.max(0) as u64))
+    }
+
+    /// Deletes the datasets counted by
+    /// [`count_stale_datasets`](Self::count_stale_datasets). Returns the number deleted.
+    pub async fn prune_stale_datasets(&self, before: DateTime<Utc>) -> Result<u64, AppError> {
+        let result = sqlx::query(
+            r#"
+            DELETE FROM datasets d
+            USING portal_health h
+            WHERE h.portal_url = d.source_portal
+              AND d.last_updated_at < $1 AND h.last_success_at > $1
+            "#,
+        )
+        .bind(before)
+        .execute(&self.pool)
+        .await
+        .map_err(AppError::DatabaseError)?;
+
+        Ok(result.rows_affected())
+    }
+
+    /// Counts the datasets no harvest has seen since `before`, optionally of
+    /// one portal (trailing slashes are ignored).
+    ///
+    /// Unlike [`count_stale_datasets`](Self::count_stale_datasets), portals
+    /// that have not been harvested since are included.
+    pub async fn count_unseen_datasets(
+        &self,
+        before: DateTime<Utc>,
+        portal_url: Option<&str>,
+    ) -> Result<i64, AppError> {
+        sqlx::query_scalar(
+            r#"
+            SELECT COUNT(*)
+            FROM datasets
+            WHERE last_updated_at < $1
+              AND ($2::text IS NULL OR rtrim(source_portal, '/') = $2)
+            "#,
+        )
+        .bind(before)
+        .bind(portal_url.map(|url| url.trim_end_matches('/')))
+        .fetch_one(&self.pool)
+        .await
+        .map_err(AppError::DatabaseError)
+    }
+
+    /// Deletes the datasets counted by
+    /// [`count_unseen_datasets`](Self::count_unseen_datasets). Returns
+    /// their IDs.
+    pub async fn prune_unseen_datasets(
+        &self,
+        before: DateTime<Utc>,
+        portal_url: Option<&str>,
+    ) -> Result<Vec<Uuid>, AppError> {
+        sqlx::query_scalar(
+            r#"
+            DELETE FROM datasets
+            WHERE last_updated_at < $1
+              AND ($2::text IS NULL OR rtrim(source_portal, '/') = $2)
+            RETURNING id
+            "#,
+        )
+        .bind(before)
+        .bind(portal_url.map(|url| url.trim_end_matches('/')))
+        .fetch_all(&self.pool)
+        .await
+        .map_err(AppError::DatabaseError)
+    }
+
+    /// Counts failed watch deliveries older than `before` and their row size in bytes.
+    pub async fn count_failed_deliveries_before(
+        &self,
+        before: DateTime<Utc>,
+    ) -> Result<(i64, u64), AppError> {
+        let (count, bytes): (i64, i64) = sqlx::query_as(
+            r#"
+            SELECT COUNT(*), COALESCE(SUM(pg_column_size(wd.*)), 0)::bigint
+            FROM watch_deliveries wd
+            WHERE wd.status = 'failed' AND wd.created_at < $1
+            "#,
+        )
+        .bind(before)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(AppError::DatabaseError)?;
+
+        Ok((count, bytes.max(0) as u64))
+    }
+
+    /// Deletes failed watch deliveries older than `before`. Returns the number deleted.
+    ///
+    /// Successful deliveries are kept: they record which datasets a watch
+    /// has already sent, and pruning them would trigger redeliveries.
+    pub async fn prune_failed_deliveries_before(
+        &self,
+        before: DateTime<Utc>,
+    ) -> Result<u64, AppError> {
+        let result =
+            sqlx::query("DELETE FROM watch_deliveries WHERE status = 'failed' AND created_at < $1")
+                .bind(before)
+                .execute(&self.pool)
+                .await
+                .map_err(AppError::DatabaseError)?;
+
+        Ok(result.rows_affected())
+    }
+
+    /// Runs `VACUUM ANALYZE` on the tables retention prunes, so the freed
+    /// space is reused by later harvests instead of growing the files.
+    pub async fn vacuum_pruned_tables(&self) -> Result<(), AppError> {
+        for table in ["datasets", "watch_deliveries"] {
+            sqlx::query(&format!("VACUUM (ANALYZE) {}", table))
+                .execute(&self.pool)
+                .await
+                .map_err(AppError::DatabaseError)?;
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,933 @@
+//! In-memory storage backend.
+//!
+//! [`MemoryStore`] keeps datasets in process and implements
+//! [`DatasetStore`] with the semantics of the SQLite backend: exact cosine
+//! ranking, keyword scoring on title and description, and every structured
+//! filter evaluated in Rust. It is meant as a fake for tests of code written
+//! against the trait; nothing is persisted, chunk embeddings are not
+//! supported and watches are kept without their query embedding.
+
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, HashMap, HashSet};
+use std::sync::{RwLock, RwLockReadGuard, RwLockWriteGuard};
+
+use async_trait::async_trait;
+use ceres_core::chunks::DatasetChunk;
+use ceres_core::error::AppError;
+use ceres_core::health::PortalHealth;
+use ceres_core::history::SearchRecord;
+use ceres_core::models::{
+    DatabaseStats, Dataset, EmbeddingModelCount, FormatCount, LanguageCount, NewDataset,
+    PortalQuality, PortalStats, SchemaMigration, SearchResult,
+};
+use ceres_core::organizations::DatasetOrganization;
+use ceres_core::quality::LOW_QUALITY;
+use ceres_core::resources::DatasetResource;
+use ceres_core::search::{
+    keyword_terms, SearchFilters, SearchStrategy, HYBRID_CANDIDATES_PER_RESULT,
+};
+use ceres_core::spatial::BoundingBox;
+use ceres_core::sync::{HarvestCheckpoint, HarvestFailure, UpsertOutcome};
+use ceres_core::watch::Watch;
+use chrono::{DateTime, Utc};
+use futures::stream::{self, BoxStream, StreamExt};
+use pgvector::Vector;
+use sqlx::types::Json;
+use uuid::Uuid;
+
+use crate::store::{cosine_similarity, fuse_results, lexical_score, DatasetStore};
+
+/// Number of formats reported in the stats facet.
+const FORMAT_FACET_LIMIT: usize = 10;
+
+/// Dataset storage in process memory, for tests.
+///
+/// # Examples
+///
+/// ```
+/// use ceres_db::{DatasetStore, MemoryStore};
+///
+/// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+/// let store = MemoryStore::new();
+/// let stats = store.get_stats(None).await?;
+/// assert_eq!(stats.total_datasets, 0);
+/// # Ok(())
+/// # }
+/// ```
+#[derive(Default)]
+pub struct MemoryStore {
+    state: RwLock<MemoryState>,
+}
+
+#[derive(Default)]
+struct MemoryState {
+    datasets: HashMap<Uuid, StoredDataset>,
+    /// (portal URL, original ID) → dataset ID
+    keys: HashMap<(String, String), Uuid>,
+    /// (content hash, model) → embedding
+    embedding_cache: HashMap<(String, String), Vector>,
+    health: HashMap<String, PortalHealth>,
+    /// (portal URL, dataset ID) → latest failure
+    failures: HashMap<(String, String), HarvestFailure>,
+    /// Portal URL → checkpoint of its interrupted harvest
+    checkpoints: HashMap<String, HarvestCheckpoint>,
+    /// Watches, oldest first
+    watches: Vec<Watch>,
+    /// Query log, oldest first
+    searches: Vec<SearchRecord>,
+}
+
+/// A dataset with the fields stored outside [`Dataset`].
+struct StoredDataset {
+    dataset: Dataset,
+    tags: Vec<String>,
+    organization: Option<DatasetOrganization>,
+    bbox: Option<BoundingBox>,
+    resources: Vec<DatasetResource>,
+}
+
+impl StoredDataset {
+    /// Returns true if no harvest has seen the dataset since `before`, and
+    /// it comes from `portal_url` if one is given.
+    fn unseen_since(&self, before: DateTime<Utc>, portal_url: Option<&str>) -> bool {
+        self.dataset.last_updated_at < before
+            && same_portal(&self.dataset.source_portal, portal_url)
+    }
+
+    /// Returns true if the dataset passes every filter.
+    fn passes(&self, filters: &SearchFilters) -> bool {
+        let dataset = &self.dataset;
+        let metadata = &dataset.metadata.0;
+        let equals = |filter: &Option<String>, value: Option<&str>| match filter {
+            Some(filter) => value == Some(filter.as_str()),
+            None => true,
+        };
+
+        equals(&filters.portal, Some(&dataset.source_portal))
+            && filters.theme.as_ref().is_none_or(|theme| {
+                metadata["groups"]
+                    .as_array()
+                    .is_some_and(|groups| groups.iter().any(|g| g["name"] == theme.as_str()))
+            })
+            && equals(&filters.embedding_model, dataset.embedding_model.as_deref())
+            && filters
+                .format
+                .as_ref()
+                .is_none_or(|format| dataset.formats.contains(format))
+            && equals(&filters.license, metadata["license_id"].as_str())
+            && filters
+                .updated_after
+                .is_none_or(|after| dataset.modified_at.is_some_and(|at| at >= after))
+            && filters
+                .updated_before
+                .is_none_or(|before| dataset.modified_at.is_some_and(|at| at < before))
+            && equals(&filters.language, dataset.language.as_deref())
+            && filters
+                .tag
+                .as_ref()
+                .is_none_or(|tag| self.tags.contains(tag))
+            && filters.organization.as_ref().is_none_or(|name| {
+                self.organization.as_ref().is_some_and(|org| {
+                    org.name == *name
+                        || org
+                            .title
+                            .as_ref()
+                            .is_some_and(|title| title.to_lowercase() == name.to_lowercase())
+                })
+            })
+            && filters.metadata.iter().all(|f| f.matches(metadata))
+            && filters
+                .bbox
+                .is_none_or(|bbox| self.bbox.is_some_and(|extent| extent.overlaps(&bbox)))
+    }
+}
+
+impl MemoryStore {
+    /// Creates an empty store.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    fn read(&self) -> RwLockReadGuard<'_, MemoryState> {
+        // A panicking writer leaves the maps consistent, so poisoning is ignored
+        self.state.read().unwrap_or_else(|e| e.into_inner())
+    }
+
+    fn write(&self) -> RwLockWriteGuard<'_, MemoryState> {
+        self.state.write().unwrap_or_else(|e| e.into_inner())
+    }
+
+    /// Scores the datasets passing `filters` and returns the `limit` best,
+    /// best first. Datasets `score` returns `None` for are left out.
+    fn top_scored(
+        &self,
+        filters: &SearchFilters,
+        limit: usize,
+        score: impl Fn(&Dataset) -> Option<f32>,
+    ) -> Vec<SearchResult> {
+        let state = self.read();
+        let mut scored: Vec<(&Dataset, f32)> = state
+            .datasets
+            .values()
+            .filter(|stored| stored.passes(filters))
+            .filter_map(|stored| score(&stored.dataset).map(|s| (&stored.dataset, s)))
+            .collect();
+
+        scored.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.id.cmp(&b.0.id)));
+        scored
+            .into_iter()
+            .take(limit)
+            .map(|(dataset, score)| SearchResult {
+                dataset: dataset.clone(),
+                similarity_score: score,
+            })
+            .collect()
+    }
+}
+
+#[async_trait]
+impl DatasetStore for MemoryStore {
+    async fn embedding_dimension(&self) -> Result<Option<usize>, AppError> {
+        Ok(self
+            .read()
+            .datasets
+            .values()
+            .find_map(|stored| stored.dataset.embedding.as_ref())
+            .map(|embedding| embedding.as_slice().len()))
+    }
+
+    async fn pending_migrations(&self) -> Result<Vec<SchemaMigration>, AppError> {
+        Ok(Vec::new())
+    }
+
+    async fn run_migrations(&self) -> Result<Vec<SchemaMigration>, AppError> {
+        Ok(Vec::new())
+    }
+
+    async fn get_hashes_for_portal(
+        &self,
+        portal_url: &str,
+        embedding_model: &str,
+    ) -> Result<HashMap<String, Option<String>>, AppError> {
+        Ok(self
+            .read()
+            .datasets
+            .values()
+            .map(|stored| &stored.dataset)
+            .filter(|dataset| dataset.source_portal == portal_url)
+            .map(|dataset| {
+                let hash = (dataset.embedding_model.as_deref() == Some(embedding_model))
+                    .then(|| dataset.content_hash.clone())
+                    .flatten();
+                (dataset.original_id.clone(), hash)
+            })
+            .collect())
+    }
+
+    async fn update_timestamp_only(
+        &self,
+        portal_url: &str,
+        original_id: &str,
+    ) -> Result<bool, AppError> {
+        let mut state = self.write();
+        let key = (portal_url.to_string(), original_id.to_string());
+        let Some(id) = state.keys.get(&key).copied() else {
+            return Ok(false);
+        };
+        if let Some(stored) = state.datasets.get_mut(&id) {
+            stored.dataset.last_updated_at = Utc::now();
+        }
+        Ok(true)
+    }
+
+    async fn upsert(&self, new_data: &NewDataset) -> Result<UpsertOutcome, AppError> {
+        let now = Utc::now();
+        let mut state = self.write();
+        let key = (new_data.source_portal.clone(), new_data.original_id.clone());
+        let previous = state
+            .keys
+            .get(&key)
+            .and_then(|id| state.datasets.get(id))
+            .map(|stored| &stored.dataset);
+
+        let outcome = match previous {
+            Some(dataset) => UpsertOutcome::Updated(dataset.id),
+            None => UpsertOutcome::Created(Uuid::new_v4()),
+        };
+        let id = outcome.id();
+        // Like the SQL backends, a missing embedding keeps the stored one
+        let (embedding, embedding_model, embedded_at) = match &new_data.embedding {
+            Some(embedding) => (
+                Some(embedding.clone()),
+                new_data.embedding_model.clone(),
+                Some(now),
+            ),
+            None => (
+                previous.and_then(|d| d.embedding.clone()),
+                previous.and_then(|d| d.embedding_model.clone()),
+                previous.and_then(|d| d.embedded_at),
+            ),
+        };
+        let dataset = Dataset {
+            id,
+            original_id: new_data.original_id.clone(),
+            source_portal: new_data.source_portal.clone(),
+            url: new_data.url.clone(),
+            title: new_data.title.clone(),
+            description: new_data.description.clone(),
+            embedding,
+            metadata: Json(new_data.metadata.clone()),
+            formats: new_data.formats.clone(),
+            first_seen_at: previous.map_or(now, |d| d.first_seen_at),
+            last_updated_at: now,
+            content_hash: Some(new_data.content_hash.clone()),
+            embedding_model,
+            embedded_at,
+            modified_at: new_data.modified_at,
+            language: new_data.language.clone(),
+            quality: new_data.quality,
+        };
+
+        state.keys.insert(key, id);
+        state.datasets.insert(
+            id,
+            StoredDataset {
+                dataset,
+                tags: new_data.tags.clone(),
+                organization: new_data.organization.clone(),
+                bbox: new_data.bbox,
+                resources: new_data.resources.clone(),
+            },
+        );
+        Ok(outcome)
+    }
+
+    async fn cached_embedding(
+        &self,
+        content_hash: &str,
+        model: &str,
+    ) -> Result<Option<Vector>, AppError> {
+        let key = (content_hash.to_string(), model.to_string());
+        Ok(self.read().embedding_cache.get(&key).cloned())
+    }
+
+    async fn cache_embedding(
+        &self,
+        content_hash: &str,
+        model: &str,
+        embedding: &Vector,
+    ) -> Result<(), AppError> {
+        let key = (content_hash.to_string(), model.to_string());
+        self.write().embedding_cache.insert(key, embedding.clone());
+        Ok(())
+    }
+
+    async fn get_chunk_hashes_for_portal(
+        &self,
+        _portal_url: &str,
+        _embedding_model: &str,
+    ) -> Result<HashMap<String, (Uuid, Option<String>)>, AppError> {
+        Err(chunks_unsupported())
+    }
+
+    async fn replace_dataset_chunks(
+        &self,
+        _dataset_id: Uuid,
+        _chunks: &[(DatasetChunk, Vector)],
+        _embedding_model: &str,
+        _chunks_hash: Option<&str>,
+    ) -> Result<(), AppError> {
+        Err(chunks_unsupported())
+    }
+
+    async fn list_portal_health(&self) -> Result<Vec<PortalHealth>, AppError> {
+        let mut health: Vec<PortalHealth> = self.read().health.values().cloned().collect();
+        health.sort_by(|a, b| a.portal_url.cmp(&b.portal_url));
+        Ok(health)
+    }
+
+    async fn get_portal_health(&self, portal_url: &str) -> Result<PortalHealth, AppError> {
+        Ok(self
+            .read()
+            .health
+            .get(portal_url)
+            .cloned()
+            .unwrap_or_else(|| PortalHealth::new(portal_url)))
+    }
+
+    async fn save_portal_health(&self, health: &PortalHealth) -> Result<(), AppError> {
+        self.write()
+            .health
+            .insert(health.portal_url.clone(), health.clone());
+        Ok(())
+    }
+
+    async fn search_with_filters(
+        &self,
+        query_vector: Vector,
+        _query_text: &str,
+        limit: usize,
+        filters: &SearchFilters,
+        _strategy: SearchStrategy,
+    ) -> Result<Vec<SearchResult>, AppError> {
+        let query = query_vector.as_slice();
+        Ok(self.top_scored(filters, limit, |dataset| {
+            dataset
+                .embedding
+                .as_ref()
+                .map(|embedding| cosine_similarity(query, embedding.as_slice()))
+        }))
+    }
+
+    /// Ranks datasets by the query terms found in their title (weighted
+    /// double) and description, ignoring the embedding model filter.
+    async fn text_search(
+        &self,
+        query_text: &str,
+        limit: usize,
+        filters: &SearchFilters,
+    ) -> Result<Vec<SearchResult>, AppError> {
+        let filters = SearchFilters {
+            embedding_model: None,
+            ..filters.clone()
+        };
+        let terms = keyword_terms(query_text);
+        if terms.is_empty() {
+            return Ok(Vec::new());
+        }
+        Ok(self.top_scored(&filters, limit, |dataset| {
+            lexical_score(&terms, &dataset.title, dataset.description.as_deref())
+        }))
+    }
+
+    async fn hybrid_search(
+        &self,
+        query_vector: Vector,
+        query_text: &str,
+        limit: usize,
+        filters: &SearchFilters,
+        strategy: SearchStrategy,
+    ) -> Result<Vec<SearchResult>, AppError> {
+        let candidates = limit * HYBRID_CANDIDATES_PER_RESULT;
+        let semantic = self
+            .search_with_filters(query_vector, query_text, candidates, filters, strategy)
+            .await?;
+        let lexical = self.text_search(query_text, candidates, filters).await?;
+        Ok(fuse_results(semantic, lexical, limit))
+    }
+
+    async fn search_with_chunks(
+        &self,
+        _query_vector: Vector,
+        _query_text: &str,
+        _limit: usize,
+        _filters: &SearchFilters,
+        _strategy: SearchStrategy,
+    ) -> Result<Vec<SearchResult>, AppError> {
+        Err(chunks_unsupported())
+    }
+
+    fn stream_all<'a>(
+        &'a self,
+        filters: &'a SearchFilters,
+        limit: Option<usize>,
+    ) -> BoxStream<'a, Result<Dataset, AppError>> {
+        let state = self.read();
+        let mut datasets: Vec<&Dataset> = state
+            .datasets
+            .values()
+            .filter(|stored| stored.passes(filters))
+            .map(|stored| &stored.dataset)
+            .collect();
+        datasets.sort_by_key(|dataset| Reverse((dataset.last_updated_at, dataset.id)));
+        let datasets: Vec<Dataset> = datasets
+            .into_iter()
+            .take(limit.unwrap_or(usize::MAX))
+            .cloned()
+            .collect();
+        stream::iter(datasets.into_iter().map(Ok)).boxed()
+    }
+
+    async fn datasets_by_ids(
+        &self,
+        ids: &[Uuid],
+        filters: &SearchFilters,
+    ) -> Result<Vec<Dataset>, AppError> {
+        let state = self.read();
+        Ok(ids
+            .iter()
+            .collect::<HashSet<_>>()
+            .into_iter()
+            .filter_map(|id| state.datasets.get(id))
+            .filter(|stored| stored.passes(filters))
+            .map(|stored| stored.dataset.clone())
+            .collect())
+    }
+
+    async fn get_stats(&self, portal: Option<&str>) -> Result<DatabaseStats, AppError> {
+        let state = self.read();
+        let datasets: Vec<&StoredDataset> = state
+            .datasets
+            .values()
+            .filter(|stored| portal.is_none_or(|p| stored.dataset.source_portal == p))
+            .collect();
+
+        let mut portals: BTreeMap<&str, PortalStats> = BTreeMap::new();
+        let mut formats: BTreeMap<&str, FormatCount> = BTreeMap::new();
+        let mut languages: BTreeMap<Option<&str>, i64> = BTreeMap::new();
+        let mut quality: BTreeMap<&str, (i64, f64, i64)> = BTreeMap::new();
+        let mut models: BTreeMap<&str, (EmbeddingModelCount, HashSet<&str>)> = BTreeMap::new();
+
+        for stored in &datasets {
+            let dataset = &stored.dataset;
+            let embedded = dataset.embedding.is_some();
+
+            let entry = portals
+                .entry(&dataset.source_portal)
+                .or_insert_with(|| PortalStats {
+                    portal: dataset.source_portal.clone(),
+                    datasets: 0,
+                    with_embeddings: 0,
+                    last_update: None,
+                    last_harvest_at: None,
+                    harvests: 0,
+                    failed_harvests: 0,
+                });
+            entry.datasets += 1;
+            entry.with_embeddings += i64::from(embedded);
+            entry.last_update = entry.last_update.max(Some(dataset.last_updated_at));
+
+            let mut seen = HashSet::new();
+            for resource in &stored.resources {
+                let Some(format) = resource.format.as_deref() else {
+                    continue;
+                };
+                let count = formats.entry(format).or_insert_with(|| FormatCount {
+                    format: format.to_string(),
+                    datasets: 0,
+                    resources: 0,
+                    total_size: 0,
+                });
+                count.datasets += i64::from(seen.insert(format));
+                count.resources += 1;
+                count.total_size += resource.size.unwrap_or(0);
+            }
+
+            *languages.entry(dataset.language.as_deref()).or_default() += 1;
+
+            if let Some(score) = dataset.quality {
+                let entry = quality.entry(&dataset.source_portal).or_default();
+                entry.0 += 1;
+                entry.1 += f64::from(score);
+                entry.2 += i64::from(score < LOW_QUALITY);
+            }
+
+            if let (true, Some(model)) = (embedded, dataset.embedding_model.as_deref()) {
+                let (count, portals) = models.entry(model).or_insert_with(|| {
+                    let count = EmbeddingModelCount {
+                        model: model.to_string(),
+                        datasets: 0,
+                        portals: 0,
+                        first_embedded_at: None,
+                        last_embedded_at: None,
+                    };
+                    (count, HashSet::new())
+                });
+                count.datasets += 1;
+                portals.insert(&dataset.source_portal);
+                count.first_embedded_at = match (count.first_embedded_at, dataset.embedded_at) {
+                    (Some(a), Some(b)) => Some(a.min(b)),
+                    (a, b) => a.or(b),
+                };
+                count.last_embedded_at = count.last_embedded_at.max(dataset.embedded_at);
+            }
+        }
+
+        let mut portals: Vec<PortalStats> = portals
+            .into_values()
+            .map(|mut stats| {
+                let url = stats.portal.trim_end_matches('/');
+                if let Some(health) = state
+                    .health
+                    .values()
+                    .find(|h| h.portal_url.trim_end_matches('/') == url)
+                {
+                    stats.last_harvest_at = health.last_success_at;
+                    stats.harvests = i64::from(health.harvests);
+                    stats.failed_harvests = i64::from(health.failed_harvests);
+                }
+                stats
+            })
+            .collect();
+        // Least recently harvested first, never-harvested portals before all
+        portals.sort_by(|a, b| {
+            let at = |s: &PortalStats| s.last_harvest_at.or(s.last_update);
+            at(a).cmp(&at(b)).then_with(|| a.portal.cmp(&b.portal))
+        });
+
+        let mut formats: Vec<FormatCount> = formats.into_values().collect();
+        formats.sort_by(|a, b| b.datasets.cmp(&a.datasets).then(a.format.cmp(&b.format)));
+        formats.truncate(FORMAT_FACET_LIMIT);
+
+        let mut languages: Vec<LanguageCount> = languages
+            .into_iter()
+            .map(|(language, datasets)| LanguageCount {
+                language: language.map(str::to_string),
+                datasets,
+            })
+            .collect();
+        languages.sort_by(|a, b| {
+            (a.language.is_none(), b.datasets, &a.language).cmp(&(
+                b.language.is_none(),
+                a.datasets,
+                &b.language,
+            ))
+        });
+
+        let mut quality: Vec<PortalQuality> = quality
+            .into_iter()
+            .map(|(portal, (datasets, sum, low))| PortalQuality {
+                portal: portal.to_string(),
+                datasets,
+                average: sum / datasets as f64,
+                low,
+            })
+            .collect();
+        quality.sort_by(|a, b| a.average.total_cmp(&b.average));
+
+        let mut embedding_models: Vec<EmbeddingModelCount> = models
+            .into_values()
+            .map(|(mut count, portals)| {
+                count.portals = portals.len() as i64;
+                count
+            })
+            .collect();
+        embedding_models.sort_by_key(|count| std::cmp::Reverse(count.datasets));
+
+        Ok(DatabaseStats {
+            total_datasets: datasets.len() as i64,
+            datasets_with_embeddings: datasets
+                .iter()
+                .filter(|stored| stored.dataset.embedding.is_some())
+                .count() as i64,
+            total_portals: portals.len() as i64,
+            last_update: datasets
+                .iter()
+                .map(|stored| stored.dataset.last_updated_at)
+                .max(),
+            formats,
+            languages,
+            quality,
+            embedding_models,
+            portals,
+        })
+    }
+
+    async fn count_portal_datasets(&self, portal_url: &str) -> Result<i64, AppError> {
+        let portal_url = portal_url.trim_end_matches('/');
+        let state = self.read();
+        Ok(state
+            .keys
+            .keys()
+            .filter(|(portal, _)| portal.trim_end_matches('/') == portal_url)
+            .count() as i64)
+    }
+
+    async fn delete_portal_datasets(&self, portal_url: &str) -> Result<u64, AppError> {
+        let portal_url = portal_url.trim_end_matches('/');
+        let mut state = self.write();
+        let MemoryState { datasets, keys, .. } = &mut *state;
+        let mut deleted = 0;
+        keys.retain(|(portal, _), id| {
+            let keep = portal.trim_end_matches('/') != portal_url;
+            if !keep {
+                datasets.remove(id);
+                deleted += 1;
+            }
+            keep
+        });
+        Ok(deleted)
+    }
+
+    async fn count_unseen_datasets(
+        &self,
+        before: DateTime<Utc>,
+        portal_url: Option<&str>,
+    ) -> Result<i64, AppError> {
+        let state = self.read();
+        Ok(state
+            .datasets
+            .values()
+            .filter(|stored| stored.unseen_since(before, portal_url))
+            .count() as i64)
+    }
+
+    async fn prune_unseen_datasets(
+        &self,
+        before: DateTime<Utc>,
+        portal_url: Option<&str>,
+    ) -> Result<Vec<Uuid>, AppError> {
+        let mut state = self.write();
+        let MemoryState { datasets, keys, .. } = &mut *state;
+        let mut pruned = Vec::new();
+        keys.retain(|_, id| {
+            let unseen = datasets
+                .get(id)
+                .is_some_and(|stored| stored.unseen_since(before, portal_url));
+            if unseen {
+                datasets.remove(id);
+                pruned.push(*id);
+            }
+            !unseen
+        });
+        Ok(pruned)
+    }
+
+    async fn record_harvest_failures(&self, failures: &[HarvestFailure]) -> Result<(), AppError> {
+        let mut state = self.write();
+        for failure in failures {
+            let key = (failure.portal_url.clone(), failure.dataset_id.clone());
+            let attempts = state.failures.get(&key).map_or(0, |f| f.attempts) + 1;
+            state.failures.insert(
+                key,
+                HarvestFailure {
+                    attempts,
+                    ..failure.clone()
+                },
+            );
+        }
+        Ok(())
+    }
+
+    async fn list_harvest_failures(
+        &self,
+        portal_url: Option<&str>,
+    ) -> Result<Vec<HarvestFailure>, AppError> {
+        let mut failures: Vec<HarvestFailure> = self
+            .read()
+            .failures
+            .values()
+            .filter(|f| same_portal(&f.portal_url, portal_url))
+            .cloned()
+            .collect();
+        failures.sort_by(|a, b| {
+            (&a.portal_url, Reverse(a.failed_at), &a.dataset_id).cmp(&(
+                &b.portal_url,
+                Reverse(b.failed_at),
+                &b.dataset_id,
+            ))
+        });
+        Ok(failures)
+    }
+
+    async fn resolve_harvest_failures(
+        &self,
+        portal_url: Option<&str>,
+        before: DateTime<Utc>,
+    ) -> Result<u64, AppError> {
+        let mut state = self.write();
+        let count = state.failures.len();
+        state
+            .failures
+            .retain(|_, f| !(f.failed_at < before && same_portal(&f.portal_url, portal_url)));
+        Ok((count - state.failures.len()) as u64)
+    }
+
+    async fn get_harvest_checkpoint(
+        &self,
+        portal_url: &str,
+    ) -> Result<Option<HarvestCheckpoint>, AppError> {
+        Ok(self.read().checkpoints.get(portal_url).cloned())
+    }
+
+    async fn save_harvest_checkpoint(
+        &self,
+        checkpoint: &HarvestCheckpoint,
+    ) -> Result<(), AppError> {
+        self.write()
+            .checkpoints
+            .insert(checkpoint.portal_url.clone(), checkpoint.clone());
+        Ok(())
+    }
+
+    async fn clear_harvest_checkpoint(&self, portal_url: &str) -> Result<(), AppError> {
+        self.write().checkpoints.remove(portal_url);
+        Ok(())
+    }
+
+    async fn create_watch(&self, watch: &Watch, _embedding: Vector) -> Result<(), AppError> {
+        let mut state = self.write();
+        if state.watches.iter().any(|w| w.name == watch.name) {
+            return Err(AppError::ConfigError(format!(
+                "A watch named '{}' already exists",
+                watch.name
+            )));
+        }
+        state.watches.push(watch.clone());
+        Ok(())
+    }
+
+    async fn list_watches(&self) -> Result<Vec<Watch>, AppError> {
+        Ok(self.read().watches.clone())
+    }
+
+    async fn delete_watch_by_id(&self, id: Uuid) -> Result<bool, AppError> {
+        let mut state = self.write();
+        let count = state.watches.len();
+        state.watches.retain(|w| w.id != id);
+        Ok(state.watches.len() < count)
+    }
+
+    async fn record_search(&self, search: &SearchRecord) -> Result<(), AppError> {
+        self.write().searches.push(search.clone());
+        Ok(())
+    }
+
+    async fn list_searches(&self, limit: usize) -> Result<Vec<SearchRecord>, AppError> {
+        Ok(self
+            .read()
+            .searches
+            .iter()
+            .rev()
+            .take(limit)
+            .cloned()
+            .collect())
+    }
+
+    async fn clear_search_history(&self) -> Result<u64, AppError> {
+        let mut state = self.write();
+        let count = state.searches.len();
+        state.searches.clear();
+        Ok(count as u64)
+    }
+}
+
+/// Returns true if `url` is `portal_url`, ignoring trailing slashes, or no
+/// portal is given.
+fn same_portal(url: &str, portal_url: Option<&str>) -> bool {
+    portal_url.is_none_or(|portal| url.trim_end_matches('/') == portal.trim_end_matches('/'))
+}
+
+/// Error for chunk embedding operations, which need PostgreSQL.
+fn chunks_unsupported() -> AppError {
+    AppError::ConfigError("Chunk embeddings are not supported by the in-memory store".to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn dataset(original_id: &str, title: &str, embedding: Vec<f32>, tags: &[&str]) -> NewDataset {
+        NewDataset {
+            original_id: original_id.to_string(),
+            source_portal: "https://dati.comune.milano.it".to_string(),
+            url: format!("https://dati.comune.milano.it/dataset/{}", original_id),
+            title: title.to_string(),
+            description: None,
+            embedding: Some(Vector::from(embedding)),
+            embedding_model: Some("test-model".to_string()),
+            metadata: json!({ "license_id": "cc-by" }),
+            formats: vec!["CSV".to_string()],
+            content_hash: NewDataset::compute_content_hash(title, None),
+            modified_at: None,
+            language: Some("it".to_string()),
+            bbox: None,
+            quality: Some(0.8),
+            tags: tags.iter().map(|t| t.to_string()).collect(),
+            organization: None,
+            resources: Vec::new(),
+        }
+    }
+
+    #[tokio::test]
+    async fn test_upsert_batch() {
+        let store = MemoryStore::new();
+        let air = store
+            .upsert(&dataset("aria", "Qualità dell'aria", vec![1.0, 0.0], &[]))
+            .await
+            .unwrap();
+        let outcomes = store
+            .upsert_batch(&[
+                dataset("bus", "Fermate autobus", vec![0.0, 1.0], &[]),
+                dataset("aria", "Qualità dell'aria PM10", vec![1.0, 0.0], &[]),
+            ])
+            .await
+            .unwrap();
+        assert_eq!(outcomes.len(), 2);
+        assert!(matches!(outcomes[0], UpsertOutcome::Created(id) if id != air.id()));
+        assert_eq!(outcomes[1], UpsertOutcome::Updated(air.id()));
+        assert_eq!(store.get_stats(None).await.unwrap().total_datasets, 2);
+    }
+
+    #[tokio::test]
+    async fn test_upsert_search_and_stats() {
+        let store = MemoryStore::new();
+        let air = store
+            .upsert(&dataset(
+                "aria",
+                "Qualità dell'aria",
+                vec![1.0, 0.0],
+                &["ambiente"],
+            ))
+            .await
+            .unwrap();
+        store
+            .upsert(&dataset(
+                "bus",
+                "Fermate autobus",
+                vec![0.0, 1.0],
+                &["trasporti"],
+            ))
+            .await
+            .unwrap();
+
+        // Re-harvesting without an embedding keeps the stored one
+        let mut unchanged = dataset("aria", "Qualità dell'aria PM10", vec![], &["ambiente"]);
+        unchanged.embedding = None;
+        assert_eq!(
+            store.upsert(&unchanged).await.unwrap(),
+            UpsertOutcome::Updated(air.id())
+        );
+        assert_eq!(store.embedding_dimension().await.unwrap(), Some(2));
+
+        let filters = SearchFilters::default();
+        let results = store
+            .search_with_filters(
+                Vector::from(vec![0.9, 0.1]),
+                "",
+                10,
+                &filters,
+                SearchStrategy::Auto,
+            )
+            .await
+            .unwrap();
+        assert_eq!(results[0].dataset.title, "Qualità dell'aria PM10");
+
+        let tagged = SearchFilters {
+            tag: Some("trasporti".to_string()),
+            license: Some("cc-by".to_string()),
+            ..Default::default()
+        };
+        let results = store.text_search("fermate", 10, &tagged).await.unwrap();
+        assert_eq!(results.len(), 1);
+        assert!(store
+            .text_search("aria", 10, &tagged)
+            .await
+            .unwrap()
+            .is_empty());
+
+        let hashes = store
+            .get_hashes_for_portal("https://dati.comune.milano.it", "other-model")
+            .await
+            .unwrap();
+        assert_eq!(hashes.len(), 2);
+        assert!(hashes.values().all(Option::is_none));
+
+        let stats = store.get_stats(None).await.unwrap();
+        assert_eq!(stats.total_datasets, 2);
+        assert_eq!(stats.datasets_with_embeddings, 2);
+        assert_eq!(stats.embedding_models[0].portals, 1);
+        assert_eq!(stats.quality[0].datasets, 2);
+    }
+}
@@ -0,0 +1,157 @@
+//! Embedded schema migrations.
+//!
+//! The SQL files in the repository's `migrations` directory are compiled
+//! into the binary and applied by `ceres migrate`, which records them in
+//! sqlx's `_sqlx_migrations` table. Databases set up with the former
+//! `make migrate` script recorded applied files in `schema_migrations`;
+//! those are adopted on the first run instead of being applied again.
+
+use std::collections::HashSet;
+
+use ceres_core::error::AppError;
+use ceres_core::models::SchemaMigration;
+use sqlx::migrate::{Migrate, MigrateError, Migration, Migrator};
+
+use crate::DatasetRepository;
+
+/// Migrations shipped with this build.
+pub static MIGRATOR: Migrator = sqlx::migrate!("../../migrations");
+
+/// File name a migration had in the `migrations` directory.
+fn file_name(migration: &Migration) -> String {
+    format!(
+        "{}_{}.sql",
+        migration.version,
+        migration.description.replace(' ', "_")
+    )
+}
+
+fn migrate_error(e: MigrateError) -> AppError {
+    match e {
+        MigrateError::Execute(e) => AppError::DatabaseError(e),
+        other => AppError::MigrationError(other.to_string()),
+    }
+}
+
+impl DatasetRepository {
+    /// Returns the shipped migrations not applied to the database yet,
+    /// oldest first, without changing anything.
+    pub async fn pending_migrations(&self) -> Result<Vec<SchemaMigration>, AppError> {
+        let applied = self.applied_migrations().await?;
+        Ok(MIGRATOR
+            .iter()
+            .filter(|m| !m.migration_type.is_down_migration())
+            .filter(|m| !applied.contains(&m.version))
+            .map(|m| SchemaMigration {
+                version: m.version,
+                description: m.description.to_string(),
+            })
+            .collect())
+    }
+
+    /// Applies pending migrations, each in its own transaction, and returns
+    /// them.
+    ///
+    /// Files recorded by the former `make migrate` script are marked as
+    /// applied first.
+    pub async fn run_migrations(&self) -> Result<Vec<SchemaMigration>, AppError> {
+        self.adopt_legacy_migrations().await?;
+        let pending = self.pending_migrations().await?;
+        MIGRATOR.run(&self.pool).await.map_err(migrate_error)?;
+        Ok(pending)
+    }
+
+    /// Versions applied according to `_sqlx_migrations` or the legacy
+    /// `schema_migrations` table.
+    async fn applied_migrations(&self) -> Result<HashSet<i64>, AppError> {
+        let mut applied = HashSet::new();
+
+        if self.table_exists("_sqlx_migrations").await? {
+            let versions: Vec<i64> =
+                sqlx::query_scalar("SELECT version FROM _sqlx_migrations WHERE success")
+                    .fetch_all(&self.pool)
+                    .await
+                    .map_err(AppError::DatabaseError)?;
+            applied.extend(versions);
+        }
+
+        let legacy = self.legacy_migration_files().await?;
+        applied.extend(
+            MIGRATOR
+                .iter()
+                .filter(|m| legacy.contains(&file_name(m)))
+                .map(|m| m.version),
+        );
+        Ok(applied)
+    }
+
+    /// Records migrations applied by the former `make migrate` script in
+    /// `_sqlx_migrations`, so they are not applied again.
+    async fn adopt_legacy_migrations(&self) -> Result<(), AppError> {
+        let legacy = self.legacy_migration_files().await?;
+        if legacy.is_empty() {
+            return Ok(());
+        }
+
+        let mut conn = self.pool.acquire().await.map_err(AppError::DatabaseError)?;
+        conn.ensure_migrations_table()
+            .await
+            .map_err(migrate_error)?;
+        for migration in MIGRATOR.iter().filter(|m| legacy.contains(&file_name(m))) {
+            sqlx::query(
+                r#"
+                INSERT INTO _sqlx_migrations (version, description, success, checksum, execution_time)
+                VALUES ($1, $2, TRUE, $3, 0)
+                ON CONFLICT (version) DO NOTHING
+                "#,
+            )
+            .bind(migration.version)
+            .bind(&*migration.description)
+            .bind(&*migration.checksum)
+            .execute(&mut *conn)
+            .await
+            .map_err(AppError::DatabaseError)?;
+        }
+        Ok(())
+    }
+
+    /// File names recorded by the former `make migrate` script.
+    async fn legacy_migration_files(&self) -> Result<HashSet<String>, AppError> {
+        if !self.table_exists("schema_migrations").await? {
+            return Ok(HashSet::new());
+        }
+        let files: Vec<String> = sqlx::query_scalar("SELECT filename FROM schema_migrations")
+            .fetch_all(&self.pool)
+            .await
+            .map_err(AppError::DatabaseError)?;
+        Ok(files.into_iter().collect())
+    }
+
+    async fn table_exists(&self, table: &str) -> Result<bool, AppError> {
+        sqlx::query_scalar("SELECT to_regclass($1) IS NOT NULL")
+            .bind(table)
+            .fetch_one(&self.pool)
+            .await
+            .map_err(AppError::DatabaseError)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_embedded_migrations_match_files() {
+        let files: Vec<String> = MIGRATOR.iter().map(file_name).collect();
+        assert_eq!(
+            files.first().map(String::as_str),
+            Some("202511290001_init.sql")
+        );
+        for file in &files {
+            let path = std::path::Path::new(env!("CARGO_MANIFEST_DIR"))
+                .join("../../migrations")
+                .join(file);
+            assert!(path.exists(), "{} not found", path.display());
+        }
+    }
+}
@@ -0,0 +1,104 @@
+//! Publishing organizations and their datasets.
+
+use std::collections::HashMap;
+
+use ceres_core::error::AppError;
+use ceres_core::organizations::{DatasetOrganization, OrganizationSummary};
+use chrono::{DateTime, Utc};
+use sqlx::PgConnection;
+
+use crate::DatasetRepository;
+
+/// Inserts or updates organizations, each given with its portal, in one
+/// query. Returns their IDs by `(source_portal, name)`.
+pub(crate) async fn upsert_organizations(
+    conn: &mut PgConnection,
+    organizations: &[(&str, &DatasetOrganization)],
+) -> Result<HashMap<(String, String), i32>, AppError> {
+    // ON CONFLICT cannot update a row twice in one statement; the last copy wins
+    let mut unique: HashMap<(&str, &str), &DatasetOrganization> = HashMap::new();
+    for (portal, organization) in organizations {
+        unique.insert((portal, organization.name.as_str()), organization);
+    }
+    if unique.is_empty() {
+        return Ok(HashMap::new());
+    }
+    let (keys, organizations): (Vec<_>, Vec<_>) = unique.into_iter().unzip();
+    let (portals, names): (Vec<&str>, Vec<&str>) = keys.into_iter().unzip();
+    let titles: Vec<Option<&str>> = organizations.iter().map(|o| o.title.as_deref()).collect();
+    let ckan_ids: Vec<Option<&str>> = organizations.iter().map(|o| o.ckan_id.as_deref()).collect();
+
+    let rows: Vec<(i32, String, String)> = sqlx::query_as(
+        r#"
+        INSERT INTO organizations (source_portal, name, title, ckan_id)
+        SELECT * FROM unnest($1::text[], $2::text[], $3::text[], $4::text[])
+        ON CONFLICT (source_portal, name)
+        DO UPDATE SET
+            title = COALESCE(EXCLUDED.title, organizations.title),
+            ckan_id = COALESCE(EXCLUDED.ckan_id, organizations.ckan_id)
+        RETURNING id, source_portal, name
+        "#,
+    )
+    .bind(&portals)
+    .bind(&names)
+    .bind(&titles)
+    .bind(&ckan_ids)
+    .fetch_all(conn)
+    .await
+    .map_err(AppError::DatabaseError)?;
+
+    Ok(rows
+        .into_iter()
+        .map(|(id, portal, name)| ((portal, name), id))
+        .collect())
+}
+
+/// Helper struct for deserializing organization summary rows
+#[derive(sqlx::FromRow)]
+struct OrganizationRow {
+    source_portal: String,
+    name: String,
+    title: Option<String>,
+    datasets: i64,
+    last_modified: Option<DateTime<Utc>>,
+}
+
+impl DatasetRepository {
+    /// Returns organizations with their dataset counts, most datasets
+    /// first, optionally from a single portal.
+    pub async fn list_organizations(
+        &self,
+        portal: Option<&str>,
+        limit: usize,
+    ) -> Result<Vec<OrganizationSummary>, AppError> {
+        let rows: Vec<OrganizationRow> = sqlx::query_as(
+            r#"
+            SELECT o.source_portal, o.name, o.title,
+                   COUNT(d.id) AS datasets,
+                   MAX(d.modified_at) AS last_modified
+            FROM organizations o
+            LEFT JOIN datasets d ON d.organization_id = o.id
+            WHERE $1::text IS NULL OR o.source_portal = $1
+            GROUP BY o.id
+            ORDER BY datasets DESC, o.source_portal, o.name
+            LIMIT $2
+            "#,
+        )
+        .bind(portal)
+        .bind(limit as i64)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(AppError::DatabaseError)?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| OrganizationSummary {
+                source_portal: row.source_portal,
+                name: row.name,
+                title: row.title,
+                datasets: row.datasets,
+                last_modified: row.last_modified,
+            })
+            .collect())
+    }
+}
@@ -0,0 +1,194 @@
+//! Portal-level maintenance: moving a portal to a new base URL, and deleting
+//! a portal's data.
+
+use ceres_core::error::AppError;
+use ceres_core::models::PortalMigration;
+
+use crate::DatasetRepository;
+
+impl DatasetRepository {
+    /// Counts the datasets harvested from `portal_url`, ignoring trailing slashes.
+    pub async fn count_portal_datasets(&self, portal_url: &str) -> Result<i64, AppError> {
+        sqlx::query_scalar("SELECT COUNT(*) FROM datasets WHERE rtrim(source_portal, '/') = $1")
+            .bind(portal_url.trim_end_matches('/'))
+            .fetch_one(&self.pool)
+            .await
+            .map_err(AppError::DatabaseError)
+    }
+
+    /// Deletes every dataset harvested from `portal_url` in one transaction,
+    /// ignoring trailing slashes. Returns the number deleted.
+    ///
+    /// Resources, tags, chunk and entity rows go with their datasets, and
+    /// the portal's organizations are deleted too. Its health record and
+    /// watches are kept, so harvesting the portal again starts from its
+    /// history.
+    pub async fn delete_portal_datasets(&self, portal_url: &str) -> Result<u64, AppError> {
+        let portal_url = portal_url.trim_end_matches('/');
+        let mut tx = self.pool.begin().await.map_err(AppError::DatabaseError)?;
+
+        let deleted = sqlx::query("DELETE FROM datasets WHERE rtrim(source_portal, '/') = $1")
+            .bind(portal_url)
+            .execute(&mut *tx)
+            .await
+            .map_err(AppError::DatabaseError)?
+            .rows_affected();
+        sqlx::query("DELETE FROM organizations WHERE rtrim(source_portal, '/') = $1")
+            .bind(portal_url)
+            .execute(&mut *tx)
+            .await
+            .map_err(AppError::DatabaseError)?;
+
+        tx.commit().await.map_err(AppError::DatabaseError)?;
+        Ok(deleted)
+    }
+
+    /// Moves everything keyed by portal URL from `from` to `to` in one transaction.
+    ///
+    /// Datasets keep their IDs, embeddings and `first_seen_at`; their
+    /// `source_portal` is rewritten, and landing page URLs starting with
+    /// `from` are rewritten to start with `to`. Watches restricted to the
+    /// portal, its organizations and its health record follow. Trailing
+    /// slashes are ignored.
+    ///
+    /// # Errors
+    ///
+    /// Returns `AppError::Generic` if datasets with the same original IDs
+    /// already exist under `to` (e.g. after harvesting the new domain),
+    /// since moving them would violate the `(source_portal, original_id)`
+    /// uniqueness. Nothing is changed in that case.
+    pub async fn migrate_portal(&self, from: &str, to: &str) -> Result<PortalMigration, AppError> {
+        let from = from.trim_end_matches('/');
+        let to = to.trim_end_matches('/');
+        let mut tx = self.pool.begin().await.map_err(AppError::DatabaseError)?;
+
+        let conflicts: i64 = sqlx::query_scalar(
+            r#"
+            SELECT COUNT(*)
+            FROM datasets old
+            JOIN datasets new ON new.original_id = old.original_id
+            WHERE rtrim(old.source_portal, '/') = $1
+              AND rtrim(new.source_portal, '/') = $2
+            "#,
+        )
+        .bind(from)
+        .bind(to)
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(AppError::DatabaseError)?;
+
+        if conflicts > 0 {
+            return Err(AppError::Generic(format!(
+                "{} datasets already exist under {}; delete them before migrating",
+                conflicts, to
+            )));
+        }
+
+        let dataset_urls = sqlx::query(
+            r#"
+            UPDATE datasets
+            SET url = $2 || substr(url, length($1) + 1)
+            WHERE rtrim(source_portal, '/') = $1
+              AND left(url, length($1)) = $1
+            "#,
+        )
+        .bind(from)
+        .bind(to)
+        .execute(&mut *tx)
+        .await
+        .map_err(AppError::DatabaseError)?
+        .rows_affected();
+
+        let datasets = sqlx::query(
+            "UPDATE datasets SET source_portal = $2 WHERE rtrim(source_portal, '/') = $1",
+        )
+        .bind(from)
+        .bind(to)
+        .execute(&mut *tx)
+        .await
+        .map_err(AppError::DatabaseError)?
+        .rows_affected();
+
+        let watches = sqlx::query("UPDATE watches SET portal = $2 WHERE rtrim(portal, '/') = $1")
+            .bind(from)
+            .bind(to)
+            .execute(&mut *tx)
+            .await
+            .map_err(AppError::DatabaseError)?
+            .rows_affected();
+
+        // Organizations already known under the new URL absorb their
+        // old counterparts' datasets
+        sqlx::query(
+            r#"
+            UPDATE datasets d
+            SET organization_id = new.id
+            FROM organizations old
+            JOIN organizations new ON new.name = old.name
+            WHERE d.organization_id = old.id
+              AND rtrim(old.source_portal, '/') = $1
+              AND rtrim(new.source_portal, '/') = $2
+            "#,
+        )
+        .bind(from)
+        .bind(to)
+        .execute(&mut *tx)
+        .await
+        .map_err(AppError::DatabaseError)?;
+        sqlx::query(
+            r#"
+            DELETE FROM organizations old
+            USING organizations new
+            WHERE new.name = old.name
+              AND rtrim(old.source_portal, '/') = $1
+              AND rtrim(new.source_portal, '/') = $2
+            "#,
+        )
+        .bind(from)
+        .bind(to)
+        .execute(&mut *tx)
+        .await
+        .map_err(AppError::DatabaseError)?;
+        sqlx::query(
+            "UPDATE organizations SET source_portal = $2 WHERE rtrim(source_portal, '/') = $1",
+        )
+        .bind(from)
+        .bind(to)
+        .execute(&mut *tx)
+        .await
+        .map_err(AppError::DatabaseError)?;
+
+        // The old record carries the history; it replaces any record the new
+        // URL may have from a harvest attempt before the migration.
+        let old_health: Option<String> = sqlx::query_scalar(
+            "SELECT portal_url FROM portal_health WHERE rtrim(portal_url, '/') = $1 LIMIT 1",
+        )
+        .bind(from)
+        .fetch_optional(&mut *tx)
+        .await
+        .map_err(AppError::DatabaseError)?;
+
+        if let Some(old_url) = &old_health {
+            sqlx::query("DELETE FROM portal_health WHERE rtrim(portal_url, '/') = $1")
+                .bind(to)
+                .execute(&mut *tx)
+                .await
+                .map_err(AppError::DatabaseError)?;
+            sqlx::query("UPDATE portal_health SET portal_url = $2 WHERE portal_url = $1")
+                .bind(old_url)
+                .bind(to)
+                .execute(&mut *tx)
+                .await
+                .map_err(AppError::DatabaseError)?;
+        }
+
+        tx.commit().await.map_err(AppError::DatabaseError)?;
+
+        Ok(PortalMigration {
+            datasets,
+            dataset_urls,
+            watches,
+            health_moved: old_health.is_some(),
+        })
+    }
+}
@@ -0,0 +1,775 @@
+//! Qdrant vector index (feature `qdrant`).
+//!
+//! [`QdrantStore`] wraps another [`DatasetStore`], which stays the store of
+//! record for datasets, resources and harvest state, and mirrors every
+//! dataset embedding into a Qdrant collection. Points carry the payload
+//! fields searches filter on most (portal, embedding model, formats,
+//! language), so Qdrant applies those while ranking; the wrapped store then
+//! loads the hits and applies the remaining filters exactly.
+//!
+//! Embeddings are kept in the wrapped store as well, so change detection,
+//! `ceres stats` and the PostgreSQL-only commands work unchanged, and a new
+//! collection can be filled from them with [`QdrantStore::backfill`].
+//! Keyword search and chunk embeddings are served by the wrapped store.
+
+use std::collections::HashMap;
+
+use async_trait::async_trait;
+use ceres_core::chunks::DatasetChunk;
+use ceres_core::error::AppError;
+use ceres_core::health::PortalHealth;
+use ceres_core::history::SearchRecord;
+use ceres_core::models::{DatabaseStats, Dataset, NewDataset, SchemaMigration, SearchResult};
+use ceres_core::search::{SearchFilters, SearchStrategy, HYBRID_CANDIDATES_PER_RESULT};
+use ceres_core::sync::{HarvestCheckpoint, HarvestFailure, ReprocessingDecision, UpsertOutcome};
+use ceres_core::watch::Watch;
+use ceres_core::HttpConfig;
+use chrono::{DateTime, Utc};
+use futures::stream::{BoxStream, TryStreamExt};
+use pgvector::Vector;
+use reqwest::{Client, Method, RequestBuilder, Response, StatusCode};
+use serde::{Deserialize, Serialize};
+use serde_json::{json, Value};
+use uuid::Uuid;
+
+use crate::store::{fuse_results, DatasetStore};
+use crate::PortalLock;
+
+/// Collection used unless configured otherwise.
+pub const DEFAULT_QDRANT_COLLECTION: &str = "ceres";
+
+/// Payload fields indexed for filtering.
+const INDEXED_FIELDS: [&str; 4] = ["source_portal", "embedding_model", "formats", "language"];
+
+/// Points per upsert request during backfill.
+const UPSERT_BATCH_SIZE: usize = 256;
+
+/// Candidates requested per wanted result when filters apply after ranking.
+const POST_FILTER_CANDIDATES_PER_RESULT: usize = 4;
+
+/// Most candidates requested from Qdrant for one search.
+const MAX_CANDIDATES: usize = 10_000;
+
+/// Dataset store whose embeddings are indexed and searched in Qdrant.
+///
+/// # Examples
+///
+/// ```no_run
+/// use ceres_core::HttpConfig;
+/// use ceres_db::{DatasetRepository, QdrantStore};
+/// # use sqlx::postgres::PgPoolOptions;
+///
+/// # async fn example() -> Result<(), Box<dyn std::error::Error>> {
+/// # let pool = PgPoolOptions::new().connect("postgresql://localhost/ceres").await?;
+/// let store = QdrantStore::new(
+///     DatasetRepository::new(pool),
+///     "http://localhost:6333",
+///     &HttpConfig::default(),
+/// )?
+/// .with_collection("ceres");
+/// if store.ensure_collection(768).await? {
+///     store.backfill().await?;
+/// }
+/// # Ok(())
+/// # }
+/// ```
+pub struct QdrantStore<S> {
+    inner: S,
+    client: Client,
+    http_config: HttpConfig,
+    base_url: String,
+    api_key: Option<String>,
+    collection: String,
+}
+
+/// A point of the collection: one dataset embedding.
+#[derive(Serialize)]
+struct Point<'a> {
+    id: Uuid,
+    vector: &'a [f32],
+    payload: Payload<'a>,
+}
+
+/// Filterable dataset fields stored with each point.
+#[derive(Serialize)]
+struct Payload<'a> {
+    source_portal: &'a str,
+    embedding_model: &'a str,
+    formats: &'a [String],
+    language: Option<&'a str>,
+}
+
+/// Entry of a `points/search` response.
+#[derive(Deserialize)]
+struct ScoredPoint {
+    id: Uuid,
+    score: f32,
+}
+
+/// Qdrant response envelope.
+#[derive(Deserialize)]
+struct QdrantResponse<T> {
+    result: T,
+}
+
+impl<S: DatasetStore> QdrantStore<S> {
+    /// Wraps `inner`, indexing embeddings in the Qdrant server at
+    /// `base_url` (e.g. `http://localhost:6333`) with the timeout of
+    /// `http_config`.
+    ///
+    /// # Errors
+    ///
+    /// Returns `AppError::InvalidUrl` if `base_url` is not a valid URL.
+    pub fn new(inner: S, base_url: &str, http_config: &HttpConfig) -> Result<Self, AppError> {
+        reqwest::Url::parse(base_url).map_err(|e| AppError::InvalidUrl(e.to_string()))?;
+
+        let client = Client::builder()
+            .timeout(http_config.timeout)
+            .build()
+            .map_err(|e| AppError::ClientError(e.to_string()))?;
+
+        Ok(Self {
+            inner,
+            client,
+            http_config: http_config.clone(),
+            base_url: base_url.trim_end_matches('/').to_string(),
+            api_key: None,
+            collection: DEFAULT_QDRANT_COLLECTION.to_string(),
+        })
+    }
+
+    /// Returns a store sending `api_key` with every request.
+    pub fn with_api_key(mut self, api_key: &str) -> Self {
+        self.api_key = Some(api_key.to_string());
+        self
+    }
+
+    /// Returns a store using the collection `name`.
+    pub fn with_collection(mut self, name: &str) -> Self {
+        self.collection = name.to_string();
+        self
+    }
+
+    /// Returns the collection name.
+    pub fn collection(&self) -> &str {
+        &self.collection
+    }
+
+    /// Creates the collection for vectors of `dimension` floats, with
+    /// payload indexes, unless it exists. Returns true if it was created.
+    ///
+    /// # Errors
+    ///
+    /// Returns `AppError::ConfigError` if the existing collection holds
+    /// vectors of another dimension, and a network or client error if
+    /// Qdrant cannot be reached.
+    pub async fn ensure_collection(&self, dimension: usize) -> Result<bool, AppError> {
+        let path = format!("/collections/{}", self.collection);
+        let response = self.execute(self.request(Method::GET, &path)).await?;
+        if response.status() == StatusCode::NOT_FOUND {
+            let body = json!({ "vectors": { "size": dimension, "distance": "Cosine" } });
+            self.send::<Value>(self.request(Method::PUT, &path).json(&body))
+                .await?;
+            for field in INDEXED_FIELDS {
+                let body = json!({ "field_name": field, "field_schema": "keyword" });
+                self.send::<Value>(
+                    self.request(Method::PUT, &format!("{}/index?wait=true", path))
+                        .json(&body),
+                )
+                .await?;
+            }
+            return Ok(true);
+        }
+
+        let info: Value = parse_response(response).await?;
+        match info
+            .pointer("/config/params/vectors/size")
+            .and_then(Value::as_u64)
+        {
+            Some(size) if size as usize == dimension => Ok(false),
+            Some(size) => Err(AppError::ConfigError(format!(
+                "Qdrant collection '{}' stores {}-dimensional vectors, but embeddings have {} dimensions. Set QDRANT_COLLECTION to another collection",
+                self.collection, size, dimension
+            ))),
+            None => Err(AppError::ConfigError(format!(
+                "Qdrant collection '{}' does not have a single unnamed vector",
+                self.collection
+            ))),
+        }
+    }
+
+    /// Copies every embedding of the wrapped store into the collection.
+    /// Returns the number of points written.
+    pub async fn backfill(&self) -> Result<usize, AppError> {
+        let filters = SearchFilters::default();
+        let mut datasets = self.inner.stream_all(&filters, None);
+        let mut batch = Vec::with_capacity(UPSERT_BATCH_SIZE);
+        let mut written = 0;
+        while let Some(dataset) = datasets.try_next().await? {
+            if dataset.embedding.is_some() {
+                batch.push(dataset);
+            }
+            if batch.len() == UPSERT_BATCH_SIZE {
+                written += self.upsert_datasets(&batch).await?;
+                batch.clear();
+            }
+        }
+        written += self.upsert_datasets(&batch).await?;
+        Ok(written)
+    }
+
+    /// Writes the embeddings of `datasets`, skipping datasets without one.
+    /// Returns the number of points written.
+    async fn upsert_datasets(&self, datasets: &[Dataset]) -> Result<usize, AppError> {
+        let points: Vec<Point> = datasets
+            .iter()
+            .filter_map(|dataset| {
+                let embedding = dataset.embedding.as_ref()?;
+                Some(Point {
+                    id: dataset.id,
+                    vector: embedding.as_slice(),
+                    payload: Payload {
+                        source_portal: &dataset.source_portal,
+                        embedding_model: dataset.embedding_model.as_deref()?,
+                        formats: &dataset.formats,
+                        language: dataset.language.as_deref(),
+                    },
+                })
+            })
+            .collect();
+        if !points.is_empty() {
+            self.upsert_points(&points).await?;
+        }
+        Ok(points.len())
+    }
+
+    async fn upsert_points(&self, points: &[Point<'_>]) -> Result<(), AppError> {
+        let path = format!("/collections/{}/points?wait=true", self.collection);
+        self.send::<Value>(
+            self.request(Method::PUT, &path)
+                .json(&json!({ "points": points })),
+        )
+        .await?;
+        Ok(())
+    }
+
+    /// Nearest points to `vector` passing the payload filters of `filters`,
+    /// best first.
+    async fn search_points(
+        &self,
+        vector: &Vector,
+        limit: usize,
+        filters: &SearchFilters,
+    ) -> Result<Vec<ScoredPoint>, AppError> {
+        let path = format!("/collections/{}/points/search", self.collection);
+        let body = json!({
+            "vector": vector.as_slice(),
+            "limit": limit,
+            "filter": payload_filter(filters),
+            "with_payload": false,
+        });
+        self.send(self.request(Method::POST, &path).json(&body))
+            .await
+    }
+
+    fn request(&self, method: Method, path: &str) -> RequestBuilder {
+        let request = self
+            .client
+            .request(method, format!("{}{}", self.base_url, path));
+        match &self.api_key {
+            Some(api_key) => request.header("api-key", api_key),
+            None => request,
+        }
+    }
+
+    /// Sends a request and returns the `result` of the response.
+    async fn send<T: for<'de> Deserialize<'de>>(
+        &self,
+        request: RequestBuilder,
+    ) -> Result<T, AppError> {
+        parse_response(self.execute(request).await?).await
+    }
+
+    async fn execute(&self, request: RequestBuilder) -> Result<Response, AppError> {
+        request.send().await.map_err(|e| {
+            if e.is_timeout() {
+                AppError::Timeout(self.http_config.timeout.as_secs())
+            } else if e.is_connect() {
+                AppError::NetworkError(format!(
+                    "Connection to Qdrant at {} failed: {}",
+                    self.base_url, e
+                ))
+            } else {
+                AppError::ClientError(e.to_string())
+            }
+        })
+    }
+}
+
+/// Returns the `result` of a successful response.
+async fn parse_response<T: for<'de> Deserialize<'de>>(response: Response) -> Result<T, AppError> {
+    let status = response.status();
+    if !status.is_success() {
+        let body = response.text().await.unwrap_or_default();
+        return Err(classify_qdrant_error(status, &error_message(body)));
+    }
+
+    let body: QdrantResponse<T> = response
+        .json()
+        .await
+        .map_err(|e| AppError::ClientError(format!("Failed to parse Qdrant response: {}", e)))?;
+    Ok(body.result)
+}
+
+/// Maps Qdrant's HTTP errors to `AppError`.
+fn classify_qdrant_error(status: StatusCode, message: &str) -> AppError {
+    match status.as_u16() {
+        401 | 403 => AppError::ConfigError(format!(
+            "Qdrant rejected the API key ({}). Check QDRANT_API_KEY",
+            message
+        )),
+        code => AppError::ClientError(format!("Qdrant HTTP {}: {}", code, message)),
+    }
+}
+
+/// Extracts the message from a Qdrant error body, falling back to the raw body.
+fn error_message(body: String) -> String {
+    serde_json::from_str::<Value>(&body)
+        .ok()
+        .and_then(|v| v["status"]["error"].as_str().map(str::to_string))
+        .unwrap_or(body)
+}
+
+/// Qdrant filter for the filters stored in the point payload.
+fn payload_filter(filters: &SearchFilters) -> Value {
+    let must: Vec<Value> = [
+        ("source_portal", &filters.portal),
+        ("embedding_model", &filters.embedding_model),
+        ("formats", &filters.format),
+        ("language", &filters.language),
+    ]
+    .into_iter()
+    .filter_map(|(key, value)| {
+        value
+            .as_ref()
+            .map(|value| json!({ "key": key, "match": { "value": value } }))
+    })
+    .collect();
+    json!({ "must": must })
+}
+
+/// Returns true if some filters are not in the payload, so hits must be
+/// filtered again after ranking.
+fn needs_post_filter(filters: &SearchFilters) -> bool {
+    filters.theme.is_some()
+        || filters.license.is_some()
+        || filters.updated_after.is_some()
+        || filters.updated_before.is_some()
+        || filters.tag.is_some()
+        || filters.organization.is_some()
+        || !filters.metadata.is_empty()
+        || filters.bbox.is_some()
+}
+
+#[async_trait]
+impl<S: DatasetStore> DatasetStore for QdrantStore<S> {
+    async fn embedding_dimension(&self) -> Result<Option<usize>, AppError> {
+        self.inner.embedding_dimension().await
+    }
+
+    async fn pending_migrations(&self) -> Result<Vec<SchemaMigration>, AppError> {
+        self.inner.pending_migrations().await
+    }
+
+    async fn run_migrations(&self) -> Result<Vec<SchemaMigration>, AppError> {
+        self.inner.run_migrations().await
+    }
+
+    async fn get_hashes_for_portal(
+        &self,
+        portal_url: &str,
+        embedding_model: &str,
+    ) -> Result<HashMap<String, Option<String>>, AppError> {
+        self.inner
+            .get_hashes_for_portal(portal_url, embedding_model)
+            .await
+    }
+
+    async fn datasets_needing_processing(
+        &self,
+        portal_url: &str,
+        embedding_model: &str,
+        candidates: &[(String, String)],
+    ) -> Result<HashMap<String, ReprocessingDecision>, AppError> {
+        self.inner
+            .datasets_needing_processing(portal_url, embedding_model, candidates)
+            .await
+    }
+
+    async fn update_timestamp_only(
+        &self,
+        portal_url: &str,
+        original_id: &str,
+    ) -> Result<bool, AppError> {
+        self.inner
+            .update_timestamp_only(portal_url, original_id)
+            .await
+    }
+
+    async fn upsert(&self, new_data: &NewDataset) -> Result<UpsertOutcome, AppError> {
+        let outcomes = self.upsert_batch(std::slice::from_ref(new_data)).await?;
+        Ok(outcomes[0])
+    }
+
+    async fn upsert_batch(&self, datasets: &[NewDataset]) -> Result<Vec<UpsertOutcome>, AppError> {
+        let outcomes = self.inner.upsert_batch(datasets).await?;
+        let points: Vec<Point> = outcomes
+            .iter()
+            .zip(datasets)
+            .filter_map(|(outcome, new_data)| {
+                Some(Point {
+                    id: outcome.id(),
+                    vector: new_data.embedding.as_ref()?.as_slice(),
+                    payload: Payload {
+                        source_portal: &new_data.source_portal,
+                        embedding_model: new_data.embedding_model.as_deref()?,
+                        formats: &new_data.formats,
+                        language: new_data.language.as_deref(),
+                    },
+                })
+            })
+            .collect();
+        if !points.is_empty() {
+            self.upsert_points(&points).await?;
+        }
+        Ok(outcomes)
+    }
+
+    async fn cached_embedding(
+        &self,
+        content_hash: &str,
+        model: &str,
+    ) -> Result<Option<Vector>, AppError> {
+        self.inner.cached_embedding(content_hash, model).await
+    }
+
+    async fn cache_embedding(
+        &self,
+        content_hash: &str,
+        model: &str,
+        embedding: &Vector,
+    ) -> Result<(), AppError> {
+        self.inner
+            .cache_embedding(content_hash, model, embedding)
+            .await
+    }
+
+    async fn get_chunk_hashes_for_portal(
+        &self,
+        portal_url: &str,
+        embedding_model: &str,
+    ) -> Result<HashMap<String, (Uuid, Option<String>)>, AppError> {
+        self.inner
+            .get_chunk_hashes_for_portal(portal_url, embedding_model)
+            .await
+    }
+
+    async fn replace_dataset_chunks(
+        &self,
+        dataset_id: Uuid,
+        chunks: &[(DatasetChunk, Vector)],
+        embedding_model: &str,
+        chunks_hash: Option<&str>,
+    ) -> Result<(), AppError> {
+        self.inner
+            .replace_dataset_chunks(dataset_id, chunks, embedding_model, chunks_hash)
+            .await
+    }
+
+    async fn list_portal_health(&self) -> Result<Vec<PortalHealth>, AppError> {
+        self.inner.list_portal_health().await
+    }
+
+    async fn get_portal_health(&self, portal_url: &str) -> Result<PortalHealth, AppError> {
+        self.inner.get_portal_health(portal_url).await
+    }
+
+    async fn save_portal_health(&self, health: &PortalHealth) -> Result<(), AppError> {
+        self.inner.save_portal_health(health).await
+    }
+
+    /// Ranks with Qdrant, widening the candidate set while filters applied
+    /// afterwards leave fewer than `limit` results. Qdrant plans filtered
+    /// searches itself, so `strategy` is ignored.
+    async fn search_with_filters(
+        &self,
+        query_vector: Vector,
+        _query_text: &str,
+        limit: usize,
+        filters: &SearchFilters,
+        _strategy: SearchStrategy,
+    ) -> Result<Vec<SearchResult>, AppError> {
+        if limit == 0 {
+            return Ok(Vec::new());
+        }
+
+        let mut candidates = if needs_post_filter(filters) {
+            (limit * POST_FILTER_CANDIDATES_PER_RESULT).min(MAX_CANDIDATES)
+        } else {
+            limit
+        };
+        loop {
+            let hits = self
+                .search_points(&query_vector, candidates, filters)
+                .await?;
+            let exhausted = hits.len() < candidates || candidates >= MAX_CANDIDATES;
+
+            // Hits of datasets deleted from the wrapped store are dropped here
+            let ids: Vec<Uuid> = hits.iter().map(|hit| hit.id).collect();
+            let mut datasets: HashMap<Uuid, Dataset> = self
+                .inner
+                .datasets_by_ids(&ids, filters)
+                .await?
+                .into_iter()
+                .map(|dataset| (dataset.id, dataset))
+                .collect();
+            let results: Vec<SearchResult> = hits
+                .into_iter()
+                .filter_map(|hit| {
+                    datasets.remove(&hit.id).map(|dataset| SearchResult {
+                        dataset,
+                        similarity_score: hit.score,
+                    })
+                })
+                .take(limit)
+                .collect();
+
+            if results.len() == limit || exhausted {
+                return Ok(results);
+            }
+            candidates = (candidates * POST_FILTER_CANDIDATES_PER_RESULT).min(MAX_CANDIDATES);
+        }
+    }
+
+    async fn text_search(
+        &self,
+        query_text: &str,
+        limit: usize,
+        filters: &SearchFilters,
+    ) -> Result<Vec<SearchResult>, AppError> {
+        self.inner.text_search(query_text, limit, filters).await
+    }
+
+    async fn hybrid_search(
+        &self,
+        query_vector: Vector,
+        query_text: &str,
+        limit: usize,
+        filters: &SearchFilters,
+        strategy: SearchStrategy,
+    ) -> Result<Vec<SearchResult>, AppError> {
+        let candidates = limit * HYBRID_CANDIDATES_PER_RESULT;
+        let semantic = self
+            .search_with_filters(query_vector, query_text, candidates, filters, strategy)
+            .await?;
+        let lexical = self
+            .inner
+            .text_search(query_text, candidates, filters)
+            .await?;
+        Ok(fuse_results(semantic, lexical, limit))
+    }
+
+    async fn search_with_chunks(
+        &self,
+        query_vector: Vector,
+        query_text: &str,
+        limit: usize,
+        filters: &SearchFilters,
+        strategy: SearchStrategy,
+    ) -> Result<Vec<SearchResult>, AppError> {
+        self.inner
+            .search_with_chunks(query_vector, query_text, limit, filters, strategy)
+            .await
+    }
+
+    fn stream_all<'a>(
+        &'a self,
+        filters: &'a SearchFilters,
+        limit: Option<usize>,
+    ) -> BoxStream<'a, Result<Dataset, AppError>> {
+        self.inner.stream_all(filters, limit)
+    }
+
+    async fn datasets_by_ids(
+        &self,
+        ids: &[Uuid],
+        filters: &SearchFilters,
+    ) -> Result<Vec<Dataset>, AppError> {
+        self.inner.datasets_by_ids(ids, filters).await
+    }
+
+    async fn get_stats(&self, portal: Option<&str>) -> Result<DatabaseStats, AppError> {
+        self.inner.get_stats(portal).await
+    }
+
+    async fn count_portal_datasets(&self, portal_url: &str) -> Result<i64, AppError> {
+        self.inner.count_portal_datasets(portal_url).await
+    }
+
+    /// Deletes the datasets from the wrapped store, then their points. Points
+    /// left behind by a failure are harmless: hits are loaded from the
+    /// wrapped store, which no longer has them.
+    async fn delete_portal_datasets(&self, portal_url: &str) -> Result<u64, AppError> {
+        let deleted = self.inner.delete_portal_datasets(portal_url).await?;
+        let portal_url = portal_url.trim_end_matches('/');
+        let path = format!("/collections/{}/points/delete?wait=true", self.collection);
+        let body = json!({
+            "filter": {
+                "must": [{
+                    "key": "source_portal",
+                    "match": { "any": [portal_url, format!("{}/", portal_url)] },
+                }],
+            },
+        });
+        self.send::<Value>(self.request(Method::POST, &path).json(&body))
+            .await?;
+        Ok(deleted)
+    }
+
+    async fn count_unseen_datasets(
+        &self,
+        before: DateTime<Utc>,
+        portal_url: Option<&str>,
+    ) -> Result<i64, AppError> {
+        self.inner.count_unseen_datasets(before, portal_url).await
+    }
+
+    async fn prune_unseen_datasets(
+        &self,
+        before: DateTime<Utc>,
+        portal_url: Option<&str>,
+    ) -> Result<Vec<Uuid>, AppError> {
+        let pruned = self.inner.prune_unseen_datasets(before, portal_url).await?;
+        let path = format!("/collections/{}/points/delete?wait=true", self.collection);
+        for ids in pruned.chunks(UPSERT_BATCH_SIZE) {
+            self.send::<Value>(
+                self.request(Method::POST, &path)
+                    .json(&json!({ "points": ids })),
+            )
+            .await?;
+        }
+        Ok(pruned)
+    }
+
+    async fn record_harvest_failures(&self, failures: &[HarvestFailure]) -> Result<(), AppError> {
+        self.inner.record_harvest_failures(failures).await
+    }
+
+    async fn list_harvest_failures(
+        &self,
+        portal_url: Option<&str>,
+    ) -> Result<Vec<HarvestFailure>, AppError> {
+        self.inner.list_harvest_failures(portal_url).await
+    }
+
+    async fn resolve_harvest_failures(
+        &self,
+        portal_url: Option<&str>,
+        before: DateTime<Utc>,
+    ) -> Result<u64, AppError> {
+        self.inner
+            .resolve_harvest_failures(portal_url, before)
+            .await
+    }
+
+    async fn get_harvest_checkpoint(
+        &self,
+        portal_url: &str,
+    ) -> Result<Option<HarvestCheckpoint>, AppError> {
+        self.inner.get_harvest_checkpoint(portal_url).await
+    }
+
+    async fn save_harvest_checkpoint(
+        &self,
+        checkpoint: &HarvestCheckpoint,
+    ) -> Result<(), AppError> {
+        self.inner.save_harvest_checkpoint(checkpoint).await
+    }
+
+    async fn clear_harvest_checkpoint(&self, portal_url: &str) -> Result<(), AppError> {
+        self.inner.clear_harvest_checkpoint(portal_url).await
+    }
+
+    async fn create_watch(&self, watch: &Watch, embedding: Vector) -> Result<(), AppError> {
+        self.inner.create_watch(watch, embedding).await
+    }
+
+    async fn list_watches(&self) -> Result<Vec<Watch>, AppError> {
+        self.inner.list_watches().await
+    }
+
+    async fn delete_watch_by_id(&self, id: Uuid) -> Result<bool, AppError> {
+        self.inner.delete_watch_by_id(id).await
+    }
+
+    async fn record_search(&self, search: &SearchRecord) -> Result<(), AppError> {
+        self.inner.record_search(search).await
+    }
+
+    async fn list_searches(&self, limit: usize) -> Result<Vec<SearchRecord>, AppError> {
+        self.inner.list_searches(limit).await
+    }
+
+    async fn clear_search_history(&self) -> Result<u64, AppError> {
+        self.inner.clear_search_history().await
+    }
+
+    async fn try_lock_portal(&self, portal_url: &str) -> Result<Option<PortalLock>, AppError> {
+        self.inner.try_lock_portal(portal_url).await
+    }
+
+    async fn lock_portal(&self, portal_url: &str) -> Result<PortalLock, AppError> {
+        self.inner.lock_portal(portal_url).await
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_payload_filter() {
+        let filters = SearchFilters {
+            portal: Some("https://dati.comune.milano.it".to_string()),
+            format: Some("CSV".to_string()),
+            tag: Some("trasporti".to_string()),
+            ..Default::default()
+        };
+        assert_eq!(
+            payload_filter(&filters),
+            json!({ "must": [
+                { "key": "source_portal", "match": { "value": "https://dati.comune.milano.it" } },
+                { "key": "formats", "match": { "value": "CSV" } },
+            ]})
+        );
+        assert!(needs_post_filter(&filters));
+        assert!(!needs_post_filter(&SearchFilters {
+            tag: None,
+            ..filters
+        }));
+    }
+
+    #[test]
+    fn test_error_message() {
+        let body =
+            r#"{"status":{"error":"Not found: Collection `ceres` doesn't exist!"},"time":0.0}"#;
+        assert_eq!(
+            error_message(body.to_string()),
+            "Not found: Collection `ceres` doesn't exist!"
+        );
+        assert!(matches!(
+            classify_qdrant_error(StatusCode::FORBIDDEN, "invalid api-key"),
+            AppError::ConfigError(_)
+        ));
+    }
+}
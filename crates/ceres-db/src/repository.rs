@@ -9,23 +9,70 @@
 //! - `get_hashes_for_portal()` - delta detection queries
 //! - `update_timestamp_only()` - timestamp-only updates
 //!
+//! Tests needing a database run against a migrated PostgreSQL with pgvector
+//! at `CERES_TEST_DATABASE_URL` and are skipped without it.
+//!
 //! Consider using testcontainers-rs for isolated PostgreSQL instances:
 //! <https://github.com/testcontainers/testcontainers-rs>
 //!
 //! See: <https://github.com/AndreaBozzo/Ceres/issues/12>
 
-use ceres_core::error::AppError;
-use ceres_core::models::{DatabaseStats, Dataset, NewDataset, SearchResult};
+use ceres_core::audit::LocalRecord;
+use ceres_core::config::DbConfig;
+use ceres_core::content_hash::content_hash;
+use ceres_core::content_hash::HashField;
+use ceres_core::error::{is_connection_error, AppError};
+use ceres_core::language::detect_language;
+use ceres_core::models::{
+    DatabaseStats, Dataset, EmbeddingModelCount, FormatCount, LanguageCount, NewDataset,
+    PortalQuality, PortalStats, SearchResult,
+};
+use ceres_core::quality::{quality_score, LOW_QUALITY};
+use ceres_core::resources::DatasetResource;
+use ceres_core::search::{
+    keyword_tsquery, needs_keyword_estimate, plan_search, CandidateEstimate, CandidateSet,
+    SearchFilters, SearchPlan, SearchStrategy, TWO_STAGE_MAX_CANDIDATES,
+};
+use ceres_core::spatial::{dataset_bbox, BoundingBox};
+use ceres_core::sync::{ReprocessingDecision, UpsertOutcome};
 use chrono::{DateTime, Utc};
+use futures::stream::{self, Stream, TryStreamExt};
 use pgvector::Vector;
+use sqlx::postgres::{PgConnection, PgPoolOptions};
 use sqlx::types::Json;
-use sqlx::{PgPool, Pool, Postgres};
+use sqlx::{Connection, PgPool, Pool, Postgres, QueryBuilder};
 use std::collections::HashMap;
+use tracing::warn;
 use uuid::Uuid;
 
+use crate::organizations::upsert_organizations;
+use crate::resources::replace_resources;
+use crate::store::reprocessing_decisions;
+use crate::tags::replace_tags;
+
 /// Column list for SELECT queries. Must remain a const literal to ensure SQL safety
 /// since format!() bypasses sqlx compile-time validation.
-const DATASET_COLUMNS: &str = "id, original_id, source_portal, url, title, description, embedding, metadata, first_seen_at, last_updated_at, content_hash";
+const DATASET_COLUMNS: &str = "id, original_id, source_portal, url, title, description, embedding, metadata, formats, first_seen_at, last_updated_at, content_hash, embedding_model, embedded_at, modified_at, language, quality";
+
+/// Datasets carrying GeoJSON in a top-level `spatial` field or extra (see
+/// [`dataset_bbox`]).
+const HAS_SPATIAL_EXTRA: &str =
+    r#"(metadata ? 'spatial' OR metadata @> '{"extras": [{"key": "spatial"}]}')"#;
+
+/// Number of formats reported in the stats facet.
+const FORMAT_FACET_LIMIT: usize = 10;
+
+/// Datasets read per query by [`DatasetRepository::stream_all`].
+pub(crate) const STREAM_PAGE_SIZE: usize = 1000;
+
+/// Progress of a keyset-paginated stream: the `(last_updated_at, id)` key of
+/// the last dataset read and how many datasets may still be read, or `None`
+/// once the last page has been read.
+pub(crate) type StreamState = Option<(Option<(DateTime<Utc>, Uuid)>, usize)>;
+
+/// Full-text document of a dataset: the generated `search_document` column,
+/// indexed by `idx_datasets_search_document`.
+pub(crate) const FTS_DOCUMENT: &str = "search_document";
 
 /// Repository for dataset persistence in PostgreSQL with pgvector.
 ///
@@ -47,7 +94,7 @@ const DATASET_COLUMNS: &str = "id, original_id, source_portal, url, title, descr
 /// ```
 #[derive(Clone)]
 pub struct DatasetRepository {
-    pool: Pool<Postgres>,
+    pub(crate) pool: Pool<Postgres>,
 }
 
 impl DatasetRepository {
@@ -55,16 +102,127 @@ impl DatasetRepository {
         Self { pool }
     }
 
+    /// Connects to the database at `database_url`.
+    ///
+    /// While the server is unreachable or starting up, the connection is
+    /// retried `config.connect_retries` times with exponential backoff.
+    /// Other failures, such as a wrong password, are returned at once.
+    pub async fn connect(database_url: &str, config: &DbConfig) -> Result<Self, AppError> {
+        let mut attempt = 0;
+        loop {
+            // A single connection fails fast; the pool would keep trying
+            // until its acquire timeout and hide the cause
+            match PgConnection::connect(database_url).await {
+                Ok(probe) => {
+                    // Best effort: the server may not see the goodbye
+                    let _ = probe.close().await;
+                    let pool = PgPoolOptions::new()
+                        .max_connections(config.max_connections)
+                        .connect(database_url)
+                        .await
+                        .map_err(AppError::DatabaseError)?;
+                    return Ok(Self::new(pool));
+                }
+                Err(e) if attempt < config.connect_retries && is_connection_error(&e) => {
+                    let delay = config.connect_retry_base_delay * 2_u32.pow(attempt);
+                    attempt += 1;
+                    warn!(
+                        "Database unavailable ({}), retrying in {:?} ({}/{})",
+                        e, delay, attempt, config.connect_retries
+                    );
+                    tokio::time::sleep(delay).await;
+                }
+                Err(e) => return Err(AppError::DatabaseError(e)),
+            }
+        }
+    }
+
+    /// Fails with [`AppError::PgvectorMissing`] unless the server has the
+    /// pgvector extension, which the schema needs.
+    pub async fn check_pgvector(&self) -> Result<(), AppError> {
+        let available: bool = sqlx::query_scalar(
+            "SELECT EXISTS (SELECT 1 FROM pg_available_extensions WHERE name = 'vector')",
+        )
+        .fetch_one(&self.pool)
+        .await
+        .map_err(AppError::DatabaseError)?;
+        if available {
+            Ok(())
+        } else {
+            Err(AppError::PgvectorMissing)
+        }
+    }
+
     /// Inserts or updates a dataset. Returns the UUID of the affected row.
     ///
-    /// TODO(robustness): Return UpsertOutcome to distinguish insert vs update
-    /// Currently returns only UUID without indicating operation type.
-    /// Consider: `pub enum UpsertOutcome { Created(Uuid), Updated(Uuid) }`
-    /// This enables accurate progress reporting in sync statistics.
-    pub async fn upsert(&self, new_data: &NewDataset) -> Result<Uuid, AppError> {
-        let embedding_vector = new_data.embedding.as_ref().cloned();
-
-        let rec: (Uuid,) = sqlx::query_as(
+    /// `embedding_model` and `embedded_at` follow the embedding: they are
+    /// set when `new_data` carries one and kept otherwise. The dataset's
+    /// tags and resources are replaced with those of `new_data`, and its
+    /// organization is created or updated.
+    ///
+    /// Returns whether the dataset row was inserted or updated, with its
+    /// UUID.
+    pub async fn upsert(&self, new_data: &NewDataset) -> Result<UpsertOutcome, AppError> {
+        let outcomes = self.upsert_batch(std::slice::from_ref(new_data)).await?;
+        Ok(outcomes[0])
+    }
+
+    /// Inserts or updates several datasets in one transaction, returning
+    /// their outcomes in the order given.
+    ///
+    /// Each dataset is written as by [`upsert`](Self::upsert), but with one
+    /// multi-row statement per table instead of one per dataset. When the
+    /// same dataset appears more than once, the last copy is stored.
+    pub async fn upsert_batch(
+        &self,
+        datasets: &[NewDataset],
+    ) -> Result<Vec<UpsertOutcome>, AppError> {
+        if datasets.is_empty() {
+            return Ok(Vec::new());
+        }
+        // ON CONFLICT cannot update a row twice in one statement
+        let mut latest: HashMap<(&str, &str), &NewDataset> = HashMap::new();
+        for dataset in datasets {
+            latest.insert((&dataset.source_portal, &dataset.original_id), dataset);
+        }
+        let unique: Vec<&NewDataset> = latest.into_values().collect();
+
+        let mut tx = self.pool.begin().await.map_err(AppError::DatabaseError)?;
+        let organizations: Vec<_> = unique
+            .iter()
+            .filter_map(|d| Some((d.source_portal.as_str(), d.organization.as_ref()?)))
+            .collect();
+        let organization_ids = upsert_organizations(&mut tx, &organizations).await?;
+
+        let column = |f: fn(&NewDataset) -> Option<&str>| -> Vec<Option<&str>> {
+            unique.iter().map(|d| f(d)).collect()
+        };
+        let text =
+            |f: fn(&NewDataset) -> &str| -> Vec<&str> { unique.iter().map(|d| f(d)).collect() };
+        let bbox = |f: fn(&BoundingBox) -> f64| -> Vec<Option<f64>> {
+            unique.iter().map(|d| d.bbox.as_ref().map(f)).collect()
+        };
+        let embeddings: Vec<Option<Vector>> = unique.iter().map(|d| d.embedding.clone()).collect();
+        let metadata: Vec<&serde_json::Value> = unique.iter().map(|d| &d.metadata).collect();
+        // Arrays of arrays must be rectangular, so formats travel as JSON
+        let formats: Vec<serde_json::Value> = unique
+            .iter()
+            .map(|d| serde_json::json!(d.formats))
+            .collect();
+        let modified_at: Vec<Option<DateTime<Utc>>> =
+            unique.iter().map(|d| d.modified_at).collect();
+        let quality: Vec<Option<f32>> = unique.iter().map(|d| d.quality).collect();
+        let organization: Vec<Option<i32>> = unique
+            .iter()
+            .map(|d| {
+                let name = &d.organization.as_ref()?.name;
+                organization_ids
+                    .get(&(d.source_portal.clone(), name.clone()))
+                    .copied()
+            })
+            .collect();
+
+        let rows: Vec<(Uuid, String, String, bool)> = sqlx::query_as(
             r#"
             INSERT INTO datasets (
                 original_id,
@@ -73,57 +231,139 @@ impl DatasetRepository {
                 title,
                 description,
                 embedding,
+                embedding_model,
                 metadata,
+                formats,
                 content_hash,
+                modified_at,
+                language,
+                bbox,
+                quality,
+                organization_id,
+                embedded_at,
                 last_updated_at
             )
-            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, NOW())
+            SELECT
+                d.original_id, d.source_portal, d.url, d.title, d.description,
+                d.embedding, d.embedding_model, d.metadata,
+                ARRAY(SELECT jsonb_array_elements_text(d.formats)),
+                d.content_hash, d.modified_at, d.language,
+                box(point(d.min_lon, d.min_lat), point(d.max_lon, d.max_lat)),
+                d.quality, d.organization_id,
+                CASE WHEN d.embedding IS NOT NULL THEN NOW() END,
+                NOW()
+            FROM unnest(
+                $1::text[], $2::text[], $3::text[], $4::text[], $5::text[],
+                $6::vector[], $7::text[], $8::jsonb[], $9::jsonb[], $10::text[],
+                $11::timestamptz[], $12::text[],
+                $13::float8[], $14::float8[], $15::float8[], $16::float8[],
+                $17::real[], $18::int[]
+            ) AS d(
+                original_id, source_portal, url, title, description,
+                embedding, embedding_model, metadata, formats, content_hash,
+                modified_at, language,
+                min_lon, min_lat, max_lon, max_lat,
+                quality, organization_id
+            )
             ON CONFLICT (source_portal, original_id)
             DO UPDATE SET
                 title = EXCLUDED.title,
                 description = EXCLUDED.description,
                 url = EXCLUDED.url,
                 embedding = COALESCE(EXCLUDED.embedding, datasets.embedding),
+                embedding_model = CASE
+                    WHEN EXCLUDED.embedding IS NOT NULL THEN EXCLUDED.embedding_model
+                    ELSE datasets.embedding_model
+                END,
+                embedded_at = CASE
+                    WHEN EXCLUDED.embedding IS NOT NULL THEN NOW()
+                    ELSE datasets.embedded_at
+                END,
                 metadata = EXCLUDED.metadata,
+                formats = EXCLUDED.formats,
                 content_hash = EXCLUDED.content_hash,
+                modified_at = EXCLUDED.modified_at,
+                language = EXCLUDED.language,
+                bbox = EXCLUDED.bbox,
+                quality = EXCLUDED.quality,
+                organization_id = EXCLUDED.organization_id,
                 last_updated_at = NOW()
-            RETURNING id
+            RETURNING id, source_portal, original_id, xmax = 0
             "#,
         )
-        .bind(&new_data.original_id)
-        .bind(&new_data.source_portal)
-        .bind(&new_data.url)
-        .bind(&new_data.title)
-        .bind(&new_data.description)
-        .bind(embedding_vector)
-        .bind(serde_json::to_value(&new_data.metadata).unwrap_or(serde_json::json!({})))
-        .bind(&new_data.content_hash)
-        .fetch_one(&self.pool)
+        .bind(text(|d| &d.original_id))
+        .bind(text(|d| &d.source_portal))
+        .bind(text(|d| &d.url))
+        .bind(text(|d| &d.title))
+        .bind(column(|d| d.description.as_deref()))
+        .bind(&embeddings)
+        .bind(column(|d| d.embedding_model.as_deref()))
+        .bind(&metadata)
+        .bind(&formats)
+        .bind(text(|d| &d.content_hash))
+        .bind(&modified_at)
+        .bind(column(|d| d.language.as_deref()))
+        .bind(bbox(|b| b.min_lon))
+        .bind(bbox(|b| b.min_lat))
+        .bind(bbox(|b| b.max_lon))
+        .bind(bbox(|b| b.max_lat))
+        .bind(&quality)
+        .bind(&organization)
+        .fetch_all(&mut *tx)
         .await
         .map_err(AppError::DatabaseError)?;
 
-        Ok(rec.0)
+        // xmax is zero in rows this statement inserted rather than updated
+        let outcomes: HashMap<(String, String), UpsertOutcome> = rows
+            .into_iter()
+            .map(|(id, portal, original_id, inserted)| {
+                let outcome = if inserted {
+                    UpsertOutcome::Created(id)
+                } else {
+                    UpsertOutcome::Updated(id)
+                };
+                ((portal, original_id), outcome)
+            })
+            .collect();
+        let outcome_of =
+            |d: &NewDataset| outcomes[&(d.source_portal.clone(), d.original_id.clone())];
+        let id_of = |d: &NewDataset| outcome_of(d).id();
+
+        let tags: Vec<(Uuid, &[String])> = unique.iter().map(|d| (id_of(d), &d.tags[..])).collect();
+        replace_tags(&mut tx, &tags).await?;
+        let resources: Vec<(Uuid, &[DatasetResource])> = unique
+            .iter()
+            .map(|d| (id_of(d), &d.resources[..]))
+            .collect();
+        replace_resources(&mut tx, &resources).await?;
+        tx.commit().await.map_err(AppError::DatabaseError)?;
+
+        Ok(datasets.iter().map(outcome_of).collect())
     }
 
     /// Returns a map of original_id → content_hash for all datasets from a portal.
     ///
-    /// TODO(performance): Optimize for large portals (100k+ datasets)
-    /// Currently loads entire HashMap into memory. Consider:
-    /// (1) Streaming hash comparison during sync, or
-    /// (2) Database-side hash check with WHERE clause, or
-    /// (3) Bloom filter for approximate membership testing
+    /// Datasets whose embedding was not produced by `embedding_model` are
+    /// reported without a hash, so the next sync re-embeds them. Harvests
+    /// use [`datasets_needing_processing`](Self::datasets_needing_processing)
+    /// instead, which does not hold the whole portal in memory.
     pub async fn get_hashes_for_portal(
         &self,
         portal_url: &str,
+        embedding_model: &str,
     ) -> Result<HashMap<String, Option<String>>, AppError> {
         let rows: Vec<HashRow> = sqlx::query_as(
             r#"
-            SELECT original_id, content_hash
+            SELECT
+                original_id,
+                CASE WHEN embedding_model IS DISTINCT FROM $2 THEN NULL ELSE content_hash END
+                    AS content_hash
             FROM datasets
             WHERE source_portal = $1
             "#,
         )
         .bind(portal_url)
+        .bind(embedding_model)
         .fetch_all(&self.pool)
         .await
         .map_err(AppError::DatabaseError)?;
@@ -136,6 +376,71 @@ impl DatasetRepository {
         Ok(hash_map)
     }
 
+    /// Delta detection for a batch of `(original_id, content_hash)` pairs,
+    /// joined against the portal's stored rows in one query so a sync never
+    /// holds the whole portal's hashes. Returns decisions only for datasets
+    /// that are new, changed or embedded by another model than
+    /// `embedding_model`.
+    pub async fn datasets_needing_processing(
+        &self,
+        portal_url: &str,
+        embedding_model: &str,
+        candidates: &[(String, String)],
+    ) -> Result<HashMap<String, ReprocessingDecision>, AppError> {
+        let (original_ids, hashes): (Vec<&str>, Vec<&str>) = candidates
+            .iter()
+            .map(|(original_id, hash)| (original_id.as_str(), hash.as_str()))
+            .unzip();
+        let rows: Vec<(String, bool, Option<String>, String)> = sqlx::query_as(
+            r#"
+            SELECT
+                c.original_id,
+                d.id IS NOT NULL,
+                CASE WHEN d.embedding_model IS DISTINCT FROM $2 THEN NULL ELSE d.content_hash END,
+                c.content_hash
+            FROM unnest($3::text[], $4::text[]) AS c(original_id, content_hash)
+            LEFT JOIN datasets d
+                ON d.source_portal = $1 AND d.original_id = c.original_id
+            WHERE d.id IS NULL
+                OR d.embedding_model IS DISTINCT FROM $2
+                OR d.content_hash IS DISTINCT FROM c.content_hash
+            "#,
+        )
+        .bind(portal_url)
+        .bind(embedding_model)
+        .bind(&original_ids)
+        .bind(&hashes)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(AppError::DatabaseError)?;
+
+        Ok(reprocessing_decisions(rows))
+    }
+
+    /// Returns the stored hash and last sync time of every dataset of a portal.
+    pub async fn list_sync_state(&self, portal_url: &str) -> Result<Vec<LocalRecord>, AppError> {
+        let rows: Vec<SyncStateRow> = sqlx::query_as(
+            r#"
+            SELECT original_id, content_hash, last_updated_at
+            FROM datasets
+            WHERE source_portal = $1
+            "#,
+        )
+        .bind(portal_url)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(AppError::DatabaseError)?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| LocalRecord {
+                original_id: row.original_id,
+                content_hash: row.content_hash,
+                last_updated_at: row.last_updated_at,
+            })
+            .collect())
+    }
+
     /// Updates only the timestamp for unchanged datasets. Returns true if a row was updated.
     pub async fn update_timestamp_only(
         &self,
@@ -158,6 +463,307 @@ impl DatasetRepository {
         Ok(result.rows_affected() > 0)
     }
 
+    /// Counts embedded datasets without a content hash.
+    ///
+    /// These predate content hashing; harvests re-embed them until a hash
+    /// is stored (see [`backfill_content_hashes`](Self::backfill_content_hashes)).
+    pub async fn count_missing_hashes(&self) -> Result<i64, AppError> {
+        let count: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM datasets WHERE content_hash IS NULL AND embedding IS NOT NULL",
+        )
+        .fetch_one(&self.pool)
+        .await
+        .map_err(AppError::DatabaseError)?;
+
+        Ok(count)
+    }
+
+    /// Counts embedded datasets without a content hash, or all embedded
+    /// datasets with `rehash` (see
+    /// [`backfill_content_hashes`](Self::backfill_content_hashes)).
+    pub async fn count_hash_backfill(&self, rehash: bool) -> Result<i64, AppError> {
+        let count: i64 = sqlx::query_scalar(
+            "SELECT COUNT(*) FROM datasets WHERE embedding IS NOT NULL AND ($1 OR content_hash IS NULL)",
+        )
+        .bind(rehash)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(AppError::DatabaseError)?;
+
+        Ok(count)
+    }
+
+    /// Stores content hashes computed from the stored title, description
+    /// and metadata, covering `hash_fields` as harvests do (see
+    /// [`SyncConfig::hash_fields`](ceres_core::SyncConfig)), for up to
+    /// `batch_size` embedded datasets with IDs greater than `after`: those
+    /// without a hash, or all of them with `rehash`.
+    ///
+    /// Datasets without an embedding are left alone so the next harvest
+    /// still embeds them. Returns the last ID scanned and the number of
+    /// hashes changed, or `None` when no datasets are left.
+    pub async fn backfill_content_hashes(
+        &self,
+        after: Uuid,
+        batch_size: usize,
+        rehash: bool,
+        hash_fields: &[HashField],
+    ) -> Result<Option<(Uuid, usize)>, AppError> {
+        let rows: Vec<BackfillRow> = sqlx::query_as(
+            r#"
+            SELECT id, title, description, metadata
+            FROM datasets
+            WHERE embedding IS NOT NULL AND ($3 OR content_hash IS NULL) AND id > $1
+            ORDER BY id
+            LIMIT $2
+            "#,
+        )
+        .bind(after)
+        .bind(batch_size as i64)
+        .bind(rehash)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(AppError::DatabaseError)?;
+
+        let Some(last) = rows.last().map(|row| row.id) else {
+            return Ok(None);
+        };
+        let (ids, hashes): (Vec<Uuid>, Vec<String>) = rows
+            .into_iter()
+            .map(|row| {
+                let hash = content_hash(
+                    &row.title,
+                    row.description.as_deref(),
+                    &row.metadata,
+                    hash_fields,
+                );
+                (row.id, hash)
+            })
+            .unzip();
+
+        let result = sqlx::query(
+            r#"
+            UPDATE datasets AS d
+            SET content_hash = v.content_hash
+            FROM unnest($1::uuid[], $2::text[]) AS v(id, content_hash)
+            WHERE d.id = v.id AND d.content_hash IS DISTINCT FROM v.content_hash
+            "#,
+        )
+        .bind(&ids)
+        .bind(&hashes)
+        .execute(&self.pool)
+        .await
+        .map_err(AppError::DatabaseError)?;
+
+        Ok(Some((last, result.rows_affected() as usize)))
+    }
+
+    /// Counts datasets without a detected language.
+    pub async fn count_missing_languages(&self) -> Result<i64, AppError> {
+        let count: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM datasets WHERE language IS NULL")
+            .fetch_one(&self.pool)
+            .await
+            .map_err(AppError::DatabaseError)?;
+
+        Ok(count)
+    }
+
+    /// Detects and stores the language of up to `batch_size` datasets
+    /// without one, with IDs greater than `after`.
+    ///
+    /// Datasets whose language cannot be detected stay NULL, so batches are
+    /// walked by ID rather than re-selecting them. Returns the last ID
+    /// scanned and the number of languages stored, or `None` when no
+    /// datasets are left.
+    pub async fn backfill_languages(
+        &self,
+        after: Uuid,
+        batch_size: usize,
+    ) -> Result<Option<(Uuid, usize)>, AppError> {
+        let rows: Vec<BackfillRow> = sqlx::query_as(
+            r#"
+            SELECT id, title, description
+            FROM datasets
+            WHERE language IS NULL AND id > $1
+            ORDER BY id
+            LIMIT $2
+            "#,
+        )
+        .bind(after)
+        .bind(batch_size as i64)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(AppError::DatabaseError)?;
+
+        let Some(last) = rows.last().map(|row| row.id) else {
+            return Ok(None);
+        };
+        let (ids, languages): (Vec<Uuid>, Vec<String>) = rows
+            .into_iter()
+            .filter_map(|row| {
+                detect_language(&row.title, row.description.as_deref())
+                    .map(|language| (row.id, language))
+            })
+            .unzip();
+
+        let result = sqlx::query(
+            r#"
+            UPDATE datasets AS d
+            SET language = v.language
+            FROM unnest($1::uuid[], $2::text[]) AS v(id, language)
+            WHERE d.id = v.id
+            "#,
+        )
+        .bind(&ids)
+        .bind(&languages)
+        .execute(&self.pool)
+        .await
+        .map_err(AppError::DatabaseError)?;
+
+        Ok(Some((last, result.rows_affected() as usize)))
+    }
+
+    /// Counts datasets with a `spatial` extra but no stored bounding box.
+    pub async fn count_missing_bboxes(&self) -> Result<i64, AppError> {
+        let query = format!(
+            "SELECT COUNT(*) FROM datasets WHERE bbox IS NULL AND {}",
+            HAS_SPATIAL_EXTRA
+        );
+        let count: i64 = sqlx::query_scalar(&query)
+            .fetch_one(&self.pool)
+            .await
+            .map_err(AppError::DatabaseError)?;
+
+        Ok(count)
+    }
+
+    /// Parses and stores the bounding box of up to `batch_size` datasets
+    /// with a `spatial` extra but no box, with IDs greater than `after`.
+    ///
+    /// Datasets whose GeoJSON cannot be parsed stay NULL, so batches are
+    /// walked by ID as in [`backfill_languages`](Self::backfill_languages).
+    /// Returns the last ID scanned and the number of boxes stored, or
+    /// `None` when no datasets are left.
+    pub async fn backfill_bboxes(
+        &self,
+        after: Uuid,
+        batch_size: usize,
+    ) -> Result<Option<(Uuid, usize)>, AppError> {
+        let query = format!(
+            r#"
+            SELECT id, metadata
+            FROM datasets
+            WHERE bbox IS NULL AND {} AND id > $1
+            ORDER BY id
+            LIMIT $2
+            "#,
+            HAS_SPATIAL_EXTRA
+        );
+        let rows: Vec<(Uuid, Json<serde_json::Value>)> = sqlx::query_as(&query)
+            .bind(after)
+            .bind(batch_size as i64)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(AppError::DatabaseError)?;
+
+        let Some(last) = rows.last().map(|(id, _)| *id) else {
+            return Ok(None);
+        };
+        let boxes: Vec<(Uuid, BoundingBox)> = rows
+            .into_iter()
+            .filter_map(|(id, metadata)| dataset_bbox(&metadata).map(|bbox| (id, bbox)))
+            .collect();
+
+        let result = sqlx::query(
+            r#"
+            UPDATE datasets AS d
+            SET bbox = box(point(v.min_lon, v.min_lat), point(v.max_lon, v.max_lat))
+            FROM unnest($1::uuid[], $2::float8[], $3::float8[], $4::float8[], $5::float8[])
+                AS v(id, min_lon, min_lat, max_lon, max_lat)
+            WHERE d.id = v.id
+            "#,
+        )
+        .bind(boxes.iter().map(|(id, _)| *id).collect::<Vec<_>>())
+        .bind(boxes.iter().map(|(_, b)| b.min_lon).collect::<Vec<_>>())
+        .bind(boxes.iter().map(|(_, b)| b.min_lat).collect::<Vec<_>>())
+        .bind(boxes.iter().map(|(_, b)| b.max_lon).collect::<Vec<_>>())
+        .bind(boxes.iter().map(|(_, b)| b.max_lat).collect::<Vec<_>>())
+        .execute(&self.pool)
+        .await
+        .map_err(AppError::DatabaseError)?;
+
+        Ok(Some((last, result.rows_affected() as usize)))
+    }
+
+    /// Counts datasets without a quality score, or all datasets with
+    /// `rescore` (see [`backfill_quality`](Self::backfill_quality)).
+    pub async fn count_quality_backfill(&self, rescore: bool) -> Result<i64, AppError> {
+        let count: i64 =
+            sqlx::query_scalar("SELECT COUNT(*) FROM datasets WHERE $1 OR quality IS NULL")
+                .bind(rescore)
+                .fetch_one(&self.pool)
+                .await
+                .map_err(AppError::DatabaseError)?;
+
+        Ok(count)
+    }
+
+    /// Scores the metadata of up to `batch_size` datasets with IDs greater
+    /// than `after`: those without a score, or all of them with `rescore`.
+    ///
+    /// Returns the last ID scanned and the number of scores stored, or
+    /// `None` when no datasets are left.
+    pub async fn backfill_quality(
+        &self,
+        after: Uuid,
+        batch_size: usize,
+        rescore: bool,
+    ) -> Result<Option<(Uuid, usize)>, AppError> {
+        let rows: Vec<QualityBackfillRow> = sqlx::query_as(
+            r#"
+            SELECT id, description, metadata, modified_at
+            FROM datasets
+            WHERE ($3 OR quality IS NULL) AND id > $1
+            ORDER BY id
+            LIMIT $2
+            "#,
+        )
+        .bind(after)
+        .bind(batch_size as i64)
+        .bind(rescore)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(AppError::DatabaseError)?;
+
+        let Some(last) = rows.last().map(|row| row.id) else {
+            return Ok(None);
+        };
+        let (ids, scores): (Vec<Uuid>, Vec<f32>) = rows
+            .into_iter()
+            .map(|row| {
+                let score =
+                    quality_score(row.description.as_deref(), &row.metadata, row.modified_at);
+                (row.id, score)
+            })
+            .unzip();
+
+        let result = sqlx::query(
+            r#"
+            UPDATE datasets AS d
+            SET quality = v.quality
+            FROM unnest($1::uuid[], $2::real[]) AS v(id, quality)
+            WHERE d.id = v.id
+            "#,
+        )
+        .bind(&ids)
+        .bind(&scores)
+        .execute(&self.pool)
+        .await
+        .map_err(AppError::DatabaseError)?;
+
+        Ok(Some((last, result.rows_affected() as usize)))
+    }
+
     /// Retrieves a dataset by UUID.
     pub async fn get(&self, id: Uuid) -> Result<Option<Dataset>, AppError> {
         let query = format!("SELECT {} FROM datasets WHERE id = $1", DATASET_COLUMNS);
@@ -170,6 +776,28 @@ impl DatasetRepository {
         Ok(result)
     }
 
+    /// Retrieves several datasets by UUID with a single query.
+    ///
+    /// The result is aligned with `ids`: entry `i` is `None` if `ids[i]`
+    /// does not exist, so callers can report missing IDs.
+    pub async fn get_many(&self, ids: &[Uuid]) -> Result<Vec<Option<Dataset>>, AppError> {
+        if ids.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let query = format!(
+            "SELECT {} FROM datasets WHERE id = ANY($1)",
+            DATASET_COLUMNS
+        );
+        let rows = sqlx::query_as::<_, Dataset>(&query)
+            .bind(ids)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(AppError::DatabaseError)?;
+
+        Ok(align_by_id(ids, rows))
+    }
+
     /// Semantic search using cosine similarity. Returns results ordered by similarity.
     pub async fn search(
         &self,
@@ -187,71 +815,224 @@ impl DatasetRepository {
             .await
             .map_err(AppError::DatabaseError)?;
 
-        Ok(results
-            .into_iter()
-            .map(|row| SearchResult {
-                dataset: Dataset {
-                    id: row.id,
-                    original_id: row.original_id,
-                    source_portal: row.source_portal,
-                    url: row.url,
-                    title: row.title,
-                    description: row.description,
-                    embedding: row.embedding,
-                    metadata: row.metadata,
-                    first_seen_at: row.first_seen_at,
-                    last_updated_at: row.last_updated_at,
-                    content_hash: row.content_hash,
-                },
-                similarity_score: row.similarity_score as f32,
-            })
-            .collect())
+        Ok(results.into_iter().map(SearchResult::from).collect())
     }
 
-    /// Lists datasets with optional portal filter and limit.
-    ///
-    /// TODO(config): Make default limit configurable via DEFAULT_EXPORT_LIMIT env var
-    /// Currently hardcoded to 10000. For large exports, consider streaming instead.
+    /// Semantic search with structured filters and automatic retrieval planning.
     ///
-    /// TODO(performance): Implement streaming/pagination for memory efficiency
-    /// Loading all datasets into memory doesn't scale. Consider returning
-    /// `impl Stream<Item = Result<Dataset, AppError>>` or cursor-based pagination.
-    pub async fn list_all(
+    /// Depending on `strategy`, corpus size and filter selectivity, this either
+    /// uses the ANN index directly or prefilters a bounded candidate set and
+    /// scores it exactly (see [`ceres_core::search`]). `query_text` is only
+    /// used for keyword pruning of the candidate set.
+    pub async fn search_with_filters(
         &self,
-        portal_filter: Option<&str>,
-        limit: Option<usize>,
-    ) -> Result<Vec<Dataset>, AppError> {
-        // TODO(config): Read default from DEFAULT_EXPORT_LIMIT env var
-        let limit_val = limit.unwrap_or(10000) as i64;
+        query_vector: Vector,
+        query_text: &str,
+        limit: usize,
+        filters: &SearchFilters,
+        strategy: SearchStrategy,
+    ) -> Result<Vec<SearchResult>, AppError> {
+        let keywords = keyword_tsquery(query_text);
+        let plan = self
+            .plan_filtered_search(filters, keywords.as_deref(), limit, strategy)
+            .await?;
 
-        let datasets = if let Some(portal) = portal_filter {
-            let query = format!(
-                "SELECT {} FROM datasets WHERE source_portal = $1 ORDER BY last_updated_at DESC LIMIT $2",
-                DATASET_COLUMNS
-            );
-            sqlx::query_as::<_, Dataset>(&query)
-                .bind(portal)
-                .bind(limit_val)
-                .fetch_all(&self.pool)
-                .await
-                .map_err(AppError::DatabaseError)?
+        let mut builder = QueryBuilder::<Postgres>::new("");
+        match plan {
+            SearchPlan::Direct => {
+                builder.push(format!("SELECT {}, 1 - (embedding <=> ", DATASET_COLUMNS));
+                builder.push_bind(query_vector.clone());
+                builder.push(") AS similarity_score FROM datasets WHERE embedding IS NOT NULL");
+                push_search_filters(&mut builder, filters);
+                builder.push(" ORDER BY embedding <=> ");
+                builder.push_bind(query_vector);
+            }
+            SearchPlan::TwoStage(candidates) => {
+                builder.push("WITH scored AS MATERIALIZED (SELECT id, embedding <=> ");
+                builder.push_bind(query_vector);
+                builder.push(" AS distance FROM datasets WHERE embedding IS NOT NULL");
+                push_search_filters(&mut builder, filters);
+                if let (CandidateSet::FiltersAndKeywords, Some(tsquery)) = (candidates, &keywords) {
+                    push_keyword_filter(&mut builder, tsquery);
+                }
+                builder.push(" LIMIT ");
+                builder.push_bind(TWO_STAGE_MAX_CANDIDATES as i64);
+                builder.push(format!(
+                    ") SELECT {}, 1 - distance AS similarity_score FROM scored JOIN datasets USING (id) ORDER BY distance",
+                    DATASET_COLUMNS
+                ));
+            }
+        }
+        builder.push(" LIMIT ");
+        builder.push_bind(limit as i64);
+
+        let rows: Vec<SearchResultRow> = builder
+            .build_query_as()
+            .fetch_all(&self.pool)
+            .await
+            .map_err(AppError::DatabaseError)?;
+
+        Ok(rows.into_iter().map(SearchResult::from).collect())
+    }
+
+    /// Gathers bounded candidate counts and picks a search plan.
+    async fn plan_filtered_search(
+        &self,
+        filters: &SearchFilters,
+        keywords: Option<&str>,
+        limit: usize,
+        strategy: SearchStrategy,
+    ) -> Result<SearchPlan, AppError> {
+        if strategy == SearchStrategy::Direct {
+            return Ok(SearchPlan::Direct);
+        }
+
+        // Planner statistics are a cheap corpus estimate; exact COUNT(*) is not.
+        let (corpus,): (i64,) = sqlx::query_as(
+            "SELECT GREATEST(reltuples, 0)::bigint FROM pg_class WHERE oid = 'datasets'::regclass",
+        )
+        .fetch_one(&self.pool)
+        .await
+        .map_err(AppError::DatabaseError)?;
+        let corpus = corpus as u64;
+
+        let filtered = if filters.is_empty() {
+            corpus
         } else {
-            let query = format!(
-                "SELECT {} FROM datasets ORDER BY last_updated_at DESC LIMIT $1",
-                DATASET_COLUMNS
-            );
-            sqlx::query_as::<_, Dataset>(&query)
-                .bind(limit_val)
-                .fetch_all(&self.pool)
-                .await
-                .map_err(AppError::DatabaseError)?
+            self.count_candidates(filters, None).await?
         };
 
-        Ok(datasets)
+        let keyword = match keywords {
+            Some(tsquery) if needs_keyword_estimate(strategy, corpus, filtered) => {
+                Some(self.count_candidates(filters, Some(tsquery)).await?)
+            }
+            _ => None,
+        };
+
+        Ok(plan_search(
+            strategy,
+            !filters.is_empty(),
+            CandidateEstimate {
+                corpus,
+                filtered,
+                keyword,
+            },
+            limit as u64,
+        ))
+    }
+
+    /// Counts prefilter candidates, stopping just above the two-stage budget.
+    async fn count_candidates(
+        &self,
+        filters: &SearchFilters,
+        keywords: Option<&str>,
+    ) -> Result<u64, AppError> {
+        let mut builder = QueryBuilder::<Postgres>::new(
+            "SELECT COUNT(*) FROM (SELECT 1 FROM datasets WHERE embedding IS NOT NULL",
+        );
+        push_search_filters(&mut builder, filters);
+        if let Some(tsquery) = keywords {
+            push_keyword_filter(&mut builder, tsquery);
+        }
+        builder.push(" LIMIT ");
+        builder.push_bind(TWO_STAGE_MAX_CANDIDATES as i64 + 1);
+        builder.push(") candidates");
+
+        let (count,): (i64,) = builder
+            .build_query_as()
+            .fetch_one(&self.pool)
+            .await
+            .map_err(AppError::DatabaseError)?;
+
+        Ok(count as u64)
+    }
+
+    /// Streams the datasets passing `filters`, most recently updated first,
+    /// stopping after `limit` datasets if set.
+    ///
+    /// Rows are read in pages of [`STREAM_PAGE_SIZE`] using keyset
+    /// pagination on `(last_updated_at, id)`, so memory use stays flat
+    /// however many datasets there are and later pages cost no more than the
+    /// first. Rows updated while the stream runs may be skipped or repeated.
+    pub fn stream_all<'a>(
+        &'a self,
+        filters: &'a SearchFilters,
+        limit: Option<usize>,
+    ) -> impl Stream<Item = Result<Dataset, AppError>> + Send + 'a {
+        let start: StreamState = Some((None, limit.unwrap_or(usize::MAX)));
+        stream::try_unfold(start, move |state| async move {
+            let Some((after, remaining)) = state.filter(|&(_, remaining)| remaining > 0) else {
+                return Ok::<_, AppError>(None);
+            };
+            let page_size = remaining.min(STREAM_PAGE_SIZE);
+            let page = self.list_page(filters, after, page_size).await?;
+            let next = match page.last() {
+                Some(last) if page.len() == page_size => {
+                    Some((Some((last.last_updated_at, last.id)), remaining - page_size))
+                }
+                _ => None,
+            };
+            Ok(Some((page, next)))
+        })
+        .map_ok(|page| stream::iter(page.into_iter().map(Ok)))
+        .try_flatten()
+    }
+
+    /// One page of [`stream_all`](Self::stream_all): up to `limit` datasets
+    /// ordered before the `after` key.
+    async fn list_page(
+        &self,
+        filters: &SearchFilters,
+        after: Option<(DateTime<Utc>, Uuid)>,
+        limit: usize,
+    ) -> Result<Vec<Dataset>, AppError> {
+        let mut builder = QueryBuilder::<Postgres>::new(format!(
+            "SELECT {} FROM datasets WHERE TRUE",
+            DATASET_COLUMNS
+        ));
+        push_search_filters(&mut builder, filters);
+        if let Some((last_updated_at, id)) = after {
+            builder.push(" AND (last_updated_at, id) < (");
+            builder.push_bind(last_updated_at);
+            builder.push(", ");
+            builder.push_bind(id);
+            builder.push(")");
+        }
+        builder.push(" ORDER BY last_updated_at DESC, id DESC LIMIT ");
+        builder.push_bind(limit as i64);
+
+        builder
+            .build_query_as::<Dataset>()
+            .fetch_all(&self.pool)
+            .await
+            .map_err(AppError::DatabaseError)
     }
 
-    /// Returns aggregated database statistics.
-    pub async fn get_stats(&self) -> Result<DatabaseStats, AppError> {
+    /// Loads the datasets among `ids` passing `filters`, in no particular
+    /// order. Unknown IDs are skipped.
+    pub async fn datasets_by_ids(
+        &self,
+        ids: &[Uuid],
+        filters: &SearchFilters,
+    ) -> Result<Vec<Dataset>, AppError> {
+        let mut builder = QueryBuilder::<Postgres>::new(format!(
+            "SELECT {} FROM datasets WHERE id = ANY(",
+            DATASET_COLUMNS
+        ));
+        builder.push_bind(ids.to_vec());
+        builder.push(")");
+        push_search_filters(&mut builder, filters);
+
+        builder
+            .build_query_as::<Dataset>()
+            .fetch_all(&self.pool)
+            .await
+            .map_err(AppError::DatabaseError)
+    }
+
+    /// Returns aggregated database statistics, optionally restricted to a
+    /// single portal.
+    pub async fn get_stats(&self, portal: Option<&str>) -> Result<DatabaseStats, AppError> {
         let row: StatsRow = sqlx::query_as(
             r#"
             SELECT
@@ -260,8 +1041,10 @@ impl DatasetRepository {
                 COUNT(DISTINCT source_portal) as portals,
                 MAX(last_updated_at) as last_update
             FROM datasets
+            WHERE $1::text IS NULL OR source_portal = $1
             "#,
         )
+        .bind(portal)
         .fetch_one(&self.pool)
         .await
         .map_err(AppError::DatabaseError)?;
@@ -271,8 +1054,194 @@ impl DatasetRepository {
             datasets_with_embeddings: row.with_embeddings.unwrap_or(0),
             total_portals: row.portals.unwrap_or(0),
             last_update: row.last_update,
+            formats: self.format_facet(portal, FORMAT_FACET_LIMIT).await?,
+            languages: self.language_counts(portal).await?,
+            quality: self.portal_quality(portal).await?,
+            embedding_models: self.embedding_model_counts(portal).await?,
+            portals: self.portal_stats(portal).await?,
         })
     }
+
+    /// Returns dataset counts and harvest history per portal, least
+    /// recently harvested first.
+    ///
+    /// Portals without harvest health records (harvested one at a time
+    /// before health tracking) fall back to their latest dataset update.
+    pub async fn portal_stats(&self, portal: Option<&str>) -> Result<Vec<PortalStats>, AppError> {
+        let rows: Vec<PortalStatsRow> = sqlx::query_as(
+            r#"
+            SELECT d.source_portal AS portal,
+                   COUNT(*) AS datasets,
+                   COUNT(d.embedding) AS with_embeddings,
+                   MAX(d.last_updated_at) AS last_update,
+                   h.last_success_at AS last_harvest_at,
+                   COALESCE(h.harvests, 0)::bigint AS harvests,
+                   COALESCE(h.failed_harvests, 0)::bigint AS failed_harvests
+            FROM datasets d
+            LEFT JOIN portal_health h ON rtrim(h.portal_url, '/') = rtrim(d.source_portal, '/')
+            WHERE $1::text IS NULL OR d.source_portal = $1
+            GROUP BY d.source_portal, h.portal_url
+            ORDER BY COALESCE(h.last_success_at, MAX(d.last_updated_at)) NULLS FIRST, portal
+            "#,
+        )
+        .bind(portal)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(AppError::DatabaseError)?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| PortalStats {
+                portal: row.portal,
+                datasets: row.datasets,
+                with_embeddings: row.with_embeddings,
+                last_update: row.last_update,
+                last_harvest_at: row.last_harvest_at,
+                harvests: row.harvests,
+                failed_harvests: row.failed_harvests,
+            })
+            .collect())
+    }
+
+    /// Returns how many datasets each embedding model produced, most used first.
+    pub async fn embedding_model_counts(
+        &self,
+        portal: Option<&str>,
+    ) -> Result<Vec<EmbeddingModelCount>, AppError> {
+        let rows: Vec<EmbeddingModelRow> = sqlx::query_as(
+            r#"
+            SELECT embedding_model AS model,
+                   COUNT(*) AS datasets,
+                   COUNT(DISTINCT source_portal) AS portals,
+                   MIN(embedded_at) AS first_embedded_at,
+                   MAX(embedded_at) AS last_embedded_at
+            FROM datasets
+            WHERE embedding IS NOT NULL AND embedding_model IS NOT NULL
+              AND ($1::text IS NULL OR source_portal = $1)
+            GROUP BY embedding_model
+            ORDER BY datasets DESC, model
+            "#,
+        )
+        .bind(portal)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(AppError::DatabaseError)?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| EmbeddingModelCount {
+                model: row.model,
+                datasets: row.datasets,
+                portals: row.portals,
+                first_embedded_at: row.first_embedded_at,
+                last_embedded_at: row.last_embedded_at,
+            })
+            .collect())
+    }
+
+    /// Returns how many datasets are in each detected language, most common
+    /// first; datasets without a language are counted last.
+    pub async fn language_counts(
+        &self,
+        portal: Option<&str>,
+    ) -> Result<Vec<LanguageCount>, AppError> {
+        let rows: Vec<LanguageCountRow> = sqlx::query_as(
+            r#"
+            SELECT language, COUNT(*) AS datasets
+            FROM datasets
+            WHERE $1::text IS NULL OR source_portal = $1
+            GROUP BY language
+            ORDER BY language IS NULL, datasets DESC, language
+            "#,
+        )
+        .bind(portal)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(AppError::DatabaseError)?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| LanguageCount {
+                language: row.language,
+                datasets: row.datasets,
+            })
+            .collect())
+    }
+
+    /// Returns the average quality score and low-quality dataset count of
+    /// each portal, lowest average first.
+    pub async fn portal_quality(
+        &self,
+        portal: Option<&str>,
+    ) -> Result<Vec<PortalQuality>, AppError> {
+        let rows: Vec<PortalQualityRow> = sqlx::query_as(
+            r#"
+            SELECT source_portal AS portal,
+                   COUNT(*) AS datasets,
+                   AVG(quality)::float8 AS average,
+                   COUNT(*) FILTER (WHERE quality < $1) AS low
+            FROM datasets
+            WHERE quality IS NOT NULL
+              AND ($2::text IS NULL OR source_portal = $2)
+            GROUP BY source_portal
+            ORDER BY average, portal
+            "#,
+        )
+        .bind(LOW_QUALITY)
+        .bind(portal)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(AppError::DatabaseError)?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| PortalQuality {
+                portal: row.portal,
+                datasets: row.datasets,
+                average: row.average,
+                low: row.low,
+            })
+            .collect())
+    }
+
+    /// Returns the `limit` most common resource formats with dataset and
+    /// resource counts and total sizes.
+    pub async fn format_facet(
+        &self,
+        portal: Option<&str>,
+        limit: usize,
+    ) -> Result<Vec<FormatCount>, AppError> {
+        let rows: Vec<FormatCountRow> = sqlx::query_as(
+            r#"
+            SELECT format,
+                   COUNT(DISTINCT dataset_id) AS datasets,
+                   COUNT(*) AS resources,
+                   COALESCE(SUM(size), 0)::bigint AS total_size
+            FROM resources
+            WHERE format IS NOT NULL
+              AND ($2::text IS NULL
+                   OR dataset_id IN (SELECT id FROM datasets WHERE source_portal = $2))
+            GROUP BY format
+            ORDER BY datasets DESC, format
+            LIMIT $1
+            "#,
+        )
+        .bind(limit as i64)
+        .bind(portal)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(AppError::DatabaseError)?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| FormatCount {
+                format: row.format,
+                datasets: row.datasets,
+                resources: row.resources,
+                total_size: row.total_size,
+            })
+            .collect())
+    }
 }
 
 /// Helper struct for deserializing stats query results
@@ -284,6 +1253,53 @@ struct StatsRow {
     last_update: Option<DateTime<Utc>>,
 }
 
+/// Helper struct for deserializing format facet rows
+#[derive(sqlx::FromRow)]
+struct FormatCountRow {
+    format: String,
+    datasets: i64,
+    resources: i64,
+    total_size: i64,
+}
+
+/// Helper struct for deserializing language count rows
+#[derive(sqlx::FromRow)]
+struct LanguageCountRow {
+    language: Option<String>,
+    datasets: i64,
+}
+
+/// Helper struct for deserializing per-portal stats rows
+#[derive(sqlx::FromRow)]
+struct PortalStatsRow {
+    portal: String,
+    datasets: i64,
+    with_embeddings: i64,
+    last_update: Option<DateTime<Utc>>,
+    last_harvest_at: Option<DateTime<Utc>>,
+    harvests: i64,
+    failed_harvests: i64,
+}
+
+/// Helper struct for deserializing portal quality rows
+#[derive(sqlx::FromRow)]
+struct PortalQualityRow {
+    portal: String,
+    datasets: i64,
+    average: f64,
+    low: i64,
+}
+
+/// Helper struct for deserializing embedding model rows
+#[derive(sqlx::FromRow)]
+struct EmbeddingModelRow {
+    model: String,
+    datasets: i64,
+    portals: i64,
+    first_embedded_at: Option<DateTime<Utc>>,
+    last_embedded_at: Option<DateTime<Utc>>,
+}
+
 /// Helper struct for deserializing search query results
 #[derive(sqlx::FromRow)]
 struct SearchResultRow {
@@ -295,12 +1311,137 @@ struct SearchResultRow {
     description: Option<String>,
     embedding: Option<Vector>,
     metadata: Json<serde_json::Value>,
+    formats: Vec<String>,
     first_seen_at: DateTime<Utc>,
     last_updated_at: DateTime<Utc>,
     content_hash: Option<String>,
+    embedding_model: Option<String>,
+    embedded_at: Option<DateTime<Utc>>,
+    modified_at: Option<DateTime<Utc>>,
+    language: Option<String>,
+    quality: Option<f32>,
     similarity_score: f64,
 }
 
+impl From<SearchResultRow> for SearchResult {
+    fn from(row: SearchResultRow) -> Self {
+        SearchResult {
+            dataset: Dataset {
+                id: row.id,
+                original_id: row.original_id,
+                source_portal: row.source_portal,
+                url: row.url,
+                title: row.title,
+                description: row.description,
+                embedding: row.embedding,
+                metadata: row.metadata,
+                formats: row.formats,
+                first_seen_at: row.first_seen_at,
+                last_updated_at: row.last_updated_at,
+                content_hash: row.content_hash,
+                embedding_model: row.embedding_model,
+                embedded_at: row.embedded_at,
+                modified_at: row.modified_at,
+                language: row.language,
+                quality: row.quality,
+            },
+            similarity_score: row.similarity_score as f32,
+        }
+    }
+}
+
+/// Appends structured search filters as `AND ...` clauses.
+pub(crate) fn push_search_filters(
+    builder: &mut QueryBuilder<'_, Postgres>,
+    filters: &SearchFilters,
+) {
+    if let Some(portal) = &filters.portal {
+        builder.push(" AND source_portal = ");
+        builder.push_bind(portal.clone());
+    }
+    if let Some(theme) = &filters.theme {
+        // Containment on the whole document lets the jsonb_path_ops index apply
+        builder.push(" AND metadata @> ");
+        builder.push_bind(serde_json::json!({ "groups": [{ "name": theme }] }));
+    }
+    if let Some(model) = &filters.embedding_model {
+        builder.push(" AND embedding_model = ");
+        builder.push_bind(model.clone());
+    }
+    if let Some(format) = &filters.format {
+        // Array containment lets the GIN index on formats apply
+        builder.push(" AND formats @> ");
+        builder.push_bind(vec![format.clone()]);
+    }
+    if let Some(license) = &filters.license {
+        builder.push(" AND metadata @> ");
+        builder.push_bind(serde_json::json!({ "license_id": license }));
+    }
+    if let Some(after) = filters.updated_after {
+        builder.push(" AND modified_at >= ");
+        builder.push_bind(after);
+    }
+    if let Some(before) = filters.updated_before {
+        builder.push(" AND modified_at < ");
+        builder.push_bind(before);
+    }
+    if let Some(language) = &filters.language {
+        builder.push(" AND language = ");
+        builder.push_bind(language.clone());
+    }
+    if let Some(tag) = &filters.tag {
+        builder.push(
+            " AND id IN (SELECT dt.dataset_id FROM dataset_tags dt JOIN tags t ON t.id = dt.tag_id WHERE t.name = ",
+        );
+        builder.push_bind(tag.clone());
+        builder.push(")");
+    }
+    if let Some(organization) = &filters.organization {
+        builder.push(" AND organization_id IN (SELECT id FROM organizations WHERE name = ");
+        builder.push_bind(organization.clone());
+        builder.push(" OR lower(title) = lower(");
+        builder.push_bind(organization.clone());
+        builder.push("))");
+    }
+    for filter in &filters.metadata {
+        // A negated filter also keeps datasets where the path is missing
+        if filter.negated() {
+            builder.push(" AND NOT COALESCE(metadata @@ ");
+            builder.push_bind(filter.jsonpath());
+            builder.push("::jsonpath, FALSE)");
+        } else {
+            builder.push(" AND metadata @@ ");
+            builder.push_bind(filter.jsonpath());
+            builder.push("::jsonpath");
+        }
+    }
+    if let Some(bbox) = filters.bbox {
+        // Box overlap lets the GiST index on bbox apply
+        builder.push(" AND bbox && box(point(");
+        builder.push_bind(bbox.min_lon);
+        builder.push(", ");
+        builder.push_bind(bbox.min_lat);
+        builder.push("), point(");
+        builder.push_bind(bbox.max_lon);
+        builder.push(", ");
+        builder.push_bind(bbox.max_lat);
+        builder.push("))");
+    }
+}
+
+/// Appends a keyword match against the full-text document.
+fn push_keyword_filter(builder: &mut QueryBuilder<'_, Postgres>, tsquery: &str) {
+    builder.push(format!(" AND {} @@ to_tsquery('simple', ", FTS_DOCUMENT));
+    builder.push_bind(tsquery.to_string());
+    builder.push(")");
+}
+
+/// Orders fetched rows by the requested IDs, with `None` for missing ones.
+fn align_by_id(ids: &[Uuid], rows: Vec<Dataset>) -> Vec<Option<Dataset>> {
+    let by_id: HashMap<Uuid, Dataset> = rows.into_iter().map(|d| (d.id, d)).collect();
+    ids.iter().map(|id| by_id.get(id).cloned()).collect()
+}
+
 /// Helper struct for deserializing hash lookup query results
 #[derive(sqlx::FromRow)]
 struct HashRow {
@@ -308,6 +1449,32 @@ struct HashRow {
     content_hash: Option<String>,
 }
 
+/// Helper struct for deserializing hash backfill candidates
+#[derive(sqlx::FromRow)]
+struct BackfillRow {
+    id: Uuid,
+    title: String,
+    description: Option<String>,
+    metadata: Json<serde_json::Value>,
+}
+
+/// Helper struct for deserializing quality backfill candidates
+#[derive(sqlx::FromRow)]
+struct QualityBackfillRow {
+    id: Uuid,
+    description: Option<String>,
+    metadata: Json<serde_json::Value>,
+    modified_at: Option<DateTime<Utc>>,
+}
+
+/// Helper struct for deserializing sync state query results
+#[derive(sqlx::FromRow)]
+struct SyncStateRow {
+    original_id: String,
+    content_hash: Option<String>,
+    last_updated_at: DateTime<Utc>,
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -326,8 +1493,17 @@ mod tests {
             title: title.to_string(),
             description,
             embedding: Some(Vector::from(vec![0.1, 0.2, 0.3])),
+            embedding_model: Some("text-embedding-004".to_string()),
             metadata: json!({"key": "value"}),
+            formats: vec!["CSV".to_string()],
             content_hash,
+            modified_at: None,
+            language: None,
+            bbox: None,
+            quality: None,
+            tags: Vec::new(),
+            organization: None,
+            resources: Vec::new(),
         };
 
         assert_eq!(new_dataset.original_id, "test-id");
@@ -354,4 +1530,108 @@ mod tests {
         assert!(serialized.is_object());
         assert_eq!(serialized["organization"], "test-org");
     }
+
+    fn dataset(id: Uuid) -> Dataset {
+        Dataset {
+            id,
+            original_id: id.to_string(),
+            source_portal: "https://example.com".to_string(),
+            url: format!("https://example.com/dataset/{}", id),
+            title: "Test Dataset".to_string(),
+            description: None,
+            embedding: None,
+            metadata: Json(json!({})),
+            formats: Vec::new(),
+            first_seen_at: Utc::now(),
+            last_updated_at: Utc::now(),
+            content_hash: None,
+            embedding_model: None,
+            embedded_at: None,
+            modified_at: None,
+            language: None,
+            quality: None,
+        }
+    }
+
+    #[test]
+    fn test_align_by_id_keeps_input_order_and_reports_missing() {
+        let (a, b, missing) = (Uuid::new_v4(), Uuid::new_v4(), Uuid::new_v4());
+        let rows = vec![dataset(b), dataset(a)];
+
+        let aligned = align_by_id(&[a, missing, b, a], rows);
+        let ids: Vec<Option<Uuid>> = aligned.iter().map(|d| d.as_ref().map(|d| d.id)).collect();
+        assert_eq!(ids, vec![Some(a), None, Some(b), Some(a)]);
+    }
+
+    /// Migrated PostgreSQL database with pgvector at
+    /// `CERES_TEST_DATABASE_URL`; tests needing one are skipped without it.
+    async fn test_repository() -> Option<DatasetRepository> {
+        let url = std::env::var("CERES_TEST_DATABASE_URL").ok()?;
+        let repo = DatasetRepository::connect(&url, &DbConfig::default())
+            .await
+            .unwrap();
+        repo.run_migrations().await.unwrap();
+        Some(repo)
+    }
+
+    #[tokio::test]
+    async fn test_backfill_content_hashes_fills_missing_hashes() {
+        let Some(repo) = test_repository().await else {
+            return;
+        };
+        let portal = format!("https://backfill-{}.example.com", Uuid::new_v4());
+        let dimension = repo.embedding_dimension().await.unwrap().unwrap_or(768);
+        let mut ids = Vec::new();
+        for name in ["bus", "tram"] {
+            let title = format!("Fermate {}", name);
+            let new_dataset = NewDataset {
+                original_id: name.to_string(),
+                source_portal: portal.clone(),
+                url: format!("{}/dataset/{}", portal, name),
+                title: title.clone(),
+                description: Some("Fermate del trasporto pubblico".to_string()),
+                embedding: Some(Vector::from(vec![0.1; dimension])),
+                embedding_model: Some("test".to_string()),
+                metadata: json!({"name": name}),
+                formats: Vec::new(),
+                content_hash: NewDataset::compute_content_hash(&title, None),
+                modified_at: None,
+                language: None,
+                bbox: None,
+                quality: None,
+                tags: Vec::new(),
+                organization: None,
+                resources: Vec::new(),
+            };
+            ids.push(repo.upsert(&new_dataset).await.unwrap().id());
+        }
+        sqlx::query("UPDATE datasets SET content_hash = NULL WHERE source_portal = $1")
+            .bind(&portal)
+            .execute(&repo.pool)
+            .await
+            .unwrap();
+        assert!(repo.count_missing_hashes().await.unwrap() >= 2);
+
+        let fields = [HashField::Tags];
+        let mut after = Uuid::nil();
+        while let Some((last, _)) = repo
+            .backfill_content_hashes(after, 1, false, &fields)
+            .await
+            .unwrap()
+        {
+            after = last;
+        }
+        assert_eq!(repo.count_missing_hashes().await.unwrap(), 0);
+
+        for dataset in repo.get_many(&ids).await.unwrap().into_iter().flatten() {
+            let expected = content_hash(
+                &dataset.title,
+                dataset.description.as_deref(),
+                &dataset.metadata.0,
+                &fields,
+            );
+            assert_eq!(dataset.content_hash, Some(expected));
+        }
+        repo.delete_portal_datasets(&portal).await.unwrap();
+    }
 }
@@ -0,0 +1,58 @@
+//! Dataset resources.
+
+use ceres_core::error::AppError;
+use ceres_core::resources::DatasetResource;
+use sqlx::PgConnection;
+use uuid::Uuid;
+
+/// Replaces the resources of several datasets in two queries.
+pub(crate) async fn replace_resources(
+    conn: &mut PgConnection,
+    datasets: &[(Uuid, &[DatasetResource])],
+) -> Result<(), AppError> {
+    let dataset_ids: Vec<Uuid> = datasets.iter().map(|(id, _)| *id).collect();
+    sqlx::query("DELETE FROM resources WHERE dataset_id = ANY($1)")
+        .bind(&dataset_ids)
+        .execute(&mut *conn)
+        .await
+        .map_err(AppError::DatabaseError)?;
+
+    // One row per resource, numbered within its dataset
+    let (owners, resources): (Vec<(Uuid, i32)>, Vec<&DatasetResource>) = datasets
+        .iter()
+        .flat_map(|(id, resources)| {
+            resources
+                .iter()
+                .enumerate()
+                .map(move |(position, resource)| ((*id, position as i32), resource))
+        })
+        .unzip();
+    if resources.is_empty() {
+        return Ok(());
+    }
+
+    let column = |f: fn(&DatasetResource) -> Option<String>| -> Vec<Option<String>> {
+        resources.iter().map(|r| f(r)).collect()
+    };
+    let (resource_dataset_ids, positions): (Vec<Uuid>, Vec<i32>) = owners.into_iter().unzip();
+    let sizes: Vec<Option<i64>> = resources.iter().map(|r| r.size).collect();
+    sqlx::query(
+        r#"
+        INSERT INTO resources (dataset_id, position, ckan_id, name, format, mimetype, size, url)
+        SELECT * FROM unnest($1::uuid[], $2::int[], $3::text[], $4::text[], $5::text[], $6::text[], $7::bigint[], $8::text[])
+        "#,
+    )
+    .bind(&resource_dataset_ids)
+    .bind(&positions)
+    .bind(column(|r| r.ckan_id.clone()))
+    .bind(column(|r| r.name.clone()))
+    .bind(column(|r| r.format.clone()))
+    .bind(column(|r| r.mimetype.clone()))
+    .bind(&sizes)
+    .bind(column(|r| r.url.clone()))
+    .execute(&mut *conn)
+    .await
+    .map_err(AppError::DatabaseError)?;
+
+    Ok(())
+}
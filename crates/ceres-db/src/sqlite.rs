@@ -0,0 +1,2245 @@
+//! SQLite storage backend (feature `sqlite`).
+//!
+//! Runs harvest, search, export and stats on a single local file, for
+//! trying Ceres without PostgreSQL and pgvector:
+//! `DATABASE_URL=sqlite://ceres.db`. The schema lives in
+//! `migrations/sqlite` and is applied on first use.
+//!
+//! Embeddings are stored as little-endian `f32` BLOBs in the `datasets`
+//! table. With the [sqlite-vec](https://github.com/asg017/sqlite-vec)
+//! extension loaded (`SQLITE_VEC_PATH`), they are also indexed in the
+//! `dataset_vectors` `vec0` virtual table, and semantic search scores only
+//! the nearest datasets it returns. Without it, searches apply structured
+//! filters in SQL, then load every remaining embedding and score it in
+//! process, so search time grows linearly with the number of datasets;
+//! that is fine for the tens of thousands of datasets of a few portals.
+//! Either way scores are exact cosine similarities computed in process:
+//! metadata filters (`--where`) are evaluated there too, and the
+//! [`SearchStrategy`] argument is ignored.
+//!
+//! The `vec0` table is created for the dimension of the first embedding
+//! stored; embeddings of another dimension are only found by the scan.
+//! It is refilled on startup when its row count no longer matches the
+//! stored embeddings, as after writes made without the extension.
+//!
+//! Chunk embeddings (`--chunks`) live in `dataset_chunks` and are scanned
+//! the same way. Watches (`create_watch`, `list_watches` and
+//! `delete_watch_by_id`) are not supported and return
+//! `AppError::ConfigError`.
+
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
+
+use async_trait::async_trait;
+use ceres_core::chunks::{aggregate_scores, DatasetChunk, CHUNK_CANDIDATES_PER_RESULT};
+use ceres_core::error::AppError;
+use ceres_core::health::PortalHealth;
+use ceres_core::history::SearchRecord;
+use ceres_core::models::{
+    DatabaseStats, Dataset, EmbeddingModelCount, FormatCount, LanguageCount, NewDataset,
+    PortalQuality, PortalStats, SchemaMigration, SearchResult,
+};
+use ceres_core::quality::LOW_QUALITY;
+use ceres_core::search::{
+    keyword_terms, SearchFilters, SearchStrategy, HYBRID_CANDIDATES_PER_RESULT,
+};
+use ceres_core::sync::{HarvestCheckpoint, HarvestFailure, ReprocessingDecision, UpsertOutcome};
+use ceres_core::watch::Watch;
+use chrono::{DateTime, Utc};
+use futures::stream::{self, BoxStream, StreamExt, TryStreamExt};
+use pgvector::Vector;
+use sqlx::migrate::Migrator;
+use sqlx::sqlite::{SqliteConnectOptions, SqliteJournalMode, SqlitePool, SqlitePoolOptions};
+use sqlx::types::Json;
+use sqlx::{QueryBuilder, Sqlite};
+use uuid::Uuid;
+
+use crate::repository::{StreamState, STREAM_PAGE_SIZE};
+use crate::store::{
+    cosine_similarity, fuse_results, lexical_score, reprocessing_decisions, DatasetStore,
+};
+
+/// Migrations of the SQLite schema shipped with this build.
+pub static SQLITE_MIGRATOR: Migrator = sqlx::migrate!("../../migrations/sqlite");
+
+/// Column list for SELECT queries, matching [`DatasetRow`].
+const DATASET_COLUMNS: &str = "id, original_id, source_portal, url, title, description, embedding, metadata, formats, first_seen_at, last_updated_at, content_hash, embedding_model, embedded_at, modified_at, language, quality";
+
+/// Number of formats reported in the stats facet.
+const FORMAT_FACET_LIMIT: i64 = 10;
+
+/// Connections kept open; SQLite serializes writes anyway.
+const MAX_CONNECTIONS: u32 = 4;
+
+/// How long a write waits for another connection's write to finish.
+const BUSY_TIMEOUT: Duration = Duration::from_secs(30);
+
+/// Entry point of the sqlite-vec extension, whatever its file is called.
+const SQLITE_VEC_ENTRY_POINT: &str = "sqlite3_vec_init";
+
+/// Nearest neighbours read from the `vec0` index per search result, so
+/// filters can drop some and still leave enough.
+const VECTOR_CANDIDATES_PER_RESULT: usize = 10;
+
+/// Most neighbours a `vec0` KNN query may return.
+const VECTOR_MAX_CANDIDATES: usize = 4096;
+
+/// Dataset storage in a local SQLite database.
+///
+/// Without sqlite-vec every search scans the stored embeddings; with it
+/// (see [`connect_with_sqlite_vec`](Self::connect_with_sqlite_vec)) they are
+/// also indexed in the `dataset_vectors` `vec0` table. Watches
+/// (`create_watch`, `list_watches`, `delete_watch_by_id`) are not
+/// supported and return `AppError::ConfigError`.
+#[derive(Clone)]
+pub struct SqliteRepository {
+    pool: SqlitePool,
+    /// Set when sqlite-vec is loaded; holds the dimension of the
+    /// `dataset_vectors` table once it exists
+    vector_index: Option<Arc<OnceLock<usize>>>,
+}
+
+impl SqliteRepository {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self {
+            pool,
+            vector_index: None,
+        }
+    }
+
+    /// Opens the database at `url` (`sqlite://path/to/file.db`), creating
+    /// the file if it does not exist.
+    ///
+    /// The schema is not touched; see [`run_migrations`](DatasetStore::run_migrations).
+    pub async fn connect(url: &str) -> Result<Self, AppError> {
+        let pool = Self::open_pool(Self::connect_options(url)?).await?;
+        Ok(Self::new(pool))
+    }
+
+    /// Opens the database at `url` like [`connect`](Self::connect), loading
+    /// the sqlite-vec extension from `extension` (e.g. `./vec0.so`) into
+    /// every connection.
+    ///
+    /// Embeddings are then indexed in the `dataset_vectors` `vec0` table,
+    /// created for the dimension of the first embedding stored, and
+    /// semantic search reads its candidates from there.
+    pub async fn connect_with_sqlite_vec(url: &str, extension: &str) -> Result<Self, AppError> {
+        let options = Self::connect_options(url)?
+            .extension_with_entrypoint(extension.to_string(), SQLITE_VEC_ENTRY_POINT);
+        let pool = Self::open_pool(options).await?;
+        Ok(Self {
+            pool,
+            vector_index: Some(Arc::new(OnceLock::new())),
+        })
+    }
+
+    fn connect_options(url: &str) -> Result<SqliteConnectOptions, AppError> {
+        Ok(SqliteConnectOptions::from_str(url)
+            .map_err(AppError::DatabaseError)?
+            .create_if_missing(true)
+            .journal_mode(SqliteJournalMode::Wal)
+            .busy_timeout(BUSY_TIMEOUT))
+    }
+
+    async fn open_pool(options: SqliteConnectOptions) -> Result<SqlitePool, AppError> {
+        SqlitePoolOptions::new()
+            .max_connections(MAX_CONNECTIONS)
+            .connect_with(options)
+            .await
+            .map_err(AppError::DatabaseError)
+    }
+
+    /// Dimension of the `dataset_vectors` table, if it exists.
+    async fn vector_table_dimension(&self) -> Result<Option<usize>, AppError> {
+        let sql: Option<String> =
+            sqlx::query_scalar("SELECT sql FROM sqlite_master WHERE name = 'dataset_vectors'")
+                .fetch_optional(&self.pool)
+                .await
+                .map_err(AppError::DatabaseError)?;
+        Ok(sql.as_deref().and_then(vector_table_dimension))
+    }
+
+    /// Brings the `vec0` index in line with the stored embeddings.
+    ///
+    /// The table is created for the dimension of the stored embeddings if
+    /// it does not exist, and recreated once no embedding has its
+    /// dimension any more. It is refilled when its row count no longer
+    /// matches, as after writes made without sqlite-vec loaded.
+    async fn sync_vector_index(&self, dimension: &OnceLock<usize>) -> Result<(), AppError> {
+        let table = self.vector_table_dimension().await?;
+        let stored = self.embedding_dimension().await?;
+        let mut tx = self.pool.begin().await.map_err(AppError::DatabaseError)?;
+        let indexed = match (table, stored) {
+            (None, None) => return Ok(()),
+            (Some(indexed), None) => indexed,
+            (Some(indexed), Some(stored))
+                if indexed == stored || count_embedded(&mut tx, indexed).await? > 0 =>
+            {
+                indexed
+            }
+            (Some(_), Some(stored)) => {
+                sqlx::query("DROP TABLE dataset_vectors")
+                    .execute(&mut *tx)
+                    .await
+                    .map_err(AppError::DatabaseError)?;
+                create_vector_table(&mut tx, stored).await?;
+                stored
+            }
+            (None, Some(stored)) => {
+                create_vector_table(&mut tx, stored).await?;
+                stored
+            }
+        };
+
+        let vectors: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM dataset_vectors")
+            .fetch_one(&mut *tx)
+            .await
+            .map_err(AppError::DatabaseError)?;
+        if vectors != count_embedded(&mut tx, indexed).await? {
+            sqlx::query("DELETE FROM dataset_vectors")
+                .execute(&mut *tx)
+                .await
+                .map_err(AppError::DatabaseError)?;
+            sqlx::query(
+                r#"
+                INSERT INTO dataset_vectors (dataset_id, embedding)
+                SELECT hex(id), embedding FROM datasets WHERE length(embedding) = ?
+                "#,
+            )
+            .bind((indexed * size_of::<f32>()) as i64)
+            .execute(&mut *tx)
+            .await
+            .map_err(AppError::DatabaseError)?;
+        }
+        tx.commit().await.map_err(AppError::DatabaseError)?;
+
+        let _ = dimension.set(indexed);
+        Ok(())
+    }
+
+    /// Returns true if embeddings are indexed in a `vec0` table.
+    fn has_vector_table(&self) -> bool {
+        self.vector_index
+            .as_ref()
+            .is_some_and(|dimension| dimension.get().is_some())
+    }
+
+    /// The datasets nearest to `query` in the `vec0` index, or `None` if
+    /// there is no index for vectors of its dimension.
+    async fn nearest_datasets(
+        &self,
+        query: &Vector,
+        candidates: usize,
+    ) -> Result<Option<Vec<Uuid>>, AppError> {
+        let dimension = self.vector_index.as_ref().and_then(|cell| cell.get());
+        if dimension != Some(&query.as_slice().len()) {
+            return Ok(None);
+        }
+        let keys: Vec<String> = sqlx::query_scalar(
+            "SELECT dataset_id FROM dataset_vectors WHERE embedding MATCH ?1 AND k = ?2",
+        )
+        .bind(encode_vector(query))
+        .bind(candidates as i64)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(AppError::DatabaseError)?;
+        Ok(Some(
+            keys.iter()
+                .filter_map(|key| Uuid::parse_str(key).ok())
+                .collect(),
+        ))
+    }
+
+    /// Scores the datasets passing `filters` and returns the `limit` best
+    /// as (dataset ID, score), best first.
+    ///
+    /// With `embedded`, only datasets with an embedding are scored. Datasets
+    /// `score` returns `None` for are left out.
+    ///
+    /// With `among`, only those datasets are scored.
+    async fn top_scored(
+        &self,
+        filters: &SearchFilters,
+        embedded: bool,
+        among: Option<&[Uuid]>,
+        limit: usize,
+        score: impl Fn(&CandidateRow) -> Option<f32> + Send,
+    ) -> Result<Vec<(Uuid, f32)>, AppError> {
+        let mut builder = QueryBuilder::<Sqlite>::new("SELECT id, title, description");
+        builder.push(if embedded {
+            ", embedding"
+        } else {
+            ", NULL AS embedding"
+        });
+        builder.push(if filters.metadata.is_empty() {
+            ", NULL AS metadata"
+        } else {
+            ", metadata"
+        });
+        builder.push(" FROM datasets WHERE TRUE");
+        if embedded {
+            builder.push(" AND embedding IS NOT NULL");
+        }
+        if let Some(ids) = among {
+            builder.push(" AND id IN (");
+            let mut separated = builder.separated(", ");
+            for id in ids {
+                separated.push_bind(*id);
+            }
+            builder.push(")");
+        }
+        push_search_filters(&mut builder, filters);
+
+        let mut scored = Vec::new();
+        let mut rows = builder.build_query_as::<CandidateRow>().fetch(&self.pool);
+        while let Some(row) = rows.try_next().await.map_err(AppError::DatabaseError)? {
+            let passes = match &row.metadata {
+                Some(metadata) => filters.metadata.iter().all(|f| f.matches(metadata)),
+                None => true,
+            };
+            if let Some(score) = passes.then(|| score(&row)).flatten() {
+                scored.push((row.id, score));
+            }
+        }
+
+        scored.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        scored.truncate(limit);
+        Ok(scored)
+    }
+
+    /// Scores the chunks of the datasets passing `filters` against `query`
+    /// and returns the `limit` best as (dataset ID, score), best first.
+    ///
+    /// Only chunks embedded with `filters.embedding_model` are compared.
+    async fn top_scored_chunks(
+        &self,
+        query: &[f32],
+        limit: usize,
+        filters: &SearchFilters,
+    ) -> Result<Vec<(Uuid, f32)>, AppError> {
+        let mut builder = QueryBuilder::<Sqlite>::new("SELECT dataset_id, embedding");
+        builder.push(if filters.metadata.is_empty() {
+            ", NULL AS metadata"
+        } else {
+            ", (SELECT metadata FROM datasets WHERE id = dataset_id) AS metadata"
+        });
+        builder.push(" FROM dataset_chunks WHERE TRUE");
+        if let Some(model) = &filters.embedding_model {
+            builder.push(" AND embedding_model = ");
+            builder.push_bind(model.clone());
+        }
+        let dataset_filters = SearchFilters {
+            embedding_model: None,
+            ..filters.clone()
+        };
+        if !dataset_filters.is_empty() {
+            builder.push(" AND dataset_id IN (SELECT id FROM datasets WHERE TRUE");
+            push_search_filters(&mut builder, &dataset_filters);
+            builder.push(")");
+        }
+
+        let mut scored = Vec::new();
+        let mut rows = builder.build_query_as::<ChunkRow>().fetch(&self.pool);
+        while let Some(row) = rows.try_next().await.map_err(AppError::DatabaseError)? {
+            let passes = match &row.metadata {
+                Some(metadata) => filters.metadata.iter().all(|f| f.matches(metadata)),
+                None => true,
+            };
+            if passes {
+                let score = cosine_similarity(query, &decode_floats(&row.embedding));
+                scored.push((row.dataset_id, score));
+            }
+        }
+
+        scored.sort_by(|a, b| b.1.total_cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+        scored.truncate(limit);
+        Ok(scored)
+    }
+
+    /// Loads the datasets of `scored`, keeping its order.
+    async fn search_results(
+        &self,
+        scored: Vec<(Uuid, f32)>,
+    ) -> Result<Vec<SearchResult>, AppError> {
+        let ids: Vec<Uuid> = scored.iter().map(|(id, _)| *id).collect();
+        let mut datasets: HashMap<Uuid, Dataset> = self
+            .datasets_by_ids(&ids, &SearchFilters::default())
+            .await?
+            .into_iter()
+            .map(|dataset| (dataset.id, dataset))
+            .collect();
+        Ok(scored
+            .into_iter()
+            .filter_map(|(id, score)| {
+                datasets.remove(&id).map(|dataset| SearchResult {
+                    dataset,
+                    similarity_score: score,
+                })
+            })
+            .collect())
+    }
+
+    /// One page of [`stream_all`](DatasetStore::stream_all): up to `limit`
+    /// rows ordered before the `after` key, before metadata filters.
+    async fn list_page(
+        &self,
+        filters: &SearchFilters,
+        after: Option<(DateTime<Utc>, Uuid)>,
+        limit: usize,
+    ) -> Result<Vec<DatasetRow>, AppError> {
+        let mut builder = QueryBuilder::<Sqlite>::new(format!(
+            "SELECT {} FROM datasets WHERE TRUE",
+            DATASET_COLUMNS
+        ));
+        push_search_filters(&mut builder, filters);
+        if let Some((last_updated_at, id)) = after {
+            builder.push(" AND (last_updated_at, id) < (");
+            builder.push_bind(last_updated_at);
+            builder.push(", ");
+            builder.push_bind(id);
+            builder.push(")");
+        }
+        builder.push(" ORDER BY last_updated_at DESC, id DESC LIMIT ");
+        builder.push_bind(limit as i64);
+
+        builder
+            .build_query_as()
+            .fetch_all(&self.pool)
+            .await
+            .map_err(AppError::DatabaseError)
+    }
+
+    /// Returns true if a table exists.
+    async fn table_exists(&self, table: &str) -> Result<bool, AppError> {
+        sqlx::query_scalar(
+            "SELECT EXISTS (SELECT 1 FROM sqlite_master WHERE type = 'table' AND name = ?)",
+        )
+        .bind(table)
+        .fetch_one(&self.pool)
+        .await
+        .map_err(AppError::DatabaseError)
+    }
+
+    async fn portal_stats(&self, portal: Option<&str>) -> Result<Vec<PortalStats>, AppError> {
+        let rows: Vec<PortalStatsRow> = sqlx::query_as(
+            r#"
+            SELECT d.source_portal AS portal,
+                   COUNT(*) AS datasets,
+                   COUNT(d.embedding) AS with_embeddings,
+                   MAX(d.last_updated_at) AS last_update,
+                   h.last_success_at AS last_harvest_at,
+                   COALESCE(h.harvests, 0) AS harvests,
+                   COALESCE(h.failed_harvests, 0) AS failed_harvests
+            FROM datasets d
+            LEFT JOIN portal_health h ON rtrim(h.portal_url, '/') = rtrim(d.source_portal, '/')
+            WHERE ?1 IS NULL OR d.source_portal = ?1
+            GROUP BY d.source_portal, h.portal_url
+            ORDER BY COALESCE(h.last_success_at, MAX(d.last_updated_at)) NULLS FIRST, portal
+            "#,
+        )
+        .bind(portal)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(AppError::DatabaseError)?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| PortalStats {
+                portal: row.portal,
+                datasets: row.datasets,
+                with_embeddings: row.with_embeddings,
+                last_update: row.last_update,
+                last_harvest_at: row.last_harvest_at,
+                harvests: row.harvests,
+                failed_harvests: row.failed_harvests,
+            })
+            .collect())
+    }
+
+    async fn format_facet(&self, portal: Option<&str>) -> Result<Vec<FormatCount>, AppError> {
+        let rows: Vec<(String, i64, i64, i64)> = sqlx::query_as(
+            r#"
+            SELECT format,
+                   COUNT(DISTINCT dataset_id) AS datasets,
+                   COUNT(*) AS resources,
+                   COALESCE(SUM(size), 0) AS total_size
+            FROM resources
+            WHERE format IS NOT NULL
+              AND (?2 IS NULL
+                   OR dataset_id IN (SELECT id FROM datasets WHERE source_portal = ?2))
+            GROUP BY format
+            ORDER BY datasets DESC, format
+            LIMIT ?1
+            "#,
+        )
+        .bind(FORMAT_FACET_LIMIT)
+        .bind(portal)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(AppError::DatabaseError)?;
+
+        Ok(rows
+            .into_iter()
+            .map(|(format, datasets, resources, total_size)| FormatCount {
+                format,
+                datasets,
+                resources,
+                total_size,
+            })
+            .collect())
+    }
+
+    async fn language_counts(&self, portal: Option<&str>) -> Result<Vec<LanguageCount>, AppError> {
+        let rows: Vec<(Option<String>, i64)> = sqlx::query_as(
+            r#"
+            SELECT language, COUNT(*) AS datasets
+            FROM datasets
+            WHERE ?1 IS NULL OR source_portal = ?1
+            GROUP BY language
+            ORDER BY language IS NULL, datasets DESC, language
+            "#,
+        )
+        .bind(portal)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(AppError::DatabaseError)?;
+
+        Ok(rows
+            .into_iter()
+            .map(|(language, datasets)| LanguageCount { language, datasets })
+            .collect())
+    }
+
+    async fn portal_quality(&self, portal: Option<&str>) -> Result<Vec<PortalQuality>, AppError> {
+        let rows: Vec<(String, i64, f64, i64)> = sqlx::query_as(
+            r#"
+            SELECT source_portal AS portal,
+                   COUNT(*) AS datasets,
+                   AVG(quality) AS average,
+                   COUNT(*) FILTER (WHERE quality < ?1) AS low
+            FROM datasets
+            WHERE quality IS NOT NULL
+              AND (?2 IS NULL OR source_portal = ?2)
+            GROUP BY source_portal
+            ORDER BY average, portal
+            "#,
+        )
+        .bind(LOW_QUALITY)
+        .bind(portal)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(AppError::DatabaseError)?;
+
+        Ok(rows
+            .into_iter()
+            .map(|(portal, datasets, average, low)| PortalQuality {
+                portal,
+                datasets,
+                average,
+                low,
+            })
+            .collect())
+    }
+
+    async fn embedding_model_counts(
+        &self,
+        portal: Option<&str>,
+    ) -> Result<Vec<EmbeddingModelCount>, AppError> {
+        let rows: Vec<EmbeddingModelRow> = sqlx::query_as(
+            r#"
+            SELECT embedding_model AS model,
+                   COUNT(*) AS datasets,
+                   COUNT(DISTINCT source_portal) AS portals,
+                   MIN(embedded_at) AS first_embedded_at,
+                   MAX(embedded_at) AS last_embedded_at
+            FROM datasets
+            WHERE embedding IS NOT NULL AND embedding_model IS NOT NULL
+              AND (?1 IS NULL OR source_portal = ?1)
+            GROUP BY embedding_model
+            ORDER BY datasets DESC, model
+            "#,
+        )
+        .bind(portal)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(AppError::DatabaseError)?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| EmbeddingModelCount {
+                model: row.model,
+                datasets: row.datasets,
+                portals: row.portals,
+                first_embedded_at: row.first_embedded_at,
+                last_embedded_at: row.last_embedded_at,
+            })
+            .collect())
+    }
+}
+
+#[async_trait]
+impl DatasetStore for SqliteRepository {
+    async fn embedding_dimension(&self) -> Result<Option<usize>, AppError> {
+        let bytes: Option<i64> = sqlx::query_scalar(
+            "SELECT length(embedding) FROM datasets WHERE embedding IS NOT NULL LIMIT 1",
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(AppError::DatabaseError)?;
+
+        Ok(bytes.map(|bytes| bytes as usize / size_of::<f32>()))
+    }
+
+    async fn pending_migrations(&self) -> Result<Vec<SchemaMigration>, AppError> {
+        let applied: Vec<i64> = if self.table_exists("_sqlx_migrations").await? {
+            sqlx::query_scalar("SELECT version FROM _sqlx_migrations WHERE success")
+                .fetch_all(&self.pool)
+                .await
+                .map_err(AppError::DatabaseError)?
+        } else {
+            Vec::new()
+        };
+
+        Ok(SQLITE_MIGRATOR
+            .iter()
+            .filter(|m| !m.migration_type.is_down_migration())
+            .filter(|m| !applied.contains(&m.version))
+            .map(|m| SchemaMigration {
+                version: m.version,
+                description: m.description.to_string(),
+            })
+            .collect())
+    }
+
+    async fn run_migrations(&self) -> Result<Vec<SchemaMigration>, AppError> {
+        let pending = self.pending_migrations().await?;
+        SQLITE_MIGRATOR
+            .run(&self.pool)
+            .await
+            .map_err(|e| AppError::MigrationError(e.to_string()))?;
+        if let Some(dimension) = &self.vector_index {
+            self.sync_vector_index(dimension).await?;
+        }
+        Ok(pending)
+    }
+
+    async fn get_hashes_for_portal(
+        &self,
+        portal_url: &str,
+        embedding_model: &str,
+    ) -> Result<HashMap<String, Option<String>>, AppError> {
+        let rows: Vec<(String, Option<String>)> = sqlx::query_as(
+            r#"
+            SELECT original_id,
+                   CASE WHEN embedding_model IS ?2 THEN content_hash END AS content_hash
+            FROM datasets
+            WHERE source_portal = ?1
+            "#,
+        )
+        .bind(portal_url)
+        .bind(embedding_model)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(AppError::DatabaseError)?;
+
+        Ok(rows.into_iter().collect())
+    }
+
+    async fn datasets_needing_processing(
+        &self,
+        portal_url: &str,
+        embedding_model: &str,
+        candidates: &[(String, String)],
+    ) -> Result<HashMap<String, ReprocessingDecision>, AppError> {
+        // The batch travels as a JSON array of [original_id, hash] pairs
+        let rows: Vec<(String, bool, Option<String>, String)> = sqlx::query_as(
+            r#"
+            WITH c(original_id, content_hash) AS (
+                SELECT json_extract(value, '$[0]'), json_extract(value, '$[1]')
+                FROM json_each(?3)
+            )
+            SELECT c.original_id,
+                   d.id IS NOT NULL,
+                   CASE WHEN d.embedding_model IS ?2 THEN d.content_hash END,
+                   c.content_hash
+            FROM c
+            LEFT JOIN datasets d ON d.source_portal = ?1 AND d.original_id = c.original_id
+            WHERE d.id IS NULL
+               OR d.embedding_model IS NOT ?2
+               OR d.content_hash IS NOT c.content_hash
+            "#,
+        )
+        .bind(portal_url)
+        .bind(embedding_model)
+        .bind(Json(candidates))
+        .fetch_all(&self.pool)
+        .await
+        .map_err(AppError::DatabaseError)?;
+
+        Ok(reprocessing_decisions(rows))
+    }
+
+    async fn update_timestamp_only(
+        &self,
+        portal_url: &str,
+        original_id: &str,
+    ) -> Result<bool, AppError> {
+        let result = sqlx::query(
+            "UPDATE datasets SET last_updated_at = ?1 WHERE source_portal = ?2 AND original_id = ?3",
+        )
+        .bind(Utc::now())
+        .bind(portal_url)
+        .bind(original_id)
+        .execute(&self.pool)
+        .await
+        .map_err(AppError::DatabaseError)?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    async fn upsert(&self, new_data: &NewDataset) -> Result<UpsertOutcome, AppError> {
+        let new_id = Uuid::new_v4();
+        let bbox = new_data.bbox;
+        let organization = new_data.organization.as_ref();
+        let mut tx = self.pool.begin().await.map_err(AppError::DatabaseError)?;
+
+        let id: Uuid = sqlx::query_scalar(
+            r#"
+            INSERT INTO datasets (
+                id, original_id, source_portal, url, title, description,
+                embedding, embedding_model, embedded_at,
+                metadata, formats, tags, organization_name, organization_title,
+                content_hash, modified_at, language, quality,
+                bbox_min_lon, bbox_min_lat, bbox_max_lon, bbox_max_lat,
+                first_seen_at, last_updated_at
+            )
+            VALUES (
+                ?1, ?2, ?3, ?4, ?5, ?6,
+                ?7, ?8, CASE WHEN ?7 IS NOT NULL THEN ?23 END,
+                ?9, ?10, ?11, ?12, ?13,
+                ?14, ?15, ?16, ?17,
+                ?18, ?19, ?20, ?21,
+                ?22, ?23
+            )
+            ON CONFLICT (source_portal, original_id)
+            DO UPDATE SET
+                title = excluded.title,
+                description = excluded.description,
+                url = excluded.url,
+                embedding = COALESCE(excluded.embedding, datasets.embedding),
+                embedding_model = CASE
+                    WHEN excluded.embedding IS NOT NULL THEN excluded.embedding_model
+                    ELSE datasets.embedding_model
+                END,
+                embedded_at = CASE
+                    WHEN excluded.embedding IS NOT NULL THEN excluded.embedded_at
+                    ELSE datasets.embedded_at
+                END,
+                metadata = excluded.metadata,
+                formats = excluded.formats,
+                tags = excluded.tags,
+                organization_name = excluded.organization_name,
+                organization_title = excluded.organization_title,
+                content_hash = excluded.content_hash,
+                modified_at = excluded.modified_at,
+                language = excluded.language,
+                quality = excluded.quality,
+                bbox_min_lon = excluded.bbox_min_lon,
+                bbox_min_lat = excluded.bbox_min_lat,
+                bbox_max_lon = excluded.bbox_max_lon,
+                bbox_max_lat = excluded.bbox_max_lat,
+                last_updated_at = excluded.last_updated_at
+            RETURNING id
+            "#,
+        )
+        .bind(new_id)
+        .bind(&new_data.original_id)
+        .bind(&new_data.source_portal)
+        .bind(&new_data.url)
+        .bind(&new_data.title)
+        .bind(&new_data.description)
+        .bind(new_data.embedding.as_ref().map(encode_vector))
+        .bind(&new_data.embedding_model)
+        .bind(Json(&new_data.metadata))
+        .bind(Json(&new_data.formats))
+        .bind(Json(&new_data.tags))
+        .bind(organization.map(|o| o.name.as_str()))
+        .bind(organization.and_then(|o| o.title.as_deref()))
+        .bind(&new_data.content_hash)
+        .bind(new_data.modified_at)
+        .bind(&new_data.language)
+        .bind(new_data.quality)
+        .bind(bbox.map(|b| b.min_lon))
+        .bind(bbox.map(|b| b.min_lat))
+        .bind(bbox.map(|b| b.max_lon))
+        .bind(bbox.map(|b| b.max_lat))
+        .bind(Utc::now())
+        .bind(Utc::now())
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(AppError::DatabaseError)?;
+
+        let mut created_index = None;
+        if let (Some(index), Some(embedding)) = (&self.vector_index, &new_data.embedding) {
+            let dimension = match index.get() {
+                Some(dimension) => *dimension,
+                None => {
+                    let dimension = embedding.as_slice().len();
+                    create_vector_table(&mut tx, dimension).await?;
+                    created_index = Some(dimension);
+                    dimension
+                }
+            };
+            // Embeddings of another dimension are left to the exact scan
+            if embedding.as_slice().len() == dimension {
+                delete_vectors(&mut tx, &[id]).await?;
+                sqlx::query(
+                    "INSERT INTO dataset_vectors (dataset_id, embedding) VALUES (hex(?), ?)",
+                )
+                .bind(id)
+                .bind(encode_vector(embedding))
+                .execute(&mut *tx)
+                .await
+                .map_err(AppError::DatabaseError)?;
+            }
+        }
+
+        sqlx::query("DELETE FROM resources WHERE dataset_id = ?")
+            .bind(id)
+            .execute(&mut *tx)
+            .await
+            .map_err(AppError::DatabaseError)?;
+        for (position, resource) in new_data.resources.iter().enumerate() {
+            sqlx::query(
+                r#"
+                INSERT INTO resources (dataset_id, position, ckan_id, name, format, mimetype, size, url)
+                VALUES (?, ?, ?, ?, ?, ?, ?, ?)
+                "#,
+            )
+            .bind(id)
+            .bind(position as i64)
+            .bind(&resource.ckan_id)
+            .bind(&resource.name)
+            .bind(&resource.format)
+            .bind(&resource.mimetype)
+            .bind(resource.size)
+            .bind(&resource.url)
+            .execute(&mut *tx)
+            .await
+            .map_err(AppError::DatabaseError)?;
+        }
+        tx.commit().await.map_err(AppError::DatabaseError)?;
+        if let (Some(index), Some(dimension)) = (&self.vector_index, created_index) {
+            let _ = index.set(dimension);
+        }
+
+        // An update keeps the stored ID
+        Ok(if id == new_id {
+            UpsertOutcome::Created(id)
+        } else {
+            UpsertOutcome::Updated(id)
+        })
+    }
+
+    async fn cached_embedding(
+        &self,
+        content_hash: &str,
+        model: &str,
+    ) -> Result<Option<Vector>, AppError> {
+        let bytes: Option<Vec<u8>> = sqlx::query_scalar(
+            "SELECT embedding FROM embedding_cache WHERE content_hash = ? AND embedding_model = ?",
+        )
+        .bind(content_hash)
+        .bind(model)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(AppError::DatabaseError)?;
+
+        Ok(bytes.map(|bytes| decode_vector(&bytes)))
+    }
+
+    async fn cache_embedding(
+        &self,
+        content_hash: &str,
+        model: &str,
+        embedding: &Vector,
+    ) -> Result<(), AppError> {
+        sqlx::query(
+            r#"
+            INSERT INTO embedding_cache (content_hash, embedding_model, embedding)
+            VALUES (?, ?, ?)
+            ON CONFLICT (content_hash, embedding_model) DO NOTHING
+            "#,
+        )
+        .bind(content_hash)
+        .bind(model)
+        .bind(encode_vector(embedding))
+        .execute(&self.pool)
+        .await
+        .map_err(AppError::DatabaseError)?;
+
+        Ok(())
+    }
+
+    async fn get_chunk_hashes_for_portal(
+        &self,
+        portal_url: &str,
+        embedding_model: &str,
+    ) -> Result<HashMap<String, (Uuid, Option<String>)>, AppError> {
+        // Chunks embedded by another model are reported without a hash,
+        // so the next sync re-embeds them
+        let rows: Vec<(String, Uuid, Option<String>)> = sqlx::query_as(
+            r#"
+            SELECT
+                original_id,
+                id,
+                CASE WHEN EXISTS (
+                    SELECT 1 FROM dataset_chunks c
+                    WHERE c.dataset_id = d.id AND c.embedding_model <> ?2
+                ) THEN NULL ELSE chunks_hash END
+            FROM datasets d
+            WHERE source_portal = ?1
+            "#,
+        )
+        .bind(portal_url)
+        .bind(embedding_model)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(AppError::DatabaseError)?;
+
+        Ok(rows
+            .into_iter()
+            .map(|(original_id, id, chunks_hash)| (original_id, (id, chunks_hash)))
+            .collect())
+    }
+
+    async fn replace_dataset_chunks(
+        &self,
+        dataset_id: Uuid,
+        chunks: &[(DatasetChunk, Vector)],
+        embedding_model: &str,
+        chunks_hash: Option<&str>,
+    ) -> Result<(), AppError> {
+        let mut tx = self.pool.begin().await.map_err(AppError::DatabaseError)?;
+
+        sqlx::query("DELETE FROM dataset_chunks WHERE dataset_id = ?")
+            .bind(dataset_id)
+            .execute(&mut *tx)
+            .await
+            .map_err(AppError::DatabaseError)?;
+        for (position, (chunk, embedding)) in chunks.iter().enumerate() {
+            sqlx::query(
+                r#"
+                INSERT INTO dataset_chunks (dataset_id, position, kind, text, embedding, embedding_model)
+                VALUES (?, ?, ?, ?, ?, ?)
+                "#,
+            )
+            .bind(dataset_id)
+            .bind(position as i64)
+            .bind(chunk.kind.as_str())
+            .bind(&chunk.text)
+            .bind(encode_vector(embedding))
+            .bind(embedding_model)
+            .execute(&mut *tx)
+            .await
+            .map_err(AppError::DatabaseError)?;
+        }
+        sqlx::query("UPDATE datasets SET chunks_hash = ?2 WHERE id = ?1")
+            .bind(dataset_id)
+            .bind(chunks_hash)
+            .execute(&mut *tx)
+            .await
+            .map_err(AppError::DatabaseError)?;
+
+        tx.commit().await.map_err(AppError::DatabaseError)
+    }
+
+    async fn list_portal_health(&self) -> Result<Vec<PortalHealth>, AppError> {
+        let rows: Vec<PortalHealthRow> = sqlx::query_as(
+            r#"
+            SELECT portal_url, consecutive_failures, last_error,
+                   last_failure_at, last_success_at, quarantined_until,
+                   harvests, failed_harvests
+            FROM portal_health
+            ORDER BY portal_url
+            "#,
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(AppError::DatabaseError)?;
+
+        Ok(rows.into_iter().map(PortalHealth::from).collect())
+    }
+
+    async fn get_portal_health(&self, portal_url: &str) -> Result<PortalHealth, AppError> {
+        let row: Option<PortalHealthRow> = sqlx::query_as(
+            r#"
+            SELECT portal_url, consecutive_failures, last_error,
+                   last_failure_at, last_success_at, quarantined_until,
+                   harvests, failed_harvests
+            FROM portal_health
+            WHERE portal_url = ?
+            "#,
+        )
+        .bind(portal_url)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(AppError::DatabaseError)?;
+
+        Ok(row
+            .map(PortalHealth::from)
+            .unwrap_or_else(|| PortalHealth::new(portal_url)))
+    }
+
+    async fn save_portal_health(&self, health: &PortalHealth) -> Result<(), AppError> {
+        sqlx::query(
+            r#"
+            INSERT OR REPLACE INTO portal_health (
+                portal_url, consecutive_failures, last_error,
+                last_failure_at, last_success_at, quarantined_until,
+                harvests, failed_harvests
+            )
+            VALUES (?, ?, ?, ?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(&health.portal_url)
+        .bind(health.consecutive_failures as i64)
+        .bind(&health.last_error)
+        .bind(health.last_failure_at)
+        .bind(health.last_success_at)
+        .bind(health.quarantined_until)
+        .bind(health.harvests as i64)
+        .bind(health.failed_harvests as i64)
+        .execute(&self.pool)
+        .await
+        .map_err(AppError::DatabaseError)?;
+
+        Ok(())
+    }
+
+    /// Scores embedded datasets passing `filters` by cosine similarity;
+    /// `query_text` and `strategy` are not needed since every score is
+    /// exact.
+    ///
+    /// With sqlite-vec, only the nearest datasets in the `vec0` index are
+    /// scored, unless filters leave fewer than `limit` of them; otherwise
+    /// every embedded dataset is.
+    async fn search_with_filters(
+        &self,
+        query_vector: Vector,
+        _query_text: &str,
+        limit: usize,
+        filters: &SearchFilters,
+        _strategy: SearchStrategy,
+    ) -> Result<Vec<SearchResult>, AppError> {
+        let query = query_vector.to_vec();
+        let score = |row: &CandidateRow| {
+            row.embedding
+                .as_deref()
+                .map(|bytes| cosine_similarity(&query, &decode_floats(bytes)))
+        };
+
+        let candidates = (limit * VECTOR_CANDIDATES_PER_RESULT).min(VECTOR_MAX_CANDIDATES);
+        if let Some(nearest) = self.nearest_datasets(&query_vector, candidates).await? {
+            let scored = self
+                .top_scored(filters, true, Some(&nearest), limit, &score)
+                .await?;
+            // Fewer neighbours than asked for means the index was exhausted
+            if scored.len() == limit || nearest.len() < candidates {
+                return self.search_results(scored).await;
+            }
+        }
+
+        let scored = self.top_scored(filters, true, None, limit, &score).await?;
+        self.search_results(scored).await
+    }
+
+    /// Ranks datasets by the query terms found in their title (weighted
+    /// double) and description, ignoring the embedding model filter.
+    async fn text_search(
+        &self,
+        query_text: &str,
+        limit: usize,
+        filters: &SearchFilters,
+    ) -> Result<Vec<SearchResult>, AppError> {
+        let filters = SearchFilters {
+            embedding_model: None,
+            ..filters.clone()
+        };
+        let terms = keyword_terms(query_text);
+        if terms.is_empty() {
+            return Ok(Vec::new());
+        }
+        let scored = self
+            .top_scored(&filters, false, None, limit, |row| {
+                lexical_score(&terms, &row.title, row.description.as_deref())
+            })
+            .await?;
+        self.search_results(scored).await
+    }
+
+    async fn hybrid_search(
+        &self,
+        query_vector: Vector,
+        query_text: &str,
+        limit: usize,
+        filters: &SearchFilters,
+        strategy: SearchStrategy,
+    ) -> Result<Vec<SearchResult>, AppError> {
+        let candidates = limit * HYBRID_CANDIDATES_PER_RESULT;
+        let semantic = self
+            .search_with_filters(query_vector, query_text, candidates, filters, strategy)
+            .await?;
+        let lexical = self.text_search(query_text, candidates, filters).await?;
+        Ok(fuse_results(semantic, lexical, limit))
+    }
+
+    /// Scores each dataset by its best-matching vector, its own or one of
+    /// its chunks', scanning every chunk of the datasets passing `filters`.
+    async fn search_with_chunks(
+        &self,
+        query_vector: Vector,
+        query_text: &str,
+        limit: usize,
+        filters: &SearchFilters,
+        strategy: SearchStrategy,
+    ) -> Result<Vec<SearchResult>, AppError> {
+        let query = query_vector.to_vec();
+        let direct = self
+            .search_with_filters(query_vector, query_text, limit, filters, strategy)
+            .await?;
+        let chunk_hits = self
+            .top_scored_chunks(&query, limit * CHUNK_CANDIDATES_PER_RESULT, filters)
+            .await?;
+
+        let top: Vec<(Uuid, f32)> = aggregate_scores(
+            direct
+                .iter()
+                .map(|r| (r.dataset.id, r.similarity_score))
+                .chain(chunk_hits),
+        )
+        .into_iter()
+        .take(limit)
+        .collect();
+        self.search_results(top).await
+    }
+
+    fn stream_all<'a>(
+        &'a self,
+        filters: &'a SearchFilters,
+        limit: Option<usize>,
+    ) -> BoxStream<'a, Result<Dataset, AppError>> {
+        let start: StreamState = Some((None, limit.unwrap_or(usize::MAX)));
+        stream::try_unfold(start, move |state| async move {
+            let Some((after, remaining)) = state.filter(|&(_, remaining)| remaining > 0) else {
+                return Ok::<_, AppError>(None);
+            };
+            // Metadata filters run in process, so a page may keep fewer
+            // datasets than it reads
+            let page_size = if filters.metadata.is_empty() {
+                remaining.min(STREAM_PAGE_SIZE)
+            } else {
+                STREAM_PAGE_SIZE
+            };
+            let rows = self.list_page(filters, after, page_size).await?;
+            let last_key = match rows.last() {
+                Some(last) if rows.len() == page_size => Some((last.last_updated_at, last.id)),
+                _ => None,
+            };
+            let page: Vec<Dataset> = rows
+                .into_iter()
+                .map(Dataset::from)
+                .filter(|dataset| {
+                    filters
+                        .metadata
+                        .iter()
+                        .all(|f| f.matches(&dataset.metadata))
+                })
+                .take(remaining)
+                .collect();
+            let next = last_key.map(|key| (Some(key), remaining - page.len()));
+            Ok(Some((page, next)))
+        })
+        .map_ok(|page| stream::iter(page.into_iter().map(Ok)))
+        .try_flatten()
+        .boxed()
+    }
+
+    async fn datasets_by_ids(
+        &self,
+        ids: &[Uuid],
+        filters: &SearchFilters,
+    ) -> Result<Vec<Dataset>, AppError> {
+        if ids.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let mut builder = QueryBuilder::<Sqlite>::new(format!(
+            "SELECT {} FROM datasets WHERE id IN (",
+            DATASET_COLUMNS
+        ));
+        let mut separated = builder.separated(", ");
+        for id in ids {
+            separated.push_bind(*id);
+        }
+        builder.push(")");
+        push_search_filters(&mut builder, filters);
+        let rows: Vec<DatasetRow> = builder
+            .build_query_as()
+            .fetch_all(&self.pool)
+            .await
+            .map_err(AppError::DatabaseError)?;
+
+        Ok(rows
+            .into_iter()
+            .map(Dataset::from)
+            .filter(|dataset| {
+                filters
+                    .metadata
+                    .iter()
+                    .all(|f| f.matches(&dataset.metadata))
+            })
+            .collect())
+    }
+
+    async fn get_stats(&self, portal: Option<&str>) -> Result<DatabaseStats, AppError> {
+        let (total, with_embeddings, portals, last_update): (i64, i64, i64, Option<DateTime<Utc>>) =
+            sqlx::query_as(
+                r#"
+            SELECT COUNT(*), COUNT(embedding), COUNT(DISTINCT source_portal), MAX(last_updated_at)
+            FROM datasets
+            WHERE ?1 IS NULL OR source_portal = ?1
+            "#,
+            )
+            .bind(portal)
+            .fetch_one(&self.pool)
+            .await
+            .map_err(AppError::DatabaseError)?;
+
+        Ok(DatabaseStats {
+            total_datasets: total,
+            datasets_with_embeddings: with_embeddings,
+            total_portals: portals,
+            last_update,
+            formats: self.format_facet(portal).await?,
+            languages: self.language_counts(portal).await?,
+            quality: self.portal_quality(portal).await?,
+            embedding_models: self.embedding_model_counts(portal).await?,
+            portals: self.portal_stats(portal).await?,
+        })
+    }
+
+    async fn count_portal_datasets(&self, portal_url: &str) -> Result<i64, AppError> {
+        sqlx::query_scalar("SELECT COUNT(*) FROM datasets WHERE rtrim(source_portal, '/') = ?1")
+            .bind(portal_url.trim_end_matches('/'))
+            .fetch_one(&self.pool)
+            .await
+            .map_err(AppError::DatabaseError)
+    }
+
+    async fn delete_portal_datasets(&self, portal_url: &str) -> Result<u64, AppError> {
+        let mut tx = self.pool.begin().await.map_err(AppError::DatabaseError)?;
+        // Resources and chunks go with their datasets (ON DELETE CASCADE)
+        let deleted: Vec<Uuid> = sqlx::query_scalar(
+            "DELETE FROM datasets WHERE rtrim(source_portal, '/') = ?1 RETURNING id",
+        )
+        .bind(portal_url.trim_end_matches('/'))
+        .fetch_all(&mut *tx)
+        .await
+        .map_err(AppError::DatabaseError)?;
+        if self.has_vector_table() {
+            delete_vectors(&mut tx, &deleted).await?;
+        }
+        tx.commit().await.map_err(AppError::DatabaseError)?;
+
+        Ok(deleted.len() as u64)
+    }
+
+    async fn count_unseen_datasets(
+        &self,
+        before: DateTime<Utc>,
+        portal_url: Option<&str>,
+    ) -> Result<i64, AppError> {
+        sqlx::query_scalar(
+            r#"
+            SELECT COUNT(*)
+            FROM datasets
+            WHERE last_updated_at < ?1
+              AND (?2 IS NULL OR rtrim(source_portal, '/') = ?2)
+            "#,
+        )
+        .bind(before)
+        .bind(portal_url.map(|url| url.trim_end_matches('/')))
+        .fetch_one(&self.pool)
+        .await
+        .map_err(AppError::DatabaseError)
+    }
+
+    async fn prune_unseen_datasets(
+        &self,
+        before: DateTime<Utc>,
+        portal_url: Option<&str>,
+    ) -> Result<Vec<Uuid>, AppError> {
+        let mut tx = self.pool.begin().await.map_err(AppError::DatabaseError)?;
+        let pruned: Vec<Uuid> = sqlx::query_scalar(
+            r#"
+            DELETE FROM datasets
+            WHERE last_updated_at < ?1
+              AND (?2 IS NULL OR rtrim(source_portal, '/') = ?2)
+            RETURNING id
+            "#,
+        )
+        .bind(before)
+        .bind(portal_url.map(|url| url.trim_end_matches('/')))
+        .fetch_all(&mut *tx)
+        .await
+        .map_err(AppError::DatabaseError)?;
+        if self.has_vector_table() {
+            delete_vectors(&mut tx, &pruned).await?;
+        }
+        tx.commit().await.map_err(AppError::DatabaseError)?;
+
+        Ok(pruned)
+    }
+
+    async fn record_harvest_failures(&self, failures: &[HarvestFailure]) -> Result<(), AppError> {
+        let mut tx = self.pool.begin().await.map_err(AppError::DatabaseError)?;
+        for failure in failures {
+            sqlx::query(
+                r#"
+                INSERT INTO harvest_failures (portal_url, dataset_id, stage, error, failed_at)
+                VALUES (?, ?, ?, ?, ?)
+                ON CONFLICT (portal_url, dataset_id)
+                DO UPDATE SET
+                    stage = excluded.stage,
+                    error = excluded.error,
+                    failed_at = excluded.failed_at,
+                    attempts = harvest_failures.attempts + 1
+                "#,
+            )
+            .bind(&failure.portal_url)
+            .bind(&failure.dataset_id)
+            .bind(&failure.stage)
+            .bind(&failure.error)
+            .bind(failure.failed_at)
+            .execute(&mut *tx)
+            .await
+            .map_err(AppError::DatabaseError)?;
+        }
+        tx.commit().await.map_err(AppError::DatabaseError)
+    }
+
+    async fn list_harvest_failures(
+        &self,
+        portal_url: Option<&str>,
+    ) -> Result<Vec<HarvestFailure>, AppError> {
+        let rows: Vec<HarvestFailureRow> = sqlx::query_as(
+            r#"
+            SELECT portal_url, dataset_id, stage, error, attempts, failed_at
+            FROM harvest_failures
+            WHERE ?1 IS NULL OR rtrim(portal_url, '/') = ?1
+            ORDER BY portal_url, failed_at DESC, dataset_id
+            "#,
+        )
+        .bind(portal_url.map(|url| url.trim_end_matches('/')))
+        .fetch_all(&self.pool)
+        .await
+        .map_err(AppError::DatabaseError)?;
+
+        Ok(rows.into_iter().map(HarvestFailure::from).collect())
+    }
+
+    async fn resolve_harvest_failures(
+        &self,
+        portal_url: Option<&str>,
+        before: DateTime<Utc>,
+    ) -> Result<u64, AppError> {
+        let result = sqlx::query(
+            r#"
+            DELETE FROM harvest_failures
+            WHERE failed_at < ?1
+              AND (?2 IS NULL OR rtrim(portal_url, '/') = ?2)
+            "#,
+        )
+        .bind(before)
+        .bind(portal_url.map(|url| url.trim_end_matches('/')))
+        .execute(&self.pool)
+        .await
+        .map_err(AppError::DatabaseError)?;
+
+        Ok(result.rows_affected())
+    }
+
+    async fn get_harvest_checkpoint(
+        &self,
+        portal_url: &str,
+    ) -> Result<Option<HarvestCheckpoint>, AppError> {
+        let row: Option<HarvestCheckpointRow> = sqlx::query_as(
+            r#"
+            SELECT portal_url, listing_offset, started_at
+            FROM harvest_checkpoints
+            WHERE portal_url = ?1
+            "#,
+        )
+        .bind(portal_url)
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(AppError::DatabaseError)?;
+
+        Ok(row.map(HarvestCheckpoint::from))
+    }
+
+    async fn save_harvest_checkpoint(
+        &self,
+        checkpoint: &HarvestCheckpoint,
+    ) -> Result<(), AppError> {
+        sqlx::query(
+            r#"
+            INSERT INTO harvest_checkpoints (portal_url, listing_offset, started_at, updated_at)
+            VALUES (?1, ?2, ?3, ?4)
+            ON CONFLICT (portal_url)
+            DO UPDATE SET
+                listing_offset = excluded.listing_offset,
+                started_at = excluded.started_at,
+                updated_at = excluded.updated_at
+            "#,
+        )
+        .bind(&checkpoint.portal_url)
+        .bind(checkpoint.offset as i64)
+        .bind(checkpoint.started_at)
+        .bind(Utc::now())
+        .execute(&self.pool)
+        .await
+        .map_err(AppError::DatabaseError)?;
+
+        Ok(())
+    }
+
+    async fn clear_harvest_checkpoint(&self, portal_url: &str) -> Result<(), AppError> {
+        sqlx::query("DELETE FROM harvest_checkpoints WHERE portal_url = ?1")
+            .bind(portal_url)
+            .execute(&self.pool)
+            .await
+            .map_err(AppError::DatabaseError)?;
+
+        Ok(())
+    }
+
+    async fn create_watch(&self, _watch: &Watch, _embedding: Vector) -> Result<(), AppError> {
+        Err(watches_unsupported())
+    }
+
+    async fn list_watches(&self) -> Result<Vec<Watch>, AppError> {
+        Err(watches_unsupported())
+    }
+
+    async fn delete_watch_by_id(&self, _id: Uuid) -> Result<bool, AppError> {
+        Err(watches_unsupported())
+    }
+
+    async fn record_search(&self, search: &SearchRecord) -> Result<(), AppError> {
+        sqlx::query(
+            r#"
+            INSERT INTO search_history (query, mode, filters, result_count, top_score, source, searched_at)
+            VALUES (?, ?, ?, ?, ?, ?, ?)
+            "#,
+        )
+        .bind(&search.query)
+        .bind(&search.mode)
+        .bind(Json(&search.filters))
+        .bind(search.result_count as i64)
+        .bind(search.top_score)
+        .bind(&search.source)
+        .bind(search.searched_at)
+        .execute(&self.pool)
+        .await
+        .map_err(AppError::DatabaseError)?;
+
+        Ok(())
+    }
+
+    async fn list_searches(&self, limit: usize) -> Result<Vec<SearchRecord>, AppError> {
+        let rows: Vec<SearchRecordRow> = sqlx::query_as(
+            r#"
+            SELECT query, mode, filters, result_count, top_score, source, searched_at
+            FROM search_history
+            ORDER BY searched_at DESC, id DESC
+            LIMIT ?
+            "#,
+        )
+        .bind(limit as i64)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(AppError::DatabaseError)?;
+
+        Ok(rows.into_iter().map(SearchRecord::from).collect())
+    }
+
+    async fn clear_search_history(&self) -> Result<u64, AppError> {
+        let result = sqlx::query("DELETE FROM search_history")
+            .execute(&self.pool)
+            .await
+            .map_err(AppError::DatabaseError)?;
+
+        Ok(result.rows_affected())
+    }
+}
+
+/// Creates the `vec0` table indexing `dimension`-dimensional embeddings,
+/// keyed by the hex of the dataset ID.
+async fn create_vector_table(
+    tx: &mut sqlx::Transaction<'_, Sqlite>,
+    dimension: usize,
+) -> Result<(), AppError> {
+    sqlx::query(&format!(
+        "CREATE VIRTUAL TABLE IF NOT EXISTS dataset_vectors USING vec0(dataset_id TEXT PRIMARY KEY, embedding float[{}] distance_metric=cosine)",
+        dimension
+    ))
+    .execute(&mut **tx)
+    .await
+    .map_err(AppError::DatabaseError)?;
+    Ok(())
+}
+
+/// Removes datasets from the `vec0` index.
+async fn delete_vectors(
+    tx: &mut sqlx::Transaction<'_, Sqlite>,
+    ids: &[Uuid],
+) -> Result<(), AppError> {
+    for id in ids {
+        sqlx::query("DELETE FROM dataset_vectors WHERE dataset_id = hex(?)")
+            .bind(*id)
+            .execute(&mut **tx)
+            .await
+            .map_err(AppError::DatabaseError)?;
+    }
+    Ok(())
+}
+
+/// Number of stored embeddings of `dimension`.
+async fn count_embedded(
+    tx: &mut sqlx::Transaction<'_, Sqlite>,
+    dimension: usize,
+) -> Result<i64, AppError> {
+    sqlx::query_scalar("SELECT COUNT(*) FROM datasets WHERE length(embedding) = ?")
+        .bind((dimension * size_of::<f32>()) as i64)
+        .fetch_one(&mut **tx)
+        .await
+        .map_err(AppError::DatabaseError)
+}
+
+/// Dimension of the `float[N]` column in the `CREATE VIRTUAL TABLE`
+/// statement of the `vec0` index.
+fn vector_table_dimension(sql: &str) -> Option<usize> {
+    let start = sql.find("float[")? + "float[".len();
+    let len = sql[start..].find(']')?;
+    sql[start..start + len].trim().parse().ok()
+}
+
+/// Error for watch operations, which need PostgreSQL.
+fn watches_unsupported() -> AppError {
+    AppError::ConfigError("Watches are not supported by the SQLite backend".to_string())
+}
+
+/// Appends structured search filters as `AND ...` clauses.
+///
+/// Metadata filters are not SQL here; callers apply them in process.
+fn push_search_filters(builder: &mut QueryBuilder<'_, Sqlite>, filters: &SearchFilters) {
+    if let Some(portal) = &filters.portal {
+        builder.push(" AND source_portal = ");
+        builder.push_bind(portal.clone());
+    }
+    if let Some(theme) = &filters.theme {
+        builder.push(
+            " AND EXISTS (SELECT 1 FROM json_each(metadata, '$.groups') g WHERE json_extract(g.value, '$.name') = ",
+        );
+        builder.push_bind(theme.clone());
+        builder.push(")");
+    }
+    if let Some(model) = &filters.embedding_model {
+        builder.push(" AND embedding_model = ");
+        builder.push_bind(model.clone());
+    }
+    if let Some(format) = &filters.format {
+        builder.push(" AND EXISTS (SELECT 1 FROM json_each(formats) WHERE value = ");
+        builder.push_bind(format.clone());
+        builder.push(")");
+    }
+    if let Some(license) = &filters.license {
+        builder.push(" AND json_extract(metadata, '$.license_id') = ");
+        builder.push_bind(license.clone());
+    }
+    if let Some(after) = filters.updated_after {
+        builder.push(" AND modified_at >= ");
+        builder.push_bind(after);
+    }
+    if let Some(before) = filters.updated_before {
+        builder.push(" AND modified_at < ");
+        builder.push_bind(before);
+    }
+    if let Some(language) = &filters.language {
+        builder.push(" AND language = ");
+        builder.push_bind(language.clone());
+    }
+    if let Some(tag) = &filters.tag {
+        builder.push(" AND EXISTS (SELECT 1 FROM json_each(tags) WHERE value = ");
+        builder.push_bind(tag.clone());
+        builder.push(")");
+    }
+    if let Some(organization) = &filters.organization {
+        builder.push(" AND (organization_name = ");
+        builder.push_bind(organization.clone());
+        builder.push(" OR lower(organization_title) = lower(");
+        builder.push_bind(organization.clone());
+        builder.push("))");
+    }
+    if let Some(bbox) = filters.bbox {
+        builder.push(" AND bbox_min_lon <= ");
+        builder.push_bind(bbox.max_lon);
+        builder.push(" AND bbox_max_lon >= ");
+        builder.push_bind(bbox.min_lon);
+        builder.push(" AND bbox_min_lat <= ");
+        builder.push_bind(bbox.max_lat);
+        builder.push(" AND bbox_max_lat >= ");
+        builder.push_bind(bbox.min_lat);
+    }
+}
+
+/// Stores a vector as little-endian `f32`s.
+fn encode_vector(vector: &Vector) -> Vec<u8> {
+    vector
+        .as_slice()
+        .iter()
+        .flat_map(|x| x.to_le_bytes())
+        .collect()
+}
+
+fn decode_floats(bytes: &[u8]) -> Vec<f32> {
+    bytes
+        .chunks_exact(size_of::<f32>())
+        .map(|b| f32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+        .collect()
+}
+
+fn decode_vector(bytes: &[u8]) -> Vector {
+    Vector::from(decode_floats(bytes))
+}
+
+/// Columns read to score a dataset
+#[derive(sqlx::FromRow)]
+struct CandidateRow {
+    id: Uuid,
+    title: String,
+    description: Option<String>,
+    embedding: Option<Vec<u8>>,
+    metadata: Option<Json<serde_json::Value>>,
+}
+
+/// Columns read to score a chunk
+#[derive(sqlx::FromRow)]
+struct ChunkRow {
+    dataset_id: Uuid,
+    embedding: Vec<u8>,
+    metadata: Option<Json<serde_json::Value>>,
+}
+
+/// Helper struct for deserializing dataset rows
+#[derive(sqlx::FromRow)]
+struct DatasetRow {
+    id: Uuid,
+    original_id: String,
+    source_portal: String,
+    url: String,
+    title: String,
+    description: Option<String>,
+    embedding: Option<Vec<u8>>,
+    metadata: Json<serde_json::Value>,
+    formats: Json<Vec<String>>,
+    first_seen_at: DateTime<Utc>,
+    last_updated_at: DateTime<Utc>,
+    content_hash: Option<String>,
+    embedding_model: Option<String>,
+    embedded_at: Option<DateTime<Utc>>,
+    modified_at: Option<DateTime<Utc>>,
+    language: Option<String>,
+    quality: Option<f64>,
+}
+
+impl From<DatasetRow> for Dataset {
+    fn from(row: DatasetRow) -> Self {
+        Dataset {
+            id: row.id,
+            original_id: row.original_id,
+            source_portal: row.source_portal,
+            url: row.url,
+            title: row.title,
+            description: row.description,
+            embedding: row.embedding.as_deref().map(decode_vector),
+            metadata: row.metadata,
+            formats: row.formats.0,
+            first_seen_at: row.first_seen_at,
+            last_updated_at: row.last_updated_at,
+            content_hash: row.content_hash,
+            embedding_model: row.embedding_model,
+            embedded_at: row.embedded_at,
+            modified_at: row.modified_at,
+            language: row.language,
+            quality: row.quality.map(|q| q as f32),
+        }
+    }
+}
+
+/// Helper struct for deserializing per-portal stats rows
+#[derive(sqlx::FromRow)]
+struct PortalStatsRow {
+    portal: String,
+    datasets: i64,
+    with_embeddings: i64,
+    last_update: Option<DateTime<Utc>>,
+    last_harvest_at: Option<DateTime<Utc>>,
+    harvests: i64,
+    failed_harvests: i64,
+}
+
+/// Helper struct for deserializing embedding model count rows
+#[derive(sqlx::FromRow)]
+struct EmbeddingModelRow {
+    model: String,
+    datasets: i64,
+    portals: i64,
+    first_embedded_at: Option<DateTime<Utc>>,
+    last_embedded_at: Option<DateTime<Utc>>,
+}
+
+/// Helper struct for deserializing `portal_health` rows
+#[derive(sqlx::FromRow)]
+struct PortalHealthRow {
+    portal_url: String,
+    consecutive_failures: i64,
+    last_error: Option<String>,
+    last_failure_at: Option<DateTime<Utc>>,
+    last_success_at: Option<DateTime<Utc>>,
+    quarantined_until: Option<DateTime<Utc>>,
+    harvests: i64,
+    failed_harvests: i64,
+}
+
+impl From<PortalHealthRow> for PortalHealth {
+    fn from(row: PortalHealthRow) -> Self {
+        PortalHealth {
+            portal_url: row.portal_url,
+            consecutive_failures: row.consecutive_failures.max(0) as u32,
+            last_error: row.last_error,
+            last_failure_at: row.last_failure_at,
+            last_success_at: row.last_success_at,
+            quarantined_until: row.quarantined_until,
+            harvests: row.harvests.max(0) as u32,
+            failed_harvests: row.failed_harvests.max(0) as u32,
+        }
+    }
+}
+
+/// Helper struct for deserializing `harvest_failures` rows
+#[derive(sqlx::FromRow)]
+struct HarvestFailureRow {
+    portal_url: String,
+    dataset_id: String,
+    stage: String,
+    error: String,
+    attempts: i64,
+    failed_at: DateTime<Utc>,
+}
+
+impl From<HarvestFailureRow> for HarvestFailure {
+    fn from(row: HarvestFailureRow) -> Self {
+        HarvestFailure {
+            portal_url: row.portal_url,
+            dataset_id: row.dataset_id,
+            stage: row.stage,
+            error: row.error,
+            attempts: row.attempts.max(0) as u32,
+            failed_at: row.failed_at,
+        }
+    }
+}
+
+/// Helper struct for deserializing `harvest_checkpoints` rows
+#[derive(sqlx::FromRow)]
+struct HarvestCheckpointRow {
+    portal_url: String,
+    listing_offset: i64,
+    started_at: DateTime<Utc>,
+}
+
+impl From<HarvestCheckpointRow> for HarvestCheckpoint {
+    fn from(row: HarvestCheckpointRow) -> Self {
+        HarvestCheckpoint {
+            portal_url: row.portal_url,
+            offset: row.listing_offset.max(0) as usize,
+            started_at: row.started_at,
+        }
+    }
+}
+
+/// Helper struct for deserializing `search_history` rows
+#[derive(sqlx::FromRow)]
+struct SearchRecordRow {
+    query: String,
+    mode: String,
+    filters: Json<serde_json::Value>,
+    result_count: i64,
+    top_score: Option<f32>,
+    source: String,
+    searched_at: DateTime<Utc>,
+}
+
+impl From<SearchRecordRow> for SearchRecord {
+    fn from(row: SearchRecordRow) -> Self {
+        SearchRecord {
+            query: row.query,
+            mode: row.mode,
+            filters: row.filters.0,
+            result_count: row.result_count.max(0) as u32,
+            top_score: row.top_score,
+            source: row.source,
+            searched_at: row.searched_at,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ceres_core::history::SearchSource;
+    use ceres_core::metadata_filter::MetadataFilter;
+    use ceres_core::sync::FailureStage;
+    use ceres_core::SyncOutcome;
+    use serde_json::json;
+
+    async fn repository() -> SqliteRepository {
+        // One connection: every in-memory connection is its own database
+        let pool = SqlitePoolOptions::new()
+            .max_connections(1)
+            .connect("sqlite::memory:")
+            .await
+            .unwrap();
+        let repo = SqliteRepository::new(pool);
+        repo.run_migrations().await.unwrap();
+        repo
+    }
+
+    fn dataset(
+        id: &str,
+        title: &str,
+        embedding: Vec<f32>,
+        metadata: serde_json::Value,
+    ) -> NewDataset {
+        let formats = NewDataset::extract_formats(&metadata);
+        NewDataset {
+            original_id: id.to_string(),
+            source_portal: "https://dati.comune.milano.it".to_string(),
+            url: format!("https://dati.comune.milano.it/dataset/{}", id),
+            title: title.to_string(),
+            description: None,
+            embedding: Some(Vector::from(embedding)),
+            embedding_model: Some("test-model".to_string()),
+            content_hash: NewDataset::compute_content_hash(title, None),
+            tags: ceres_core::tags::dataset_tags(&metadata),
+            resources: ceres_core::resources::dataset_resources(&metadata),
+            organization: None,
+            formats,
+            metadata,
+            modified_at: None,
+            language: None,
+            bbox: None,
+            quality: None,
+        }
+    }
+
+    #[tokio::test]
+    async fn test_datasets_needing_processing() {
+        let repo = repository().await;
+        let air = dataset("aria", "Qualità dell'aria", vec![1.0, 0.0], json!({}));
+        repo.upsert(&air).await.unwrap();
+
+        let portal = "https://dati.comune.milano.it";
+        let candidates = vec![
+            ("aria".to_string(), air.content_hash.clone()),
+            ("bus".to_string(), "new-hash".to_string()),
+        ];
+        let decisions = repo
+            .datasets_needing_processing(portal, "test-model", &candidates)
+            .await
+            .unwrap();
+        assert_eq!(decisions.len(), 1);
+        assert_eq!(decisions["bus"].outcome, SyncOutcome::Created);
+
+        // Another model, or changed content, needs a new embedding
+        let decisions = repo
+            .datasets_needing_processing(portal, "other-model", &candidates[..1])
+            .await
+            .unwrap();
+        assert!(decisions["aria"].is_legacy());
+        let changed = [("aria".to_string(), "changed".to_string())];
+        let decisions = repo
+            .datasets_needing_processing(portal, "test-model", &changed)
+            .await
+            .unwrap();
+        assert_eq!(decisions["aria"].outcome, SyncOutcome::Updated);
+    }
+
+    #[tokio::test]
+    async fn test_harvest_search_and_export() {
+        let repo = repository().await;
+        assert!(repo.pending_migrations().await.unwrap().is_empty());
+
+        let air = dataset(
+            "aria",
+            "Qualità dell'aria",
+            vec![1.0, 0.0],
+            json!({"tags": [{"name": "Ambiente"}], "resources": [{"format": "CSV", "size": 10}]}),
+        );
+        let created = repo.upsert(&air).await.unwrap();
+        let id = created.id();
+        assert_eq!(created, UpsertOutcome::Created(id));
+        // Upserting again keeps the row and its ID
+        assert_eq!(repo.upsert(&air).await.unwrap(), UpsertOutcome::Updated(id));
+        repo.upsert(&dataset(
+            "bus",
+            "Fermate autobus",
+            vec![0.0, 1.0],
+            json!({}),
+        ))
+        .await
+        .unwrap();
+
+        assert_eq!(repo.embedding_dimension().await.unwrap(), Some(2));
+        let hashes = repo
+            .get_hashes_for_portal("https://dati.comune.milano.it", "test-model")
+            .await
+            .unwrap();
+        assert_eq!(hashes["aria"].as_deref(), Some(air.content_hash.as_str()));
+
+        let filters = SearchFilters::default();
+        let results = repo
+            .search_with_filters(
+                Vector::from(vec![0.9, 0.1]),
+                "",
+                10,
+                &filters,
+                SearchStrategy::Auto,
+            )
+            .await
+            .unwrap();
+        assert_eq!(results[0].dataset.original_id, "aria");
+        assert_eq!(results.len(), 2);
+
+        let tagged = SearchFilters {
+            tag: Some("ambiente".to_string()),
+            ..Default::default()
+        };
+        let results = repo
+            .search_with_filters(
+                Vector::from(vec![0.0, 1.0]),
+                "",
+                10,
+                &tagged,
+                SearchStrategy::Auto,
+            )
+            .await
+            .unwrap();
+        assert_eq!(results.len(), 1);
+
+        let results = repo.text_search("autobus", 10, &filters).await.unwrap();
+        assert_eq!(results[0].dataset.original_id, "bus");
+
+        let where_filter = SearchFilters {
+            metadata: vec![MetadataFilter::parse("tags.name=Ambiente").unwrap()],
+            ..Default::default()
+        };
+        let exported: Vec<Dataset> = repo
+            .stream_all(&where_filter, None)
+            .try_collect()
+            .await
+            .unwrap();
+        assert_eq!(exported.len(), 1);
+        assert_eq!(exported[0].formats, vec!["CSV"]);
+
+        let stats = repo.get_stats(None).await.unwrap();
+        assert_eq!(stats.total_datasets, 2);
+        assert_eq!(stats.datasets_with_embeddings, 2);
+        assert_eq!(stats.formats[0].resources, 1);
+        assert_eq!(stats.portals[0].datasets, 2);
+    }
+
+    #[tokio::test]
+    async fn test_chunk_embeddings() {
+        let repo = repository().await;
+        let portal = "https://dati.comune.milano.it";
+        let air = repo
+            .upsert(&dataset(
+                "aria",
+                "Qualità dell'aria",
+                vec![1.0, 0.0],
+                json!({}),
+            ))
+            .await
+            .unwrap()
+            .id();
+        repo.upsert(&dataset(
+            "bus",
+            "Fermate autobus",
+            vec![0.6, 0.8],
+            json!({}),
+        ))
+        .await
+        .unwrap();
+
+        let hashes = repo
+            .get_chunk_hashes_for_portal(portal, "test-model")
+            .await
+            .unwrap();
+        assert_eq!(hashes["aria"], (air, None));
+
+        // A resource of the air dataset matches the query exactly
+        let chunk = DatasetChunk {
+            kind: ceres_core::chunks::ChunkKind::Resource,
+            text: "Qualità dell'aria: centraline".to_string(),
+        };
+        repo.replace_dataset_chunks(
+            air,
+            &[(chunk, Vector::from(vec![0.0, 1.0]))],
+            "test-model",
+            Some("chunks-hash"),
+        )
+        .await
+        .unwrap();
+        let hashes = repo
+            .get_chunk_hashes_for_portal(portal, "test-model")
+            .await
+            .unwrap();
+        assert_eq!(hashes["aria"].1.as_deref(), Some("chunks-hash"));
+        // Chunks of another model need embedding again
+        let hashes = repo
+            .get_chunk_hashes_for_portal(portal, "other-model")
+            .await
+            .unwrap();
+        assert_eq!(hashes["aria"].1, None);
+
+        let filters = SearchFilters {
+            embedding_model: Some("test-model".to_string()),
+            ..Default::default()
+        };
+        let query = Vector::from(vec![0.0, 1.0]);
+        let direct = repo
+            .search_with_filters(query.clone(), "", 1, &filters, SearchStrategy::default())
+            .await
+            .unwrap();
+        assert_eq!(direct[0].dataset.original_id, "bus");
+        let results = repo
+            .search_with_chunks(query.clone(), "", 2, &filters, SearchStrategy::default())
+            .await
+            .unwrap();
+        assert_eq!(results[0].dataset.original_id, "aria");
+        assert!((results[0].similarity_score - 1.0).abs() < 1e-6);
+        assert_eq!(results[1].dataset.original_id, "bus");
+
+        // Chunks go with their dataset
+        repo.delete_portal_datasets(portal).await.unwrap();
+        let results = repo
+            .search_with_chunks(query, "", 2, &filters, SearchStrategy::default())
+            .await
+            .unwrap();
+        assert!(results.is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_connect_with_missing_sqlite_vec() {
+        let result =
+            SqliteRepository::connect_with_sqlite_vec("sqlite::memory:", "/nonexistent/vec0").await;
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_vector_table_dimension() {
+        let sql = "CREATE VIRTUAL TABLE dataset_vectors USING vec0(dataset_id TEXT PRIMARY KEY, embedding float[768] distance_metric=cosine)";
+        assert_eq!(vector_table_dimension(sql), Some(768));
+        assert_eq!(vector_table_dimension("CREATE TABLE t (x)"), None);
+    }
+
+    #[tokio::test]
+    async fn test_stream_all_crosses_pages() {
+        let repo = repository().await;
+        let total = STREAM_PAGE_SIZE + 5;
+        for i in 0..total {
+            repo.upsert(&dataset(&i.to_string(), "Bilancio", vec![1.0], json!({})))
+                .await
+                .unwrap();
+        }
+
+        let filters = SearchFilters::default();
+        let all: Vec<Dataset> = repo.stream_all(&filters, None).try_collect().await.unwrap();
+        assert_eq!(all.len(), total);
+        let ids: std::collections::HashSet<_> = all.iter().map(|d| d.id).collect();
+        assert_eq!(ids.len(), total);
+        assert!(all
+            .windows(2)
+            .all(|w| w[0].last_updated_at >= w[1].last_updated_at));
+
+        let limited: Vec<Dataset> = repo
+            .stream_all(&filters, Some(STREAM_PAGE_SIZE + 1))
+            .try_collect()
+            .await
+            .unwrap();
+        assert_eq!(limited.len(), STREAM_PAGE_SIZE + 1);
+    }
+
+    #[tokio::test]
+    async fn test_delete_portal_datasets() {
+        let repo = repository().await;
+        let metadata = json!({"resources": [{"format": "CSV"}]});
+        repo.upsert(&dataset("aria", "Aria", vec![1.0], metadata.clone()))
+            .await
+            .unwrap();
+        let mut other = dataset("bus", "Fermate", vec![1.0], metadata);
+        other.source_portal = "https://dati.gov.it".to_string();
+        repo.upsert(&other).await.unwrap();
+
+        // Trailing slashes are ignored
+        let portal = "https://dati.comune.milano.it/";
+        assert_eq!(repo.count_portal_datasets(portal).await.unwrap(), 1);
+        assert_eq!(repo.delete_portal_datasets(portal).await.unwrap(), 1);
+        assert_eq!(repo.count_portal_datasets(portal).await.unwrap(), 0);
+        assert_eq!(repo.get_stats(None).await.unwrap().total_datasets, 1);
+
+        let resources: i64 = sqlx::query_scalar("SELECT COUNT(*) FROM resources")
+            .fetch_one(&repo.pool)
+            .await
+            .unwrap();
+        assert_eq!(resources, 1);
+    }
+
+    #[tokio::test]
+    async fn test_prune_unseen_datasets() {
+        let repo = repository().await;
+        let air = repo
+            .upsert(&dataset("aria", "Aria", vec![1.0], json!({})))
+            .await
+            .unwrap();
+        repo.upsert(&dataset("bus", "Fermate", vec![1.0], json!({})))
+            .await
+            .unwrap();
+        let long_ago = Utc::now() - chrono::Duration::days(200);
+        sqlx::query("UPDATE datasets SET last_updated_at = ?1 WHERE id = ?2")
+            .bind(long_ago)
+            .bind(air.id())
+            .execute(&repo.pool)
+            .await
+            .unwrap();
+
+        let before = Utc::now() - chrono::Duration::days(180);
+        let other = Some("https://dati.gov.it");
+        assert_eq!(repo.count_unseen_datasets(before, other).await.unwrap(), 0);
+        assert_eq!(repo.count_unseen_datasets(before, None).await.unwrap(), 1);
+        let pruned = repo.prune_unseen_datasets(before, None).await.unwrap();
+        assert_eq!(pruned, vec![air.id()]);
+        assert_eq!(repo.get_stats(None).await.unwrap().total_datasets, 1);
+    }
+
+    #[tokio::test]
+    async fn test_harvest_failures() {
+        let repo = repository().await;
+        let portal = "https://dati.comune.milano.it/";
+        let failure =
+            |id: &str, stage| HarvestFailure::new(portal, id, stage, format!("{} failed", id));
+        repo.record_harvest_failures(&[
+            failure("aria", FailureStage::Fetch),
+            failure("bus", FailureStage::Embed),
+        ])
+        .await
+        .unwrap();
+        let started = Utc::now();
+        repo.record_harvest_failures(&[failure("bus", FailureStage::Store)])
+            .await
+            .unwrap();
+
+        let failures = repo
+            .list_harvest_failures(Some("https://dati.comune.milano.it"))
+            .await
+            .unwrap();
+        assert_eq!(failures.len(), 2);
+        assert_eq!(failures[0].dataset_id, "bus");
+        assert_eq!(failures[0].stage, "store");
+        assert_eq!(failures[0].attempts, 2);
+        assert!(repo
+            .list_harvest_failures(Some("https://dati.gov.it"))
+            .await
+            .unwrap()
+            .is_empty());
+
+        // A later harvest got past aria
+        let resolved = repo
+            .resolve_harvest_failures(Some(portal), started)
+            .await
+            .unwrap();
+        assert_eq!(resolved, 1);
+        let failures = repo.list_harvest_failures(None).await.unwrap();
+        assert_eq!(failures.len(), 1);
+        assert_eq!(failures[0].dataset_id, "bus");
+    }
+
+    #[tokio::test]
+    async fn test_harvest_checkpoints() {
+        let repo = repository().await;
+        let portal = "https://dati.comune.milano.it";
+        assert!(repo.get_harvest_checkpoint(portal).await.unwrap().is_none());
+
+        let mut checkpoint = HarvestCheckpoint {
+            portal_url: portal.to_string(),
+            offset: 100,
+            started_at: Utc::now(),
+        };
+        repo.save_harvest_checkpoint(&checkpoint).await.unwrap();
+        checkpoint.offset = 200;
+        repo.save_harvest_checkpoint(&checkpoint).await.unwrap();
+        let saved = repo.get_harvest_checkpoint(portal).await.unwrap().unwrap();
+        assert_eq!(saved.offset, 200);
+        assert_eq!(saved.started_at, checkpoint.started_at);
+
+        repo.clear_harvest_checkpoint(portal).await.unwrap();
+        assert!(repo.get_harvest_checkpoint(portal).await.unwrap().is_none());
+    }
+
+    #[tokio::test]
+    async fn test_search_history() {
+        let repo = repository().await;
+        let filters = SearchFilters {
+            tag: Some("mobilita".to_string()),
+            ..Default::default()
+        };
+        let first = SearchRecord::new("autobus", "text", &filters, &[], SearchSource::Cli);
+        let second = SearchRecord {
+            query: "qualità dell'aria".to_string(),
+            result_count: 3,
+            top_score: Some(0.82),
+            ..SearchRecord::new(
+                "",
+                "hybrid",
+                &SearchFilters::default(),
+                &[],
+                SearchSource::Api,
+            )
+        };
+        repo.record_search(&first).await.unwrap();
+        repo.record_search(&second).await.unwrap();
+
+        let searches = repo.list_searches(10).await.unwrap();
+        assert_eq!(searches, vec![second.clone(), first]);
+        assert_eq!(repo.list_searches(1).await.unwrap(), vec![second]);
+
+        assert_eq!(repo.clear_search_history().await.unwrap(), 2);
+        assert!(repo.list_searches(10).await.unwrap().is_empty());
+    }
+}
@@ -0,0 +1,655 @@
+//! Storage backends.
+//!
+//! [`DatasetStore`] covers what harvesting, search, export and `ceres stats`
+//! need from a database, so they run on PostgreSQL ([`DatasetRepository`])
+//! or, with the `sqlite` feature, on a local SQLite file
+//! (`SqliteRepository`). Watches are part of it too, so `ceres serve` can
+//! manage them, but the SQLite backend rejects them. Everything else
+//! (clusters, enrichment, maintenance, index tuning) is only available on
+//! PostgreSQL.
+
+use std::collections::HashMap;
+
+use async_trait::async_trait;
+use ceres_core::chunks::DatasetChunk;
+use ceres_core::error::AppError;
+use ceres_core::health::PortalHealth;
+use ceres_core::history::SearchRecord;
+use ceres_core::models::{DatabaseStats, Dataset, NewDataset, SchemaMigration, SearchResult};
+use ceres_core::search::{SearchFilters, SearchStrategy};
+use ceres_core::sync::{
+    needs_reprocessing, HarvestCheckpoint, HarvestFailure, ReprocessingDecision, UpsertOutcome,
+};
+use ceres_core::watch::Watch;
+use chrono::{DateTime, Utc};
+use futures::stream::{BoxStream, StreamExt};
+use pgvector::Vector;
+use uuid::Uuid;
+
+use crate::{DatasetRepository, PortalLock};
+
+/// Returns true if `database_url` points to a SQLite database
+/// (`sqlite://path` or `sqlite::memory:`).
+pub fn is_sqlite_url(database_url: &str) -> bool {
+    database_url.starts_with("sqlite:")
+}
+
+/// Decisions for `(original_id, stored, stored_hash, new_hash)` rows of
+/// datasets needing processing; `stored_hash` is `None` for datasets
+/// embedded by another model.
+pub(crate) fn reprocessing_decisions(
+    rows: impl IntoIterator<Item = (String, bool, Option<String>, String)>,
+) -> HashMap<String, ReprocessingDecision> {
+    rows.into_iter()
+        .map(|(original_id, stored, stored_hash, new_hash)| {
+            let existing = stored.then_some(stored_hash);
+            let decision = needs_reprocessing(existing.as_ref(), &new_hash);
+            (original_id, decision)
+        })
+        .collect()
+}
+
+/// Dataset storage used by harvest, search, export and stats.
+///
+/// Methods behave as the [`DatasetRepository`] methods of the same name.
+#[async_trait]
+pub trait DatasetStore: Send + Sync {
+    /// Dimension of stored embeddings, or `None` if not fixed yet.
+    async fn embedding_dimension(&self) -> Result<Option<usize>, AppError>;
+
+    /// Shipped migrations not applied yet, oldest first.
+    async fn pending_migrations(&self) -> Result<Vec<SchemaMigration>, AppError>;
+
+    /// Applies pending migrations and returns them.
+    async fn run_migrations(&self) -> Result<Vec<SchemaMigration>, AppError>;
+
+    /// Map of original_id → content hash of a portal's datasets; datasets
+    /// embedded by another model have no hash.
+    async fn get_hashes_for_portal(
+        &self,
+        portal_url: &str,
+        embedding_model: &str,
+    ) -> Result<HashMap<String, Option<String>>, AppError>;
+
+    /// Delta detection for a batch of `(original_id, content_hash)` pairs
+    /// fetched from a portal: the decision for each dataset that is new,
+    /// changed or embedded by another model. Unchanged datasets are left
+    /// out.
+    ///
+    /// The default reads all of the portal's hashes; stores that can join
+    /// the batch against stored rows override this.
+    async fn datasets_needing_processing(
+        &self,
+        portal_url: &str,
+        embedding_model: &str,
+        candidates: &[(String, String)],
+    ) -> Result<HashMap<String, ReprocessingDecision>, AppError> {
+        let hashes = self
+            .get_hashes_for_portal(portal_url, embedding_model)
+            .await?;
+        Ok(reprocessing_decisions(candidates.iter().filter_map(
+            |(original_id, new_hash)| match hashes.get(original_id) {
+                Some(Some(hash)) if hash == new_hash => None,
+                stored => Some((
+                    original_id.clone(),
+                    stored.is_some(),
+                    stored.cloned().flatten(),
+                    new_hash.clone(),
+                )),
+            },
+        )))
+    }
+
+    /// Marks an unchanged dataset as seen. Returns true if it exists.
+    async fn update_timestamp_only(
+        &self,
+        portal_url: &str,
+        original_id: &str,
+    ) -> Result<bool, AppError>;
+
+    /// Inserts or updates a dataset with its tags, organization and
+    /// resources. Returns whether it was created or updated, with its UUID.
+    async fn upsert(&self, new_data: &NewDataset) -> Result<UpsertOutcome, AppError>;
+
+    /// Upserts several datasets, returning their outcomes in the order
+    /// given. Stores that can write them in fewer round trips override this.
+    async fn upsert_batch(&self, datasets: &[NewDataset]) -> Result<Vec<UpsertOutcome>, AppError> {
+        let mut outcomes = Vec::with_capacity(datasets.len());
+        for new_data in datasets {
+            outcomes.push(self.upsert(new_data).await?);
+        }
+        Ok(outcomes)
+    }
+
+    /// Cached embedding of content with this hash, by this model.
+    async fn cached_embedding(
+        &self,
+        content_hash: &str,
+        model: &str,
+    ) -> Result<Option<Vector>, AppError>;
+
+    /// Caches an embedding by content hash and model.
+    async fn cache_embedding(
+        &self,
+        content_hash: &str,
+        model: &str,
+        embedding: &Vector,
+    ) -> Result<(), AppError>;
+
+    /// Map of original_id → (dataset ID, chunks hash) of a portal's datasets.
+    async fn get_chunk_hashes_for_portal(
+        &self,
+        portal_url: &str,
+        embedding_model: &str,
+    ) -> Result<HashMap<String, (Uuid, Option<String>)>, AppError>;
+
+    /// Replaces the chunk embeddings of a dataset.
+    async fn replace_dataset_chunks(
+        &self,
+        dataset_id: Uuid,
+        chunks: &[(DatasetChunk, Vector)],
+        embedding_model: &str,
+        chunks_hash: Option<&str>,
+    ) -> Result<(), AppError>;
+
+    /// Harvest health of every tracked portal.
+    async fn list_portal_health(&self) -> Result<Vec<PortalHealth>, AppError>;
+
+    /// Harvest health of a portal; healthy if never recorded.
+    async fn get_portal_health(&self, portal_url: &str) -> Result<PortalHealth, AppError>;
+
+    /// Stores a portal's harvest health.
+    async fn save_portal_health(&self, health: &PortalHealth) -> Result<(), AppError>;
+
+    /// Semantic search with structured filters.
+    async fn search_with_filters(
+        &self,
+        query_vector: Vector,
+        query_text: &str,
+        limit: usize,
+        filters: &SearchFilters,
+        strategy: SearchStrategy,
+    ) -> Result<Vec<SearchResult>, AppError>;
+
+    /// Keyword search without embeddings.
+    async fn text_search(
+        &self,
+        query_text: &str,
+        limit: usize,
+        filters: &SearchFilters,
+    ) -> Result<Vec<SearchResult>, AppError>;
+
+    /// Semantic and keyword rankings fused by reciprocal rank fusion.
+    async fn hybrid_search(
+        &self,
+        query_vector: Vector,
+        query_text: &str,
+        limit: usize,
+        filters: &SearchFilters,
+        strategy: SearchStrategy,
+    ) -> Result<Vec<SearchResult>, AppError>;
+
+    /// Semantic search over dataset and chunk embeddings.
+    async fn search_with_chunks(
+        &self,
+        query_vector: Vector,
+        query_text: &str,
+        limit: usize,
+        filters: &SearchFilters,
+        strategy: SearchStrategy,
+    ) -> Result<Vec<SearchResult>, AppError>;
+
+    /// Datasets matching `filters`, most recently updated first, read a
+    /// page at a time; at most `limit` if set.
+    fn stream_all<'a>(
+        &'a self,
+        filters: &'a SearchFilters,
+        limit: Option<usize>,
+    ) -> BoxStream<'a, Result<Dataset, AppError>>;
+
+    /// Datasets among `ids` passing `filters`, in no particular order.
+    async fn datasets_by_ids(
+        &self,
+        ids: &[Uuid],
+        filters: &SearchFilters,
+    ) -> Result<Vec<Dataset>, AppError>;
+
+    /// Database statistics, optionally for a single portal.
+    async fn get_stats(&self, portal: Option<&str>) -> Result<DatabaseStats, AppError>;
+
+    /// Number of datasets harvested from a portal; trailing slashes are
+    /// ignored.
+    async fn count_portal_datasets(&self, portal_url: &str) -> Result<i64, AppError>;
+
+    /// Deletes every dataset harvested from a portal, with its resources
+    /// and embeddings. Returns the number deleted.
+    async fn delete_portal_datasets(&self, portal_url: &str) -> Result<u64, AppError>;
+
+    /// Number of datasets no harvest has seen since `before`, optionally of
+    /// one portal.
+    async fn count_unseen_datasets(
+        &self,
+        before: DateTime<Utc>,
+        portal_url: Option<&str>,
+    ) -> Result<i64, AppError>;
+
+    /// Deletes the datasets no harvest has seen since `before`, optionally
+    /// of one portal. Returns their IDs.
+    async fn prune_unseen_datasets(
+        &self,
+        before: DateTime<Utc>,
+        portal_url: Option<&str>,
+    ) -> Result<Vec<Uuid>, AppError>;
+
+    /// Records datasets a harvest failed to process in the dead-letter
+    /// table, replacing each one's earlier failure and counting the attempt.
+    async fn record_harvest_failures(&self, failures: &[HarvestFailure]) -> Result<(), AppError>;
+
+    /// Recorded harvest failures, optionally of one portal, by portal and
+    /// latest first; trailing slashes are ignored.
+    async fn list_harvest_failures(
+        &self,
+        portal_url: Option<&str>,
+    ) -> Result<Vec<HarvestFailure>, AppError>;
+
+    /// Deletes the harvest failures recorded before `before`, optionally of
+    /// one portal. Returns the number deleted.
+    async fn resolve_harvest_failures(
+        &self,
+        portal_url: Option<&str>,
+        before: DateTime<Utc>,
+    ) -> Result<u64, AppError>;
+
+    /// The checkpoint an interrupted harvest of a portal left, if any.
+    async fn get_harvest_checkpoint(
+        &self,
+        portal_url: &str,
+    ) -> Result<Option<HarvestCheckpoint>, AppError>;
+
+    /// Saves how far a harvest of a portal got, replacing its checkpoint.
+    async fn save_harvest_checkpoint(&self, checkpoint: &HarvestCheckpoint)
+        -> Result<(), AppError>;
+
+    /// Deletes the checkpoint of a portal, once a harvest got through it
+    /// all.
+    async fn clear_harvest_checkpoint(&self, portal_url: &str) -> Result<(), AppError>;
+
+    /// Stores a new watch with its query embedding.
+    async fn create_watch(&self, watch: &Watch, embedding: Vector) -> Result<(), AppError>;
+
+    /// Returns all watches, oldest first.
+    async fn list_watches(&self) -> Result<Vec<Watch>, AppError>;
+
+    /// Deletes a watch by ID. Returns true if it existed.
+    async fn delete_watch_by_id(&self, id: Uuid) -> Result<bool, AppError>;
+
+    /// Adds a search to the query log.
+    async fn record_search(&self, search: &SearchRecord) -> Result<(), AppError>;
+
+    /// The latest `limit` searches of the query log, latest first.
+    async fn list_searches(&self, limit: usize) -> Result<Vec<SearchRecord>, AppError>;
+
+    /// Deletes the whole query log. Returns the number of searches deleted.
+    async fn clear_search_history(&self) -> Result<u64, AppError>;
+
+    /// Locks a portal for a harvest, or returns `None` if another harvest
+    /// holds it.
+    ///
+    /// The default guards nothing, for stores only one process uses.
+    async fn try_lock_portal(&self, _portal_url: &str) -> Result<Option<PortalLock>, AppError> {
+        Ok(Some(PortalLock::unshared()))
+    }
+
+    /// Locks a portal for a harvest, waiting for another harvest holding it.
+    ///
+    /// The default guards nothing, for stores only one process uses.
+    async fn lock_portal(&self, _portal_url: &str) -> Result<PortalLock, AppError> {
+        Ok(PortalLock::unshared())
+    }
+}
+
+/// Keyword score of a dataset for `terms` (see
+/// [`keyword_terms`](ceres_core::search::keyword_terms)), from 0 to 1:
+/// terms in the title count double. `None` if no term matches.
+pub(crate) fn lexical_score(
+    terms: &[String],
+    title: &str,
+    description: Option<&str>,
+) -> Option<f32> {
+    let title = title.to_lowercase();
+    let description = description.unwrap_or_default().to_lowercase();
+    let rank: f32 = terms
+        .iter()
+        .map(|term| {
+            if title.contains(term.as_str()) {
+                2.0
+            } else if description.contains(term.as_str()) {
+                1.0
+            } else {
+                0.0
+            }
+        })
+        .sum();
+    (rank > 0.0).then(|| rank / (rank + 1.0))
+}
+
+/// Cosine similarity of two vectors; 0 if either is all zeros.
+pub(crate) fn cosine_similarity(a: &[f32], b: &[f32]) -> f32 {
+    let dot: f32 = a.iter().zip(b).map(|(x, y)| x * y).sum();
+    let norm = |v: &[f32]| v.iter().map(|x| x * x).sum::<f32>().sqrt();
+    let norms = norm(a) * norm(b);
+    if norms == 0.0 {
+        0.0
+    } else {
+        dot / norms
+    }
+}
+
+/// Fuses semantic and keyword results with reciprocal rank fusion, keeping
+/// the `limit` best.
+pub(crate) fn fuse_results(
+    semantic: Vec<SearchResult>,
+    lexical: Vec<SearchResult>,
+    limit: usize,
+) -> Vec<SearchResult> {
+    let fused = ceres_core::search::reciprocal_rank_fusion(&[
+        semantic.iter().map(|r| r.dataset.id).collect(),
+        lexical.iter().map(|r| r.dataset.id).collect(),
+    ]);
+    let mut datasets: HashMap<Uuid, Dataset> = semantic
+        .into_iter()
+        .chain(lexical)
+        .map(|r| (r.dataset.id, r.dataset))
+        .collect();
+
+    fused
+        .into_iter()
+        .take(limit)
+        .filter_map(|(id, score)| {
+            datasets.remove(&id).map(|dataset| SearchResult {
+                dataset,
+                similarity_score: score,
+            })
+        })
+        .collect()
+}
+
+#[async_trait]
+impl DatasetStore for DatasetRepository {
+    async fn embedding_dimension(&self) -> Result<Option<usize>, AppError> {
+        DatasetRepository::embedding_dimension(self).await
+    }
+
+    async fn pending_migrations(&self) -> Result<Vec<SchemaMigration>, AppError> {
+        DatasetRepository::pending_migrations(self).await
+    }
+
+    async fn run_migrations(&self) -> Result<Vec<SchemaMigration>, AppError> {
+        DatasetRepository::run_migrations(self).await
+    }
+
+    async fn get_hashes_for_portal(
+        &self,
+        portal_url: &str,
+        embedding_model: &str,
+    ) -> Result<HashMap<String, Option<String>>, AppError> {
+        DatasetRepository::get_hashes_for_portal(self, portal_url, embedding_model).await
+    }
+
+    async fn datasets_needing_processing(
+        &self,
+        portal_url: &str,
+        embedding_model: &str,
+        candidates: &[(String, String)],
+    ) -> Result<HashMap<String, ReprocessingDecision>, AppError> {
+        DatasetRepository::datasets_needing_processing(
+            self,
+            portal_url,
+            embedding_model,
+            candidates,
+        )
+        .await
+    }
+
+    async fn update_timestamp_only(
+        &self,
+        portal_url: &str,
+        original_id: &str,
+    ) -> Result<bool, AppError> {
+        DatasetRepository::update_timestamp_only(self, portal_url, original_id).await
+    }
+
+    async fn upsert(&self, new_data: &NewDataset) -> Result<UpsertOutcome, AppError> {
+        DatasetRepository::upsert(self, new_data).await
+    }
+
+    async fn upsert_batch(&self, datasets: &[NewDataset]) -> Result<Vec<UpsertOutcome>, AppError> {
+        DatasetRepository::upsert_batch(self, datasets).await
+    }
+
+    async fn cached_embedding(
+        &self,
+        content_hash: &str,
+        model: &str,
+    ) -> Result<Option<Vector>, AppError> {
+        DatasetRepository::cached_embedding(self, content_hash, model).await
+    }
+
+    async fn cache_embedding(
+        &self,
+        content_hash: &str,
+        model: &str,
+        embedding: &Vector,
+    ) -> Result<(), AppError> {
+        DatasetRepository::cache_embedding(self, content_hash, model, embedding).await
+    }
+
+    async fn get_chunk_hashes_for_portal(
+        &self,
+        portal_url: &str,
+        embedding_model: &str,
+    ) -> Result<HashMap<String, (Uuid, Option<String>)>, AppError> {
+        DatasetRepository::get_chunk_hashes_for_portal(self, portal_url, embedding_model).await
+    }
+
+    async fn replace_dataset_chunks(
+        &self,
+        dataset_id: Uuid,
+        chunks: &[(DatasetChunk, Vector)],
+        embedding_model: &str,
+        chunks_hash: Option<&str>,
+    ) -> Result<(), AppError> {
+        DatasetRepository::replace_dataset_chunks(
+            self,
+            dataset_id,
+            chunks,
+            embedding_model,
+            chunks_hash,
+        )
+        .await
+    }
+
+    async fn list_portal_health(&self) -> Result<Vec<PortalHealth>, AppError> {
+        DatasetRepository::list_portal_health(self).await
+    }
+
+    async fn get_portal_health(&self, portal_url: &str) -> Result<PortalHealth, AppError> {
+        DatasetRepository::get_portal_health(self, portal_url).await
+    }
+
+    async fn save_portal_health(&self, health: &PortalHealth) -> Result<(), AppError> {
+        DatasetRepository::save_portal_health(self, health).await
+    }
+
+    async fn search_with_filters(
+        &self,
+        query_vector: Vector,
+        query_text: &str,
+        limit: usize,
+        filters: &SearchFilters,
+        strategy: SearchStrategy,
+    ) -> Result<Vec<SearchResult>, AppError> {
+        DatasetRepository::search_with_filters(
+            self,
+            query_vector,
+            query_text,
+            limit,
+            filters,
+            strategy,
+        )
+        .await
+    }
+
+    async fn text_search(
+        &self,
+        query_text: &str,
+        limit: usize,
+        filters: &SearchFilters,
+    ) -> Result<Vec<SearchResult>, AppError> {
+        DatasetRepository::text_search(self, query_text, limit, filters).await
+    }
+
+    async fn hybrid_search(
+        &self,
+        query_vector: Vector,
+        query_text: &str,
+        limit: usize,
+        filters: &SearchFilters,
+        strategy: SearchStrategy,
+    ) -> Result<Vec<SearchResult>, AppError> {
+        DatasetRepository::hybrid_search(self, query_vector, query_text, limit, filters, strategy)
+            .await
+    }
+
+    async fn search_with_chunks(
+        &self,
+        query_vector: Vector,
+        query_text: &str,
+        limit: usize,
+        filters: &SearchFilters,
+        strategy: SearchStrategy,
+    ) -> Result<Vec<SearchResult>, AppError> {
+        DatasetRepository::search_with_chunks(
+            self,
+            query_vector,
+            query_text,
+            limit,
+            filters,
+            strategy,
+        )
+        .await
+    }
+
+    fn stream_all<'a>(
+        &'a self,
+        filters: &'a SearchFilters,
+        limit: Option<usize>,
+    ) -> BoxStream<'a, Result<Dataset, AppError>> {
+        DatasetRepository::stream_all(self, filters, limit).boxed()
+    }
+
+    async fn datasets_by_ids(
+        &self,
+        ids: &[Uuid],
+        filters: &SearchFilters,
+    ) -> Result<Vec<Dataset>, AppError> {
+        DatasetRepository::datasets_by_ids(self, ids, filters).await
+    }
+
+    async fn get_stats(&self, portal: Option<&str>) -> Result<DatabaseStats, AppError> {
+        DatasetRepository::get_stats(self, portal).await
+    }
+
+    async fn count_portal_datasets(&self, portal_url: &str) -> Result<i64, AppError> {
+        DatasetRepository::count_portal_datasets(self, portal_url).await
+    }
+
+    async fn delete_portal_datasets(&self, portal_url: &str) -> Result<u64, AppError> {
+        DatasetRepository::delete_portal_datasets(self, portal_url).await
+    }
+
+    async fn count_unseen_datasets(
+        &self,
+        before: DateTime<Utc>,
+        portal_url: Option<&str>,
+    ) -> Result<i64, AppError> {
+        DatasetRepository::count_unseen_datasets(self, before, portal_url).await
+    }
+
+    async fn prune_unseen_datasets(
+        &self,
+        before: DateTime<Utc>,
+        portal_url: Option<&str>,
+    ) -> Result<Vec<Uuid>, AppError> {
+        DatasetRepository::prune_unseen_datasets(self, before, portal_url).await
+    }
+
+    async fn record_harvest_failures(&self, failures: &[HarvestFailure]) -> Result<(), AppError> {
+        DatasetRepository::record_harvest_failures(self, failures).await
+    }
+
+    async fn list_harvest_failures(
+        &self,
+        portal_url: Option<&str>,
+    ) -> Result<Vec<HarvestFailure>, AppError> {
+        DatasetRepository::list_harvest_failures(self, portal_url).await
+    }
+
+    async fn resolve_harvest_failures(
+        &self,
+        portal_url: Option<&str>,
+        before: DateTime<Utc>,
+    ) -> Result<u64, AppError> {
+        DatasetRepository::resolve_harvest_failures(self, portal_url, before).await
+    }
+
+    async fn get_harvest_checkpoint(
+        &self,
+        portal_url: &str,
+    ) -> Result<Option<HarvestCheckpoint>, AppError> {
+        DatasetRepository::get_harvest_checkpoint(self, portal_url).await
+    }
+
+    async fn save_harvest_checkpoint(
+        &self,
+        checkpoint: &HarvestCheckpoint,
+    ) -> Result<(), AppError> {
+        DatasetRepository::save_harvest_checkpoint(self, checkpoint).await
+    }
+
+    async fn clear_harvest_checkpoint(&self, portal_url: &str) -> Result<(), AppError> {
+        DatasetRepository::clear_harvest_checkpoint(self, portal_url).await
+    }
+
+    async fn create_watch(&self, watch: &Watch, embedding: Vector) -> Result<(), AppError> {
+        DatasetRepository::create_watch(self, watch, embedding).await
+    }
+
+    async fn list_watches(&self) -> Result<Vec<Watch>, AppError> {
+        DatasetRepository::list_watches(self).await
+    }
+
+    async fn delete_watch_by_id(&self, id: Uuid) -> Result<bool, AppError> {
+        DatasetRepository::delete_watch_by_id(self, id).await
+    }
+
+    async fn record_search(&self, search: &SearchRecord) -> Result<(), AppError> {
+        DatasetRepository::record_search(self, search).await
+    }
+
+    async fn list_searches(&self, limit: usize) -> Result<Vec<SearchRecord>, AppError> {
+        DatasetRepository::list_searches(self, limit).await
+    }
+
+    async fn clear_search_history(&self) -> Result<u64, AppError> {
+        DatasetRepository::clear_search_history(self).await
+    }
+
+    async fn try_lock_portal(&self, portal_url: &str) -> Result<Option<PortalLock>, AppError> {
+        DatasetRepository::try_lock_portal(self, portal_url).await
+    }
+
+    async fn lock_portal(&self, portal_url: &str) -> Result<PortalLock, AppError> {
+        DatasetRepository::lock_portal(self, portal_url).await
+    }
+}
@@ -0,0 +1,159 @@
+//! Normalized tags and their dataset links.
+
+use ceres_core::error::AppError;
+use ceres_core::models::TagCount;
+use ceres_core::tags::dataset_tags;
+use sqlx::types::Json;
+use sqlx::PgConnection;
+use uuid::Uuid;
+
+use crate::DatasetRepository;
+
+/// Datasets whose metadata lists tags but that have none stored.
+const UNTAGGED: &str = "jsonb_typeof(metadata->'tags') = 'array' \
+    AND jsonb_array_length(metadata->'tags') > 0 \
+    AND NOT EXISTS (SELECT 1 FROM dataset_tags dt WHERE dt.dataset_id = datasets.id)";
+
+/// Replaces the tags of a dataset, creating missing tags.
+pub(crate) async fn replace_dataset_tags(
+    conn: &mut PgConnection,
+    dataset_id: Uuid,
+    tags: &[String],
+) -> Result<(), AppError> {
+    replace_tags(conn, &[(dataset_id, tags)]).await
+}
+
+/// Replaces the tags of several datasets in three queries, creating
+/// missing tags.
+pub(crate) async fn replace_tags(
+    conn: &mut PgConnection,
+    datasets: &[(Uuid, &[String])],
+) -> Result<(), AppError> {
+    let dataset_ids: Vec<Uuid> = datasets.iter().map(|(id, _)| *id).collect();
+    // One (dataset, tag) pair per element
+    let (link_ids, link_names): (Vec<Uuid>, Vec<&str>) = datasets
+        .iter()
+        .flat_map(|(id, tags)| tags.iter().map(move |tag| (*id, tag.as_str())))
+        .unzip();
+
+    sqlx::query("INSERT INTO tags (name) SELECT unnest($1::text[]) ON CONFLICT (name) DO NOTHING")
+        .bind(&link_names)
+        .execute(&mut *conn)
+        .await
+        .map_err(AppError::DatabaseError)?;
+
+    sqlx::query("DELETE FROM dataset_tags WHERE dataset_id = ANY($1)")
+        .bind(&dataset_ids)
+        .execute(&mut *conn)
+        .await
+        .map_err(AppError::DatabaseError)?;
+
+    sqlx::query(
+        r#"
+        INSERT INTO dataset_tags (dataset_id, tag_id)
+        SELECT DISTINCT l.dataset_id, t.id
+        FROM unnest($1::uuid[], $2::text[]) AS l(dataset_id, name)
+        JOIN tags t ON t.name = l.name
+        "#,
+    )
+    .bind(&link_ids)
+    .bind(&link_names)
+    .execute(&mut *conn)
+    .await
+    .map_err(AppError::DatabaseError)?;
+
+    Ok(())
+}
+
+/// Helper struct for deserializing tag count rows
+#[derive(sqlx::FromRow)]
+struct TagCountRow {
+    name: String,
+    datasets: i64,
+}
+
+impl DatasetRepository {
+    /// Returns the `limit` most used tags with their dataset counts,
+    /// optionally counting a single portal's datasets.
+    pub async fn top_tags(
+        &self,
+        portal: Option<&str>,
+        limit: usize,
+    ) -> Result<Vec<TagCount>, AppError> {
+        let rows: Vec<TagCountRow> = sqlx::query_as(
+            r#"
+            SELECT t.name, COUNT(*) AS datasets
+            FROM dataset_tags dt
+            JOIN tags t ON t.id = dt.tag_id
+            JOIN datasets d ON d.id = dt.dataset_id
+            WHERE $1::text IS NULL OR d.source_portal = $1
+            GROUP BY t.name
+            ORDER BY datasets DESC, t.name
+            LIMIT $2
+            "#,
+        )
+        .bind(portal)
+        .bind(limit as i64)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(AppError::DatabaseError)?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| TagCount {
+                tag: row.name,
+                datasets: row.datasets,
+            })
+            .collect())
+    }
+
+    /// Counts datasets with CKAN tags but no stored tags.
+    pub async fn count_untagged(&self) -> Result<i64, AppError> {
+        let query = format!("SELECT COUNT(*) FROM datasets WHERE {}", UNTAGGED);
+        let count: i64 = sqlx::query_scalar(&query)
+            .fetch_one(&self.pool)
+            .await
+            .map_err(AppError::DatabaseError)?;
+
+        Ok(count)
+    }
+
+    /// Stores the normalized tags of up to `batch_size` datasets with CKAN
+    /// tags but none stored, with IDs greater than `after`.
+    ///
+    /// Returns the last ID scanned and the number of datasets tagged, or
+    /// `None` when no datasets are left.
+    pub async fn backfill_tags(
+        &self,
+        after: Uuid,
+        batch_size: usize,
+    ) -> Result<Option<(Uuid, usize)>, AppError> {
+        let query = format!(
+            "SELECT id, metadata FROM datasets WHERE {} AND id > $1 ORDER BY id LIMIT $2",
+            UNTAGGED
+        );
+        let rows: Vec<(Uuid, Json<serde_json::Value>)> = sqlx::query_as(&query)
+            .bind(after)
+            .bind(batch_size as i64)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(AppError::DatabaseError)?;
+
+        let Some(last) = rows.last().map(|(id, _)| *id) else {
+            return Ok(None);
+        };
+
+        let mut tx = self.pool.begin().await.map_err(AppError::DatabaseError)?;
+        let mut tagged = 0;
+        for (id, metadata) in rows {
+            let tags = dataset_tags(&metadata);
+            if !tags.is_empty() {
+                replace_dataset_tags(&mut tx, id, &tags).await?;
+                tagged += 1;
+            }
+        }
+        tx.commit().await.map_err(AppError::DatabaseError)?;
+
+        Ok(Some((last, tagged)))
+    }
+}
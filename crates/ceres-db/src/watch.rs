@@ -0,0 +1,261 @@
+//! Persistence for watched topics and their webhook delivery log.
+
+use ceres_core::error::AppError;
+use ceres_core::watch::{DeliveryStatus, Watch, WatchDelivery, WatchMatch};
+use chrono::{DateTime, Utc};
+use pgvector::Vector;
+use uuid::Uuid;
+
+use crate::DatasetRepository;
+
+/// Column list for watch queries.
+const WATCH_COLUMNS: &str =
+    "id, name, query, min_score, portal, embedding_model, webhook_url, secret, created_at";
+
+/// Helper struct for deserializing `watches` rows
+#[derive(sqlx::FromRow)]
+struct WatchRow {
+    id: Uuid,
+    name: String,
+    query: String,
+    min_score: f32,
+    portal: Option<String>,
+    embedding_model: String,
+    webhook_url: String,
+    secret: String,
+    created_at: DateTime<Utc>,
+}
+
+impl From<WatchRow> for Watch {
+    fn from(row: WatchRow) -> Self {
+        Watch {
+            id: row.id,
+            name: row.name,
+            query: row.query,
+            min_score: row.min_score,
+            portal: row.portal,
+            embedding_model: row.embedding_model,
+            webhook_url: row.webhook_url,
+            secret: row.secret,
+            created_at: row.created_at,
+        }
+    }
+}
+
+/// Helper struct for deserializing new-match query results
+#[derive(sqlx::FromRow)]
+struct WatchMatchRow {
+    id: Uuid,
+    title: String,
+    url: String,
+    source_portal: String,
+    similarity_score: f64,
+}
+
+/// Helper struct for deserializing delivery log rows
+#[derive(sqlx::FromRow)]
+struct WatchDeliveryRow {
+    id: Uuid,
+    status: String,
+    dataset_count: Option<i32>,
+    error: Option<String>,
+    created_at: DateTime<Utc>,
+}
+
+impl DatasetRepository {
+    /// Stores a new watch with its query embedding.
+    ///
+    /// # Errors
+    ///
+    /// Returns `AppError::ConfigError` if a watch with the same name exists.
+    pub async fn create_watch(&self, watch: &Watch, embedding: Vector) -> Result<(), AppError> {
+        let result = sqlx::query(
+            r#"
+            INSERT INTO watches (
+                id, name, query, embedding, embedding_model, min_score,
+                portal, webhook_url, secret, created_at
+            )
+            VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)
+            ON CONFLICT (name) DO NOTHING
+            "#,
+        )
+        .bind(watch.id)
+        .bind(&watch.name)
+        .bind(&watch.query)
+        .bind(embedding)
+        .bind(&watch.embedding_model)
+        .bind(watch.min_score)
+        .bind(&watch.portal)
+        .bind(&watch.webhook_url)
+        .bind(&watch.secret)
+        .bind(watch.created_at)
+        .execute(&self.pool)
+        .await
+        .map_err(AppError::DatabaseError)?;
+
+        if result.rows_affected() == 0 {
+            return Err(AppError::ConfigError(format!(
+                "A watch named '{}' already exists",
+                watch.name
+            )));
+        }
+        Ok(())
+    }
+
+    /// Returns all watches, oldest first.
+    pub async fn list_watches(&self) -> Result<Vec<Watch>, AppError> {
+        let query = format!("SELECT {} FROM watches ORDER BY created_at", WATCH_COLUMNS);
+        let rows: Vec<WatchRow> = sqlx::query_as(&query)
+            .fetch_all(&self.pool)
+            .await
+            .map_err(AppError::DatabaseError)?;
+
+        Ok(rows.into_iter().map(Watch::from).collect())
+    }
+
+    /// Finds a watch by name.
+    pub async fn get_watch_by_name(&self, name: &str) -> Result<Option<Watch>, AppError> {
+        let query = format!("SELECT {} FROM watches WHERE name = $1", WATCH_COLUMNS);
+        let row: Option<WatchRow> = sqlx::query_as(&query)
+            .bind(name)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(AppError::DatabaseError)?;
+
+        Ok(row.map(Watch::from))
+    }
+
+    /// Deletes a watch and its delivery log. Returns true if it existed.
+    pub async fn delete_watch(&self, name: &str) -> Result<bool, AppError> {
+        let result = sqlx::query("DELETE FROM watches WHERE name = $1")
+            .bind(name)
+            .execute(&self.pool)
+            .await
+            .map_err(AppError::DatabaseError)?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Deletes a watch by ID and its delivery log. Returns true if it
+    /// existed.
+    pub async fn delete_watch_by_id(&self, id: Uuid) -> Result<bool, AppError> {
+        let result = sqlx::query("DELETE FROM watches WHERE id = $1")
+            .bind(id)
+            .execute(&self.pool)
+            .await
+            .map_err(AppError::DatabaseError)?;
+
+        Ok(result.rows_affected() > 0)
+    }
+
+    /// Returns datasets newly matching a watch, best match first.
+    ///
+    /// A dataset matches if it was first seen after the watch was created,
+    /// was embedded with the watch's model, passes the portal restriction and
+    /// similarity threshold, and has not been delivered successfully yet.
+    /// Datasets from failed deliveries are therefore retried.
+    pub async fn new_watch_matches(
+        &self,
+        watch: &Watch,
+        limit: usize,
+    ) -> Result<Vec<WatchMatch>, AppError> {
+        let rows: Vec<WatchMatchRow> = sqlx::query_as(
+            r#"
+            SELECT d.id, d.title, d.url, d.source_portal,
+                   1 - (d.embedding <=> w.embedding) AS similarity_score
+            FROM watches w
+            JOIN datasets d
+              ON d.embedding IS NOT NULL
+             AND d.embedding_model = w.embedding_model
+             AND d.first_seen_at > w.created_at
+             AND (w.portal IS NULL OR d.source_portal = w.portal)
+            WHERE w.id = $1
+              AND 1 - (d.embedding <=> w.embedding) >= w.min_score
+              AND NOT EXISTS (
+                  SELECT 1 FROM watch_deliveries wd
+                  WHERE wd.watch_id = w.id
+                    AND wd.status = 'delivered'
+                    AND wd.dataset_ids @> ARRAY[d.id]
+              )
+            ORDER BY d.embedding <=> w.embedding
+            LIMIT $2
+            "#,
+        )
+        .bind(watch.id)
+        .bind(limit as i64)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(AppError::DatabaseError)?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| WatchMatch {
+                id: row.id,
+                title: row.title,
+                url: row.url,
+                source_portal: row.source_portal,
+                similarity_score: row.similarity_score as f32,
+            })
+            .collect())
+    }
+
+    /// Appends an entry to a watch's delivery log.
+    pub async fn record_watch_delivery(
+        &self,
+        watch_id: Uuid,
+        delivery_id: Uuid,
+        dataset_ids: &[Uuid],
+        status: DeliveryStatus,
+        error: Option<&str>,
+    ) -> Result<(), AppError> {
+        sqlx::query(
+            r#"
+            INSERT INTO watch_deliveries (id, watch_id, dataset_ids, status, error)
+            VALUES ($1, $2, $3, $4, $5)
+            "#,
+        )
+        .bind(delivery_id)
+        .bind(watch_id)
+        .bind(dataset_ids)
+        .bind(status.as_str())
+        .bind(error)
+        .execute(&self.pool)
+        .await
+        .map_err(AppError::DatabaseError)?;
+
+        Ok(())
+    }
+
+    /// Returns the most recent deliveries of a watch, newest first.
+    pub async fn list_watch_deliveries(
+        &self,
+        watch_id: Uuid,
+        limit: usize,
+    ) -> Result<Vec<WatchDelivery>, AppError> {
+        let rows: Vec<WatchDeliveryRow> = sqlx::query_as(
+            r#"
+            SELECT id, status, cardinality(dataset_ids) AS dataset_count, error, created_at
+            FROM watch_deliveries
+            WHERE watch_id = $1
+            ORDER BY created_at DESC
+            LIMIT $2
+            "#,
+        )
+        .bind(watch_id)
+        .bind(limit as i64)
+        .fetch_all(&self.pool)
+        .await
+        .map_err(AppError::DatabaseError)?;
+
+        Ok(rows
+            .into_iter()
+            .map(|row| WatchDelivery {
+                id: row.id,
+                status: row.status,
+                dataset_count: row.dataset_count.unwrap_or(0).max(0) as usize,
+                error: row.error,
+                created_at: row.created_at,
+            })
+            .collect())
+    }
+}
@@ -0,0 +1,11 @@
+-- Migration: Indexes for two-stage search prefiltering
+-- The first stage of a two-stage search selects candidates by portal,
+-- theme (CKAN groups) and query keywords before exact vector scoring.
+
+-- Keyword prefilter. The expression must match FTS_DOCUMENT in ceres-db.
+CREATE INDEX IF NOT EXISTS idx_datasets_fts
+    ON datasets USING gin (to_tsvector('simple', title || ' ' || coalesce(description, '')));
+
+-- Containment filters on metadata (e.g. {"groups": [{"name": "..."}]})
+CREATE INDEX IF NOT EXISTS idx_datasets_metadata
+    ON datasets USING gin (metadata jsonb_path_ops);
@@ -0,0 +1,15 @@
+-- Migration: Tag embeddings with the model that produced them
+-- Portals can override the embedding model, so vectors from different models
+-- coexist in the table. Search only compares vectors from the same model.
+
+ALTER TABLE datasets ADD COLUMN IF NOT EXISTS embedding_model VARCHAR;
+
+-- Existing embeddings were all produced by the default model
+UPDATE datasets
+SET embedding_model = 'text-embedding-004'
+WHERE embedding IS NOT NULL AND embedding_model IS NULL;
+
+CREATE INDEX IF NOT EXISTS idx_datasets_embedding_model
+    ON datasets(embedding_model);
+
+COMMENT ON COLUMN datasets.embedding_model IS 'Embedding model that produced the embedding column. NULL when no embedding is stored.';
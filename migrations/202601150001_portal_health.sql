@@ -0,0 +1,14 @@
+-- Migration: Portal health tracking for quarantine
+-- Portals that fail repeatedly are quarantined and skipped by batch harvests
+-- until cleared manually or until quarantined_until passes.
+
+CREATE TABLE IF NOT EXISTS portal_health (
+    portal_url VARCHAR PRIMARY KEY,
+    consecutive_failures INTEGER NOT NULL DEFAULT 0,
+    last_error TEXT,
+    last_failure_at TIMESTAMPTZ,
+    last_success_at TIMESTAMPTZ,
+    quarantined_until TIMESTAMPTZ
+);
+
+COMMENT ON COLUMN portal_health.quarantined_until IS 'End of the current quarantine. NULL or in the past means the portal is harvested normally.';
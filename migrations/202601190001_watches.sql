@@ -0,0 +1,32 @@
+-- Migration: Watched topics with signed webhook deliveries
+-- A watch is a saved search bound to a webhook. After each harvest, new
+-- datasets matching the watch are delivered and logged in watch_deliveries.
+
+CREATE TABLE IF NOT EXISTS watches (
+    id UUID PRIMARY KEY DEFAULT gen_random_uuid(),
+    name VARCHAR NOT NULL UNIQUE,
+    query TEXT NOT NULL,
+    embedding vector(768) NOT NULL,
+    embedding_model VARCHAR NOT NULL,
+    min_score REAL NOT NULL,
+    portal VARCHAR,
+    webhook_url VARCHAR NOT NULL,
+    secret VARCHAR NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE TABLE IF NOT EXISTS watch_deliveries (
+    id UUID PRIMARY KEY,
+    watch_id UUID NOT NULL REFERENCES watches(id) ON DELETE CASCADE,
+    dataset_ids UUID[] NOT NULL,
+    status VARCHAR NOT NULL CHECK (status IN ('delivered', 'failed')),
+    error TEXT,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_watch_deliveries_watch
+    ON watch_deliveries(watch_id, created_at DESC);
+
+-- Supports the "already delivered" check when collecting new matches
+CREATE INDEX IF NOT EXISTS idx_watch_deliveries_datasets
+    ON watch_deliveries USING gin (dataset_ids);
@@ -0,0 +1,31 @@
+-- Migration: Resource formats per dataset
+-- Distinct, normalized resource formats (CSV, JSON, SHP, ...) extracted from
+-- the CKAN resources at conversion time, for `ceres search --format` and the
+-- formats facet in `ceres stats`. The normalization below must match
+-- NewDataset::normalize_format in ceres-core.
+
+ALTER TABLE datasets ADD COLUMN IF NOT EXISTS formats text[] NOT NULL DEFAULT '{}';
+
+-- Backfill from the stored CKAN metadata
+UPDATE datasets d
+SET formats = extracted.formats
+FROM (
+    SELECT id, array_agg(DISTINCT format ORDER BY format) AS formats
+    FROM (
+        SELECT
+            datasets.id,
+            upper(ltrim(regexp_replace(btrim(resource->>'format'), '^.*/', ''), '.')) AS format
+        FROM datasets,
+             jsonb_array_elements(
+                 CASE WHEN jsonb_typeof(metadata->'resources') = 'array'
+                      THEN metadata->'resources'
+                      ELSE '[]'::jsonb
+                 END
+             ) AS resource
+    ) resource_formats
+    WHERE format <> ''
+    GROUP BY id
+) extracted
+WHERE d.id = extracted.id;
+
+CREATE INDEX IF NOT EXISTS idx_datasets_formats ON datasets USING gin (formats);
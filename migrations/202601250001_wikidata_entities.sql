@@ -0,0 +1,30 @@
+-- Migration: Wikidata links for publishers and places
+-- `ceres enrich` resolves organization and place names found in the CKAN
+-- metadata to Wikidata items. Lookups are cached per normalized label, so
+-- each distinct name is searched once; misses are cached too (qid NULL) and
+-- retried after a while.
+
+CREATE TABLE IF NOT EXISTS wikidata_labels (
+    language VARCHAR NOT NULL,
+    label_key VARCHAR NOT NULL,
+    qid VARCHAR,
+    label VARCHAR,
+    description TEXT,
+    resolved_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (language, label_key)
+);
+
+CREATE TABLE IF NOT EXISTS dataset_entities (
+    dataset_id UUID NOT NULL REFERENCES datasets(id) ON DELETE CASCADE,
+    role VARCHAR NOT NULL CHECK (role IN ('publisher', 'place')),
+    qid VARCHAR NOT NULL,
+    PRIMARY KEY (dataset_id, role, qid)
+);
+
+-- Cross-portal joins on publisher or place identity
+CREATE INDEX IF NOT EXISTS idx_dataset_entities_qid ON dataset_entities(qid, role);
+
+-- Content hash the links were computed from; metadata is only rewritten when
+-- the content hash changes, so a differing hash means the links are stale
+ALTER TABLE datasets ADD COLUMN IF NOT EXISTS entities_hash VARCHAR(64);
+ALTER TABLE datasets ADD COLUMN IF NOT EXISTS entities_resolved_at TIMESTAMPTZ;
@@ -0,0 +1,27 @@
+-- Migration: Embedding cache keyed by content hash and model
+-- The content hash covers exactly the text that is embedded (title and
+-- description), so a vector computed once can be reused for identical
+-- metadata on other portals and by re-runs after a partial failure.
+--
+-- The vector column matches the dimension of datasets.embedding, which
+-- `ceres index resize` may have changed from the initial 768.
+
+DO $$
+DECLARE
+    dimension INTEGER;
+BEGIN
+    SELECT atttypmod INTO dimension
+    FROM pg_attribute
+    WHERE attrelid = 'datasets'::regclass AND attname = 'embedding';
+
+    EXECUTE format(
+        'CREATE TABLE IF NOT EXISTS embedding_cache (
+            content_hash VARCHAR(64) NOT NULL,
+            embedding_model VARCHAR NOT NULL,
+            embedding vector(%s) NOT NULL,
+            created_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+            PRIMARY KEY (content_hash, embedding_model)
+        )',
+        dimension
+    );
+END $$;
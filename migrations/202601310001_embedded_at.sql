@@ -0,0 +1,13 @@
+-- Migration: Record when each embedding was generated
+-- Together with embedding_model this tells which rows were embedded by which
+-- model and when, so re-embedding can target specific rows.
+
+ALTER TABLE datasets ADD COLUMN IF NOT EXISTS embedded_at TIMESTAMPTZ;
+
+-- The exact time is unknown for existing embeddings; the last update is the
+-- latest they can have been generated
+UPDATE datasets
+SET embedded_at = last_updated_at
+WHERE embedding IS NOT NULL AND embedded_at IS NULL;
+
+COMMENT ON COLUMN datasets.embedded_at IS 'When the embedding column was generated. NULL when no embedding is stored.';
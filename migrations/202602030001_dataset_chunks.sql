@@ -0,0 +1,36 @@
+-- Migration: Chunk embeddings (multi-vector mode)
+-- Portals with `chunk_embeddings = true` also embed each resource and each
+-- part of a long description separately. Search scores a dataset by its
+-- best-matching vector, so a query matching a single file finds its dataset.
+--
+-- The vector column matches the dimension of datasets.embedding, which
+-- `ceres index resize` may have changed from the initial 768.
+
+DO $$
+DECLARE
+    dimension INTEGER;
+BEGIN
+    SELECT atttypmod INTO dimension
+    FROM pg_attribute
+    WHERE attrelid = 'datasets'::regclass AND attname = 'embedding';
+
+    EXECUTE format(
+        'CREATE TABLE IF NOT EXISTS dataset_chunks (
+            dataset_id UUID NOT NULL REFERENCES datasets(id) ON DELETE CASCADE,
+            position INTEGER NOT NULL,
+            kind VARCHAR NOT NULL CHECK (kind IN (''description'', ''resource'')),
+            text TEXT NOT NULL,
+            embedding vector(%s) NOT NULL,
+            embedding_model VARCHAR NOT NULL,
+            PRIMARY KEY (dataset_id, position)
+        )',
+        dimension
+    );
+END $$;
+
+CREATE INDEX IF NOT EXISTS idx_dataset_chunks_embedding
+    ON dataset_chunks USING hnsw (embedding vector_cosine_ops);
+
+-- Hash of the chunk texts the stored chunks were embedded from; resources
+-- are not covered by content_hash, so chunks are compared separately
+ALTER TABLE datasets ADD COLUMN IF NOT EXISTS chunks_hash VARCHAR(64);
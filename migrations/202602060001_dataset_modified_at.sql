@@ -0,0 +1,17 @@
+-- Migration: Upstream modification time per dataset
+-- CKAN's `metadata_modified`, parsed at conversion time, for
+-- `ceres search --updated-after/--updated-before`. last_updated_at cannot
+-- serve here: it is bumped on every harvest, changed or not.
+
+ALTER TABLE datasets ADD COLUMN IF NOT EXISTS modified_at TIMESTAMPTZ;
+
+-- Backfill from the stored CKAN metadata. CKAN timestamps carry no offset
+-- and are in UTC; values not shaped like a timestamp are left NULL.
+UPDATE datasets
+SET modified_at = (metadata->>'metadata_modified')::timestamp AT TIME ZONE 'UTC'
+WHERE modified_at IS NULL
+  AND metadata->>'metadata_modified' ~ '^\d{4}-\d{2}-\d{2}T\d{2}:\d{2}:\d{2}(\.\d+)?$';
+
+CREATE INDEX IF NOT EXISTS idx_datasets_modified_at ON datasets (modified_at);
+
+COMMENT ON COLUMN datasets.modified_at IS 'Last modification reported by the source portal (CKAN metadata_modified). NULL when unknown.';
@@ -0,0 +1,20 @@
+-- Migration: Full-text search document for hybrid search
+-- `ceres search --mode hybrid` fuses a full-text ranking with the vector
+-- ranking, so exact terms such as dataset codes are found even when the
+-- embedding misses them. The document weights the CKAN name (slug) and the
+-- title above the description, for ts_rank_cd.
+--
+-- The document replaces the expression index used by the keyword prefilter
+-- of two-stage search; FTS_DOCUMENT in ceres-db refers to this column.
+
+ALTER TABLE datasets ADD COLUMN IF NOT EXISTS search_document tsvector
+    GENERATED ALWAYS AS (
+        setweight(to_tsvector('simple', coalesce(metadata->>'name', '')), 'A') ||
+        setweight(to_tsvector('simple', title), 'A') ||
+        setweight(to_tsvector('simple', coalesce(description, '')), 'B')
+    ) STORED;
+
+CREATE INDEX IF NOT EXISTS idx_datasets_search_document
+    ON datasets USING gin (search_document);
+
+DROP INDEX IF EXISTS idx_datasets_fts;
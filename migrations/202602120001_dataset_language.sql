@@ -0,0 +1,10 @@
+-- Migration: Detected language per dataset
+-- ISO 639-1 code detected from title and description at harvest, for
+-- `ceres search --language` and the `ceres stats` breakdown. Existing rows
+-- are filled by `ceres backfill-languages`, as detection runs in Ceres.
+
+ALTER TABLE datasets ADD COLUMN IF NOT EXISTS language TEXT;
+
+CREATE INDEX IF NOT EXISTS idx_datasets_language ON datasets (language);
+
+COMMENT ON COLUMN datasets.language IS 'ISO 639-1 code detected from title and description. NULL when undetected or not yet backfilled.';
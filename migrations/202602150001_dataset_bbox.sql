@@ -0,0 +1,11 @@
+-- Migration: Geographic extent per dataset
+-- Bounding box of the GeoJSON in a dataset's `spatial` extra, for
+-- `ceres search --bbox` and `--near`. Stored as a native box (x = longitude,
+-- y = latitude) so no PostGIS is needed. Existing rows are filled by
+-- `ceres backfill-spatial`, as the GeoJSON is parsed in Ceres.
+
+ALTER TABLE datasets ADD COLUMN IF NOT EXISTS bbox BOX;
+
+CREATE INDEX IF NOT EXISTS idx_datasets_bbox ON datasets USING GIST (bbox);
+
+COMMENT ON COLUMN datasets.bbox IS 'Longitude/latitude bounding box of the spatial extra (WGS 84). NULL when absent or not yet backfilled.';
@@ -0,0 +1,9 @@
+-- Migration: Metadata quality score per dataset
+-- Weighted score from 0 to 1 of description, license, resources and
+-- recency, computed at harvest (see ceres_core::quality). Used by
+-- `ceres search --quality-weight` and reported per portal by `ceres stats`.
+-- Existing rows are filled by `ceres backfill-quality`.
+
+ALTER TABLE datasets ADD COLUMN IF NOT EXISTS quality REAL;
+
+COMMENT ON COLUMN datasets.quality IS 'Metadata quality score from 0 to 1, as of the last harvest that changed the dataset. NULL when not yet backfilled.';
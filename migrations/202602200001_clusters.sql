@@ -0,0 +1,23 @@
+-- Migration: Topic clusters
+-- Written by `ceres cluster`, which groups datasets by embedding with
+-- k-means. Each run replaces the previous clustering.
+
+CREATE TABLE IF NOT EXISTS clusters (
+    cluster INTEGER PRIMARY KEY,
+    keywords TEXT[] NOT NULL,
+    datasets INTEGER NOT NULL,
+    -- Portal the run was limited to, NULL for all portals
+    source_portal TEXT,
+    embedding_model TEXT NOT NULL,
+    created_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE TABLE IF NOT EXISTS dataset_clusters (
+    dataset_id UUID PRIMARY KEY REFERENCES datasets(id) ON DELETE CASCADE,
+    cluster INTEGER NOT NULL REFERENCES clusters(cluster) ON DELETE CASCADE
+);
+
+CREATE INDEX IF NOT EXISTS idx_dataset_clusters_cluster ON dataset_clusters (cluster);
+
+COMMENT ON TABLE clusters IS 'Topics found by the last `ceres cluster` run, largest first.';
+COMMENT ON TABLE dataset_clusters IS 'Cluster of each dataset in the last `ceres cluster` run.';
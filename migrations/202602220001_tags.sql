@@ -0,0 +1,20 @@
+-- Migration: Normalized tags
+-- CKAN tags normalized at harvest (lowercase, without accents, singular;
+-- see ceres_core::tags), for `ceres search --tag` and `ceres top-tags`.
+-- Existing datasets are tagged by `ceres backfill-tags`.
+
+CREATE TABLE IF NOT EXISTS tags (
+    id SERIAL PRIMARY KEY,
+    name TEXT NOT NULL UNIQUE
+);
+
+CREATE TABLE IF NOT EXISTS dataset_tags (
+    dataset_id UUID NOT NULL REFERENCES datasets(id) ON DELETE CASCADE,
+    tag_id INTEGER NOT NULL REFERENCES tags(id) ON DELETE CASCADE,
+    PRIMARY KEY (dataset_id, tag_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_dataset_tags_tag ON dataset_tags (tag_id);
+
+COMMENT ON TABLE tags IS 'Distinct normalized tags.';
+COMMENT ON TABLE dataset_tags IS 'Normalized tags of each dataset.';
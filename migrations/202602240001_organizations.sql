@@ -0,0 +1,42 @@
+-- Migration: Publishing organizations
+-- CKAN organizations of harvested datasets, keyed by portal and CKAN name,
+-- for `ceres orgs list` and the `--org` filter. Harvests keep them up to
+-- date; existing datasets are linked here from their stored metadata.
+
+CREATE TABLE IF NOT EXISTS organizations (
+    id SERIAL PRIMARY KEY,
+    source_portal TEXT NOT NULL,
+    name TEXT NOT NULL,
+    title TEXT,
+    ckan_id TEXT,
+    UNIQUE (source_portal, name)
+);
+
+ALTER TABLE datasets
+    ADD COLUMN IF NOT EXISTS organization_id INTEGER REFERENCES organizations(id) ON DELETE SET NULL;
+
+CREATE INDEX IF NOT EXISTS idx_datasets_organization ON datasets (organization_id);
+
+-- Most recently updated dataset wins when titles differ
+INSERT INTO organizations (source_portal, name, title, ckan_id)
+SELECT DISTINCT ON (source_portal, org_name)
+    source_portal,
+    org_name,
+    NULLIF(btrim(metadata->'organization'->>'title'), ''),
+    NULLIF(btrim(metadata->'organization'->>'id'), '')
+FROM (
+    SELECT source_portal, metadata, last_updated_at,
+           btrim(metadata->'organization'->>'name') AS org_name
+    FROM datasets
+) d
+WHERE org_name <> ''
+ORDER BY source_portal, org_name, last_updated_at DESC
+ON CONFLICT (source_portal, name) DO NOTHING;
+
+UPDATE datasets d
+SET organization_id = o.id
+FROM organizations o
+WHERE o.source_portal = d.source_portal
+  AND o.name = btrim(d.metadata->'organization'->>'name');
+
+COMMENT ON TABLE organizations IS 'CKAN organizations (publishers) of harvested datasets.';
@@ -0,0 +1,44 @@
+-- Migration: Dataset resources
+-- One row per CKAN resource (name, normalized format, MIME type, size,
+-- download URL), replaced on every upsert, for resource-level format
+-- statistics in `ceres stats`. The backfill below must match
+-- ceres_core::resources::DatasetResource::from_value.
+
+CREATE TABLE IF NOT EXISTS resources (
+    dataset_id UUID NOT NULL REFERENCES datasets(id) ON DELETE CASCADE,
+    position INTEGER NOT NULL,
+    ckan_id TEXT,
+    name TEXT,
+    format TEXT,
+    mimetype TEXT,
+    size BIGINT,
+    url TEXT,
+    PRIMARY KEY (dataset_id, position)
+);
+
+CREATE INDEX IF NOT EXISTS idx_resources_format ON resources (format);
+
+-- Backfill from the stored CKAN metadata
+INSERT INTO resources (dataset_id, position, ckan_id, name, format, mimetype, size, url)
+SELECT
+    d.id,
+    r.position - 1,
+    NULLIF(btrim(r.resource->>'id'), ''),
+    NULLIF(btrim(r.resource->>'name'), ''),
+    NULLIF(upper(ltrim(regexp_replace(btrim(r.resource->>'format'), '^.*/', ''), '.')), ''),
+    NULLIF(btrim(r.resource->>'mimetype'), ''),
+    CASE WHEN btrim(r.resource->>'size') ~ '^[0-9]{1,18}$'
+         THEN btrim(r.resource->>'size')::bigint
+    END,
+    NULLIF(btrim(r.resource->>'url'), '')
+FROM datasets d,
+     jsonb_array_elements(
+         CASE WHEN jsonb_typeof(d.metadata->'resources') = 'array'
+              THEN d.metadata->'resources'
+              ELSE '[]'::jsonb
+         END
+     ) WITH ORDINALITY AS r(resource, position)
+WHERE jsonb_typeof(r.resource) = 'object'
+ON CONFLICT DO NOTHING;
+
+COMMENT ON TABLE resources IS 'Resources of each dataset, in CKAN order.';
@@ -0,0 +1,7 @@
+-- Migration: Harvest counts per portal
+-- Total harvests and failed harvests since tracking began, for the failure
+-- rate in `ceres stats`. consecutive_failures only covers the current
+-- streak and is reset by every success.
+
+ALTER TABLE portal_health ADD COLUMN IF NOT EXISTS harvests INTEGER NOT NULL DEFAULT 0;
+ALTER TABLE portal_health ADD COLUMN IF NOT EXISTS failed_harvests INTEGER NOT NULL DEFAULT 0;
@@ -0,0 +1,17 @@
+-- Migration: Dead-letter table of datasets harvests failed to process
+-- One row per portal and dataset with its latest failure, kept until a
+-- later harvest gets past the dataset, so failures can be analyzed with
+-- `ceres failures` and retried with `ceres harvest --retry-failed`.
+
+CREATE TABLE IF NOT EXISTS harvest_failures (
+    portal_url VARCHAR NOT NULL,
+    dataset_id VARCHAR NOT NULL,
+    stage VARCHAR NOT NULL,
+    error TEXT NOT NULL,
+    attempts INTEGER NOT NULL DEFAULT 1,
+    failed_at TIMESTAMPTZ NOT NULL DEFAULT NOW(),
+    PRIMARY KEY (portal_url, dataset_id)
+);
+
+COMMENT ON COLUMN harvest_failures.stage IS 'Stage of the latest failure: fetch, detect, embed or store.';
+COMMENT ON COLUMN harvest_failures.attempts IS 'Harvests that failed the dataset since it was last processed.';
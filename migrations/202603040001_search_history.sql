@@ -0,0 +1,20 @@
+-- Migration: Log of searches run with `ceres search` and `ceres serve`
+-- One row per search with its filters, result count and best score, listed
+-- with `ceres history search` and kept for query analytics and evaluation
+-- sets.
+
+CREATE TABLE IF NOT EXISTS search_history (
+    id BIGSERIAL PRIMARY KEY,
+    query TEXT NOT NULL,
+    mode VARCHAR NOT NULL,
+    filters JSONB NOT NULL DEFAULT '{}',
+    result_count INTEGER NOT NULL,
+    top_score REAL,
+    source VARCHAR NOT NULL,
+    searched_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+CREATE INDEX IF NOT EXISTS idx_search_history_searched_at ON search_history (searched_at DESC);
+
+COMMENT ON COLUMN search_history.filters IS 'Filters set besides the query, by name (portal, tag, where, bbox, ...).';
+COMMENT ON COLUMN search_history.source IS 'Where the search was run from: cli, api or grpc.';
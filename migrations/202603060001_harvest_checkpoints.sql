@@ -0,0 +1,13 @@
+-- Migration: Checkpoints of interrupted harvests
+-- How many datasets from the start of its portal's listing a harvest
+-- finished, saved as it goes, so the next harvest after an interruption
+-- resumes there. Removed once a harvest gets through the whole listing.
+
+CREATE TABLE IF NOT EXISTS harvest_checkpoints (
+    portal_url VARCHAR PRIMARY KEY,
+    listing_offset INTEGER NOT NULL,
+    started_at TIMESTAMPTZ NOT NULL,
+    updated_at TIMESTAMPTZ NOT NULL DEFAULT NOW()
+);
+
+COMMENT ON COLUMN harvest_checkpoints.started_at IS 'Start of the interrupted harvest; failures recorded before it are resolved when the resumed harvest completes.';
@@ -0,0 +1,69 @@
+-- Migration: SQLite backend schema
+-- Local storage for harvest, search, export and stats without PostgreSQL.
+-- Mirrors the PostgreSQL tables those commands use; embeddings are stored
+-- as little-endian f32 BLOBs and ranked in process. Tags, formats and
+-- metadata are JSON text. Timestamps are RFC 3339 text in UTC, so they
+-- order correctly as strings.
+
+CREATE TABLE IF NOT EXISTS datasets (
+    id BLOB PRIMARY KEY,
+    original_id TEXT NOT NULL,
+    source_portal TEXT NOT NULL,
+    url TEXT NOT NULL,
+    title TEXT NOT NULL,
+    description TEXT,
+    embedding BLOB,
+    embedding_model TEXT,
+    embedded_at TEXT,
+    metadata TEXT NOT NULL DEFAULT '{}',
+    formats TEXT NOT NULL DEFAULT '[]',
+    tags TEXT NOT NULL DEFAULT '[]',
+    organization_name TEXT,
+    organization_title TEXT,
+    content_hash TEXT,
+    modified_at TEXT,
+    language TEXT,
+    quality REAL,
+    bbox_min_lon REAL,
+    bbox_min_lat REAL,
+    bbox_max_lon REAL,
+    bbox_max_lat REAL,
+    first_seen_at TEXT NOT NULL,
+    last_updated_at TEXT NOT NULL,
+    UNIQUE (source_portal, original_id)
+);
+
+CREATE INDEX IF NOT EXISTS idx_datasets_embedding_model ON datasets (embedding_model);
+CREATE INDEX IF NOT EXISTS idx_datasets_last_updated_at ON datasets (last_updated_at);
+
+CREATE TABLE IF NOT EXISTS resources (
+    dataset_id BLOB NOT NULL REFERENCES datasets(id) ON DELETE CASCADE,
+    position INTEGER NOT NULL,
+    ckan_id TEXT,
+    name TEXT,
+    format TEXT,
+    mimetype TEXT,
+    size INTEGER,
+    url TEXT,
+    PRIMARY KEY (dataset_id, position)
+);
+
+CREATE INDEX IF NOT EXISTS idx_resources_format ON resources (format);
+
+CREATE TABLE IF NOT EXISTS embedding_cache (
+    content_hash TEXT NOT NULL,
+    embedding_model TEXT NOT NULL,
+    embedding BLOB NOT NULL,
+    PRIMARY KEY (content_hash, embedding_model)
+);
+
+CREATE TABLE IF NOT EXISTS portal_health (
+    portal_url TEXT PRIMARY KEY,
+    consecutive_failures INTEGER NOT NULL DEFAULT 0,
+    last_error TEXT,
+    last_failure_at TEXT,
+    last_success_at TEXT,
+    quarantined_until TEXT,
+    harvests INTEGER NOT NULL DEFAULT 0,
+    failed_harvests INTEGER NOT NULL DEFAULT 0
+);
@@ -0,0 +1,13 @@
+-- Migration: Dead-letter table of datasets harvests failed to process
+-- One row per portal and dataset with its latest failure, kept until a
+-- later harvest gets past the dataset.
+
+CREATE TABLE IF NOT EXISTS harvest_failures (
+    portal_url TEXT NOT NULL,
+    dataset_id TEXT NOT NULL,
+    stage TEXT NOT NULL,
+    error TEXT NOT NULL,
+    attempts INTEGER NOT NULL DEFAULT 1,
+    failed_at TEXT NOT NULL,
+    PRIMARY KEY (portal_url, dataset_id)
+);
@@ -0,0 +1,14 @@
+-- Migration: Log of searches run with `ceres search` and `ceres serve`
+
+CREATE TABLE IF NOT EXISTS search_history (
+    id INTEGER PRIMARY KEY AUTOINCREMENT,
+    query TEXT NOT NULL,
+    mode TEXT NOT NULL,
+    filters TEXT NOT NULL DEFAULT '{}',
+    result_count INTEGER NOT NULL,
+    top_score REAL,
+    source TEXT NOT NULL,
+    searched_at TEXT NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS idx_search_history_searched_at ON search_history (searched_at);
@@ -0,0 +1,8 @@
+-- Migration: Checkpoints of interrupted harvests
+
+CREATE TABLE IF NOT EXISTS harvest_checkpoints (
+    portal_url TEXT PRIMARY KEY,
+    listing_offset INTEGER NOT NULL,
+    started_at TEXT NOT NULL,
+    updated_at TEXT NOT NULL
+);
@@ -0,0 +1,18 @@
+-- Migration: Chunk embeddings on SQLite (multi-vector mode)
+-- Portals with `chunk_embeddings = true` also embed each resource and each
+-- part of a long description separately; see the PostgreSQL migration.
+-- Embeddings are little-endian f32 BLOBs, scored in process like those of
+-- datasets.
+
+CREATE TABLE IF NOT EXISTS dataset_chunks (
+    dataset_id BLOB NOT NULL REFERENCES datasets(id) ON DELETE CASCADE,
+    position INTEGER NOT NULL,
+    kind TEXT NOT NULL CHECK (kind IN ('description', 'resource')),
+    text TEXT NOT NULL,
+    embedding BLOB NOT NULL,
+    embedding_model TEXT NOT NULL,
+    PRIMARY KEY (dataset_id, position)
+);
+
+-- Hash of the chunk texts the stored chunks were embedded from
+ALTER TABLE datasets ADD COLUMN chunks_hash TEXT;